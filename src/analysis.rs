@@ -1,8 +1,12 @@
 // PR analysis and decision engine.
 // Determines the recommended next action based on PR state.
 
+use crate::behind::Rebase;
 use crate::checks::ChecksSummary;
-use crate::threads::{find_actionable_threads, ActionableThread, ReviewThread};
+use crate::github::MergeableStatus;
+use crate::threads::{
+    find_actionable_threads, find_awaiting_input_threads, ActionableThread, ReviewThread,
+};
 
 /// The recommended next action for the PR.
 #[derive(Debug, Clone)]
@@ -15,6 +19,12 @@ pub enum NextAction {
         /// True if CI is still pending.
         ci_pending: bool,
     },
+    /// The PR conflicts with its base branch and there are no pending review
+    /// comments. CI results don't count for much until it's rebased.
+    ResolveConflicts {
+        /// Checks that failed, which may be caused by the conflicts.
+        failed_check_names: Vec<String>,
+    },
     /// CI has failed and there are no pending review comments.
     FixCiFailures {
         failed_check_names: Vec<String>,
@@ -23,47 +33,121 @@ pub enum NextAction {
     WaitForCi {
         pending_check_names: Vec<String>,
     },
+    /// The branch has fallen far behind its base, or the base changed CI
+    /// configuration, so CI results don't reflect what merging would do.
+    Rebase {
+        rebase: Rebase,
+    },
+    /// CI is passing and nothing needs a response, but Claude asked reviewers
+    /// questions that haven't been answered yet.
+    AwaitHumanInput {
+        threads: Vec<ReviewThread>,
+    },
     /// Everything is good - all checks passed, no pending comments.
     PrReady,
 }
 
-/// Analyze PR state and determine the next action.
-pub fn analyze_pr(checks: &ChecksSummary, threads: Vec<ReviewThread>) -> NextAction {
+impl NextAction {
+    /// What the recommendation asks for. Two analyses with the same
+    /// fingerprint advise the same thing: the same threads (with the same
+    /// latest comment) or the same failed checks. Waiting on CI is one
+    /// recommendation however many checks are still running.
+    pub fn fingerprint(&self) -> String {
+        let thread_key = |t: &ReviewThread| {
+            format!("{}@{}", t.id, t.last_comment().map(|c| c.id.as_str()).unwrap_or(""))
+        };
+        match self {
+            NextAction::RespondToComments { threads, also_has_ci_failures, .. } => format!(
+                "respond:{}:{}",
+                threads.iter().map(|t| thread_key(&t.thread)).collect::<Vec<_>>().join(","),
+                also_has_ci_failures
+            ),
+            NextAction::ResolveConflicts { .. } => "conflicts".to_string(),
+            NextAction::FixCiFailures { failed_check_names } => {
+                let mut names = failed_check_names.clone();
+                names.sort();
+                format!("fix:{}", names.join(","))
+            }
+            NextAction::WaitForCi { .. } => "wait".to_string(),
+            NextAction::Rebase { rebase } => format!("rebase:{}", rebase.base),
+            NextAction::AwaitHumanInput { threads } => format!(
+                "await:{}",
+                threads.iter().map(thread_key).collect::<Vec<_>>().join(",")
+            ),
+            NextAction::PrReady => "ready".to_string(),
+        }
+    }
+}
+
+/// Analyze PR state and list everything it needs, most pressing first. The
+/// first item is the headline recommendation; the rest are what else is going on (a failing
+/// check behind the review comments, checks still running behind a failure),
+/// so an agent can plan with the whole picture. A PR that needs nothing gets
+/// just `PrReady`.
+pub fn analyze_actions(
+    checks: &ChecksSummary,
+    threads: Vec<ReviewThread>,
+    mergeable: &MergeableStatus,
+    rebase: Option<&Rebase>,
+) -> Vec<NextAction> {
+    let awaiting_input = find_awaiting_input_threads(&threads);
     let actionable_threads = find_actionable_threads(threads);
-    let failed_checks = checks.failed();
-    let pending_checks = checks.pending();
+    let failed_check_names: Vec<String> = checks.failed().iter().map(|c| c.name.clone()).collect();
+    let pending_check_names: Vec<String> = checks.pending().iter().map(|c| c.name.clone()).collect();
+    let mut actions = Vec::new();
 
     // Priority 1: Respond to review comments
     if !actionable_threads.is_empty() {
-        return NextAction::RespondToComments {
+        actions.push(NextAction::RespondToComments {
             threads: actionable_threads,
-            also_has_ci_failures: !failed_checks.is_empty(),
-            ci_pending: !pending_checks.is_empty(),
-        };
+            also_has_ci_failures: !failed_check_names.is_empty(),
+            ci_pending: !pending_check_names.is_empty(),
+        });
     }
 
-    // Priority 2: Fix CI failures
-    if !failed_checks.is_empty() {
-        return NextAction::FixCiFailures {
-            failed_check_names: failed_checks.iter().map(|c| c.name.clone()).collect(),
-        };
+    // Priority 2: Rebase onto the base branch. A conflicted PR can't merge
+    // however green CI is, and CI reruns after the rebase anyway.
+    if *mergeable == MergeableStatus::Conflicting {
+        actions.push(NextAction::ResolveConflicts {
+            failed_check_names: failed_check_names.clone(),
+        });
     }
 
-    // Priority 3: Wait for CI
-    if !pending_checks.is_empty() {
-        return NextAction::WaitForCi {
-            pending_check_names: pending_checks.iter().map(|c| c.name.clone()).collect(),
-        };
+    // Priority 3: Fix CI failures
+    if !failed_check_names.is_empty() {
+        actions.push(NextAction::FixCiFailures { failed_check_names });
+    }
+
+    // Priority 4: Wait for CI
+    if !pending_check_names.is_empty() {
+        actions.push(NextAction::WaitForCi { pending_check_names });
+    }
+
+    // Priority 5: Catch up with the base branch
+    if let Some(rebase) = rebase {
+        actions.push(NextAction::Rebase {
+            rebase: rebase.clone(),
+        });
+    }
+
+    // Priority 6: Wait for reviewers to answer Claude's questions
+    if !awaiting_input.is_empty() {
+        actions.push(NextAction::AwaitHumanInput {
+            threads: awaiting_input,
+        });
     }
 
     // All good!
-    NextAction::PrReady
+    if actions.is_empty() {
+        actions.push(NextAction::PrReady);
+    }
+    actions
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::checks::{Check, CheckStatus};
+    use crate::checks::{Check, CheckSource, CheckStatus};
     use crate::threads::ThreadComment;
 
     fn make_check(name: &str, status: CheckStatus) -> Check {
@@ -71,28 +155,23 @@ mod tests {
             name: name.to_string(),
             status,
             url: None,
+            source: CheckSource::CheckRun,
+            started_at: None,
+            attempts: 1,
+            app: None,
         }
     }
 
     fn make_comment(author: &str, body: &str) -> ThreadComment {
-        ThreadComment {
-            id: format!("comment_{}", body.len()),
-            author: author.to_string(),
-            body: body.to_string(),
-            diff_hunk: None,
-            url: None,
-            created_at: None,
-        }
+        ThreadComment::for_test(&format!("comment_{}", body.len()), author, body)
     }
 
     fn make_thread(id: &str, resolved: bool, comments: Vec<ThreadComment>) -> ReviewThread {
         ReviewThread {
-            id: id.to_string(),
             is_resolved: resolved,
-            is_outdated: false,
             path: Some("src/main.rs".to_string()),
             line: Some(42),
-            comments,
+            ..ReviewThread::for_test(id, comments)
         }
     }
 
@@ -106,7 +185,7 @@ mod tests {
         };
         let threads = vec![]; // No threads
 
-        match analyze_pr(&checks, threads) {
+        match analyze_actions(&checks, threads, &MergeableStatus::Unknown, None).remove(0) {
             NextAction::PrReady => {}
             other => panic!("Expected PrReady, got {:?}", other),
         }
@@ -123,7 +202,7 @@ mod tests {
             vec![make_comment("reviewer", "Looks good!")],
         )];
 
-        match analyze_pr(&checks, threads) {
+        match analyze_actions(&checks, threads, &MergeableStatus::Unknown, None).remove(0) {
             NextAction::PrReady => {}
             other => panic!("Expected PrReady, got {:?}", other),
         }
@@ -140,7 +219,7 @@ mod tests {
             vec![make_comment("reviewer", "Please fix this")],
         )];
 
-        match analyze_pr(&checks, threads) {
+        match analyze_actions(&checks, threads, &MergeableStatus::Unknown, None).remove(0) {
             NextAction::RespondToComments {
                 threads,
                 also_has_ci_failures,
@@ -165,7 +244,7 @@ mod tests {
             vec![make_comment("reviewer", "Question?")],
         )];
 
-        match analyze_pr(&checks, threads) {
+        match analyze_actions(&checks, threads, &MergeableStatus::Unknown, None).remove(0) {
             NextAction::RespondToComments {
                 also_has_ci_failures,
                 ..
@@ -186,7 +265,7 @@ mod tests {
         };
         let threads = vec![]; // No actionable threads
 
-        match analyze_pr(&checks, threads) {
+        match analyze_actions(&checks, threads, &MergeableStatus::Unknown, None).remove(0) {
             NextAction::FixCiFailures { failed_check_names } => {
                 assert_eq!(failed_check_names, vec!["test"]);
             }
@@ -204,7 +283,7 @@ mod tests {
         };
         let threads = vec![];
 
-        match analyze_pr(&checks, threads) {
+        match analyze_actions(&checks, threads, &MergeableStatus::Unknown, None).remove(0) {
             NextAction::WaitForCi { pending_check_names } => {
                 assert_eq!(pending_check_names, vec!["test"]);
             }
@@ -224,7 +303,7 @@ mod tests {
             vec![make_comment("reviewer", "Fix this")],
         )];
 
-        match analyze_pr(&checks, threads) {
+        match analyze_actions(&checks, threads, &MergeableStatus::Unknown, None).remove(0) {
             NextAction::RespondToComments { .. } => {}
             other => panic!("Expected RespondToComments, got {:?}", other),
         }
@@ -241,12 +320,35 @@ mod tests {
         };
         let threads = vec![];
 
-        match analyze_pr(&checks, threads) {
+        match analyze_actions(&checks, threads, &MergeableStatus::Unknown, None).remove(0) {
             NextAction::FixCiFailures { .. } => {}
             other => panic!("Expected FixCiFailures, got {:?}", other),
         }
     }
 
+    #[test]
+    fn analyze_await_human_input() {
+        let checks = ChecksSummary {
+            checks: vec![make_check("build", CheckStatus::Pass)],
+        };
+        let threads = vec![make_thread(
+            "T1",
+            false,
+            vec![
+                make_comment("reviewer", "Should this be configurable?"),
+                make_comment("claude", "🤖 From Claude: ❓ Per repo or per user?"),
+            ],
+        )];
+
+        match analyze_actions(&checks, threads, &MergeableStatus::Unknown, None).remove(0) {
+            NextAction::AwaitHumanInput { threads } => {
+                assert_eq!(threads.len(), 1);
+                assert_eq!(threads[0].id, "T1");
+            }
+            other => panic!("Expected AwaitHumanInput, got {:?}", other),
+        }
+    }
+
     #[test]
     fn analyze_ignores_paperclip_thread() {
         // A paperclip thread should not be treated as actionable
@@ -259,7 +361,7 @@ mod tests {
             vec![make_comment("reviewer", ":paperclip: For human review only")],
         )];
 
-        match analyze_pr(&checks, threads) {
+        match analyze_actions(&checks, threads, &MergeableStatus::Unknown, None).remove(0) {
             NextAction::PrReady => {}
             other => panic!("Expected PrReady, got {:?}", other),
         }
@@ -283,7 +385,7 @@ mod tests {
             ),
         ];
 
-        match analyze_pr(&checks, threads) {
+        match analyze_actions(&checks, threads, &MergeableStatus::Unknown, None).remove(0) {
             NextAction::RespondToComments { threads, .. } => {
                 assert_eq!(threads.len(), 1);
                 assert_eq!(threads[0].thread.id, "T2");
@@ -291,4 +393,77 @@ mod tests {
             other => panic!("Expected RespondToComments, got {:?}", other),
         }
     }
+
+    #[test]
+    fn conflicts_come_after_comments_and_before_ci() {
+        let checks = ChecksSummary {
+            checks: vec![
+                make_check("build", CheckStatus::Fail),
+                make_check("test", CheckStatus::Pass),
+            ],
+        };
+        match analyze_actions(&checks, vec![], &MergeableStatus::Conflicting, None).remove(0) {
+            NextAction::ResolveConflicts { failed_check_names } => {
+                assert_eq!(failed_check_names, vec!["build"]);
+            }
+            other => panic!("Expected ResolveConflicts, got {:?}", other),
+        }
+
+        let passing = ChecksSummary { checks: vec![make_check("build", CheckStatus::Pass)] };
+        assert!(matches!(
+            analyze_actions(&passing, vec![], &MergeableStatus::Conflicting, None).remove(0),
+            NextAction::ResolveConflicts { .. }
+        ));
+
+        let threads = vec![make_thread("T1", false, vec![make_comment("reviewer", "Fix")])];
+        assert!(matches!(
+            analyze_actions(&passing, threads, &MergeableStatus::Conflicting, None).remove(0),
+            NextAction::RespondToComments { .. }
+        ));
+    }
+
+    #[test]
+    fn action_list_has_everything_most_pressing_first() {
+        let checks = ChecksSummary {
+            checks: vec![
+                make_check("build", CheckStatus::Fail),
+                make_check("e2e", CheckStatus::Pending),
+            ],
+        };
+        let threads = vec![make_thread("T1", false, vec![make_comment("reviewer", "Fix")])];
+        let actions = analyze_actions(&checks, threads, &MergeableStatus::Mergeable, None);
+        let kinds: Vec<String> = actions.iter().map(NextAction::fingerprint).collect();
+        assert_eq!(kinds, vec!["respond:T1@comment_3:true", "fix:build", "wait"]);
+
+        let passing = ChecksSummary { checks: vec![make_check("build", CheckStatus::Pass)] };
+        let actions = analyze_actions(&passing, vec![], &MergeableStatus::Mergeable, None);
+        assert!(matches!(actions.as_slice(), [NextAction::PrReady]));
+
+        let rebase = Rebase::recommended("main", 80, &[], 50).unwrap();
+        let actions = analyze_actions(&passing, vec![], &MergeableStatus::Mergeable, Some(&rebase));
+        assert!(matches!(actions.as_slice(), [NextAction::Rebase { .. }]));
+    }
+
+    #[test]
+    fn fingerprint_changes_with_new_comments_not_ci_progress() {
+        let pending = |names: &[&str]| ChecksSummary {
+            checks: names.iter().map(|n| make_check(n, CheckStatus::Pending)).collect(),
+        };
+        assert_eq!(
+            analyze_actions(&pending(&["build", "test"]), vec![], &MergeableStatus::Unknown, None).remove(0).fingerprint(),
+            analyze_actions(&pending(&["test"]), vec![], &MergeableStatus::Unknown, None).remove(0).fingerprint()
+        );
+
+        let passing = ChecksSummary { checks: vec![make_check("build", CheckStatus::Pass)] };
+        let one = vec![make_thread("T1", false, vec![make_comment("reviewer", "Fix")])];
+        let two = vec![make_thread(
+            "T1",
+            false,
+            vec![make_comment("reviewer", "Fix"), make_comment("reviewer", "And this too")],
+        )];
+        assert_ne!(
+            analyze_actions(&passing, one, &MergeableStatus::Unknown, None).remove(0).fingerprint(),
+            analyze_actions(&passing, two, &MergeableStatus::Unknown, None).remove(0).fingerprint()
+        );
+    }
 }
@@ -0,0 +1,380 @@
+// Check run annotations via the GitHub Checks API.
+// Linters and test reporters (clippy, eslint, GH Actions `::error` lines, the
+// CircleCI Checks app, ...) attach file/line annotations to their check runs.
+// These are usually a much better failure signal than raw logs, and they only
+// need `gh` auth — no CircleCI token.
+
+use crate::request_log::TracedOutput;
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::process::Command;
+
+/// A check run on the PR's head commit, as listed by the Checks API.
+#[derive(Debug, Clone)]
+pub struct CheckRun {
+    pub id: u64,
+    pub name: String,
+    /// e.g. "success", "failure", "timed_out"; None while still running.
+    pub conclusion: Option<String>,
+    pub annotations_count: u64,
+}
+
+impl CheckRun {
+    /// Returns true if the check run concluded in a way GitHub shows as failed.
+    pub fn is_failed(&self) -> bool {
+        matches!(
+            self.conclusion.as_deref(),
+            Some("failure") | Some("timed_out") | Some("action_required")
+        )
+    }
+}
+
+/// A single check annotation (GitHub's "highlighted error" in the UI).
+#[derive(Debug, Clone)]
+pub struct Annotation {
+    /// Name of the check run the annotation belongs to.
+    pub check_name: String,
+    pub path: String,
+    pub start_line: Option<u32>,
+    pub end_line: Option<u32>,
+    /// "failure", "warning", or "notice".
+    pub level: String,
+    pub message: String,
+}
+
+impl Annotation {
+    /// Format the annotation location as `path:line` (or `path:start-end`).
+    /// Returns an empty string for annotations without a path.
+    pub fn location(&self) -> String {
+        if self.path.is_empty() {
+            return String::new();
+        }
+        match (self.start_line, self.end_line) {
+            (Some(start), Some(end)) if end > start => format!("{}:{}-{}", self.path, start, end),
+            (Some(start), _) => format!("{}:{}", self.path, start),
+            _ => self.path.clone(),
+        }
+    }
+}
+
+/// Trait for Checks API operations, allowing test implementations.
+pub trait AnnotationsClient {
+    /// List check runs on the PR's head commit.
+    fn fetch_check_runs(&self, owner: &str, repo: &str, pr_number: u64) -> Result<Vec<CheckRun>>;
+
+    /// Fetch annotations for a single check run. `check_name` is copied onto
+    /// each returned annotation.
+    fn fetch_annotations(
+        &self,
+        owner: &str,
+        repo: &str,
+        check_run_id: u64,
+        check_name: &str,
+    ) -> Result<Vec<Annotation>>;
+}
+
+/// Real client that uses `gh api`.
+pub struct RealAnnotationsClient;
+
+#[derive(Deserialize)]
+struct HeadRefView {
+    #[serde(rename = "headRefOid")]
+    head_ref_oid: String,
+}
+
+#[derive(Deserialize)]
+struct CheckRunsResponse {
+    check_runs: Vec<CheckRunResponse>,
+}
+
+#[derive(Deserialize)]
+struct CheckRunResponse {
+    id: u64,
+    name: String,
+    conclusion: Option<String>,
+    output: CheckRunOutput,
+}
+
+#[derive(Deserialize)]
+struct CheckRunOutput {
+    #[serde(default)]
+    annotations_count: u64,
+}
+
+#[derive(Deserialize)]
+struct AnnotationResponse {
+    #[serde(default)]
+    path: String,
+    start_line: Option<u32>,
+    end_line: Option<u32>,
+    annotation_level: String,
+    message: String,
+}
+
+/// Look up the head commit SHA of a PR using `gh pr view`.
+pub fn fetch_head_sha(owner: &str, repo: &str, pr_number: u64) -> Result<String> {
+    let output = Command::new("gh")
+        .args([
+            "pr",
+            "view",
+            &pr_number.to_string(),
+            "--repo",
+            &format!("{}/{}", owner, repo),
+            "--json",
+            "headRefOid",
+        ])
+        .traced_output()
+        .context("Failed to run 'gh pr view'")?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        anyhow::bail!("Failed to fetch PR head commit: {}", stderr.trim());
+    }
+
+    let view: HeadRefView =
+        serde_json::from_slice(&output.stdout).context("Failed to parse gh pr view output")?;
+    Ok(view.head_ref_oid)
+}
+
+impl AnnotationsClient for RealAnnotationsClient {
+    fn fetch_check_runs(&self, owner: &str, repo: &str, pr_number: u64) -> Result<Vec<CheckRun>> {
+        let sha = fetch_head_sha(owner, repo, pr_number)?;
+        let path = format!(
+            "/repos/{}/{}/commits/{}/check-runs?per_page=100",
+            owner, repo, sha
+        );
+        let output = Command::new("gh")
+            .args(["api", &path])
+            .traced_output()
+            .context("Failed to run 'gh api' for check runs")?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            anyhow::bail!("gh api check runs failed: {}", stderr.trim());
+        }
+        let parsed: CheckRunsResponse =
+            serde_json::from_slice(&output.stdout).context("parse check runs")?;
+        Ok(parsed
+            .check_runs
+            .into_iter()
+            .map(|r| CheckRun {
+                id: r.id,
+                name: r.name,
+                conclusion: r.conclusion,
+                annotations_count: r.output.annotations_count,
+            })
+            .collect())
+    }
+
+    fn fetch_annotations(
+        &self,
+        owner: &str,
+        repo: &str,
+        check_run_id: u64,
+        check_name: &str,
+    ) -> Result<Vec<Annotation>> {
+        let path = format!(
+            "/repos/{}/{}/check-runs/{}/annotations?per_page=100",
+            owner, repo, check_run_id
+        );
+        let output = Command::new("gh")
+            .args(["api", &path])
+            .traced_output()
+            .context("Failed to run 'gh api' for annotations")?;
+        if !output.status.success() {
+            // Annotations may 404 in some cases; treat as "no annotations".
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            if stderr.contains("HTTP 404") {
+                return Ok(vec![]);
+            }
+            anyhow::bail!("gh api annotations failed: {}", stderr.trim());
+        }
+        let parsed: Vec<AnnotationResponse> =
+            serde_json::from_slice(&output.stdout).context("parse annotations")?;
+        Ok(parsed
+            .into_iter()
+            .map(|a| Annotation {
+                check_name: check_name.to_string(),
+                path: a.path,
+                start_line: a.start_line,
+                end_line: a.end_line,
+                level: a.annotation_level,
+                message: a.message,
+            })
+            .collect())
+    }
+}
+
+/// Fetch failure/warning annotations for the failed check runs named in
+/// `failed_check_names`. Notice-level annotations are dropped as noise.
+/// Per-check fetch errors are reported as warnings and skipped.
+pub fn get_failed_check_annotations(
+    client: &dyn AnnotationsClient,
+    owner: &str,
+    repo: &str,
+    pr_number: u64,
+    failed_check_names: &[&str],
+) -> Result<Vec<Annotation>> {
+    let runs = client.fetch_check_runs(owner, repo, pr_number)?;
+
+    let mut annotations = Vec::new();
+    for run in runs {
+        if !run.is_failed()
+            || run.annotations_count == 0
+            || !failed_check_names.contains(&run.name.as_str())
+        {
+            continue;
+        }
+        match client.fetch_annotations(owner, repo, run.id, &run.name) {
+            Ok(anns) => annotations.extend(anns.into_iter().filter(|a| a.level != "notice")),
+            Err(e) => eprintln!(
+                "Warning: Failed to fetch annotations for {}: {}",
+                run.name, e
+            ),
+        }
+    }
+    Ok(annotations)
+}
+
+/// Format annotations as a `path:line: [level] message` list grouped by check.
+pub fn format_annotations(annotations: &[Annotation]) -> String {
+    let mut out = String::new();
+    let mut current_check: Option<&str> = None;
+    for a in annotations {
+        if current_check != Some(a.check_name.as_str()) {
+            if current_check.is_some() {
+                out.push('\n');
+            }
+            out.push_str(&format!("### {}\n", a.check_name));
+            current_check = Some(&a.check_name);
+        }
+        let location = a.location();
+        // Multi-line messages are indented under the bullet.
+        let message = a.message.trim_end().replace('\n', "\n    ");
+        if location.is_empty() {
+            out.push_str(&format!("- [{}] {}\n", a.level, message));
+        } else {
+            out.push_str(&format!("- {}: [{}] {}\n", location, a.level, message));
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+
+    struct TestAnnotationsClient {
+        runs: Vec<CheckRun>,
+        annotations: Vec<Annotation>,
+        fetched_ids: RefCell<Vec<u64>>,
+    }
+
+    impl AnnotationsClient for TestAnnotationsClient {
+        fn fetch_check_runs(&self, _: &str, _: &str, _: u64) -> Result<Vec<CheckRun>> {
+            Ok(self.runs.clone())
+        }
+
+        fn fetch_annotations(
+            &self,
+            _: &str,
+            _: &str,
+            check_run_id: u64,
+            check_name: &str,
+        ) -> Result<Vec<Annotation>> {
+            self.fetched_ids.borrow_mut().push(check_run_id);
+            Ok(self
+                .annotations
+                .iter()
+                .filter(|a| a.check_name == check_name)
+                .cloned()
+                .collect())
+        }
+    }
+
+    fn make_run(id: u64, name: &str, conclusion: &str, annotations_count: u64) -> CheckRun {
+        CheckRun {
+            id,
+            name: name.to_string(),
+            conclusion: Some(conclusion.to_string()),
+            annotations_count,
+        }
+    }
+
+    fn make_annotation(check: &str, path: &str, line: Option<u32>, level: &str, msg: &str) -> Annotation {
+        Annotation {
+            check_name: check.to_string(),
+            path: path.to_string(),
+            start_line: line,
+            end_line: line,
+            level: level.to_string(),
+            message: msg.to_string(),
+        }
+    }
+
+    #[test]
+    fn location_formats() {
+        let mut a = make_annotation("lint", "src/lib.rs", Some(12), "failure", "x");
+        assert_eq!(a.location(), "src/lib.rs:12");
+        a.end_line = Some(14);
+        assert_eq!(a.location(), "src/lib.rs:12-14");
+        a.start_line = None;
+        assert_eq!(a.location(), "src/lib.rs");
+        a.path = String::new();
+        assert_eq!(a.location(), "");
+    }
+
+    #[test]
+    fn only_fetches_failed_runs_with_annotations() {
+        let client = TestAnnotationsClient {
+            runs: vec![
+                make_run(1, "lint", "failure", 2),
+                make_run(2, "build", "success", 5),
+                make_run(3, "test", "failure", 0),
+                make_run(4, "excluded", "failure", 3),
+            ],
+            annotations: vec![
+                make_annotation("lint", "src/a.rs", Some(1), "failure", "unused variable"),
+                make_annotation("lint", "src/b.rs", Some(2), "notice", "fyi"),
+            ],
+            fetched_ids: RefCell::new(vec![]),
+        };
+
+        let anns =
+            get_failed_check_annotations(&client, "o", "r", 1, &["lint", "test"]).unwrap();
+        // Only `lint` qualifies: `build` passed, `test` has no annotations,
+        // `excluded` isn't in the (filtered) failed-check list.
+        assert_eq!(*client.fetched_ids.borrow(), vec![1]);
+        // Notice-level annotations are dropped.
+        assert_eq!(anns.len(), 1);
+        assert_eq!(anns[0].message, "unused variable");
+    }
+
+    #[test]
+    fn timed_out_counts_as_failed() {
+        assert!(make_run(1, "x", "timed_out", 1).is_failed());
+        assert!(!make_run(1, "x", "neutral", 1).is_failed());
+        let pending = CheckRun {
+            id: 1,
+            name: "x".into(),
+            conclusion: None,
+            annotations_count: 0,
+        };
+        assert!(!pending.is_failed());
+    }
+
+    #[test]
+    fn format_annotations_basic() {
+        let anns = vec![
+            make_annotation("build", ".github", Some(1592), "failure", "Process failed"),
+            make_annotation("build", "", None, "failure", "Localserver died"),
+            make_annotation("lint", "src/x.rs", Some(3), "warning", "line one\nline two"),
+        ];
+        let s = format_annotations(&anns);
+        assert!(s.contains("### build\n"));
+        assert!(s.contains("- .github:1592: [failure] Process failed\n"));
+        assert!(s.contains("- [failure] Localserver died\n"));
+        assert!(s.contains("### lint\n"));
+        assert!(s.contains("- src/x.rs:3: [warning] line one\n    line two\n"));
+    }
+}
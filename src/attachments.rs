@@ -0,0 +1,181 @@
+// Downloading images attached to review comments.
+// A reviewer's screenshot is often the whole point of a comment ("this
+// dialog overflows"), but an agent only sees its URL, and attachments on
+// private repositories can't be fetched without GitHub credentials. With
+// `--download-attachments <dir>`, the images in the comments the analysis
+// shows are downloaded (with the `gh` token, sent only to GitHub hosts) and
+// their local paths printed next to the comment, so a vision-capable agent
+// can open them.
+
+use crate::concurrent;
+use crate::request_log::TracedOutput;
+use anyhow::{bail, Context, Result};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Larger files are skipped; screenshots are far smaller.
+const MAX_ATTACHMENT_BYTES: u64 = 25 * 1024 * 1024;
+
+/// Fetches attachments, allowing test implementations.
+pub trait Downloader: Sync {
+    /// The file's contents and, if the server said, its content type.
+    fn fetch(&self, url: &str) -> Result<(Vec<u8>, Option<String>)>;
+}
+
+/// Downloads over HTTPS, authenticating to GitHub with the `gh` token.
+pub struct RealDownloader {
+    token: Option<String>,
+}
+
+impl RealDownloader {
+    pub fn new() -> Self {
+        let token = Command::new("gh")
+            .args(["auth", "token"])
+            .traced_output()
+            .ok()
+            .filter(|o| o.status.success())
+            .map(|o| String::from_utf8_lossy(&o.stdout).trim().to_string())
+            .filter(|t| !t.is_empty());
+        Self { token }
+    }
+}
+
+/// Whether the GitHub token may be sent to `url`'s host.
+fn is_github_host(url: &str) -> bool {
+    let Ok(url) = reqwest::Url::parse(url) else {
+        return false;
+    };
+    matches!(url.host_str(), Some(host) if host == "github.com" || host.ends_with(".githubusercontent.com"))
+}
+
+impl Downloader for RealDownloader {
+    fn fetch(&self, url: &str) -> Result<(Vec<u8>, Option<String>)> {
+        let mut request = reqwest::blocking::Client::new().get(url);
+        if let Some(token) = self.token.as_ref().filter(|_| is_github_host(url)) {
+            request = request.header("Authorization", format!("token {}", token));
+        }
+        let response = request.send().context("Request failed")?;
+        if !response.status().is_success() {
+            bail!("HTTP {}", response.status());
+        }
+        if response.content_length().is_some_and(|len| len > MAX_ATTACHMENT_BYTES) {
+            bail!("larger than {} MB", MAX_ATTACHMENT_BYTES / 1024 / 1024);
+        }
+        let content_type = response
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .map(String::from);
+        Ok((response.bytes()?.to_vec(), content_type))
+    }
+}
+
+/// Download every URL into `dir`, returning each URL's local path. Files
+/// already downloaded (by an earlier analysis) are reused.
+pub fn download_all(downloader: &dyn Downloader, dir: &Path, urls: &[String]) -> Vec<(String, Result<PathBuf>)> {
+    if let Err(e) = std::fs::create_dir_all(dir) {
+        let message = format!("Failed to create {}: {}", dir.display(), e);
+        return urls.iter().map(|u| (u.clone(), Err(anyhow::anyhow!(message.clone())))).collect();
+    }
+    let paths = concurrent::map(urls, |url| download(downloader, dir, url));
+    urls.iter().cloned().zip(paths).collect()
+}
+
+fn download(downloader: &dyn Downloader, dir: &Path, url: &str) -> Result<PathBuf> {
+    let prefix = url_prefix(url);
+    if let Some(existing) = std::fs::read_dir(dir)?
+        .flatten()
+        .find(|e| e.file_name().to_string_lossy().starts_with(&prefix))
+    {
+        return Ok(existing.path());
+    }
+    let (bytes, content_type) = downloader.fetch(url)?;
+    let path = dir.join(file_name(url, content_type.as_deref()));
+    std::fs::write(&path, bytes).with_context(|| format!("Failed to write {}", path.display()))?;
+    Ok(path)
+}
+
+/// Identifies a URL's file in the download directory.
+fn url_prefix(url: &str) -> String {
+    let mut hasher = DefaultHasher::new();
+    url.hash(&mut hasher);
+    format!("{:016x}-", hasher.finish())
+}
+
+/// e.g. "<hash>-screenshot.png": the URL's last path segment, with an
+/// extension from the content type if it has none.
+fn file_name(url: &str, content_type: Option<&str>) -> String {
+    let segment = url
+        .split(['?', '#'])
+        .next()
+        .unwrap_or(url)
+        .rsplit('/')
+        .find(|s| !s.is_empty())
+        .unwrap_or("attachment");
+    let mut name: String = segment
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || "-_.".contains(c) { c } else { '_' })
+        .collect();
+    if !name.contains('.') {
+        let extension = match content_type.map(|t| t.split(';').next().unwrap_or(t).trim()) {
+            Some("image/png") => ".png",
+            Some("image/jpeg") => ".jpg",
+            Some("image/gif") => ".gif",
+            Some("image/webp") => ".webp",
+            Some("image/svg+xml") => ".svg",
+            _ => "",
+        };
+        name.push_str(extension);
+    }
+    format!("{}{}", url_prefix(url), name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[derive(Default)]
+    struct CountingDownloader(AtomicUsize);
+
+    impl Downloader for CountingDownloader {
+        fn fetch(&self, url: &str) -> Result<(Vec<u8>, Option<String>)> {
+            if url.contains("missing") {
+                bail!("HTTP 404 Not Found");
+            }
+            self.0.fetch_add(1, Ordering::SeqCst);
+            Ok((b"PNG".to_vec(), Some("image/png".to_string())))
+        }
+    }
+
+    #[test]
+    fn downloads_once_and_names_files_by_type() {
+        let dir = std::env::temp_dir().join(format!("pr-loop-attachments-{}", std::process::id()));
+        let urls = vec![
+            "https://github.com/user-attachments/assets/0a1b".to_string(),
+            "https://example.com/missing.png".to_string(),
+        ];
+        let downloader = CountingDownloader::default();
+
+        let results = download_all(&downloader, &dir, &urls);
+        let path = results[0].1.as_ref().unwrap();
+        assert!(path.file_name().unwrap().to_string_lossy().ends_with("-0a1b.png"));
+        assert_eq!(std::fs::read(path).unwrap(), b"PNG");
+        assert!(results[1].1.is_err());
+
+        // A later analysis reuses the file
+        download_all(&downloader, &dir, &urls[..1]);
+        assert_eq!(downloader.0.load(Ordering::SeqCst), 1);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn token_only_goes_to_github() {
+        assert!(is_github_host("https://github.com/user-attachments/assets/1"));
+        assert!(is_github_host("https://private-user-images.githubusercontent.com/1.png"));
+        assert!(!is_github_host("https://github.com.evil.example/1.png"));
+        assert!(!is_github_host("https://imgur.com/1.png"));
+    }
+}
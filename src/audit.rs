@@ -0,0 +1,317 @@
+// Audit log of every mutation pr-loop performs on a PR.
+// Replies, comment edits/deletions, thread resolutions, description edits,
+// ready flips, and review requests are appended to `.pr-loop/audit.log` at
+// the repository root, so a human can review exactly what the agent did.
+//
+// Logging wraps the real clients (`Audited<C>` implements both ReplyClient
+// and PrClient), so every caller is covered without threading a logger
+// through each call site.
+
+use crate::pr::{MergeMethod, PrClient, PrState};
+use crate::reply::{ReplyClient, ReplyResult};
+use anyhow::{Context, Result};
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Directory (relative to the repo root) holding pr-loop's local state.
+pub const STATE_DIR: &str = ".pr-loop";
+const AUDIT_FILE: &str = "audit.log";
+
+/// Where audit entries are appended, and which PR they're about.
+#[derive(Debug, Clone)]
+pub struct AuditLog {
+    /// None disables logging.
+    path: Option<PathBuf>,
+    /// e.g. "owner/repo#123"
+    pr_label: String,
+}
+
+impl AuditLog {
+    /// Log under `<root>/.pr-loop/audit.log`.
+    pub fn new(root: &Path, pr_label: String) -> Self {
+        Self {
+            path: Some(root.join(STATE_DIR).join(AUDIT_FILE)),
+            pr_label,
+        }
+    }
+
+    /// An audit log that records nothing.
+    pub fn disabled() -> Self {
+        Self {
+            path: None,
+            pr_label: String::new(),
+        }
+    }
+
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    /// Append one entry. Failures to write are reported as warnings rather
+    /// than failing the mutation that already happened.
+    pub fn record(&self, action: &str, detail: &str) {
+        let Some(path) = &self.path else { return };
+        let line = format_entry(&jiff::Timestamp::now().to_string(), &self.pr_label, action, detail);
+        if let Err(e) = append_line(path, &line) {
+            eprintln!("Warning: Failed to write audit log {}: {}", path.display(), e);
+        }
+    }
+
+    /// Read back the entries recorded for this log's PR.
+    pub fn entries_for_pr(&self) -> Result<Vec<String>> {
+        let Some(path) = &self.path else {
+            return Ok(vec![]);
+        };
+        if !path.exists() {
+            return Ok(vec![]);
+        }
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        Ok(entries_for(&content, &self.pr_label))
+    }
+}
+
+/// Format an entry as a single tab-separated line.
+fn format_entry(timestamp: &str, pr_label: &str, action: &str, detail: &str) -> String {
+    // Keep each entry on one line even if a detail contains newlines/tabs.
+    let detail = detail.replace(['\n', '\t'], " ");
+    format!("{}\t{}\t{}\t{}", timestamp, pr_label, action, detail)
+}
+
+/// Select the lines of a log belonging to `pr_label`.
+fn entries_for(content: &str, pr_label: &str) -> Vec<String> {
+    content
+        .lines()
+        .filter(|line| line.split('\t').nth(1) == Some(pr_label))
+        .map(str::to_string)
+        .collect()
+}
+
+fn append_line(path: &Path, line: &str) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+        // Keep local state out of the user's commits.
+        let gitignore = dir.join(".gitignore");
+        if !gitignore.exists() {
+            std::fs::write(&gitignore, "*\n")?;
+        }
+    }
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(file, "{}", line)?;
+    Ok(())
+}
+
+/// Short single-line preview of a comment body for the log.
+fn preview(body: &str) -> String {
+    const MAX_CHARS: usize = 80;
+    let first_line = body.lines().next().unwrap_or("");
+    if first_line.chars().count() > MAX_CHARS || body.lines().nth(1).is_some() {
+        let truncated: String = first_line.chars().take(MAX_CHARS).collect();
+        format!("{}…", truncated)
+    } else {
+        first_line.to_string()
+    }
+}
+
+/// Format audit entries as a Markdown PR comment.
+pub fn format_digest(entries: &[String]) -> String {
+    let mut out = String::from("### pr-loop activity log\n\n");
+    out.push_str("| Time | Action | Details |\n|---|---|---|\n");
+    for entry in entries {
+        let fields: Vec<&str> = entry.splitn(4, '\t').collect();
+        if let [time, _, action, detail] = fields.as_slice() {
+            out.push_str(&format!(
+                "| {} | {} | {} |\n",
+                time,
+                action,
+                detail.replace('|', "\\|")
+            ));
+        }
+    }
+    out
+}
+
+/// A client wrapper that records successful mutations to an audit log.
+pub struct Audited<C> {
+    inner: C,
+    log: AuditLog,
+}
+
+impl<C> Audited<C> {
+    pub fn new(inner: C, log: AuditLog) -> Self {
+        Self { inner, log }
+    }
+}
+
+impl<C: ReplyClient> ReplyClient for Audited<C> {
+    fn post_reply(&self, thread_id: &str, body: &str) -> Result<ReplyResult> {
+        let result = self.inner.post_reply(thread_id, body)?;
+        self.log.record(
+            "reply_posted",
+            &format!(
+                "thread={} comment={} body=\"{}\"",
+                thread_id,
+                result.comment_id,
+                preview(body)
+            ),
+        );
+        Ok(result)
+    }
+
+    fn delete_comment(&self, comment_id: &str) -> Result<()> {
+        self.inner.delete_comment(comment_id)?;
+        self.log
+            .record("comment_deleted", &format!("comment={}", comment_id));
+        Ok(())
+    }
+
+    fn update_comment(&self, comment_id: &str, body: &str) -> Result<()> {
+        self.inner.update_comment(comment_id, body)?;
+        self.log.record(
+            "comment_edited",
+            &format!("comment={} body=\"{}\"", comment_id, preview(body)),
+        );
+        Ok(())
+    }
+
+    fn resolve_thread(&self, thread_id: &str) -> Result<()> {
+        self.inner.resolve_thread(thread_id)?;
+        self.log
+            .record("thread_resolved", &format!("thread={}", thread_id));
+        Ok(())
+    }
+}
+
+impl<C: PrClient> PrClient for Audited<C> {
+    fn is_draft(&self, owner: &str, repo: &str, pr_number: u64) -> Result<bool> {
+        self.inner.is_draft(owner, repo, pr_number)
+    }
+
+    fn get_body(&self, owner: &str, repo: &str, pr_number: u64) -> Result<String> {
+        self.inner.get_body(owner, repo, pr_number)
+    }
+
+    fn set_body(&self, owner: &str, repo: &str, pr_number: u64, body: &str) -> Result<()> {
+        self.inner.set_body(owner, repo, pr_number, body)?;
+        self.log
+            .record("body_edited", &format!("length={}", body.chars().count()));
+        Ok(())
+    }
+
+    fn mark_ready(&self, owner: &str, repo: &str, pr_number: u64) -> Result<()> {
+        self.inner.mark_ready(owner, repo, pr_number)?;
+        self.log.record("marked_ready", "");
+        Ok(())
+    }
+
+    fn get_commit_count(&self, owner: &str, repo: &str, pr_number: u64) -> Result<usize> {
+        self.inner.get_commit_count(owner, repo, pr_number)
+    }
+
+    fn add_reviewer(&self, owner: &str, repo: &str, pr_number: u64, reviewer: &str) -> Result<()> {
+        self.inner.add_reviewer(owner, repo, pr_number, reviewer)?;
+        self.log
+            .record("reviewer_requested", &format!("reviewer={}", reviewer));
+        Ok(())
+    }
+
+    fn add_comment(&self, owner: &str, repo: &str, pr_number: u64, body: &str) -> Result<()> {
+        self.inner.add_comment(owner, repo, pr_number, body)?;
+        self.log
+            .record("pr_comment_posted", &format!("body=\"{}\"", preview(body)));
+        Ok(())
+    }
+
+    fn merge(&self, owner: &str, repo: &str, pr_number: u64, method: MergeMethod, auto: bool) -> Result<()> {
+        self.inner.merge(owner, repo, pr_number, method, auto)?;
+        let event = if auto { "auto_merge_enabled" } else { "merged" };
+        self.log.record(event, &format!("method={}", method.flag().trim_start_matches('-')));
+        Ok(())
+    }
+
+    fn state(&self, owner: &str, repo: &str, pr_number: u64) -> Result<PrState> {
+        self.inner.state(owner, repo, pr_number)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct OkReplyClient;
+
+    impl ReplyClient for OkReplyClient {
+        fn post_reply(&self, _thread_id: &str, _body: &str) -> Result<ReplyResult> {
+            Ok(ReplyResult {
+                comment_id: "C9".to_string(),
+                url: None,
+            })
+        }
+        fn delete_comment(&self, _comment_id: &str) -> Result<()> {
+            anyhow::bail!("Test failure")
+        }
+        fn update_comment(&self, _comment_id: &str, _body: &str) -> Result<()> {
+            Ok(())
+        }
+        fn resolve_thread(&self, _thread_id: &str) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    fn temp_root(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("pr-loop-audit-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn records_successful_mutations_only() {
+        let root = temp_root("mutations");
+        let log = AuditLog::new(&root, "o/r#1".to_string());
+        let client = Audited::new(OkReplyClient, log.clone());
+
+        client.post_reply("T1", "Fixed it\nwith details").unwrap();
+        client.resolve_thread("T1").unwrap();
+        assert!(client.delete_comment("C1").is_err());
+
+        let entries = log.entries_for_pr().unwrap();
+        assert_eq!(entries.len(), 2);
+        assert!(entries[0].ends_with("\to/r#1\treply_posted\tthread=T1 comment=C9 body=\"Fixed it…\""));
+        assert!(entries[1].ends_with("\tthread_resolved\tthread=T1"));
+        // The state dir ignores itself
+        assert_eq!(
+            std::fs::read_to_string(root.join(STATE_DIR).join(".gitignore")).unwrap(),
+            "*\n"
+        );
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn entries_are_filtered_by_pr() {
+        let content = "t1\to/r#1\tmarked_ready\t\nt2\to/r#2\tmarked_ready\t\n";
+        assert_eq!(entries_for(content, "o/r#2"), vec!["t2\to/r#2\tmarked_ready\t"]);
+    }
+
+    #[test]
+    fn format_entry_stays_on_one_line() {
+        assert_eq!(
+            format_entry("t", "o/r#1", "comment_edited", "a\tb\nc"),
+            "t\to/r#1\tcomment_edited\ta b c"
+        );
+    }
+
+    #[test]
+    fn disabled_log_records_nothing() {
+        let log = AuditLog::disabled();
+        log.record("marked_ready", "");
+        assert!(log.path().is_none());
+        assert!(log.entries_for_pr().unwrap().is_empty());
+    }
+
+    #[test]
+    fn digest_is_markdown_table() {
+        let digest = format_digest(&["t1\to/r#1\tcomment_deleted\tcomment=C1 a|b".to_string()]);
+        assert!(digest.contains("| t1 | comment_deleted | comment=C1 a\\|b |"));
+    }
+}
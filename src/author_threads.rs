@@ -0,0 +1,194 @@
+// Threads the PR's author opened on their own PR.
+// Claude usually runs as the PR author, so a thread the author started
+// without the Claude marker is the human writing on their own PR — often a
+// note to the agent ("remember to update the docs here") rather than review
+// feedback waiting on a reply. `[author_threads] policy` decides what they
+// are:
+//
+// - `actionable` (the default): like any other thread.
+// - `informational`: no response needed, but shown in the analysis as notes
+//   from the author, for context.
+// - `paperclip`: ignored, as if marked 📎.
+//
+// Once someone else joins the thread it's a conversation and counts as
+// usual. Like side conversations, filtering wraps the threads client
+// (`AuthorThreads<C>`), so waits and the analysis agree.
+
+use crate::request_log::TracedOutput;
+use crate::threads::{PendingReview, ReviewThread, ThreadsClient};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::process::Command;
+use std::sync::{Mutex, OnceLock};
+
+/// What threads the PR author opened on their own PR count as.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AuthorThreadPolicy {
+    #[default]
+    Actionable,
+    Informational,
+    Paperclip,
+}
+
+/// Looks up the PR author's login.
+pub type AuthorLookup = Box<dyn Fn() -> Option<String> + Send + Sync>;
+
+/// Whether `thread` was opened by `author` (not as Claude) and nobody else
+/// has commented in it since, apart from Claude.
+pub fn is_authors_own(thread: &ReviewThread, author: &str) -> bool {
+    let Some(first) = thread.comments.first() else {
+        return false;
+    };
+    !first.is_from_claude()
+        && thread
+            .comments
+            .iter()
+            .filter(|c| !c.is_from_claude())
+            .all(|c| c.author.eq_ignore_ascii_case(author))
+}
+
+/// The PR author's login.
+pub fn fetch_pr_author(owner: &str, repo: &str, pr_number: u64) -> Result<String> {
+    let output = Command::new("gh")
+        .args([
+            "pr",
+            "view",
+            &pr_number.to_string(),
+            "--repo",
+            &format!("{}/{}", owner, repo),
+            "--json",
+            "author",
+            "--jq",
+            ".author.login",
+        ])
+        .traced_output()
+        .context("Failed to run 'gh pr view'")?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        anyhow::bail!("Failed to look up the PR author: {}", stderr.trim());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// A threads client whose results leave out the author's own threads,
+/// unless the policy makes them actionable.
+pub struct AuthorThreads<C> {
+    inner: C,
+    policy: AuthorThreadPolicy,
+    author_lookup: AuthorLookup,
+    /// Looked up the first time a thread might be the author's own.
+    author: OnceLock<Option<String>>,
+    /// With `informational`, the threads left out by the last fetch.
+    notes: Mutex<Vec<ReviewThread>>,
+}
+
+impl<C> AuthorThreads<C> {
+    pub fn new(inner: C, policy: AuthorThreadPolicy, author_lookup: AuthorLookup) -> Self {
+        Self {
+            inner,
+            policy,
+            author_lookup,
+            author: OnceLock::new(),
+            notes: Mutex::new(vec![]),
+        }
+    }
+
+    /// The author's notes left out by the last fetch (`informational` only).
+    pub fn notes(&self) -> Vec<ReviewThread> {
+        self.notes.lock().expect("notes lock poisoned").clone()
+    }
+}
+
+impl<C: ThreadsClient> ThreadsClient for AuthorThreads<C> {
+    fn fetch_threads(&self, owner: &str, repo: &str, pr_number: u64) -> Result<Vec<ReviewThread>> {
+        let threads = self.inner.fetch_threads(owner, repo, pr_number)?;
+        if self.policy == AuthorThreadPolicy::Actionable || !threads.iter().any(|t| t.needs_response()) {
+            return Ok(threads);
+        }
+        let Some(author) = self.author.get_or_init(|| (self.author_lookup)()) else {
+            return Ok(threads);
+        };
+        let (own, rest): (Vec<ReviewThread>, Vec<ReviewThread>) = threads
+            .into_iter()
+            .partition(|t| t.needs_response() && is_authors_own(t, author));
+        if self.policy == AuthorThreadPolicy::Informational {
+            *self.notes.lock().expect("notes lock poisoned") = own;
+        }
+        Ok(rest)
+    }
+
+    fn fetch_thread_by_comment_id(&self, comment_id: &str) -> Result<ReviewThread> {
+        self.inner.fetch_thread_by_comment_id(comment_id)
+    }
+
+    fn fetch_pending_reviews(
+        &self,
+        owner: &str,
+        repo: &str,
+        pr_number: u64,
+    ) -> Result<Vec<PendingReview>> {
+        self.inner.fetch_pending_reviews(owner, repo, pr_number)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::threads::ThreadComment;
+
+    fn comment(author: &str, body: &str) -> ThreadComment {
+        ThreadComment::for_test(&format!("C-{}", body.len()), author, body)
+    }
+
+    fn thread(id: &str, comments: Vec<ThreadComment>) -> ReviewThread {
+        ReviewThread {
+            line: Some(3),
+            ..ReviewThread::for_test(id, comments)
+        }
+    }
+
+    struct FixedThreads(Vec<ReviewThread>);
+
+    impl ThreadsClient for FixedThreads {
+        fn fetch_threads(&self, _: &str, _: &str, _: u64) -> Result<Vec<ReviewThread>> {
+            Ok(self.0.clone())
+        }
+        fn fetch_thread_by_comment_id(&self, _: &str) -> Result<ReviewThread> {
+            anyhow::bail!("not used")
+        }
+    }
+
+    #[test]
+    fn sets_aside_the_authors_own_threads_by_policy() {
+        let note = thread("T1", vec![comment("alice", "Remember to update the docs here")]);
+        let joined = thread(
+            "T2",
+            vec![comment("alice", "Is this right?"), comment("bob", "No, use the other API")],
+        );
+        let review = thread("T3", vec![comment("bob", "Please rename this")]);
+        let all = vec![note.clone(), joined.clone(), review.clone()];
+        assert!(is_authors_own(&note, "Alice"));
+        assert!(!is_authors_own(&joined, "alice"));
+
+        let fetch = |policy| {
+            let client = AuthorThreads::new(
+                FixedThreads(all.clone()),
+                policy,
+                Box::new(|| Some("alice".to_string())),
+            );
+            let ids: Vec<String> = client.fetch_threads("o", "r", 1).unwrap().into_iter().map(|t| t.id).collect();
+            let notes: Vec<String> = client.notes().into_iter().map(|t| t.id).collect();
+            (ids, notes)
+        };
+        assert_eq!(fetch(AuthorThreadPolicy::Actionable).0, vec!["T1", "T2", "T3"]);
+        assert_eq!(
+            fetch(AuthorThreadPolicy::Informational),
+            (vec!["T2".to_string(), "T3".to_string()], vec!["T1".to_string()])
+        );
+        assert_eq!(
+            fetch(AuthorThreadPolicy::Paperclip),
+            (vec!["T2".to_string(), "T3".to_string()], vec![])
+        );
+    }
+}
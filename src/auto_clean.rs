@@ -0,0 +1,177 @@
+// Opportunistic cleanup of pure-Claude threads during iteration.
+// `clean-threads` and `ready` delete resolved threads where only Claude
+// spoke, but on a long PR they pile up until the end. With `--auto-clean`,
+// every thread fetch also deletes those that have sat resolved for a while,
+// so the PR stays readable as the loop runs.
+//
+// Like snoozing, this wraps the threads client: the deleted threads are left
+// out of the result, so the snapshot that triggered a deletion already looks
+// like the next one and wait modes don't mistake the cleanup for activity.
+
+use crate::reply::ReplyClient;
+use crate::threads::{PendingReview, ReviewThread, ThreadsClient};
+use anyhow::Result;
+use jiff::{SignedDuration, Timestamp};
+
+/// A threads client that deletes stale pure-Claude threads as it fetches.
+pub struct AutoClean<C, R> {
+    inner: C,
+    deleter: R,
+    /// How long a thread's last comment must be old; None disables cleanup.
+    min_age: Option<SignedDuration>,
+}
+
+impl<C, R> AutoClean<C, R> {
+    pub fn new(inner: C, deleter: R, min_age_minutes: Option<u64>) -> Self {
+        Self {
+            inner,
+            deleter,
+            min_age: min_age_minutes.map(|m| SignedDuration::from_mins(m as i64)),
+        }
+    }
+}
+
+/// Returns true if `thread` is a resolved pure-Claude thread whose last
+/// comment is at least `min_age` old. Threads without timestamps are kept.
+fn is_stale(thread: &ReviewThread, min_age: SignedDuration, now: Timestamp) -> bool {
+    if thread.has_paperclip() || !thread.is_resolved || !thread.is_pure_claude() {
+        return false;
+    }
+    thread
+        .last_comment()
+        .and_then(|c| c.created_at.as_deref())
+        .and_then(|t| t.parse::<Timestamp>().ok())
+        .is_some_and(|at| now.duration_since(at) >= min_age)
+}
+
+impl<C: ThreadsClient, R: ReplyClient + Sync> ThreadsClient for AutoClean<C, R> {
+    fn fetch_threads(&self, owner: &str, repo: &str, pr_number: u64) -> Result<Vec<ReviewThread>> {
+        let threads = self.inner.fetch_threads(owner, repo, pr_number)?;
+        let Some(min_age) = self.min_age else {
+            return Ok(threads);
+        };
+        let now = Timestamp::now();
+        let mut kept = Vec::with_capacity(threads.len());
+        for thread in threads {
+            if !is_stale(&thread, min_age, now) {
+                kept.push(thread);
+                continue;
+            }
+            let failed = thread
+                .comment_ids()
+                .into_iter()
+                .filter(|id| match self.deleter.delete_comment(id) {
+                    Ok(()) => false,
+                    Err(e) => {
+                        eprintln!("Warning: Failed to delete comment {}: {}", id, e);
+                        true
+                    }
+                })
+                .count();
+            if failed == 0 {
+                eprintln!("🧹 Deleted resolved pure-Claude thread {}", thread.id);
+            } else {
+                // Partly deleted; report it as it was and retry next fetch
+                kept.push(thread);
+            }
+        }
+        Ok(kept)
+    }
+
+    fn fetch_thread_by_comment_id(&self, comment_id: &str) -> Result<ReviewThread> {
+        self.inner.fetch_thread_by_comment_id(comment_id)
+    }
+
+    fn fetch_pending_reviews(
+        &self,
+        owner: &str,
+        repo: &str,
+        pr_number: u64,
+    ) -> Result<Vec<PendingReview>> {
+        self.inner.fetch_pending_reviews(owner, repo, pr_number)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::reply::ReplyResult;
+    use crate::threads::{ThreadComment, CLAUDE_MARKER};
+    use std::sync::Mutex;
+
+    struct FixedThreads(Vec<ReviewThread>);
+
+    impl ThreadsClient for FixedThreads {
+        fn fetch_threads(&self, _: &str, _: &str, _: u64) -> Result<Vec<ReviewThread>> {
+            Ok(self.0.clone())
+        }
+
+        fn fetch_thread_by_comment_id(&self, _: &str) -> Result<ReviewThread> {
+            anyhow::bail!("not used")
+        }
+    }
+
+    #[derive(Default)]
+    struct RecordingDeleter(Mutex<Vec<String>>);
+
+    impl ReplyClient for &RecordingDeleter {
+        fn post_reply(&self, _: &str, _: &str) -> Result<ReplyResult> {
+            anyhow::bail!("not used")
+        }
+        fn delete_comment(&self, comment_id: &str) -> Result<()> {
+            self.0.lock().unwrap().push(comment_id.to_string());
+            Ok(())
+        }
+        fn update_comment(&self, _: &str, _: &str) -> Result<()> {
+            anyhow::bail!("not used")
+        }
+        fn resolve_thread(&self, _: &str) -> Result<()> {
+            anyhow::bail!("not used")
+        }
+    }
+
+    fn thread(id: &str, resolved: bool, author: &str, body: &str, minutes_ago: i64) -> ReviewThread {
+        let at = Timestamp::now() - SignedDuration::from_mins(minutes_ago);
+        let comment = ThreadComment {
+            created_at: Some(at.to_string()),
+            ..ThreadComment::for_test(&format!("{}-C1", id), author, body)
+        };
+        ReviewThread {
+            is_resolved: resolved,
+            path: None,
+            line: None,
+            ..ReviewThread::for_test(id, vec![comment])
+        }
+    }
+
+    #[test]
+    fn deletes_only_stale_resolved_pure_claude_threads() {
+        let claude = format!("{} Note to self", CLAUDE_MARKER);
+        let threads = FixedThreads(vec![
+            thread("OLD", true, "bot", &claude, 30),
+            thread("RECENT", true, "bot", &claude, 2),
+            thread("OPEN", false, "bot", &claude, 30),
+            thread("HUMAN", true, "alice", "Looks good", 30),
+        ]);
+        let deleter = RecordingDeleter::default();
+
+        let client = AutoClean::new(threads, &deleter, Some(10));
+        let ids: Vec<_> = client
+            .fetch_threads("o", "r", 1)
+            .unwrap()
+            .into_iter()
+            .map(|t| t.id)
+            .collect();
+        assert_eq!(ids, vec!["RECENT", "OPEN", "HUMAN"]);
+        assert_eq!(*deleter.0.lock().unwrap(), vec!["OLD-C1"]);
+    }
+
+    #[test]
+    fn disabled_passes_threads_through() {
+        let claude = format!("{} Note to self", CLAUDE_MARKER);
+        let deleter = RecordingDeleter::default();
+        let client = AutoClean::new(FixedThreads(vec![thread("OLD", true, "bot", &claude, 30)]), &deleter, None);
+        assert_eq!(client.fetch_threads("o", "r", 1).unwrap().len(), 1);
+        assert!(deleter.0.lock().unwrap().is_empty());
+    }
+}
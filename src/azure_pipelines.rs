@@ -0,0 +1,289 @@
+// Azure Pipelines API integration. Parallel to circleci.rs and gh_actions.rs:
+// for a failing check whose details link is an Azure DevOps build
+// (dev.azure.com/ORG/PROJECT/_build/results?buildId=N), reads the build's
+// timeline to find the failed tasks and fetches their logs, authenticating
+// with a personal access token from AZURE_DEVOPS_TOKEN. Logs stream through
+// the same head/tail window and download budget as CircleCI's and come back
+// as `FailedStepLog`s, so the rest of the analysis treats them alike.
+
+use crate::circleci::{CircleCiFailureInfo, FailedStepLog};
+use crate::gh_actions::split_timestamp;
+use crate::log_window::{LogBudget, LogWindow, HEAD_BYTES, TAIL_BYTES};
+use crate::retry::{self, RetryBudget};
+use anyhow::{Context, Result};
+use serde::Deserialize;
+
+/// A failing Azure Pipelines build we know how to look up by URL.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AzureBuildInfo {
+    pub organization: String,
+    /// As it appears in the URL (percent-encoded), ready for API paths.
+    pub project: String,
+    pub build_id: u64,
+}
+
+/// One stage, phase, job, or task of a build, from its timeline.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TimelineRecord {
+    pub id: String,
+    pub parent_id: Option<String>,
+    /// "Stage", "Phase", "Job", "Task", ...
+    #[serde(rename = "type")]
+    pub record_type: String,
+    pub name: String,
+    /// "succeeded", "failed", "canceled", ...; None while still running.
+    pub result: Option<String>,
+    pub log: Option<LogReference>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct LogReference {
+    pub id: u64,
+}
+
+/// Trait for Azure DevOps API operations, allowing test implementations.
+pub trait AzurePipelinesClient {
+    fn fetch_timeline(&self, build: &AzureBuildInfo) -> Result<Vec<TimelineRecord>>;
+    /// Fetch one log of the build, with timestamps stripped.
+    fn fetch_log(&self, build: &AzureBuildInfo, log_id: u64) -> Result<String>;
+}
+
+/// Real Azure DevOps client using reqwest.
+pub struct RealAzurePipelinesClient {
+    token: String,
+    log_budget: LogBudget,
+    retry_budget: RetryBudget,
+}
+
+impl RealAzurePipelinesClient {
+    /// Share the log download and retry budgets of the rest of the analysis.
+    pub fn new(token: String, log_budget: LogBudget, retry_budget: RetryBudget) -> Self {
+        Self {
+            token,
+            log_budget,
+            retry_budget,
+        }
+    }
+
+    fn api_url(build: &AzureBuildInfo, path: &str) -> String {
+        format!(
+            "https://dev.azure.com/{}/{}/_apis/build/builds/{}/{}?api-version=7.1",
+            build.organization, build.project, build.build_id, path
+        )
+    }
+
+    /// Azure DevOps answers a rejected token with a 203 sign-in page rather
+    /// than an error status.
+    fn check_status(response: &reqwest::blocking::Response, what: &str) -> Result<()> {
+        let status = response.status();
+        if status == 401 || status == 203 {
+            anyhow::bail!("Azure DevOps rejected AZURE_DEVOPS_TOKEN (it needs the Build: Read scope)");
+        }
+        if !status.is_success() {
+            anyhow::bail!("Azure DevOps {} API error: {}", what, status);
+        }
+        Ok(())
+    }
+}
+
+#[derive(Deserialize)]
+struct TimelineResponse {
+    records: Vec<TimelineRecord>,
+}
+
+impl AzurePipelinesClient for RealAzurePipelinesClient {
+    fn fetch_timeline(&self, build: &AzureBuildInfo) -> Result<Vec<TimelineRecord>> {
+        let client = reqwest::blocking::Client::new();
+        let url = Self::api_url(build, "timeline");
+        let response = retry::http(|| client.get(&url).basic_auth("", Some(&self.token)).send())
+            .context("Failed to send request to Azure DevOps timeline API")?;
+        Self::check_status(&response, "timeline")?;
+        let timeline: TimelineResponse = response
+            .json()
+            .context("Failed to parse Azure DevOps build timeline")?;
+        Ok(timeline.records)
+    }
+
+    fn fetch_log(&self, build: &AzureBuildInfo, log_id: u64) -> Result<String> {
+        let client = reqwest::blocking::Client::new();
+        let url = Self::api_url(build, &format!("logs/{}", log_id));
+        let response = retry::http_within(&self.retry_budget, || {
+            client.get(&url).basic_auth("", Some(&self.token)).send()
+        })
+        .context("Failed to send request to Azure DevOps log API")?;
+        Self::check_status(&response, "log")?;
+        let mut window = LogWindow::new(HEAD_BYTES, TAIL_BYTES);
+        window.read_from(response, &self.log_budget);
+        Ok(strip_timestamps(&window.finish()))
+    }
+}
+
+/// Check if a URL is an Azure Pipelines build URL.
+pub fn is_azure_pipelines_url(url: &str) -> bool {
+    url.contains("dev.azure.com/") && url.contains("/_build/results")
+}
+
+/// Parse an Azure Pipelines build URL.
+/// Example: https://dev.azure.com/org/project/_build/results?buildId=123&view=logs
+pub fn parse_azure_pipelines_url(url: &str) -> Option<AzureBuildInfo> {
+    let (path, query) = url.split_once('?')?;
+    let after_host = &path[path.find("dev.azure.com/")? + "dev.azure.com/".len()..];
+    let parts: Vec<&str> = after_host.split('/').collect();
+    // Expected: organization, project, "_build", "results"
+    if parts.len() < 4 || parts[2] != "_build" || parts[3] != "results" {
+        return None;
+    }
+    let build_id = query
+        .split('&')
+        .find_map(|pair| pair.strip_prefix("buildId="))?
+        .parse()
+        .ok()?;
+    Some(AzureBuildInfo {
+        organization: parts[0].to_string(),
+        project: parts[1].to_string(),
+        build_id,
+    })
+}
+
+/// Drop the `<RFC-3339 timestamp> ` prefix Azure puts on every log line.
+fn strip_timestamps(log: &str) -> String {
+    let mut out = String::new();
+    for line in log.lines() {
+        out.push_str(split_timestamp(line).1);
+        out.push('\n');
+    }
+    out
+}
+
+/// The job a timeline record belongs to, found by walking up its parents.
+fn job_name<'a>(records: &'a [TimelineRecord], record: &'a TimelineRecord) -> &'a str {
+    let mut current = record;
+    // Bounded, in case a malformed timeline has a cycle
+    for _ in 0..records.len() {
+        let Some(parent) = current
+            .parent_id
+            .as_deref()
+            .and_then(|id| records.iter().find(|r| r.id == id))
+        else {
+            break;
+        };
+        if parent.record_type == "Job" {
+            return &parent.name;
+        }
+        current = parent;
+    }
+    &record.name
+}
+
+/// Fetch the logs of a build's failed tasks, returned as `FailedStepLog`
+/// entries so they can be merged with CircleCI-style output. Like GH Actions,
+/// a task has one log with stdout and stderr interleaved, so `error` is left
+/// empty. A task whose log can't be fetched is listed in `fetch_errors`
+/// instead of failing the others.
+pub fn get_failed_task_logs(
+    client: &dyn AzurePipelinesClient,
+    build: &AzureBuildInfo,
+) -> Result<CircleCiFailureInfo> {
+    let records = client.fetch_timeline(build)?;
+    let mut info = CircleCiFailureInfo::default();
+    for task in records
+        .iter()
+        .filter(|r| r.record_type == "Task" && r.result.as_deref() == Some("failed"))
+    {
+        let job_name = job_name(&records, task).to_string();
+        let Some(log) = &task.log else {
+            info.fetch_errors
+                .push(format!("{} / {}: the task has no log", job_name, task.name));
+            continue;
+        };
+        match client.fetch_log(build, log.id) {
+            Ok(output) => info.step_logs.push(FailedStepLog {
+                job_name,
+                step_name: task.name.clone(),
+                output,
+                error: String::new(),
+                container: None,
+                full_log_url: None,
+            }),
+            Err(e) => info
+                .fetch_errors
+                .push(format!("{} / {}: {:#}", job_name, task.name, e)),
+        }
+    }
+    Ok(info)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn parses_azure_pipelines_url() {
+        let url = "https://dev.azure.com/acme/My%20Project/_build/results?view=logs&buildId=4242&j=abc";
+        assert!(is_azure_pipelines_url(url));
+        assert_eq!(
+            parse_azure_pipelines_url(url),
+            Some(AzureBuildInfo {
+                organization: "acme".to_string(),
+                project: "My%20Project".to_string(),
+                build_id: 4242,
+            })
+        );
+        assert!(!is_azure_pipelines_url("https://github.com/owner/repo/actions/runs/1/job/2"));
+        assert_eq!(parse_azure_pipelines_url("https://dev.azure.com/acme/proj/_build/results"), None);
+    }
+
+    struct TestAzureClient {
+        records: Vec<TimelineRecord>,
+        logs: HashMap<u64, String>,
+    }
+
+    impl AzurePipelinesClient for TestAzureClient {
+        fn fetch_timeline(&self, _: &AzureBuildInfo) -> Result<Vec<TimelineRecord>> {
+            Ok(self.records.clone())
+        }
+        fn fetch_log(&self, _: &AzureBuildInfo, log_id: u64) -> Result<String> {
+            self.logs
+                .get(&log_id)
+                .cloned()
+                .ok_or_else(|| anyhow::anyhow!("Azure DevOps log API error: 500"))
+        }
+    }
+
+    fn record(id: &str, parent: Option<&str>, kind: &str, name: &str, result: &str, log: Option<u64>) -> TimelineRecord {
+        TimelineRecord {
+            id: id.to_string(),
+            parent_id: parent.map(str::to_string),
+            record_type: kind.to_string(),
+            name: name.to_string(),
+            result: Some(result.to_string()),
+            log: log.map(|id| LogReference { id }),
+        }
+    }
+
+    #[test]
+    fn fetches_failed_task_logs_under_their_job() {
+        let client = TestAzureClient {
+            records: vec![
+                record("s", None, "Stage", "Build", "failed", None),
+                record("j", Some("s"), "Job", "Linux", "failed", Some(1)),
+                record("t1", Some("j"), "Task", "Restore", "succeeded", Some(2)),
+                record("t2", Some("j"), "Task", "Test", "failed", Some(3)),
+                record("t3", Some("j"), "Task", "Publish results", "failed", Some(4)),
+            ],
+            logs: HashMap::from([(3, "FAILED: test_login\n".to_string())]),
+        };
+        let build = parse_azure_pipelines_url("https://dev.azure.com/o/p/_build/results?buildId=1").unwrap();
+        let info = get_failed_task_logs(&client, &build).unwrap();
+        assert_eq!(info.step_logs.len(), 1);
+        assert_eq!(info.step_logs[0].job_name, "Linux");
+        assert_eq!(info.step_logs[0].step_name, "Test");
+        assert_eq!(info.step_logs[0].output, "FAILED: test_login\n");
+        assert_eq!(
+            info.fetch_errors,
+            vec!["Linux / Publish results: Azure DevOps log API error: 500".to_string()]
+        );
+    }
+}
@@ -0,0 +1,184 @@
+// Failures the PR didn't cause.
+// When a check fails on the PR, its latest finished run on the base branch
+// says whether the base branch is broken too. The analysis points such
+// failures out ("this failure exists on main; not caused by this PR") so the
+// agent doesn't try to fix someone else's breakage, and with
+// `--ignore-base-failures` they stop counting as actionable at all.
+//
+// Like snoozes, ignoring wraps the checks client (`IgnoringBaseFailures<C>`),
+// so waits and the analysis agree on what's failing.
+
+use crate::checks::{BaseChecks, Check, CheckStatus, ChecksClient, CommitChecks};
+use crate::github::MergeableStatus;
+use anyhow::Result;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::sync::Mutex;
+
+/// A failing check whose latest run on the base branch failed too.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct BaseFailure {
+    pub check: String,
+    /// e.g. "main"
+    pub branch: String,
+    /// The failed run on the base branch.
+    pub base_url: Option<String>,
+}
+
+impl BaseFailure {
+    pub fn describe(&self) -> String {
+        format!(
+            "{} is also failing on {}{}: this failure exists on {}; not caused by this PR",
+            self.check,
+            self.branch,
+            self.base_url.as_deref().map(|u| format!(" ({})", u)).unwrap_or_default(),
+            self.branch
+        )
+    }
+}
+
+/// The failing `checks` whose latest finished run on the base branch failed.
+pub fn failing_on_base(checks: &[Check], base: &BaseChecks) -> Vec<BaseFailure> {
+    checks
+        .iter()
+        .filter(|c| c.status == CheckStatus::Fail)
+        .filter_map(|c| {
+            let on_base = base
+                .checks
+                .iter()
+                .find(|b| b.name == c.name && b.status == CheckStatus::Fail)?;
+            Some(BaseFailure {
+                check: c.name.clone(),
+                branch: base.branch.clone(),
+                base_url: on_base.url.clone(),
+            })
+        })
+        .collect()
+}
+
+/// A client whose results leave out failures that also happen on the base
+/// branch.
+pub struct IgnoringBaseFailures<C> {
+    inner: C,
+    /// Checks already reported as ignored, so polling doesn't repeat them.
+    announced: Mutex<HashSet<String>>,
+}
+
+impl<C> IgnoringBaseFailures<C> {
+    pub fn new(inner: C) -> Self {
+        Self {
+            inner,
+            announced: Mutex::new(HashSet::new()),
+        }
+    }
+}
+
+impl<C: ChecksClient> ChecksClient for IgnoringBaseFailures<C> {
+    fn fetch_checks(&self, owner: &str, repo: &str, pr_number: u64) -> Result<Vec<Check>> {
+        let checks = self.inner.fetch_checks(owner, repo, pr_number)?;
+        if !checks.iter().any(|c| c.status == CheckStatus::Fail) {
+            return Ok(checks);
+        }
+        let base = match self.inner.fetch_base_checks(owner, repo, pr_number) {
+            Ok(base) => base,
+            Err(e) => {
+                eprintln!("Warning: Failed to fetch base branch checks: {}", e);
+                return Ok(checks);
+            }
+        };
+        let ignored = failing_on_base(&checks, &base);
+        let mut announced = self.announced.lock().expect("announced lock poisoned");
+        for failure in &ignored {
+            if announced.insert(failure.check.clone()) {
+                eprintln!("Ignoring {} (--ignore-base-failures).", failure.describe());
+            }
+        }
+        Ok(checks
+            .into_iter()
+            .filter(|c| !ignored.iter().any(|f| f.check == c.name))
+            .collect())
+    }
+
+    fn fetch_check_history(
+        &self,
+        owner: &str,
+        repo: &str,
+        pr_number: u64,
+        count: u64,
+    ) -> Result<Vec<CommitChecks>> {
+        self.inner.fetch_check_history(owner, repo, pr_number, count)
+    }
+
+    fn fetch_base_checks(&self, owner: &str, repo: &str, pr_number: u64) -> Result<BaseChecks> {
+        self.inner.fetch_base_checks(owner, repo, pr_number)
+    }
+
+    fn fetch_required_checks(&self, owner: &str, repo: &str, pr_number: u64) -> Result<Vec<String>> {
+        self.inner.fetch_required_checks(owner, repo, pr_number)
+    }
+
+    fn fetch_mergeable_status(&self, owner: &str, repo: &str, pr_number: u64) -> Result<MergeableStatus> {
+        self.inner.fetch_mergeable_status(owner, repo, pr_number)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::checks::CheckSource;
+
+    fn check(name: &str, status: CheckStatus) -> Check {
+        Check {
+            name: name.to_string(),
+            status,
+            url: Some(format!("https://ci/{}", name)),
+            source: CheckSource::CheckRun,
+            started_at: None,
+            attempts: 1,
+            app: None,
+        }
+    }
+
+    struct BrokenMain;
+
+    impl ChecksClient for BrokenMain {
+        fn fetch_checks(&self, _: &str, _: &str, _: u64) -> Result<Vec<Check>> {
+            Ok(vec![
+                check("build", CheckStatus::Fail),
+                check("test", CheckStatus::Fail),
+                check("lint", CheckStatus::Pass),
+            ])
+        }
+
+        fn fetch_base_checks(&self, _: &str, _: &str, _: u64) -> Result<BaseChecks> {
+            Ok(BaseChecks {
+                branch: "main".to_string(),
+                checks: vec![check("build", CheckStatus::Pass), check("test", CheckStatus::Fail)],
+            })
+        }
+    }
+
+    #[test]
+    fn finds_failures_shared_with_base() {
+        let checks = BrokenMain.fetch_checks("o", "r", 1).unwrap();
+        let base = BrokenMain.fetch_base_checks("o", "r", 1).unwrap();
+        let shared = failing_on_base(&checks, &base);
+        assert_eq!(shared.len(), 1);
+        assert_eq!(
+            shared[0].describe(),
+            "test is also failing on main (https://ci/test): this failure exists on main; not caused by this PR"
+        );
+    }
+
+    #[test]
+    fn ignoring_drops_only_shared_failures() {
+        let client = IgnoringBaseFailures::new(BrokenMain);
+        let names: Vec<_> = client
+            .fetch_checks("o", "r", 1)
+            .unwrap()
+            .into_iter()
+            .map(|c| c.name)
+            .collect();
+        assert_eq!(names, vec!["build", "lint"]);
+    }
+}
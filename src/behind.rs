@@ -0,0 +1,142 @@
+// How far the PR branch has fallen behind its base.
+// A branch that's far behind is tested against an old base: CI can pass on
+// it and the merge still break, and the further behind, the bigger the
+// surprise. GitHub's compare API (head...base) gives how many commits the
+// base has gained since the branch point and which files they touched. When
+// those include CI configuration, the checks the PR is held to have changed
+// since its CI ran, so a green run says less about the merged result either
+// way. Either reason makes rebasing part of the recommendation.
+
+use crate::request_log::TracedOutput;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::process::Command;
+
+/// Commits behind the base after which a rebase is recommended, unless
+/// `[rebase] behind_threshold` says otherwise.
+pub const DEFAULT_BEHIND_THRESHOLD: u64 = 50;
+
+/// Where changes to which checks run (and how) live.
+const CI_CONFIG_PREFIXES: &[&str] = &[".github/workflows/", ".circleci/"];
+
+/// A recommendation to rebase onto the base branch.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Rebase {
+    /// The base branch, e.g. "main".
+    pub base: String,
+    /// Commits on the base since the PR branched off.
+    pub behind_by: u64,
+    /// CI configuration files changed on the base since then.
+    pub ci_changes: Vec<String>,
+}
+
+impl Rebase {
+    /// Whether a branch `behind_by` commits behind should be rebased, given
+    /// the files changed on the base since it branched off.
+    pub fn recommended(base: &str, behind_by: u64, changed_files: &[String], threshold: u64) -> Option<Self> {
+        let ci_changes: Vec<String> = changed_files
+            .iter()
+            .filter(|f| CI_CONFIG_PREFIXES.iter().any(|p| f.starts_with(p)))
+            .cloned()
+            .collect();
+        (behind_by > threshold || !ci_changes.is_empty()).then(|| Self {
+            base: base.to_string(),
+            behind_by,
+            ci_changes,
+        })
+    }
+
+    /// e.g. "72 commits behind main, including changes to .circleci/config.yml"
+    pub fn describe(&self) -> String {
+        let mut out = format!(
+            "{} commit{} behind {}",
+            self.behind_by,
+            if self.behind_by == 1 { "" } else { "s" },
+            self.base
+        );
+        if !self.ci_changes.is_empty() {
+            out.push_str(&format!(", including changes to {}", self.ci_changes.join(", ")));
+        }
+        out
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GhPrRefs {
+    base_ref_name: String,
+    head_ref_oid: String,
+}
+
+#[derive(Deserialize)]
+struct Comparison {
+    ahead_by: u64,
+    #[serde(default)]
+    files: Vec<ComparisonFile>,
+}
+
+#[derive(Deserialize)]
+struct ComparisonFile {
+    filename: String,
+}
+
+fn run_gh(args: &[&str], what: &str) -> Result<Vec<u8>> {
+    let output = Command::new("gh")
+        .args(args)
+        .traced_output()
+        .context(format!("Failed to run gh to {}", what))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        anyhow::bail!("Failed to {}: {}", what, stderr.trim());
+    }
+    Ok(output.stdout)
+}
+
+/// Compare the PR's head with its base, recommending a rebase if the branch
+/// is more than `threshold` commits behind or the base changed CI config.
+pub fn check(owner: &str, repo: &str, pr_number: u64, threshold: u64) -> Result<Option<Rebase>> {
+    let stdout = run_gh(
+        &[
+            "pr",
+            "view",
+            &pr_number.to_string(),
+            "--repo",
+            &format!("{}/{}", owner, repo),
+            "--json",
+            "baseRefName,headRefOid",
+        ],
+        "view the PR's branches",
+    )?;
+    let refs: GhPrRefs = serde_json::from_slice(&stdout).context("Failed to parse gh pr view output")?;
+
+    // head...base: what the base has that the head doesn't
+    let path = format!(
+        "repos/{}/{}/compare/{}...{}",
+        owner,
+        repo,
+        refs.head_ref_oid,
+        urlencoding::encode(&refs.base_ref_name)
+    );
+    let stdout = run_gh(&["api", &path], "compare the PR with its base")?;
+    let comparison: Comparison = serde_json::from_slice(&stdout).context("Failed to parse comparison")?;
+    let files: Vec<String> = comparison.files.into_iter().map(|f| f.filename).collect();
+    Ok(Rebase::recommended(&refs.base_ref_name, comparison.ahead_by, &files, threshold))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recommends_rebase_when_far_behind_or_ci_changed() {
+        let files = vec!["src/lib.rs".to_string()];
+        assert_eq!(Rebase::recommended("main", 50, &files, 50), None);
+
+        let far = Rebase::recommended("main", 51, &files, 50).unwrap();
+        assert_eq!(far.describe(), "51 commits behind main");
+
+        let ci = vec!["src/lib.rs".to_string(), ".circleci/config.yml".to_string()];
+        let changed = Rebase::recommended("main", 1, &ci, 50).unwrap();
+        assert_eq!(changed.describe(), "1 commit behind main, including changes to .circleci/config.yml");
+    }
+}
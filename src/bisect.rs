@@ -0,0 +1,180 @@
+// CI bisect helper for `pr-loop ci-bisect`.
+// Walks the check results on a PR's recent commits to find where a check
+// went from passing to failing, narrowing down the breaking change.
+
+use crate::checks::{CheckStatus, CommitChecks};
+use anyhow::{Context, Result};
+use glob::Pattern;
+
+/// The named check's result on one commit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CheckOutcome {
+    Passed,
+    Failed,
+    Pending,
+    /// No matching check reported (or it was skipped/cancelled).
+    NotRun,
+}
+
+/// Where the check broke, as indices into the commit history (oldest first).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BisectResult {
+    /// The check has no pass/fail result on any fetched commit.
+    NeverRan,
+    /// The most recent result is a pass.
+    Passing { latest: usize },
+    /// The check is failing. `last_green` is None if it failed on every
+    /// fetched commit that ran it.
+    Broken {
+        last_green: Option<usize>,
+        first_red: usize,
+    },
+}
+
+impl BisectResult {
+    /// Commits that may have introduced the failure: everything after the
+    /// last green commit up to and including the first red one.
+    pub fn suspects(&self) -> std::ops::Range<usize> {
+        match self {
+            BisectResult::Broken {
+                last_green,
+                first_red,
+            } => last_green.map_or(0, |g| g + 1)..first_red + 1,
+            _ => 0..0,
+        }
+    }
+}
+
+/// Result of checks matching `pattern` on a commit. Several matches (e.g.
+/// matrix variants) combine: any failure fails, then any pending is pending.
+pub fn outcome_on(commit: &CommitChecks, pattern: &Pattern) -> CheckOutcome {
+    let statuses: Vec<&CheckStatus> = commit
+        .checks
+        .iter()
+        .filter(|c| pattern.matches(&c.name))
+        .map(|c| &c.status)
+        .collect();
+    if statuses.contains(&&CheckStatus::Fail) {
+        CheckOutcome::Failed
+    } else if statuses.contains(&&CheckStatus::Pending) {
+        CheckOutcome::Pending
+    } else if statuses.contains(&&CheckStatus::Pass) {
+        CheckOutcome::Passed
+    } else {
+        CheckOutcome::NotRun
+    }
+}
+
+/// Find where the check named by `check` (a name or glob) started failing.
+pub fn bisect(history: &[CommitChecks], check: &str) -> Result<BisectResult> {
+    let pattern = Pattern::new(check).context(format!("Invalid check pattern: {}", check))?;
+    let outcomes: Vec<CheckOutcome> = history.iter().map(|c| outcome_on(c, &pattern)).collect();
+
+    let decided = |o: &CheckOutcome| matches!(o, CheckOutcome::Passed | CheckOutcome::Failed);
+    let Some(latest) = outcomes.iter().rposition(decided) else {
+        return Ok(BisectResult::NeverRan);
+    };
+    if outcomes[latest] == CheckOutcome::Passed {
+        return Ok(BisectResult::Passing { latest });
+    }
+
+    let mut first_red = latest;
+    for i in (0..latest).rev() {
+        match outcomes[i] {
+            CheckOutcome::Passed => {
+                return Ok(BisectResult::Broken {
+                    last_green: Some(i),
+                    first_red,
+                });
+            }
+            CheckOutcome::Failed => first_red = i,
+            CheckOutcome::Pending | CheckOutcome::NotRun => {}
+        }
+    }
+    Ok(BisectResult::Broken {
+        last_green: None,
+        first_red,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::checks::{Check, CheckSource};
+
+    fn commit(sha: &str, checks: &[(&str, CheckStatus)]) -> CommitChecks {
+        CommitChecks {
+            sha: sha.to_string(),
+            abbreviated_sha: sha.to_string(),
+            headline: format!("commit {}", sha),
+            checks: checks
+                .iter()
+                .map(|(name, status)| Check {
+                    name: name.to_string(),
+                    status: status.clone(),
+                    url: None,
+                    source: CheckSource::CheckRun,
+                    started_at: None,
+                    attempts: 1,
+                    app: None,
+                })
+                .collect(),
+        }
+    }
+
+    use CheckStatus::{Fail, Pass, Pending};
+
+    #[test]
+    fn finds_transition_skipping_commits_without_results() {
+        let history = vec![
+            commit("a", &[("ci/test", Pass)]),
+            commit("b", &[("ci/test", Pass), ("lint", Fail)]),
+            commit("c", &[("lint", Pass)]),
+            commit("d", &[("ci/test", Fail)]),
+            commit("e", &[("ci/test", Fail)]),
+            commit("f", &[("ci/test", Pending)]),
+        ];
+        let result = bisect(&history, "ci/test").unwrap();
+        assert_eq!(
+            result,
+            BisectResult::Broken {
+                last_green: Some(1),
+                first_red: 3
+            }
+        );
+        // c never ran the check, so it's a suspect too
+        assert_eq!(result.suspects(), 2..4);
+    }
+
+    #[test]
+    fn failing_throughout_has_no_green() {
+        let history = vec![
+            commit("a", &[]),
+            commit("b", &[("test (linux)", Pass), ("test (mac)", Fail)]),
+            commit("c", &[("test (linux)", Fail)]),
+        ];
+        let result = bisect(&history, "test *").unwrap();
+        assert_eq!(
+            result,
+            BisectResult::Broken {
+                last_green: None,
+                first_red: 1
+            }
+        );
+        assert_eq!(result.suspects(), 0..2);
+    }
+
+    #[test]
+    fn passing_or_never_ran() {
+        let history = vec![
+            commit("a", &[("ci/test", Fail)]),
+            commit("b", &[("ci/test", Pass)]),
+        ];
+        assert_eq!(
+            bisect(&history, "ci/test").unwrap(),
+            BisectResult::Passing { latest: 1 }
+        );
+        assert_eq!(bisect(&history, "other").unwrap(), BisectResult::NeverRan);
+        assert!(bisect(&history, "[").is_err());
+    }
+}
@@ -0,0 +1,364 @@
+// History of the PR description edits pr-loop makes.
+// The description is the one place pr-loop rewrites text a human wrote: a
+// status block update or its removal in `ready` that goes wrong could
+// clobber the rest of it. Every edit saves the description it replaced to
+// `.pr-loop/bodies/<owner>-<repo>-<pr>/<timestamp>.md` (the newest few are
+// kept), ready to restore with `gh pr edit --body-file`, and with --verbose
+// prints a unified diff of the change.
+//
+// Edits are read-modify-write, and a human may edit the description in
+// between. Before writing, the description is fetched again; if it no longer
+// matches what was read, the edit is refused rather than overwriting theirs,
+// and the next iteration starts over from their version.
+//
+// Like the audit log this wraps the PR client (`Tracked<C>`), so every
+// caller of `set_body` is covered.
+
+use crate::audit::STATE_DIR;
+use crate::pr::{MergeMethod, PrClient, PrState};
+use anyhow::{bail, Context, Result};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+const BODIES_DIR: &str = "bodies";
+/// Previous descriptions kept per PR.
+const MAX_KEPT: usize = 10;
+/// Unchanged lines shown around each change in a diff.
+const CONTEXT: usize = 3;
+
+/// Where replaced descriptions are saved, and whether to print diffs.
+#[derive(Debug, Clone)]
+pub struct BodyHistory {
+    dir: PathBuf,
+    verbose: bool,
+}
+
+impl BodyHistory {
+    /// Save under `<root>/.pr-loop/bodies/`, in a directory for `pr_label`.
+    pub fn new(root: &Path, pr_label: &str, verbose: bool) -> Self {
+        let pr_dir = pr_label.replace(['/', '#'], "-");
+        Self {
+            dir: root.join(STATE_DIR).join(BODIES_DIR).join(pr_dir),
+            verbose,
+        }
+    }
+
+    /// Save the description an edit replaced and, with --verbose, show the
+    /// change. Failures are warnings: the edit already happened.
+    pub fn record(&self, before: &str, after: &str) {
+        let saved = match save(&self.dir, before) {
+            Ok(path) => Some(path),
+            Err(e) => {
+                eprintln!("Warning: Failed to save the previous PR description: {:#}", e);
+                None
+            }
+        };
+        if self.verbose {
+            match saved {
+                Some(path) => eprintln!("PR description edited (previous saved to {}):", path.display()),
+                None => eprintln!("PR description edited:"),
+            }
+            eprint!("{}", unified_diff(before, after));
+        }
+    }
+}
+
+/// Write `body` to a new timestamped file in `dir`, dropping the oldest
+/// beyond `MAX_KEPT`. Returns the new file's path.
+fn save(dir: &Path, body: &str) -> Result<PathBuf> {
+    std::fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+    if let Some(state_dir) = dir.ancestors().find(|d| d.ends_with(STATE_DIR)) {
+        let gitignore = state_dir.join(".gitignore");
+        if !gitignore.exists() {
+            std::fs::write(&gitignore, "*\n")?;
+        }
+    }
+    let stamp = jiff::Timestamp::now().strftime("%Y%m%dT%H%M%S%.3fZ");
+    let path = dir.join(format!("{}.md", stamp));
+    std::fs::write(&path, body).with_context(|| format!("Failed to write {}", path.display()))?;
+
+    let mut saved: Vec<PathBuf> = std::fs::read_dir(dir)?
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .filter(|p| p.extension().is_some_and(|ext| ext == "md"))
+        .collect();
+    saved.sort();
+    for old in saved.iter().rev().skip(MAX_KEPT) {
+        let _ = std::fs::remove_file(old);
+    }
+    Ok(path)
+}
+
+/// A PR client that records each description edit in a `BodyHistory`, and
+/// refuses edits based on a description that has changed since.
+pub struct Tracked<C> {
+    inner: C,
+    history: BodyHistory,
+    /// The description last read, by PR: what an edit following it (as they
+    /// all do) was based on.
+    last_read: Mutex<Option<(String, String)>>,
+}
+
+impl<C> Tracked<C> {
+    pub fn new(inner: C, history: BodyHistory) -> Self {
+        Self {
+            inner,
+            history,
+            last_read: Mutex::new(None),
+        }
+    }
+}
+
+fn pr_key(owner: &str, repo: &str, pr_number: u64) -> String {
+    format!("{}/{}#{}", owner, repo, pr_number)
+}
+
+impl<C: PrClient> PrClient for Tracked<C> {
+    fn is_draft(&self, owner: &str, repo: &str, pr_number: u64) -> Result<bool> {
+        self.inner.is_draft(owner, repo, pr_number)
+    }
+
+    fn get_body(&self, owner: &str, repo: &str, pr_number: u64) -> Result<String> {
+        let body = self.inner.get_body(owner, repo, pr_number)?;
+        *self.last_read.lock().expect("last_read lock poisoned") =
+            Some((pr_key(owner, repo, pr_number), body.clone()));
+        Ok(body)
+    }
+
+    fn set_body(&self, owner: &str, repo: &str, pr_number: u64, body: &str) -> Result<()> {
+        let key = pr_key(owner, repo, pr_number);
+        let last_read = self.last_read.lock().expect("last_read lock poisoned").take();
+        let before = self.inner.get_body(owner, repo, pr_number)?;
+        if let Some((read_key, read)) = last_read
+            && read_key == key
+        {
+            check_unchanged(&read, &before)?;
+        }
+        self.inner.set_body(owner, repo, pr_number, body)?;
+        if before != body {
+            self.history.record(&before, body);
+        }
+        Ok(())
+    }
+
+    fn mark_ready(&self, owner: &str, repo: &str, pr_number: u64) -> Result<()> {
+        self.inner.mark_ready(owner, repo, pr_number)
+    }
+
+    fn get_commit_count(&self, owner: &str, repo: &str, pr_number: u64) -> Result<usize> {
+        self.inner.get_commit_count(owner, repo, pr_number)
+    }
+
+    fn add_reviewer(&self, owner: &str, repo: &str, pr_number: u64, reviewer: &str) -> Result<()> {
+        self.inner.add_reviewer(owner, repo, pr_number, reviewer)
+    }
+
+    fn add_comment(&self, owner: &str, repo: &str, pr_number: u64, body: &str) -> Result<()> {
+        self.inner.add_comment(owner, repo, pr_number, body)
+    }
+
+    fn merge(&self, owner: &str, repo: &str, pr_number: u64, method: MergeMethod, auto: bool) -> Result<()> {
+        self.inner.merge(owner, repo, pr_number, method, auto)
+    }
+
+    fn state(&self, owner: &str, repo: &str, pr_number: u64) -> Result<PrState> {
+        self.inner.state(owner, repo, pr_number)
+    }
+}
+
+/// Fail, showing what changed, if the description is no longer what was
+/// read: writing would undo someone else's edit.
+fn check_unchanged(read: &str, current: &str) -> Result<()> {
+    if read == current {
+        return Ok(());
+    }
+    bail!(
+        "The PR description was edited after pr-loop read it; not overwriting that edit. \
+         Re-run to apply the change on top of it. The edit:\n{}",
+        unified_diff(read, current).trim_end()
+    )
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Edit<'a> {
+    Same(&'a str),
+    Removed(&'a str),
+    Added(&'a str),
+}
+
+/// The line edits turning `old` into `new`, from their longest common
+/// subsequence. Quadratic, which is fine at description sizes.
+fn edits<'a>(old: &[&'a str], new: &[&'a str]) -> Vec<Edit<'a>> {
+    let (n, m) = (old.len(), new.len());
+    // lcs[i][j]: length of the LCS of old[i..] and new[j..]
+    let mut lcs = vec![vec![0usize; m + 1]; n + 1];
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            lcs[i][j] = if old[i] == new[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+    let (mut i, mut j) = (0, 0);
+    let mut result = Vec::with_capacity(n.max(m));
+    while i < n || j < m {
+        if i < n && j < m && old[i] == new[j] {
+            result.push(Edit::Same(old[i]));
+            i += 1;
+            j += 1;
+        } else if i < n && (j == m || lcs[i + 1][j] >= lcs[i][j + 1]) {
+            result.push(Edit::Removed(old[i]));
+            i += 1;
+        } else {
+            result.push(Edit::Added(new[j]));
+            j += 1;
+        }
+    }
+    result
+}
+
+/// A unified diff of `old` to `new`, line by line; empty if they're equal.
+pub fn unified_diff(old: &str, new: &str) -> String {
+    let old_lines: Vec<&str> = old.lines().collect();
+    let new_lines: Vec<&str> = new.lines().collect();
+    let edits = edits(&old_lines, &new_lines);
+
+    // Ranges of edits to show: each change with its context, overlapping ones merged
+    let mut hunks: Vec<(usize, usize)> = vec![];
+    for (index, _) in edits.iter().enumerate().filter(|(_, e)| !matches!(e, Edit::Same(_))) {
+        let start = index.saturating_sub(CONTEXT);
+        let end = (index + CONTEXT + 1).min(edits.len());
+        match hunks.last_mut() {
+            Some(last) if start <= last.1 => last.1 = end,
+            _ => hunks.push((start, end)),
+        }
+    }
+    if hunks.is_empty() {
+        return String::new();
+    }
+
+    let mut diff = String::from("--- before\n+++ after\n");
+    for (start, end) in hunks {
+        let old_before = edits[..start].iter().filter(|e| !matches!(e, Edit::Added(_))).count();
+        let new_before = edits[..start].iter().filter(|e| !matches!(e, Edit::Removed(_))).count();
+        let hunk = &edits[start..end];
+        let old_count = hunk.iter().filter(|e| !matches!(e, Edit::Added(_))).count();
+        let new_count = hunk.iter().filter(|e| !matches!(e, Edit::Removed(_))).count();
+        // Ranges are 1-based, except an empty one names the line before it
+        let first = |before: usize, count: usize| if count == 0 { before } else { before + 1 };
+        diff.push_str(&format!(
+            "@@ -{},{} +{},{} @@\n",
+            first(old_before, old_count),
+            old_count,
+            first(new_before, new_count),
+            new_count
+        ));
+        for edit in hunk {
+            let (prefix, line) = match edit {
+                Edit::Same(l) => (' ', l),
+                Edit::Removed(l) => ('-', l),
+                Edit::Added(l) => ('+', l),
+            };
+            diff.push(prefix);
+            diff.push_str(line);
+            diff.push('\n');
+        }
+    }
+    diff
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn diff_shows_changes_with_context() {
+        assert_eq!(unified_diff("a\nb\n", "a\nb\n"), "");
+
+        let old = "Intro\n\nDetails\n1\n2\n3\n4\n5\n6\n7\nEnd\n";
+        let new = "<!-- status -->\nIntro\n\nDetails\n1\n2\n3\n4\n5\n6\n7\nThe end\n";
+        assert_eq!(
+            unified_diff(old, new),
+            "--- before\n+++ after\n\
+             @@ -1,3 +1,4 @@\n+<!-- status -->\n Intro\n \n Details\n\
+             @@ -8,4 +9,4 @@\n 5\n 6\n 7\n-End\n+The end\n"
+        );
+    }
+
+    /// A PR whose description is kept in memory.
+    struct FakePr(Mutex<String>);
+
+    impl PrClient for FakePr {
+        fn is_draft(&self, _: &str, _: &str, _: u64) -> Result<bool> {
+            Ok(true)
+        }
+        fn get_body(&self, _: &str, _: &str, _: u64) -> Result<String> {
+            Ok(self.0.lock().unwrap().clone())
+        }
+        fn set_body(&self, _: &str, _: &str, _: u64, body: &str) -> Result<()> {
+            *self.0.lock().unwrap() = body.to_string();
+            Ok(())
+        }
+        fn mark_ready(&self, _: &str, _: &str, _: u64) -> Result<()> {
+            Ok(())
+        }
+        fn get_commit_count(&self, _: &str, _: &str, _: u64) -> Result<usize> {
+            Ok(1)
+        }
+        fn add_reviewer(&self, _: &str, _: &str, _: u64, _: &str) -> Result<()> {
+            Ok(())
+        }
+        fn add_comment(&self, _: &str, _: &str, _: u64, _: &str) -> Result<()> {
+            Ok(())
+        }
+        fn merge(&self, _: &str, _: &str, _: u64, _: MergeMethod, _: bool) -> Result<()> {
+            Ok(())
+        }
+        fn state(&self, _: &str, _: &str, _: u64) -> Result<PrState> {
+            Ok(PrState::Open)
+        }
+    }
+
+    #[test]
+    fn refuses_to_overwrite_an_edit_made_since_the_read() {
+        let root = std::env::temp_dir().join(format!("pr-loop-body-race-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        let pr = Tracked::new(FakePr(Mutex::new("Intro\n".to_string())), BodyHistory::new(&root, "o/r#1", false));
+
+        let read = pr.get_body("o", "r", 1).unwrap();
+        pr.set_body("o", "r", 1, &format!("<!-- status -->\n{}", read)).unwrap();
+
+        pr.get_body("o", "r", 1).unwrap();
+        // A human edits the description in the meantime
+        pr.inner.set_body("o", "r", 1, "<!-- status -->\nBetter intro\n").unwrap();
+        let err = pr.set_body("o", "r", 1, "Intro\n").unwrap_err().to_string();
+        assert!(err.contains("-Intro\n+Better intro"), "{}", err);
+        assert_eq!(pr.get_body("o", "r", 1).unwrap(), "<!-- status -->\nBetter intro\n");
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn keeps_the_newest_previous_bodies() {
+        let root = std::env::temp_dir().join(format!("pr-loop-bodies-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        let history = BodyHistory::new(&root, "o/r#1", false);
+        let dir = history.dir.clone();
+        assert!(dir.ends_with(".pr-loop/bodies/o-r-1"));
+
+        for i in 0..MAX_KEPT + 2 {
+            history.record(&format!("body {}", i), "new");
+            // Saved files are named by the millisecond
+            std::thread::sleep(std::time::Duration::from_millis(2));
+        }
+        let mut kept: Vec<String> = std::fs::read_dir(&dir)
+            .unwrap()
+            .map(|e| std::fs::read_to_string(e.unwrap().path()).unwrap())
+            .collect();
+        kept.sort();
+        assert_eq!(kept.len(), MAX_KEPT);
+        assert!(!kept.contains(&"body 0".to_string()));
+        assert!(kept.contains(&format!("body {}", MAX_KEPT + 1)));
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,239 @@
+// Detect CI failures caused by corrupted dependency caches.
+// A truncated cargo registry tarball or a package whose checksum no longer
+// matches fails the build in ways that look like real errors, but no code
+// change fixes them. Only messages a damaged cache produces count — a
+// missing module is far more often a real missing import — and only in
+// GitHub Actions logs, whose caches `rerun --bust-caches` can clear. A match
+// suggests a cache-busting rerun before the agent goes after the failure.
+
+use crate::circleci::FailedStepLog;
+use crate::request_log::TracedOutput;
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::process::Command;
+
+/// A kind of cache corruption and the (lowercase) log fragments that give
+/// it away.
+struct Signature {
+    kind: &'static str,
+    needles: &'static [&'static str],
+}
+
+const SIGNATURES: &[Signature] = &[
+    Signature {
+        kind: "checksum mismatch",
+        needles: &[
+            "checksum mismatch",
+            "checksum did not match",
+            "eintegrity",
+            "integrity checksum failed",
+        ],
+    },
+    Signature {
+        kind: "cargo registry",
+        needles: &["failed to unpack package", "failed to verify the checksum of"],
+    },
+    Signature {
+        kind: "corrupt archive",
+        needles: &[
+            "gzip: stdin: unexpected end of file",
+            "tar: unexpected eof in archive",
+            "zip end header not found",
+        ],
+    },
+];
+
+/// A failed step whose log matches a cache-corruption signature.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CacheSuspect {
+    pub job_name: String,
+    pub step_name: String,
+    pub kind: &'static str,
+    /// The first matching log line.
+    pub line: String,
+}
+
+/// Find step logs that look like cache corruption rather than a real
+/// failure. At most one suspect per step.
+pub fn detect_cache_corruption(logs: &[FailedStepLog]) -> Vec<CacheSuspect> {
+    logs.iter()
+        .filter_map(|log| {
+            let text = format!("{}\n{}", log.error, log.output);
+            text.lines().find_map(|line| {
+                let lower = line.to_lowercase();
+                let matched = SIGNATURES
+                    .iter()
+                    .find(|s| s.needles.iter().any(|n| lower.contains(n)))?;
+                Some(CacheSuspect {
+                    job_name: log.job_name.clone(),
+                    step_name: log.step_name.clone(),
+                    kind: matched.kind,
+                    line: line.trim().to_string(),
+                })
+            })
+        })
+        .collect()
+}
+
+/// Trait for GitHub Actions cache and rerun operations, allowing test
+/// implementations.
+pub trait ActionsCacheClient {
+    /// IDs of the Actions caches scoped to `git_ref`.
+    fn list_cache_ids(&self, owner: &str, repo: &str, git_ref: &str) -> Result<Vec<u64>>;
+
+    fn delete_cache(&self, owner: &str, repo: &str, cache_id: u64) -> Result<()>;
+
+    /// Re-run the failed jobs of a workflow run.
+    fn rerun_failed_jobs(&self, owner: &str, repo: &str, run_id: u64) -> Result<()>;
+}
+
+/// Real client that uses `gh`.
+pub struct RealActionsCacheClient;
+
+#[derive(Deserialize)]
+struct CachesResponse {
+    actions_caches: Vec<CacheEntry>,
+}
+
+#[derive(Deserialize)]
+struct CacheEntry {
+    id: u64,
+}
+
+fn run_gh(args: &[&str], what: &str) -> Result<Vec<u8>> {
+    let output = Command::new("gh")
+        .args(args)
+        .traced_output()
+        .context(format!("Failed to run gh to {}", what))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        anyhow::bail!("Failed to {}: {}", what, stderr.trim());
+    }
+    Ok(output.stdout)
+}
+
+impl ActionsCacheClient for RealActionsCacheClient {
+    fn list_cache_ids(&self, owner: &str, repo: &str, git_ref: &str) -> Result<Vec<u64>> {
+        let path = format!(
+            "repos/{}/{}/actions/caches?ref={}&per_page=100",
+            owner,
+            repo,
+            urlencoding::encode(git_ref)
+        );
+        let stdout = run_gh(&["api", &path], "list Actions caches")?;
+        let parsed: CachesResponse =
+            serde_json::from_slice(&stdout).context("parse Actions caches")?;
+        Ok(parsed.actions_caches.into_iter().map(|c| c.id).collect())
+    }
+
+    fn delete_cache(&self, owner: &str, repo: &str, cache_id: u64) -> Result<()> {
+        let path = format!("repos/{}/{}/actions/caches/{}", owner, repo, cache_id);
+        run_gh(&["api", "-X", "DELETE", &path], "delete Actions cache")?;
+        Ok(())
+    }
+
+    fn rerun_failed_jobs(&self, owner: &str, repo: &str, run_id: u64) -> Result<()> {
+        run_gh(
+            &[
+                "run",
+                "rerun",
+                &run_id.to_string(),
+                "--failed",
+                "--repo",
+                &format!("{}/{}", owner, repo),
+            ],
+            "re-run GitHub Actions jobs",
+        )?;
+        Ok(())
+    }
+}
+
+/// Delete the Actions caches saved by the PR's own runs (scoped to
+/// `refs/pull/N/merge`). Caches on the base branch are shared with every
+/// other PR and are left alone. Returns the number deleted.
+pub fn clear_pr_caches(
+    client: &dyn ActionsCacheClient,
+    owner: &str,
+    repo: &str,
+    pr_number: u64,
+) -> Result<usize> {
+    let git_ref = format!("refs/pull/{}/merge", pr_number);
+    let ids = client.list_cache_ids(owner, repo, &git_ref)?;
+    for id in &ids {
+        client.delete_cache(owner, repo, *id)?;
+    }
+    Ok(ids.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+
+    fn step_log(job: &str, output: &str) -> FailedStepLog {
+        FailedStepLog {
+            job_name: job.to_string(),
+            step_name: "Install".to_string(),
+            output: output.to_string(),
+            error: String::new(),
+            container: None,
+            full_log_url: None,
+        }
+    }
+
+    #[test]
+    fn detects_signatures() {
+        let logs = vec![
+            step_log(
+                "web",
+                "npm ERR! code EINTEGRITY\nnpm ERR! sha512-abc integrity checksum failed",
+            ),
+            step_log("api", "error: failed to unpack package `serde v1.0.1`\n"),
+            step_log("deps", "gzip: stdin: unexpected end of file\ntar: Error is not recoverable\n"),
+            step_log("lint", "Error: Cannot find module 'eslint-plugin-react'\n"),
+            step_log("test", "assertion failed: left == right\n"),
+            step_log("e2e", "at Object.<anonymous> (node_modules/jest/run.js:3:1)\n"),
+            step_log("build", "error: failed to load source for dependency `foo`\n"),
+        ];
+        let suspects = detect_cache_corruption(&logs);
+        let found: Vec<_> = suspects.iter().map(|s| (s.job_name.as_str(), s.kind)).collect();
+        assert_eq!(
+            found,
+            vec![
+                ("web", "checksum mismatch"),
+                ("api", "cargo registry"),
+                ("deps", "corrupt archive"),
+            ]
+        );
+        assert_eq!(suspects[0].line, "npm ERR! code EINTEGRITY");
+    }
+
+    struct TestActionsCacheClient {
+        deleted: RefCell<Vec<u64>>,
+    }
+
+    impl ActionsCacheClient for TestActionsCacheClient {
+        fn list_cache_ids(&self, _owner: &str, _repo: &str, git_ref: &str) -> Result<Vec<u64>> {
+            assert_eq!(git_ref, "refs/pull/7/merge");
+            Ok(vec![11, 12])
+        }
+
+        fn delete_cache(&self, _owner: &str, _repo: &str, cache_id: u64) -> Result<()> {
+            self.deleted.borrow_mut().push(cache_id);
+            Ok(())
+        }
+
+        fn rerun_failed_jobs(&self, _owner: &str, _repo: &str, _run_id: u64) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn clears_only_pr_scoped_caches() {
+        let client = TestActionsCacheClient {
+            deleted: RefCell::new(vec![]),
+        };
+        assert_eq!(clear_pr_caches(&client, "o", "r", 7).unwrap(), 2);
+        assert_eq!(*client.deleted.borrow(), vec![11, 12]);
+    }
+}
@@ -0,0 +1,267 @@
+// What works in this environment.
+// Features need different things from the environment: posting replies needs
+// a token that can write to the repository, `--upload-logs` the `gist`
+// scope, `--forward-webhooks` the gh-webhook extension, detailed CircleCI
+// logs a CircleCI token. Rather than discovering a missing one mid-flow as a
+// cryptic GraphQL error, commands check what they need up front (`require`)
+// and fail with what's missing and how to get it; `pr-loop doctor` prints
+// the whole matrix.
+//
+// Fine-grained tokens and GitHub App tokens don't report scopes, so for them
+// requirements are "unknown" and commands go ahead.
+
+use crate::credentials::Credentials;
+use crate::github::parse_gh_version;
+use crate::request_log::TracedOutput;
+use anyhow::{bail, Result};
+use std::process::Command;
+
+/// Where the repository is hosted.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Forge {
+    GitHubCom,
+    /// GitHub Enterprise Server, by host name.
+    Enterprise(String),
+}
+
+/// How gh is authenticated.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Auth {
+    /// Not logged in (or the token was rejected).
+    None,
+    /// A token that doesn't report its scopes (fine-grained or GitHub App).
+    UnknownScopes,
+    /// A classic token with these OAuth scopes.
+    Scopes(Vec<String>),
+}
+
+/// The facts features depend on.
+#[derive(Debug, Clone)]
+pub struct Environment {
+    /// None if gh wasn't found or its version couldn't be read.
+    pub gh_version: Option<(u32, u32, u32)>,
+    pub forge: Forge,
+    pub auth: Auth,
+    pub circleci_token: bool,
+    /// Whether the gh-webhook extension is installed; None if not checked.
+    pub webhook_extension: Option<bool>,
+}
+
+impl Environment {
+    /// Probe gh and the token. With `extensions`, also list gh extensions
+    /// (only `--forward-webhooks` and `doctor` need that).
+    pub fn detect(creds: &Credentials, extensions: bool) -> Self {
+        let run = |args: &[&str]| {
+            Command::new("gh")
+                .args(args)
+                .traced_output()
+                .ok()
+                .filter(|o| o.status.success())
+                .map(|o| String::from_utf8_lossy(&o.stdout).into_owned())
+        };
+        let host = std::env::var("GH_HOST").unwrap_or_default();
+        Self {
+            gh_version: run(&["--version"]).as_deref().and_then(parse_gh_version),
+            forge: if host.is_empty() || host == "github.com" {
+                Forge::GitHubCom
+            } else {
+                Forge::Enterprise(host)
+            },
+            auth: match run(&["api", "--include", "user"]) {
+                Some(response) => parse_scopes(&response).map_or(Auth::UnknownScopes, Auth::Scopes),
+                None => Auth::None,
+            },
+            circleci_token: creds.has_circleci_token(),
+            webhook_extension: extensions
+                .then(|| run(&["extension", "list"]).is_some_and(|list| list.contains("gh-webhook"))),
+        }
+    }
+}
+
+/// The `X-OAuth-Scopes` header of a `gh api --include` response; None if the
+/// token doesn't report scopes.
+fn parse_scopes(response: &str) -> Option<Vec<String>> {
+    response
+        .lines()
+        .take_while(|line| !line.trim().is_empty())
+        .find_map(|line| {
+            let (name, value) = line.split_once(':')?;
+            name.trim().eq_ignore_ascii_case("x-oauth-scopes").then(|| {
+                value
+                    .split(',')
+                    .map(|s| s.trim().to_string())
+                    .filter(|s| !s.is_empty())
+                    .collect()
+            })
+        })
+}
+
+/// Something pr-loop can do that depends on the environment.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Feature {
+    /// Reading checks, threads, and reviews.
+    Analyze,
+    /// Replying, resolving, marking ready, editing the status block, rerunning checks.
+    Write,
+    /// `file-followups`.
+    FileIssues,
+    /// `--upload-logs`.
+    UploadLogs,
+    /// `--forward-webhooks`.
+    ForwardWebhooks,
+    /// Full CircleCI failure logs.
+    CircleCiLogs,
+    /// `--download-attachments` for private repositories.
+    PrivateAttachments,
+}
+
+impl Feature {
+    pub const ALL: [Feature; 7] = [
+        Feature::Analyze,
+        Feature::Write,
+        Feature::FileIssues,
+        Feature::UploadLogs,
+        Feature::ForwardWebhooks,
+        Feature::CircleCiLogs,
+        Feature::PrivateAttachments,
+    ];
+
+    pub fn describe(&self) -> &'static str {
+        match self {
+            Feature::Analyze => "analysis and waits",
+            Feature::Write => "reply, resolve, ready, status block",
+            Feature::FileIssues => "file-followups",
+            Feature::UploadLogs => "--upload-logs",
+            Feature::ForwardWebhooks => "--forward-webhooks",
+            Feature::CircleCiLogs => "CircleCI failure logs",
+            Feature::PrivateAttachments => "--download-attachments (private repos)",
+        }
+    }
+}
+
+/// Whether a feature works here.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Availability {
+    Available,
+    /// Why not, and how to fix it.
+    Unavailable(String),
+    /// It can't be told in advance (e.g. the token doesn't report scopes).
+    Unknown(String),
+}
+
+/// Whether `auth` grants `scope` (or a scope that includes it).
+fn has_scope(scopes: &[String], scope: &str) -> bool {
+    let implied_by: &[&str] = match scope {
+        "public_repo" => &["repo"],
+        "write:repo_hook" => &["admin:repo_hook"],
+        _ => &[],
+    };
+    scopes.iter().any(|s| s == scope || implied_by.contains(&s.as_str()))
+}
+
+/// Check that the token has one of `scopes`.
+fn scope_check(auth: &Auth, scopes: &[&str], refresh: &str) -> Availability {
+    match auth {
+        Auth::None => Availability::Unavailable("requires a GitHub login (run `gh auth login`)".to_string()),
+        Auth::UnknownScopes => {
+            Availability::Unknown("the token doesn't report its scopes (fine-grained or app token)".to_string())
+        }
+        Auth::Scopes(granted) if scopes.iter().any(|s| has_scope(granted, s)) => Availability::Available,
+        Auth::Scopes(granted) => Availability::Unavailable(format!(
+            "requires the `{}` scope; the token has {} (run `gh auth refresh -s {}`)",
+            scopes.join("` or `"),
+            if granted.is_empty() { "none".to_string() } else { granted.join(", ") },
+            refresh
+        )),
+    }
+}
+
+pub fn check(feature: Feature, env: &Environment) -> Availability {
+    if env.gh_version.is_none() && feature != Feature::CircleCiLogs {
+        return Availability::Unknown("gh's version couldn't be determined".to_string());
+    }
+    match feature {
+        Feature::Analyze => match env.auth {
+            Auth::None => Availability::Unavailable("requires a GitHub login (run `gh auth login`)".to_string()),
+            _ => Availability::Available,
+        },
+        Feature::Write | Feature::FileIssues => scope_check(&env.auth, &["repo", "public_repo"], "repo"),
+        Feature::UploadLogs => scope_check(&env.auth, &["gist"], "gist"),
+        Feature::ForwardWebhooks => match env.webhook_extension {
+            Some(false) => Availability::Unavailable(
+                "requires the gh-webhook extension (run `gh extension install cli/gh-webhook`)".to_string(),
+            ),
+            _ => scope_check(&env.auth, &["admin:repo_hook"], "admin:repo_hook"),
+        },
+        Feature::CircleCiLogs if env.circleci_token => Availability::Available,
+        Feature::CircleCiLogs => {
+            Availability::Unavailable("requires CIRCLECI_TOKEN (or a per-org token in the config)".to_string())
+        }
+        Feature::PrivateAttachments => match &env.forge {
+            Forge::GitHubCom => scope_check(&env.auth, &["repo"], "repo"),
+            Forge::Enterprise(host) => Availability::Unavailable(format!(
+                "the gh token is only sent to github.com, not {}",
+                host
+            )),
+        },
+    }
+}
+
+/// Every feature's availability.
+pub fn matrix(env: &Environment) -> Vec<(Feature, Availability)> {
+    Feature::ALL.iter().map(|&f| (f, check(f, env))).collect()
+}
+
+/// Fail if `feature` is known not to work here.
+pub fn require(feature: Feature, env: &Environment) -> Result<()> {
+    if let Availability::Unavailable(why) = check(feature, env) {
+        bail!("{} {}", feature.describe(), why);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn env(auth: Auth) -> Environment {
+        Environment {
+            gh_version: Some((2, 50, 0)),
+            forge: Forge::GitHubCom,
+            auth,
+            circleci_token: false,
+            webhook_extension: Some(false),
+        }
+    }
+
+    #[test]
+    fn reads_scopes_from_response_headers() {
+        let response = "HTTP/2.0 200 OK\nContent-Type: application/json\nX-Oauth-Scopes: gist, read:org, repo\n\n{\"login\":\"x\"}";
+        assert_eq!(
+            parse_scopes(response),
+            Some(vec!["gist".to_string(), "read:org".to_string(), "repo".to_string()])
+        );
+        assert_eq!(parse_scopes("HTTP/2.0 200 OK\nContent-Type: application/json\n\n{}"), None);
+    }
+
+    #[test]
+    fn explains_missing_requirements() {
+        let read_only = env(Auth::Scopes(vec!["read:org".to_string()]));
+        assert_eq!(check(Feature::Analyze, &read_only), Availability::Available);
+        assert_eq!(
+            require(Feature::Write, &read_only).unwrap_err().to_string(),
+            "reply, resolve, ready, status block requires the `repo` or `public_repo` scope; \
+             the token has read:org (run `gh auth refresh -s repo`)"
+        );
+        assert!(matches!(check(Feature::ForwardWebhooks, &read_only), Availability::Unavailable(why) if why.contains("gh-webhook")));
+
+        let full = env(Auth::Scopes(vec!["repo".to_string(), "gist".to_string()]));
+        assert!(require(Feature::Write, &full).is_ok());
+        assert!(require(Feature::UploadLogs, &full).is_ok());
+
+        // Fine-grained tokens can't be checked in advance
+        let fine_grained = env(Auth::UnknownScopes);
+        assert!(matches!(check(Feature::Write, &fine_grained), Availability::Unknown(_)));
+        assert!(require(Feature::Write, &fine_grained).is_ok());
+    }
+}
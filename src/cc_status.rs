@@ -126,8 +126,7 @@ fn pick_live_session_for_cwd(cwd: &Path) -> Option<(PathBuf, SessionState, Strin
     let target = cwd
         .canonicalize()
         .unwrap_or_else(|_| cwd.to_path_buf());
-    let home = std::env::var_os("HOME")?;
-    let dir = PathBuf::from(home).join(".claude/sessions");
+    let dir = crate::config::home_dir()?.join(".claude").join("sessions");
     let entries = std::fs::read_dir(&dir).ok()?;
 
     let mut candidates: Vec<(i64, PathBuf, SessionState, String)> = Vec::new();
@@ -204,6 +203,11 @@ fn apply_session_state(status: &mut CcStatus, session: &SessionState) {
 
 fn session_dir_for_cwd(cwd: &Path) -> Option<PathBuf> {
     let abs = cwd.canonicalize().ok().unwrap_or_else(|| cwd.to_path_buf());
+    // Windows canonical paths carry a `\\?\` prefix Claude Code doesn't use
+    let abs = match abs.to_str().and_then(|s| s.strip_prefix(r"\\?\")) {
+        Some(stripped) => PathBuf::from(stripped),
+        None => abs,
+    };
     // Claude Code encodes the CWD for its project directory by replacing
     // anything that isn't alphanumeric, `-`, or `_` with `-`. So
     // `/Users/foo/.config` → `-Users-foo--config`,
@@ -213,8 +217,7 @@ fn session_dir_for_cwd(cwd: &Path) -> Option<PathBuf> {
         .chars()
         .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '-' })
         .collect();
-    let home = std::env::var_os("HOME")?;
-    Some(PathBuf::from(home).join(".claude/projects").join(encoded))
+    Some(crate::config::home_dir()?.join(".claude").join("projects").join(encoded))
 }
 
 fn newest_jsonl(dir: &Path) -> Option<PathBuf> {
@@ -472,7 +475,7 @@ fn preview_for_tool(name: &str, input: Option<&Value>) -> Option<String> {
 }
 
 fn basename<'a>(path: &'a str) -> &'a str {
-    path.rsplit('/').next().unwrap_or(path)
+    path.rsplit(['/', '\\']).next().unwrap_or(path)
 }
 
 fn truncate(s: &str, max: usize) -> String {
@@ -537,6 +540,7 @@ mod tests {
     fn basename_strips_path() {
         assert_eq!(basename("/foo/bar/baz.rs"), "baz.rs");
         assert_eq!(basename("baz.rs"), "baz.rs");
+        assert_eq!(basename(r"C:\src\baz.rs"), "baz.rs");
     }
 
     #[test]
@@ -0,0 +1,277 @@
+// Publish the analysis as a check run on the PR head commit.
+// Makes the loop's view of the PR ("waiting on CI", "3 threads to answer")
+// visible in the PR UI, with annotations on the threads it still has to
+// address. Creating check runs requires a GitHub App token (e.g. the
+// `GITHUB_TOKEN` of an Actions workflow); gh's OAuth token is refused.
+
+use crate::analysis::NextAction;
+use crate::request_log;
+use crate::threads::ActionableThread;
+use crate::triage::ThreadTag;
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::io::Write;
+use std::process::{Command, Stdio};
+use std::time::Instant;
+
+/// Name of the published check run. Excluded from the checks pr-loop
+/// considers, so the loop never waits on its own output.
+pub const ANALYSIS_CHECK_NAME: &str = "pr-loop analysis";
+
+/// GitHub accepts at most 50 annotations per check run request.
+const MAX_ANNOTATIONS: usize = 50;
+
+/// Longest annotation message we send; the API caps it at 64KB.
+const MAX_MESSAGE_CHARS: usize = 4000;
+
+/// Longest summary the API accepts.
+const MAX_SUMMARY_CHARS: usize = 65535;
+
+/// Ends a summary cut off at `MAX_SUMMARY_CHARS`.
+const TRUNCATED_NOTE: &str = "\n\n… (cut off; run `pr-loop` for the whole analysis)\n";
+
+/// Request body for `POST /repos/{owner}/{repo}/check-runs`.
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct CheckRunRequest {
+    pub name: String,
+    pub head_sha: String,
+    pub status: String,
+    pub conclusion: String,
+    pub output: CheckRunOutput,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct CheckRunOutput {
+    pub title: String,
+    pub summary: String,
+    pub annotations: Vec<CheckRunAnnotation>,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct CheckRunAnnotation {
+    pub path: String,
+    pub start_line: u64,
+    pub end_line: u64,
+    /// "notice", "warning", or "failure".
+    pub annotation_level: String,
+    pub title: String,
+    pub message: String,
+}
+
+/// Build the check run for an analysis, with the Markdown the analysis
+/// prints as its summary. It always completes (never "failure"), so the
+/// analysis can't block merging by itself: "success" when the PR is ready,
+/// "neutral" otherwise.
+pub fn build_check_run(head_sha: &str, action: &NextAction, summary: String) -> CheckRunRequest {
+    let conclusion = match action {
+        NextAction::PrReady => "success",
+        _ => "neutral",
+    };
+    let annotations = match action {
+        NextAction::RespondToComments { threads, .. } => threads
+            .iter()
+            .filter_map(thread_annotation)
+            .take(MAX_ANNOTATIONS)
+            .collect(),
+        _ => vec![],
+    };
+    CheckRunRequest {
+        name: ANALYSIS_CHECK_NAME.to_string(),
+        head_sha: head_sha.to_string(),
+        status: "completed".to_string(),
+        conclusion: conclusion.to_string(),
+        output: CheckRunOutput {
+            title: title(action),
+            summary: fit_summary(summary),
+            annotations,
+        },
+    }
+}
+
+/// One-line title, matching the heading `format_recommendation` uses.
+pub fn title(action: &NextAction) -> String {
+    match action {
+        NextAction::RespondToComments { threads, .. } => format!(
+            "ACTION REQUIRED: Respond to {} review thread{}",
+            threads.len(),
+            if threads.len() == 1 { "" } else { "s" }
+        ),
+        NextAction::ResolveConflicts { .. } => "ACTION REQUIRED: Resolve merge conflicts".to_string(),
+        NextAction::FixCiFailures { failed_check_names } => format!(
+            "ACTION REQUIRED: Fix {} failing check{}",
+            failed_check_names.len(),
+            if failed_check_names.len() == 1 { "" } else { "s" }
+        ),
+        NextAction::WaitForCi { .. } => "WAITING: CI checks in progress".to_string(),
+        NextAction::Rebase { rebase } => format!("ACTION REQUIRED: Rebase onto {}", rebase.base),
+        NextAction::AwaitHumanInput { threads } => format!(
+            "WAITING: {} question{} for reviewers",
+            threads.len(),
+            if threads.len() == 1 { "" } else { "s" }
+        ),
+        NextAction::PrReady => "PR READY".to_string(),
+    }
+}
+
+/// Cut `summary` down to what the API accepts.
+fn fit_summary(summary: String) -> String {
+    if summary.chars().count() <= MAX_SUMMARY_CHARS {
+        return summary;
+    }
+    let keep = MAX_SUMMARY_CHARS - TRUNCATED_NOTE.chars().count();
+    let mut fitted: String = summary.chars().take(keep).collect();
+    fitted.push_str(TRUNCATED_NOTE);
+    fitted
+}
+
+/// Annotation pointing at a thread's file and lines. Threads without a line
+/// (file-level or outdated comments) can't be annotated, and neither can
+/// comments on removed code, whose line is in the old version of the file.
+fn thread_annotation(actionable: &ActionableThread) -> Option<CheckRunAnnotation> {
+    if actionable.thread.on_removed_code {
+        return None;
+    }
+    let path = actionable.thread.path.clone()?;
+    let line = actionable.thread.line?;
+    let last = actionable.thread.comments.last()?;
+    let level = match actionable.tag {
+        Some(ThreadTag::Blocking) => "warning",
+        _ => "notice",
+    };
+    Some(CheckRunAnnotation {
+        path,
+        start_line: actionable.thread.start_line.unwrap_or(line),
+        end_line: line,
+        annotation_level: level.to_string(),
+        title: format!("Review comment by @{}", last.author),
+        message: last.body.trim().chars().take(MAX_MESSAGE_CHARS).collect(),
+    })
+}
+
+/// Trait for publishing check runs, allowing test implementations.
+pub trait CheckRunClient {
+    /// Create the check run. Returns its HTML URL.
+    fn create_check_run(&self, owner: &str, repo: &str, run: &CheckRunRequest) -> Result<String>;
+}
+
+/// Real client that uses `gh api`.
+pub struct RealCheckRunClient;
+
+impl CheckRunClient for RealCheckRunClient {
+    fn create_check_run(&self, owner: &str, repo: &str, run: &CheckRunRequest) -> Result<String> {
+        let body = serde_json::to_vec(run).context("serialize check run")?;
+        let mut command = Command::new("gh");
+        command
+            .args([
+                "api",
+                "-X",
+                "POST",
+                &format!("repos/{}/{}/check-runs", owner, repo),
+                "--input",
+                "-",
+                "--jq",
+                ".html_url",
+            ])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+        let started = Instant::now();
+        let mut child = command
+            .spawn()
+            .context("Failed to run 'gh api' to create check run")?;
+        child
+            .stdin
+            .take()
+            .context("gh api stdin unavailable")?
+            .write_all(&body)
+            .context("Failed to send check run to gh api")?;
+        let output = child.wait_with_output();
+        request_log::record(&command, started, output.as_ref().ok());
+        let output = output.context("Failed to wait for gh api")?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            if stderr.contains("HTTP 403") {
+                anyhow::bail!(
+                    "creating check runs requires a GitHub App token (e.g. GITHUB_TOKEN in Actions): {}",
+                    stderr.trim()
+                );
+            }
+            anyhow::bail!("gh api create check run failed: {}", stderr.trim());
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::threads::{ReviewThread, ThreadComment};
+
+    fn thread(id: &str, path: Option<&str>, line: Option<u64>, body: &str) -> ActionableThread {
+        let comment = ThreadComment::for_test(&format!("{}-c", id), "reviewer", body);
+        ActionableThread {
+            thread: ReviewThread {
+                path: path.map(String::from),
+                line,
+                ..ReviewThread::for_test(id, vec![comment])
+            },
+            tag: None,
+        }
+    }
+
+    #[test]
+    fn annotates_threads_with_a_line() {
+        let mut blocking = thread("T1", Some("src/lib.rs"), Some(12), "This will panic");
+        blocking.tag = Some(ThreadTag::Blocking);
+        let action = NextAction::RespondToComments {
+            threads: vec![
+                blocking,
+                thread("T2", Some("README.md"), None, "typo somewhere"),
+            ],
+            also_has_ci_failures: false,
+            ci_pending: false,
+        };
+        let run = build_check_run("abc123", &action, "## ACTION REQUIRED\n".to_string());
+
+        assert_eq!(run.name, ANALYSIS_CHECK_NAME);
+        assert_eq!(run.conclusion, "neutral");
+        assert_eq!(run.output.title, "ACTION REQUIRED: Respond to 2 review threads");
+        assert_eq!(run.output.summary, "## ACTION REQUIRED\n");
+        assert_eq!(
+            run.output.annotations,
+            vec![CheckRunAnnotation {
+                path: "src/lib.rs".to_string(),
+                start_line: 12,
+                end_line: 12,
+                annotation_level: "warning".to_string(),
+                title: "Review comment by @reviewer".to_string(),
+                message: "This will panic".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn ready_and_waiting_runs() {
+        let ready = build_check_run("abc", &NextAction::PrReady, String::new());
+        assert_eq!(ready.conclusion, "success");
+        assert_eq!(ready.output.title, "PR READY");
+
+        let waiting = build_check_run(
+            "abc",
+            &NextAction::WaitForCi {
+                pending_check_names: vec!["test".to_string()],
+            },
+            String::new(),
+        );
+        assert_eq!(waiting.conclusion, "neutral");
+        assert_eq!(waiting.status, "completed");
+        assert!(waiting.output.annotations.is_empty());
+    }
+
+    #[test]
+    fn long_summaries_are_cut_to_fit() {
+        let run = build_check_run("abc", &NextAction::PrReady, "🤖".repeat(MAX_SUMMARY_CHARS + 1));
+        assert_eq!(run.output.summary.chars().count(), MAX_SUMMARY_CHARS);
+        assert!(run.output.summary.ends_with(TRUNCATED_NOTE));
+    }
+}
@@ -0,0 +1,401 @@
+// Local checkout synchronization for `pr-loop checkout`.
+// Fetches the PR head, switches to (or creates) its branch, fast-forwards it
+// when safe, and reports when the local branch is ahead of or has diverged
+// from the PR, so edits aren't made against a stale checkout.
+
+use crate::request_log::TracedOutput;
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::process::Command;
+
+/// The PR's head branch and commit as GitHub sees them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PrHead {
+    pub branch: String,
+    pub sha: String,
+}
+
+/// Fetch the PR's head branch name and SHA using `gh pr view`.
+pub fn fetch_pr_head(owner: &str, repo: &str, pr_number: u64) -> Result<PrHead> {
+    let output = Command::new("gh")
+        .args([
+            "pr",
+            "view",
+            &pr_number.to_string(),
+            "--repo",
+            &format!("{}/{}", owner, repo),
+            "--json",
+            "headRefName,headRefOid",
+        ])
+        .traced_output()
+        .context("Failed to run 'gh pr view'")?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        anyhow::bail!("Failed to get PR head: {}", stderr.trim());
+    }
+
+    #[derive(Deserialize)]
+    struct HeadView {
+        #[serde(rename = "headRefName")]
+        head_ref_name: String,
+        #[serde(rename = "headRefOid")]
+        head_ref_oid: String,
+    }
+
+    let view: HeadView =
+        serde_json::from_slice(&output.stdout).context("Failed to parse PR view output")?;
+
+    Ok(PrHead {
+        branch: view.head_ref_name,
+        sha: view.head_ref_oid,
+    })
+}
+
+/// Git operations needed to sync the checkout, allowing test implementations.
+pub trait CheckoutGit {
+    /// Remote names and fetch URLs.
+    fn remotes(&self) -> Result<Vec<(String, String)>>;
+    /// Fetch `pull/<N>/head` from a remote (name or URL); returns the fetched SHA.
+    fn fetch_pull_head(&self, remote: &str, pr_number: u64) -> Result<String>;
+    /// Name of the checked-out branch, or None if HEAD is detached.
+    fn current_branch(&self) -> Result<Option<String>>;
+    /// Resolve a revision to a SHA, or None if it doesn't exist.
+    fn resolve(&self, rev: &str) -> Result<Option<String>>;
+    /// Returns true if `ancestor` is an ancestor of (or equal to) `descendant`.
+    fn is_ancestor(&self, ancestor: &str, descendant: &str) -> Result<bool>;
+    /// Returns true if the worktree has uncommitted changes to tracked files.
+    fn is_dirty(&self) -> Result<bool>;
+    fn switch_branch(&self, branch: &str) -> Result<()>;
+    fn create_branch(&self, branch: &str, sha: &str) -> Result<()>;
+    fn fast_forward(&self, sha: &str) -> Result<()>;
+}
+
+pub struct RealCheckoutGit;
+
+impl RealCheckoutGit {
+    fn git(&self, args: &[&str]) -> Result<std::process::Output> {
+        Command::new("git")
+            .args(args)
+            .output()
+            .with_context(|| format!("Failed to run 'git {}'", args[0]))
+    }
+
+    /// Run git and return trimmed stdout, failing with git's stderr.
+    fn git_ok(&self, args: &[&str]) -> Result<String> {
+        let output = self.git(args)?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            anyhow::bail!("'git {}' failed: {}", args.join(" "), stderr.trim());
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    }
+}
+
+impl CheckoutGit for RealCheckoutGit {
+    fn remotes(&self) -> Result<Vec<(String, String)>> {
+        let out = self.git_ok(&["remote", "-v"])?;
+        Ok(out
+            .lines()
+            .filter(|l| l.ends_with("(fetch)"))
+            .filter_map(|l| {
+                let mut parts = l.split_whitespace();
+                Some((parts.next()?.to_string(), parts.next()?.to_string()))
+            })
+            .collect())
+    }
+
+    fn fetch_pull_head(&self, remote: &str, pr_number: u64) -> Result<String> {
+        self.git_ok(&["fetch", "--quiet", remote, &format!("pull/{}/head", pr_number)])?;
+        self.git_ok(&["rev-parse", "FETCH_HEAD"])
+    }
+
+    fn current_branch(&self) -> Result<Option<String>> {
+        let output = self.git(&["symbolic-ref", "--quiet", "--short", "HEAD"])?;
+        Ok(output
+            .status
+            .success()
+            .then(|| String::from_utf8_lossy(&output.stdout).trim().to_string()))
+    }
+
+    fn resolve(&self, rev: &str) -> Result<Option<String>> {
+        let output = self.git(&["rev-parse", "--verify", "--quiet", &format!("{}^{{commit}}", rev)])?;
+        Ok(output
+            .status
+            .success()
+            .then(|| String::from_utf8_lossy(&output.stdout).trim().to_string()))
+    }
+
+    fn is_ancestor(&self, ancestor: &str, descendant: &str) -> Result<bool> {
+        let output = self.git(&["merge-base", "--is-ancestor", ancestor, descendant])?;
+        match output.status.code() {
+            Some(0) => Ok(true),
+            Some(1) => Ok(false),
+            _ => {
+                let stderr = String::from_utf8_lossy(&output.stderr);
+                anyhow::bail!("'git merge-base' failed: {}", stderr.trim())
+            }
+        }
+    }
+
+    fn is_dirty(&self) -> Result<bool> {
+        let out = self.git_ok(&["status", "--porcelain", "--untracked-files=no"])?;
+        Ok(!out.is_empty())
+    }
+
+    fn switch_branch(&self, branch: &str) -> Result<()> {
+        self.git_ok(&["checkout", "--quiet", branch]).map(|_| ())
+    }
+
+    fn create_branch(&self, branch: &str, sha: &str) -> Result<()> {
+        self.git_ok(&["checkout", "--quiet", "-b", branch, sha]).map(|_| ())
+    }
+
+    fn fast_forward(&self, sha: &str) -> Result<()> {
+        self.git_ok(&["merge", "--quiet", "--ff-only", sha]).map(|_| ())
+    }
+}
+
+/// Pick the remote to fetch from: one whose URL points at `owner/repo`, or
+/// the repository's GitHub URL if no remote does.
+pub fn pick_remote(remotes: &[(String, String)], owner: &str, repo: &str) -> String {
+    let slug = format!("{}/{}", owner, repo).to_lowercase();
+    remotes
+        .iter()
+        .find(|(_, url)| {
+            let url = url.to_lowercase();
+            let url = url.trim_end_matches('/').trim_end_matches(".git");
+            url.ends_with(&format!("/{}", slug)) || url.ends_with(&format!(":{}", slug))
+        })
+        .map(|(name, _)| name.clone())
+        .unwrap_or_else(|| format!("https://github.com/{}/{}.git", owner, repo))
+}
+
+/// What syncing did to the local branch.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SyncOutcome {
+    /// The branch already matched the PR head.
+    UpToDate,
+    /// The branch didn't exist locally and was created at the PR head.
+    Created,
+    /// The branch was behind and was fast-forwarded from this SHA.
+    FastForwarded { from: String },
+    /// The branch has commits the PR doesn't (not pushed yet).
+    LocalAhead,
+    /// The branch and the PR each have commits the other lacks; left untouched.
+    Diverged { local: String },
+}
+
+/// Bring the local checkout to the PR head: switch to its branch (creating
+/// it if needed) and fast-forward when that's safe. Never discards commits.
+pub fn sync_checkout(
+    git: &dyn CheckoutGit,
+    head: &PrHead,
+    remote: &str,
+    pr_number: u64,
+) -> Result<SyncOutcome> {
+    let fetched = git.fetch_pull_head(remote, pr_number)?;
+
+    if git.current_branch()?.as_deref() != Some(head.branch.as_str()) {
+        if git.is_dirty()? {
+            anyhow::bail!(
+                "The worktree has uncommitted changes; commit or stash them before switching to '{}'",
+                head.branch
+            );
+        }
+        if git.resolve(&format!("refs/heads/{}", head.branch))?.is_none() {
+            git.create_branch(&head.branch, &fetched)?;
+            return Ok(SyncOutcome::Created);
+        }
+        git.switch_branch(&head.branch)?;
+    }
+
+    let local = git
+        .resolve("HEAD")?
+        .ok_or_else(|| anyhow::anyhow!("Failed to resolve HEAD"))?;
+    if local == fetched {
+        Ok(SyncOutcome::UpToDate)
+    } else if git.is_ancestor(&local, &fetched)? {
+        git.fast_forward(&fetched)?;
+        Ok(SyncOutcome::FastForwarded { from: local })
+    } else if git.is_ancestor(&fetched, &local)? {
+        Ok(SyncOutcome::LocalAhead)
+    } else {
+        Ok(SyncOutcome::Diverged { local })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+
+    /// A fake repository with a linear-or-forked history described by
+    /// ancestor pairs.
+    struct TestCheckoutGit {
+        fetched: String,
+        branch: RefCell<Option<String>>,
+        branches: RefCell<Vec<(String, String)>>,
+        ancestors: Vec<(&'static str, &'static str)>,
+        dirty: bool,
+        calls: RefCell<Vec<String>>,
+    }
+
+    impl TestCheckoutGit {
+        fn new(current: Option<(&str, &str)>, fetched: &str) -> Self {
+            Self {
+                fetched: fetched.to_string(),
+                branch: RefCell::new(current.map(|(b, _)| b.to_string())),
+                branches: RefCell::new(
+                    current
+                        .map(|(b, sha)| vec![(b.to_string(), sha.to_string())])
+                        .unwrap_or_default(),
+                ),
+                ancestors: vec![],
+                dirty: false,
+                calls: RefCell::new(vec![]),
+            }
+        }
+
+        fn head_sha(&self) -> Option<String> {
+            let branch = self.branch.borrow().clone()?;
+            self.branches
+                .borrow()
+                .iter()
+                .find(|(b, _)| *b == branch)
+                .map(|(_, sha)| sha.clone())
+        }
+    }
+
+    impl CheckoutGit for TestCheckoutGit {
+        fn remotes(&self) -> Result<Vec<(String, String)>> {
+            Ok(vec![])
+        }
+        fn fetch_pull_head(&self, remote: &str, pr_number: u64) -> Result<String> {
+            self.calls
+                .borrow_mut()
+                .push(format!("fetch {} {}", remote, pr_number));
+            Ok(self.fetched.clone())
+        }
+        fn current_branch(&self) -> Result<Option<String>> {
+            Ok(self.branch.borrow().clone())
+        }
+        fn resolve(&self, rev: &str) -> Result<Option<String>> {
+            if rev == "HEAD" {
+                return Ok(self.head_sha());
+            }
+            let name = rev.trim_start_matches("refs/heads/");
+            Ok(self
+                .branches
+                .borrow()
+                .iter()
+                .find(|(b, _)| b == name)
+                .map(|(_, sha)| sha.clone()))
+        }
+        fn is_ancestor(&self, ancestor: &str, descendant: &str) -> Result<bool> {
+            Ok(ancestor == descendant || self.ancestors.contains(&(ancestor, descendant)))
+        }
+        fn is_dirty(&self) -> Result<bool> {
+            Ok(self.dirty)
+        }
+        fn switch_branch(&self, branch: &str) -> Result<()> {
+            self.calls.borrow_mut().push(format!("switch {}", branch));
+            *self.branch.borrow_mut() = Some(branch.to_string());
+            Ok(())
+        }
+        fn create_branch(&self, branch: &str, sha: &str) -> Result<()> {
+            self.calls
+                .borrow_mut()
+                .push(format!("create {} {}", branch, sha));
+            self.branches
+                .borrow_mut()
+                .push((branch.to_string(), sha.to_string()));
+            *self.branch.borrow_mut() = Some(branch.to_string());
+            Ok(())
+        }
+        fn fast_forward(&self, sha: &str) -> Result<()> {
+            self.calls.borrow_mut().push(format!("ff {}", sha));
+            let branch = self.branch.borrow().clone().unwrap();
+            for (b, s) in self.branches.borrow_mut().iter_mut() {
+                if *b == branch {
+                    *s = sha.to_string();
+                }
+            }
+            Ok(())
+        }
+    }
+
+    fn head() -> PrHead {
+        PrHead {
+            branch: "feature".to_string(),
+            sha: "new".to_string(),
+        }
+    }
+
+    #[test]
+    fn up_to_date_does_nothing() {
+        let git = TestCheckoutGit::new(Some(("feature", "new")), "new");
+        assert_eq!(sync_checkout(&git, &head(), "origin", 7).unwrap(), SyncOutcome::UpToDate);
+        assert_eq!(*git.calls.borrow(), vec!["fetch origin 7"]);
+    }
+
+    #[test]
+    fn behind_is_fast_forwarded() {
+        let mut git = TestCheckoutGit::new(Some(("feature", "old")), "new");
+        git.ancestors.push(("old", "new"));
+        assert_eq!(
+            sync_checkout(&git, &head(), "origin", 7).unwrap(),
+            SyncOutcome::FastForwarded {
+                from: "old".to_string()
+            }
+        );
+        assert_eq!(git.resolve("HEAD").unwrap().as_deref(), Some("new"));
+    }
+
+    #[test]
+    fn ahead_and_diverged_are_left_alone() {
+        let mut git = TestCheckoutGit::new(Some(("feature", "local")), "new");
+        git.ancestors.push(("new", "local"));
+        assert_eq!(
+            sync_checkout(&git, &head(), "origin", 7).unwrap(),
+            SyncOutcome::LocalAhead
+        );
+
+        let git = TestCheckoutGit::new(Some(("feature", "local")), "new");
+        assert_eq!(
+            sync_checkout(&git, &head(), "origin", 7).unwrap(),
+            SyncOutcome::Diverged {
+                local: "local".to_string()
+            }
+        );
+        assert_eq!(*git.calls.borrow(), vec!["fetch origin 7"]);
+    }
+
+    #[test]
+    fn missing_branch_is_created() {
+        let git = TestCheckoutGit::new(Some(("main", "m")), "new");
+        assert_eq!(sync_checkout(&git, &head(), "origin", 7).unwrap(), SyncOutcome::Created);
+        assert_eq!(git.current_branch().unwrap().as_deref(), Some("feature"));
+    }
+
+    #[test]
+    fn refuses_to_switch_with_uncommitted_changes() {
+        let mut git = TestCheckoutGit::new(Some(("main", "m")), "new");
+        git.dirty = true;
+        assert!(sync_checkout(&git, &head(), "origin", 7).is_err());
+        assert_eq!(*git.calls.borrow(), vec!["fetch origin 7"]);
+    }
+
+    #[test]
+    fn picks_remote_matching_repo() {
+        let remotes = vec![
+            ("fork".to_string(), "git@github.com:me/repo.git".to_string()),
+            ("upstream".to_string(), "https://github.com/Acme/Repo".to_string()),
+        ];
+        assert_eq!(pick_remote(&remotes, "acme", "repo"), "upstream");
+        assert_eq!(
+            pick_remote(&remotes, "other", "repo"),
+            "https://github.com/other/repo.git"
+        );
+    }
+}
@@ -1,13 +1,21 @@
 // CI status check handling.
-// Fetches and filters PR status checks using the GitHub API.
+// Fetches and filters PR status checks using the GitHub API. Check runs and
+// legacy commit statuses are read from the head commit's status rollup and
+// reconciled into one list; `gh pr checks` is the fallback.
 
+use crate::gh_actions::parse_gh_actions_url;
+use crate::github::{fetch_mergeable_status_from_gh, MergeableStatus};
+use crate::graphql::gh_graphql;
+use crate::request_log::TracedOutput;
+use crate::retry;
 use anyhow::{Context, Result};
 use glob::Pattern;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
 use std::process::Command;
 
 /// Status of a CI check.
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum CheckStatus {
     Pass,
     Fail,
@@ -46,21 +54,62 @@ fn classify_check(bucket: &str, description: Option<&str>) -> CheckStatus {
     base
 }
 
+/// Where a check came from. GitHub reports two kinds of CI signal on a
+/// commit: check runs (Checks API — GitHub Apps and Actions) and commit
+/// statuses (Status API — e.g. CircleCI's `ci/circleci: *` contexts).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CheckSource {
+    CheckRun,
+    CommitStatus,
+    /// Provenance isn't reported (the `gh pr checks` fallback).
+    Unknown,
+}
+
 /// A single CI check result.
 #[derive(Debug, Clone)]
 pub struct Check {
     pub name: String,
     pub status: CheckStatus,
     pub url: Option<String>,
+    pub source: CheckSource,
+    /// ISO-8601 start time (check runs) or creation time (commit statuses).
+    /// None for runs that are queued but haven't started.
+    pub started_at: Option<String>,
+    /// Number of attempts seen for this check name. 1 unless re-runs were
+    /// collapsed by [`collapse_reruns`].
+    pub attempts: u32,
+    /// Slug of the GitHub App that created the check run (e.g. "codecov"),
+    /// or the login of a commit status's creator with any `[bot]` suffix
+    /// stripped. None when unknown.
+    pub app: Option<String>,
+}
+
+impl Check {
+    /// Display name, annotated with the attempt number when re-run.
+    pub fn display_name(&self) -> String {
+        if self.attempts > 1 {
+            format!("{} (attempt {})", self.name, self.attempts)
+        } else {
+            self.name.clone()
+        }
+    }
 }
 
 /// Summary of all checks for a PR.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct ChecksSummary {
     pub checks: Vec<Check>,
 }
 
 impl ChecksSummary {
+    /// Build a summary, collapsing re-run attempts so only the latest
+    /// attempt of each check counts.
+    pub fn new(checks: Vec<Check>) -> Self {
+        Self {
+            checks: collapse_reruns(checks),
+        }
+    }
+
     /// Returns checks that have failed.
     pub fn failed(&self) -> Vec<&Check> {
         self.checks
@@ -79,42 +128,781 @@ impl ChecksSummary {
 
 }
 
-/// Trait for fetching checks, allowing test implementations.
-pub trait ChecksClient {
+/// Checks reported on one commit of a PR.
+#[derive(Debug, Clone)]
+pub struct CommitChecks {
+    pub sha: String,
+    pub abbreviated_sha: String,
+    pub headline: String,
+    pub checks: Vec<Check>,
+}
+
+/// The latest finished result of each check on the PR's base branch.
+#[derive(Debug, Clone, Default)]
+pub struct BaseChecks {
+    /// e.g. "main"
+    pub branch: String,
+    pub checks: Vec<Check>,
+}
+
+/// How many of the base branch's latest commits are searched for a finished
+/// run of each check (its newest commit's may still be running).
+const BASE_HISTORY_COMMITS: u64 = 5;
+
+/// Trait for fetching checks, allowing test implementations. `Sync` so
+/// checks can be fetched while other requests are in flight.
+pub trait ChecksClient: Sync {
     fn fetch_checks(&self, owner: &str, repo: &str, pr_number: u64) -> Result<Vec<Check>>;
+
+    /// Fetch the checks on each of the PR's last `count` commits, oldest first.
+    fn fetch_check_history(
+        &self,
+        _owner: &str,
+        _repo: &str,
+        _pr_number: u64,
+        _count: u64,
+    ) -> Result<Vec<CommitChecks>> {
+        Ok(vec![])
+    }
+
+    /// Fetch the latest finished result of each check on the PR's base branch.
+    fn fetch_base_checks(&self, _owner: &str, _repo: &str, _pr_number: u64) -> Result<BaseChecks> {
+        Ok(BaseChecks::default())
+    }
+
+    /// Names of the head commit's checks that branch protection requires.
+    fn fetch_required_checks(&self, _owner: &str, _repo: &str, _pr_number: u64) -> Result<Vec<String>> {
+        Ok(vec![])
+    }
+
+    /// Whether the PR merges cleanly into its base branch.
+    fn fetch_mergeable_status(&self, _owner: &str, _repo: &str, _pr_number: u64) -> Result<MergeableStatus> {
+        Ok(MergeableStatus::Unknown)
+    }
 }
 
-/// Real client that uses `gh pr checks`.
+/// Real client that reads the status check rollup via `gh api graphql`,
+/// falling back to `gh pr checks` if that fails.
 pub struct RealChecksClient;
 
 impl ChecksClient for RealChecksClient {
     fn fetch_checks(&self, owner: &str, repo: &str, pr_number: u64) -> Result<Vec<Check>> {
-        fetch_checks_from_gh(owner, repo, pr_number)
+        match fetch_checks_from_rollup(owner, repo, pr_number) {
+            Ok(checks) => Ok(merge_checks(checks)),
+            Err(e) => {
+                eprintln!(
+                    "Warning: Failed to fetch check rollup ({}); falling back to 'gh pr checks'",
+                    e
+                );
+                fetch_checks_from_gh(owner, repo, pr_number).map(merge_checks)
+            }
+        }
+    }
+
+    fn fetch_check_history(
+        &self,
+        owner: &str,
+        repo: &str,
+        pr_number: u64,
+        count: u64,
+    ) -> Result<Vec<CommitChecks>> {
+        fetch_check_history_from_graphql(owner, repo, pr_number, count)
+    }
+
+    fn fetch_base_checks(&self, owner: &str, repo: &str, pr_number: u64) -> Result<BaseChecks> {
+        fetch_base_checks_from_graphql(owner, repo, pr_number)
+    }
+
+    fn fetch_required_checks(&self, owner: &str, repo: &str, pr_number: u64) -> Result<Vec<String>> {
+        fetch_required_checks_from_graphql(owner, repo, pr_number)
+    }
+
+    fn fetch_mergeable_status(&self, owner: &str, repo: &str, pr_number: u64) -> Result<MergeableStatus> {
+        fetch_mergeable_status_from_gh(owner, repo, pr_number)
+    }
+}
+
+/// Map a check run's status/conclusion to a `gh pr checks`-style bucket.
+/// Mirrors gh's own mapping: anything not yet completed is pending, and
+/// unrecognized conclusions (STALE, STARTUP_FAILURE, ...) count as failures.
+fn check_run_bucket(status: &str, conclusion: Option<&str>) -> &'static str {
+    if status != "COMPLETED" {
+        return "pending";
+    }
+    match conclusion.unwrap_or("") {
+        "SUCCESS" | "NEUTRAL" => "pass",
+        "SKIPPED" => "skipping",
+        "CANCELLED" => "cancel",
+        "" => "pending",
+        _ => "fail",
     }
 }
 
+/// Map a commit status state to a `gh pr checks`-style bucket.
+fn status_context_bucket(state: &str) -> &'static str {
+    match state {
+        "SUCCESS" => "pass",
+        "FAILURE" | "ERROR" => "fail",
+        _ => "pending", // PENDING, EXPECTED
+    }
+}
+
+/// Reconcile check runs and commit statuses into one list. GitHub often
+/// reports the same job more than once: identical entries (same name and
+/// URL) collapse into one, and when a commit status shares its name with a
+/// check run, the check run wins — borrowing the status's URL if it has none.
+/// Order of first appearance is preserved.
+pub fn merge_checks(checks: Vec<Check>) -> Vec<Check> {
+    let mut merged: Vec<Check> = Vec::new();
+    for check in checks {
+        if merged
+            .iter()
+            .any(|m| m.name == check.name && m.url == check.url && m.source == check.source)
+        {
+            continue;
+        }
+        let counterpart = merged.iter_mut().find(|m| {
+            m.name == check.name
+                && matches!(
+                    (m.source, check.source),
+                    (CheckSource::CheckRun, CheckSource::CommitStatus)
+                        | (CheckSource::CommitStatus, CheckSource::CheckRun)
+                )
+        });
+        match counterpart {
+            Some(existing) if check.source == CheckSource::CheckRun => {
+                let status_url = existing.url.take();
+                *existing = Check {
+                    url: check.url.or(status_url),
+                    ..check
+                };
+            }
+            Some(existing) => {
+                if existing.url.is_none() {
+                    existing.url = check.url;
+                }
+            }
+            None => merged.push(check),
+        }
+    }
+    merged
+}
+
+/// Group checks by name and keep only the latest attempt of each. When a job
+/// is re-run, the old failed attempt and the new pending one can both be
+/// reported, which would make the PR look failed forever. Latest is decided
+/// by `started_at` (a run that hasn't started yet is newest; ties go to the
+/// later entry). The survivor's `attempts` records how many were seen.
+/// GitHub Actions jobs are only attempts of each other within one workflow
+/// run (a re-run keeps the run ID), so same-named jobs of separate runs, like
+/// the `push` and `pull_request` runs of one workflow, stay apart as in
+/// [`merge_checks`]. Order of first appearance is preserved.
+pub fn collapse_reruns(checks: Vec<Check>) -> Vec<Check> {
+    let run_id = |check: &Check| check.url.as_deref().and_then(parse_gh_actions_url).map(|job| job.run_id);
+    let mut collapsed: Vec<Check> = Vec::new();
+    for check in checks {
+        match collapsed
+            .iter_mut()
+            .find(|c| c.name == check.name && run_id(c) == run_id(&check))
+        {
+            Some(existing) => {
+                let attempts = existing.attempts + check.attempts;
+                let newer = match (&existing.started_at, &check.started_at) {
+                    (_, None) => true,
+                    (None, Some(_)) => false,
+                    (Some(a), Some(b)) => b >= a,
+                };
+                if newer {
+                    *existing = check;
+                }
+                existing.attempts = attempts;
+            }
+            None => collapsed.push(check),
+        }
+    }
+    collapsed
+}
+
+/// Rules for collapsing matrix variants into one logical check for display.
+#[derive(Debug, Clone)]
+pub struct CollapseRules {
+    /// Group checks named `base (variant...)` — GitHub Actions' matrix naming —
+    /// under `base` when at least two share the same base.
+    pub matrix: bool,
+    /// Glob patterns; checks matching a pattern are grouped under the pattern
+    /// itself. Checked before the matrix rule; first match wins.
+    pub patterns: Vec<String>,
+}
+
+impl Default for CollapseRules {
+    fn default() -> Self {
+        Self {
+            matrix: true,
+            patterns: vec![],
+        }
+    }
+}
+
+/// One logical check: either a single check or the variants of a matrix job.
+#[derive(Debug, Clone)]
+pub struct CheckGroup<'a> {
+    pub name: String,
+    pub variants: Vec<&'a Check>,
+}
+
+impl CheckGroup<'_> {
+    /// True if this group aggregates more than one check.
+    pub fn is_matrix(&self) -> bool {
+        self.variants.len() > 1
+    }
+
+    /// Overall status: failed if any variant failed, else pending if any is
+    /// pending, else passed if any passed; otherwise the first variant's.
+    pub fn status(&self) -> CheckStatus {
+        for status in [CheckStatus::Fail, CheckStatus::Pending, CheckStatus::Pass] {
+            if self.variants.iter().any(|c| c.status == status) {
+                return status;
+            }
+        }
+        self.variants[0].status.clone()
+    }
+
+    /// Variants with the given status.
+    pub fn with_status(&self, status: &CheckStatus) -> Vec<&Check> {
+        self.variants
+            .iter()
+            .filter(|c| c.status == *status)
+            .copied()
+            .collect()
+    }
+
+    /// Display label: the check's own display name, or for matrix groups
+    /// `name [8 variants: 6 passed, 1 failed, 1 pending]`.
+    pub fn label(&self) -> String {
+        if !self.is_matrix() {
+            return self.variants[0].display_name();
+        }
+        let mut counts = Vec::new();
+        for (status, word) in [
+            (CheckStatus::Pass, "passed"),
+            (CheckStatus::Fail, "failed"),
+            (CheckStatus::Pending, "pending"),
+            (CheckStatus::Skipping, "skipped"),
+            (CheckStatus::Cancelled, "cancelled"),
+        ] {
+            let n = self.with_status(&status).len();
+            if n > 0 {
+                counts.push(format!("{} {}", n, word));
+            }
+        }
+        format!(
+            "{} [{} variants: {}]",
+            self.name,
+            self.variants.len(),
+            counts.join(", ")
+        )
+    }
+}
+
+/// Strip a trailing ` (…)` matrix suffix: `test (ubuntu, 1.75, shard 3/8)` → `test`.
+fn matrix_base_name(name: &str) -> Option<&str> {
+    if !name.ends_with(')') {
+        return None;
+    }
+    let idx = name.find(" (")?;
+    Some(&name[..idx])
+}
+
+/// Group checks into logical checks per `rules`, preserving order of first
+/// appearance. Invalid patterns are an error.
+pub fn group_checks<'a>(checks: &[&'a Check], rules: &CollapseRules) -> Result<Vec<CheckGroup<'a>>> {
+    let patterns: Vec<(Pattern, &str)> = rules
+        .patterns
+        .iter()
+        .map(|p| {
+            Pattern::new(p)
+                .map(|compiled| (compiled, p.as_str()))
+                .context(format!("Invalid collapse pattern: {}", p))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let key_for = |check: &Check| -> Option<String> {
+        if let Some((_, raw)) = patterns.iter().find(|(p, _)| p.matches(&check.name)) {
+            return Some(raw.to_string());
+        }
+        if rules.matrix {
+            return matrix_base_name(&check.name).map(str::to_string);
+        }
+        None
+    };
+
+    let mut groups: Vec<CheckGroup<'a>> = Vec::new();
+    for &check in checks {
+        let name = key_for(check).unwrap_or_else(|| check.name.clone());
+        match groups.iter_mut().find(|g| g.name == name) {
+            Some(group) => group.variants.push(check),
+            None => groups.push(CheckGroup {
+                name,
+                variants: vec![check],
+            }),
+        }
+    }
+
+    // A lone matrix variant isn't worth collapsing; show its full name.
+    for group in &mut groups {
+        if group.variants.len() == 1 {
+            group.name = group.variants[0].name.clone();
+        }
+    }
+    Ok(groups)
+}
+
+// GraphQL response structures for the status check rollup
+#[derive(Deserialize)]
+struct RollupResponse {
+    data: Option<RollupData>,
+    errors: Option<Vec<GraphQLError>>,
+}
+
+#[derive(Deserialize)]
+struct GraphQLError {
+    message: String,
+}
+
+#[derive(Deserialize)]
+struct RollupData {
+    repository: Option<RollupRepository>,
+}
+
+#[derive(Deserialize)]
+struct RollupRepository {
+    #[serde(rename = "pullRequest")]
+    pull_request: Option<RollupPullRequest>,
+}
+
+#[derive(Deserialize)]
+struct RollupPullRequest {
+    commits: RollupCommits,
+}
+
+#[derive(Deserialize)]
+struct RollupCommits {
+    nodes: Vec<RollupCommitNode>,
+}
+
+#[derive(Deserialize)]
+struct RollupCommitNode {
+    commit: RollupCommit,
+}
+
+#[derive(Deserialize)]
+struct RollupCommit {
+    // Only requested by the history query
+    #[serde(default)]
+    oid: String,
+    #[serde(default, rename = "abbreviatedOid")]
+    abbreviated_oid: String,
+    #[serde(default, rename = "messageHeadline")]
+    message_headline: String,
+    #[serde(rename = "statusCheckRollup")]
+    status_check_rollup: Option<StatusCheckRollup>,
+}
+
+#[derive(Deserialize)]
+struct StatusCheckRollup {
+    contexts: RollupContexts,
+}
+
+#[derive(Deserialize)]
+struct RollupContexts {
+    nodes: Vec<RollupContextNode>,
+    #[serde(rename = "pageInfo")]
+    page_info: PageInfo,
+}
+
+#[derive(Deserialize)]
+struct PageInfo {
+    #[serde(rename = "hasNextPage")]
+    has_next_page: bool,
+    #[serde(rename = "endCursor")]
+    end_cursor: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "__typename")]
+enum RollupContextNode {
+    CheckRun {
+        name: String,
+        status: String,
+        conclusion: Option<String>,
+        #[serde(rename = "detailsUrl")]
+        details_url: Option<String>,
+        #[serde(rename = "startedAt")]
+        started_at: Option<String>,
+        #[serde(rename = "checkSuite")]
+        check_suite: Option<RollupCheckSuite>,
+    },
+    StatusContext {
+        context: String,
+        state: String,
+        #[serde(rename = "targetUrl")]
+        target_url: Option<String>,
+        description: Option<String>,
+        #[serde(rename = "createdAt")]
+        created_at: Option<String>,
+        creator: Option<RollupActor>,
+    },
+}
+
+#[derive(Deserialize)]
+struct RollupCheckSuite {
+    app: Option<RollupApp>,
+}
+
+#[derive(Deserialize)]
+struct RollupApp {
+    slug: String,
+}
+
+#[derive(Deserialize)]
+struct RollupActor {
+    login: String,
+}
+
+impl RollupContextNode {
+    fn into_check(self) -> Check {
+        match self {
+            RollupContextNode::CheckRun {
+                name,
+                status,
+                conclusion,
+                details_url,
+                started_at,
+                check_suite,
+            } => Check {
+                status: CheckStatus::from_bucket(check_run_bucket(&status, conclusion.as_deref())),
+                name,
+                url: details_url.filter(|u| !u.is_empty()),
+                source: CheckSource::CheckRun,
+                started_at,
+                attempts: 1,
+                app: check_suite.and_then(|s| s.app).map(|a| a.slug),
+            },
+            RollupContextNode::StatusContext {
+                context,
+                state,
+                target_url,
+                description,
+                created_at,
+                creator,
+            } => Check {
+                status: classify_check(status_context_bucket(&state), description.as_deref()),
+                name: context,
+                url: target_url.filter(|u| !u.is_empty()),
+                source: CheckSource::CommitStatus,
+                started_at: created_at,
+                attempts: 1,
+                app: creator.map(|c| c.login.trim_end_matches("[bot]").to_string()),
+            },
+        }
+    }
+}
+
+/// GraphQL query for the head commit's status check rollup (loaded from graphql/operation/).
+const FETCH_CHECK_ROLLUP_QUERY: &str =
+    include_str!("../graphql/operation/fetch_check_rollup.graphql");
+
+/// Fetch check runs and commit statuses from the head commit's status rollup.
+fn fetch_checks_from_rollup(owner: &str, repo: &str, pr_number: u64) -> Result<Vec<Check>> {
+    let mut checks = Vec::new();
+    let mut cursor: Option<String> = None;
+
+    loop {
+        let mut variables = json!({ "owner": owner, "repo": repo, "pr": pr_number });
+        if let Some(c) = &cursor {
+            variables["cursor"] = json!(c);
+        }
+
+        let output = gh_graphql(FETCH_CHECK_ROLLUP_QUERY, variables)
+            .context("Failed to run 'gh api graphql' for checks")?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            anyhow::bail!("GraphQL query failed: {}", stderr.trim());
+        }
+
+        let response: RollupResponse = serde_json::from_slice(&output.stdout)
+            .context("Failed to parse GraphQL response")?;
+
+        if let Some(errors) = response.errors {
+            let messages: Vec<_> = errors.iter().map(|e| e.message.as_str()).collect();
+            anyhow::bail!("GraphQL errors: {}", messages.join(", "));
+        }
+
+        let commit = response
+            .data
+            .and_then(|d| d.repository)
+            .and_then(|r| r.pull_request)
+            .and_then(|pr| pr.commits.nodes.into_iter().next())
+            .ok_or_else(|| anyhow::anyhow!("PR not found or has no commits"))?;
+
+        // No rollup means nothing has reported on the head commit yet.
+        let Some(rollup) = commit.commit.status_check_rollup else {
+            break;
+        };
+
+        checks.extend(rollup.contexts.nodes.into_iter().map(RollupContextNode::into_check));
+
+        if !rollup.contexts.page_info.has_next_page {
+            break;
+        }
+        cursor = rollup.contexts.page_info.end_cursor;
+    }
+
+    Ok(checks)
+}
+
+/// GraphQL query for per-commit check history (loaded from graphql/operation/).
+const FETCH_CHECK_HISTORY_QUERY: &str =
+    include_str!("../graphql/operation/fetch_check_history.graphql");
+
+/// Fetch the status rollup of each of the PR's last `count` commits. Only the
+/// first 100 contexts per commit are read.
+fn fetch_check_history_from_graphql(
+    owner: &str,
+    repo: &str,
+    pr_number: u64,
+    count: u64,
+) -> Result<Vec<CommitChecks>> {
+    let variables = json!({ "owner": owner, "repo": repo, "pr": pr_number, "count": count.min(100) });
+    let output = gh_graphql(FETCH_CHECK_HISTORY_QUERY, variables)
+        .context("Failed to run 'gh api graphql' for check history")?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        anyhow::bail!("GraphQL query failed: {}", stderr.trim());
+    }
+
+    let response: RollupResponse = serde_json::from_slice(&output.stdout)
+        .context("Failed to parse GraphQL response")?;
+
+    if let Some(errors) = response.errors {
+        let messages: Vec<_> = errors.iter().map(|e| e.message.as_str()).collect();
+        anyhow::bail!("GraphQL errors: {}", messages.join(", "));
+    }
+
+    let commits = response
+        .data
+        .and_then(|d| d.repository)
+        .and_then(|r| r.pull_request)
+        .map(|pr| pr.commits.nodes)
+        .ok_or_else(|| anyhow::anyhow!("PR not found or no access"))?;
+
+    Ok(commits
+        .into_iter()
+        .map(|node| {
+            let commit = node.commit;
+            let checks = commit
+                .status_check_rollup
+                .map(|r| r.contexts.nodes.into_iter().map(RollupContextNode::into_check).collect())
+                .unwrap_or_default();
+            CommitChecks {
+                sha: commit.oid,
+                abbreviated_sha: commit.abbreviated_oid,
+                headline: commit.message_headline,
+                checks: collapse_reruns(merge_checks(checks)),
+            }
+        })
+        .collect())
+}
+
+/// GraphQL query for the base branch's recent check results (loaded from graphql/operation/).
+const FETCH_BASE_CHECKS_QUERY: &str =
+    include_str!("../graphql/operation/fetch_base_checks.graphql");
+
+#[derive(Deserialize)]
+struct BaseChecksResponse {
+    data: Option<BaseChecksData>,
+    errors: Option<Vec<GraphQLError>>,
+}
+
+#[derive(Deserialize)]
+struct BaseChecksData {
+    repository: Option<BaseChecksRepository>,
+}
+
+#[derive(Deserialize)]
+struct BaseChecksRepository {
+    #[serde(rename = "pullRequest")]
+    pull_request: Option<BaseChecksPullRequest>,
+}
+
+#[derive(Deserialize)]
+struct BaseChecksPullRequest {
+    #[serde(rename = "baseRefName")]
+    base_ref_name: String,
+    /// None if the base branch was deleted.
+    #[serde(rename = "baseRef")]
+    base_ref: Option<BaseRef>,
+}
+
+#[derive(Deserialize)]
+struct BaseRef {
+    target: Option<BaseRefTarget>,
+}
+
+#[derive(Deserialize)]
+struct BaseRefTarget {
+    /// Absent when the ref doesn't point at a commit.
+    history: Option<BaseHistory>,
+}
+
+#[derive(Deserialize)]
+struct BaseHistory {
+    nodes: Vec<RollupCommit>,
+}
+
+/// Fetch the status rollups of the base branch's latest commits and keep
+/// the newest finished result of each check.
+fn fetch_base_checks_from_graphql(owner: &str, repo: &str, pr_number: u64) -> Result<BaseChecks> {
+    let variables =
+        json!({ "owner": owner, "repo": repo, "pr": pr_number, "count": BASE_HISTORY_COMMITS });
+    let output = gh_graphql(FETCH_BASE_CHECKS_QUERY, variables)
+        .context("Failed to run 'gh api graphql' for base branch checks")?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        anyhow::bail!("GraphQL query failed: {}", stderr.trim());
+    }
+
+    let response: BaseChecksResponse = serde_json::from_slice(&output.stdout)
+        .context("Failed to parse GraphQL response")?;
+
+    if let Some(errors) = response.errors {
+        let messages: Vec<_> = errors.iter().map(|e| e.message.as_str()).collect();
+        anyhow::bail!("GraphQL errors: {}", messages.join(", "));
+    }
+
+    let pr = response
+        .data
+        .and_then(|d| d.repository)
+        .and_then(|r| r.pull_request)
+        .ok_or_else(|| anyhow::anyhow!("PR not found or no access"))?;
+    let commits: Vec<Vec<Check>> = pr
+        .base_ref
+        .and_then(|r| r.target)
+        .and_then(|t| t.history)
+        .map(|h| h.nodes)
+        .unwrap_or_default()
+        .into_iter()
+        .map(|commit| {
+            let checks = commit
+                .status_check_rollup
+                .map(|r| r.contexts.nodes.into_iter().map(RollupContextNode::into_check).collect())
+                .unwrap_or_default();
+            collapse_reruns(merge_checks(checks))
+        })
+        .collect();
+
+    Ok(BaseChecks {
+        branch: pr.base_ref_name,
+        checks: latest_finished(commits),
+    })
+}
+
+/// The newest passing or failing result of each check, given each commit's
+/// checks newest commit first.
+fn latest_finished(commits: Vec<Vec<Check>>) -> Vec<Check> {
+    let mut latest: Vec<Check> = Vec::new();
+    for check in commits.into_iter().flatten() {
+        let finished = matches!(check.status, CheckStatus::Pass | CheckStatus::Fail);
+        if finished && !latest.iter().any(|c| c.name == check.name) {
+            latest.push(check);
+        }
+    }
+    latest
+}
+
+/// GraphQL query for which checks are required (loaded from graphql/operation/).
+const FETCH_REQUIRED_CHECKS_QUERY: &str =
+    include_str!("../graphql/operation/fetch_required_checks.graphql");
+
+#[derive(Deserialize)]
+#[serde(tag = "__typename")]
+enum RequiredContextNode {
+    CheckRun {
+        name: String,
+        #[serde(rename = "isRequired")]
+        is_required: bool,
+    },
+    StatusContext {
+        context: String,
+        #[serde(rename = "isRequired")]
+        is_required: bool,
+    },
+}
+
+/// Fetch the names of the head commit's required checks. Only the first 100
+/// contexts are read.
+fn fetch_required_checks_from_graphql(owner: &str, repo: &str, pr_number: u64) -> Result<Vec<String>> {
+    let variables = json!({ "owner": owner, "repo": repo, "pr": pr_number });
+    let output = gh_graphql(FETCH_REQUIRED_CHECKS_QUERY, variables)
+        .context("Failed to run 'gh api graphql' for required checks")?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        anyhow::bail!("GraphQL query failed: {}", stderr.trim());
+    }
+
+    let response: serde_json::Value = serde_json::from_slice(&output.stdout)
+        .context("Failed to parse GraphQL response")?;
+    if let Some(errors) = response.get("errors") {
+        anyhow::bail!("GraphQL errors: {}", errors);
+    }
+    let nodes = response
+        .pointer("/data/repository/pullRequest/commits/nodes/0/commit/statusCheckRollup/contexts/nodes")
+        .cloned()
+        .unwrap_or_default();
+    let nodes: Vec<RequiredContextNode> = match nodes {
+        serde_json::Value::Null => vec![],
+        nodes => serde_json::from_value(nodes).context("Failed to parse required checks")?,
+    };
+    Ok(nodes
+        .into_iter()
+        .filter_map(|node| match node {
+            RequiredContextNode::CheckRun { name, is_required: true } => Some(name),
+            RequiredContextNode::StatusContext { context, is_required: true } => Some(context),
+            _ => None,
+        })
+        .collect())
+}
+
 #[derive(Deserialize)]
 struct GhCheck {
     name: String,
     bucket: String,
     link: Option<String>,
     description: Option<String>,
+    #[serde(rename = "startedAt")]
+    started_at: Option<String>,
 }
 
 /// Fetch checks using `gh pr checks --json`.
 fn fetch_checks_from_gh(owner: &str, repo: &str, pr_number: u64) -> Result<Vec<Check>> {
-    let output = Command::new("gh")
-        .args([
-            "pr",
-            "checks",
-            &pr_number.to_string(),
-            "--repo",
-            &format!("{}/{}", owner, repo),
-            "--json",
-            "name,bucket,link,description",
-        ])
-        .output()
-        .context("Failed to run 'gh pr checks'")?;
+    let output = retry::gh(|| {
+        Command::new("gh")
+            .args([
+                "pr",
+                "checks",
+                &pr_number.to_string(),
+                "--repo",
+                &format!("{}/{}", owner, repo),
+                "--json",
+                "name,bucket,link,description,startedAt",
+            ])
+            .traced_output()
+    })
+    .context("Failed to run 'gh pr checks'")?;
 
     if !output.status.success() {
         let stderr = String::from_utf8_lossy(&output.stderr);
@@ -129,7 +917,12 @@ fn fetch_checks_from_gh(owner: &str, repo: &str, pr_number: u64) -> Result<Vec<C
         .map(|c| Check {
             status: classify_check(&c.bucket, c.description.as_deref()),
             name: c.name,
-            url: c.link,
+            url: c.link.filter(|u| !u.is_empty()),
+            source: CheckSource::Unknown,
+            // gh reports a zero timestamp for checks that haven't started.
+            started_at: c.started_at.filter(|t| !t.is_empty() && !t.starts_with("0001-")),
+            attempts: 1,
+            app: None,
         })
         .collect())
 }
@@ -169,18 +962,76 @@ pub fn filter_checks(
         .collect())
 }
 
+/// Drop checks created by any of the given GitHub Apps (case-insensitive
+/// slug match). More robust than name globs for apps that generate dynamic
+/// per-shard check names. Checks with unknown provenance are kept.
+pub fn filter_checks_by_app(checks: Vec<Check>, exclude_apps: &[String]) -> Vec<Check> {
+    if exclude_apps.is_empty() {
+        return checks;
+    }
+    checks
+        .into_iter()
+        .filter(|check| match &check.app {
+            Some(app) => !exclude_apps.iter().any(|e| e.eq_ignore_ascii_case(app)),
+            None => true,
+        })
+        .collect()
+}
+
+/// Add a pending placeholder for each `delayed` pattern that no check
+/// matches yet. Checks from a downstream `workflow_run` workflow only register
+/// once the upstream run finishes, so until then their absence must not read
+/// as "all checks passed".
+pub fn add_delayed_placeholders(mut checks: Vec<Check>, delayed: &[String]) -> Result<Vec<Check>> {
+    for pattern in delayed {
+        let compiled =
+            Pattern::new(pattern).context(format!("Invalid delayed check pattern: {}", pattern))?;
+        if !checks.iter().any(|c| compiled.matches(&c.name)) {
+            checks.push(Check {
+                name: pattern.clone(),
+                status: CheckStatus::Pending,
+                url: None,
+                source: CheckSource::Unknown,
+                started_at: None,
+                attempts: 1,
+                app: None,
+            });
+        }
+    }
+    Ok(checks)
+}
+
+/// Which checks to consider, from `--include-checks`, `--exclude-checks`,
+/// and `--exclude-apps` (or their config/env equivalents), plus the
+/// `delayed_checks` config that are expected to show up late.
+#[derive(Debug, Clone, Default)]
+pub struct CheckFilters {
+    pub include_patterns: Vec<String>,
+    pub exclude_patterns: Vec<String>,
+    pub exclude_apps: Vec<String>,
+    pub delayed_checks: Vec<String>,
+}
+
+impl CheckFilters {
+    /// Apply all filters to a list of checks, then fill in placeholders for
+    /// delayed checks that haven't registered.
+    pub fn apply(&self, checks: Vec<Check>) -> Result<Vec<Check>> {
+        let checks = filter_checks(checks, &self.include_patterns, &self.exclude_patterns)?;
+        let checks = filter_checks_by_app(checks, &self.exclude_apps);
+        add_delayed_placeholders(checks, &self.delayed_checks)
+    }
+}
+
 /// Fetch and filter checks for a PR.
 pub fn get_checks_summary(
     client: &dyn ChecksClient,
     owner: &str,
     repo: &str,
     pr_number: u64,
-    include_patterns: &[String],
-    exclude_patterns: &[String],
+    filters: &CheckFilters,
 ) -> Result<ChecksSummary> {
     let checks = client.fetch_checks(owner, repo, pr_number)?;
-    let filtered = filter_checks(checks, include_patterns, exclude_patterns)?;
-    Ok(ChecksSummary { checks: filtered })
+    Ok(ChecksSummary::new(filters.apply(checks)?))
 }
 
 #[cfg(test)]
@@ -203,6 +1054,27 @@ mod tests {
             name: name.to_string(),
             status,
             url: Some(format!("https://example.com/{}", name)),
+            source: CheckSource::CheckRun,
+            started_at: None,
+            attempts: 1,
+            app: None,
+        }
+    }
+
+    fn make_sourced_check(
+        name: &str,
+        status: CheckStatus,
+        url: Option<&str>,
+        source: CheckSource,
+    ) -> Check {
+        Check {
+            name: name.to_string(),
+            status,
+            url: url.map(str::to_string),
+            source,
+            started_at: None,
+            attempts: 1,
+            app: None,
         }
     }
 
@@ -350,9 +1222,339 @@ mod tests {
         };
 
         let summary =
-            get_checks_summary(&client, "owner", "repo", 1, &[], &[]).unwrap();
+            get_checks_summary(&client, "owner", "repo", 1, &CheckFilters::default()).unwrap();
         assert_eq!(summary.checks.len(), 3);
         assert_eq!(summary.failed().len(), 1);
         assert_eq!(summary.pending().len(), 1);
     }
+
+    #[test]
+    fn check_run_bucket_mapping() {
+        assert_eq!(check_run_bucket("IN_PROGRESS", None), "pending");
+        assert_eq!(check_run_bucket("QUEUED", None), "pending");
+        assert_eq!(check_run_bucket("COMPLETED", Some("SUCCESS")), "pass");
+        assert_eq!(check_run_bucket("COMPLETED", Some("NEUTRAL")), "pass");
+        assert_eq!(check_run_bucket("COMPLETED", Some("SKIPPED")), "skipping");
+        assert_eq!(check_run_bucket("COMPLETED", Some("CANCELLED")), "cancel");
+        assert_eq!(check_run_bucket("COMPLETED", Some("FAILURE")), "fail");
+        assert_eq!(check_run_bucket("COMPLETED", Some("TIMED_OUT")), "fail");
+        assert_eq!(check_run_bucket("COMPLETED", Some("STARTUP_FAILURE")), "fail");
+    }
+
+    #[test]
+    fn status_context_bucket_mapping() {
+        assert_eq!(status_context_bucket("SUCCESS"), "pass");
+        assert_eq!(status_context_bucket("FAILURE"), "fail");
+        assert_eq!(status_context_bucket("ERROR"), "fail");
+        assert_eq!(status_context_bucket("PENDING"), "pending");
+        assert_eq!(status_context_bucket("EXPECTED"), "pending");
+    }
+
+    #[test]
+    fn rollup_nodes_parse_with_provenance() {
+        let json = r#"[
+            {"__typename": "CheckRun", "name": "build", "status": "COMPLETED",
+             "conclusion": "FAILURE", "detailsUrl": "https://github.com/o/r/actions/runs/1/job/2"},
+            {"__typename": "StatusContext", "context": "ci/circleci: test", "state": "FAILURE",
+             "targetUrl": "", "description": "Your CircleCI tests were canceled"}
+        ]"#;
+        let nodes: Vec<RollupContextNode> = serde_json::from_str(json).unwrap();
+        let checks: Vec<Check> = nodes.into_iter().map(RollupContextNode::into_check).collect();
+
+        assert_eq!(checks[0].name, "build");
+        assert_eq!(checks[0].status, CheckStatus::Fail);
+        assert_eq!(checks[0].source, CheckSource::CheckRun);
+        assert!(checks[0].url.is_some());
+
+        assert_eq!(checks[1].name, "ci/circleci: test");
+        // CircleCI auto-cancel reclassification still applies to statuses.
+        assert_eq!(checks[1].status, CheckStatus::Pending);
+        assert_eq!(checks[1].source, CheckSource::CommitStatus);
+        // Empty URLs are treated as missing.
+        assert!(checks[1].url.is_none());
+    }
+
+    #[test]
+    fn merge_collapses_identical_entries() {
+        let merged = merge_checks(vec![
+            make_sourced_check("lint", CheckStatus::Pass, Some("https://x/1"), CheckSource::CheckRun),
+            make_sourced_check("lint", CheckStatus::Pass, Some("https://x/1"), CheckSource::CheckRun),
+            make_sourced_check("test", CheckStatus::Fail, Some("https://x/2"), CheckSource::CheckRun),
+        ]);
+        assert_eq!(merged.len(), 2);
+        assert_eq!(merged[0].name, "lint");
+        assert_eq!(merged[1].name, "test");
+    }
+
+    #[test]
+    fn merge_prefers_check_run_over_status_and_fills_url() {
+        let merged = merge_checks(vec![
+            make_sourced_check("deploy", CheckStatus::Fail, Some("https://status/1"), CheckSource::CommitStatus),
+            make_sourced_check("deploy", CheckStatus::Pending, None, CheckSource::CheckRun),
+        ]);
+        assert_eq!(merged.len(), 1);
+        assert_eq!(merged[0].source, CheckSource::CheckRun);
+        assert_eq!(merged[0].status, CheckStatus::Pending);
+        assert_eq!(merged[0].url.as_deref(), Some("https://status/1"));
+
+        // Same when the check run comes first.
+        let merged = merge_checks(vec![
+            make_sourced_check("deploy", CheckStatus::Pass, None, CheckSource::CheckRun),
+            make_sourced_check("deploy", CheckStatus::Pass, Some("https://status/1"), CheckSource::CommitStatus),
+        ]);
+        assert_eq!(merged.len(), 1);
+        assert_eq!(merged[0].source, CheckSource::CheckRun);
+        assert_eq!(merged[0].url.as_deref(), Some("https://status/1"));
+    }
+
+    #[test]
+    fn merge_keeps_distinct_same_named_check_runs() {
+        // Two check runs with the same name but different URLs (e.g. a
+        // workflow triggered by both `push` and `pull_request`) are separate jobs.
+        let merged = merge_checks(vec![
+            make_sourced_check("build", CheckStatus::Pass, Some("https://x/1"), CheckSource::CheckRun),
+            make_sourced_check("build", CheckStatus::Fail, Some("https://x/2"), CheckSource::CheckRun),
+        ]);
+        assert_eq!(merged.len(), 2);
+    }
+
+    fn make_attempt(name: &str, status: CheckStatus, started_at: Option<&str>) -> Check {
+        Check {
+            started_at: started_at.map(str::to_string),
+            ..make_check(name, status)
+        }
+    }
+
+    #[test]
+    fn latest_finished_skips_pending_runs() {
+        let newest = vec![
+            make_check("test", CheckStatus::Pending),
+            make_check("lint", CheckStatus::Pass),
+        ];
+        let older = vec![
+            make_check("test", CheckStatus::Fail),
+            make_check("lint", CheckStatus::Fail),
+            make_check("deploy", CheckStatus::Skipping),
+        ];
+        let latest = latest_finished(vec![newest, older]);
+        let summary: Vec<_> = latest.iter().map(|c| (c.name.as_str(), c.status.clone())).collect();
+        assert_eq!(
+            summary,
+            vec![("lint", CheckStatus::Pass), ("test", CheckStatus::Fail)]
+        );
+    }
+
+    #[test]
+    fn collapse_reruns_keeps_latest_attempt() {
+        let collapsed = collapse_reruns(vec![
+            make_attempt("test", CheckStatus::Fail, Some("2026-01-01T10:00:00Z")),
+            make_attempt("lint", CheckStatus::Pass, Some("2026-01-01T10:00:00Z")),
+            make_attempt("test", CheckStatus::Pending, Some("2026-01-01T11:00:00Z")),
+        ]);
+        assert_eq!(collapsed.len(), 2);
+        assert_eq!(collapsed[0].name, "test");
+        assert_eq!(collapsed[0].status, CheckStatus::Pending);
+        assert_eq!(collapsed[0].attempts, 2);
+        assert_eq!(collapsed[1].attempts, 1);
+    }
+
+    #[test]
+    fn collapse_reruns_ignores_list_order() {
+        // The newer attempt can be reported first.
+        let collapsed = collapse_reruns(vec![
+            make_attempt("test", CheckStatus::Pass, Some("2026-01-01T11:00:00Z")),
+            make_attempt("test", CheckStatus::Fail, Some("2026-01-01T10:00:00Z")),
+        ]);
+        assert_eq!(collapsed.len(), 1);
+        assert_eq!(collapsed[0].status, CheckStatus::Pass);
+        assert_eq!(collapsed[0].attempts, 2);
+    }
+
+    #[test]
+    fn collapse_reruns_treats_unstarted_as_newest() {
+        let collapsed = collapse_reruns(vec![
+            make_attempt("test", CheckStatus::Fail, Some("2026-01-01T10:00:00Z")),
+            make_attempt("test", CheckStatus::Pending, None),
+            make_attempt("test", CheckStatus::Fail, Some("2026-01-01T09:00:00Z")),
+        ]);
+        assert_eq!(collapsed.len(), 1);
+        assert_eq!(collapsed[0].status, CheckStatus::Pending);
+        assert_eq!(collapsed[0].attempts, 3);
+        assert_eq!(collapsed[0].display_name(), "test (attempt 3)");
+    }
+
+    #[test]
+    fn collapse_reruns_keeps_separate_workflow_runs_apart() {
+        let job = |status, started_at, url: &str| Check {
+            url: Some(url.to_string()),
+            ..make_attempt("build", status, Some(started_at))
+        };
+        let collapsed = collapse_reruns(vec![
+            // The `push` run, and the `pull_request` run re-run once
+            job(CheckStatus::Pass, "2026-01-01T10:00:00Z", "https://github.com/o/r/actions/runs/1/job/10"),
+            job(CheckStatus::Fail, "2026-01-01T10:00:00Z", "https://github.com/o/r/actions/runs/2/job/20"),
+            job(CheckStatus::Pending, "2026-01-01T11:00:00Z", "https://github.com/o/r/actions/runs/2/job/21"),
+        ]);
+        let summary: Vec<_> = collapsed.iter().map(|c| (c.display_name(), c.status.clone())).collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("build".to_string(), CheckStatus::Pass),
+                ("build (attempt 2)".to_string(), CheckStatus::Pending),
+            ]
+        );
+    }
+
+    #[test]
+    fn summary_new_collapses_reruns() {
+        let summary = ChecksSummary::new(vec![
+            make_attempt("test", CheckStatus::Fail, Some("2026-01-01T10:00:00Z")),
+            make_attempt("test", CheckStatus::Pass, Some("2026-01-01T11:00:00Z")),
+        ]);
+        assert!(summary.failed().is_empty());
+        assert_eq!(summary.checks.len(), 1);
+    }
+
+    fn make_app_check(name: &str, app: Option<&str>) -> Check {
+        Check {
+            app: app.map(str::to_string),
+            ..make_check(name, CheckStatus::Pass)
+        }
+    }
+
+    #[test]
+    fn filter_by_app_excludes_matching_apps() {
+        let checks = vec![
+            make_app_check("codecov/patch", Some("codecov")),
+            make_app_check("SonarCloud Code Analysis shard 3", Some("sonarqubecloud")),
+            make_app_check("build", Some("github-actions")),
+            make_app_check("legacy", None),
+        ];
+        let filtered = filter_checks_by_app(
+            checks,
+            &["Codecov".to_string(), "sonarqubecloud".to_string()],
+        );
+        let names: Vec<_> = filtered.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, vec!["build", "legacy"]);
+    }
+
+    #[test]
+    fn check_filters_apply_combines_globs_and_apps() {
+        let filters = CheckFilters {
+            include_patterns: vec![],
+            exclude_patterns: vec!["lint".to_string()],
+            exclude_apps: vec!["codecov".to_string()],
+            delayed_checks: vec![],
+        };
+        let filtered = filters
+            .apply(vec![
+                make_app_check("lint", Some("github-actions")),
+                make_app_check("codecov/project", Some("codecov")),
+                make_app_check("test", Some("github-actions")),
+            ])
+            .unwrap();
+        assert_eq!(filtered.len(), 1);
+        assert_eq!(filtered[0].name, "test");
+    }
+
+    #[test]
+    fn delayed_checks_pending_until_registered() {
+        let filters = CheckFilters {
+            delayed_checks: vec!["deploy-preview*".to_string(), "test".to_string()],
+            ..Default::default()
+        };
+        let filtered = filters
+            .apply(vec![make_app_check("test", Some("github-actions"))])
+            .unwrap();
+        assert_eq!(filtered.len(), 2);
+        assert_eq!(filtered[1].name, "deploy-preview*");
+        assert_eq!(filtered[1].status, CheckStatus::Pending);
+
+        // Once the downstream run registers, the real check replaces the placeholder
+        let filtered = filters
+            .apply(vec![
+                make_app_check("test", Some("github-actions")),
+                make_app_check("deploy-preview (web)", Some("github-actions")),
+            ])
+            .unwrap();
+        assert_eq!(filtered.len(), 2);
+        assert!(filtered.iter().all(|c| c.source != CheckSource::Unknown));
+    }
+
+    #[test]
+    fn rollup_nodes_parse_app() {
+        let json = r#"[
+            {"__typename": "CheckRun", "name": "codecov/patch", "status": "COMPLETED",
+             "conclusion": "FAILURE", "checkSuite": {"app": {"slug": "codecov"}}},
+            {"__typename": "StatusContext", "context": "ci/circleci: test", "state": "SUCCESS",
+             "creator": {"login": "circleci-app[bot]"}}
+        ]"#;
+        let nodes: Vec<RollupContextNode> = serde_json::from_str(json).unwrap();
+        let checks: Vec<Check> = nodes.into_iter().map(RollupContextNode::into_check).collect();
+        assert_eq!(checks[0].app.as_deref(), Some("codecov"));
+        assert_eq!(checks[1].app.as_deref(), Some("circleci-app"));
+    }
+
+    #[test]
+    fn matrix_base_name_strips_suffix() {
+        assert_eq!(matrix_base_name("test (ubuntu, 1.75, shard 3/8)"), Some("test"));
+        assert_eq!(matrix_base_name("build"), None);
+        assert_eq!(matrix_base_name("lint (fix)ed"), None);
+    }
+
+    #[test]
+    fn group_checks_collapses_matrix_variants() {
+        let checks = [
+            make_check("test (ubuntu, shard 1/3)", CheckStatus::Pass),
+            make_check("build", CheckStatus::Pass),
+            make_check("test (ubuntu, shard 2/3)", CheckStatus::Fail),
+            make_check("test (ubuntu, shard 3/3)", CheckStatus::Pending),
+            make_check("lint (clippy)", CheckStatus::Pass),
+        ];
+        let refs: Vec<&Check> = checks.iter().collect();
+        let groups = group_checks(&refs, &CollapseRules::default()).unwrap();
+
+        assert_eq!(groups.len(), 3);
+        assert_eq!(groups[0].name, "test");
+        assert!(groups[0].is_matrix());
+        assert_eq!(groups[0].status(), CheckStatus::Fail);
+        assert_eq!(
+            groups[0].label(),
+            "test [3 variants: 1 passed, 1 failed, 1 pending]"
+        );
+        assert_eq!(groups[0].with_status(&CheckStatus::Fail)[0].name, "test (ubuntu, shard 2/3)");
+        assert_eq!(groups[1].label(), "build");
+        // A lone variant keeps its full name.
+        assert_eq!(groups[2].name, "lint (clippy)");
+        assert!(!groups[2].is_matrix());
+    }
+
+    #[test]
+    fn group_checks_with_patterns_and_matrix_disabled() {
+        let checks = [
+            make_check("e2e-shard-1", CheckStatus::Pass),
+            make_check("e2e-shard-2", CheckStatus::Pass),
+            make_check("test (a)", CheckStatus::Pass),
+            make_check("test (b)", CheckStatus::Pass),
+        ];
+        let refs: Vec<&Check> = checks.iter().collect();
+        let rules = CollapseRules {
+            matrix: false,
+            patterns: vec!["e2e-shard-*".to_string()],
+        };
+        let groups = group_checks(&refs, &rules).unwrap();
+        let names: Vec<_> = groups.iter().map(|g| g.name.as_str()).collect();
+        assert_eq!(names, vec!["e2e-shard-*", "test (a)", "test (b)"]);
+        assert_eq!(groups[0].status(), CheckStatus::Pass);
+        assert_eq!(groups[0].label(), "e2e-shard-* [2 variants: 2 passed]");
+    }
+
+    #[test]
+    fn group_checks_rejects_invalid_pattern() {
+        let rules = CollapseRules {
+            matrix: true,
+            patterns: vec!["[".to_string()],
+        };
+        assert!(group_checks(&[], &rules).is_err());
+    }
 }
@@ -1,8 +1,16 @@
 // CircleCI API integration.
 // Fetches job details and step logs for failed CI checks.
 
+use crate::cache_hints::CacheSuspect;
+use crate::log_window::{LogBudget, LogWindow, HEAD_BYTES, TAIL_BYTES};
+use crate::retry::{self, RetryBudget};
+use crate::test_report::{self, MetadataItem, MAX_REPORT_ARTIFACTS, MAX_REPORT_BYTES};
+use crate::wait::{sleep_until_next_poll, CancelFile};
 use anyhow::{Context, Result};
 use serde::Deserialize;
+use std::io::Read;
+use std::time::{Duration, Instant};
+use yaml_rust2::YamlLoader;
 
 /// Parsed CircleCI job info from a status check URL.
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -132,6 +140,18 @@ pub struct StepAction {
 #[derive(Debug, Clone)]
 pub struct JobDetails {
     pub job_name: String,
+    /// ID of the workflow the job ran in.
+    pub workflow_id: String,
+    /// Number of containers the job ran on (1 unless `parallelism` is set).
+    pub parallelism: u32,
+    /// e.g. "medium" or "large"; None if not reported.
+    pub resource_class: Option<String>,
+    /// Wall-clock run time; None while the job is still running.
+    pub duration_ms: Option<u64>,
+    /// The job's page, where it can be re-run with SSH.
+    pub url: Option<String>,
+    /// The primary Docker image of a docker-executor job.
+    pub image: Option<String>,
     pub steps: Vec<JobStep>,
 }
 
@@ -149,22 +169,49 @@ pub struct FailedStepLog {
     pub step_name: String,
     pub output: String,
     pub error: String,
+    /// Which container of a parallel job failed, e.g. "container 3/8"
+    /// (counting from 1; CircleCI's node index is one less). None for jobs
+    /// without parallelism.
+    pub container: Option<String>,
+    /// Link to everything captured of the log (not truncated for display,
+    /// but still windowed if long), when uploaded with `--upload-logs`.
+    pub full_log_url: Option<String>,
 }
 
-/// A single test failure from CircleCI test metadata.
+/// A single test failure from CircleCI test metadata or a JUnit report.
 #[derive(Debug, Clone)]
 pub struct TestFailure {
     pub job_name: String,
     pub classname: String,
     pub test_name: String,
     pub message: String,
+    /// e.g. "tests/test_api.py:42", when the report or message says.
+    pub location: Option<String>,
+}
+
+/// Job-level facts about a failed CircleCI job, for a human stepping in.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FailedJob {
+    pub job_name: String,
+    pub resource_class: Option<String>,
+    pub duration_ms: Option<u64>,
+    pub url: Option<String>,
+    /// The primary Docker image, for reproducing in the job's environment.
+    pub image: Option<String>,
 }
 
 /// Combined failure info from a CircleCI job.
 #[derive(Debug, Clone, Default)]
 pub struct CircleCiFailureInfo {
+    pub jobs: Vec<FailedJob>,
     pub step_logs: Vec<FailedStepLog>,
     pub test_failures: Vec<TestFailure>,
+    /// Failed steps whose output couldn't be fetched, e.g.
+    /// "test / Run tests: CircleCI output API error: 500".
+    pub fetch_errors: Vec<String>,
+    /// GitHub Actions steps whose logs look like a corrupted dependency
+    /// cache, which `rerun --bust-caches` can clear.
+    pub cache_suspects: Vec<CacheSuspect>,
 }
 
 /// Trait for CircleCI API operations.
@@ -182,6 +229,35 @@ pub trait CircleCiClient {
 
     /// Fetch test failures from the v2 API test metadata endpoint.
     fn fetch_test_failures(&self, job_info: &CircleCiJobInfo) -> Result<Vec<RawTestFailure>>;
+
+    /// Fetch the job's XML artifacts (candidate JUnit reports) from the v2 API.
+    fn fetch_report_artifacts(&self, job_info: &CircleCiJobInfo) -> Result<Vec<String>>;
+
+    /// Re-run a workflow via the v2 API, optionally only its failed jobs.
+    /// Returns the ID of the new workflow.
+    fn rerun_workflow(&self, workflow_id: &str, from_failed: bool) -> Result<String>;
+
+    /// Fetch a workflow's name and status from the v2 API.
+    fn fetch_workflow(&self, workflow_id: &str) -> Result<Workflow>;
+}
+
+/// A CircleCI workflow and its status.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Workflow {
+    pub id: String,
+    pub name: String,
+    /// e.g. "running", "success", "failed", "failing", "canceled"
+    pub status: String,
+}
+
+impl Workflow {
+    /// Returns true once the workflow can no longer change outcome.
+    pub fn is_finished(&self) -> bool {
+        matches!(
+            self.status.as_str(),
+            "success" | "failed" | "error" | "canceled" | "not_run" | "unauthorized"
+        )
+    }
 }
 
 /// A test failure as returned from the CircleCI API (without job_name context).
@@ -190,26 +266,99 @@ pub struct RawTestFailure {
     pub classname: String,
     pub test_name: String,
     pub message: String,
+    pub location: Option<String>,
 }
 
 /// Real CircleCI client using reqwest.
 pub struct RealCircleCiClient {
     token: String,
+    log_budget: LogBudget,
+    retry_budget: RetryBudget,
 }
 
 impl RealCircleCiClient {
     pub fn new(token: String) -> Self {
-        Self { token }
+        Self {
+            token,
+            log_budget: LogBudget::new(DEFAULT_LOG_DOWNLOAD_CAP),
+            retry_budget: RetryBudget::new(DEFAULT_OUTPUT_RETRIES),
+        }
+    }
+
+    /// Share a step output download budget with other clients, so the cap
+    /// applies across every failed step of the analysis.
+    pub fn with_log_budget(mut self, budget: LogBudget) -> Self {
+        self.log_budget = budget;
+        self
+    }
+
+    /// Share the retries of step output fetches with other clients, so a
+    /// CircleCI outage costs the analysis a bounded amount of backoff.
+    pub fn with_retry_budget(mut self, budget: RetryBudget) -> Self {
+        self.retry_budget = budget;
+        self
+    }
+
+    /// Stream one raw output stream through a head/tail window. Missing
+    /// output (a step that wrote nothing to it) reads as empty.
+    fn fetch_raw_output(&self, client: &reqwest::blocking::Client, url: &str) -> Result<String> {
+        let response = retry::http_within(&self.retry_budget, || {
+            client.get(url).header("Circle-Token", &self.token).send()
+        })
+        .context("Failed to send request to CircleCI output API")?;
+        if response.status() == 404 {
+            return Ok(String::new());
+        }
+        if !response.status().is_success() {
+            anyhow::bail!("CircleCI output API error: {}", response.status());
+        }
+        let mut window = LogWindow::new(HEAD_BYTES, TAIL_BYTES);
+        window.read_from(response, &self.log_budget);
+        Ok(window.finish())
     }
 }
 
+/// Step output downloaded per analysis when not configured (64 MB).
+pub const DEFAULT_LOG_DOWNLOAD_CAP: u64 = 64 * 1024 * 1024;
+
+/// Retries of step output fetches per analysis.
+pub const DEFAULT_OUTPUT_RETRIES: u32 = 6;
+
 // Response types for JSON deserialization
 #[derive(Deserialize)]
 struct JobDetailsResponse {
+    #[serde(default = "default_parallel")]
+    parallel: u32,
+    build_url: Option<String>,
+    build_time_millis: Option<u64>,
+    picard: Option<PicardResponse>,
+    circle_yml: Option<CircleYmlResponse>,
     steps: Vec<StepResponse>,
     workflows: WorkflowsResponse,
 }
 
+/// The project's config as compiled for this pipeline (orbs, executors and
+/// parameters expanded).
+#[derive(Deserialize)]
+struct CircleYmlResponse {
+    string: String,
+}
+
+/// Executor details; only present for jobs run on CircleCI 2.0+.
+#[derive(Deserialize)]
+struct PicardResponse {
+    resource_class: Option<ResourceClassResponse>,
+}
+
+#[derive(Deserialize)]
+struct ResourceClassResponse {
+    class: String,
+}
+
+fn default_parallel() -> u32 {
+    1
+}
+
 #[derive(Deserialize)]
 struct StepResponse {
     name: String,
@@ -226,22 +375,37 @@ struct ActionResponse {
 #[derive(Deserialize)]
 struct WorkflowsResponse {
     job_name: String,
+    workflow_id: String,
 }
 
 // V2 API response types for test metadata
 #[derive(Deserialize)]
 struct TestsResponse {
-    items: Vec<TestItem>,
+    items: Vec<MetadataItem>,
     next_page_token: Option<String>,
 }
 
 #[derive(Deserialize)]
-struct TestItem {
-    classname: String,
+struct ArtifactsResponse {
+    items: Vec<ArtifactItem>,
+}
+
+#[derive(Deserialize)]
+struct ArtifactItem {
+    path: String,
+    url: String,
+}
+
+#[derive(Deserialize)]
+struct RerunResponse {
+    workflow_id: String,
+}
+
+#[derive(Deserialize)]
+struct WorkflowResponse {
+    id: String,
     name: String,
-    result: String,
-    #[serde(default)]
-    message: String,
+    status: String,
 }
 
 impl CircleCiClient for RealCircleCiClient {
@@ -255,12 +419,14 @@ impl CircleCiClient for RealCircleCiClient {
             job_info.job_number
         );
 
-        let response = client
-            .get(&url)
-            .header("Circle-Token", &self.token)
-            .header("Accept", "application/json")
-            .send()
-            .context("Failed to send request to CircleCI API")?;
+        let response = retry::http(|| {
+            client
+                .get(&url)
+                .header("Circle-Token", &self.token)
+                .header("Accept", "application/json")
+                .send()
+        })
+        .context("Failed to send request to CircleCI API")?;
 
         if response.status() == 404 {
             anyhow::bail!("Job not found: {}", job_info.job_number);
@@ -276,8 +442,21 @@ impl CircleCiClient for RealCircleCiClient {
             .json()
             .context("Failed to parse CircleCI job details")?;
 
+        let image = details
+            .circle_yml
+            .as_ref()
+            .and_then(|c| job_image(&c.string, &details.workflows.job_name));
         Ok(JobDetails {
             job_name: details.workflows.job_name,
+            workflow_id: details.workflows.workflow_id,
+            parallelism: details.parallel.max(1),
+            resource_class: details
+                .picard
+                .and_then(|p| p.resource_class)
+                .map(|r| r.class),
+            duration_ms: details.build_time_millis,
+            url: details.build_url,
+            image,
             steps: details
                 .steps
                 .into_iter()
@@ -310,23 +489,16 @@ impl CircleCiClient for RealCircleCiClient {
             job_info.job_number
         );
 
-        // Fetch stdout
-        let output_url = format!("{}/output/{}/{}", base, task_index, step_id);
-        let output = client
-            .get(&output_url)
-            .header("Circle-Token", &self.token)
-            .send()
-            .and_then(|r| r.text())
-            .unwrap_or_default();
-
-        // Fetch stderr
-        let error_url = format!("{}/error/{}/{}", base, task_index, step_id);
-        let error = client
-            .get(&error_url)
-            .header("Circle-Token", &self.token)
-            .send()
-            .and_then(|r| r.text())
-            .unwrap_or_default();
+        // Responses are gzip-compressed on the wire and streamed, so only
+        // the head/tail windows of huge steps are ever held in memory
+        let output = self.fetch_raw_output(
+            &client,
+            &format!("{}/output/{}/{}", base, task_index, step_id),
+        )?;
+        let error = self.fetch_raw_output(
+            &client,
+            &format!("{}/error/{}/{}", base, task_index, step_id),
+        )?;
 
         Ok(StepOutput { output, error })
     }
@@ -346,12 +518,14 @@ impl CircleCiClient for RealCircleCiClient {
                 url.push_str(&format!("?page-token={}", token));
             }
 
-            let response = client
-                .get(&url)
-                .header("Circle-Token", &self.token)
-                .header("Accept", "application/json")
-                .send()
-                .context("Failed to send request to CircleCI tests API")?;
+            let response = retry::http(|| {
+                client
+                    .get(&url)
+                    .header("Circle-Token", &self.token)
+                    .header("Accept", "application/json")
+                    .send()
+            })
+            .context("Failed to send request to CircleCI tests API")?;
 
             if response.status() == 404 {
                 // No test metadata for this job — not an error
@@ -365,15 +539,7 @@ impl CircleCiClient for RealCircleCiClient {
                 .json()
                 .context("Failed to parse CircleCI test metadata")?;
 
-            for item in tests.items {
-                if item.result == "failure" {
-                    failures.push(RawTestFailure {
-                        classname: item.classname,
-                        test_name: item.name,
-                        message: item.message,
-                    });
-                }
-            }
+            failures.extend(test_report::metadata_failures(tests.items));
 
             match tests.next_page_token {
                 Some(token) if !token.is_empty() => page_token = Some(token),
@@ -383,6 +549,162 @@ impl CircleCiClient for RealCircleCiClient {
 
         Ok(failures)
     }
+
+    fn fetch_report_artifacts(&self, job_info: &CircleCiJobInfo) -> Result<Vec<String>> {
+        let client = reqwest::blocking::Client::new();
+        let url = format!(
+            "https://circleci.com/api/v2/project/{}/{}/artifacts",
+            job_info.project_slug(),
+            job_info.job_number
+        );
+
+        let response = retry::http(|| {
+            client
+                .get(&url)
+                .header("Circle-Token", &self.token)
+                .header("Accept", "application/json")
+                .send()
+        })
+        .context("Failed to send request to CircleCI artifacts API")?;
+        if !response.status().is_success() {
+            anyhow::bail!("CircleCI artifacts API error: {}", response.status());
+        }
+        let artifacts: ArtifactsResponse = response
+            .json()
+            .context("Failed to parse CircleCI artifacts")?;
+
+        let mut reports = Vec::new();
+        for artifact in artifacts
+            .items
+            .iter()
+            .filter(|a| test_report::is_report_artifact(&a.path))
+            .take(MAX_REPORT_ARTIFACTS)
+        {
+            let Ok(response) = retry::http(|| client.get(&artifact.url).header("Circle-Token", &self.token).send()) else {
+                continue;
+            };
+            if !response.status().is_success()
+                || response.content_length().is_some_and(|len| len > MAX_REPORT_BYTES)
+            {
+                continue;
+            }
+            let mut xml = String::new();
+            if response.take(MAX_REPORT_BYTES).read_to_string(&mut xml).is_ok() {
+                reports.push(xml);
+            }
+        }
+        Ok(reports)
+    }
+
+    fn rerun_workflow(&self, workflow_id: &str, from_failed: bool) -> Result<String> {
+        let client = reqwest::blocking::Client::new();
+        let url = format!("https://circleci.com/api/v2/workflow/{}/rerun", workflow_id);
+
+        let response = client
+            .post(&url)
+            .header("Circle-Token", &self.token)
+            .header("Accept", "application/json")
+            .json(&serde_json::json!({ "from_failed": from_failed }))
+            .send()
+            .context("Failed to send rerun request to CircleCI API")?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().unwrap_or_default();
+            anyhow::bail!("CircleCI rerun API error: {} {}", status, body.trim());
+        }
+
+        let rerun: RerunResponse = response
+            .json()
+            .context("Failed to parse CircleCI rerun response")?;
+        Ok(rerun.workflow_id)
+    }
+
+    fn fetch_workflow(&self, workflow_id: &str) -> Result<Workflow> {
+        let client = reqwest::blocking::Client::new();
+        let url = format!("https://circleci.com/api/v2/workflow/{}", workflow_id);
+
+        let response = retry::http(|| {
+            client
+                .get(&url)
+                .header("Circle-Token", &self.token)
+                .header("Accept", "application/json")
+                .send()
+        })
+        .context("Failed to send request to CircleCI workflow API")?;
+
+        if !response.status().is_success() {
+            anyhow::bail!("CircleCI workflow API error: {}", response.status());
+        }
+
+        let workflow: WorkflowResponse = response
+            .json()
+            .context("Failed to parse CircleCI workflow")?;
+        Ok(Workflow {
+            id: workflow.id,
+            name: workflow.name,
+            status: workflow.status,
+        })
+    }
+}
+
+/// Re-run the workflows containing the given failed jobs, once per workflow.
+/// Returns (old workflow ID, new workflow ID) pairs.
+pub fn rerun_failed_workflows(
+    client: &dyn CircleCiClient,
+    jobs: &[CircleCiJobInfo],
+    from_failed: bool,
+) -> Result<Vec<(String, String)>> {
+    let mut reruns: Vec<(String, String)> = Vec::new();
+    for job in jobs {
+        let workflow_id = client.fetch_job_details(job)?.workflow_id;
+        if reruns.iter().any(|(old, _)| *old == workflow_id) {
+            continue;
+        }
+        let new_id = client.rerun_workflow(&workflow_id, from_failed)?;
+        reruns.push((workflow_id, new_id));
+    }
+    Ok(reruns)
+}
+
+/// How a wait for re-run workflows ended.
+#[derive(Debug, PartialEq, Eq)]
+pub enum WorkflowsOutcome {
+    /// Every workflow finished; in the order they were given.
+    Finished(Vec<Workflow>),
+    Timeout,
+    Cancelled,
+}
+
+/// Poll until every workflow has finished. Each (client, workflow ID) pair
+/// uses its own client, since tokens can be per-org. A failed fetch is
+/// reported and retried on the next poll.
+pub fn wait_for_workflows<C: CircleCiClient>(
+    workflows: &[(C, String)],
+    timeout: Duration,
+    poll_interval: Duration,
+    cancel: Option<&CancelFile>,
+) -> WorkflowsOutcome {
+    let start = Instant::now();
+    loop {
+        let mut finished = Vec::new();
+        for (client, id) in workflows {
+            match client.fetch_workflow(id) {
+                Ok(wf) if wf.is_finished() => finished.push(wf),
+                Ok(_) => {}
+                Err(e) => eprintln!("Warning: Failed to fetch workflow {}: {}", id, e),
+            }
+        }
+        if finished.len() == workflows.len() {
+            return WorkflowsOutcome::Finished(finished);
+        }
+        if start.elapsed() >= timeout {
+            return WorkflowsOutcome::Timeout;
+        }
+        if sleep_until_next_poll(poll_interval.min(timeout.saturating_sub(start.elapsed())), cancel) {
+            return WorkflowsOutcome::Cancelled;
+        }
+    }
 }
 
 /// Fetch failure info (step logs + test failures) for a job.
@@ -392,31 +714,40 @@ pub fn get_job_failures(
 ) -> Result<CircleCiFailureInfo> {
     let details = client.fetch_job_details(job_info)?;
 
+    // In a parallel job each step has one action per container; only the
+    // failing containers' output is fetched. One step's output failing to
+    // download doesn't keep the others from being shown.
     let mut step_logs = Vec::new();
+    let mut fetch_errors = Vec::new();
     for step in &details.steps {
         for action in &step.actions {
-            if action.failed {
-                let output = client.fetch_step_output(job_info, action.index, action.step)?;
-                step_logs.push(FailedStepLog {
+            if !action.failed {
+                continue;
+            }
+            let container = (details.parallelism > 1)
+                .then(|| format!("container {}/{}", action.index + 1, details.parallelism));
+            match client.fetch_step_output(job_info, action.index, action.step) {
+                Ok(output) => step_logs.push(FailedStepLog {
                     job_name: details.job_name.clone(),
                     step_name: step.name.clone(),
                     output: output.output,
                     error: output.error,
-                });
+                    container,
+                    full_log_url: None,
+                }),
+                Err(e) => {
+                    let step_label = match &container {
+                        Some(container) => format!("{} ({})", step.name, container),
+                        None => step.name.clone(),
+                    };
+                    fetch_errors.push(format!("{} / {}: {:#}", details.job_name, step_label, e));
+                }
             }
         }
     }
 
-    let test_failures = match client.fetch_test_failures(job_info) {
-        Ok(raw) => raw
-            .into_iter()
-            .map(|f| TestFailure {
-                job_name: details.job_name.clone(),
-                classname: f.classname,
-                test_name: f.test_name,
-                message: f.message,
-            })
-            .collect(),
+    let mut raw_failures = match client.fetch_test_failures(job_info) {
+        Ok(raw) => raw,
         Err(e) => {
             eprintln!(
                 "Warning: Failed to fetch test metadata for job {}: {}",
@@ -425,13 +756,56 @@ pub fn get_job_failures(
             vec![]
         }
     };
+    // Jobs without `store_test_results` may still upload their JUnit reports
+    if raw_failures.is_empty() && (!step_logs.is_empty() || !fetch_errors.is_empty()) {
+        match client.fetch_report_artifacts(job_info) {
+            Ok(reports) => {
+                for xml in reports {
+                    raw_failures.extend(test_report::junit_failures(&xml).unwrap_or_default());
+                }
+            }
+            Err(e) => eprintln!(
+                "Warning: Failed to fetch test report artifacts for job {}: {}",
+                details.job_name, e
+            ),
+        }
+    }
+    let test_failures = raw_failures
+        .into_iter()
+        .map(|f| TestFailure {
+            job_name: details.job_name.clone(),
+            classname: f.classname,
+            test_name: f.test_name,
+            message: f.message,
+            location: f.location,
+        })
+        .collect();
 
     Ok(CircleCiFailureInfo {
+        jobs: vec![FailedJob {
+            job_name: details.job_name,
+            resource_class: details.resource_class,
+            duration_ms: details.duration_ms,
+            url: details.url,
+            image: details.image,
+        }],
         step_logs,
         test_failures,
+        fetch_errors,
+        cache_suspects: vec![],
     })
 }
 
+/// The primary Docker image of `job_name` in a compiled CircleCI config
+/// (`jobs.<name>.docker[0].image`). None for machine or macOS executors.
+pub fn job_image(config_yaml: &str, job_name: &str) -> Option<String> {
+    let docs = YamlLoader::load_from_str(config_yaml).ok()?;
+    // Indexing a missing key yields `BadValue` rather than panicking
+    docs.first()?["jobs"][job_name]["docker"][0]["image"]
+        .as_str()
+        .map(String::from)
+}
+
 /// Check if a URL is a CircleCI URL.
 pub fn is_circleci_url(url: &str) -> bool {
     url.contains("circleci.com")
@@ -440,6 +814,7 @@ pub fn is_circleci_url(url: &str) -> bool {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::cell::RefCell;
 
     #[test]
     fn parse_classic_url() {
@@ -521,6 +896,8 @@ mod tests {
         pub job_details: Option<JobDetails>,
         pub step_outputs: Vec<StepOutput>,
         pub test_failures: Vec<RawTestFailure>,
+        pub report_artifacts: Vec<String>,
+        pub reruns: RefCell<Vec<(String, bool)>>,
     }
 
     impl CircleCiClient for TestCircleCiClient {
@@ -545,6 +922,25 @@ mod tests {
         fn fetch_test_failures(&self, _job_info: &CircleCiJobInfo) -> Result<Vec<RawTestFailure>> {
             Ok(self.test_failures.clone())
         }
+
+        fn fetch_report_artifacts(&self, _job_info: &CircleCiJobInfo) -> Result<Vec<String>> {
+            Ok(self.report_artifacts.clone())
+        }
+
+        fn rerun_workflow(&self, workflow_id: &str, from_failed: bool) -> Result<String> {
+            self.reruns
+                .borrow_mut()
+                .push((workflow_id.to_string(), from_failed));
+            Ok(format!("{}-rerun", workflow_id))
+        }
+
+        fn fetch_workflow(&self, workflow_id: &str) -> Result<Workflow> {
+            Ok(Workflow {
+                id: workflow_id.to_string(),
+                name: "build".to_string(),
+                status: "running".to_string(),
+            })
+        }
     }
 
     #[test]
@@ -552,6 +948,12 @@ mod tests {
         let client = TestCircleCiClient {
             job_details: Some(JobDetails {
                 job_name: "test-job".to_string(),
+                workflow_id: "wf-1".to_string(),
+                parallelism: 1,
+                resource_class: None,
+                duration_ms: None,
+                url: None,
+                image: None,
                 steps: vec![
                     JobStep {
                         name: "Checkout".to_string(),
@@ -582,6 +984,8 @@ mod tests {
                 },
             ],
             test_failures: vec![],
+            report_artifacts: vec![],
+            reruns: RefCell::default(),
         };
 
         let job_info = CircleCiJobInfo {
@@ -597,11 +1001,155 @@ mod tests {
         assert_eq!(info.step_logs[0].error, "test failed: assertion error");
     }
 
+    #[test]
+    fn get_job_failures_keeps_going_when_a_step_output_fails() {
+        let failed_step = |name: &str, index: u32| JobStep {
+            name: name.to_string(),
+            actions: vec![StepAction {
+                index,
+                step: 0,
+                failed: true,
+            }],
+        };
+        let client = TestCircleCiClient {
+            job_details: Some(JobDetails {
+                job_name: "test-job".to_string(),
+                workflow_id: "wf-1".to_string(),
+                parallelism: 1,
+                resource_class: None,
+                duration_ms: None,
+                url: None,
+                image: None,
+                // No output is configured for task 5, so its fetch fails
+                steps: vec![failed_step("Lint", 5), failed_step("Run tests", 0)],
+            }),
+            step_outputs: vec![StepOutput {
+                output: "test output".to_string(),
+                error: "".to_string(),
+            }],
+            test_failures: vec![],
+            report_artifacts: vec![],
+            reruns: RefCell::default(),
+        };
+        let job_info = CircleCiJobInfo {
+            vcs: "gh".to_string(),
+            owner: "owner".to_string(),
+            repo: "repo".to_string(),
+            job_number: 123,
+        };
+
+        let info = get_job_failures(&client, &job_info).unwrap();
+        assert_eq!(info.step_logs.len(), 1);
+        assert_eq!(info.step_logs[0].step_name, "Run tests");
+        assert_eq!(info.fetch_errors, vec!["test-job / Lint: No step output configured"]);
+    }
+
+    #[test]
+    fn job_details_response_parses_metadata() {
+        let json = r#"{
+            "parallel": 4,
+            "build_url": "https://circleci.com/gh/owner/repo/123",
+            "build_time_millis": 252000,
+            "picard": {"executor": "docker", "resource_class": {"class": "large", "cpu": 4}},
+            "steps": [],
+            "workflows": {"job_name": "test", "workflow_id": "wf-1"}
+        }"#;
+        let parsed: JobDetailsResponse = serde_json::from_str(json).unwrap();
+        assert_eq!(parsed.parallel, 4);
+        assert_eq!(parsed.build_time_millis, Some(252000));
+        assert_eq!(parsed.picard.and_then(|p| p.resource_class).unwrap().class, "large");
+
+        // Older or still-running jobs omit most of it
+        let json = r#"{"steps": [], "workflows": {"job_name": "test", "workflow_id": "wf-1"}}"#;
+        let parsed: JobDetailsResponse = serde_json::from_str(json).unwrap();
+        assert_eq!(parsed.parallel, 1);
+        assert!(parsed.picard.is_none() && parsed.build_url.is_none());
+    }
+
+    #[test]
+    fn job_image_reads_primary_container() {
+        let config = r#"
+version: 2
+jobs:
+  test:
+    docker:
+      - image: cimg/rust:1.80
+      - image: cimg/postgres:16.1
+    steps: [checkout]
+  integration:
+    machine:
+      image: ubuntu-2204:current
+"#;
+        assert_eq!(job_image(config, "test").as_deref(), Some("cimg/rust:1.80"));
+        assert_eq!(job_image(config, "integration"), None);
+        assert_eq!(job_image(config, "missing"), None);
+        assert_eq!(job_image("not: [valid", "test"), None);
+    }
+
+    #[test]
+    fn get_job_failures_labels_failing_container() {
+        let action = |index, failed| StepAction {
+            index,
+            step: 100,
+            failed,
+        };
+        let client = TestCircleCiClient {
+            job_details: Some(JobDetails {
+                job_name: "test-job".to_string(),
+                workflow_id: "wf-1".to_string(),
+                parallelism: 3,
+                resource_class: None,
+                duration_ms: None,
+                url: None,
+                image: None,
+                steps: vec![JobStep {
+                    name: "Run tests".to_string(),
+                    actions: vec![action(0, false), action(1, false), action(2, true)],
+                }],
+            }),
+            step_outputs: vec![
+                StepOutput {
+                    output: "container 0".to_string(),
+                    error: "".to_string(),
+                },
+                StepOutput {
+                    output: "container 1".to_string(),
+                    error: "".to_string(),
+                },
+                StepOutput {
+                    output: "container 2".to_string(),
+                    error: "".to_string(),
+                },
+            ],
+            test_failures: vec![],
+            report_artifacts: vec![],
+            reruns: RefCell::default(),
+        };
+
+        let job_info = CircleCiJobInfo {
+            vcs: "gh".to_string(),
+            owner: "owner".to_string(),
+            repo: "repo".to_string(),
+            job_number: 123,
+        };
+
+        let info = get_job_failures(&client, &job_info).unwrap();
+        assert_eq!(info.step_logs.len(), 1);
+        assert_eq!(info.step_logs[0].output, "container 2");
+        assert_eq!(info.step_logs[0].container.as_deref(), Some("container 3/3"));
+    }
+
     #[test]
     fn get_job_failures_empty_when_all_pass() {
         let client = TestCircleCiClient {
             job_details: Some(JobDetails {
                 job_name: "test-job".to_string(),
+                workflow_id: "wf-1".to_string(),
+                parallelism: 1,
+                resource_class: None,
+                duration_ms: None,
+                url: None,
+                image: None,
                 steps: vec![JobStep {
                     name: "Checkout".to_string(),
                     actions: vec![StepAction {
@@ -613,6 +1161,8 @@ mod tests {
             }),
             step_outputs: vec![],
             test_failures: vec![],
+            report_artifacts: vec![],
+            reruns: RefCell::default(),
         };
 
         let job_info = CircleCiJobInfo {
@@ -632,6 +1182,12 @@ mod tests {
         let client = TestCircleCiClient {
             job_details: Some(JobDetails {
                 job_name: "test-job".to_string(),
+                workflow_id: "wf-1".to_string(),
+                parallelism: 1,
+                resource_class: None,
+                duration_ms: None,
+                url: None,
+                image: None,
                 steps: vec![JobStep {
                     name: "Run tests".to_string(),
                     actions: vec![StepAction {
@@ -650,13 +1206,17 @@ mod tests {
                     classname: "com.example.MyTest".to_string(),
                     test_name: "should work".to_string(),
                     message: "expected true but was false".to_string(),
+                    location: None,
                 },
                 RawTestFailure {
                     classname: "com.example.OtherTest".to_string(),
                     test_name: "should also work".to_string(),
                     message: "timeout".to_string(),
+                    location: None,
                 },
             ],
+            report_artifacts: vec![],
+            reruns: RefCell::default(),
         };
 
         let job_info = CircleCiJobInfo {
@@ -675,4 +1235,113 @@ mod tests {
         assert_eq!(info.test_failures[0].message, "expected true but was false");
         assert_eq!(info.test_failures[1].classname, "com.example.OtherTest");
     }
+
+    #[test]
+    fn get_job_failures_reads_junit_artifacts_without_metadata() {
+        let client = TestCircleCiClient {
+            job_details: Some(JobDetails {
+                job_name: "test-job".to_string(),
+                workflow_id: "wf-1".to_string(),
+                parallelism: 1,
+                resource_class: None,
+                duration_ms: None,
+                url: None,
+                image: None,
+                steps: vec![JobStep {
+                    name: "Run tests".to_string(),
+                    actions: vec![StepAction { index: 0, step: 0, failed: true }],
+                }],
+            }),
+            step_outputs: vec![StepOutput {
+                output: "....F....".to_string(),
+                error: String::new(),
+            }],
+            test_failures: vec![],
+            report_artifacts: vec![
+                "<coverage/>".to_string(),
+                r#"<testsuite><testcase classname="a" name="b"><failure message="boom"/></testcase></testsuite>"#
+                    .to_string(),
+            ],
+            reruns: RefCell::default(),
+        };
+        let job_info = CircleCiJobInfo {
+            vcs: "gh".to_string(),
+            owner: "owner".to_string(),
+            repo: "repo".to_string(),
+            job_number: 123,
+        };
+
+        let info = get_job_failures(&client, &job_info).unwrap();
+        assert_eq!(info.test_failures.len(), 1);
+        assert_eq!(info.test_failures[0].job_name, "test-job");
+        assert_eq!(info.test_failures[0].message, "boom");
+    }
+
+    #[test]
+    fn rerun_failed_workflows_dedupes_by_workflow() {
+        let client = TestCircleCiClient {
+            job_details: Some(JobDetails {
+                job_name: "test".to_string(),
+                workflow_id: "wf-1".to_string(),
+                parallelism: 1,
+                resource_class: None,
+                duration_ms: None,
+                url: None,
+                image: None,
+                steps: vec![],
+            }),
+            step_outputs: vec![],
+            test_failures: vec![],
+            report_artifacts: vec![],
+            reruns: RefCell::default(),
+        };
+        let job = |n| CircleCiJobInfo {
+            vcs: "gh".to_string(),
+            owner: "o".to_string(),
+            repo: "r".to_string(),
+            job_number: n,
+        };
+        let reruns = rerun_failed_workflows(&client, &[job(1), job(2)], true).unwrap();
+        assert_eq!(reruns, vec![("wf-1".to_string(), "wf-1-rerun".to_string())]);
+        assert_eq!(*client.reruns.borrow(), vec![("wf-1".to_string(), true)]);
+    }
+
+    #[test]
+    fn workflow_finished_statuses() {
+        let wf = |status: &str| Workflow {
+            id: "w".to_string(),
+            name: "build".to_string(),
+            status: status.to_string(),
+        };
+        assert!(wf("success").is_finished());
+        assert!(wf("failed").is_finished());
+        assert!(!wf("running").is_finished());
+        assert!(!wf("failing").is_finished());
+        assert!(!wf("on_hold").is_finished());
+    }
+
+    #[test]
+    fn waiting_for_workflows_stops_at_timeout_or_cancel() {
+        let client = || TestCircleCiClient {
+            job_details: None,
+            step_outputs: vec![],
+            test_failures: vec![],
+            report_artifacts: vec![],
+            reruns: RefCell::default(),
+        };
+        // The test client's workflows never finish
+        let workflows = vec![(client(), "wf-1-rerun".to_string())];
+        let outcome = wait_for_workflows(&workflows, Duration::ZERO, Duration::from_secs(60), None);
+        assert_eq!(outcome, WorkflowsOutcome::Timeout);
+
+        let dir = std::env::temp_dir().join(format!("pr-loop-rerun-cancel-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("cancel");
+        let cancel = CancelFile::new(path.clone());
+        std::fs::write(&path, "stop").unwrap();
+        let outcome = wait_for_workflows(&workflows, Duration::from_secs(600), Duration::from_secs(60), Some(&cancel));
+        assert_eq!(outcome, WorkflowsOutcome::Cancelled);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
 }
@@ -1,6 +1,10 @@
 // CLI argument parsing using clap.
 // Defines the command-line interface for pr-loop.
 
+use crate::context::OutputFormat;
+use crate::reply::IfResolved;
+use crate::threads::ReplyStatus;
+use crate::triage::ThreadOrder;
 use clap::{Parser, Subcommand};
 
 #[derive(Parser, Debug)]
@@ -24,6 +28,11 @@ pub struct Cli {
     #[arg(long = "exclude-checks", global = true, env = "PR_LOOP_EXCLUDE_CHECKS", value_delimiter = ',')]
     pub exclude_checks: Vec<String>,
 
+    /// GitHub App slug(s) whose checks should be ignored, e.g. `codecov,sonarqubecloud`
+    /// (can be repeated). Overrides config file's `[checks].exclude_apps`.
+    #[arg(long = "exclude-apps", global = true, env = "PR_LOOP_EXCLUDE_APPS", value_delimiter = ',')]
+    pub exclude_apps: Vec<String>,
+
     /// Wait until the PR becomes actionable (has comments needing response or CI failures)
     #[arg(long, conflicts_with = "wait_until_actionable_or_happy")]
     pub wait_until_actionable: bool,
@@ -33,6 +42,27 @@ pub struct Cli {
     #[arg(long, conflicts_with = "wait_until_actionable")]
     pub wait_until_actionable_or_happy: bool,
 
+    /// With --wait-until-actionable-or-happy, only report happy once every review thread is
+    /// resolved (the bar `ready` uses), not merely when none is awaiting a reply
+    #[arg(long, env = "PR_LOOP_STRICT_HAPPY")]
+    pub strict_happy: bool,
+
+    /// When --wait-until-actionable-or-happy ends happy, go straight on to `pr-loop ready`
+    /// (with all its checks). Implies --strict-happy, since `ready` needs every thread resolved.
+    #[arg(long, requires = "wait_until_actionable_or_happy")]
+    pub then_ready: bool,
+
+    /// While analyzing and waiting, delete resolved pure-Claude threads once their last
+    /// comment is this many minutes old (default 10), instead of leaving them all for
+    /// `clean-threads` or `ready`.
+    #[arg(long, value_name = "MINUTES", num_args = 0..=1, default_missing_value = "10", env = "PR_LOOP_AUTO_CLEAN")]
+    pub auto_clean: Option<u64>,
+
+    /// Don't treat a failing check as actionable when its latest run on the base branch
+    /// failed too (the base branch is broken, not the PR)
+    #[arg(long, env = "PR_LOOP_IGNORE_BASE_FAILURES")]
+    pub ignore_base_failures: bool,
+
     /// Timeout in seconds for wait modes (default: 1800 = 30 minutes)
     #[arg(long, default_value = "1800")]
     pub timeout: u64,
@@ -41,10 +71,93 @@ pub struct Cli {
     #[arg(long, default_value = "5")]
     pub poll_interval: u64,
 
-    /// Minimum seconds to wait after last push before considering PR "happy" (default: 30)
+    /// Once a wait mode sees new review comments, keep polling until no further comments
+    /// have arrived for this many seconds so the whole batch is handled at once (default: 0)
+    #[arg(long, default_value = "0", value_name = "SECONDS")]
+    pub comment_debounce: u64,
+
+    /// Before analyzing, wait until no new comments or check transitions have occurred
+    /// for this many seconds (e.g. while a reviewer is still leaving a batch of comments).
+    /// Combines with the other wait modes: applied after they return actionable.
+    #[arg(long, value_name = "SECONDS")]
+    pub wait_until_quiet: Option<u64>,
+
+    /// End a wait early (exit code 3) when this file appears or its contents change, so
+    /// an orchestrator can cancel without killing pr-loop. With --maintain-status, the
+    /// file's contents (if any) become the status message.
+    #[arg(long, value_name = "PATH", env = "PR_LOOP_CANCEL_FILE")]
+    pub cancel_file: Option<std::path::PathBuf>,
+
+    /// During waits, also listen on this address (e.g. 127.0.0.1:8787) for GitHub webhook
+    /// deliveries, and poll as soon as one concerns the PR instead of at the next tick.
+    /// Point a repository webhook or `gh webhook forward` at it, or pass --forward-webhooks.
+    #[arg(long, value_name = "ADDR", env = "PR_LOOP_LISTEN")]
+    pub listen: Option<String>,
+
+    /// With --listen, run `gh webhook forward` (the cli/gh-webhook extension) to have
+    /// GitHub deliver the repository's events to it through a temporary webhook.
+    #[arg(long, requires = "listen")]
+    pub forward_webhooks: bool,
+
+    /// The secret webhook deliveries to --listen are signed with; unsigned or
+    /// mis-signed deliveries are rejected. Also passed to --forward-webhooks.
+    #[arg(long, value_name = "SECRET", env = "PR_LOOP_WEBHOOK_SECRET", hide_env_values = true)]
+    pub webhook_secret: Option<String>,
+
+    /// When a wait mode ends, also print why as one JSON line on stdout (before the
+    /// analysis): the result plus the actionable thread IDs and failing checks.
+    #[arg(long, env = "PR_LOOP_WAIT_JSON")]
+    pub wait_json: bool,
+
+    /// After the analysis, list every thread and check with whether it counted and why
+    /// (resolved, snoozed, Claude replied last, excluded by a pattern, ...), and which
+    /// priority picked the recommendation.
+    #[arg(long, env = "PR_LOOP_EXPLAIN")]
+    pub explain: bool,
+
+    /// Format of the analysis: Markdown for reading, or one JSON document with the
+    /// next action, actionable threads, check statuses, and CI failure details.
+    #[arg(long, value_enum, default_value = "markdown")]
+    pub format: OutputFormat,
+
+    /// Minimum seconds to wait after last push before considering PR "happy" (default: 30).
+    /// Skipped when HEAD hasn't moved since a happy wait last saw the PR's checks at it:
+    /// nothing was pushed, so CI has nothing new to register.
     #[arg(long, default_value = "30")]
     pub min_wait_after_push: u64,
 
+    /// Outside the `[working_hours]` configured in the config file, idle until working hours
+    /// begin before analyzing, so the loop doesn't reply while no human can supervise.
+    #[arg(long)]
+    pub pause_outside_hours: bool,
+
+    /// Tag each actionable review thread as blocking, question, comment, or nit
+    /// (a heuristic based on keywords and CODEOWNERS).
+    #[arg(long, env = "PR_LOOP_TAG_THREADS")]
+    pub tag_threads: bool,
+
+    /// Order in which actionable review threads are listed. `urgency` lists blocking
+    /// feedback first and implies --tag-threads.
+    #[arg(long, value_enum, default_value = "posted", env = "PR_LOOP_THREAD_ORDER")]
+    pub thread_order: ThreadOrder,
+
+    /// Also publish the analysis as a "pr-loop analysis" check run on the PR head, with
+    /// annotations at unaddressed review threads. Needs a GitHub App token (e.g. GITHUB_TOKEN
+    /// in Actions).
+    #[arg(long, env = "PR_LOOP_PUBLISH_CHECK")]
+    pub publish_check: bool,
+
+    /// When failing CI logs are too long to show in full, upload everything captured of
+    /// them (long logs are downloaded as excerpts) to a secret gist and link to it.
+    #[arg(long, env = "PR_LOOP_UPLOAD_LOGS")]
+    pub upload_logs: bool,
+
+    /// Download the images attached to the review comments the analysis shows into this
+    /// directory (authenticating to GitHub with the gh token), and print each one's local
+    /// path next to its comment.
+    #[arg(long, value_name = "DIR", env = "PR_LOOP_DOWNLOAD_ATTACHMENTS")]
+    pub download_attachments: Option<std::path::PathBuf>,
+
     /// Maintain a status block in the PR description indicating LLM iteration is in progress.
     /// Requires the PR to be in draft mode.
     #[arg(long)]
@@ -55,6 +168,21 @@ pub struct Cli {
     #[arg(long)]
     pub status_message: Option<String>,
 
+    /// Version of the JSON output structure to produce (default: the latest). Lets
+    /// prompts and tools written against an older version keep working after an upgrade.
+    #[arg(
+        long,
+        global = true,
+        env = "PR_LOOP_OUTPUT_VERSION",
+        value_parser = clap::value_parser!(u32).range(1..=crate::context::SCHEMA_VERSION as i64)
+    )]
+    pub output_version: Option<u32>,
+
+    /// Print more detail about what pr-loop changes, such as a diff of each edit it
+    /// makes to the PR description.
+    #[arg(short, long, global = true)]
+    pub verbose: bool,
+
     #[command(subcommand)]
     pub command: Option<Command>,
 }
@@ -70,11 +198,72 @@ pub enum Command {
         #[arg(long)]
         in_reply_to: String,
 
+        /// The message to post (will be prefixed with "🤖 From Claude:")
+        #[arg(long, required_unless_present_any = ["template", "suggestion"], conflicts_with = "template")]
+        message: Option<String>,
+
+        /// Post a named reply template from the `[reply_templates]` config section
+        /// instead of --message.
+        #[arg(long, value_name = "NAME")]
+        template: Option<String>,
+
+        /// Value for a `{KEY}` placeholder in the template. Repeatable.
+        #[arg(long = "var", value_name = "KEY=VALUE", value_parser = parse_var, requires = "template")]
+        vars: Vec<(String, String)>,
+
+        /// Mark the reply as claiming the feedback is fixed (✅), asking the reviewer a
+        /// question (❓), or deferring to a follow-up (📝). Shown after the Claude marker
+        /// and used by the analysis to tell what the thread is waiting on.
+        #[arg(long, value_enum)]
+        status: Option<ReplyStatus>,
+
+        /// Start the reply with a quote of the comment being answered, trimmed to this
+        /// many lines (default 3), so it reads on its own in email notifications.
+        #[arg(long, value_name = "LINES", num_args = 0..=1, default_missing_value = "3")]
+        quote: Option<usize>,
+
+        /// What to do if a reviewer resolved the thread in the meantime: `skip` the reply,
+        /// or `post` it and leave the thread resolved.
+        #[arg(long, value_enum, default_value = "skip")]
+        if_resolved: IfResolved,
+
+        /// Suggest code in place of the lines the thread is on (its location in the
+        /// analysis), as a GitHub suggestion the reviewer can apply in one click. A
+        /// path to a file holding the code, or the code itself; empty suggests
+        /// deleting the lines.
+        #[arg(long, value_name = "FILE_OR_CODE")]
+        suggestion: Option<String>,
+    },
+
+    /// Post a top-level comment on the PR (not in a review thread), e.g. to announce
+    /// a force-push or a CI rerun. Prefixed with the Claude marker like replies.
+    Comment {
         /// The message to post (will be prefixed with "🤖 From Claude:")
         #[arg(long)]
         message: String,
     },
 
+    /// Post several replies at once, in order, each as `reply` would.
+    /// Reads a JSON list of `{"in_reply_to": ID, "message": TEXT}` objects (with
+    /// an optional `"status"`: fixed, question, or followup) and reports which
+    /// replies were posted. Exits 1 if any failed.
+    ReplyBatch {
+        /// JSON file with the replies; reads stdin when omitted or `-`.
+        file: Option<std::path::PathBuf>,
+    },
+
+    /// Resolve review threads, e.g. after the reviewer agreed a fix in a thread
+    /// Claude answered. Subject to `[policy] allow_resolve_human_threads`.
+    Resolve {
+        /// Review thread ID to resolve (as printed by the analysis). Repeatable.
+        #[arg(long = "thread", value_name = "ID", required_unless_present = "comments")]
+        threads: Vec<String>,
+
+        /// Resolve the thread containing this comment ID. Repeatable.
+        #[arg(long = "comment", value_name = "ID")]
+        comments: Vec<String>,
+    },
+
     /// Mark the PR as ready for review.
     /// Validates the PR is happy (CI passing, no unresolved threads), removes the status block,
     /// and marks the PR as non-draft.
@@ -88,6 +277,47 @@ pub enum Command {
         /// Can be specified multiple times: --reviewer alice --reviewer bob
         #[arg(long)]
         reviewer: Vec<String>,
+
+        /// Explicitly confirm marking the PR ready. Required when the config's
+        /// `[policy] require_confirm_for_ready` is set.
+        #[arg(long)]
+        confirm: bool,
+
+        /// Mark the PR ready even though some threads are still waiting for a
+        /// reviewer to answer Claude's question (`reply --status question`).
+        #[arg(long)]
+        force: bool,
+
+        /// Also post the thread statistics `ready` prints to the PR as a comment.
+        #[arg(long)]
+        post_stats: bool,
+    },
+
+    /// Merge the PR once it's happy: validates it like `ready` (no unresolved threads,
+    /// CI passing), merges it, and waits until GitHub reports it merged. Exits 0 once
+    /// merged, 1 if it's closed instead, 2 on --timeout.
+    Merge {
+        /// Squash the commits into one (the default).
+        #[arg(long, group = "merge_method")]
+        squash: bool,
+
+        /// Rebase the commits onto the base branch.
+        #[arg(long, group = "merge_method")]
+        rebase: bool,
+
+        /// Create a merge commit.
+        #[arg(long, group = "merge_method")]
+        merge: bool,
+
+        /// Enable GitHub auto-merge instead of merging now, so the PR lands once
+        /// required checks and reviews allow. Checks may still be running.
+        #[arg(long)]
+        auto: bool,
+
+        /// Return once the merge (or auto-merge) is requested instead of waiting for
+        /// it to complete.
+        #[arg(long)]
+        no_wait: bool,
     },
 
     /// Delete resolved review threads where all comments are from Claude.
@@ -95,6 +325,146 @@ pub enum Command {
     /// Unlike `ready`, this does not validate PR state or mark it as non-draft.
     CleanThreads,
 
+    /// Keep running, re-analyzing the PR every --poll-interval and printing a
+    /// new recommendation whenever the next action changes (a new comment, a
+    /// check failing or recovering). Runs until interrupted or --cancel-file.
+    Watch,
+
+    /// After marking the PR ready, keep watching it for a while and notify
+    /// (webhook and/or desktop) as soon as a new reviewer comment or a newly
+    /// failing required check needs the agent again. Exits 0 when something
+    /// does, 2 when the window passes quietly. Polls every --poll-interval.
+    PostReadyWatch {
+        /// How long to watch, e.g. 30m, 2h.
+        #[arg(long = "for", value_name = "DURATION")]
+        duration: String,
+
+        /// URL to POST `{"text": ...}` to. Overrides `[notify] webhook`.
+        #[arg(long)]
+        webhook: Option<String>,
+
+        /// Show a desktop notification (osascript on macOS, notify-send elsewhere).
+        #[arg(long)]
+        desktop: bool,
+    },
+
+    /// File a GitHub issue for each paperclip thread (deferred work), quoting the
+    /// discussion and linking the PR, then reply on the thread with the issue link.
+    /// Threads that already have such a reply are skipped.
+    FileFollowups {
+        /// Print the issues that would be filed without creating them.
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Park review threads or checks so they don't count as actionable until the snooze
+    /// expires (e.g. a discussion deferred to a follow-up). Recorded in .pr-loop/snoozes.json.
+    Snooze {
+        /// Review thread ID to snooze (as printed by the analysis). Repeatable.
+        #[arg(long = "thread", value_name = "ID")]
+        threads: Vec<String>,
+
+        /// Check name to snooze. Repeatable.
+        #[arg(long = "check", value_name = "NAME")]
+        checks: Vec<String>,
+
+        /// How long to snooze, e.g. 30m, 2h, 3d.
+        #[arg(long = "for", value_name = "DURATION", required_unless_present_any = ["list", "clear"])]
+        duration: Option<String>,
+
+        /// Why, shown when the analysis lists snoozed items.
+        #[arg(long)]
+        reason: Option<String>,
+
+        /// Remove the snooze on the given threads and checks instead.
+        #[arg(long, conflicts_with_all = ["duration", "reason"])]
+        clear: bool,
+
+        /// List this PR's active snoozes.
+        #[arg(long, conflicts_with_all = ["threads", "checks", "duration", "clear"])]
+        list: bool,
+    },
+
+    /// Show the mutations pr-loop has made to this PR (from .pr-loop/audit.log).
+    Audit {
+        /// Also post the log as a comment on the PR.
+        #[arg(long)]
+        post: bool,
+    },
+
+    /// Print a briefing with everything needed to start an iteration: PR title and
+    /// description, linked issues, diff stat, actionable threads with their diff hunks,
+    /// failing checks with extracted errors, and the recommended next action.
+    Context {
+        /// Output format.
+        #[arg(long, value_enum, default_value = "markdown")]
+        format: OutputFormat,
+    },
+
+    /// Print each actionable review thread as a structured task in JSON: thread and
+    /// comment IDs, file and line, urgency tag, the ask, constraints the reviewer
+    /// mentioned, and any suggested change.
+    Tasks,
+
+    /// List every review thread, resolved or not: location, comment count, who
+    /// commented last, whether it's pure Claude or marked 📎, and whether the
+    /// analysis counts it as actionable and why.
+    Threads {
+        /// Print the list as JSON.
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Sync the local checkout to the PR head: fetch it, switch to (or create) the PR's
+    /// branch, fast-forward it if behind, and verify HEAD matches the PR's head commit.
+    /// Warns instead of touching a branch that is ahead of or has diverged from the PR.
+    Checkout,
+
+    /// Find the commit where a CI check started failing by walking the check results
+    /// on the PR's recent commits, and show what that change touched.
+    CiBisect {
+        /// Check name, or a glob matching several (e.g. "test (*)").
+        #[arg(long)]
+        check: String,
+
+        /// How many of the PR's most recent commits to examine (max 100).
+        #[arg(long, default_value = "30")]
+        max_commits: u64,
+    },
+
+    /// Run a failed check's tests locally and report whether the failure
+    /// reproduces. The command comes from `[repro.commands]` in the config, or
+    /// from the repository's toolchain (cargo, npm/pnpm/yarn, pytest).
+    /// Exits 1 if it reproduces, 0 if the tests pass locally.
+    Repro {
+        /// Check name, or a glob matching several (e.g. "test (*)").
+        #[arg(long)]
+        check: String,
+
+        /// Run inside the CircleCI job's Docker image (with the checkout
+        /// mounted at /repo), to catch "passes locally, fails in CI".
+        #[arg(long)]
+        docker: bool,
+    },
+
+    /// Re-run the CircleCI workflows containing failed checks.
+    Rerun {
+        /// Only re-run the failed jobs (and their dependents) instead of whole workflows.
+        #[arg(long)]
+        failed_only: bool,
+
+        /// Wait for the new workflows to finish (bounded by --timeout, polling every
+        /// --poll-interval). Exits 0 if they all succeed, 1 if any fail, 2 on timeout, 3 if
+        /// cancelled by --cancel-file.
+        #[arg(long)]
+        wait: bool,
+
+        /// For failures that look like cache corruption: first delete the PR's GitHub Actions
+        /// caches, and also re-run failed GitHub Actions runs.
+        #[arg(long)]
+        bust_caches: bool,
+    },
+
     /// Show CI check status and failure logs.
     /// Does not modify the PR or post comments. Works on any PR (draft or not).
     Checks,
@@ -150,19 +520,99 @@ pub enum Command {
         action: ConfigAction,
     },
 
+    /// Write a .tar.gz for bug reports: version info, the effective config, recent
+    /// gh request metadata, and a snapshot of the last analysis's inputs. Comment
+    /// text, request bodies, and credentials are left out.
+    DebugBundle {
+        /// Where to write the bundle (default: pr-loop-debug-<timestamp>.tar.gz in the
+        /// current directory).
+        #[arg(long, short)]
+        output: Option<std::path::PathBuf>,
+    },
+
+    /// Print the JSON Schema for `pr-loop context --format json` (for --output-version,
+    /// if given). The output's `schema_version` field matches the schema's.
+    Schema {
+        /// Print the schema for `pr-loop --format json` (the analysis) instead.
+        #[arg(long)]
+        analysis: bool,
+    },
+
+    /// Rerun the decision engine on the analysis captured in a debug bundle (or a bare
+    /// last-analysis.json) and print the recommendation, without contacting GitHub.
+    /// CI logs, annotations, and linked issues aren't captured and are left out.
+    Replay {
+        /// Path to the bundle.
+        bundle: std::path::PathBuf,
+    },
+
     /// Print the Claude Code status that `pr-loop web` infers for the current
     /// directory — the transcript path it picked, the session file it matched,
     /// and the resulting activity. Useful for debugging why the web UI isn't
     /// showing the expected state.
     CcStatus,
+
+    /// Check what works in this environment: gh's version, the token's scopes,
+    /// the CircleCI token, and which features each of them enables or rules out.
+    Doctor,
+
+    /// Scaffold the loop's integration files in this repository: a starter
+    /// .github/pr-loop.toml and a pr-loop section in CLAUDE.md, tailored to the CI
+    /// providers found. Existing files are kept unless --force.
+    Init {
+        /// Also write a Claude Code Stop hook (.claude/hooks/pr-loop-stop.sh) that
+        /// keeps the agent working while the PR still needs something.
+        #[arg(long)]
+        hooks: bool,
+
+        /// Replace files (and the CLAUDE.md section) that already exist.
+        #[arg(long)]
+        force: bool,
+    },
+
+    /// Smoke-test the token against a sandbox repository (--repo is required): open
+    /// a disposable draft PR, then open a review thread, reply, resolve it, add and
+    /// remove the status block, and clean up Claude's threads, checking each step.
+    /// Closes the PR and deletes its branch afterwards. Exits 1 if any step failed.
+    Selftest,
+
+    /// Run a Model Context Protocol server on stdio exposing `analyze`, `reply`,
+    /// `resolve`, `checks`, and `ready` as tools, for agents to call directly.
+    /// --repo and --pr, if given, apply to every call.
+    Mcp,
 }
 
 #[derive(clap::Subcommand, Debug)]
 pub enum ConfigAction {
     /// Print the path to the config file (whether or not it exists).
     Path,
-    /// Print the effective config (file contents + defaults filled in) as TOML.
+    /// Print the effective config (user file layered over the repository's
+    /// .github/pr-loop.toml) as TOML.
     Print,
+    /// List each setting in effect as `section.key = value`.
+    Show {
+        /// Also show which config file each value came from.
+        #[arg(long)]
+        origin: bool,
+    },
+    /// Print the effective value of one setting, e.g. `checks.exclude_apps`.
+    /// Exits 1 if it isn't set (the default applies).
+    Get { key: String },
+    /// Set a value in the user config file. VALUE is read as TOML (`true`, `30`,
+    /// `["a", "b"]`), or as a string if it isn't valid TOML.
+    Set { key: String, value: String },
+    /// Remove a setting from the user config file.
+    Unset { key: String },
+    /// Check the user and repository config files for unknown keys and type errors.
+    Validate,
+}
+
+/// Parse a `--var KEY=VALUE` pair. The value may itself contain `=`.
+fn parse_var(raw: &str) -> Result<(String, String), String> {
+    match raw.split_once('=') {
+        Some((key, value)) if !key.is_empty() => Ok((key.to_string(), value.to_string())),
+        _ => Err(format!("expected KEY=VALUE, got '{}'", raw)),
+    }
 }
 
 #[cfg(test)]
@@ -185,6 +635,7 @@ mod tests {
         unsafe {
             std::env::remove_var("PR_LOOP_INCLUDE_CHECKS");
             std::env::remove_var("PR_LOOP_EXCLUDE_CHECKS");
+            std::env::remove_var("PR_LOOP_EXCLUDE_APPS");
         }
 
         let cli = Cli::parse_from(["pr-loop"]);
@@ -192,6 +643,7 @@ mod tests {
         assert!(cli.pr.is_none());
         assert!(cli.include_checks.is_empty());
         assert!(cli.exclude_checks.is_empty());
+        assert!(cli.exclude_apps.is_empty());
         assert!(cli.command.is_none());
     }
 
@@ -217,6 +669,17 @@ mod tests {
         assert_eq!(cli.exclude_checks, vec!["lint"]);
     }
 
+    #[test]
+    #[serial]
+    fn parse_exclude_apps() {
+        // SAFETY: Test is serialized via #[serial]
+        unsafe {
+            std::env::remove_var("PR_LOOP_EXCLUDE_APPS");
+        }
+        let cli = Cli::parse_from(["pr-loop", "--exclude-apps", "codecov,sonarqubecloud", "checks"]);
+        assert_eq!(cli.exclude_apps, vec!["codecov", "sonarqubecloud"]);
+    }
+
     #[test]
     fn parse_reply_command() {
         let cli = Cli::parse_from([
@@ -228,12 +691,45 @@ mod tests {
             "Fixed the issue",
         ]);
         match cli.command {
-            Some(Command::Reply { in_reply_to, message }) => {
+            Some(Command::Reply { in_reply_to, message, quote, .. }) => {
                 assert_eq!(in_reply_to, "PRRC_456");
-                assert_eq!(message, "Fixed the issue");
+                assert_eq!(message.as_deref(), Some("Fixed the issue"));
+                assert_eq!(quote, None);
             }
             _ => panic!("Expected Reply command"),
         }
+
+        let cli = Cli::parse_from(["pr-loop", "reply", "--in-reply-to", "C1", "--message", "m", "--quote"]);
+        assert!(matches!(
+            cli.command,
+            Some(Command::Reply { quote: Some(3), status: None, if_resolved: IfResolved::Skip, .. })
+        ));
+        let cli = Cli::parse_from(["pr-loop", "reply", "--in-reply-to", "C1", "--message", "m", "--if-resolved", "post"]);
+        assert!(matches!(cli.command, Some(Command::Reply { if_resolved: IfResolved::Post, .. })));
+        let cli = Cli::parse_from(["pr-loop", "reply", "--in-reply-to", "C1", "--message", "m", "--status", "question"]);
+        assert!(matches!(cli.command, Some(Command::Reply { status: Some(ReplyStatus::Question), .. })));
+        let cli = Cli::parse_from(["pr-loop", "reply", "--in-reply-to", "C1", "--message", "m", "--quote", "1"]);
+        assert!(matches!(cli.command, Some(Command::Reply { quote: Some(1), .. })));
+    }
+
+    #[test]
+    fn parse_reply_template() {
+        let cli = Cli::parse_from([
+            "pr-loop", "reply", "--in-reply-to", "C1", "--template", "fixed-in", "--var", "commit=abc=1",
+        ]);
+        match cli.command {
+            Some(Command::Reply { template, vars, message, .. }) => {
+                assert_eq!(template.as_deref(), Some("fixed-in"));
+                assert_eq!(vars, vec![("commit".to_string(), "abc=1".to_string())]);
+                assert_eq!(message, None);
+            }
+            _ => panic!("Expected Reply command"),
+        }
+        assert!(Cli::try_parse_from(["pr-loop", "reply", "--in-reply-to", "C1"]).is_err());
+        assert!(Cli::try_parse_from([
+            "pr-loop", "reply", "--in-reply-to", "C1", "--template", "t", "--var", "novalue",
+        ])
+        .is_err());
     }
 
     #[test]
@@ -255,6 +751,74 @@ mod tests {
         assert!(matches!(cli.command, Some(Command::Reply { .. })));
     }
 
+    #[test]
+    fn parse_context_format() {
+        let cli = Cli::parse_from(["pr-loop", "context"]);
+        assert!(matches!(
+            cli.command,
+            Some(Command::Context { format: OutputFormat::Markdown })
+        ));
+        let cli = Cli::parse_from(["pr-loop", "context", "--format", "json"]);
+        assert!(matches!(
+            cli.command,
+            Some(Command::Context { format: OutputFormat::Json })
+        ));
+    }
+
+    #[test]
+    fn parse_ci_bisect() {
+        let cli = Cli::parse_from(["pr-loop", "ci-bisect", "--check", "ci/test"]);
+        match cli.command {
+            Some(Command::CiBisect { check, max_commits }) => {
+                assert_eq!(check, "ci/test");
+                assert_eq!(max_commits, 30);
+            }
+            _ => panic!("Expected CiBisect command"),
+        }
+    }
+
+    #[test]
+    fn parse_repro() {
+        let cli = Cli::parse_from(["pr-loop", "repro", "--check", "ci/test"]);
+        match cli.command {
+            Some(Command::Repro { check, docker }) => {
+                assert_eq!(check, "ci/test");
+                assert!(!docker);
+            }
+            _ => panic!("Expected Repro command"),
+        }
+    }
+
+    #[test]
+    fn parse_rerun() {
+        let cli = Cli::parse_from(["pr-loop", "rerun", "--failed-only"]);
+        assert!(matches!(
+            cli.command,
+            Some(Command::Rerun {
+                failed_only: true,
+                wait: false,
+                bust_caches: false
+            })
+        ));
+    }
+
+    #[test]
+    fn parse_publish_check() {
+        assert!(!Cli::parse_from(["pr-loop"]).publish_check);
+        assert!(Cli::parse_from(["pr-loop", "--publish-check"]).publish_check);
+    }
+
+    #[test]
+    fn parse_thread_order() {
+        let cli = Cli::parse_from(["pr-loop"]);
+        assert_eq!(cli.thread_order, ThreadOrder::Posted);
+        assert!(!cli.tag_threads);
+
+        let cli = Cli::parse_from(["pr-loop", "--thread-order", "urgency", "--tag-threads"]);
+        assert_eq!(cli.thread_order, ThreadOrder::Urgency);
+        assert!(cli.tag_threads);
+    }
+
     #[test]
     #[serial]
     fn parse_check_filters_from_env() {
@@ -315,12 +879,170 @@ mod tests {
         assert_eq!(cli.poll_interval, 10);
     }
 
+    #[test]
+    fn parse_wait_until_quiet() {
+        let cli = Cli::parse_from(["pr-loop"]);
+        assert_eq!(cli.wait_until_quiet, None);
+
+        let cli = Cli::parse_from(["pr-loop", "--wait-until-actionable", "--wait-until-quiet", "120"]);
+        assert!(cli.wait_until_actionable);
+        assert_eq!(cli.wait_until_quiet, Some(120));
+    }
+
+    #[test]
+    fn parse_pause_outside_hours() {
+        let cli = Cli::parse_from(["pr-loop"]);
+        assert!(!cli.pause_outside_hours);
+        let cli = Cli::parse_from(["pr-loop", "--wait-until-actionable", "--pause-outside-hours"]);
+        assert!(cli.pause_outside_hours);
+    }
+
+    #[test]
+    fn parse_ready_confirm() {
+        let cli = Cli::parse_from(["pr-loop", "ready", "--confirm"]);
+        assert!(matches!(cli.command, Some(Command::Ready { confirm: true, .. })));
+    }
+
+    #[test]
+    fn parse_ready_post_stats() {
+        let cli = Cli::parse_from(["pr-loop", "ready", "--post-stats"]);
+        assert!(matches!(cli.command, Some(Command::Ready { post_stats: true, .. })));
+    }
+
+    #[test]
+    fn parse_analysis_format() {
+        assert_eq!(Cli::parse_from(["pr-loop"]).format, OutputFormat::Markdown);
+        assert_eq!(Cli::parse_from(["pr-loop", "--format", "json"]).format, OutputFormat::Json);
+        // The `context` subcommand has a --format of its own
+        let cli = Cli::parse_from(["pr-loop", "context", "--format", "json"]);
+        assert_eq!(cli.format, OutputFormat::Markdown);
+    }
+
+    #[test]
+    fn parse_auto_clean() {
+        assert_eq!(Cli::parse_from(["pr-loop"]).auto_clean, None);
+        assert_eq!(Cli::parse_from(["pr-loop", "--auto-clean"]).auto_clean, Some(10));
+        assert_eq!(Cli::parse_from(["pr-loop", "--auto-clean", "30"]).auto_clean, Some(30));
+    }
+
+    #[test]
+    fn parse_then_ready() {
+        let cli = Cli::parse_from(["pr-loop", "--wait-until-actionable-or-happy", "--then-ready"]);
+        assert!(cli.then_ready);
+        assert!(Cli::try_parse_from(["pr-loop", "--then-ready"]).is_err());
+    }
+
+    #[test]
+    fn parse_ready_force() {
+        let cli = Cli::parse_from(["pr-loop", "ready", "--force"]);
+        assert!(matches!(cli.command, Some(Command::Ready { force: true, confirm: false, .. })));
+    }
+
+    #[test]
+    fn parse_audit_subcommand() {
+        let cli = Cli::parse_from(["pr-loop", "audit", "--post"]);
+        assert!(matches!(cli.command, Some(Command::Audit { post: true })));
+    }
+
+    #[test]
+    fn parse_snooze_subcommand() {
+        let cli = Cli::parse_from(["pr-loop", "snooze", "--thread", "PRRT_1", "--for", "2h"]);
+        match cli.command {
+            Some(Command::Snooze { threads, duration, clear, .. }) => {
+                assert_eq!(threads, vec!["PRRT_1"]);
+                assert_eq!(duration.as_deref(), Some("2h"));
+                assert!(!clear);
+            }
+            other => panic!("unexpected command: {:?}", other),
+        }
+
+        assert!(Cli::try_parse_from(["pr-loop", "snooze", "--thread", "PRRT_1"]).is_err());
+        assert!(Cli::try_parse_from(["pr-loop", "snooze", "--clear", "--check", "e2e"]).is_ok());
+    }
+
+    #[test]
+    fn parse_comment_debounce() {
+        let cli = Cli::parse_from(["pr-loop"]);
+        assert_eq!(cli.comment_debounce, 0);
+
+        let cli = Cli::parse_from(["pr-loop", "--wait-until-actionable", "--comment-debounce", "45"]);
+        assert_eq!(cli.comment_debounce, 45);
+    }
+
+    #[test]
+    fn parse_reply_batch_subcommand() {
+        let cli = Cli::parse_from(["pr-loop", "reply-batch", "replies.json"]);
+        assert!(matches!(cli.command, Some(Command::ReplyBatch { file: Some(_) })));
+        let cli = Cli::parse_from(["pr-loop", "reply-batch"]);
+        assert!(matches!(cli.command, Some(Command::ReplyBatch { file: None })));
+    }
+
+    #[test]
+    fn parse_resolve_subcommand() {
+        let cli = Cli::parse_from(["pr-loop", "resolve", "--thread", "PRRT_1", "--comment", "PRRC_2"]);
+        match cli.command {
+            Some(Command::Resolve { threads, comments }) => {
+                assert_eq!(threads, vec!["PRRT_1"]);
+                assert_eq!(comments, vec!["PRRC_2"]);
+            }
+            other => panic!("unexpected command: {:?}", other),
+        }
+        assert!(Cli::try_parse_from(["pr-loop", "resolve", "--comment", "PRRC_2"]).is_ok());
+        assert!(Cli::try_parse_from(["pr-loop", "resolve"]).is_err());
+    }
+
+    #[test]
+    fn parse_post_ready_watch() {
+        let cli = Cli::parse_from(["pr-loop", "post-ready-watch", "--for", "2h", "--desktop"]);
+        match cli.command {
+            Some(Command::PostReadyWatch { duration, webhook, desktop }) => {
+                assert_eq!(duration, "2h");
+                assert_eq!(webhook, None);
+                assert!(desktop);
+            }
+            other => panic!("unexpected command: {:?}", other),
+        }
+        assert!(Cli::try_parse_from(["pr-loop", "post-ready-watch"]).is_err());
+    }
+
+    #[test]
+    fn parse_listen() {
+        let cli = Cli::parse_from(["pr-loop", "--wait-until-actionable", "--listen", "127.0.0.1:8787", "--forward-webhooks"]);
+        assert_eq!(cli.listen.as_deref(), Some("127.0.0.1:8787"));
+        assert!(cli.forward_webhooks);
+        assert!(Cli::try_parse_from(["pr-loop", "--forward-webhooks"]).is_err());
+    }
+
+    #[test]
+    fn parse_watch() {
+        let cli = Cli::parse_from(["pr-loop", "--poll-interval", "30", "watch"]);
+        assert!(matches!(cli.command, Some(Command::Watch)));
+        assert_eq!(cli.poll_interval, 30);
+    }
+
+    #[test]
+    fn parse_download_attachments() {
+        assert!(Cli::parse_from(["pr-loop"]).download_attachments.is_none());
+        let cli = Cli::parse_from(["pr-loop", "--download-attachments", "/tmp/shots"]);
+        assert_eq!(cli.download_attachments, Some(std::path::PathBuf::from("/tmp/shots")));
+    }
+
+    #[test]
+    fn parse_ignore_base_failures() {
+        assert!(!Cli::parse_from(["pr-loop"]).ignore_base_failures);
+        assert!(Cli::parse_from(["pr-loop", "--ignore-base-failures"]).ignore_base_failures);
+    }
+
     #[test]
     fn parse_wait_until_actionable_or_happy() {
         let cli = Cli::parse_from(["pr-loop", "--wait-until-actionable-or-happy"]);
         assert!(cli.wait_until_actionable_or_happy);
         assert!(!cli.wait_until_actionable);
         assert_eq!(cli.min_wait_after_push, 30); // default 30 seconds
+        assert!(!cli.strict_happy);
+
+        let cli = Cli::parse_from(["pr-loop", "--wait-until-actionable-or-happy", "--strict-happy"]);
+        assert!(cli.strict_happy);
     }
 
     #[test]
@@ -361,7 +1083,7 @@ mod tests {
     fn parse_ready_command() {
         let cli = Cli::parse_from(["pr-loop", "ready"]);
         match cli.command {
-            Some(Command::Ready { preserve_claude_threads, reviewer }) => {
+            Some(Command::Ready { preserve_claude_threads, reviewer, .. }) => {
                 assert!(!preserve_claude_threads);
                 assert!(reviewer.is_empty());
             }
@@ -375,7 +1097,7 @@ mod tests {
         assert_eq!(cli.repo, Some("owner/repo".to_string()));
         assert_eq!(cli.pr, Some(123));
         match cli.command {
-            Some(Command::Ready { preserve_claude_threads, reviewer }) => {
+            Some(Command::Ready { preserve_claude_threads, reviewer, .. }) => {
                 assert!(!preserve_claude_threads);
                 assert!(reviewer.is_empty());
             }
@@ -415,7 +1137,7 @@ mod tests {
     fn parse_ready_command_with_preserve_claude_threads() {
         let cli = Cli::parse_from(["pr-loop", "ready", "--preserve-claude-threads"]);
         match cli.command {
-            Some(Command::Ready { preserve_claude_threads, reviewer }) => {
+            Some(Command::Ready { preserve_claude_threads, reviewer, .. }) => {
                 assert!(preserve_claude_threads);
                 assert!(reviewer.is_empty());
             }
@@ -485,6 +1207,68 @@ mod tests {
         }
     }
 
+    #[test]
+    fn parse_explain() {
+        assert!(!Cli::parse_from(["pr-loop"]).explain);
+        assert!(Cli::parse_from(["pr-loop", "--explain"]).explain);
+    }
+
+    #[test]
+    fn parse_merge() {
+        let cli = Cli::parse_from(["pr-loop", "merge", "--rebase", "--auto"]);
+        match cli.command {
+            Some(Command::Merge { squash, rebase, merge, auto, no_wait }) => {
+                assert!(!squash && rebase && !merge);
+                assert!(auto);
+                assert!(!no_wait);
+            }
+            other => panic!("unexpected command: {:?}", other),
+        }
+        assert!(Cli::try_parse_from(["pr-loop", "merge", "--squash", "--merge"]).is_err());
+    }
+
+    #[test]
+    fn parse_doctor() {
+        let cli = Cli::parse_from(["pr-loop", "doctor"]);
+        assert!(matches!(cli.command, Some(Command::Doctor)));
+    }
+
+    #[test]
+    fn parse_init() {
+        let cli = Cli::parse_from(["pr-loop", "init", "--hooks"]);
+        assert!(matches!(cli.command, Some(Command::Init { hooks: true, force: false })));
+    }
+
+    #[test]
+    fn parse_selftest() {
+        let cli = Cli::parse_from(["pr-loop", "--repo", "owner/sandbox", "selftest"]);
+        assert!(matches!(cli.command, Some(Command::Selftest)));
+        assert_eq!(cli.repo.as_deref(), Some("owner/sandbox"));
+    }
+
+    #[test]
+    fn parse_mcp() {
+        let cli = Cli::parse_from(["pr-loop", "mcp", "--pr", "12"]);
+        assert!(matches!(cli.command, Some(Command::Mcp)));
+        assert_eq!(cli.pr, Some(12));
+    }
+
+    #[test]
+    fn parse_threads() {
+        let cli = Cli::parse_from(["pr-loop", "threads", "--json"]);
+        assert!(matches!(cli.command, Some(Command::Threads { json: true })));
+    }
+
+    #[test]
+    fn parse_comment() {
+        let cli = Cli::parse_from(["pr-loop", "comment", "--message", "Squashed and force-pushed"]);
+        match cli.command {
+            Some(Command::Comment { message }) => assert_eq!(message, "Squashed and force-pushed"),
+            other => panic!("unexpected command: {:?}", other),
+        }
+        assert!(Cli::try_parse_from(["pr-loop", "comment"]).is_err());
+    }
+
     #[test]
     fn parse_config_path() {
         let cli = Cli::parse_from(["pr-loop", "config", "path"]);
@@ -503,11 +1287,34 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn parse_config_show_origin() {
+        let cli = Cli::parse_from(["pr-loop", "config", "show", "--origin"]);
+        assert!(matches!(
+            cli.command,
+            Some(Command::Config { action: ConfigAction::Show { origin: true } })
+        ));
+    }
+
+    #[test]
+    fn parse_config_set() {
+        let cli = Cli::parse_from(["pr-loop", "config", "set", "hub.port", "9000"]);
+        match cli.command {
+            Some(Command::Config {
+                action: ConfigAction::Set { key, value },
+            }) => {
+                assert_eq!(key, "hub.port");
+                assert_eq!(value, "9000");
+            }
+            _ => panic!("Expected config set"),
+        }
+    }
+
     #[test]
     fn parse_ready_command_with_reviewer() {
         let cli = Cli::parse_from(["pr-loop", "ready", "--reviewer", "octocat"]);
         match cli.command {
-            Some(Command::Ready { preserve_claude_threads, reviewer }) => {
+            Some(Command::Ready { preserve_claude_threads, reviewer, .. }) => {
                 assert!(!preserve_claude_threads);
                 assert_eq!(reviewer, vec!["octocat".to_string()]);
             }
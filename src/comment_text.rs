@@ -0,0 +1,358 @@
+// Plain-text rendering of comment bodies.
+// Reviewers (and bots) write comments GitHub renders from HTML: tables,
+// collapsed `<details>` blocks, image attachments, hidden `<!-- -->`
+// metadata. Shown raw, that markup buries the actual request. `to_text`
+// flattens it for the agent's view: table rows become `a | b` lines, details
+// are expanded under their summary, and images become their alt text and URL.
+// Code blocks are left exactly as written, and the stored body is never
+// changed (replies and edits work on the original).
+
+/// The comment body with its HTML flattened to text.
+pub fn to_text(body: &str) -> String {
+    if !body.contains('<') && !body.contains("![") {
+        return body.to_string();
+    }
+
+    let mut out = String::new();
+    let mut prose = String::new();
+    let mut in_fence = false;
+    for line in body.split_inclusive('\n') {
+        let is_fence = line.trim_start().starts_with("```");
+        if in_fence || is_fence {
+            if !prose.is_empty() {
+                out.push_str(&flatten(&prose));
+                prose.clear();
+            }
+            out.push_str(line);
+            if is_fence {
+                in_fence = !in_fence;
+            }
+        } else {
+            prose.push_str(line);
+        }
+    }
+    out.push_str(&flatten(&prose));
+    tidy(&out)
+}
+
+/// URLs of the images in a comment (Markdown `![..](url)` and `<img src>`),
+/// in order, outside code blocks.
+pub fn image_urls(body: &str) -> Vec<String> {
+    let mut urls: Vec<String> = Vec::new();
+    let mut in_fence = false;
+    for line in body.lines() {
+        if line.trim_start().starts_with("```") {
+            in_fence = !in_fence;
+            continue;
+        }
+        if in_fence {
+            continue;
+        }
+        let mut rest = line;
+        while let Some(start) = rest.find(['<', '!']) {
+            rest = &rest[start..];
+            let found = match rest.strip_prefix("![") {
+                Some(candidate) => candidate.find("](").and_then(|alt_end| {
+                    let url_part = &candidate[alt_end + 2..];
+                    Some(url_part[..url_part.find(')')?].to_string())
+                }),
+                None => parse_tag(rest)
+                    .filter(|(tag, _)| tag.name == "img" && !tag.closing)
+                    .and_then(|(tag, _)| attribute(&tag.attrs, "src")),
+            };
+            if let Some(url) = found.filter(|u| !u.is_empty() && !urls.contains(u)) {
+                urls.push(url);
+            }
+            rest = &rest[1..];
+        }
+    }
+    urls
+}
+
+/// Where the renderer is within a table.
+#[derive(Default)]
+struct TableState {
+    depth: usize,
+    in_cell: bool,
+    first_cell: bool,
+}
+
+fn flatten(text: &str) -> String {
+    let text = markdown_images(text);
+    let mut out = String::new();
+    let mut table = TableState::default();
+    let mut rest = text.as_str();
+    while let Some(start) = rest.find('<') {
+        push_text(&mut out, &rest[..start], &table);
+        rest = &rest[start..];
+        if let Some(after) = rest.strip_prefix("<!--") {
+            rest = after.find("-->").map_or("", |end| &after[end + 3..]);
+            continue;
+        }
+        match parse_tag(rest) {
+            Some((tag, len)) => {
+                render_tag(&mut out, &tag, &mut table);
+                rest = &rest[len..];
+            }
+            None => {
+                push_text(&mut out, "<", &table);
+                rest = &rest[1..];
+            }
+        }
+    }
+    push_text(&mut out, rest, &table);
+    out
+}
+
+fn push_text(out: &mut String, text: &str, table: &TableState) {
+    let text = decode_entities(text);
+    if table.depth > 0 && !table.in_cell {
+        // Indentation between table tags
+        if !text.trim().is_empty() {
+            out.push_str(text.trim());
+        }
+    } else if table.in_cell {
+        let collapsed = text.split_whitespace().collect::<Vec<_>>().join(" ");
+        if text.starts_with(char::is_whitespace) && !out.ends_with([' ', '|']) && !collapsed.is_empty() {
+            out.push(' ');
+        }
+        out.push_str(&collapsed);
+    } else {
+        out.push_str(&text);
+    }
+}
+
+struct Tag {
+    /// Lowercase, e.g. "td".
+    name: String,
+    closing: bool,
+    /// The text between the name and `>`.
+    attrs: String,
+}
+
+/// Parse the HTML tag at the start of `s`, returning it and its length.
+/// None if `s` doesn't start with something tag-shaped (e.g. "a < b").
+fn parse_tag(s: &str) -> Option<(Tag, usize)> {
+    let inner_start = if s.starts_with("</") { 2 } else { 1 };
+    let first = s[inner_start..].chars().next()?;
+    if !first.is_ascii_alphabetic() {
+        return None;
+    }
+    let end = s.find('>')?;
+    let inner = &s[inner_start..end];
+    if inner.contains('<') {
+        return None;
+    }
+    let name_len = inner
+        .find(|c: char| !c.is_ascii_alphanumeric())
+        .unwrap_or(inner.len());
+    Some((
+        Tag {
+            name: inner[..name_len].to_ascii_lowercase(),
+            closing: inner_start == 2,
+            attrs: inner[name_len..].trim_end_matches('/').to_string(),
+        },
+        end + 1,
+    ))
+}
+
+fn render_tag(out: &mut String, tag: &Tag, table: &mut TableState) {
+    match (tag.name.as_str(), tag.closing) {
+        ("img", false) => {
+            let alt = attribute(&tag.attrs, "alt").unwrap_or_default();
+            let src = attribute(&tag.attrs, "src").unwrap_or_default();
+            out.push_str(&image(&alt, &src));
+        }
+        ("br", _) => out.push('\n'),
+        ("summary", false) => {
+            start_line(out);
+            out.push_str("**");
+        }
+        ("summary", true) => out.push_str("**\n"),
+        ("details" | "p" | "div" | "ul" | "ol", _) => start_line(out),
+        ("li", false) => {
+            start_line(out);
+            out.push_str("- ");
+        }
+        ("table", false) => {
+            start_line(out);
+            table.depth += 1;
+        }
+        ("table", true) => {
+            table.depth = table.depth.saturating_sub(1);
+            start_line(out);
+        }
+        ("tr", false) => {
+            start_line(out);
+            table.first_cell = true;
+        }
+        ("tr", true) => out.push('\n'),
+        ("td" | "th", false) => {
+            if !table.first_cell {
+                out.push_str(" | ");
+            }
+            table.first_cell = false;
+            table.in_cell = true;
+        }
+        ("td" | "th", true) => table.in_cell = false,
+        ("code", _) => out.push('`'),
+        ("b" | "strong", _) => out.push_str("**"),
+        _ => {}
+    }
+}
+
+fn start_line(out: &mut String) {
+    if !out.is_empty() && !out.ends_with('\n') {
+        out.push('\n');
+    }
+}
+
+fn image(alt: &str, url: &str) -> String {
+    match alt.trim() {
+        "" | "image" => format!("[image]({})", url),
+        alt => format!("[image: {}]({})", alt, url),
+    }
+}
+
+/// The value of attribute `name` in a tag's attribute text.
+fn attribute(attrs: &str, name: &str) -> Option<String> {
+    let mut rest = attrs;
+    while let Some(pos) = rest.find(name) {
+        let preceded_ok = rest[..pos].ends_with(char::is_whitespace) || pos == 0;
+        let after = rest[pos + name.len()..].trim_start();
+        if preceded_ok && let Some(value) = after.strip_prefix('=') {
+            let value = value.trim_start();
+            let quote = value.chars().next()?;
+            let value = if quote == '"' || quote == '\'' {
+                let value = &value[1..];
+                &value[..value.find(quote)?]
+            } else {
+                value.split_whitespace().next()?
+            };
+            return Some(decode_entities(value));
+        }
+        rest = &rest[pos + name.len()..];
+    }
+    None
+}
+
+/// Markdown `![alt](url)` images, as `[image: alt](url)`.
+fn markdown_images(text: &str) -> String {
+    let mut out = String::new();
+    let mut rest = text;
+    while let Some(start) = rest.find("![") {
+        out.push_str(&rest[..start]);
+        let candidate = &rest[start + 2..];
+        let parsed = candidate.find("](").and_then(|alt_end| {
+            let url_part = &candidate[alt_end + 2..];
+            let url_end = url_part.find(')')?;
+            Some((&candidate[..alt_end], &url_part[..url_end], alt_end + 2 + url_end + 1))
+        });
+        match parsed {
+            Some((alt, url, len)) if !alt.contains('\n') => {
+                out.push_str(&image(alt, url));
+                rest = &candidate[len..];
+            }
+            _ => {
+                out.push_str("![");
+                rest = candidate;
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+fn decode_entities(text: &str) -> String {
+    if !text.contains('&') {
+        return text.to_string();
+    }
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&nbsp;", " ")
+        .replace("&amp;", "&")
+}
+
+/// Drop trailing spaces and runs of blank lines left behind by the markup.
+fn tidy(text: &str) -> String {
+    let mut out = String::new();
+    let mut blank_run = 0;
+    for line in text.trim_start_matches('\n').lines() {
+        let line = line.trim_end();
+        if line.is_empty() {
+            blank_run += 1;
+            if blank_run > 1 {
+                continue;
+            }
+        } else {
+            blank_run = 0;
+        }
+        out.push_str(line);
+        out.push('\n');
+    }
+    out.trim_end_matches('\n').to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plain_markdown_is_unchanged() {
+        let body = "Rename this.\n\n```rust\nlet a = b < c;\n```";
+        assert_eq!(to_text(body), body);
+        assert_eq!(to_text("if a < b then"), "if a < b then");
+    }
+
+    #[test]
+    fn flattens_tables_details_and_images() {
+        let body = r#"Benchmarks regressed:
+<table>
+  <tr><th>Case</th><th>Before</th><th>After</th></tr>
+  <tr>
+    <td>parse</td>
+    <td>1.2 ms</td>
+    <td>3.4&nbsp;ms</td>
+  </tr>
+</table>
+<details>
+<summary>Full log</summary>
+
+thread 'main' panicked
+</details>
+
+<img width="400" alt="Screenshot of the dialog" src="https://github.com/user-attachments/assets/1">
+![](https://example.com/chart.png)
+<!-- bot metadata: {"id": 3} -->
+Please take a look &amp; fix."#;
+        assert_eq!(
+            to_text(body),
+            "Benchmarks regressed:\n\
+             Case | Before | After\n\
+             parse | 1.2 ms | 3.4 ms\n\
+             \n\
+             **Full log**\n\
+             \n\
+             thread 'main' panicked\n\
+             \n\
+             [image: Screenshot of the dialog](https://github.com/user-attachments/assets/1)\n\
+             [image](https://example.com/chart.png)\n\
+             \n\
+             Please take a look & fix."
+        );
+    }
+
+    #[test]
+    fn finds_image_urls() {
+        let body = "See ![dialog](https://a/1.png) and\n<img alt=\"x\" src=\"https://a/2\">\n```\n![no](https://a/3)\n```";
+        assert_eq!(image_urls(body), vec!["https://a/1.png", "https://a/2"]);
+    }
+
+    #[test]
+    fn leaves_code_blocks_alone() {
+        let body = "<b>Try</b>:\n```html\n<table><tr><td>x</td></tr></table>\n```\n";
+        assert_eq!(to_text(body), "**Try**:\n```html\n<table><tr><td>x</td></tr></table>\n```");
+    }
+}
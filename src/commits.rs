@@ -1,8 +1,9 @@
 // Fetch commits on a PR via GitHub GraphQL.
 
+use crate::graphql::gh_graphql;
 use anyhow::{Context, Result};
 use serde::Deserialize;
-use std::process::Command;
+use serde_json::json;
 
 /// A commit on a PR.
 #[derive(Debug, Clone)]
@@ -123,26 +124,12 @@ fn fetch_pr_info_from_graphql(owner: &str, repo: &str, pr_number: u64) -> Result
     let mut url: Option<String> = None;
 
     loop {
-        let mut args = vec![
-            "api".to_string(),
-            "graphql".to_string(),
-            "-f".to_string(),
-            format!("query={}", FETCH_COMMITS_QUERY),
-            "-f".to_string(),
-            format!("owner={}", owner),
-            "-f".to_string(),
-            format!("repo={}", repo),
-            "-F".to_string(),
-            format!("pr={}", pr_number),
-        ];
+        let mut variables = json!({ "owner": owner, "repo": repo, "pr": pr_number });
         if let Some(c) = &cursor {
-            args.push("-f".to_string());
-            args.push(format!("cursor={}", c));
+            variables["cursor"] = json!(c);
         }
 
-        let output = Command::new("gh")
-            .args(&args)
-            .output()
+        let output = gh_graphql(FETCH_COMMITS_QUERY, variables)
             .context("Failed to run 'gh api graphql' for commits")?;
 
         if !output.status.success() {
@@ -0,0 +1,61 @@
+// Running independent fetches at the same time.
+// Nearly all of pr-loop's time goes to waiting on `gh` subprocesses and
+// CircleCI HTTP calls, and an analysis makes a dozen or more of them that
+// don't depend on each other (checks, threads, each failed job's logs, ...).
+// Running them on scoped threads cuts an analysis of a PR with many failed
+// jobs from the sum of those waits to roughly the longest one, while the
+// clients stay the simple blocking code they are (and their test doubles
+// don't need a runtime). An async runtime would buy nothing more here: the
+// work is a handful of subprocesses and HTTP calls, not thousands of sockets.
+// Replies, including `reply-batch`'s, are left serial on purpose: they're
+// mutations, which GitHub asks clients to send one at a time (concurrent ones
+// trip secondary rate limits), and a batch's replies to one thread have to
+// land in order.
+
+use std::thread::ScopedJoinHandle;
+
+/// How many fetches run at once. Bounded so a PR with dozens of failed jobs
+/// doesn't start dozens of `gh` processes (and hit secondary rate limits).
+const MAX_CONCURRENT_FETCHES: usize = 8;
+
+/// Wait for a scoped thread, re-raising its panic if it had one.
+pub fn join<T>(handle: ScopedJoinHandle<'_, T>) -> T {
+    handle
+        .join()
+        .unwrap_or_else(|panic| std::panic::resume_unwind(panic))
+}
+
+/// Apply `f` to every item, up to `MAX_CONCURRENT_FETCHES` at a time, and
+/// return the results in the items' order.
+pub fn map<T: Sync, R: Send>(items: &[T], f: impl Fn(&T) -> R + Sync) -> Vec<R> {
+    let mut results = Vec::with_capacity(items.len());
+    for chunk in items.chunks(MAX_CONCURRENT_FETCHES) {
+        std::thread::scope(|s| {
+            let handles: Vec<_> = chunk.iter().map(|item| s.spawn(|| f(item))).collect();
+            results.extend(handles.into_iter().map(join));
+        });
+    }
+    results
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[test]
+    fn map_keeps_order_and_bounds_concurrency() {
+        let running = AtomicUsize::new(0);
+        let peak = AtomicUsize::new(0);
+        let items: Vec<u64> = (0..20).collect();
+        let doubled = map(&items, |n| {
+            let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+            peak.fetch_max(now, Ordering::SeqCst);
+            std::thread::sleep(std::time::Duration::from_millis(5));
+            running.fetch_sub(1, Ordering::SeqCst);
+            n * 2
+        });
+        assert_eq!(doubled, items.iter().map(|n| n * 2).collect::<Vec<_>>());
+        assert!(peak.load(Ordering::SeqCst) <= MAX_CONCURRENT_FETCHES);
+    }
+}
@@ -1,16 +1,35 @@
 // User config loaded from ~/.config/pr-loop/config.toml (or
-// $XDG_CONFIG_HOME/pr-loop/config.toml).
+// $XDG_CONFIG_HOME/pr-loop/config.toml), layered over team-wide defaults
+// checked into the repository at .github/pr-loop.toml.
 //
 // Intentionally kept small and optional — every field has a default, the
-// file doesn't need to exist, and CLI flags override config values.
+// files don't need to exist, and CLI flags override config values.
 
+use crate::author_threads::AuthorThreadPolicy;
+use crate::behind::DEFAULT_BEHIND_THRESHOLD;
+use crate::checks::CollapseRules;
+use crate::circleci::DEFAULT_LOG_DOWNLOAD_CAP;
+use crate::credentials::RealSecretStore;
+use crate::policy::Policy;
 use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
 
 pub const DEFAULT_HUB_PORT: u16 = 10099;
 pub const DEFAULT_BIND: &str = "127.0.0.1";
 
+/// Settings (or whole sections) whose values are secrets or say where
+/// secrets live, so they're never copied out, e.g. into debug bundles.
+pub const SECRET_KEYS: &[&str] = &["credentials", "notify.webhook"];
+
+/// Whether a `section.key` setting is one of the `SECRET_KEYS`.
+pub fn is_secret(key: &str) -> bool {
+    SECRET_KEYS
+        .iter()
+        .any(|secret| key == *secret || key.strip_prefix(secret).is_some_and(|rest| rest.starts_with('.')))
+}
+
 #[derive(Debug, Clone, Default, Deserialize, Serialize, PartialEq)]
 #[serde(deny_unknown_fields)]
 pub struct Config {
@@ -18,6 +37,32 @@ pub struct Config {
     pub web: WebConfig,
     #[serde(default)]
     pub hub: HubConfig,
+    #[serde(default)]
+    pub checks: ChecksConfig,
+    #[serde(default)]
+    pub working_hours: WorkingHoursConfig,
+    #[serde(default)]
+    pub circleci: CircleCiConfig,
+    #[serde(default)]
+    pub credentials: CredentialsConfig,
+    #[serde(default)]
+    pub audit: AuditConfig,
+    #[serde(default)]
+    pub policy: PolicyConfig,
+    #[serde(default)]
+    pub repro: ReproConfig,
+    #[serde(default)]
+    pub escalation: EscalationConfig,
+    #[serde(default)]
+    pub notify: NotifyConfig,
+    #[serde(default)]
+    pub mentions: MentionsConfig,
+    #[serde(default)]
+    pub rebase: RebaseConfig,
+    #[serde(default)]
+    pub author_threads: AuthorThreadsConfig,
+    /// Named reply bodies for `reply --template`, with `{var}` placeholders.
+    pub reply_templates: Option<BTreeMap<String, String>>,
 }
 
 #[derive(Debug, Clone, Default, Deserialize, Serialize, PartialEq)]
@@ -34,7 +79,211 @@ pub struct HubConfig {
     pub port: Option<u16>,
 }
 
+#[derive(Debug, Clone, Default, Deserialize, Serialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct ChecksConfig {
+    /// GitHub App slugs whose checks are ignored (e.g. ["codecov"]).
+    pub exclude_apps: Option<Vec<String>>,
+    /// Collapse `name (variant...)` matrix checks into one line (default: true).
+    pub collapse_matrix: Option<bool>,
+    /// Extra glob patterns whose matching checks are shown as one line.
+    pub collapse_patterns: Option<Vec<String>>,
+    /// Glob patterns for checks that register late, e.g. from a downstream
+    /// `workflow_run` workflow; they count as pending until they appear.
+    pub delayed_checks: Option<Vec<String>>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct WorkingHoursConfig {
+    /// Start of the working day, "HH:MM". Unset means no working hours.
+    pub start: Option<String>,
+    /// End of the working day, "HH:MM".
+    pub end: Option<String>,
+    /// Working days, e.g. ["mon", "tue"]. Defaults to Monday–Friday.
+    pub days: Option<Vec<String>>,
+    /// IANA time zone, e.g. "America/New_York". Defaults to the system zone.
+    pub timezone: Option<String>,
+    /// Poll interval in seconds for wait modes outside working hours.
+    pub off_hours_poll_interval: Option<u64>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct CircleCiConfig {
+    /// Per-organization settings, e.g. `[circleci.org."acme"]`.
+    pub org: Option<BTreeMap<String, CircleCiOrgConfig>>,
+    /// Megabytes of failed step output downloaded per analysis, across all
+    /// failed steps (default: 64).
+    pub max_log_mb: Option<u64>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct CircleCiOrgConfig {
+    /// Environment variable holding the CircleCI token for this org.
+    pub token_env: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct CredentialsConfig {
+    /// Command that prints a secret given its name as the last argument
+    /// (e.g. "pass show pr-loop"). Consulted when a token's env var is unset.
+    pub helper: Option<String>,
+    /// Look secrets up in the OS keychain under service "pr-loop".
+    pub keychain: Option<bool>,
+    /// 1Password secret references by secret name, read with `op read`, e.g.
+    /// `circleci = "op://Engineering/CircleCI/token"`.
+    pub op: Option<BTreeMap<String, String>>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct AuditConfig {
+    /// Append mutations to `.pr-loop/audit.log` in the repo (default: true).
+    pub enabled: Option<bool>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct PolicyConfig {
+    /// Allow deleting comments (default: true).
+    pub allow_delete_comments: Option<bool>,
+    /// Allow resolving threads started by a human (default: true).
+    pub allow_resolve_human_threads: Option<bool>,
+    /// Only mark the PR ready with `ready --confirm` (default: false).
+    pub require_confirm_for_ready: Option<bool>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct ReproConfig {
+    /// Commands for `pr-loop repro` by check name or glob, with `{tests}`
+    /// (the failing tests) and `{check}` placeholders.
+    pub commands: Option<BTreeMap<String, String>>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct EscalationConfig {
+    /// Ask a human for help once a check has failed this many consecutive
+    /// runs with the same error, or a thread is still open after this many
+    /// Claude replies. Unset disables escalation.
+    pub after: Option<u32>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct NotifyConfig {
+    /// URL `post-ready-watch` POSTs `{"text": ...}` to when the PR needs
+    /// the agent again (e.g. a Slack incoming webhook). Secret: anyone with
+    /// the URL can post.
+    pub webhook: Option<String>,
+    /// Also show a desktop notification (default: false).
+    pub desktop: Option<bool>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct MentionsConfig {
+    /// Whether threads whose latest comment @mentions someone else still
+    /// need a response from the agent (default: false, they're left out).
+    pub actionable: Option<bool>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct RebaseConfig {
+    /// Recommend rebasing once the PR branch is more than this many commits
+    /// behind its base (default: 50).
+    pub behind_threshold: Option<u64>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct AuthorThreadsConfig {
+    /// What threads the PR author opened on their own PR count as:
+    /// "actionable" (the default), "informational", or "paperclip".
+    pub policy: Option<AuthorThreadPolicy>,
+}
+
+/// Team-wide settings checked into the repository. Limited to sections
+/// that are safe to share: anything that could run commands or point at
+/// credentials (e.g. `[credentials]`) stays in the user's own config.
+#[derive(Debug, Clone, Default, Deserialize, Serialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct RepoConfig {
+    #[serde(default)]
+    pub checks: ChecksConfig,
+    #[serde(default)]
+    pub policy: PolicyConfig,
+    #[serde(default)]
+    pub escalation: EscalationConfig,
+    #[serde(default)]
+    pub mentions: MentionsConfig,
+    #[serde(default)]
+    pub rebase: RebaseConfig,
+    #[serde(default)]
+    pub author_threads: AuthorThreadsConfig,
+    /// Named reply bodies for `reply --template`, with `{var}` placeholders.
+    pub reply_templates: Option<BTreeMap<String, String>>,
+}
+
+/// Combine two "allow" settings so that a refusal from either side wins.
+fn most_restrictive_allow(user: Option<bool>, repo: Option<bool>) -> Option<bool> {
+    match (user, repo) {
+        (Some(false), _) | (_, Some(false)) => Some(false),
+        _ => user.or(repo),
+    }
+}
+
 impl Config {
+    /// Layer this (user) config over repository defaults. User values win,
+    /// except that policy guardrails combine to the stricter of the two, so
+    /// a team's restrictions can't be loosened by one person's config.
+    pub fn merge_repo(mut self, repo: &RepoConfig) -> Config {
+        let checks = &mut self.checks;
+        checks.exclude_apps = checks.exclude_apps.take().or(repo.checks.exclude_apps.clone());
+        checks.collapse_matrix = checks.collapse_matrix.or(repo.checks.collapse_matrix);
+        checks.collapse_patterns = checks
+            .collapse_patterns
+            .take()
+            .or(repo.checks.collapse_patterns.clone());
+        checks.delayed_checks = checks
+            .delayed_checks
+            .take()
+            .or(repo.checks.delayed_checks.clone());
+
+        let policy = &mut self.policy;
+        policy.allow_delete_comments =
+            most_restrictive_allow(policy.allow_delete_comments, repo.policy.allow_delete_comments);
+        policy.allow_resolve_human_threads = most_restrictive_allow(
+            policy.allow_resolve_human_threads,
+            repo.policy.allow_resolve_human_threads,
+        );
+        policy.require_confirm_for_ready = match (
+            policy.require_confirm_for_ready,
+            repo.policy.require_confirm_for_ready,
+        ) {
+            (Some(true), _) | (_, Some(true)) => Some(true),
+            (user, repo) => user.or(repo),
+        };
+
+        self.escalation.after = self.escalation.after.or(repo.escalation.after);
+        self.mentions.actionable = self.mentions.actionable.or(repo.mentions.actionable);
+        self.rebase.behind_threshold = self.rebase.behind_threshold.or(repo.rebase.behind_threshold);
+        self.author_threads.policy = self.author_threads.policy.or(repo.author_threads.policy);
+
+        // Templates merge by name; a user's template replaces the team's
+        if let Some(repo_templates) = &repo.reply_templates {
+            let mut templates = repo_templates.clone();
+            templates.extend(self.reply_templates.take().unwrap_or_default());
+            self.reply_templates = Some(templates);
+        }
+        self
+    }
+
     /// Addresses the hub should bind on (after applying defaults).
     pub fn hub_binds(&self) -> Vec<String> {
         bind_or_default(self.hub.bind.as_ref())
@@ -47,6 +296,113 @@ impl Config {
     pub fn web_binds(&self) -> Vec<String> {
         bind_or_default(self.web.bind.as_ref())
     }
+    /// GitHub App slugs whose checks should be ignored.
+    pub fn exclude_apps(&self) -> Vec<String> {
+        self.checks.exclude_apps.clone().unwrap_or_default()
+    }
+    /// Checks expected to appear after the others (see `delayed_checks`).
+    pub fn delayed_checks(&self) -> Vec<String> {
+        self.checks.delayed_checks.clone().unwrap_or_default()
+    }
+    /// Iterations after which a stuck check or thread is escalated; None
+    /// when escalation is off.
+    pub fn escalate_after(&self) -> Option<u32> {
+        self.escalation.after.filter(|&n| n > 0)
+    }
+    /// Whether threads addressed to someone else still need a response.
+    pub fn mentioned_others_actionable(&self) -> bool {
+        self.mentions.actionable.unwrap_or(false)
+    }
+    /// What the PR author's own threads count as.
+    pub fn author_thread_policy(&self) -> AuthorThreadPolicy {
+        self.author_threads.policy.unwrap_or_default()
+    }
+    /// Commits behind the base after which rebasing is recommended.
+    pub fn rebase_threshold(&self) -> u64 {
+        self.rebase.behind_threshold.unwrap_or(DEFAULT_BEHIND_THRESHOLD)
+    }
+    /// Webhook for `post-ready-watch` notifications.
+    pub fn notify_webhook(&self) -> Option<String> {
+        self.notify.webhook.clone()
+    }
+    pub fn notify_desktop(&self) -> bool {
+        self.notify.desktop.unwrap_or(false)
+    }
+    /// Cap on CircleCI step output downloaded per analysis, in bytes.
+    pub fn circleci_log_cap(&self) -> u64 {
+        self.circleci
+            .max_log_mb
+            .map_or(DEFAULT_LOG_DOWNLOAD_CAP, |mb| mb.saturating_mul(1024 * 1024))
+    }
+    /// (org, env var) pairs for per-organization CircleCI tokens. Orgs
+    /// without `token_env` are looked up in the secret store only.
+    pub fn circleci_org_token_envs(&self) -> Vec<(String, Option<String>)> {
+        self.circleci
+            .org
+            .iter()
+            .flatten()
+            .map(|(org, c)| (org.clone(), c.token_env.clone()))
+            .collect()
+    }
+    /// Where to look for tokens not set in the environment.
+    pub fn secret_store(&self) -> RealSecretStore {
+        RealSecretStore::new(
+            self.credentials.op.clone().unwrap_or_default(),
+            self.credentials.helper.clone(),
+            self.credentials.keychain.unwrap_or(false),
+        )
+    }
+    /// Guardrails on which mutations are permitted.
+    pub fn policy(&self) -> Policy {
+        let defaults = Policy::default();
+        Policy {
+            allow_delete_comments: self
+                .policy
+                .allow_delete_comments
+                .unwrap_or(defaults.allow_delete_comments),
+            allow_resolve_human_threads: self
+                .policy
+                .allow_resolve_human_threads
+                .unwrap_or(defaults.allow_resolve_human_threads),
+            require_confirm_for_ready: self
+                .policy
+                .require_confirm_for_ready
+                .unwrap_or(defaults.require_confirm_for_ready),
+        }
+    }
+    /// Whether mutations are recorded in the audit log.
+    pub fn audit_enabled(&self) -> bool {
+        self.audit.enabled.unwrap_or(true)
+    }
+    /// The reply template called `name`, if configured.
+    pub fn reply_template(&self, name: &str) -> Option<&str> {
+        self.reply_templates.as_ref()?.get(name).map(String::as_str)
+    }
+    /// Names of the configured reply templates.
+    pub fn reply_template_names(&self) -> Vec<&str> {
+        self.reply_templates.iter().flatten().map(|(k, _)| k.as_str()).collect()
+    }
+    /// The `pr-loop repro` command template for `check`: an exact name
+    /// match, else the first matching glob.
+    pub fn repro_command(&self, check: &str) -> Option<&str> {
+        let commands = self.repro.commands.as_ref()?;
+        commands
+            .get(check)
+            .or_else(|| {
+                commands
+                    .iter()
+                    .find(|(pattern, _)| glob::Pattern::new(pattern).is_ok_and(|p| p.matches(check)))
+                    .map(|(_, command)| command)
+            })
+            .map(String::as_str)
+    }
+    /// Rules for collapsing matrix check variants in output.
+    pub fn collapse_rules(&self) -> CollapseRules {
+        CollapseRules {
+            matrix: self.checks.collapse_matrix.unwrap_or(true),
+            patterns: self.checks.collapse_patterns.clone().unwrap_or_default(),
+        }
+    }
 }
 
 fn bind_or_default(v: Option<&Vec<String>>) -> Vec<String> {
@@ -56,40 +412,137 @@ fn bind_or_default(v: Option<&Vec<String>>) -> Vec<String> {
     }
 }
 
-/// Resolve the path to the config file, honoring XDG_CONFIG_HOME.
+/// The user's home directory: `HOME`, or `USERPROFILE` on Windows, where
+/// `HOME` usually isn't set.
+pub fn home_dir() -> Option<PathBuf> {
+    ["HOME", "USERPROFILE"]
+        .iter()
+        .filter_map(std::env::var_os)
+        .find(|v| !v.is_empty())
+        .map(PathBuf::from)
+}
+
+/// Resolve the path to the config file, honoring XDG_CONFIG_HOME (and
+/// APPDATA on Windows).
 pub fn config_path() -> Result<PathBuf> {
     if let Ok(xdg) = std::env::var("XDG_CONFIG_HOME") {
         if !xdg.is_empty() {
             return Ok(PathBuf::from(xdg).join("pr-loop").join("config.toml"));
         }
     }
-    let home = std::env::var("HOME").context("HOME not set")?;
-    Ok(PathBuf::from(home).join(".config/pr-loop/config.toml"))
+    #[cfg(windows)]
+    if let Some(appdata) = std::env::var_os("APPDATA").filter(|v| !v.is_empty()) {
+        return Ok(PathBuf::from(appdata).join("pr-loop").join("config.toml"));
+    }
+    let home = home_dir().context("HOME not set")?;
+    Ok(home.join(".config").join("pr-loop").join("config.toml"))
 }
 
-/// Load the config file. Returns `Config::default()` if the file is missing.
-/// Warns on parse errors but still returns defaults so the tool doesn't
-/// refuse to start because of a broken config.
-pub fn load() -> Config {
-    let Ok(path) = config_path() else {
-        return Config::default();
-    };
-    let Ok(text) = std::fs::read_to_string(&path) else {
-        return Config::default();
-    };
-    match toml::from_str::<Config>(&text) {
-        Ok(c) => c,
+/// Path of the repository-level config under a checkout root.
+pub fn repo_config_path(root: &Path) -> PathBuf {
+    root.join(".github").join("pr-loop.toml")
+}
+
+/// Read and parse a config file. None if it's missing; warns and returns
+/// None on parse errors so the tool doesn't refuse to start because of a
+/// broken config.
+fn read_file<T: for<'de> Deserialize<'de>>(path: &Path) -> Option<T> {
+    let text = std::fs::read_to_string(path).ok()?;
+    match toml::from_str::<T>(&text) {
+        Ok(c) => Some(c),
         Err(e) => {
             eprintln!(
                 "Warning: failed to parse {}: {}. Using defaults.",
                 path.display(),
                 e
             );
-            Config::default()
+            None
         }
     }
 }
 
+/// The config files in effect: the user's, and the current repository's.
+#[derive(Debug, Default)]
+pub struct Layers {
+    pub user: Option<(PathBuf, Config)>,
+    pub repo: Option<(PathBuf, RepoConfig)>,
+}
+
+impl Layers {
+    /// Find and read both config files. Either may be absent.
+    pub fn read() -> Self {
+        let user = config_path()
+            .ok()
+            .and_then(|p| read_file(&p).map(|c| (p, c)));
+        let repo = crate::git::get_repo_root()
+            .ok()
+            .map(|root| repo_config_path(&root))
+            .and_then(|p| read_file(&p).map(|c| (p, c)));
+        Self { user, repo }
+    }
+
+    /// The merged config.
+    pub fn effective(&self) -> Config {
+        let user = self.user.as_ref().map(|(_, c)| c.clone()).unwrap_or_default();
+        match &self.repo {
+            Some((_, repo)) => user.merge_repo(repo),
+            None => user,
+        }
+    }
+
+    /// Every setting in the effective config as (`section.key`, TOML value,
+    /// origin) rows, where origin is the file the value came from, or
+    /// "merged" when policy guardrails combined both.
+    pub fn origins(&self) -> Vec<(String, String, String)> {
+        let flat = |v: Option<toml::Value>| {
+            let mut out = BTreeMap::new();
+            if let Some(v) = v {
+                flatten("", &v, &mut out);
+            }
+            out
+        };
+        let user = flat(self.user.as_ref().and_then(|(_, c)| toml::Value::try_from(c).ok()));
+        let repo = flat(self.repo.as_ref().and_then(|(_, c)| toml::Value::try_from(c).ok()));
+        let effective = flat(toml::Value::try_from(self.effective()).ok());
+        let label = |p: Option<&PathBuf>| p.map(|p| p.display().to_string()).unwrap_or_default();
+        effective
+            .into_iter()
+            .map(|(key, value)| {
+                let origin = if user.get(&key) == Some(&value) {
+                    label(self.user.as_ref().map(|(p, _)| p))
+                } else if repo.get(&key) == Some(&value) {
+                    label(self.repo.as_ref().map(|(p, _)| p))
+                } else {
+                    "merged".to_string()
+                };
+                (key, value.to_string(), origin)
+            })
+            .collect()
+    }
+}
+
+/// Flatten nested tables into dotted keys; arrays and scalars are leaves.
+fn flatten(prefix: &str, value: &toml::Value, out: &mut BTreeMap<String, toml::Value>) {
+    match value {
+        toml::Value::Table(table) => {
+            for (k, v) in table {
+                let key = if prefix.is_empty() { k.clone() } else { format!("{}.{}", prefix, k) };
+                flatten(&key, v, out);
+            }
+        }
+        leaf => {
+            out.insert(prefix.to_string(), leaf.clone());
+        }
+    }
+}
+
+/// Load the effective config: the user's config file layered over the
+/// current repository's `.github/pr-loop.toml`. Missing files count as
+/// empty.
+pub fn load() -> Config {
+    Layers::read().effective()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -158,6 +611,233 @@ bind = []
         assert_eq!(c.hub_binds(), vec!["127.0.0.1".to_string()]);
     }
 
+    #[test]
+    fn parses_checks_section() {
+        let c = parse(
+            r#"[checks]
+exclude_apps = ["codecov", "sonarqubecloud"]
+delayed_checks = ["deploy-preview*"]
+"#,
+        );
+        assert_eq!(c.delayed_checks(), vec!["deploy-preview*".to_string()]);
+        assert_eq!(
+            c.exclude_apps(),
+            vec!["codecov".to_string(), "sonarqubecloud".to_string()]
+        );
+        assert!(Config::default().exclude_apps().is_empty());
+    }
+
+    #[test]
+    fn parses_working_hours_section() {
+        let c = parse(
+            r#"[working_hours]
+start = "09:00"
+end = "18:00"
+days = ["mon", "tue", "wed", "thu"]
+timezone = "Europe/Berlin"
+off_hours_poll_interval = 300
+"#,
+        );
+        assert_eq!(c.working_hours.start.as_deref(), Some("09:00"));
+        assert_eq!(c.working_hours.days.as_ref().map(Vec::len), Some(4));
+        assert_eq!(c.working_hours.off_hours_poll_interval, Some(300));
+    }
+
+    #[test]
+    fn parses_circleci_org_tokens() {
+        let c = parse(
+            r#"[circleci.org."acme"]
+token_env = "ACME_CIRCLECI_TOKEN"
+
+[circleci.org.globex]
+"#,
+        );
+        assert_eq!(
+            c.circleci_org_token_envs(),
+            vec![
+                ("acme".to_string(), Some("ACME_CIRCLECI_TOKEN".to_string())),
+                ("globex".to_string(), None),
+            ]
+        );
+        assert!(Config::default().circleci_org_token_envs().is_empty());
+    }
+
+    #[test]
+    fn parses_circleci_log_cap() {
+        let c = parse("[circleci]\nmax_log_mb = 8\n");
+        assert_eq!(c.circleci_log_cap(), 8 * 1024 * 1024);
+        assert_eq!(Config::default().circleci_log_cap(), DEFAULT_LOG_DOWNLOAD_CAP);
+    }
+
+    #[test]
+    fn parses_credentials_section() {
+        let c = parse(
+            r#"[credentials]
+helper = "pass show pr-loop"
+keychain = true
+
+[credentials.op]
+circleci = "op://Engineering/CircleCI/token"
+"circleci/acme" = "op://Acme/CircleCI/token"
+"#,
+        );
+        let store = c.secret_store();
+        assert_eq!(store.helper.as_deref(), Some("pass show pr-loop"));
+        assert_eq!(store.op_refs.len(), 2);
+        assert_eq!(
+            store.op_refs.get("circleci/acme").map(String::as_str),
+            Some("op://Acme/CircleCI/token")
+        );
+        assert!(store.keychain);
+        assert!(!Config::default().secret_store().keychain);
+    }
+
+    #[test]
+    fn parses_policy_section() {
+        assert_eq!(Config::default().policy(), Policy::default());
+        let c = parse(
+            r#"[policy]
+allow_delete_comments = false
+require_confirm_for_ready = true
+"#,
+        );
+        let policy = c.policy();
+        assert!(!policy.allow_delete_comments);
+        assert!(policy.allow_resolve_human_threads);
+        assert!(policy.require_confirm_for_ready);
+    }
+
+    #[test]
+    fn parses_notify_section() {
+        assert!(!Config::default().notify_desktop());
+        let c = parse("[notify]\nwebhook = \"https://hooks.example/x\"\ndesktop = true\n");
+        assert_eq!(c.notify_webhook().as_deref(), Some("https://hooks.example/x"));
+        assert!(c.notify_desktop());
+    }
+
+    #[test]
+    fn parses_audit_section() {
+        assert!(Config::default().audit_enabled());
+        let c = parse("[audit]\nenabled = false\n");
+        assert!(!c.audit_enabled());
+    }
+
+    #[test]
+    fn parses_collapse_rules() {
+        let defaults = Config::default().collapse_rules();
+        assert!(defaults.matrix);
+        assert!(defaults.patterns.is_empty());
+
+        let c = parse(
+            r#"[checks]
+collapse_matrix = false
+collapse_patterns = ["e2e-shard-*"]
+"#,
+        );
+        let rules = c.collapse_rules();
+        assert!(!rules.matrix);
+        assert_eq!(rules.patterns, vec!["e2e-shard-*".to_string()]);
+    }
+
+    #[test]
+    fn repo_config_layers_under_user_config() {
+        let user = parse(
+            r#"[checks]
+exclude_apps = ["codecov"]
+
+[policy]
+allow_delete_comments = true
+
+[reply_templates]
+fixed-in = "Fixed in {commit}, thanks!"
+"#,
+        );
+        let repo: RepoConfig = toml::from_str(
+            r#"[checks]
+exclude_apps = ["sonarqubecloud"]
+delayed_checks = ["deploy-preview"]
+
+[policy]
+allow_delete_comments = false
+require_confirm_for_ready = true
+
+[escalation]
+after = 3
+
+[mentions]
+actionable = true
+
+[author_threads]
+policy = "informational"
+
+[reply_templates]
+fixed-in = "Fixed in {commit}."
+followup = "Filed {issue} to follow up."
+"#,
+        )
+        .unwrap();
+        let merged = user.merge_repo(&repo);
+        assert_eq!(merged.exclude_apps(), vec!["codecov".to_string()]);
+        assert_eq!(merged.delayed_checks(), vec!["deploy-preview".to_string()]);
+        // Team guardrails can't be loosened
+        assert!(!merged.policy().allow_delete_comments);
+        assert!(merged.policy().require_confirm_for_ready);
+        assert_eq!(merged.escalate_after(), Some(3));
+        assert!(merged.mentioned_others_actionable());
+        assert!(!Config::default().mentioned_others_actionable());
+        assert_eq!(merged.author_thread_policy(), AuthorThreadPolicy::Informational);
+        // Templates merge by name
+        assert_eq!(merged.reply_template("fixed-in"), Some("Fixed in {commit}, thanks!"));
+        assert_eq!(merged.reply_template_names(), vec!["fixed-in", "followup"]);
+    }
+
+    #[test]
+    fn repro_command_prefers_exact_name() {
+        let c = parse(
+            r#"[repro.commands]
+"test (*)" = "cargo test {tests}"
+"test (linux)" = "make test"
+"#,
+        );
+        assert_eq!(c.repro_command("test (linux)"), Some("make test"));
+        assert_eq!(c.repro_command("test (macos)"), Some("cargo test {tests}"));
+        assert_eq!(c.repro_command("lint"), None);
+    }
+
+    #[test]
+    fn repo_config_rejects_credentials() {
+        assert!(toml::from_str::<RepoConfig>("[credentials]\nhelper = \"sh -c evil\"\n").is_err());
+    }
+
+    #[test]
+    fn origins_name_the_contributing_file() {
+        let layers = Layers {
+            user: Some((PathBuf::from("/home/me/config.toml"), parse("[policy]\nallow_delete_comments = true\n"))),
+            repo: Some((
+                PathBuf::from("/repo/.github/pr-loop.toml"),
+                toml::from_str(
+                    "[checks]\ncollapse_matrix = false\n[policy]\nallow_delete_comments = false\n",
+                )
+                .unwrap(),
+            )),
+        };
+        assert_eq!(
+            layers.origins(),
+            vec![
+                (
+                    "checks.collapse_matrix".to_string(),
+                    "false".to_string(),
+                    "/repo/.github/pr-loop.toml".to_string()
+                ),
+                (
+                    "policy.allow_delete_comments".to_string(),
+                    "false".to_string(),
+                    "/repo/.github/pr-loop.toml".to_string()
+                ),
+            ]
+        );
+    }
+
     #[test]
     fn unknown_field_rejected() {
         let r: Result<Config, _> = toml::from_str(
@@ -175,7 +855,7 @@ nonsense_field = 1
         let prev_xdg = std::env::var("XDG_CONFIG_HOME").ok();
         unsafe { std::env::set_var("XDG_CONFIG_HOME", "/tmp/xdg"); }
         let p = config_path().unwrap();
-        assert_eq!(p, PathBuf::from("/tmp/xdg/pr-loop/config.toml"));
+        assert_eq!(p, PathBuf::from("/tmp/xdg").join("pr-loop").join("config.toml"));
         // restore
         match prev_xdg {
             Some(v) => unsafe { std::env::set_var("XDG_CONFIG_HOME", v); },
@@ -183,6 +863,8 @@ nonsense_field = 1
         }
     }
 
+    // Windows prefers APPDATA, which is always set
+    #[cfg(not(windows))]
     #[test]
     fn config_path_falls_back_to_home() {
         let prev_xdg = std::env::var("XDG_CONFIG_HOME").ok();
@@ -192,7 +874,7 @@ nonsense_field = 1
             std::env::set_var("HOME", "/tmp/home");
         }
         let p = config_path().unwrap();
-        assert_eq!(p, PathBuf::from("/tmp/home/.config/pr-loop/config.toml"));
+        assert_eq!(p, PathBuf::from("/tmp/home").join(".config").join("pr-loop").join("config.toml"));
         unsafe {
             if let Some(v) = prev_home { std::env::set_var("HOME", v); }
             if let Some(v) = prev_xdg { std::env::set_var("XDG_CONFIG_HOME", v); }
@@ -0,0 +1,133 @@
+// Editing and validating config files for `pr-loop config get/set/unset/validate`.
+// Edits go through toml_edit so comments and layout in a hand-written file
+// survive, and every edit is validated against the config schema before
+// it's written, so a typo can't leave the file unparseable.
+
+use crate::config::{Config, RepoConfig};
+use anyhow::{Context, Result};
+use toml_edit::{DocumentMut, Item, Table, Value};
+
+/// Split a dotted key like `checks.exclude_apps` or
+/// `circleci.org.acme.token_env` into its parts.
+fn key_parts(key: &str) -> Result<Vec<&str>> {
+    let parts: Vec<&str> = key.split('.').collect();
+    if parts.len() < 2 || parts.iter().any(|p| p.is_empty()) {
+        anyhow::bail!(
+            "Invalid key '{}': expected `section.key`, e.g. `checks.exclude_apps`",
+            key
+        );
+    }
+    Ok(parts)
+}
+
+/// Interpret a command-line value as TOML (`true`, `30`, `["a", "b"]`),
+/// falling back to a plain string so `codecov` needn't be quoted.
+pub fn parse_value(raw: &str) -> Value {
+    raw.parse::<Value>().unwrap_or_else(|_| Value::from(raw))
+}
+
+/// Set `key` to `raw` in the config text, returning the new text. Fails
+/// without changing anything if the result wouldn't be a valid config.
+pub fn set(text: &str, key: &str, raw: &str) -> Result<String> {
+    let parts = key_parts(key)?;
+    let mut doc: DocumentMut = text.parse().context("Config file is not valid TOML")?;
+    let (field, sections) = parts.split_last().expect("key has at least two parts");
+    let mut table: &mut Table = doc.as_table_mut();
+    for section in sections {
+        let entry = table.entry(section).or_insert_with(|| {
+            let mut t = Table::new();
+            t.set_implicit(true);
+            Item::Table(t)
+        });
+        table = entry
+            .as_table_mut()
+            .with_context(|| format!("'{}' is not a section", section))?;
+    }
+    table[field] = Item::Value(parse_value(raw));
+    let updated = doc.to_string();
+    validate(&updated).with_context(|| format!("Not setting {}", key))?;
+    Ok(updated)
+}
+
+/// Remove `key` from the config text, returning the new text. Errors if
+/// the key isn't set.
+pub fn unset(text: &str, key: &str) -> Result<String> {
+    let parts = key_parts(key)?;
+    let mut doc: DocumentMut = text.parse().context("Config file is not valid TOML")?;
+    let (field, sections) = parts.split_last().expect("key has at least two parts");
+    let mut table: &mut Table = doc.as_table_mut();
+    for section in sections {
+        table = table
+            .get_mut(section)
+            .and_then(Item::as_table_mut)
+            .with_context(|| format!("{} is not set", key))?;
+    }
+    if table.remove(field).is_none() {
+        anyhow::bail!("{} is not set", key);
+    }
+    Ok(doc.to_string())
+}
+
+/// Check user config text against the schema. Unknown keys and type
+/// mismatches are reported with their location and the expected fields.
+pub fn validate(text: &str) -> Result<()> {
+    toml::from_str::<Config>(text).map_err(|e| anyhow::anyhow!("{}", e.to_string().trim_end()))?;
+    Ok(())
+}
+
+/// Check repository config text against the (narrower) repo schema.
+pub fn validate_repo(text: &str) -> Result<()> {
+    toml::from_str::<RepoConfig>(text)
+        .map_err(|e| anyhow::anyhow!("{}", e.to_string().trim_end()))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn set_preserves_comments_and_infers_types() {
+        let text = "# my settings\n[checks]\nexclude_apps = [\"codecov\"] # noisy\n";
+        let text = set(text, "checks.collapse_matrix", "false").unwrap();
+        let text = set(&text, "policy.allow_delete_comments", "false").unwrap();
+        let text = set(&text, "circleci.org.acme.token_env", "ACME_TOKEN").unwrap();
+        assert!(text.starts_with("# my settings\n"));
+        assert!(text.contains("exclude_apps = [\"codecov\"] # noisy"));
+
+        let cfg: Config = toml::from_str(&text).unwrap();
+        assert_eq!(cfg.checks.collapse_matrix, Some(false));
+        assert!(!cfg.policy().allow_delete_comments);
+        assert_eq!(
+            cfg.circleci_org_token_envs(),
+            vec![("acme".to_string(), Some("ACME_TOKEN".to_string()))]
+        );
+    }
+
+    #[test]
+    fn set_rejects_unknown_keys_and_wrong_types() {
+        let err = set("", "checks.exclude_app", "codecov").unwrap_err();
+        assert!(format!("{:#}", err).contains("unknown field `exclude_app`"));
+
+        let err = set("", "hub.port", "not-a-port").unwrap_err();
+        assert!(format!("{:#}", err).contains("invalid type"));
+
+        assert!(set("", "port", "1").is_err());
+    }
+
+    #[test]
+    fn unset_removes_key() {
+        let text = "[hub]\nport = 9000\nbind = [\"0.0.0.0\"]\n";
+        let text = unset(text, "hub.port").unwrap();
+        assert_eq!(text, "[hub]\nbind = [\"0.0.0.0\"]\n");
+        assert!(unset(&text, "hub.port").is_err());
+        assert!(unset(&text, "web.bind").is_err());
+    }
+
+    #[test]
+    fn validate_repo_is_narrower() {
+        let text = "[credentials]\nkeychain = true\n";
+        assert!(validate(text).is_ok());
+        assert!(validate_repo(text).is_err());
+    }
+}
@@ -0,0 +1,695 @@
+// Agent briefing document for `pr-loop context`.
+// Gathers everything an agent needs at the start of an iteration (PR
+// description, linked issues, diff stat, actionable threads, failing checks,
+// and the recommended next action) into one Markdown or JSON document.
+// The JSON form is a contract with agent prompts and tooling: it's described
+// by schema/briefing.v<N>.schema.json (printed by `pr-loop schema`) and
+// carries its schema version, so consumers can check compatibility across
+// upgrades. The model always serializes as the latest version; older
+// versions (`--output-version`) are derived from that by `downgrade`.
+
+use crate::analysis::NextAction;
+use crate::annotations::Annotation;
+use crate::checks::ChecksSummary;
+use crate::circleci::{CircleCiFailureInfo, TestFailure};
+use crate::config::Config;
+use crate::files::{format_diff_stat, ChangedFile};
+use crate::issues::{acceptance_criteria, LinkedIssue};
+use crate::log_window::looks_like_error;
+use crate::repro::{suggested_commands, Toolchain};
+use crate::comment_text;
+use crate::threads::{comment_reference, ActionableThread};
+use crate::workflows;
+use serde::Serialize;
+
+/// Output format for the briefing and the default analysis.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum OutputFormat {
+    #[default]
+    Markdown,
+    Json,
+}
+
+/// Version of the JSON briefing's structure. Bump it only when a field must
+/// be removed, renamed, or change meaning (adding a field doesn't need a
+/// bump): add a schema file for the new version and teach `downgrade` to
+/// turn the new form into the previous one.
+///
+/// History:
+/// - 1: initial version.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// JSON Schema for each briefing version, oldest first.
+const BRIEFING_SCHEMAS: &[&str] = &[include_str!("../schema/briefing.v1.schema.json")];
+
+/// JSON Schema for a briefing version (1..=SCHEMA_VERSION).
+pub fn briefing_schema(version: u32) -> Option<&'static str> {
+    BRIEFING_SCHEMAS.get((version as usize).checked_sub(1)?).copied()
+}
+
+/// Maximum error lines extracted from each failed step's log.
+const MAX_ERROR_LINES: usize = 20;
+
+#[derive(Debug, Serialize)]
+pub struct Briefing {
+    /// Always SCHEMA_VERSION.
+    pub schema_version: u32,
+    pub pr: PrSummary,
+    pub linked_issues: Vec<IssueSummary>,
+    pub diff_stat: DiffStat,
+    pub threads: Vec<ThreadSummary>,
+    pub failing_checks: Vec<FailingCheck>,
+    pub next_action: ActionSummary,
+    /// Everything the PR needs, most pressing first; `next_action` is the first.
+    pub actions: Vec<ActionSummary>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PrSummary {
+    pub owner: String,
+    pub repo: String,
+    pub number: u64,
+    pub title: String,
+    pub url: String,
+    /// Description with pr-loop's status block removed.
+    pub body: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct IssueSummary {
+    pub reference: String,
+    pub title: String,
+    pub url: String,
+    pub state: String,
+    pub acceptance_criteria: Vec<String>,
+}
+
+impl IssueSummary {
+    pub fn new(issue: &LinkedIssue, owner: &str, repo: &str) -> Self {
+        Self {
+            reference: issue.reference(owner, repo),
+            title: issue.title.clone(),
+            url: issue.url.clone(),
+            state: issue.state.clone(),
+            acceptance_criteria: acceptance_criteria(&issue.body),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct DiffStat {
+    pub additions: u64,
+    pub deletions: u64,
+    pub files: Vec<ChangedFile>,
+}
+
+impl DiffStat {
+    pub fn new(files: Vec<ChangedFile>) -> Self {
+        Self {
+            additions: files.iter().map(|f| f.additions).sum(),
+            deletions: files.iter().map(|f| f.deletions).sum(),
+            files,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct ThreadSummary {
+    pub id: String,
+    /// "path:line" (or a range, file, or outdated line).
+    pub location: String,
+    pub path: Option<String>,
+    pub line: Option<u64>,
+    pub start_line: Option<u64>,
+    pub original_line: Option<u64>,
+    pub file_level: bool,
+    pub removed_code: bool,
+    pub tag: Option<&'static str>,
+    pub outdated: bool,
+    pub diff_hunk: Option<String>,
+    pub comments: Vec<CommentSummary>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CommentSummary {
+    pub id: String,
+    /// Permalink to the comment.
+    pub url: Option<String>,
+    pub author: String,
+    pub body: String,
+}
+
+impl ThreadSummary {
+    pub fn new(actionable: &ActionableThread) -> Self {
+        let thread = &actionable.thread;
+        Self {
+            id: thread.id.clone(),
+            location: actionable.location(),
+            path: thread.path.clone(),
+            line: thread.line,
+            start_line: thread.start_line,
+            original_line: thread.original_line,
+            file_level: thread.is_file_level,
+            removed_code: thread.on_removed_code,
+            tag: actionable.tag.map(|t| t.label()),
+            outdated: thread.is_outdated,
+            // The hunk on the first comment shows the code under discussion
+            diff_hunk: thread.diff_hunk().map(String::from),
+            comments: thread
+                .comments
+                .iter()
+                .map(|c| CommentSummary {
+                    id: c.id.clone(),
+                    url: c.url.clone(),
+                    author: c.author.clone(),
+                    body: comment_text::to_text(&c.body),
+                })
+                .collect(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct FailingCheck {
+    pub name: String,
+    pub url: Option<String>,
+    /// Errors extracted from annotations, test results, and step logs.
+    pub errors: Vec<String>,
+    /// Commands that reproduce or verify the failure locally (see
+    /// `add_suggested_commands`).
+    pub suggested_commands: Vec<String>,
+}
+
+/// Fill in each failing check's `suggested_commands`: its `[repro.commands]`
+/// entry, or commands for the failing tests CI reported for it.
+pub fn add_suggested_commands(
+    checks: &mut [FailingCheck],
+    ci_info: &CircleCiFailureInfo,
+    cfg: &Config,
+    toolchain: Option<Toolchain>,
+) {
+    for check in checks {
+        let failures: Vec<TestFailure> = ci_info
+            .test_failures
+            .iter()
+            .filter(|t| job_matches_check(&t.job_name, &check.name))
+            .cloned()
+            .collect();
+        check.suggested_commands =
+            suggested_commands(cfg.repro_command(&check.name), toolchain, &check.name, &failures);
+    }
+}
+
+/// Collect failing checks with the errors found for each. CI errors whose job
+/// doesn't correspond to a failing check get an entry of their own.
+pub fn failing_checks(
+    checks: &ChecksSummary,
+    annotations: &[Annotation],
+    ci_info: &CircleCiFailureInfo,
+) -> Vec<FailingCheck> {
+    let mut result: Vec<FailingCheck> = checks
+        .failed()
+        .iter()
+        .map(|c| FailingCheck {
+            name: c.name.clone(),
+            url: c.url.clone(),
+            errors: vec![],
+            suggested_commands: vec![],
+        })
+        .collect();
+
+    let mut push_error = |job: &str, error: String| {
+        match result.iter_mut().find(|c| job_matches_check(job, &c.name)) {
+            Some(check) => check.errors.push(error),
+            None => result.push(FailingCheck {
+                name: job.to_string(),
+                url: None,
+                errors: vec![error],
+                suggested_commands: vec![],
+            }),
+        }
+    };
+
+    for a in annotations.iter().filter(|a| a.level == "failure") {
+        let location = a.location();
+        let message = a.message.trim_end().to_string();
+        let error = if location.is_empty() {
+            message
+        } else {
+            format!("{}: {}", location, message)
+        };
+        push_error(&a.check_name, error);
+    }
+    for t in &ci_info.test_failures {
+        let test = match &t.location {
+            Some(location) => format!("{} / {} ({})", t.classname, t.test_name, location),
+            None => format!("{} / {}", t.classname, t.test_name),
+        };
+        let error = if t.message.is_empty() {
+            test
+        } else {
+            format!("{}: {}", test, first_line(&t.message))
+        };
+        push_error(&t.job_name, error);
+    }
+    for log in &ci_info.step_logs {
+        let text = if log.error.trim().is_empty() { &log.output } else { &log.error };
+        let lines = extract_error_lines(text, MAX_ERROR_LINES);
+        if !lines.is_empty() {
+            let step = match &log.container {
+                Some(container) => format!("step \"{}\" ({})", log.step_name, container),
+                None => format!("step \"{}\"", log.step_name),
+            };
+            push_error(&log.job_name, format!("{}:\n{}", step, lines.join("\n")));
+        }
+    }
+    result
+}
+
+/// CircleCI reports jobs by bare name while the check is "ci/circleci: name".
+fn job_matches_check(job: &str, check: &str) -> bool {
+    check == job || check.ends_with(&format!(": {}", job))
+}
+
+fn first_line(s: &str) -> &str {
+    s.lines().next().unwrap_or("").trim()
+}
+
+/// Pull the lines that look like errors out of a log, or its tail if none do.
+fn extract_error_lines(log: &str, max_lines: usize) -> Vec<String> {
+    let lines: Vec<&str> = log.lines().filter(|l| !l.trim().is_empty()).collect();
+    let errors: Vec<&str> = lines.iter().filter(|l| looks_like_error(l)).copied().collect();
+    let picked = if errors.is_empty() {
+        &lines[lines.len().saturating_sub(max_lines)..]
+    } else {
+        &errors[..errors.len().min(max_lines)]
+    };
+    picked.iter().map(|l| l.trim_end().to_string()).collect()
+}
+
+#[derive(Debug, Serialize)]
+pub struct ActionSummary {
+    /// "respond_to_comments", "resolve_conflicts", "fix_ci_failures", "wait_for_ci",
+    /// "rebase", "await_human_input", or "pr_ready"
+    pub kind: &'static str,
+    pub summary: String,
+}
+
+impl ActionSummary {
+    pub fn new(action: &NextAction) -> Self {
+        match action {
+            NextAction::RespondToComments { threads, .. } => Self {
+                kind: "respond_to_comments",
+                summary: format!(
+                    "Respond to {} review thread{} with `pr-loop reply`.",
+                    threads.len(),
+                    if threads.len() == 1 { "" } else { "s" }
+                ),
+            },
+            NextAction::ResolveConflicts { .. } => Self {
+                kind: "resolve_conflicts",
+                summary: "Rebase onto the base branch to resolve merge conflicts.".to_string(),
+            },
+            NextAction::FixCiFailures { failed_check_names } => Self {
+                kind: "fix_ci_failures",
+                summary: format!("Fix failing checks: {}.", failed_check_names.join(", ")),
+            },
+            NextAction::WaitForCi { pending_check_names } => Self {
+                kind: "wait_for_ci",
+                summary: format!(
+                    "Wait for {} pending check{}.",
+                    pending_check_names.len(),
+                    if pending_check_names.len() == 1 { "" } else { "s" }
+                ),
+            },
+            NextAction::Rebase { rebase } => Self {
+                kind: "rebase",
+                summary: format!("Rebase onto {}: the branch is {}.", rebase.base, rebase.describe()),
+            },
+            NextAction::AwaitHumanInput { threads } => Self {
+                kind: "await_human_input",
+                summary: format!(
+                    "Wait for reviewers to answer Claude's question{} in {} thread{}.",
+                    if threads.len() == 1 { "" } else { "s" },
+                    threads.len(),
+                    if threads.len() == 1 { "" } else { "s" }
+                ),
+            },
+            NextAction::PrReady => Self {
+                kind: "pr_ready",
+                summary: "CI is passing and no comments need a response.".to_string(),
+            },
+        }
+    }
+}
+
+impl Briefing {
+    /// JSON in the given schema version (1..=SCHEMA_VERSION).
+    pub fn to_json(&self, version: u32) -> String {
+        let mut value = serde_json::to_value(self).expect("briefing serializes");
+        for from in (version + 1..=SCHEMA_VERSION).rev() {
+            downgrade(&mut value, from);
+        }
+        serde_json::to_string_pretty(&value).expect("briefing serializes")
+    }
+
+    pub fn to_markdown(&self) -> String {
+        let mut out = String::new();
+        let pr = &self.pr;
+        out.push_str(&format!(
+            "# {}/{}#{}: {}\n\n{}\n\n",
+            pr.owner, pr.repo, pr.number, pr.title, pr.url
+        ));
+
+        out.push_str("## Next Action\n\n");
+        out.push_str(&format!("**{}** — {}\n\n", self.next_action.kind, self.next_action.summary));
+        let commands = workflows::for_next_action(self.next_action.kind);
+        if !commands.is_empty() {
+            out.push_str("Commands:\n\n");
+            for example in commands {
+                out.push_str(&format!("- `{}` — {}\n", example.command, example.purpose));
+            }
+            out.push('\n');
+        }
+        if self.actions.len() > 1 {
+            out.push_str("Also:\n\n");
+            for action in &self.actions[1..] {
+                out.push_str(&format!("- **{}** — {}\n", action.kind, action.summary));
+            }
+            out.push('\n');
+        }
+
+        out.push_str("## Description\n\n");
+        let body = pr.body.trim();
+        out.push_str(if body.is_empty() { "_No description._" } else { body });
+        out.push_str("\n\n");
+
+        if !self.linked_issues.is_empty() {
+            out.push_str("## Linked Issues\n\n");
+            for issue in &self.linked_issues {
+                out.push_str(&format!(
+                    "- **[{}]({})** {} ({})\n",
+                    issue.reference,
+                    issue.url,
+                    issue.title,
+                    issue.state.to_lowercase()
+                ));
+                for criterion in &issue.acceptance_criteria {
+                    out.push_str(&format!("  - [ ] {}\n", criterion));
+                }
+            }
+            out.push('\n');
+        }
+
+        out.push_str("## Changed Files\n\n");
+        out.push_str(&format!("{}\n\n", format_diff_stat(&self.diff_stat.files)));
+        for f in &self.diff_stat.files {
+            out.push_str(&format!("- `{}` +{} −{}\n", f.path, f.additions, f.deletions));
+        }
+        if !self.diff_stat.files.is_empty() {
+            out.push('\n');
+        }
+
+        out.push_str(&format!("## Actionable Threads ({})\n\n", self.threads.len()));
+        for t in &self.threads {
+            let mut heading = format!("### {} (thread `{}`)", t.location, t.id);
+            if let Some(tag) = t.tag {
+                heading.push_str(&format!(" [{}]", tag));
+            }
+            if t.outdated && !t.location.ends_with("(outdated)") {
+                heading.push_str(" (outdated)");
+            }
+            out.push_str(&heading);
+            out.push_str("\n\n");
+            if let Some(hunk) = &t.diff_hunk {
+                out.push_str(&format!("```diff\n{}\n```\n\n", hunk.trim_end()));
+            }
+            for c in &t.comments {
+                out.push_str(&format!(
+                    "**@{}** ({}):\n",
+                    c.author,
+                    comment_reference(&c.id, c.url.as_deref())
+                ));
+                for line in c.body.lines() {
+                    out.push_str(&format!("> {}\n", line));
+                }
+                out.push('\n');
+            }
+        }
+
+        out.push_str(&format!("## Failing Checks ({})\n\n", self.failing_checks.len()));
+        for check in &self.failing_checks {
+            match &check.url {
+                Some(url) => out.push_str(&format!("### {} ({})\n\n", check.name, url)),
+                None => out.push_str(&format!("### {}\n\n", check.name)),
+            }
+            for error in &check.errors {
+                out.push_str(&format!("```\n{}\n```\n\n", error.trim_end()));
+            }
+            if !check.suggested_commands.is_empty() {
+                out.push_str("To reproduce locally:\n\n```bash\n");
+                for command in &check.suggested_commands {
+                    out.push_str(&format!("{}\n", command));
+                }
+                out.push_str("```\n\n");
+            }
+        }
+        out
+    }
+}
+
+/// Turn a version `from` briefing into version `from - 1`. There's only
+/// one version so far; each breaking change adds its inverse here.
+fn downgrade(value: &mut serde_json::Value, from: u32) {
+    value["schema_version"] = (from - 1).into();
+}
+
+/// Check `value` against the subset of JSON Schema the published schemas
+/// use: type, const, enum, properties, required, additionalProperties,
+/// and items.
+#[cfg(test)]
+pub(crate) fn conforms(value: &serde_json::Value, schema: &serde_json::Value, at: &str) -> Result<(), String> {
+    use serde_json::Value;
+    if let Some(c) = schema.get("const") {
+        return if value == c { Ok(()) } else { Err(format!("{}: expected {}", at, c)) };
+    }
+    if let Some(Value::Array(options)) = schema.get("enum") {
+        return if options.contains(value) { Ok(()) } else { Err(format!("{}: {} not allowed", at, value)) };
+    }
+    let types: Vec<&str> = match schema.get("type") {
+        Some(Value::String(t)) => vec![t.as_str()],
+        Some(Value::Array(ts)) => ts.iter().filter_map(|t| t.as_str()).collect(),
+        _ => vec![],
+    };
+    let type_ok = types.iter().any(|t| match *t {
+        "object" => value.is_object(),
+        "array" => value.is_array(),
+        "string" => value.is_string(),
+        "integer" => value.is_u64() || value.is_i64(),
+        "boolean" => value.is_boolean(),
+        "null" => value.is_null(),
+        _ => false,
+    });
+    if !type_ok {
+        return Err(format!("{}: {} is not {:?}", at, value, types));
+    }
+    if let (Value::Object(obj), Some(Value::Object(props))) = (value, schema.get("properties")) {
+        for required in schema["required"].as_array().into_iter().flatten() {
+            let key = required.as_str().unwrap();
+            if !obj.contains_key(key) {
+                return Err(format!("{}: missing {}", at, key));
+            }
+        }
+        for (key, v) in obj {
+            match props.get(key) {
+                Some(s) => conforms(v, s, &format!("{}.{}", at, key))?,
+                None => return Err(format!("{}: unexpected field {}", at, key)),
+            }
+        }
+    }
+    if let (Value::Array(items), Some(item_schema)) = (value, schema.get("items")) {
+        for (i, item) in items.iter().enumerate() {
+            conforms(item, item_schema, &format!("{}[{}]", at, i))?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::checks::{Check, CheckSource, CheckStatus};
+    use crate::circleci::{FailedStepLog, TestFailure};
+
+    fn failed_check(name: &str) -> Check {
+        Check {
+            name: name.to_string(),
+            status: CheckStatus::Fail,
+            url: Some(format!("https://ci/{}", name)),
+            source: CheckSource::CheckRun,
+            started_at: None,
+            attempts: 1,
+            app: None,
+        }
+    }
+
+    #[test]
+    fn errors_are_attached_to_their_checks() {
+        let checks = ChecksSummary {
+            checks: vec![failed_check("ci/circleci: test"), failed_check("lint")],
+        };
+        let annotations = vec![Annotation {
+            check_name: "lint".to_string(),
+            path: "src/a.rs".to_string(),
+            start_line: Some(3),
+            end_line: None,
+            level: "failure".to_string(),
+            message: "unused variable".to_string(),
+        }];
+        let ci_info = CircleCiFailureInfo {
+            jobs: vec![],
+            step_logs: vec![FailedStepLog {
+                job_name: "deploy".to_string(),
+                step_name: "push".to_string(),
+                output: "ok\nError: denied\n".to_string(),
+                error: String::new(),
+                container: None,
+                full_log_url: None,
+            }],
+            test_failures: vec![TestFailure {
+                job_name: "test".to_string(),
+                classname: "FooTest".to_string(),
+                test_name: "bar".to_string(),
+                message: "expected 1\nstack...".to_string(),
+                location: None,
+            }],
+            fetch_errors: vec![],
+            cache_suspects: vec![],
+        };
+
+        let result = failing_checks(&checks, &annotations, &ci_info);
+        assert_eq!(result.len(), 3);
+        assert_eq!(result[0].errors, vec!["FooTest / bar: expected 1"]);
+        assert_eq!(result[1].errors, vec!["src/a.rs:3: unused variable"]);
+        assert_eq!(result[2].name, "deploy");
+        assert_eq!(result[2].errors, vec!["step \"push\":\nError: denied"]);
+
+        let mut result = result;
+        let cfg: Config = toml::from_str("[repro.commands]\nlint = \"cargo clippy\"\n").unwrap();
+        add_suggested_commands(&mut result, &ci_info, &cfg, Some(Toolchain::Cargo));
+        assert_eq!(result[0].suggested_commands, vec!["cargo test -p FooTest bar"]);
+        assert_eq!(result[1].suggested_commands, vec!["cargo clippy"]);
+        assert!(result[2].suggested_commands.is_empty());
+    }
+
+    #[test]
+    fn extract_error_lines_falls_back_to_tail() {
+        let log = "one\ntwo\nthree\n";
+        assert_eq!(extract_error_lines(log, 2), vec!["two", "three"]);
+        let log = "compiling\nerror[E0308]: mismatched types\nmore\ntest foo ... FAILED\n";
+        assert_eq!(
+            extract_error_lines(log, 5),
+            vec!["error[E0308]: mismatched types", "test foo ... FAILED"]
+        );
+    }
+
+    fn sample_briefing() -> Briefing {
+        Briefing {
+            schema_version: SCHEMA_VERSION,
+            pr: PrSummary {
+                owner: "o".to_string(),
+                repo: "r".to_string(),
+                number: 5,
+                title: "Add retries".to_string(),
+                url: "https://github.com/o/r/pull/5".to_string(),
+                body: String::new(),
+            },
+            linked_issues: vec![IssueSummary {
+                reference: "#2".to_string(),
+                title: "Flaky fetch".to_string(),
+                url: "https://github.com/o/r/issues/2".to_string(),
+                state: "OPEN".to_string(),
+                acceptance_criteria: vec!["Retries 3 times".to_string()],
+            }],
+            diff_stat: DiffStat::new(vec![]),
+            threads: vec![],
+            failing_checks: vec![],
+            next_action: ActionSummary::new(&NextAction::PrReady),
+            actions: vec![ActionSummary::new(&NextAction::PrReady)],
+        }
+    }
+
+    #[test]
+    fn markdown_contains_all_sections() {
+        let briefing = sample_briefing();
+        let md = briefing.to_markdown();
+        assert!(md.starts_with("# o/r#5: Add retries\n"));
+        assert!(md.contains("**pr_ready**"));
+        assert!(md.contains("_No description._"));
+        assert!(md.contains("  - [ ] Retries 3 times\n"));
+        assert!(md.contains("0 files changed, +0 −0"));
+        assert!(md.contains("## Actionable Threads (0)"));
+
+        let json: serde_json::Value =
+            serde_json::from_str(&briefing.to_json(SCHEMA_VERSION)).unwrap();
+        assert_eq!(json["next_action"]["kind"], "pr_ready");
+        assert_eq!(json["linked_issues"][0]["acceptance_criteria"][0], "Retries 3 times");
+    }
+
+    #[test]
+    fn every_version_matches_its_published_schema() {
+        let mut briefing = sample_briefing();
+        briefing.diff_stat = DiffStat::new(vec![ChangedFile {
+            path: "src/a.rs".to_string(),
+            additions: 3,
+            deletions: 1,
+            change_type: "MODIFIED".to_string(),
+        }]);
+        briefing.threads = vec![ThreadSummary {
+            id: "T1".to_string(),
+            location: "src/a.rs:3".to_string(),
+            path: Some("src/a.rs".to_string()),
+            line: Some(3),
+            start_line: Some(2),
+            original_line: Some(3),
+            file_level: false,
+            removed_code: false,
+            tag: Some("nit"),
+            outdated: false,
+            diff_hunk: None,
+            comments: vec![CommentSummary {
+                id: "C1".to_string(),
+                url: Some("https://github.com/o/r/pull/5#discussion_r1".to_string()),
+                author: "reviewer".to_string(),
+                body: "rename".to_string(),
+            }],
+        }];
+        briefing.failing_checks = vec![FailingCheck {
+            name: "lint".to_string(),
+            url: None,
+            errors: vec!["boom".to_string()],
+            suggested_commands: vec!["cargo clippy".to_string()],
+        }];
+
+        assert!(briefing_schema(0).is_none());
+        assert!(briefing_schema(SCHEMA_VERSION + 1).is_none());
+        for version in 1..=SCHEMA_VERSION {
+            let schema: serde_json::Value =
+                serde_json::from_str(briefing_schema(version).unwrap()).unwrap();
+            assert_eq!(schema["properties"]["schema_version"]["const"], version);
+            assert!(schema["$id"].as_str().unwrap().ends_with(&format!(":{}", version)));
+
+            let json: serde_json::Value = serde_json::from_str(&briefing.to_json(version)).unwrap();
+            assert_eq!(json["schema_version"], version);
+            conforms(&json, &schema, "$").unwrap_or_else(|e| panic!("v{}: {}", version, e));
+
+            let mut broken = json.clone();
+            broken["next_action"]["kind"] = "retry".into();
+            assert!(conforms(&broken, &schema, "$").is_err());
+        }
+
+        let json: serde_json::Value = serde_json::from_str(&briefing.to_json(1)).unwrap();
+        assert_eq!(json["threads"][0]["location"], "src/a.rs:3");
+        assert_eq!(json["threads"][0]["line"], 3);
+    }
+}
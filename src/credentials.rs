@@ -1,13 +1,42 @@
-// Credential handling for GitHub and CircleCI APIs.
-// Validates gh CLI authentication and reads CircleCI token from environment.
+// Credential handling for GitHub, CircleCI, and Azure DevOps APIs.
+// Validates gh CLI authentication and reads CircleCI and Azure DevOps tokens
+// from environment.
+// Per-organization CircleCI tokens can be configured for contributors working
+// across orgs where one token doesn't have access to all projects.
+// Tokens missing from the environment can come from 1Password (`op read`), a
+// credential helper command, or the OS keychain, so they needn't sit in
+// plaintext env vars.
 
+use crate::request_log::TracedOutput;
 use anyhow::{Context, Result};
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
 use std::process::Command;
 
-/// Credentials needed to interact with CircleCI.
-#[derive(Debug, Clone)]
+/// Credentials needed to interact with CircleCI and Azure DevOps.
+#[derive(Debug, Clone, Default)]
 pub struct Credentials {
+    /// Default token, from CIRCLECI_TOKEN.
     pub circleci_token: Option<String>,
+    /// Tokens for specific organizations, keyed by lowercased org name.
+    pub circleci_org_tokens: HashMap<String, String>,
+    /// Azure DevOps personal access token, from AZURE_DEVOPS_TOKEN.
+    pub azure_devops_token: Option<String>,
+}
+
+impl Credentials {
+    /// CircleCI token for projects owned by `org`, falling back to the default token.
+    pub fn circleci_token_for(&self, org: &str) -> Option<&str> {
+        self.circleci_org_tokens
+            .get(&org.to_lowercase())
+            .or(self.circleci_token.as_ref())
+            .map(String::as_str)
+    }
+
+    /// Returns true if any CircleCI token (default or per-org) is available.
+    pub fn has_circleci_token(&self) -> bool {
+        self.circleci_token.is_some() || !self.circleci_org_tokens.is_empty()
+    }
 }
 
 /// Trait for obtaining credentials, allowing test implementations.
@@ -15,16 +44,135 @@ pub trait CredentialProvider {
     fn get_credentials(&self) -> Result<Credentials>;
 }
 
-/// Real credential provider that validates gh auth and reads CircleCI token from env.
-pub struct RealCredentialProvider;
+/// Secret name used for the default CircleCI token.
+pub const CIRCLECI_SECRET: &str = "circleci";
+
+/// Secret name used for an organization's CircleCI token, e.g. "circleci/acme".
+pub fn circleci_org_secret(org: &str) -> String {
+    format!("{}/{}", CIRCLECI_SECRET, org.to_lowercase())
+}
+
+/// A source of named secrets besides environment variables, allowing test
+/// implementations.
+pub trait SecretStore {
+    /// Look up a secret by name, returning None if it isn't available.
+    fn get_secret(&self, name: &str) -> Option<String>;
+}
+
+/// Looks secrets up via configured 1Password references (`op read`), a
+/// user-configured helper command, and/or the OS keychain (macOS `security`,
+/// or `secret-tool` elsewhere), in that order. Results are cached for the
+/// life of the process so each secret is resolved at most once.
+#[derive(Debug, Clone, Default)]
+pub struct RealSecretStore {
+    /// Secret name → 1Password secret reference, e.g. "op://Dev/CircleCI/token".
+    pub op_refs: BTreeMap<String, String>,
+    /// Command run as `sh -c '<helper> <name>'` (`cmd /C` on Windows); its
+    /// stdout is the secret.
+    pub helper: Option<String>,
+    /// Whether to consult the OS keychain (service "pr-loop", account = name).
+    pub keychain: bool,
+    cache: RefCell<HashMap<String, Option<String>>>,
+}
+
+impl RealSecretStore {
+    pub fn new(op_refs: BTreeMap<String, String>, helper: Option<String>, keychain: bool) -> Self {
+        Self {
+            op_refs,
+            helper,
+            keychain,
+            cache: RefCell::default(),
+        }
+    }
+
+    fn lookup(&self, name: &str) -> Option<String> {
+        if let Some(reference) = self.op_refs.get(name) {
+            let secret = run_secret_command("op", &["read", "--no-newline", reference]);
+            if secret.is_none() {
+                eprintln!(
+                    "Warning: `op read {}` failed (is the 1Password CLI signed in?)",
+                    reference
+                );
+            }
+            return secret;
+        }
+        if let Some(helper) = &self.helper {
+            let secret = if cfg!(windows) {
+                let script = format!("{} \"{}\"", helper, name);
+                run_secret_command("cmd", &["/C", &script])
+            } else {
+                let script = format!("{} \"$1\"", helper);
+                run_secret_command("sh", &["-c", &script, "sh", name])
+            };
+            if secret.is_some() {
+                return secret;
+            }
+        }
+        // No keychain integration on Windows yet; use a helper or 1Password there
+        if self.keychain && !cfg!(windows) {
+            let args: &[&str] = if cfg!(target_os = "macos") {
+                &["find-generic-password", "-s", KEYCHAIN_SERVICE, "-a", name, "-w"]
+            } else {
+                &["lookup", "service", KEYCHAIN_SERVICE, "account", name]
+            };
+            let program = if cfg!(target_os = "macos") { "security" } else { "secret-tool" };
+            return run_secret_command(program, args);
+        }
+        None
+    }
+}
+
+impl SecretStore for RealSecretStore {
+    fn get_secret(&self, name: &str) -> Option<String> {
+        if let Some(cached) = self.cache.borrow().get(name) {
+            return cached.clone();
+        }
+        let secret = self.lookup(name);
+        self.cache
+            .borrow_mut()
+            .insert(name.to_string(), secret.clone());
+        secret
+    }
+}
+
+/// Keychain service name under which pr-loop secrets are stored.
+const KEYCHAIN_SERVICE: &str = "pr-loop";
+
+/// Run a command and return its trimmed stdout if it succeeds with output.
+fn run_secret_command(program: &str, args: &[&str]) -> Option<String> {
+    let output = Command::new(program).args(args).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let secret = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    (!secret.is_empty()).then_some(secret)
+}
+
+/// Real credential provider that validates gh auth and reads CircleCI tokens
+/// from env, falling back to the secret store.
+pub struct RealCredentialProvider {
+    /// Org name → environment variable holding that org's CircleCI token, if any.
+    pub circleci_org_token_envs: Vec<(String, Option<String>)>,
+    pub secret_store: RealSecretStore,
+}
 
 impl CredentialProvider for RealCredentialProvider {
     fn get_credentials(&self) -> Result<Credentials> {
         // Validate gh CLI is authenticated (we use gh CLI for GitHub API calls)
         check_gh_auth()?;
-        let circleci_token = get_circleci_token();
+        let circleci_token =
+            get_circleci_token().or_else(|| self.secret_store.get_secret(CIRCLECI_SECRET));
+        let circleci_org_tokens = get_org_tokens(
+            &self.circleci_org_token_envs,
+            |var| std::env::var(var).ok(),
+            &self.secret_store,
+        );
 
-        Ok(Credentials { circleci_token })
+        Ok(Credentials {
+            circleci_token,
+            circleci_org_tokens,
+            azure_devops_token: get_azure_devops_token(),
+        })
     }
 }
 
@@ -32,7 +180,7 @@ impl CredentialProvider for RealCredentialProvider {
 fn check_gh_auth() -> Result<()> {
     let output = Command::new("gh")
         .args(["auth", "token"])
-        .output()
+        .traced_output()
         .context("Failed to run 'gh auth token'. Is the GitHub CLI installed?")?;
 
     if !output.status.success() {
@@ -51,6 +199,43 @@ fn get_circleci_token() -> Option<String> {
     std::env::var("CIRCLECI_TOKEN").ok().filter(|s| !s.is_empty())
 }
 
+/// Get the Azure DevOps token from AZURE_DEVOPS_TOKEN environment variable.
+fn get_azure_devops_token() -> Option<String> {
+    std::env::var("AZURE_DEVOPS_TOKEN").ok().filter(|s| !s.is_empty())
+}
+
+/// Resolve per-org tokens from their environment variables, falling back to
+/// the secret store. Orgs with no token anywhere are reported as warnings and
+/// skipped (the default token still applies).
+fn get_org_tokens(
+    org_token_envs: &[(String, Option<String>)],
+    env: impl Fn(&str) -> Option<String>,
+    store: &dyn SecretStore,
+) -> HashMap<String, String> {
+    let mut tokens = HashMap::new();
+    for (org, var) in org_token_envs {
+        let token = var
+            .as_deref()
+            .and_then(&env)
+            .filter(|s| !s.is_empty())
+            .or_else(|| store.get_secret(&circleci_org_secret(org)));
+        match token {
+            Some(token) => {
+                tokens.insert(org.to_lowercase(), token);
+            }
+            None => eprintln!(
+                "Warning: No CircleCI token found for org '{}' ({}).",
+                org,
+                match var {
+                    Some(var) => format!("{} is not set", var),
+                    None => format!("no secret named {}", circleci_org_secret(org)),
+                }
+            ),
+        }
+    }
+    tokens
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -64,6 +249,7 @@ mod tests {
         fn get_credentials(&self) -> Result<Credentials> {
             Ok(Credentials {
                 circleci_token: self.circleci_token.clone(),
+                ..Default::default()
             })
         }
     }
@@ -87,4 +273,112 @@ mod tests {
         let creds = provider.get_credentials().unwrap();
         assert!(creds.circleci_token.is_none());
     }
+
+    #[test]
+    fn org_token_overrides_default() {
+        let mut creds = Credentials {
+            circleci_token: Some("default".to_string()),
+            ..Default::default()
+        };
+        creds
+            .circleci_org_tokens
+            .insert("acme".to_string(), "acme_token".to_string());
+
+        assert_eq!(creds.circleci_token_for("Acme"), Some("acme_token"));
+        assert_eq!(creds.circleci_token_for("other"), Some("default"));
+
+        creds.circleci_token = None;
+        assert_eq!(creds.circleci_token_for("other"), None);
+        assert!(creds.has_circleci_token());
+    }
+
+    struct TestSecretStore {
+        secrets: HashMap<String, String>,
+    }
+
+    impl SecretStore for TestSecretStore {
+        fn get_secret(&self, name: &str) -> Option<String> {
+            self.secrets.get(name).cloned()
+        }
+    }
+
+    #[test]
+    fn get_org_tokens_skips_unset_vars() {
+        let envs = vec![
+            ("Acme".to_string(), Some("ACME_TOKEN".to_string())),
+            ("globex".to_string(), Some("GLOBEX_TOKEN".to_string())),
+            ("initech".to_string(), Some("EMPTY_TOKEN".to_string())),
+        ];
+        let store = TestSecretStore {
+            secrets: HashMap::new(),
+        };
+        let tokens = get_org_tokens(
+            &envs,
+            |var| match var {
+                "ACME_TOKEN" => Some("a".to_string()),
+                "EMPTY_TOKEN" => Some(String::new()),
+                _ => None,
+            },
+            &store,
+        );
+        assert_eq!(tokens.len(), 1);
+        assert_eq!(tokens.get("acme").map(String::as_str), Some("a"));
+    }
+
+    #[test]
+    fn get_org_tokens_falls_back_to_secret_store() {
+        let envs = vec![
+            ("acme".to_string(), Some("ACME_TOKEN".to_string())),
+            ("Globex".to_string(), None),
+        ];
+        let store = TestSecretStore {
+            secrets: HashMap::from([
+                ("circleci/acme".to_string(), "from_store".to_string()),
+                ("circleci/globex".to_string(), "g".to_string()),
+            ]),
+        };
+
+        // Env wins over the store when set
+        let tokens = get_org_tokens(&envs, |_| Some("from_env".to_string()), &store);
+        assert_eq!(tokens.get("acme").map(String::as_str), Some("from_env"));
+        assert_eq!(tokens.get("globex").map(String::as_str), Some("g"));
+
+        let tokens = get_org_tokens(&envs, |_| None, &store);
+        assert_eq!(tokens.get("acme").map(String::as_str), Some("from_store"));
+    }
+
+    // Helpers here are written for sh; Windows runs them with cmd
+    #[cfg(unix)]
+    #[test]
+    fn helper_command_output_is_used() {
+        let store = RealSecretStore::new(
+            BTreeMap::new(),
+            Some("printf 'secret-for-%s\\n'".to_string()),
+            false,
+        );
+        assert_eq!(
+            store.get_secret("circleci/acme").as_deref(),
+            Some("secret-for-circleci/acme")
+        );
+
+        let failing = RealSecretStore::new(BTreeMap::new(), Some("false".to_string()), false);
+        assert_eq!(failing.get_secret("circleci"), None);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn secrets_are_cached_for_process_lifetime() {
+        let dir = std::env::temp_dir().join(format!("pr-loop-secret-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let counter = dir.join("calls");
+        // The helper appends to a file on each call so we can count invocations.
+        let helper = format!("echo x >> '{}'; echo", counter.display());
+        let store = RealSecretStore::new(BTreeMap::new(), Some(helper), false);
+
+        assert_eq!(store.get_secret("circleci").as_deref(), Some("circleci"));
+        assert_eq!(store.get_secret("circleci").as_deref(), Some("circleci"));
+        let calls = std::fs::read_to_string(&counter).unwrap();
+        assert_eq!(calls.lines().count(), 1);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
 }
@@ -0,0 +1,529 @@
+// `pr-loop debug-bundle`: package what's needed to report a wrong analysis.
+// The bundle is a .tar.gz with version info, the effective config, recent
+// gh request metadata (see request_log), and a snapshot of the inputs to
+// the last analysis, so "it told me the PR was ready but it wasn't" can be
+// reported with evidence, and `pr-loop replay` can rerun the decision
+// engine on the snapshot without access to the PR. Everything in it is
+// redacted: no comment text, no request bodies, no credentials, and the
+// home directory is shortened to `~`.
+
+use crate::analysis::NextAction;
+use crate::audit::STATE_DIR;
+use crate::behind::Rebase;
+use crate::check_run;
+use crate::checks::{Check, CheckSource, CheckStatus, ChecksSummary};
+use crate::config::{self, Layers};
+use crate::files::ChangedFile;
+use crate::github::{MergeableStatus, PrContext};
+use crate::mentions::mentioned_logins;
+use crate::request_log;
+use crate::threads::{
+    PendingReview, ReviewThread, ThreadComment, CLAUDE_MARKER, PAPERCLIP_EMOJI,
+    PAPERCLIP_SHORTCODE,
+};
+use anyhow::{Context, Result};
+use flate2::Compression;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::Read;
+use std::path::Path;
+use std::process::Command;
+
+const SNAPSHOT_FILE: &str = "last-analysis.json";
+/// Request log entries included in a bundle.
+const BUNDLED_REQUESTS: usize = 200;
+
+/// The inputs and result of an analysis, minus anything people wrote.
+/// Enough is kept to rerun the decision engine (`pr-loop replay`): which
+/// comments are Claude's, carry a paperclip, or @mention someone is recorded
+/// instead of their text. Threads left out as addressed to someone else were
+/// already dropped from `threads`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AnalysisSnapshot {
+    pub at: String,
+    pub owner: String,
+    pub repo: String,
+    pub pr_number: u64,
+    /// The recommendation's headline, e.g. "WAITING: CI checks in progress".
+    pub recommendation: String,
+    /// Checks after filtering, as the analysis saw them.
+    pub checks: Vec<CheckSnapshot>,
+    pub threads: Vec<ThreadSnapshot>,
+    #[serde(default = "unknown_mergeable")]
+    pub mergeable_status: MergeableStatus,
+    #[serde(default)]
+    pub pending_reviews: Vec<PendingReview>,
+    #[serde(default)]
+    pub changed_files: Vec<ChangedFile>,
+    /// The rebase the analysis recommended, if the branch was far behind.
+    #[serde(default)]
+    pub rebase: Option<Rebase>,
+    /// With `[mentions] actionable = true`, the logins each actionable
+    /// thread is addressed to instead of the agent, by thread ID.
+    #[serde(default)]
+    pub side_conversations: HashMap<String, Vec<String>>,
+}
+
+fn unknown_mergeable() -> MergeableStatus {
+    MergeableStatus::Unknown
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CheckSnapshot {
+    pub name: String,
+    pub status: CheckStatus,
+    pub source: CheckSource,
+    pub url: Option<String>,
+    pub started_at: Option<String>,
+    pub app: Option<String>,
+    pub attempts: u32,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ThreadSnapshot {
+    pub id: String,
+    pub path: Option<String>,
+    pub line: Option<u64>,
+    #[serde(default)]
+    pub start_line: Option<u64>,
+    #[serde(default)]
+    pub original_line: Option<u64>,
+    #[serde(default)]
+    pub is_file_level: bool,
+    #[serde(default)]
+    pub on_removed_code: bool,
+    pub is_resolved: bool,
+    pub is_outdated: bool,
+    pub comments: Vec<CommentSnapshot>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CommentSnapshot {
+    pub id: String,
+    pub author: String,
+    pub created_at: Option<String>,
+    /// Starts with CLAUDE_MARKER.
+    pub from_claude: bool,
+    /// Contains a paperclip marker.
+    pub paperclip: bool,
+    /// The logins @mentioned, as `mentions::mentioned_logins` finds them.
+    #[serde(default)]
+    pub mentions: Vec<String>,
+}
+
+impl AnalysisSnapshot {
+    pub fn new(
+        pr_context: &PrContext,
+        checks: &ChecksSummary,
+        threads: &[ReviewThread],
+        action: &NextAction,
+    ) -> Self {
+        Self {
+            at: jiff::Timestamp::now().to_string(),
+            owner: pr_context.owner.clone(),
+            repo: pr_context.repo.clone(),
+            pr_number: pr_context.pr_number,
+            recommendation: check_run::title(action),
+            checks: checks
+                .checks
+                .iter()
+                .map(|c| CheckSnapshot {
+                    name: c.name.clone(),
+                    status: c.status.clone(),
+                    source: c.source,
+                    url: c.url.clone(),
+                    started_at: c.started_at.clone(),
+                    app: c.app.clone(),
+                    attempts: c.attempts,
+                })
+                .collect(),
+            threads: threads
+                .iter()
+                .map(|t| ThreadSnapshot {
+                    id: t.id.clone(),
+                    path: t.path.clone(),
+                    line: t.line,
+                    start_line: t.start_line,
+                    original_line: t.original_line,
+                    is_file_level: t.is_file_level,
+                    on_removed_code: t.on_removed_code,
+                    is_resolved: t.is_resolved,
+                    is_outdated: t.is_outdated,
+                    comments: t
+                        .comments
+                        .iter()
+                        .map(|c| CommentSnapshot {
+                            id: c.id.clone(),
+                            author: c.author.clone(),
+                            created_at: c.created_at.clone(),
+                            from_claude: c.is_from_claude(),
+                            paperclip: c.body.contains(PAPERCLIP_SHORTCODE)
+                                || c.body.contains(PAPERCLIP_EMOJI),
+                            mentions: mentioned_logins(&c.body),
+                        })
+                        .collect(),
+                })
+                .collect(),
+            mergeable_status: MergeableStatus::Unknown,
+            pending_reviews: vec![],
+            changed_files: vec![],
+            rebase: None,
+            side_conversations: HashMap::new(),
+        }
+    }
+
+    pub fn pr_context(&self) -> PrContext {
+        PrContext {
+            owner: self.owner.clone(),
+            repo: self.repo.clone(),
+            pr_number: self.pr_number,
+        }
+    }
+
+    pub fn checks_summary(&self) -> ChecksSummary {
+        ChecksSummary {
+            checks: self
+                .checks
+                .iter()
+                .map(|c| Check {
+                    name: c.name.clone(),
+                    status: c.status.clone(),
+                    url: c.url.clone(),
+                    source: c.source,
+                    started_at: c.started_at.clone(),
+                    attempts: c.attempts,
+                    app: c.app.clone(),
+                })
+                .collect(),
+        }
+    }
+
+    /// Rebuild the review threads. Comment text wasn't captured, so bodies
+    /// are placeholders that keep the markers and mentions the decision
+    /// engine looks for.
+    pub fn threads(&self) -> Vec<ReviewThread> {
+        self.threads
+            .iter()
+            .map(|t| ReviewThread {
+                id: t.id.clone(),
+                is_resolved: t.is_resolved,
+                is_outdated: t.is_outdated,
+                path: t.path.clone(),
+                line: t.line,
+                start_line: t.start_line,
+                original_line: t.original_line,
+                is_file_level: t.is_file_level,
+                on_removed_code: t.on_removed_code,
+                comments: t
+                    .comments
+                    .iter()
+                    .map(|c| {
+                        let mut body = if c.from_claude {
+                            format!("{} (comment text not captured)", CLAUDE_MARKER)
+                        } else {
+                            "(comment text not captured)".to_string()
+                        };
+                        if c.paperclip {
+                            body.push_str(&format!(" {}", PAPERCLIP_EMOJI));
+                        }
+                        for login in &c.mentions {
+                            body.push_str(&format!(" @{}", login));
+                        }
+                        ThreadComment {
+                            id: c.id.clone(),
+                            author: c.author.clone(),
+                            body,
+                            diff_hunk: None,
+                            url: None,
+                            created_at: c.created_at.clone(),
+                            review: None,
+                        }
+                    })
+                    .collect(),
+            })
+            .collect()
+    }
+}
+
+/// Read a snapshot from a debug bundle (`.tar.gz`) or a bare
+/// `last-analysis.json`.
+pub fn read_snapshot(path: &Path) -> Result<AnalysisSnapshot> {
+    let file =
+        std::fs::File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    let name = path.to_string_lossy();
+    let json = if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+        let mut archive = tar::Archive::new(GzDecoder::new(file));
+        let mut found = None;
+        for entry in archive.entries().context("Failed to read bundle")? {
+            let mut entry = entry.context("Failed to read bundle")?;
+            if entry.path()?.file_name().and_then(|n| n.to_str()) == Some(SNAPSHOT_FILE) {
+                let mut json = String::new();
+                entry.read_to_string(&mut json)?;
+                found = Some(json);
+                break;
+            }
+        }
+        found.with_context(|| {
+            format!(
+                "{} has no {}; was an analysis run before the bundle was made?",
+                path.display(),
+                SNAPSHOT_FILE
+            )
+        })?
+    } else {
+        std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?
+    };
+    serde_json::from_str(&json).context("Failed to parse analysis snapshot")
+}
+
+/// Save the snapshot to `<root>/.pr-loop/last-analysis.json`, replacing the
+/// previous one.
+pub fn save_snapshot(root: &Path, snapshot: &AnalysisSnapshot) -> Result<()> {
+    let dir = root.join(STATE_DIR);
+    std::fs::create_dir_all(&dir)?;
+    let gitignore = dir.join(".gitignore");
+    if !gitignore.exists() {
+        std::fs::write(&gitignore, "*\n")?;
+    }
+    let json = serde_json::to_string_pretty(snapshot).context("serialize analysis snapshot")?;
+    std::fs::write(dir.join(SNAPSHOT_FILE), json + "\n")?;
+    Ok(())
+}
+
+/// pr-loop, OS, gh, and git versions.
+fn version_info() -> String {
+    let tool_version = |program: &str| {
+        Command::new(program)
+            .arg("--version")
+            .output()
+            .ok()
+            .filter(|o| o.status.success())
+            .and_then(|o| String::from_utf8_lossy(&o.stdout).lines().next().map(str::to_string))
+            .unwrap_or_else(|| format!("{}: not found", program))
+    };
+    format!(
+        "pr-loop {}\nos: {} {}\n{}\n{}\n",
+        env!("CARGO_PKG_VERSION"),
+        std::env::consts::OS,
+        std::env::consts::ARCH,
+        tool_version("gh"),
+        tool_version("git"),
+    )
+}
+
+/// The effective config as `key = value  # origin` lines, without the values
+/// of secret settings (see `config::SECRET_KEYS`).
+fn config_listing(rows: &[(String, String, String)]) -> String {
+    if rows.is_empty() {
+        return "# No settings; everything is at its default.\n".to_string();
+    }
+    rows.iter()
+        .map(|(key, value, origin)| {
+            let value = if config::is_secret(key) {
+                "<redacted>"
+            } else {
+                value
+            };
+            format!("{} = {}  # {}\n", key, value, origin)
+        })
+        .collect()
+}
+
+/// Shorten the home directory to `~` so bundles don't carry usernames.
+fn shorten_home(text: &str) -> String {
+    match crate::config::home_dir() {
+        Some(home) => text.replace(&*home.to_string_lossy(), "~"),
+        None => text.to_string(),
+    }
+}
+
+/// Gather the bundle's files as (name, contents). `root` is the repository
+/// root holding `.pr-loop/`; without one, only version and config are
+/// included.
+pub fn collect(root: Option<&Path>) -> Vec<(String, String)> {
+    let mut files = vec![
+        ("version.txt".to_string(), version_info()),
+        (
+            "config.txt".to_string(),
+            shorten_home(&config_listing(&Layers::read().origins())),
+        ),
+    ];
+    let Some(root) = root else { return files };
+    let requests: String = request_log::recent_entries(&request_log::log_path(root), BUNDLED_REQUESTS)
+        .iter()
+        .filter_map(|e| serde_json::to_string(e).ok())
+        .map(|line| line + "\n")
+        .collect();
+    if !requests.is_empty() {
+        files.push(("requests.jsonl".to_string(), shorten_home(&requests)));
+    }
+    if let Ok(snapshot) = std::fs::read_to_string(root.join(STATE_DIR).join(SNAPSHOT_FILE)) {
+        files.push((SNAPSHOT_FILE.to_string(), snapshot));
+    }
+    files
+}
+
+/// Write the files into a gzipped tarball at `out`, under a top-level
+/// directory named after the archive.
+pub fn write_bundle(out: &Path, files: &[(String, String)]) -> Result<()> {
+    let prefix = out
+        .file_name()
+        .and_then(|n| n.to_str())
+        .map(|n| n.trim_end_matches(".tar.gz").to_string())
+        .unwrap_or_else(|| "pr-loop-debug".to_string());
+    let file = std::fs::File::create(out)
+        .with_context(|| format!("Failed to create {}", out.display()))?;
+    let mut tar = tar::Builder::new(GzEncoder::new(file, Compression::default()));
+    for (name, contents) in files {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(contents.len() as u64);
+        header.set_mode(0o644);
+        header.set_mtime(jiff::Timestamp::now().as_second().max(0) as u64);
+        header.set_cksum();
+        tar.append_data(&mut header, format!("{}/{}", prefix, name), contents.as_bytes())
+            .with_context(|| format!("Failed to add {} to bundle", name))?;
+    }
+    tar.into_inner()?.finish()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::analyze_actions;
+
+    fn sample_snapshot() -> AnalysisSnapshot {
+        let checks = ChecksSummary {
+            checks: vec![Check {
+                name: "test".to_string(),
+                status: CheckStatus::Pass,
+                url: None,
+                source: CheckSource::CheckRun,
+                started_at: None,
+                attempts: 2,
+                app: Some("github-actions".to_string()),
+            }],
+        };
+        let comment = ThreadComment::for_test;
+        let thread = |id: &str, comments| ReviewThread {
+            line: Some(3),
+            ..ReviewThread::for_test(id, comments)
+        };
+        let threads = vec![
+            thread("T1", vec![comment("C1", "reviewer", "private remark")]),
+            thread(
+                "T2",
+                vec![
+                    comment("C2", "reviewer", "why?"),
+                    comment("C3", "bot", &format!("{} because", CLAUDE_MARKER)),
+                ],
+            ),
+            thread("T3", vec![comment("C4", "reviewer", ":paperclip: later")]),
+        ];
+        let pr = PrContext {
+            owner: "o".to_string(),
+            repo: "r".to_string(),
+            pr_number: 1,
+        };
+        let action = analyze_actions(&checks, threads.clone(), &MergeableStatus::Unknown, None).remove(0);
+        AnalysisSnapshot::new(&pr, &checks, &threads, &action)
+    }
+
+    #[test]
+    fn snapshot_omits_comment_text() {
+        let snapshot = sample_snapshot();
+        let json = serde_json::to_string(&snapshot).unwrap();
+        assert_eq!(snapshot.recommendation, "ACTION REQUIRED: Respond to 1 review thread");
+        assert!(json.contains("\"status\":\"Pass\""));
+        assert!(!json.contains("private remark"));
+        assert!(!json.contains("because"));
+    }
+
+    #[test]
+    fn replayed_snapshot_reaches_same_decision() {
+        let json = serde_json::to_string(&sample_snapshot()).unwrap();
+        let snapshot: AnalysisSnapshot = serde_json::from_str(&json).unwrap();
+        let threads = snapshot.threads();
+        match analyze_actions(&snapshot.checks_summary(), threads, &snapshot.mergeable_status, None).remove(0) {
+            NextAction::RespondToComments { threads, .. } => {
+                let ids: Vec<_> = threads.iter().map(|t| t.thread.id.as_str()).collect();
+                assert_eq!(ids, vec!["T1"]);
+            }
+            other => panic!("Expected RespondToComments, got {:?}", other),
+        }
+        assert_eq!(snapshot.checks_summary().checks[0].attempts, 2);
+        assert_eq!(snapshot.pr_context().pr_number, 1);
+    }
+
+    #[test]
+    fn replay_keeps_the_rebase_and_mentions() {
+        let mut snapshot = sample_snapshot();
+        snapshot.threads.retain(|t| t.id != "T1");
+        snapshot.threads[0].comments[0].mentions = vec!["alice".to_string()];
+        snapshot.rebase = Some(Rebase {
+            base: "main".to_string(),
+            behind_by: 80,
+            ci_changes: vec![],
+        });
+        let json = serde_json::to_string(&snapshot).unwrap();
+        let snapshot: AnalysisSnapshot = serde_json::from_str(&json).unwrap();
+
+        let threads = snapshot.threads();
+        assert_eq!(mentioned_logins(&threads[0].comments[0].body), vec!["alice".to_string()]);
+        let action = analyze_actions(
+            &snapshot.checks_summary(),
+            threads,
+            &snapshot.mergeable_status,
+            snapshot.rebase.as_ref(),
+        )
+        .remove(0);
+        assert!(matches!(action, NextAction::Rebase { .. }), "got {:?}", action);
+    }
+
+    #[test]
+    fn config_listing_redacts_secrets() {
+        let rows = vec![
+            ("checks.exclude_apps".to_string(), "[\"codecov\"]".to_string(), "user".to_string()),
+            ("credentials.helper".to_string(), "\"secret-cmd\"".to_string(), "user".to_string()),
+            ("notify.desktop".to_string(), "true".to_string(), "user".to_string()),
+            ("notify.webhook".to_string(), "\"https://hooks.slack.com/T/B/x\"".to_string(), "user".to_string()),
+        ];
+        assert_eq!(
+            config_listing(&rows),
+            "checks.exclude_apps = [\"codecov\"]  # user\ncredentials.helper = <redacted>  # user\n\
+             notify.desktop = true  # user\nnotify.webhook = <redacted>  # user\n"
+        );
+    }
+
+    #[test]
+    fn writes_readable_tarball() {
+        let dir = std::env::temp_dir().join(format!("pr-loop-bundle-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let out = dir.join("pr-loop-debug-1.tar.gz");
+        let files = vec![
+            ("version.txt".to_string(), "pr-loop 0.1.0\n".to_string()),
+            ("config.txt".to_string(), "# none\n".to_string()),
+        ];
+        write_bundle(&out, &files).unwrap();
+
+        let mut archive = tar::Archive::new(GzDecoder::new(std::fs::File::open(&out).unwrap()));
+        let mut found = vec![];
+        for entry in archive.entries().unwrap() {
+            let mut entry = entry.unwrap();
+            let mut contents = String::new();
+            entry.read_to_string(&mut contents).unwrap();
+            found.push((entry.path().unwrap().display().to_string(), contents));
+        }
+        assert_eq!(
+            found,
+            vec![
+                ("pr-loop-debug-1/version.txt".to_string(), "pr-loop 0.1.0\n".to_string()),
+                ("pr-loop-debug-1/config.txt".to_string(), "# none\n".to_string()),
+            ]
+        );
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,308 @@
+// Escalating to a human when the loop is stuck.
+// With `[escalation] after = N` configured, a check that has failed N
+// consecutive runs with the same error (see streaks.rs), or a thread Claude
+// has already answered N times that the reviewer still isn't happy with,
+// stops the loop: instead of advising more fixes, the analysis posts a
+// Claude-marked summary asking for help and exits with `EXIT_CODE`, so an
+// orchestrator knows to pause rather than start another iteration.
+//
+// Posted escalations are remembered in `.pr-loop/escalations.json`, so
+// rerunning the analysis on the same state doesn't post the summary again.
+
+use crate::audit::STATE_DIR;
+use crate::context::FailingCheck;
+use crate::streaks::FailureStreak;
+use crate::threads::{ReviewThread, CLAUDE_MARKER};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Exit code of an analysis that escalated.
+pub const EXIT_CODE: i32 = 4;
+
+const ESCALATIONS_FILE: &str = "escalations.json";
+
+/// Something the loop isn't making progress on.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum StuckItem {
+    Check {
+        name: String,
+        /// Consecutive failed runs.
+        runs: u32,
+        /// The failed runs' URLs (or start times), oldest first.
+        history: Vec<String>,
+        /// Errors extracted from the latest run.
+        errors: Vec<String>,
+    },
+    Thread {
+        id: String,
+        location: String,
+        /// How many times Claude has replied.
+        replies: u32,
+        last_comment_id: String,
+        /// The reviewer's latest comment.
+        last_comment: String,
+    },
+}
+
+impl StuckItem {
+    /// Identifies this state of the item: a new failed run or a new comment
+    /// is a new escalation.
+    fn key(&self) -> String {
+        match self {
+            StuckItem::Check { name, history, .. } => {
+                format!("check:{}@{}", name, history.last().map(String::as_str).unwrap_or(""))
+            }
+            StuckItem::Thread { id, last_comment_id, .. } => {
+                format!("thread:{}@{}", id, last_comment_id)
+            }
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        match self {
+            StuckItem::Check { name, runs, .. } => {
+                format!("{} has failed {} consecutive runs with the same error", name, runs)
+            }
+            StuckItem::Thread { location, replies, .. } => format!(
+                "the thread on {} is still open after {} replies from Claude",
+                location, replies
+            ),
+        }
+    }
+}
+
+/// The checks and threads stuck for at least `after` iterations. Threads
+/// should be the actionable ones (the reviewer spoke last).
+pub fn find_stuck(
+    after: u32,
+    streaks: &[FailureStreak],
+    failing: &[FailingCheck],
+    threads: &[&ReviewThread],
+) -> Vec<StuckItem> {
+    let mut stuck: Vec<StuckItem> = streaks
+        .iter()
+        .filter(|s| s.same_error && s.runs >= after)
+        .map(|s| StuckItem::Check {
+            name: s.check.clone(),
+            runs: s.runs,
+            history: s.history.clone(),
+            errors: failing
+                .iter()
+                .find(|f| f.name == s.check)
+                .map(|f| f.errors.clone())
+                .unwrap_or_default(),
+        })
+        .collect();
+    for thread in threads {
+        let replies = thread
+            .comments
+            .iter()
+            .filter(|c| c.is_from_claude())
+            .count() as u32;
+        let Some(last) = thread.last_comment() else {
+            continue;
+        };
+        if replies >= after && !last.is_from_claude() {
+            stuck.push(StuckItem::Thread {
+                id: thread.id.clone(),
+                location: thread.location(),
+                replies,
+                last_comment_id: last.id.clone(),
+                last_comment: last.body.clone(),
+            });
+        }
+    }
+    stuck
+}
+
+/// The comment asking for help, with each item's history.
+pub fn format_summary(stuck: &[StuckItem]) -> String {
+    let mut out = format!(
+        "{} I'm stuck on this PR and have stopped iterating. A human needs to take a look:\n",
+        CLAUDE_MARKER
+    );
+    for item in stuck {
+        out.push_str(&format!("\n**{}**\n", capitalize(&item.describe())));
+        match item {
+            StuckItem::Check { history, errors, .. } => {
+                if !errors.is_empty() {
+                    out.push_str("\n```\n");
+                    for error in errors {
+                        out.push_str(error.trim_end());
+                        out.push('\n');
+                    }
+                    out.push_str("```\n");
+                }
+                out.push_str("\nFailed runs:\n");
+                for run in history {
+                    out.push_str(&format!("- {}\n", run));
+                }
+            }
+            StuckItem::Thread { last_comment, .. } => {
+                out.push_str("\nLatest reviewer comment:\n");
+                for line in last_comment.lines() {
+                    out.push_str(&format!("> {}\n", line));
+                }
+            }
+        }
+    }
+    out
+}
+
+fn capitalize(s: &str) -> String {
+    let mut chars = s.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct PostedEscalation {
+    /// e.g. "owner/repo#123"
+    pr: String,
+    key: String,
+}
+
+/// The escalations already posted, recorded under a repository root.
+#[derive(Debug, Clone, Default)]
+pub struct Escalations {
+    /// None when there's no repository to store them in.
+    path: Option<PathBuf>,
+    entries: Vec<PostedEscalation>,
+}
+
+impl Escalations {
+    /// Load `<root>/.pr-loop/escalations.json`.
+    pub fn load(root: &Path) -> Result<Self> {
+        let path = root.join(STATE_DIR).join(ESCALATIONS_FILE);
+        let entries = match std::fs::read_to_string(&path) {
+            Ok(content) => serde_json::from_str(&content)
+                .with_context(|| format!("Failed to parse {}", path.display()))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => vec![],
+            Err(e) => return Err(e).with_context(|| format!("Failed to read {}", path.display())),
+        };
+        Ok(Self {
+            path: Some(path),
+            entries,
+        })
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let Some(path) = &self.path else { return Ok(()) };
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+            let gitignore = dir.join(".gitignore");
+            if !gitignore.exists() {
+                std::fs::write(&gitignore, "*\n")?;
+            }
+        }
+        std::fs::write(path, serde_json::to_string_pretty(&self.entries)? + "\n")
+            .with_context(|| format!("Failed to write {}", path.display()))
+    }
+
+    /// Whether a summary covering every item in `stuck` was already posted.
+    pub fn already_posted(&self, pr: &str, stuck: &[StuckItem]) -> bool {
+        stuck
+            .iter()
+            .all(|item| self.entries.iter().any(|e| e.pr == pr && e.key == item.key()))
+    }
+
+    pub fn mark_posted(&mut self, pr: &str, stuck: &[StuckItem]) {
+        for item in stuck {
+            let key = item.key();
+            if !self.entries.iter().any(|e| e.pr == pr && e.key == key) {
+                self.entries.push(PostedEscalation {
+                    pr: pr.to_string(),
+                    key,
+                });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::threads::ThreadComment;
+
+    fn comment(id: &str, body: &str) -> ThreadComment {
+        ThreadComment::for_test(id, "someone", body)
+    }
+
+    fn thread(comments: Vec<ThreadComment>) -> ReviewThread {
+        ReviewThread {
+            line: Some(3),
+            ..ReviewThread::for_test("T1", comments)
+        }
+    }
+
+    #[test]
+    fn finds_long_streaks_and_back_and_forth_threads() {
+        let claude = |text: &str| format!("{} {}", CLAUDE_MARKER, text);
+        let streaks = vec![
+            FailureStreak {
+                check: "ci/test".to_string(),
+                runs: 3,
+                same_error: true,
+                history: vec!["run-1".into(), "run-2".into(), "run-3".into()],
+            },
+            FailureStreak {
+                check: "lint".to_string(),
+                runs: 2,
+                same_error: true,
+                history: vec!["run-4".into(), "run-5".into()],
+            },
+        ];
+        let failing = vec![FailingCheck {
+            name: "ci/test".to_string(),
+            url: None,
+            errors: vec!["test foo ... FAILED".to_string()],
+            suggested_commands: vec![],
+        }];
+        let stuck_thread = thread(vec![
+            comment("C1", "Rename this"),
+            comment("C2", &claude("Done")),
+            comment("C3", "Not like that"),
+            comment("C4", &claude("Fixed")),
+            comment("C5", "Still wrong"),
+            comment("C6", &claude("Fixed again")),
+            comment("C7", "No"),
+        ]);
+        let fresh_thread = thread(vec![comment("C8", "Typo")]);
+
+        let stuck = find_stuck(3, &streaks, &failing, &[&stuck_thread, &fresh_thread]);
+        assert_eq!(stuck.len(), 2);
+        assert_eq!(stuck[0].describe(), "ci/test has failed 3 consecutive runs with the same error");
+        assert_eq!(
+            stuck[1].describe(),
+            "the thread on src/lib.rs:3 is still open after 3 replies from Claude"
+        );
+
+        let summary = format_summary(&stuck);
+        assert!(summary.starts_with(CLAUDE_MARKER));
+        assert!(summary.contains("test foo ... FAILED"));
+        assert!(summary.contains("- run-3\n"));
+        assert!(summary.contains("> No\n"));
+    }
+
+    #[test]
+    fn remembers_what_was_posted() {
+        let item = |last: &str| StuckItem::Thread {
+            id: "T1".to_string(),
+            location: String::new(),
+            replies: 3,
+            last_comment_id: last.to_string(),
+            last_comment: String::new(),
+        };
+        let mut posted = Escalations::default();
+        assert!(!posted.already_posted("o/r#1", &[item("C7")]));
+        posted.mark_posted("o/r#1", &[item("C7")]);
+        assert!(posted.already_posted("o/r#1", &[item("C7")]));
+        // A new reviewer comment is a new escalation
+        assert!(!posted.already_posted("o/r#1", &[item("C7"), item("C9")]));
+        assert!(!posted.already_posted("o/r#2", &[item("C7")]));
+    }
+}
@@ -0,0 +1,357 @@
+// Why the analysis recommended what it did.
+// Threads and checks pass through several filters before the decision
+// engine sees them: snoozes, paperclips, side conversations, check name
+// globs, excluded apps, base-branch failures. When a recommendation is
+// surprising ("why is it waiting when that thread is open?"), `--explain`
+// lists every thread and check the PR has, whether it counted, and the
+// first rule that decided it, followed by the priority that picked the
+// recommendation.
+//
+// The reasons are worked out again from the unfiltered threads and checks
+// with the same rules the clients and analysis apply, so they can't drift
+// into a separate notion of what counts.
+
+use crate::analysis::NextAction;
+use crate::author_threads::{self, AuthorThreadPolicy};
+use crate::base_failures::BaseFailure;
+use crate::checks::{Check, CheckFilters, CheckStatus};
+use crate::mentions;
+use crate::snooze::{SnoozeKind, Snoozes};
+use crate::threads::{ReplyStatus, ReviewThread};
+use glob::Pattern;
+
+/// One thread or check and what the analysis made of it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Entry {
+    /// e.g. "src/main.rs:42" or "build"
+    pub subject: String,
+    /// Whether the decision engine considered it.
+    pub counted: bool,
+    pub reason: String,
+}
+
+impl Entry {
+    fn new(subject: &str, counted: bool, reason: impl Into<String>) -> Self {
+        Self {
+            subject: subject.to_string(),
+            counted,
+            reason: reason.into(),
+        }
+    }
+}
+
+/// What the thread filters were set to.
+pub struct ThreadRules<'a> {
+    pub snoozes: &'a Snoozes,
+    /// e.g. "owner/repo#123"
+    pub pr: &'a str,
+    /// Logins mentions of which are addressed to the agent.
+    pub own_logins: &'a [String],
+    /// `[mentions] actionable`
+    pub mentions_actionable: bool,
+    /// The PR author, when `author_policy` needs it.
+    pub pr_author: Option<&'a str>,
+    /// `[author_threads] policy`
+    pub author_policy: AuthorThreadPolicy,
+}
+
+/// Why each thread did or didn't need a response.
+pub fn threads(threads: &[ReviewThread], rules: &ThreadRules) -> Vec<Entry> {
+    threads
+        .iter()
+        .map(|thread| {
+            let subject = thread.location();
+            let Some(last) = thread.last_comment() else {
+                return Entry::new(&subject, false, "no comments");
+            };
+            if let Some(snooze) = rules.snoozes.find(rules.pr, SnoozeKind::Thread, &thread.id) {
+                let reason = snooze.reason.as_deref().map_or(String::new(), |r| format!(": {}", r));
+                return Entry::new(&subject, false, format!("snoozed until {}{}", snooze.until, reason));
+            }
+            if thread.is_resolved {
+                return Entry::new(&subject, false, "resolved");
+            }
+            if thread.has_paperclip() {
+                return Entry::new(&subject, false, "has a 📎 paperclip (left for a human)");
+            }
+            if last.is_from_claude() {
+                return match thread.last_reply_status() {
+                    Some(ReplyStatus::Question) => {
+                        Entry::new(&subject, true, "waiting for an answer to Claude's question")
+                    }
+                    _ => Entry::new(&subject, false, "Claude replied last"),
+                };
+            }
+            let others = mentions::addressed_elsewhere(thread, rules.own_logins);
+            if !others.is_empty() && !rules.mentions_actionable {
+                return Entry::new(
+                    &subject,
+                    false,
+                    format!("addressed to {} ([mentions] actionable = false)", mentions::describe(&others)),
+                );
+            }
+            if rules.author_policy != AuthorThreadPolicy::Actionable
+                && let Some(author) = rules.pr_author
+                && author_threads::is_authors_own(thread, author)
+            {
+                let policy = match rules.author_policy {
+                    AuthorThreadPolicy::Informational => "informational",
+                    _ => "paperclip",
+                };
+                return Entry::new(
+                    &subject,
+                    false,
+                    format!("the PR author's own note ([author_threads] policy = \"{}\")", policy),
+                );
+            }
+            Entry::new(&subject, true, format!("needs a response (@{} commented last)", last.author))
+        })
+        .collect()
+}
+
+/// What the check filters were set to.
+pub struct CheckRules<'a> {
+    pub filters: &'a CheckFilters,
+    pub snoozes: &'a Snoozes,
+    /// e.g. "owner/repo#123"
+    pub pr: &'a str,
+    /// Failures ignored with `--ignore-base-failures`.
+    pub base_failures: &'a [BaseFailure],
+}
+
+/// Why each check was or wasn't considered, and for delayed checks that
+/// haven't registered, that they're waited on anyway.
+pub fn checks(checks: &[Check], rules: &CheckRules) -> Vec<Entry> {
+    let compile = |patterns: &[String]| -> Vec<(String, Pattern)> {
+        patterns
+            .iter()
+            .filter_map(|p| Some((p.clone(), Pattern::new(p).ok()?)))
+            .collect()
+    };
+    let includes = compile(&rules.filters.include_patterns);
+    let excludes = compile(&rules.filters.exclude_patterns);
+    let delayed = compile(&rules.filters.delayed_checks);
+
+    let mut entries: Vec<Entry> = checks
+        .iter()
+        .map(|check| {
+            let subject = check.name.as_str();
+            if !includes.is_empty() && !includes.iter().any(|(_, p)| p.matches(subject)) {
+                return Entry::new(subject, false, "not matched by --include-checks");
+            }
+            if let Some((pattern, _)) = excludes.iter().find(|(_, p)| p.matches(subject)) {
+                return Entry::new(subject, false, format!("excluded by `{}`", pattern));
+            }
+            if let Some(app) = &check.app
+                && rules.filters.exclude_apps.iter().any(|e| e.eq_ignore_ascii_case(app))
+            {
+                return Entry::new(subject, false, format!("from excluded app `{}`", app));
+            }
+            if let Some(snooze) = rules.snoozes.find(rules.pr, SnoozeKind::Check, subject) {
+                return Entry::new(subject, false, format!("snoozed until {}", snooze.until));
+            }
+            if let Some(failure) = rules.base_failures.iter().find(|f| f.check == check.name) {
+                return Entry::new(
+                    subject,
+                    false,
+                    format!("also failing on {} (--ignore-base-failures)", failure.branch),
+                );
+            }
+            let status = match check.status {
+                CheckStatus::Pass => "passed",
+                CheckStatus::Fail => "failed",
+                CheckStatus::Pending => "still running",
+                CheckStatus::Skipping => "skipped",
+                CheckStatus::Cancelled => "cancelled",
+            };
+            Entry::new(subject, true, status)
+        })
+        .collect();
+    for (pattern, compiled) in &delayed {
+        if !checks.iter().any(|c| compiled.matches(&c.name)) {
+            entries.push(Entry::new(pattern, true, "delayed check that hasn't registered yet; waited on"));
+        }
+    }
+    entries
+}
+
+/// Which priority produced the recommendation.
+pub fn decision(action: &NextAction) -> String {
+    match action {
+        NextAction::RespondToComments { threads, .. } => format!(
+            "{} thread{} need{} a response, which comes before CI.",
+            threads.len(),
+            if threads.len() == 1 { "" } else { "s" },
+            if threads.len() == 1 { "s" } else { "" }
+        ),
+        NextAction::ResolveConflicts { .. } => {
+            "No thread needs a response and the PR conflicts with its base branch, which comes before CI."
+                .to_string()
+        }
+        NextAction::FixCiFailures { failed_check_names } => format!(
+            "No thread needs a response and {} check{} failed.",
+            failed_check_names.len(),
+            if failed_check_names.len() == 1 { "" } else { "s" }
+        ),
+        NextAction::WaitForCi { pending_check_names } => format!(
+            "No thread needs a response, nothing failed, and {} check{} still running.",
+            pending_check_names.len(),
+            if pending_check_names.len() == 1 { " is" } else { "s are" }
+        ),
+        NextAction::Rebase { rebase } => format!(
+            "CI passed and no thread needs a response, but the branch is {}.",
+            rebase.describe()
+        ),
+        NextAction::AwaitHumanInput { threads } => format!(
+            "CI passed and nothing needs a response, but {} of Claude's questions {} unanswered.",
+            threads.len(),
+            if threads.len() == 1 { "is" } else { "are" }
+        ),
+        NextAction::PrReady => "CI passed and no thread needs a response or an answer.".to_string(),
+    }
+}
+
+/// The trace as printed after the recommendation.
+pub fn render(threads: &[Entry], checks: &[Entry], decision: &str) -> String {
+    let section = |title: &str, entries: &[Entry]| {
+        let mut out = format!("{}:\n", title);
+        if entries.is_empty() {
+            out.push_str("  (none)\n");
+        }
+        for entry in entries {
+            let mark = if entry.counted { '✓' } else { '·' };
+            out.push_str(&format!("  {} {}: {}\n", mark, entry.subject, entry.reason));
+        }
+        out
+    };
+    format!(
+        "{}\n{}\nDecision: {}\n",
+        section("Threads", threads),
+        section("Checks", checks),
+        decision
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::checks::CheckSource;
+    use crate::threads::{ThreadComment, CLAUDE_MARKER};
+
+    fn thread(id: &str, line: u64, resolved: bool, comments: &[(&str, &str)]) -> ReviewThread {
+        let comments = comments
+            .iter()
+            .enumerate()
+            .map(|(i, (author, body))| {
+                ThreadComment::for_test(&format!("{}-{}", id, i), author, body)
+            })
+            .collect();
+        ReviewThread {
+            is_resolved: resolved,
+            line: Some(line),
+            ..ReviewThread::for_test(id, comments)
+        }
+    }
+
+    fn check(name: &str, status: CheckStatus, app: Option<&str>) -> Check {
+        Check {
+            name: name.to_string(),
+            status,
+            url: None,
+            source: CheckSource::CheckRun,
+            started_at: None,
+            attempts: 1,
+            app: app.map(String::from),
+        }
+    }
+
+    #[test]
+    fn explains_each_thread() {
+        let snoozes = Snoozes::default();
+        let rules = ThreadRules {
+            snoozes: &snoozes,
+            pr: "o/r#1",
+            own_logins: &["me".to_string()],
+            mentions_actionable: false,
+            pr_author: Some("carol"),
+            author_policy: AuthorThreadPolicy::Informational,
+        };
+        let claude = format!("{} Done", CLAUDE_MARKER);
+        let entries = threads(
+            &[
+                thread("T1", 1, false, &[("bob", "Rename this")]),
+                thread("T2", 2, true, &[("bob", "Rename this")]),
+                thread("T3", 3, false, &[("bob", ":paperclip: later")]),
+                thread("T4", 4, false, &[("bob", "Rename this"), ("me", &claude)]),
+                thread("T5", 5, false, &[("bob", "@alice is this right?")]),
+                thread("T6", 6, false, &[("carol", "Keep the old name for now")]),
+            ],
+            &rules,
+        );
+        let reasons: Vec<(&str, bool, &str)> = entries
+            .iter()
+            .map(|e| (e.subject.as_str(), e.counted, e.reason.as_str()))
+            .collect();
+        assert_eq!(
+            reasons,
+            vec![
+                ("src/lib.rs:1", true, "needs a response (@bob commented last)"),
+                ("src/lib.rs:2", false, "resolved"),
+                ("src/lib.rs:3", false, "has a 📎 paperclip (left for a human)"),
+                ("src/lib.rs:4", false, "Claude replied last"),
+                ("src/lib.rs:5", false, "addressed to @alice ([mentions] actionable = false)"),
+                (
+                    "src/lib.rs:6",
+                    false,
+                    "the PR author's own note ([author_threads] policy = \"informational\")"
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn explains_each_check() {
+        let filters = CheckFilters {
+            include_patterns: vec![],
+            exclude_patterns: vec!["lint-*".to_string()],
+            exclude_apps: vec!["codecov".to_string()],
+            delayed_checks: vec!["deploy-preview".to_string()],
+        };
+        let snoozes = Snoozes::default();
+        let base_failures = vec![BaseFailure {
+            check: "e2e".to_string(),
+            branch: "main".to_string(),
+            base_url: None,
+        }];
+        let rules = CheckRules {
+            filters: &filters,
+            snoozes: &snoozes,
+            pr: "o/r#1",
+            base_failures: &base_failures,
+        };
+        let entries = checks(
+            &[
+                check("build", CheckStatus::Fail, None),
+                check("lint-docs", CheckStatus::Fail, None),
+                check("codecov/patch", CheckStatus::Fail, Some("codecov")),
+                check("e2e", CheckStatus::Fail, None),
+            ],
+            &rules,
+        );
+        let reasons: Vec<&str> = entries.iter().map(|e| e.reason.as_str()).collect();
+        assert_eq!(
+            reasons,
+            vec![
+                "failed",
+                "excluded by `lint-*`",
+                "from excluded app `codecov`",
+                "also failing on main (--ignore-base-failures)",
+                "delayed check that hasn't registered yet; waited on",
+            ]
+        );
+        assert_eq!(
+            decision(&NextAction::FixCiFailures { failed_check_names: vec!["build".to_string()] }),
+            "No thread needs a response and 1 check failed."
+        );
+    }
+}
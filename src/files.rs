@@ -0,0 +1,292 @@
+// Files changed by a PR, with per-file line counts, via GitHub GraphQL.
+
+use crate::graphql::gh_graphql;
+use crate::request_log::TracedOutput;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::process::Command;
+
+/// A file changed by the PR.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChangedFile {
+    pub path: String,
+    pub additions: u64,
+    pub deletions: u64,
+    /// GitHub's patch status, e.g. "ADDED", "MODIFIED", "DELETED", "RENAMED".
+    pub change_type: String,
+}
+
+/// Trait for fetching changed files, allowing test implementations.
+pub trait FilesClient {
+    fn fetch_changed_files(&self, owner: &str, repo: &str, pr_number: u64)
+        -> Result<Vec<ChangedFile>>;
+
+    /// Files changed between two commits (`base..head`), or by `head` alone
+    /// when there's no base.
+    fn fetch_commit_files(
+        &self,
+        owner: &str,
+        repo: &str,
+        base: Option<&str>,
+        head: &str,
+    ) -> Result<Vec<ChangedFile>>;
+}
+
+pub struct RealFilesClient;
+
+impl FilesClient for RealFilesClient {
+    fn fetch_changed_files(
+        &self,
+        owner: &str,
+        repo: &str,
+        pr_number: u64,
+    ) -> Result<Vec<ChangedFile>> {
+        fetch_changed_files_from_graphql(owner, repo, pr_number)
+    }
+
+    fn fetch_commit_files(
+        &self,
+        owner: &str,
+        repo: &str,
+        base: Option<&str>,
+        head: &str,
+    ) -> Result<Vec<ChangedFile>> {
+        let endpoint = match base {
+            Some(base) => format!("repos/{}/{}/compare/{}...{}", owner, repo, base, head),
+            None => format!("repos/{}/{}/commits/{}", owner, repo, head),
+        };
+        let output = Command::new("gh")
+            .args(["api", &endpoint])
+            .traced_output()
+            .context("Failed to run 'gh api' for commit files")?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            anyhow::bail!("Failed to fetch commit files: {}", stderr.trim());
+        }
+
+        // Both endpoints return the changed files under `files`.
+        #[derive(Deserialize)]
+        struct WithFiles {
+            #[serde(default)]
+            files: Vec<RestFile>,
+        }
+
+        #[derive(Deserialize)]
+        struct RestFile {
+            filename: String,
+            additions: u64,
+            deletions: u64,
+            status: String,
+        }
+
+        let response: WithFiles =
+            serde_json::from_slice(&output.stdout).context("Failed to parse commit files")?;
+        Ok(response
+            .files
+            .into_iter()
+            .map(|f| ChangedFile {
+                path: f.filename,
+                additions: f.additions,
+                deletions: f.deletions,
+                change_type: f.status.to_uppercase(),
+            })
+            .collect())
+    }
+}
+
+/// Format a one-line summary, e.g. "3 files changed, +120 −14".
+pub fn format_diff_stat(files: &[ChangedFile]) -> String {
+    let additions: u64 = files.iter().map(|f| f.additions).sum();
+    let deletions: u64 = files.iter().map(|f| f.deletions).sum();
+    format!(
+        "{} file{} changed, +{} −{}",
+        files.len(),
+        if files.len() == 1 { "" } else { "s" },
+        additions,
+        deletions
+    )
+}
+
+/// Compact list of the most-changed files, e.g.
+/// "`src/a.rs` (+100 −3), `src/b.rs` (+10 −1), and 3 more".
+pub fn format_top_files(files: &[ChangedFile], max: usize) -> String {
+    let mut sorted: Vec<&ChangedFile> = files.iter().collect();
+    sorted.sort_by_key(|f| std::cmp::Reverse(f.additions + f.deletions));
+    let mut parts: Vec<String> = sorted
+        .iter()
+        .take(max)
+        .map(|f| format!("`{}` (+{} −{})", f.path, f.additions, f.deletions))
+        .collect();
+    if files.len() > max {
+        parts.push(format!("and {} more", files.len() - max));
+    }
+    parts.join(", ")
+}
+
+/// GraphQL query for fetching changed files (loaded from graphql/operation/).
+const FETCH_CHANGED_FILES_QUERY: &str =
+    include_str!("../graphql/operation/fetch_changed_files.graphql");
+
+#[derive(Deserialize)]
+struct GraphQLResponse {
+    data: Option<GraphQLData>,
+    errors: Option<Vec<GraphQLError>>,
+}
+
+#[derive(Deserialize)]
+struct GraphQLError {
+    message: String,
+}
+
+#[derive(Deserialize)]
+struct GraphQLData {
+    repository: Option<RepositoryData>,
+}
+
+#[derive(Deserialize)]
+struct RepositoryData {
+    #[serde(rename = "pullRequest")]
+    pull_request: Option<PullRequestData>,
+}
+
+#[derive(Deserialize)]
+struct PullRequestData {
+    files: FilesConnection,
+}
+
+#[derive(Deserialize)]
+struct FilesConnection {
+    nodes: Vec<FileNode>,
+    #[serde(rename = "pageInfo")]
+    page_info: PageInfo,
+}
+
+#[derive(Deserialize)]
+struct PageInfo {
+    #[serde(rename = "hasNextPage")]
+    has_next_page: bool,
+    #[serde(rename = "endCursor")]
+    end_cursor: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct FileNode {
+    path: String,
+    additions: u64,
+    deletions: u64,
+    #[serde(rename = "changeType")]
+    change_type: String,
+}
+
+fn fetch_changed_files_from_graphql(
+    owner: &str,
+    repo: &str,
+    pr_number: u64,
+) -> Result<Vec<ChangedFile>> {
+    let mut files = Vec::new();
+    let mut cursor: Option<String> = None;
+
+    loop {
+        let mut variables = json!({ "owner": owner, "repo": repo, "pr": pr_number });
+        if let Some(c) = &cursor {
+            variables["cursor"] = json!(c);
+        }
+
+        let output = gh_graphql(FETCH_CHANGED_FILES_QUERY, variables)
+            .context("Failed to run 'gh api graphql' for changed files")?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            anyhow::bail!("GraphQL query failed: {}", stderr.trim());
+        }
+
+        let response: GraphQLResponse = serde_json::from_slice(&output.stdout)
+            .context("Failed to parse GraphQL response")?;
+
+        if let Some(errors) = response.errors {
+            let messages: Vec<_> = errors.iter().map(|e| e.message.as_str()).collect();
+            anyhow::bail!("GraphQL errors: {}", messages.join(", "));
+        }
+
+        let connection = response
+            .data
+            .and_then(|d| d.repository)
+            .and_then(|r| r.pull_request)
+            .map(|pr| pr.files)
+            .ok_or_else(|| anyhow::anyhow!("PR not found or no access"))?;
+
+        files.extend(connection.nodes.into_iter().map(|n| ChangedFile {
+            path: n.path,
+            additions: n.additions,
+            deletions: n.deletions,
+            change_type: n.change_type,
+        }));
+
+        if !connection.page_info.has_next_page {
+            break;
+        }
+        cursor = connection.page_info.end_cursor;
+    }
+
+    Ok(files)
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    pub struct TestFilesClient {
+        pub files: Vec<ChangedFile>,
+    }
+
+    impl FilesClient for TestFilesClient {
+        fn fetch_changed_files(&self, _owner: &str, _repo: &str, _pr: u64) -> Result<Vec<ChangedFile>> {
+            Ok(self.files.clone())
+        }
+
+        fn fetch_commit_files(
+            &self,
+            _owner: &str,
+            _repo: &str,
+            _base: Option<&str>,
+            _head: &str,
+        ) -> Result<Vec<ChangedFile>> {
+            Ok(self.files.clone())
+        }
+    }
+
+    pub fn changed_file(path: &str, additions: u64, deletions: u64) -> ChangedFile {
+        ChangedFile {
+            path: path.to_string(),
+            additions,
+            deletions,
+            change_type: "MODIFIED".to_string(),
+        }
+    }
+
+    #[test]
+    fn diff_stat_sums_lines() {
+        let client = TestFilesClient {
+            files: vec![changed_file("a.rs", 10, 2), changed_file("b.rs", 1, 0)],
+        };
+        let files = client.fetch_changed_files("o", "r", 1).unwrap();
+        assert_eq!(format_diff_stat(&files), "2 files changed, +11 −2");
+        assert_eq!(format_diff_stat(&files[..1]), "1 file changed, +10 −2");
+    }
+
+    #[test]
+    fn top_files_sorted_by_churn() {
+        let files = vec![
+            changed_file("small.rs", 1, 0),
+            changed_file("big.rs", 50, 50),
+            changed_file("mid.rs", 5, 5),
+        ];
+        assert_eq!(
+            format_top_files(&files, 2),
+            "`big.rs` (+50 −50), `mid.rs` (+5 −5), and 1 more"
+        );
+        assert_eq!(format_top_files(&files[..1], 2), "`small.rs` (+1 −0)");
+    }
+}
@@ -0,0 +1,182 @@
+// File follow-up issues for paperclip threads.
+// A paperclip thread usually means "not in this PR": deferred work that's
+// easy to lose once the PR merges. `pr-loop file-followups` turns each one
+// into a GitHub issue quoting the discussion and linking back to it, then
+// replies on the thread with the issue link. The reply doubles as the record
+// that a thread has been filed, so re-running doesn't file duplicates.
+
+use crate::request_log;
+use crate::threads::{ReviewThread, PAPERCLIP_EMOJI, PAPERCLIP_SHORTCODE};
+use anyhow::{Context, Result};
+use std::io::Write;
+use std::process::{Command, Stdio};
+use std::time::Instant;
+
+/// Start of the reply posted once a thread's issue is filed.
+pub const FILED_REPLY_PREFIX: &str = "Filed a follow-up issue:";
+
+/// Longest issue title taken from a comment.
+const MAX_TITLE_CHARS: usize = 80;
+
+/// An issue to file for one thread.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FollowupIssue {
+    pub title: String,
+    pub body: String,
+}
+
+/// Trait for creating issues, allowing test implementations.
+pub trait IssueCreator {
+    /// Create an issue in `owner/repo`. Returns its URL.
+    fn create_issue(&self, owner: &str, repo: &str, issue: &FollowupIssue) -> Result<String>;
+}
+
+/// Real client that uses `gh issue create`.
+pub struct RealIssueCreator;
+
+impl IssueCreator for RealIssueCreator {
+    fn create_issue(&self, owner: &str, repo: &str, issue: &FollowupIssue) -> Result<String> {
+        let mut command = Command::new("gh");
+        command
+            .args(["issue", "create", "--repo", &format!("{}/{}", owner, repo)])
+            .args(["--title", &issue.title, "--body-file", "-"])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+        let started = Instant::now();
+        let mut child = command
+            .spawn()
+            .context("Failed to run 'gh issue create'")?;
+        child
+            .stdin
+            .take()
+            .context("gh issue create stdin unavailable")?
+            .write_all(issue.body.as_bytes())
+            .context("Failed to send issue body to gh")?;
+        let output = child.wait_with_output();
+        request_log::record(&command, started, output.as_ref().ok());
+        let output = output.context("Failed to wait for gh issue create")?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            anyhow::bail!("gh issue create failed: {}", stderr.trim());
+        }
+        // gh prints the new issue's URL as the last line
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        stdout
+            .lines()
+            .last()
+            .map(|l| l.trim().to_string())
+            .filter(|l| !l.is_empty())
+            .context("gh issue create didn't print the issue URL")
+    }
+}
+
+/// Returns true if pr-loop already replied with a follow-up issue link.
+pub fn already_filed(thread: &ReviewThread) -> bool {
+    thread.comments.iter().any(|c| {
+        c.is_from_claude() && c.body.contains(FILED_REPLY_PREFIX)
+    })
+}
+
+fn strip_markers(body: &str) -> String {
+    body.replace(PAPERCLIP_SHORTCODE, "")
+        .replace(PAPERCLIP_EMOJI, "")
+}
+
+/// Build the issue for a paperclip thread: titled after the first comment,
+/// quoting the whole discussion, and linking the PR and the code location.
+pub fn issue_for_thread(thread: &ReviewThread, owner: &str, repo: &str, pr_number: u64) -> FollowupIssue {
+    let first_body = thread
+        .comments
+        .first()
+        .map(|c| strip_markers(&c.body))
+        .unwrap_or_default();
+    let first_line = first_body
+        .lines()
+        .map(str::trim)
+        .find(|l| !l.is_empty())
+        .unwrap_or("Follow-up from review");
+    let title = if first_line.chars().count() > MAX_TITLE_CHARS {
+        let cut: String = first_line.chars().take(MAX_TITLE_CHARS - 1).collect();
+        format!("{}…", cut.trim_end())
+    } else {
+        first_line.to_string()
+    };
+
+    let mut body = format!("Follow-up from review of {}/{}#{}", owner, repo, pr_number);
+    if thread.path.is_some() {
+        body.push_str(&format!(" at `{}`", thread.location()));
+    }
+    body.push('.');
+    if let Some(url) = thread.comments.first().and_then(|c| c.url.as_deref()) {
+        body.push_str(&format!(" ([thread]({}))", url));
+    }
+    body.push_str("\n\n");
+    for comment in &thread.comments {
+        body.push_str(&format!("> **@{}**:\n", comment.author));
+        for line in strip_markers(&comment.body).trim().lines() {
+            body.push_str(&format!("> {}\n", line).replace("> \n", ">\n"));
+        }
+        body.push('\n');
+    }
+    FollowupIssue {
+        title,
+        body: body.trim_end().to_string() + "\n",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::threads::{ThreadComment, CLAUDE_MARKER};
+
+    fn comment(author: &str, body: &str) -> ThreadComment {
+        ThreadComment {
+            url: Some("https://github.com/o/r/pull/7#discussion_r1".to_string()),
+            ..ThreadComment::for_test(&format!("C-{}", author), author, body)
+        }
+    }
+
+    fn thread(comments: Vec<ThreadComment>) -> ReviewThread {
+        ReviewThread {
+            line: Some(42),
+            ..ReviewThread::for_test("T1", comments)
+        }
+    }
+
+    #[test]
+    fn builds_issue_from_thread() {
+        let t = thread(vec![
+            comment("alice", ":paperclip: Let's cache this lookup\n\nIt runs per request."),
+            comment("bob", "Agreed, later."),
+        ]);
+        let issue = issue_for_thread(&t, "o", "r", 7);
+        assert_eq!(issue.title, "Let's cache this lookup");
+        assert_eq!(
+            issue.body,
+            "Follow-up from review of o/r#7 at `src/lib.rs:42`. \
+             ([thread](https://github.com/o/r/pull/7#discussion_r1))\n\n\
+             > **@alice**:\n> Let's cache this lookup\n>\n> It runs per request.\n\n\
+             > **@bob**:\n> Agreed, later.\n"
+        );
+    }
+
+    #[test]
+    fn truncates_long_titles() {
+        let t = thread(vec![comment("alice", &format!("📎 {}", "word ".repeat(40)))]);
+        let title = issue_for_thread(&t, "o", "r", 7).title;
+        assert!(title.ends_with('…'));
+        assert!(title.chars().count() <= MAX_TITLE_CHARS);
+    }
+
+    #[test]
+    fn detects_filed_threads() {
+        let mut t = thread(vec![comment("alice", ":paperclip: later")]);
+        assert!(!already_filed(&t));
+        t.comments.push(comment(
+            "claude",
+            &format!("{} {} https://github.com/o/r/issues/9", CLAUDE_MARKER, FILED_REPLY_PREFIX),
+        ));
+        assert!(already_filed(&t));
+    }
+}
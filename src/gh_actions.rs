@@ -1,8 +1,10 @@
-// GitHub Actions API integration. Parallel to circleci.rs — fetches structured
-// annotations and the failed-step portion of job logs for a failing GH Actions
-// check, so we can surface them alongside CircleCI failure details.
+// GitHub Actions API integration. Parallel to circleci.rs — fetches the
+// failed-step portion of job logs for a failing GH Actions check, so we can
+// surface them alongside CircleCI failure details. Annotations are handled
+// generically for all check runs in annotations.rs.
 
 use crate::circleci::FailedStepLog;
+use crate::request_log::TracedOutput;
 use anyhow::{Context, Result};
 use serde::Deserialize;
 use std::process::Command;
@@ -12,18 +14,11 @@ use std::process::Command;
 pub struct GhActionsJobInfo {
     pub owner: String,
     pub repo: String,
+    /// The workflow run the job belongs to.
+    pub run_id: u64,
     pub job_id: u64,
 }
 
-/// A single check annotation (GitHub's "highlighted error" in the UI).
-#[derive(Debug, Clone)]
-pub struct Annotation {
-    pub message: String,
-    pub path: String,
-    pub start_line: Option<u32>,
-    pub level: String,
-}
-
 /// Trait for GH Actions API operations, allowing test implementations.
 pub trait GhActionsClient {
     fn fetch_job_details(&self, job_info: &GhActionsJobInfo) -> Result<JobDetails>;
@@ -35,7 +30,9 @@ pub trait GhActionsClient {
         &self,
         job_info: &GhActionsJobInfo,
     ) -> Result<std::collections::HashMap<String, String>>;
-    fn fetch_annotations(&self, job_info: &GhActionsJobInfo) -> Result<Vec<Annotation>>;
+    /// Return the job's whole log from the /jobs/{id}/logs API. Used when
+    /// `gh run view` refuses because other jobs in the run are still going.
+    fn fetch_job_log(&self, job_info: &GhActionsJobInfo) -> Result<String>;
 }
 
 #[derive(Debug, Clone)]
@@ -48,11 +45,8 @@ pub struct JobDetails {
 pub struct Step {
     pub name: String,
     pub conclusion: Option<String>,
-    // Timestamps were used by an earlier timestamp-based log extraction
-    // path; kept on the struct for completeness/tests but currently unused.
-    #[allow(dead_code)]
+    /// Used to slice the raw job log when `gh run view` isn't available.
     pub started_at: Option<String>,
-    #[allow(dead_code)]
     pub completed_at: Option<String>,
 }
 
@@ -72,14 +66,6 @@ struct StepResponse {
     completed_at: Option<String>,
 }
 
-#[derive(Deserialize)]
-struct AnnotationResponse {
-    message: String,
-    path: String,
-    start_line: Option<u32>,
-    annotation_level: String,
-}
-
 impl GhActionsClient for RealGhActionsClient {
     fn fetch_job_details(&self, job_info: &GhActionsJobInfo) -> Result<JobDetails> {
         let path = format!(
@@ -88,7 +74,7 @@ impl GhActionsClient for RealGhActionsClient {
         );
         let output = Command::new("gh")
             .args(["api", &path])
-            .output()
+            .traced_output()
             .context("Failed to run 'gh api' for job details")?;
         if !output.status.success() {
             let stderr = String::from_utf8_lossy(&output.stderr);
@@ -126,7 +112,7 @@ impl GhActionsClient for RealGhActionsClient {
                 &job_info.job_id.to_string(),
                 "--log-failed",
             ])
-            .output()
+            .traced_output()
             .context("Failed to run 'gh run view --log-failed'")?;
         if !output.status.success() {
             let stderr = String::from_utf8_lossy(&output.stderr);
@@ -136,35 +122,20 @@ impl GhActionsClient for RealGhActionsClient {
         Ok(parse_log_failed_output(&text))
     }
 
-    fn fetch_annotations(&self, job_info: &GhActionsJobInfo) -> Result<Vec<Annotation>> {
-        // For GH Actions checks, the check_run_id equals the job_id.
+    fn fetch_job_log(&self, job_info: &GhActionsJobInfo) -> Result<String> {
         let path = format!(
-            "/repos/{}/{}/check-runs/{}/annotations",
+            "/repos/{}/{}/actions/jobs/{}/logs",
             job_info.owner, job_info.repo, job_info.job_id
         );
         let output = Command::new("gh")
             .args(["api", &path])
-            .output()
-            .context("Failed to run 'gh api' for annotations")?;
+            .traced_output()
+            .context("Failed to run 'gh api' for job logs")?;
         if !output.status.success() {
-            // Annotations may 404 in some cases; treat as "no annotations".
             let stderr = String::from_utf8_lossy(&output.stderr);
-            if stderr.contains("HTTP 404") {
-                return Ok(vec![]);
-            }
-            anyhow::bail!("gh api annotations failed: {}", stderr.trim());
+            anyhow::bail!("gh api job logs failed: {}", stderr.trim());
         }
-        let parsed: Vec<AnnotationResponse> =
-            serde_json::from_slice(&output.stdout).context("parse annotations")?;
-        Ok(parsed
-            .into_iter()
-            .map(|a| Annotation {
-                message: a.message,
-                path: a.path,
-                start_line: a.start_line,
-                level: a.annotation_level,
-            })
-            .collect())
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
     }
 }
 
@@ -190,14 +161,17 @@ pub fn parse_gh_actions_url(url: &str) -> Option<GhActionsJobInfo> {
     Some(GhActionsJobInfo {
         owner: parts[0].to_string(),
         repo: parts[1].to_string(),
+        run_id: parts[4].parse().ok()?,
         job_id: parts[6].parse().ok()?,
     })
 }
 
-/// Fetch failed-step logs plus annotations for a GH Actions job, returned as
-/// `FailedStepLog` entries so they can be merged with CircleCI-style output.
-/// The `output` field gets the log for the failed step and the `error` field
-/// gets the formatted annotations (since that's the structured signal).
+/// Fetch failed-step logs for a GH Actions job, returned as `FailedStepLog`
+/// entries so they can be merged with CircleCI-style output. The `output`
+/// field gets the log for the failed step; `error` is left empty since
+/// GH Actions doesn't separate stderr. `gh run view --log-failed` only works
+/// once the whole run has finished, so until then (a job failed while others
+/// are still running) the job's raw log is sliced by step timestamps instead.
 pub fn get_failed_step_logs(
     client: &dyn GhActionsClient,
     job_info: &GhActionsJobInfo,
@@ -213,22 +187,68 @@ pub fn get_failed_step_logs(
     }
 
     let logs_by_step = client.fetch_failed_step_logs(job_info).unwrap_or_default();
-    let annotations = client.fetch_annotations(job_info).unwrap_or_default();
-    let annotations_text = format_annotations(&annotations);
+    let raw_log = if logs_by_step.is_empty() {
+        client.fetch_job_log(job_info).ok()
+    } else {
+        None
+    };
 
     let logs: Vec<FailedStepLog> = failed
         .into_iter()
         .map(|step| FailedStepLog {
             job_name: details.name.clone(),
             step_name: step.name.clone(),
-            output: logs_by_step.get(&step.name).cloned().unwrap_or_default(),
-            error: annotations_text.clone(),
+            output: match &raw_log {
+                Some(raw) => slice_step_log(raw, step),
+                None => logs_by_step.get(&step.name).cloned().unwrap_or_default(),
+            },
+            error: String::new(),
+            container: None,
+            full_log_url: None,
         })
         .collect();
 
     Ok(logs)
 }
 
+/// Split a log line into its timestamp and content. Lines are
+/// `<RFC-3339 timestamp> <content>`; anything else has no timestamp.
+pub fn split_timestamp(line: &str) -> (Option<jiff::Timestamp>, &str) {
+    let line = line.strip_prefix('\u{feff}').unwrap_or(line);
+    match line.split_once(' ') {
+        Some((ts, content)) => match ts.parse() {
+            Ok(at) => (Some(at), content),
+            Err(_) => (None, line),
+        },
+        None => (None, line),
+    }
+}
+
+/// The lines of a raw job log written while `step` ran, with timestamps
+/// stripped. Step times from the API are truncated to the second, so the
+/// window runs to the end of the second the step completed in. Untimestamped
+/// lines belong with the line before them.
+fn slice_step_log(raw: &str, step: &Step) -> String {
+    let parse = |at: &Option<String>| at.as_deref().and_then(|t| t.parse::<jiff::Timestamp>().ok());
+    let (Some(start), Some(end)) = (parse(&step.started_at), parse(&step.completed_at)) else {
+        return String::new();
+    };
+    let end = end + jiff::SignedDuration::from_secs(1);
+    let mut out = String::new();
+    let mut inside = false;
+    for line in raw.lines() {
+        let (at, content) = split_timestamp(line);
+        if let Some(at) = at {
+            inside = at >= start && at < end;
+        }
+        if inside {
+            out.push_str(content);
+            out.push('\n');
+        }
+    }
+    out
+}
+
 /// Parse the output of `gh run view --log-failed`. Each line is
 /// `<jobname>\t<stepname>\t<timestamp> <content>`. We group by step name,
 /// stripping the timestamp so the rendered output isn't swamped by repeated
@@ -261,26 +281,6 @@ fn parse_log_failed_output(text: &str) -> std::collections::HashMap<String, Stri
     by_step
 }
 
-fn format_annotations(annotations: &[Annotation]) -> String {
-    if annotations.is_empty() {
-        return String::new();
-    }
-    let mut out = String::new();
-    for a in annotations {
-        let location = match (&a.path, a.start_line) {
-            (p, Some(l)) if !p.is_empty() => format!("{}:{}", p, l),
-            (p, _) if !p.is_empty() => p.clone(),
-            _ => String::new(),
-        };
-        if location.is_empty() {
-            out.push_str(&format!("[{}] {}\n", a.level, a.message));
-        } else {
-            out.push_str(&format!("[{}] {} — {}\n", a.level, location, a.message));
-        }
-    }
-    out
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -291,6 +291,7 @@ mod tests {
         let info = parse_gh_actions_url(url).unwrap();
         assert_eq!(info.owner, "owner");
         assert_eq!(info.repo, "repo");
+        assert_eq!(info.run_id, 123);
         assert_eq!(info.job_id, 456);
     }
 
@@ -332,32 +333,10 @@ identity-e2e\tDifferent step\t2026-04-22T18:42:05.0000000Z other step
         assert!(other.contains("other step"));
     }
 
-    #[test]
-    fn format_annotations_basic() {
-        let anns = vec![
-            Annotation {
-                message: "Process failed".into(),
-                path: ".github".into(),
-                start_line: Some(1592),
-                level: "failure".into(),
-            },
-            Annotation {
-                message: "Localserver died".into(),
-                path: "".into(),
-                start_line: None,
-                level: "failure".into(),
-            },
-        ];
-        let s = format_annotations(&anns);
-        assert!(s.contains(".github:1592"));
-        assert!(s.contains("Process failed"));
-        assert!(s.contains("Localserver died"));
-    }
-
     pub struct TestGhActionsClient {
         pub job_details: Option<JobDetails>,
         pub logs_by_step: std::collections::HashMap<String, String>,
-        pub annotations: Vec<Annotation>,
+        pub job_log: Option<String>,
     }
 
     impl GhActionsClient for TestGhActionsClient {
@@ -372,8 +351,39 @@ identity-e2e\tDifferent step\t2026-04-22T18:42:05.0000000Z other step
         ) -> Result<std::collections::HashMap<String, String>> {
             Ok(self.logs_by_step.clone())
         }
-        fn fetch_annotations(&self, _: &GhActionsJobInfo) -> Result<Vec<Annotation>> {
-            Ok(self.annotations.clone())
+        fn fetch_job_log(&self, _: &GhActionsJobInfo) -> Result<String> {
+            self.job_log
+                .clone()
+                .ok_or_else(|| anyhow::anyhow!("no test job log"))
+        }
+    }
+
+    fn test_job() -> JobDetails {
+        JobDetails {
+            name: "test-job".into(),
+            steps: vec![
+                Step {
+                    name: "setup".into(),
+                    conclusion: Some("success".into()),
+                    started_at: Some("2026-01-01T00:00:00Z".into()),
+                    completed_at: Some("2026-01-01T00:00:01Z".into()),
+                },
+                Step {
+                    name: "bad".into(),
+                    conclusion: Some("failure".into()),
+                    started_at: Some("2026-01-01T00:00:02Z".into()),
+                    completed_at: Some("2026-01-01T00:00:05Z".into()),
+                },
+            ],
+        }
+    }
+
+    fn test_info() -> GhActionsJobInfo {
+        GhActionsJobInfo {
+            owner: "o".into(),
+            repo: "r".into(),
+            run_id: 1,
+            job_id: 1,
         }
     }
 
@@ -382,34 +392,37 @@ identity-e2e\tDifferent step\t2026-04-22T18:42:05.0000000Z other step
         let mut logs_by_step = std::collections::HashMap::new();
         logs_by_step.insert("bad".to_string(), "fail line\n".to_string());
         let client = TestGhActionsClient {
-            job_details: Some(JobDetails {
-                name: "test-job".into(),
-                steps: vec![
-                    Step {
-                        name: "setup".into(),
-                        conclusion: Some("success".into()),
-                        started_at: Some("2026-01-01T00:00:00Z".into()),
-                        completed_at: Some("2026-01-01T00:00:01Z".into()),
-                    },
-                    Step {
-                        name: "bad".into(),
-                        conclusion: Some("failure".into()),
-                        started_at: Some("2026-01-01T00:00:01Z".into()),
-                        completed_at: Some("2026-01-01T00:00:05Z".into()),
-                    },
-                ],
-            }),
+            job_details: Some(test_job()),
             logs_by_step,
-            annotations: vec![],
+            job_log: None,
         };
-        let info = GhActionsJobInfo {
-            owner: "o".into(),
-            repo: "r".into(),
-            job_id: 1,
-        };
-        let logs = get_failed_step_logs(&client, &info).unwrap();
+        let logs = get_failed_step_logs(&client, &test_info()).unwrap();
         assert_eq!(logs.len(), 1);
         assert_eq!(logs[0].step_name, "bad");
         assert!(logs[0].output.contains("fail line"));
     }
+
+    #[test]
+    fn get_failed_step_logs_slices_raw_log_while_run_in_progress() {
+        let job_log = "\
+\u{feff}2026-01-01T00:00:00.1000000Z ##[group]Run setup
+2026-01-01T00:00:01.9000000Z setup done
+2026-01-01T00:00:02.0000000Z ##[group]Run cargo test
+2026-01-01T00:00:04.5000000Z test foo ... FAILED
+continuation without timestamp
+2026-01-01T00:00:05.7000000Z ##[error]Process completed with exit code 101.
+2026-01-01T00:00:06.0000000Z Post job cleanup.
+";
+        let client = TestGhActionsClient {
+            job_details: Some(test_job()),
+            logs_by_step: std::collections::HashMap::new(),
+            job_log: Some(job_log.to_string()),
+        };
+        let logs = get_failed_step_logs(&client, &test_info()).unwrap();
+        assert_eq!(
+            logs[0].output,
+            "##[group]Run cargo test\ntest foo ... FAILED\ncontinuation without timestamp\n\
+             ##[error]Process completed with exit code 101.\n"
+        );
+    }
 }
@@ -0,0 +1,221 @@
+// Upload captured CI logs to a secret gist.
+// Failure output is truncated to keep the analysis readable; with
+// `--upload-logs` everything pr-loop captured of each log is put in a gist and
+// linked from the output. That is still an excerpt for long logs — only
+// their start, end, and middle error lines are downloaded (see
+// log_window.rs) — so each file says so and points at the job's own page.
+
+use crate::circleci::FailedStepLog;
+use crate::request_log;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::process::{Command, Stdio};
+use std::time::Instant;
+
+/// Request body for `POST /gists`.
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct GistRequest {
+    pub description: String,
+    pub public: bool,
+    pub files: BTreeMap<String, GistFile>,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct GistFile {
+    pub content: String,
+}
+
+/// Trait for gist operations, allowing test implementations.
+pub trait GistClient {
+    /// Create a gist. Returns its HTML URL.
+    fn create_gist(&self, gist: &GistRequest) -> Result<String>;
+}
+
+/// Real client that uses `gh api`.
+pub struct RealGistClient;
+
+#[derive(Deserialize)]
+struct GistResponse {
+    html_url: String,
+}
+
+impl GistClient for RealGistClient {
+    fn create_gist(&self, gist: &GistRequest) -> Result<String> {
+        let body = serde_json::to_vec(gist).context("serialize gist")?;
+        let mut command = Command::new("gh");
+        command
+            .args(["api", "-X", "POST", "gists", "--input", "-"])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+        let started = Instant::now();
+        let mut child = command
+            .spawn()
+            .context("Failed to run 'gh api' to create gist")?;
+        child
+            .stdin
+            .take()
+            .context("gh api stdin unavailable")?
+            .write_all(&body)
+            .context("Failed to send gist to gh api")?;
+        let output = child.wait_with_output();
+        request_log::record(&command, started, output.as_ref().ok());
+        let output = output.context("Failed to wait for gh api")?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            anyhow::bail!("gh api create gist failed: {}", stderr.trim());
+        }
+        let parsed: GistResponse =
+            serde_json::from_slice(&output.stdout).context("parse gist response")?;
+        Ok(parsed.html_url)
+    }
+}
+
+/// Gist file name for a step's log, e.g. "test--Run-tests.log" (or
+/// "test--Run-tests--container-3-8.log" for a parallel job). Gist file
+/// names can't contain slashes, so anything but alphanumerics, `-`, `_`,
+/// and `.` becomes `-`.
+pub fn log_file_name(log: &FailedStepLog) -> String {
+    let clean = |s: &str| -> String {
+        s.chars()
+            .map(|c| if c.is_ascii_alphanumeric() || "-_.".contains(c) { c } else { '-' })
+            .collect()
+    };
+    match &log.container {
+        Some(container) => format!(
+            "{}--{}--{}.log",
+            clean(&log.job_name),
+            clean(&log.step_name),
+            clean(container)
+        ),
+        None => format!("{}--{}.log", clean(&log.job_name), clean(&log.step_name)),
+    }
+}
+
+/// The anchor GitHub gives a file on a gist page.
+fn file_anchor(name: &str) -> String {
+    let slug: String = name
+        .to_lowercase()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+        .collect();
+    format!("file-{}", slug)
+}
+
+/// First line of every uploaded file.
+const EXCERPT_NOTE: &str = "Log excerpt captured by pr-loop: long logs keep only their start, their \
+end, and error lines from the middle. The complete log is on the CI job's page.";
+
+/// Captured text of a step's log, under the excerpt note: stderr (if any)
+/// followed by stdout.
+fn captured_log(log: &FailedStepLog) -> String {
+    let text = match (log.error.is_empty(), log.output.is_empty()) {
+        (true, _) => log.output.clone(),
+        (false, true) => log.error.clone(),
+        (false, false) => format!("--- stderr ---\n{}\n--- stdout ---\n{}", log.error, log.output),
+    };
+    format!("{}\n\n{}", EXCERPT_NOTE, text)
+}
+
+/// Upload the given logs to one secret gist and set each log's
+/// `full_log_url` to a link to its file. Duplicate job/step names get a
+/// numeric suffix so no log is overwritten.
+pub fn upload_logs(
+    client: &dyn GistClient,
+    description: &str,
+    logs: &mut [&mut FailedStepLog],
+) -> Result<()> {
+    if logs.is_empty() {
+        return Ok(());
+    }
+    let mut files = BTreeMap::new();
+    let mut names = Vec::with_capacity(logs.len());
+    for log in logs.iter() {
+        let base = log_file_name(log);
+        let mut name = base.clone();
+        let mut n = 2;
+        while files.contains_key(&name) {
+            name = format!("{}.{}.log", base.trim_end_matches(".log"), n);
+            n += 1;
+        }
+        files.insert(name.clone(), GistFile { content: captured_log(log) });
+        names.push(name);
+    }
+    let url = client.create_gist(&GistRequest {
+        description: description.to_string(),
+        public: false,
+        files,
+    })?;
+    for (log, name) in logs.iter_mut().zip(names) {
+        log.full_log_url = Some(format!("{}#{}", url, file_anchor(&name)));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+
+    /// Test client that records created gists.
+    pub struct TestGistClient {
+        pub created: RefCell<Vec<GistRequest>>,
+    }
+
+    impl GistClient for TestGistClient {
+        fn create_gist(&self, gist: &GistRequest) -> Result<String> {
+            self.created.borrow_mut().push(gist.clone());
+            Ok("https://gist.github.com/abc".to_string())
+        }
+    }
+
+    fn step_log(job: &str, step: &str, output: &str, error: &str) -> FailedStepLog {
+        FailedStepLog {
+            job_name: job.to_string(),
+            step_name: step.to_string(),
+            output: output.to_string(),
+            error: error.to_string(),
+            container: None,
+            full_log_url: None,
+        }
+    }
+
+    #[test]
+    fn uploads_one_secret_gist_and_links_each_file() {
+        let client = TestGistClient {
+            created: RefCell::new(vec![]),
+        };
+        let mut a = step_log("test", "Run tests", "lots of output", "");
+        let mut b = step_log("test", "Run tests", "more", "boom");
+        upload_logs(&client, "CI log excerpts for o/r#1", &mut [&mut a, &mut b]).unwrap();
+
+        let created = client.created.borrow();
+        assert_eq!(created.len(), 1);
+        assert!(!created[0].public);
+        let names: Vec<_> = created[0].files.keys().cloned().collect();
+        assert_eq!(names, vec!["test--Run-tests.2.log", "test--Run-tests.log"]);
+        assert_eq!(
+            created[0].files["test--Run-tests.2.log"].content,
+            format!("{}\n\n--- stderr ---\nboom\n--- stdout ---\nmore", EXCERPT_NOTE)
+        );
+        assert_eq!(
+            a.full_log_url.as_deref(),
+            Some("https://gist.github.com/abc#file-test--run-tests-log")
+        );
+        assert_eq!(
+            b.full_log_url.as_deref(),
+            Some("https://gist.github.com/abc#file-test--run-tests-2-log")
+        );
+    }
+
+    #[test]
+    fn nothing_to_upload() {
+        let client = TestGistClient {
+            created: RefCell::new(vec![]),
+        };
+        upload_logs(&client, "unused", &mut []).unwrap();
+        assert!(client.created.borrow().is_empty());
+    }
+}
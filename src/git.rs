@@ -2,6 +2,7 @@
 // Uses git CLI to get commit timestamps.
 
 use anyhow::{Context, Result};
+use std::path::PathBuf;
 use std::process::Command;
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
@@ -27,6 +28,19 @@ impl GitClient for RealGitClient {
     }
 }
 
+/// Get the top-level directory of the current git checkout.
+pub fn get_repo_root() -> Result<PathBuf> {
+    let output = Command::new("git")
+        .args(["rev-parse", "--show-toplevel"])
+        .output()
+        .context("Failed to run 'git rev-parse'")?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        anyhow::bail!("Failed to find repository root: {}", stderr.trim());
+    }
+    Ok(PathBuf::from(String::from_utf8_lossy(&output.stdout).trim()))
+}
+
 fn get_head_hash_from_git() -> Result<String> {
     let output = Command::new("git")
         .args(["rev-parse", "HEAD"])
@@ -1,9 +1,11 @@
 // GitHub API interactions and context detection.
 // Uses `gh` CLI for repo/PR detection and API calls.
 
+use crate::request_log::TracedOutput;
 use anyhow::{Context, Result};
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use std::process::Command;
+use std::sync::OnceLock;
 
 /// Context about the current repository and PR.
 #[derive(Debug, Clone)]
@@ -56,7 +58,7 @@ struct GhPrView {
 fn detect_repo_from_gh() -> Result<(String, String)> {
     let output = Command::new("gh")
         .args(["repo", "view", "--json", "owner,name"])
-        .output()
+        .traced_output()
         .context("Failed to run 'gh repo view'. Is this a git repository?")?;
 
     if !output.status.success() {
@@ -76,7 +78,7 @@ fn detect_pr_from_gh(_owner: &str, _repo: &str) -> Result<u64> {
     // only when no repo is specified. With --repo, it requires an explicit PR identifier.
     let output = Command::new("gh")
         .args(["pr", "view", "--json", "number"])
-        .output()
+        .traced_output()
         .context("Failed to run 'gh pr view'")?;
 
     if !output.status.success() {
@@ -90,44 +92,24 @@ fn detect_pr_from_gh(_owner: &str, _repo: &str) -> Result<u64> {
 }
 
 /// Whether a PR has merge conflicts.
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
 pub enum MergeableStatus {
     Mergeable,
     Conflicting,
+    #[default]
     Unknown,
 }
 
-/// Trait for fetching PR mergeability, allowing test implementations.
-pub trait MergeableClient {
-    fn fetch_mergeable_status(
-        &self,
-        owner: &str,
-        repo: &str,
-        pr_number: u64,
-    ) -> Result<MergeableStatus>;
-}
-
-/// Real client that uses `gh pr view`.
-pub struct RealMergeableClient;
-
-impl MergeableClient for RealMergeableClient {
-    fn fetch_mergeable_status(
-        &self,
-        owner: &str,
-        repo: &str,
-        pr_number: u64,
-    ) -> Result<MergeableStatus> {
-        fetch_mergeable_status_from_gh(owner, repo, pr_number)
-    }
-}
-
 #[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
 struct GhPrMergeable {
     mergeable: String,
+    #[serde(default)]
+    merge_state_status: String,
 }
 
-/// Fetch PR mergeability using `gh pr view --json mergeable`.
-fn fetch_mergeable_status_from_gh(
+/// Fetch PR mergeability using `gh pr view --json mergeable,mergeStateStatus`.
+pub fn fetch_mergeable_status_from_gh(
     owner: &str,
     repo: &str,
     pr_number: u64,
@@ -140,9 +122,9 @@ fn fetch_mergeable_status_from_gh(
             "--repo",
             &format!("{}/{}", owner, repo),
             "--json",
-            "mergeable",
+            "mergeable,mergeStateStatus",
         ])
-        .output()
+        .traced_output()
         .context("Failed to run 'gh pr view'")?;
 
     if !output.status.success() {
@@ -153,11 +135,18 @@ fn fetch_mergeable_status_from_gh(
     let pr: GhPrMergeable =
         serde_json::from_slice(&output.stdout).context("Failed to parse gh pr view output")?;
 
-    Ok(match pr.mergeable.as_str() {
-        "MERGEABLE" => MergeableStatus::Mergeable,
-        "CONFLICTING" => MergeableStatus::Conflicting,
+    Ok(parse_mergeable(&pr.mergeable, &pr.merge_state_status))
+}
+
+/// GitHub computes `mergeable` lazily and reports UNKNOWN until it has; the
+/// merge state is DIRTY whenever the merge commit can't be created cleanly,
+/// so either one showing a conflict is enough.
+fn parse_mergeable(mergeable: &str, merge_state_status: &str) -> MergeableStatus {
+    match (mergeable, merge_state_status) {
+        ("CONFLICTING", _) | (_, "DIRTY") => MergeableStatus::Conflicting,
+        ("MERGEABLE", _) => MergeableStatus::Mergeable,
         _ => MergeableStatus::Unknown,
-    })
+    }
 }
 
 /// Resolve PR context from CLI args and/or auto-detection.
@@ -187,6 +176,58 @@ pub fn resolve_pr_context(
     })
 }
 
+/// Oldest gh with everything pr-loop uses; `gh pr checks --json` arrived in 2.40.
+pub const MIN_GH_VERSION: (u32, u32, u32) = (2, 40, 0);
+
+const GH_INSTALL_HINT: &str = "Install it from https://cli.github.com/ (or e.g. `brew install gh`), \
+    then run `gh auth login`.";
+
+static GH_PROBE: OnceLock<Result<(), String>> = OnceLock::new();
+
+/// Check once per process that gh is installed and new enough, so a missing
+/// or outdated gh fails up front with instructions instead of as a confusing
+/// error deep inside whichever command runs gh first.
+pub fn ensure_gh_available() -> Result<()> {
+    GH_PROBE
+        .get_or_init(|| {
+            let output = Command::new("gh").arg("--version").traced_output();
+            let stdout = output
+                .ok()
+                .filter(|o| o.status.success())
+                .map(|o| String::from_utf8_lossy(&o.stdout).into_owned());
+            check_gh_version(stdout.as_deref())
+        })
+        .clone()
+        .map_err(anyhow::Error::msg)
+}
+
+/// Judge `gh --version` output (None if gh couldn't be run). Versions that
+/// can't be parsed (e.g. a development build) are given the benefit of the doubt.
+fn check_gh_version(version_output: Option<&str>) -> std::result::Result<(), String> {
+    let Some(output) = version_output else {
+        return Err(format!("The GitHub CLI (gh) was not found on PATH. {}", GH_INSTALL_HINT));
+    };
+    let Some(version) = parse_gh_version(output) else {
+        return Ok(());
+    };
+    if version < MIN_GH_VERSION {
+        let (major, minor, patch) = MIN_GH_VERSION;
+        return Err(format!(
+            "gh {}.{}.{} is too old; pr-loop needs gh {}.{}.{} or newer. \
+             Upgrade with your package manager (e.g. `brew upgrade gh`) or from https://cli.github.com/.",
+            version.0, version.1, version.2, major, minor, patch
+        ));
+    }
+    Ok(())
+}
+
+/// The version in `gh version 2.45.0 (2024-03-04)`.
+pub fn parse_gh_version(output: &str) -> Option<(u32, u32, u32)> {
+    let word = output.lines().next()?.strip_prefix("gh version ")?.split_whitespace().next()?;
+    let mut parts = word.split('.').map(|p| p.parse::<u32>().ok());
+    Some((parts.next()??, parts.next()??, parts.next().flatten().unwrap_or(0)))
+}
+
 /// Parse "owner/repo" format from CLI arg.
 fn parse_repo_arg(repo_str: &str) -> Result<(String, String)> {
     let parts: Vec<&str> = repo_str.split('/').collect();
@@ -222,6 +263,29 @@ mod tests {
         }
     }
 
+    #[test]
+    fn gh_version_checks() {
+        assert_eq!(
+            parse_gh_version("gh version 2.45.0 (2024-03-04)\nhttps://github.com/cli/cli/releases/tag/v2.45.0\n"),
+            Some((2, 45, 0))
+        );
+        assert!(check_gh_version(Some("gh version 2.45.0 (2024-03-04)")).is_ok());
+        assert!(check_gh_version(Some("gh version DEV")).is_ok());
+
+        let old = check_gh_version(Some("gh version 2.14.7 (2022-08-25)")).unwrap_err();
+        assert!(old.starts_with("gh 2.14.7 is too old; pr-loop needs gh 2.40.0 or newer."));
+        let missing = check_gh_version(None).unwrap_err();
+        assert!(missing.contains("not found on PATH") && missing.contains("cli.github.com"));
+    }
+
+    #[test]
+    fn conflicts_from_either_field() {
+        assert_eq!(parse_mergeable("MERGEABLE", "CLEAN"), MergeableStatus::Mergeable);
+        assert_eq!(parse_mergeable("CONFLICTING", "DIRTY"), MergeableStatus::Conflicting);
+        assert_eq!(parse_mergeable("UNKNOWN", "DIRTY"), MergeableStatus::Conflicting);
+        assert_eq!(parse_mergeable("UNKNOWN", "UNKNOWN"), MergeableStatus::Unknown);
+    }
+
     #[test]
     fn parse_repo_arg_valid() {
         let (owner, repo) = parse_repo_arg("glasser/pr-loop-test-repo").unwrap();
@@ -0,0 +1,190 @@
+// Running GraphQL operations through `gh api graphql`.
+// The query and variables are sent as a JSON request body on stdin
+// (`--input -`) rather than as `-f key=value` arguments: comment bodies can
+// be long enough to hit the OS argument-length limit, and `-f` gives special
+// meaning to values starting with `@`. On stdin any body goes through as-is.
+//
+// Work that would take one request per item (say, the next page of comments
+// of each long thread) can be coalesced with `batch_operation`: one request
+// runs the operation's root field once per item, under aliases.
+
+use crate::{request_log, retry};
+use serde_json::{json, Value};
+use std::io::Write;
+use std::process::{Command, Output, Stdio};
+use std::time::Instant;
+
+/// The JSON request body for an operation.
+pub fn request_body(query: &str, variables: &Value) -> Vec<u8> {
+    serde_json::to_vec(&json!({ "query": query, "variables": variables }))
+        .expect("GraphQL request serializes")
+}
+
+/// Run `query` with `variables` (a JSON object) via `gh api graphql`,
+/// recording it in the request log like `traced_output` does. Transient
+/// failures are retried (mutations only when GitHub can't have applied them).
+pub fn gh_graphql(query: &str, variables: Value) -> std::io::Result<Output> {
+    let run = || {
+        let mut command = Command::new("gh");
+        command
+            .args(["api", "graphql", "--input", "-"])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+        let started = Instant::now();
+        let result = run_with_input(&mut command, &request_body(query, &variables));
+        request_log::record_graphql(&command, started, result.as_ref().ok(), query, &variables);
+        result
+    };
+    if query.trim_start().starts_with("mutation") {
+        retry::gh_mutation(run)
+    } else {
+        retry::gh(run)
+    }
+}
+
+/// The alias of the `index`th item's result in a `batch_operation` query.
+pub fn batch_alias(index: usize) -> String {
+    format!("item{}", index)
+}
+
+/// Coalesce `operation` over `items` into one query: its root field runs
+/// once per item, under `batch_alias(i)`, with that item's variables (a JSON
+/// object each). `operation` is a single-field query from graphql/operation/,
+/// so what's sent is still the schema-validated operation; each copy's
+/// variables are renamed with its index (`$id` becomes `$id_0`).
+pub fn batch_operation(operation: &str, items: &[Value]) -> (String, Value) {
+    let open = operation.find('{').expect("operation has a selection set");
+    let close = operation.rfind('}').expect("operation has a selection set");
+    let header = operation[..open].trim();
+    let body = operation[open + 1..close].trim();
+    let (name, definitions) = match header.split_once('(') {
+        Some((name, rest)) => (name.trim(), rest.trim_end().trim_end_matches(')')),
+        None => (header, ""),
+    };
+    let definitions: Vec<(&str, &str)> = definitions
+        .split(',')
+        .filter_map(|d| d.split_once(':'))
+        .map(|(var, ty)| (var.trim().trim_start_matches('$'), ty.trim()))
+        .collect();
+
+    let mut all_definitions = Vec::new();
+    let mut fields = Vec::new();
+    let mut variables = serde_json::Map::new();
+    for (i, item) in items.iter().enumerate() {
+        let mut field = body.to_string();
+        for (var, ty) in &definitions {
+            let renamed = format!("{}_{}", var, i);
+            field = rename_variable(&field, var, &renamed);
+            all_definitions.push(format!("${}: {}", renamed, ty));
+            if let Some(value) = item.get(*var) {
+                variables.insert(renamed, value.clone());
+            }
+        }
+        fields.push(format!("{}: {}", batch_alias(i), field));
+    }
+    let query = format!(
+        "{}Batch({}) {{\n{}\n}}",
+        name,
+        all_definitions.join(", "),
+        fields.join("\n")
+    );
+    (query, Value::Object(variables))
+}
+
+/// Replace uses of `$from` (not `$fromX`) in `text` with `$to`.
+fn rename_variable(text: &str, from: &str, to: &str) -> String {
+    let pattern = format!("${}", from);
+    let mut out = String::new();
+    let mut rest = text;
+    while let Some(pos) = rest.find(&pattern) {
+        let after = &rest[pos + pattern.len()..];
+        out.push_str(&rest[..pos]);
+        if after.starts_with(|c: char| c.is_ascii_alphanumeric() || c == '_') {
+            out.push_str(&pattern);
+        } else {
+            out.push('$');
+            out.push_str(to);
+        }
+        rest = after;
+    }
+    out.push_str(rest);
+    out
+}
+
+fn run_with_input(command: &mut Command, input: &[u8]) -> std::io::Result<Output> {
+    let mut child = command.spawn()?;
+    // Write from a separate thread: gh may start answering before it has read
+    // a large body, and neither side would make progress with both pipes full.
+    let mut stdin = child.stdin.take().expect("stdin is piped");
+    let input = input.to_vec();
+    let writer = std::thread::spawn(move || stdin.write_all(&input));
+    let output = child.wait_with_output()?;
+    match writer.join() {
+        Ok(Ok(())) => Ok(output),
+        // gh exiting early closes the pipe; its own error output says why.
+        Ok(Err(e)) if e.kind() == std::io::ErrorKind::BrokenPipe => Ok(output),
+        Ok(Err(e)) => Err(e),
+        Err(_) => Err(std::io::Error::other("stdin writer panicked")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(body: &str) -> Value {
+        let request = request_body("mutation AddReply { x }", &json!({ "threadId": "T1", "body": body }));
+        serde_json::from_slice(&request).unwrap()
+    }
+
+    #[test]
+    fn bodies_survive_unchanged() {
+        for body in [
+            "@/etc/passwd",
+            "quotes \" ' ` and $(subshells) and \\backslashes\\",
+            "multibyte: ü 日本語 🤖\u{200d}🔧",
+            "key=value\n-f body=injected",
+        ] {
+            let request = round_trip(body);
+            assert_eq!(request["query"], "mutation AddReply { x }");
+            assert_eq!(request["variables"]["body"], body);
+        }
+    }
+
+    #[test]
+    fn batches_aliased_copies_of_an_operation() {
+        let operation = "query Fetch($id: ID!, $idx: Int) {\n  node(id: $id) { a(n: $idx) }\n}\n";
+        let (query, variables) = batch_operation(operation, &[json!({ "id": "T1", "idx": 2 }), json!({ "id": "T2" })]);
+        assert_eq!(
+            query,
+            "query FetchBatch($id_0: ID!, $idx_0: Int, $id_1: ID!, $idx_1: Int) {\n\
+             item0: node(id: $id_0) { a(n: $idx_0) }\n\
+             item1: node(id: $id_1) { a(n: $idx_1) }\n}"
+        );
+        assert_eq!(variables, json!({ "id_0": "T1", "idx_0": 2, "id_1": "T2" }));
+    }
+
+    #[test]
+    fn huge_bodies_go_through_stdin() {
+        // Far beyond what fits in a single argument on Linux (128 KiB).
+        let body = "🤖 ".repeat(200_000);
+        assert_eq!(round_trip(&body)["variables"]["body"], body.as_str());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn huge_bodies_reach_the_subprocess_whole() {
+        let body = "🤖 ".repeat(200_000);
+        let mut command = Command::new("wc");
+        command
+            .arg("-c")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+        let input = request_body("q", &json!({ "body": body }));
+        let output = run_with_input(&mut command, &input).unwrap();
+        let count: usize = String::from_utf8_lossy(&output.stdout).trim().parse().unwrap();
+        assert_eq!(count, input.len());
+    }
+}
@@ -55,4 +55,17 @@ mod tests {
             );
         }
     }
+
+    #[test]
+    fn validate_batched_operations() {
+        // Batching repeats an operation under aliases; the result must still
+        // be a valid query
+        let schema = load_schema();
+        let operation = include_str!("../graphql/operation/fetch_remaining_comments.graphql");
+        let items = [serde_json::json!({ "id": "T1" }), serde_json::json!({ "id": "T2", "cursor": "c" })];
+        let (query, _) = crate::graphql::batch_operation(operation, &items);
+        if let Err(e) = ExecutableDocument::parse_and_validate(&schema, &query, "batch.graphql") {
+            panic!("Batched query is invalid:\n{}\n\n{}", e, query);
+        }
+    }
 }
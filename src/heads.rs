@@ -0,0 +1,104 @@
+// Whether anything was pushed since CI was last seen reporting on a PR.
+// `min_wait_after_push` gives CI time to register checks for a fresh push
+// before an all-green PR counts as happy. When the agent only answered
+// threads, nothing was pushed and the checks it sees are already the ones
+// for the head, so the delay just slows the loop down. Each happy wait that
+// sees the PR's checks records the HEAD it saw them at in
+// `.pr-loop/heads.json`; a HEAD equal to that one means there's nothing for
+// CI to catch up with. Plain analyses don't record anything, so looking at a
+// PR right after a push doesn't use up the delay.
+
+use crate::audit::STATE_DIR;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+const HEADS_FILE: &str = "heads.json";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct SeenHead {
+    /// e.g. "owner/repo#123"
+    pr: String,
+    head: String,
+}
+
+/// The HEAD each PR's checks were last seen at, recorded under a repository
+/// root.
+#[derive(Debug, Clone, Default)]
+pub struct SeenHeads {
+    /// None when there's no repository to store them in.
+    path: Option<PathBuf>,
+    entries: Vec<SeenHead>,
+}
+
+impl SeenHeads {
+    /// Load `<root>/.pr-loop/heads.json`.
+    pub fn load(root: &Path) -> Result<Self> {
+        let path = root.join(STATE_DIR).join(HEADS_FILE);
+        let entries = match std::fs::read_to_string(&path) {
+            Ok(content) => serde_json::from_str(&content)
+                .with_context(|| format!("Failed to parse {}", path.display()))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => vec![],
+            Err(e) => return Err(e).with_context(|| format!("Failed to read {}", path.display())),
+        };
+        Ok(Self {
+            path: Some(path),
+            entries,
+        })
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let Some(path) = &self.path else { return Ok(()) };
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+            let gitignore = dir.join(".gitignore");
+            if !gitignore.exists() {
+                std::fs::write(&gitignore, "*\n")?;
+            }
+        }
+        std::fs::write(path, serde_json::to_string_pretty(&self.entries)? + "\n")
+            .with_context(|| format!("Failed to write {}", path.display()))
+    }
+
+    /// Whether `head` differs from the one `pr`'s checks were last seen at.
+    /// A PR never seen counts as moved, so the push-settling delay is only
+    /// skipped when it's known to be safe.
+    pub fn moved(&self, pr: &str, head: &str) -> bool {
+        !self.entries.iter().any(|e| e.pr == pr && e.head == head)
+    }
+
+    /// Record that `pr`'s checks were seen at `head`.
+    pub fn record(&mut self, pr: &str, head: &str) {
+        match self.entries.iter_mut().find(|e| e.pr == pr) {
+            Some(entry) => entry.head = head.to_string(),
+            None => self.entries.push(SeenHead {
+                pr: pr.to_string(),
+                head: head.to_string(),
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn head_moves_only_when_it_changes() {
+        let root = std::env::temp_dir().join(format!("pr-loop-heads-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        let mut heads = SeenHeads::load(&root).unwrap();
+        assert!(heads.moved("o/r#1", "aaa"));
+        heads.record("o/r#1", "aaa");
+        assert!(!heads.moved("o/r#1", "aaa"));
+        assert!(heads.moved("o/r#2", "aaa"));
+        heads.save().unwrap();
+
+        let mut reloaded = SeenHeads::load(&root).unwrap();
+        assert!(!reloaded.moved("o/r#1", "aaa"));
+        assert!(reloaded.moved("o/r#1", "bbb"));
+        reloaded.record("o/r#1", "bbb");
+        assert!(!reloaded.moved("o/r#1", "bbb"));
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
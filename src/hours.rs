@@ -0,0 +1,290 @@
+// Working-hours awareness for wait modes.
+// Outside the configured hours, waits can poll less often, notifications are
+// held back, and the loop can idle until a human is around to supervise,
+// instead of replying at 3am. The hours are checked on every poll, so a wait
+// that runs into or out of them adjusts as it goes.
+
+use crate::config::WorkingHoursConfig;
+use anyhow::{Context, Result};
+use jiff::civil::{Time, Weekday};
+use jiff::tz::TimeZone;
+use jiff::Zoned;
+use std::sync::OnceLock;
+use std::time::Duration;
+
+const DEFAULT_DAYS: [Weekday; 5] = [
+    Weekday::Monday,
+    Weekday::Tuesday,
+    Weekday::Wednesday,
+    Weekday::Thursday,
+    Weekday::Friday,
+];
+
+/// The configured working hours, set at startup.
+static CONFIGURED: OnceLock<WorkingHours> = OnceLock::new();
+
+/// Make `hours` the working hours that polling and notifications go by.
+pub fn configure(hours: WorkingHours) {
+    let _ = CONFIGURED.set(hours);
+}
+
+/// Whether it's inside working hours now; always true without any configured.
+pub fn is_working_time() -> bool {
+    CONFIGURED.get().is_none_or(WorkingHours::is_working_time)
+}
+
+/// `interval`, lengthened to the off-hours poll interval while outside
+/// working hours.
+pub fn paced(interval: Duration) -> Duration {
+    match CONFIGURED.get() {
+        Some(hours) => hours.poll_interval(interval, &Zoned::now()),
+        None => interval,
+    }
+}
+
+/// A parsed working-hours window.
+#[derive(Debug, Clone)]
+pub struct WorkingHours {
+    start: Time,
+    end: Time,
+    days: Vec<Weekday>,
+    tz: TimeZone,
+    /// Poll interval to use in wait modes outside working hours.
+    pub off_hours_poll_interval: Option<u64>,
+}
+
+impl WorkingHours {
+    /// Parse the `[working_hours]` config section. Returns None if no hours
+    /// are configured.
+    pub fn from_config(config: &WorkingHoursConfig) -> Result<Option<Self>> {
+        let (start, end) = match (&config.start, &config.end) {
+            (None, None) => return Ok(None),
+            (Some(start), Some(end)) => (parse_time(start)?, parse_time(end)?),
+            _ => anyhow::bail!("working_hours needs both `start` and `end`"),
+        };
+        let days = match &config.days {
+            Some(days) => days.iter().map(|d| parse_weekday(d)).collect::<Result<_>>()?,
+            None => DEFAULT_DAYS.to_vec(),
+        };
+        let tz = match &config.timezone {
+            Some(name) => {
+                TimeZone::get(name).context(format!("Unknown working_hours timezone: {}", name))?
+            }
+            None => TimeZone::system(),
+        };
+        Ok(Some(Self {
+            start,
+            end,
+            days,
+            tz,
+            off_hours_poll_interval: config.off_hours_poll_interval,
+        }))
+    }
+
+    /// Returns true if `now` falls inside working hours. Windows where `end`
+    /// is before `start` (e.g. 22:00–06:00) wrap past midnight and belong to
+    /// the day they start on.
+    pub fn contains(&self, now: &Zoned) -> bool {
+        let now = now.with_time_zone(self.tz.clone());
+        let t = now.time();
+        if self.start <= self.end {
+            self.days.contains(&now.weekday()) && self.start <= t && t < self.end
+        } else if t >= self.start {
+            self.days.contains(&now.weekday())
+        } else {
+            let yesterday = now.date().yesterday().map(|d| d.weekday());
+            t < self.end && yesterday.is_ok_and(|d| self.days.contains(&d))
+        }
+    }
+
+    /// Returns true if the current time is inside working hours.
+    pub fn is_working_time(&self) -> bool {
+        self.contains(&Zoned::now())
+    }
+
+    /// How often to poll at `now`, given the usual `interval`: never more
+    /// often than that, and no more often than `off_hours_poll_interval`
+    /// outside working hours.
+    pub fn poll_interval(&self, interval: Duration, now: &Zoned) -> Duration {
+        match self.off_hours_poll_interval {
+            Some(off_hours) if !self.contains(now) => interval.max(Duration::from_secs(off_hours)),
+            _ => interval,
+        }
+    }
+
+    /// Time from `now` until working hours next begin (zero if inside them).
+    pub fn until_next_start(&self, now: &Zoned) -> Duration {
+        if self.contains(now) {
+            return Duration::ZERO;
+        }
+        let now = now.with_time_zone(self.tz.clone());
+        let mut date = now.date();
+        for _ in 0..8 {
+            if self.days.contains(&date.weekday())
+                && let Ok(start) = date.to_datetime(self.start).to_zoned(self.tz.clone())
+                && start > now
+            {
+                let secs = start.timestamp().as_second() - now.timestamp().as_second();
+                return Duration::from_secs(secs.max(0) as u64);
+            }
+            match date.tomorrow() {
+                Ok(next) => date = next,
+                Err(_) => break,
+            }
+        }
+        Duration::ZERO
+    }
+
+    /// Human-readable description, e.g. "09:00–18:00 Mon–Fri (America/New_York)".
+    pub fn describe(&self) -> String {
+        let days: Vec<_> = self.days.iter().map(|d| weekday_abbrev(*d)).collect();
+        format!(
+            "{}–{} {} ({})",
+            self.start.strftime("%H:%M"),
+            self.end.strftime("%H:%M"),
+            days.join(","),
+            self.tz.iana_name().unwrap_or("local time")
+        )
+    }
+}
+
+fn parse_time(s: &str) -> Result<Time> {
+    s.parse::<Time>()
+        .context(format!("Invalid working_hours time '{}' (expected HH:MM)", s))
+}
+
+fn parse_weekday(s: &str) -> Result<Weekday> {
+    Ok(match s.to_lowercase().get(..3) {
+        Some("mon") => Weekday::Monday,
+        Some("tue") => Weekday::Tuesday,
+        Some("wed") => Weekday::Wednesday,
+        Some("thu") => Weekday::Thursday,
+        Some("fri") => Weekday::Friday,
+        Some("sat") => Weekday::Saturday,
+        Some("sun") => Weekday::Sunday,
+        _ => anyhow::bail!("Invalid working_hours day '{}'", s),
+    })
+}
+
+fn weekday_abbrev(day: Weekday) -> &'static str {
+    match day {
+        Weekday::Monday => "Mon",
+        Weekday::Tuesday => "Tue",
+        Weekday::Wednesday => "Wed",
+        Weekday::Thursday => "Thu",
+        Weekday::Friday => "Fri",
+        Weekday::Saturday => "Sat",
+        Weekday::Sunday => "Sun",
+    }
+}
+
+/// Format a duration as e.g. "5h12m" for status messages.
+pub fn format_duration(d: Duration) -> String {
+    let mins = d.as_secs().div_ceil(60);
+    if mins >= 60 {
+        format!("{}h{:02}m", mins / 60, mins % 60)
+    } else {
+        format!("{}m", mins)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hours(start: &str, end: &str, days: Option<Vec<&str>>) -> WorkingHours {
+        WorkingHours::from_config(&WorkingHoursConfig {
+            start: Some(start.to_string()),
+            end: Some(end.to_string()),
+            days: days.map(|d| d.into_iter().map(String::from).collect()),
+            timezone: Some("UTC".to_string()),
+            off_hours_poll_interval: None,
+        })
+        .unwrap()
+        .unwrap()
+    }
+
+    fn at(s: &str) -> Zoned {
+        // 2026-10-12 is a Monday
+        format!("{}[UTC]", s).parse().unwrap()
+    }
+
+    #[test]
+    fn unconfigured_is_none() {
+        assert!(WorkingHours::from_config(&WorkingHoursConfig::default())
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn rejects_invalid_config() {
+        let mut config = WorkingHoursConfig {
+            start: Some("09:00".to_string()),
+            ..Default::default()
+        };
+        assert!(WorkingHours::from_config(&config).is_err());
+        config.end = Some("25:00".to_string());
+        assert!(WorkingHours::from_config(&config).is_err());
+        config.end = Some("17:00".to_string());
+        config.days = Some(vec!["someday".to_string()]);
+        assert!(WorkingHours::from_config(&config).is_err());
+    }
+
+    #[test]
+    fn contains_weekday_window() {
+        let h = hours("09:00", "18:00", None);
+        assert!(h.contains(&at("2026-10-12T09:00:00")));
+        assert!(h.contains(&at("2026-10-12T17:59:00")));
+        assert!(!h.contains(&at("2026-10-12T18:00:00")));
+        assert!(!h.contains(&at("2026-10-12T03:00:00")));
+        // Saturday
+        assert!(!h.contains(&at("2026-10-17T12:00:00")));
+    }
+
+    #[test]
+    fn contains_overnight_window() {
+        let h = hours("22:00", "06:00", Some(vec!["fri"]));
+        assert!(h.contains(&at("2026-10-16T23:00:00")));
+        // Early Saturday belongs to Friday's window
+        assert!(h.contains(&at("2026-10-17T05:00:00")));
+        assert!(!h.contains(&at("2026-10-17T23:00:00")));
+    }
+
+    #[test]
+    fn until_next_start_skips_weekend() {
+        let h = hours("09:00", "18:00", None);
+        assert_eq!(h.until_next_start(&at("2026-10-12T10:00:00")), Duration::ZERO);
+        assert_eq!(
+            h.until_next_start(&at("2026-10-12T03:00:00")),
+            Duration::from_secs(6 * 3600)
+        );
+        // Friday evening → Monday 09:00
+        assert_eq!(
+            h.until_next_start(&at("2026-10-16T20:00:00")),
+            Duration::from_secs((4 + 48 + 9) * 3600)
+        );
+    }
+
+    #[test]
+    fn polls_less_often_only_outside_hours() {
+        let mut h = hours("09:00", "18:00", None);
+        let interval = Duration::from_secs(30);
+        assert_eq!(h.poll_interval(interval, &at("2026-10-12T03:00:00")), interval);
+        h.off_hours_poll_interval = Some(300);
+        assert_eq!(h.poll_interval(interval, &at("2026-10-12T10:00:00")), interval);
+        assert_eq!(
+            h.poll_interval(interval, &at("2026-10-12T03:00:00")),
+            Duration::from_secs(300)
+        );
+        let slow = Duration::from_secs(600);
+        assert_eq!(h.poll_interval(slow, &at("2026-10-12T03:00:00")), slow);
+    }
+
+    #[test]
+    fn describe_and_format_duration() {
+        let h = hours("09:00", "18:00", Some(vec!["Mon", "tuesday"]));
+        assert_eq!(h.describe(), "09:00–18:00 Mon,Tue (UTC)");
+        assert_eq!(format_duration(Duration::from_secs(90)), "2m");
+        assert_eq!(format_duration(Duration::from_secs(5 * 3600 + 12 * 60)), "5h12m");
+    }
+}
@@ -371,6 +371,12 @@ h1 {{ font-size: 18px; margin-bottom: 16px; }}
 // -- LaunchAgent install/uninstall (unchanged behavior) ----------------------
 
 pub fn install() -> Result<()> {
+    if !cfg!(target_os = "macos") {
+        anyhow::bail!(
+            "--install writes a macOS LaunchAgent; elsewhere, start `pr-loop hub` at login \
+             yourself (a systemd user unit, or a Task Scheduler task on Windows)"
+        );
+    }
     let plist_path = plist_path()?;
     let exe = std::env::current_exe().context("current_exe")?;
     let log_path = log_path()?;
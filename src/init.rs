@@ -0,0 +1,324 @@
+// `pr-loop init`: scaffold the files a repository needs to adopt the loop.
+// Writes a starter `.github/pr-loop.toml` (team defaults, every setting
+// commented out until someone decides on it), a section in CLAUDE.md telling
+// the agent how the loop works, and with `--hooks` a Claude Code Stop hook
+// that keeps the agent going while the PR still needs work. What's written is
+// tailored to the CI providers found in the checkout: CircleCI repositories
+// get the token and `repro --docker` notes, GitHub Actions ones the
+// `delayed_checks` hint for `workflow_run` workflows.
+//
+// Existing files are left alone unless `--force`; CLAUDE.md is appended to,
+// between markers, so rerunning init only ever touches its own section.
+
+use anyhow::{Context, Result};
+use std::path::{Path, PathBuf};
+
+const CLAUDE_MD_START: &str = "<!-- pr-loop:start -->";
+const CLAUDE_MD_END: &str = "<!-- pr-loop:end -->";
+
+/// Where the Stop hook is written, relative to the repository root.
+pub const STOP_HOOK_PATH: &str = ".claude/hooks/pr-loop-stop.sh";
+
+/// A CI provider pr-loop has specific support for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CiProvider {
+    CircleCi,
+    GitHubActions,
+}
+
+impl CiProvider {
+    pub fn name(self) -> &'static str {
+        match self {
+            CiProvider::CircleCi => "CircleCI",
+            CiProvider::GitHubActions => "GitHub Actions",
+        }
+    }
+}
+
+/// The CI providers configured in the checkout at `root`.
+pub fn detect_providers(root: &Path) -> Vec<CiProvider> {
+    let mut providers = Vec::new();
+    if root.join(".circleci/config.yml").exists() || root.join(".circleci/config.yaml").exists() {
+        providers.push(CiProvider::CircleCi);
+    }
+    let has_workflows = std::fs::read_dir(root.join(".github/workflows"))
+        .map(|entries| entries.flatten().any(|e| is_yaml(&e.path())))
+        .unwrap_or(false);
+    if has_workflows {
+        providers.push(CiProvider::GitHubActions);
+    }
+    providers
+}
+
+fn is_yaml(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext == "yml" || ext == "yaml")
+}
+
+/// How a scaffolded file is written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum WriteMode {
+    /// The whole file.
+    Whole,
+    /// A marked section appended to (or replaced in) the file.
+    Section,
+}
+
+/// A file init writes, relative to the repository root.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScaffoldFile {
+    pub path: PathBuf,
+    content: String,
+    mode: WriteMode,
+    executable: bool,
+}
+
+/// What happened to each file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    Created,
+    Overwritten,
+    Appended,
+    /// Already there; left alone (rerun with `--force` to replace).
+    Kept,
+}
+
+impl Outcome {
+    pub fn describe(self) -> &'static str {
+        match self {
+            Outcome::Created => "created",
+            Outcome::Overwritten => "overwritten",
+            Outcome::Appended => "added pr-loop section",
+            Outcome::Kept => "already exists, kept (--force to replace)",
+        }
+    }
+}
+
+/// The files to write for a repository using `providers`.
+pub fn plan(providers: &[CiProvider], hooks: bool) -> Vec<ScaffoldFile> {
+    let mut files = vec![
+        ScaffoldFile {
+            path: PathBuf::from(".github/pr-loop.toml"),
+            content: repo_config(providers),
+            mode: WriteMode::Whole,
+            executable: false,
+        },
+        ScaffoldFile {
+            path: PathBuf::from("CLAUDE.md"),
+            content: claude_md_section(providers),
+            mode: WriteMode::Section,
+            executable: false,
+        },
+    ];
+    if hooks {
+        files.push(ScaffoldFile {
+            path: PathBuf::from(STOP_HOOK_PATH),
+            content: STOP_HOOK.to_string(),
+            mode: WriteMode::Whole,
+            executable: true,
+        });
+    }
+    files
+}
+
+/// Write `files` under `root`.
+pub fn apply(root: &Path, files: &[ScaffoldFile], force: bool) -> Result<Vec<(PathBuf, Outcome)>> {
+    let mut outcomes = Vec::new();
+    for file in files {
+        let path = root.join(&file.path);
+        let existing = match std::fs::read_to_string(&path) {
+            Ok(content) => Some(content),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+            Err(e) => return Err(e).with_context(|| format!("Failed to read {}", path.display())),
+        };
+        let (content, outcome) = match (file.mode, existing) {
+            (_, None) => (file.content.clone(), Outcome::Created),
+            (WriteMode::Whole, Some(_)) if force => (file.content.clone(), Outcome::Overwritten),
+            (WriteMode::Section, Some(current)) => match replace_section(&current, &file.content) {
+                Some(_) if !force => (current, Outcome::Kept),
+                Some(replaced) => (replaced, Outcome::Overwritten),
+                None => (format!("{}\n\n{}", current.trim_end(), file.content), Outcome::Appended),
+            },
+            (WriteMode::Whole, Some(current)) => (current, Outcome::Kept),
+        };
+        if outcome != Outcome::Kept {
+            if let Some(dir) = path.parent() {
+                std::fs::create_dir_all(dir)
+                    .with_context(|| format!("Failed to create {}", dir.display()))?;
+            }
+            std::fs::write(&path, content).with_context(|| format!("Failed to write {}", path.display()))?;
+            if file.executable {
+                make_executable(&path)?;
+            }
+        }
+        outcomes.push((file.path.clone(), outcome));
+    }
+    Ok(outcomes)
+}
+
+/// `current` with its marked pr-loop section replaced by `section`, or None
+/// if it has none.
+fn replace_section(current: &str, section: &str) -> Option<String> {
+    let start = current.find(CLAUDE_MD_START)?;
+    let end = current[start..].find(CLAUDE_MD_END)? + start + CLAUDE_MD_END.len();
+    let rest = current[end..].strip_prefix('\n').unwrap_or(&current[end..]);
+    Some(format!("{}{}{}", &current[..start], section, rest))
+}
+
+#[cfg(unix)]
+fn make_executable(path: &Path) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o755))
+        .with_context(|| format!("Failed to make {} executable", path.display()))
+}
+
+#[cfg(not(unix))]
+fn make_executable(_path: &Path) -> Result<()> {
+    Ok(())
+}
+
+fn repo_config(providers: &[CiProvider]) -> String {
+    let mut out = String::from(
+        "# Team defaults for pr-loop, layered under each user's\n\
+         # ~/.config/pr-loop/config.toml. Uncomment what the team agrees on;\n\
+         # `pr-loop config validate` checks the file.\n\
+         \n\
+         [checks]\n\
+         # Ignore checks from these GitHub Apps (e.g. coverage bots)\n\
+         # exclude_apps = [\"codecov\"]\n",
+    );
+    if providers.contains(&CiProvider::GitHubActions) {
+        out.push_str(
+            "# Checks from `workflow_run` workflows register only after the workflow\n\
+             # they follow; list them so waits hold out for them\n",
+        );
+    } else {
+        out.push_str("# Checks that register a while after a push; waits hold out for them\n");
+    }
+    out.push_str(
+        "# delayed_checks = [\"deploy-preview\"]\n\
+         \n\
+         [policy]\n\
+         # Have `ready` ask before marking the PR ready for review\n\
+         # require_confirm_for_ready = true\n\
+         \n\
+         [escalation]\n\
+         # Ask a human once a check keeps failing the same way, or a thread is\n\
+         # still open, after this many iterations\n\
+         # after = 3\n\
+         \n\
+         [rebase]\n\
+         # Recommend rebasing once the branch is this many commits behind\n\
+         # behind_threshold = 50\n",
+    );
+    out
+}
+
+fn claude_md_section(providers: &[CiProvider]) -> String {
+    let mut out = format!(
+        "{}\n\
+         ## PR Loop\n\
+         \n\
+         PRs here are iterated with `pr-loop`. It reads the PR's review threads and CI\n\
+         checks and says what to do next.\n\
+         \n\
+         - `pr-loop` analyzes the PR and recommends the next action.\n\
+         - `pr-loop --wait-until-actionable --maintain-status` waits until something needs\n\
+         \x20 attention (`--wait-until-actionable-or-happy` also returns once all is well).\n\
+         - `pr-loop reply --in-reply-to <COMMENT_ID> --message \"...\"` answers a review\n\
+         \x20 thread; `pr-loop resolve --thread <THREAD_ID>` resolves one.\n\
+         - `pr-loop context --format json` gives the full briefing as JSON.\n\
+         - `pr-loop ready` marks the PR ready for review once it's happy.\n\
+         \n\
+         Address one item at a time: fix it, commit (new commits, no amending), push,\n\
+         then run pr-loop again.\n",
+        CLAUDE_MD_START
+    );
+    if providers.contains(&CiProvider::CircleCi) {
+        out.push_str(
+            "\n\
+             CI runs on CircleCI. Failure logs need a token in `CIRCLECI_TOKEN`;\n\
+             `pr-loop repro --check <name> --docker` reruns a failing job's commands in its\n\
+             image, and `pr-loop rerun` reruns failed workflows.\n",
+        );
+    }
+    if providers.contains(&CiProvider::GitHubActions) {
+        out.push_str(
+            "\n\
+             CI runs on GitHub Actions; pr-loop fetches failure logs with `gh`, no extra\n\
+             token needed. `pr-loop repro --check <name>` suggests how to reproduce a\n\
+             failure locally.\n",
+        );
+    }
+    out.push_str(CLAUDE_MD_END);
+    out.push('\n');
+    out
+}
+
+const STOP_HOOK: &str = r#"#!/bin/sh
+# Claude Code Stop hook written by `pr-loop init`: keeps the agent from
+# stopping while the PR still needs work from it. Needs jq. Register it in
+# .claude/settings.json:
+#
+#   "hooks": {"Stop": [{"hooks": [{"type": "command",
+#     "command": ".claude/hooks/pr-loop-stop.sh"}]}]}
+
+input=$(cat)
+# Already continuing because of this hook: let it stop rather than loop forever
+case "$input" in
+  *'"stop_hook_active":true'* | *'"stop_hook_active": true'*) exit 0 ;;
+esac
+
+kind=$(pr-loop context --format json 2>/dev/null | jq -r '.next_action.kind') || exit 0
+case "$kind" in
+  respond_to_comments | resolve_conflicts | fix_ci_failures | rebase)
+    echo "The PR still needs work (next action: $kind). Run pr-loop to see what." >&2
+    exit 2
+    ;;
+esac
+exit 0
+"#;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::RepoConfig;
+
+    fn temp_root(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("pr-loop-init-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn starter_config_parses_for_every_provider() {
+        for providers in [vec![], vec![CiProvider::CircleCi, CiProvider::GitHubActions]] {
+            let config: RepoConfig = toml::from_str(&repo_config(&providers)).unwrap();
+            assert_eq!(config, RepoConfig::default());
+        }
+    }
+
+    #[test]
+    fn writes_once_and_keeps_existing_files() {
+        let root = temp_root("apply");
+        std::fs::create_dir_all(root.join(".circleci")).unwrap();
+        std::fs::write(root.join(".circleci/config.yml"), "version: 2.1\n").unwrap();
+        std::fs::write(root.join("CLAUDE.md"), "# Agent Instructions\n").unwrap();
+        let providers = detect_providers(&root);
+        assert_eq!(providers, vec![CiProvider::CircleCi]);
+
+        let files = plan(&providers, true);
+        let outcomes: Vec<Outcome> = apply(&root, &files, false).unwrap().into_iter().map(|(_, o)| o).collect();
+        assert_eq!(outcomes, vec![Outcome::Created, Outcome::Appended, Outcome::Created]);
+        let claude_md = std::fs::read_to_string(root.join("CLAUDE.md")).unwrap();
+        assert!(claude_md.starts_with("# Agent Instructions\n\n<!-- pr-loop:start -->\n## PR Loop"));
+        assert!(claude_md.contains("CIRCLECI_TOKEN"));
+
+        // Rerunning leaves everything alone; --force rewrites pr-loop's own section only
+        let outcomes: Vec<Outcome> = apply(&root, &files, false).unwrap().into_iter().map(|(_, o)| o).collect();
+        assert_eq!(outcomes, vec![Outcome::Kept; 3]);
+        apply(&root, &files, true).unwrap();
+        assert_eq!(std::fs::read_to_string(root.join("CLAUDE.md")).unwrap(), claude_md);
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,243 @@
+// Issues linked to a PR (e.g. via "Closes #N") via GitHub GraphQL.
+// Shown in the analysis so fixes and replies can be checked against the
+// original requirements.
+
+use crate::graphql::gh_graphql;
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use serde_json::json;
+
+/// An issue the PR will close when merged.
+#[derive(Debug, Clone)]
+pub struct LinkedIssue {
+    pub number: u64,
+    pub title: String,
+    pub url: String,
+    /// "OPEN" or "CLOSED"
+    pub state: String,
+    pub body: String,
+    /// "owner/repo" the issue lives in (may differ from the PR's repo).
+    pub repo: String,
+}
+
+impl LinkedIssue {
+    /// Reference to the issue as seen from `owner/repo`: "#12" for the same
+    /// repo, "other/repo#12" otherwise.
+    pub fn reference(&self, owner: &str, repo: &str) -> String {
+        if self.repo.eq_ignore_ascii_case(&format!("{}/{}", owner, repo)) {
+            format!("#{}", self.number)
+        } else {
+            format!("{}#{}", self.repo, self.number)
+        }
+    }
+}
+
+/// Trait for fetching linked issues, allowing test implementations.
+pub trait IssuesClient {
+    fn fetch_linked_issues(&self, owner: &str, repo: &str, pr_number: u64)
+        -> Result<Vec<LinkedIssue>>;
+}
+
+pub struct RealIssuesClient;
+
+impl IssuesClient for RealIssuesClient {
+    fn fetch_linked_issues(
+        &self,
+        owner: &str,
+        repo: &str,
+        pr_number: u64,
+    ) -> Result<Vec<LinkedIssue>> {
+        fetch_linked_issues_graphql(owner, repo, pr_number)
+    }
+}
+
+/// GraphQL query for fetching linked issues (loaded from graphql/operation/).
+const FETCH_LINKED_ISSUES_QUERY: &str =
+    include_str!("../graphql/operation/fetch_linked_issues.graphql");
+
+#[derive(Deserialize)]
+struct GraphQLResponse {
+    data: Option<GraphQLData>,
+    errors: Option<Vec<GraphQLError>>,
+}
+
+#[derive(Deserialize)]
+struct GraphQLError {
+    message: String,
+}
+
+#[derive(Deserialize)]
+struct GraphQLData {
+    repository: Option<RepositoryData>,
+}
+
+#[derive(Deserialize)]
+struct RepositoryData {
+    #[serde(rename = "pullRequest")]
+    pull_request: Option<PullRequestData>,
+}
+
+#[derive(Deserialize)]
+struct PullRequestData {
+    #[serde(rename = "closingIssuesReferences")]
+    closing_issues_references: IssuesConnection,
+}
+
+#[derive(Deserialize)]
+struct IssuesConnection {
+    nodes: Vec<IssueNode>,
+}
+
+#[derive(Deserialize)]
+struct IssueNode {
+    number: u64,
+    title: String,
+    url: String,
+    state: String,
+    body: String,
+    repository: IssueRepository,
+}
+
+#[derive(Deserialize)]
+struct IssueRepository {
+    #[serde(rename = "nameWithOwner")]
+    name_with_owner: String,
+}
+
+fn fetch_linked_issues_graphql(owner: &str, repo: &str, pr_number: u64) -> Result<Vec<LinkedIssue>> {
+    let output = gh_graphql(FETCH_LINKED_ISSUES_QUERY, json!({ "owner": owner, "repo": repo, "pr": pr_number }))
+        .context("Failed to run 'gh api graphql' for linked issues")?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        anyhow::bail!("GraphQL query failed: {}", stderr.trim());
+    }
+
+    let response: GraphQLResponse = serde_json::from_slice(&output.stdout)
+        .context("Failed to parse GraphQL response")?;
+
+    if let Some(errors) = response.errors {
+        let messages: Vec<_> = errors.iter().map(|e| e.message.as_str()).collect();
+        anyhow::bail!("GraphQL errors: {}", messages.join(", "));
+    }
+
+    let nodes = response
+        .data
+        .and_then(|d| d.repository)
+        .and_then(|r| r.pull_request)
+        .map(|pr| pr.closing_issues_references.nodes)
+        .ok_or_else(|| anyhow::anyhow!("PR not found or no access"))?;
+
+    Ok(nodes
+        .into_iter()
+        .map(|n| LinkedIssue {
+            number: n.number,
+            title: n.title,
+            url: n.url,
+            state: n.state,
+            body: n.body,
+            repo: n.repository.name_with_owner,
+        })
+        .collect())
+}
+
+/// Extract acceptance criteria from an issue body: the items under an
+/// "Acceptance criteria" (or "Requirements") heading if there is one,
+/// otherwise any task-list items ("- [ ] ...").
+pub fn acceptance_criteria(body: &str) -> Vec<String> {
+    let lines: Vec<&str> = body.lines().map(str::trim).collect();
+    let section_start = lines.iter().position(|line| {
+        let heading = line.trim_start_matches('#').trim().trim_matches('*').trim_end_matches(':');
+        (line.starts_with('#') || line.starts_with("**"))
+            && ["acceptance criteria", "requirements"]
+                .iter()
+                .any(|h| heading.eq_ignore_ascii_case(h))
+    });
+
+    let candidates: Vec<&str> = match section_start {
+        Some(start) => lines[start + 1..]
+            .iter()
+            .take_while(|line| !line.starts_with('#'))
+            .copied()
+            .collect(),
+        None => lines
+            .iter()
+            .filter(|line| task_item(line).is_some())
+            .copied()
+            .collect(),
+    };
+
+    candidates
+        .into_iter()
+        .filter_map(|line| task_item(line).or_else(|| list_item(line)))
+        .map(str::to_string)
+        .collect()
+}
+
+/// Text of a task-list item ("- [ ] foo" / "- [x] foo").
+fn task_item(line: &str) -> Option<&str> {
+    let rest = list_item(line)?;
+    ["[ ] ", "[x] ", "[X] "]
+        .iter()
+        .find_map(|box_| rest.strip_prefix(box_))
+        .map(str::trim)
+}
+
+/// Text of a bulleted or numbered list item.
+fn list_item(line: &str) -> Option<&str> {
+    if let Some(rest) = line.strip_prefix("- ").or_else(|| line.strip_prefix("* ")) {
+        return Some(rest.trim());
+    }
+    let (num, rest) = line.split_once(". ")?;
+    (!num.is_empty() && num.chars().all(|c| c.is_ascii_digit())).then(|| rest.trim())
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    pub struct TestIssuesClient {
+        pub issues: Vec<LinkedIssue>,
+    }
+
+    impl IssuesClient for TestIssuesClient {
+        fn fetch_linked_issues(&self, _owner: &str, _repo: &str, _pr: u64) -> Result<Vec<LinkedIssue>> {
+            Ok(self.issues.clone())
+        }
+    }
+
+    #[test]
+    fn criteria_from_heading_section() {
+        let body = "Some context.\n\n- not a criterion\n\n## Acceptance Criteria\n\n- [ ] Retries on 503\n- Logs each retry\n1. Gives up after 3\n\n## Notes\n- unrelated";
+        assert_eq!(
+            acceptance_criteria(body),
+            vec!["Retries on 503", "Logs each retry", "Gives up after 3"]
+        );
+        let body = "**Requirements:**\n* works offline";
+        assert_eq!(acceptance_criteria(body), vec!["works offline"]);
+    }
+
+    #[test]
+    fn criteria_fall_back_to_task_list() {
+        let body = "Do the thing.\n- background\n- [x] part one\n- [ ] part two";
+        assert_eq!(acceptance_criteria(body), vec!["part one", "part two"]);
+        assert!(acceptance_criteria("Just prose.").is_empty());
+    }
+
+    #[test]
+    fn reference_is_short_for_same_repo() {
+        let client = TestIssuesClient {
+            issues: vec![LinkedIssue {
+                number: 7,
+                title: "Bug".to_string(),
+                url: String::new(),
+                state: "OPEN".to_string(),
+                body: String::new(),
+                repo: "Owner/Repo".to_string(),
+            }],
+        };
+        let issue = &client.fetch_linked_issues("owner", "repo", 1).unwrap()[0];
+        assert_eq!(issue.reference("owner", "repo"), "#7");
+        assert_eq!(issue.reference("owner", "fork"), "Owner/Repo#7");
+    }
+}
@@ -0,0 +1,285 @@
+// Waking waits as soon as GitHub reports a change.
+// Polling notices a finished check or a new review comment only at the next
+// tick. With `--listen <addr>`, the wait modes also run a small HTTP server
+// for GitHub webhook deliveries: point a repository webhook (or a
+// `gh webhook forward` stream) at it, or pass `--forward-webhooks` to have
+// pr-loop start the forwarder itself. A delivery that may concern the PR
+// wakes the sleeping wait, which polls right away.
+//
+// Deliveries only say *that* something happened; the wait still polls for
+// what the PR looks like, so a missed or spurious delivery costs at most one
+// poll interval or one extra poll.
+
+use anyhow::{anyhow, Context, Result};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::io::Read;
+use std::net::SocketAddr;
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::{Condvar, Mutex};
+use std::thread;
+use std::time::Duration;
+use tiny_http::{Method, Request, Response, Server};
+
+/// Webhook events that can change what a wait sees.
+const FORWARDED_EVENTS: &[&str] = &[
+    "check_run",
+    "check_suite",
+    "status",
+    "workflow_run",
+    "pull_request",
+    "pull_request_review",
+    "pull_request_review_comment",
+    "pull_request_review_thread",
+    "issue_comment",
+];
+
+/// Larger deliveries are cut off; only the event's PR numbers are read.
+const MAX_DELIVERY_BYTES: u64 = 25 * 1024 * 1024;
+
+/// How long a woken wait lets a burst of deliveries (every check in a
+/// suite finishing) arrive before polling.
+pub const EVENT_SETTLE: Duration = Duration::from_secs(2);
+
+/// Whether a relevant delivery arrived since a wait last looked.
+static PENDING: Mutex<bool> = Mutex::new(false);
+static ARRIVED: Condvar = Condvar::new();
+
+fn notify() {
+    *PENDING.lock().expect("pending lock poisoned") = true;
+    ARRIVED.notify_all();
+}
+
+/// Wait up to `timeout` for a relevant delivery, returning true (and
+/// consuming it) if one arrived. Without a listener this just sleeps.
+pub fn wait_for_event(timeout: Duration) -> bool {
+    let pending = PENDING.lock().expect("pending lock poisoned");
+    let (mut pending, _) = ARRIVED
+        .wait_timeout_while(pending, timeout, |pending| !*pending)
+        .expect("pending lock poisoned");
+    std::mem::replace(&mut *pending, false)
+}
+
+/// The running webhook receiver. Stops forwarding when dropped or (on Unix) when
+/// pr-loop exits.
+pub struct Listener {
+    addr: SocketAddr,
+    forwarder: Option<Forwarder>,
+}
+
+/// A running `gh webhook forward`: on Unix, a shell wrapper and the pipe
+/// whose closing stops it; elsewhere, gh itself.
+struct Forwarder {
+    child: Child,
+    stdin: Option<ChildStdin>,
+}
+
+/// Listen on `bind` (e.g. "127.0.0.1:8787") for deliveries concerning PR
+/// `pr_number`. With a `secret`, deliveries must be signed with it.
+pub fn start(bind: &str, pr_number: u64, secret: Option<String>) -> Result<Listener> {
+    let server = Server::http(bind).map_err(|e| anyhow!("Failed to listen on {}: {}", bind, e))?;
+    let addr = server
+        .server_addr()
+        .to_ip()
+        .ok_or_else(|| anyhow!("{} is not a TCP address", bind))?;
+    thread::spawn(move || {
+        for request in server.incoming_requests() {
+            handle(request, pr_number, secret.as_deref());
+        }
+    });
+    eprintln!("Listening for webhook deliveries on http://{}/", addr);
+    Ok(Listener {
+        addr,
+        forwarder: None,
+    })
+}
+
+impl Listener {
+    /// Have `gh webhook forward` (the cli/gh-webhook extension) register a
+    /// temporary webhook on the repository that delivers here.
+    pub fn forward_from_github(&mut self, owner: &str, repo: &str, secret: Option<&str>) -> Result<()> {
+        let url = format!("http://{}/", self.addr);
+        let repo = format!("{}/{}", owner, repo);
+        let events = FORWARDED_EVENTS.join(",");
+        let mut args = vec!["webhook", "forward", "--repo", &repo, "--events", &events, "--url", &url];
+        if let Some(secret) = secret {
+            args.extend(["--secret", secret]);
+        }
+        let forwarder = spawn_forwarder(&args).context("Failed to run 'gh webhook forward'")?;
+        eprintln!(
+            "Forwarding {}'s webhook events with 'gh webhook forward' (needs `gh extension install cli/gh-webhook`).",
+            repo
+        );
+        self.forwarder = Some(forwarder);
+        Ok(())
+    }
+}
+
+/// pr-loop usually ends with process::exit, which skips destructors: the
+/// wrapper stops gh once its stdin closes, which happens however pr-loop
+/// exits.
+#[cfg(unix)]
+fn spawn_forwarder(args: &[&str]) -> std::io::Result<Forwarder> {
+    let mut child = Command::new("sh")
+        .args(["-c", "\"$@\" & forwarder=$!; read _; kill $forwarder", "sh", "gh"])
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .spawn()?;
+    let stdin = child.stdin.take();
+    Ok(Forwarder { child, stdin })
+}
+
+/// Without sh, gh runs directly and is killed when the listener is dropped;
+/// an exit that skips that leaves it running until its console closes.
+#[cfg(not(unix))]
+fn spawn_forwarder(args: &[&str]) -> std::io::Result<Forwarder> {
+    let child = Command::new("gh")
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .spawn()?;
+    Ok(Forwarder { child, stdin: None })
+}
+
+impl Drop for Listener {
+    fn drop(&mut self) {
+        if let Some(Forwarder { mut child, stdin }) = self.forwarder.take() {
+            match stdin {
+                Some(stdin) => drop(stdin),
+                None => {
+                    let _ = child.kill();
+                }
+            }
+            let _ = child.wait();
+        }
+    }
+}
+
+fn handle(mut request: Request, pr_number: u64, secret: Option<&str>) {
+    let header = |name: &str| {
+        request
+            .headers()
+            .iter()
+            .find(|h| h.field.as_str().as_str().eq_ignore_ascii_case(name))
+            .map(|h| h.value.as_str().to_string())
+    };
+    let event = header("X-GitHub-Event").unwrap_or_default();
+    let signature = header("X-Hub-Signature-256");
+    let mut body = Vec::new();
+    let read = request
+        .as_reader()
+        .take(MAX_DELIVERY_BYTES)
+        .read_to_end(&mut body);
+
+    let status = if *request.method() != Method::Post {
+        405
+    } else if read.is_err() {
+        400
+    } else if let Some(secret) = secret
+        && !signature_matches(secret, &body, signature.as_deref())
+    {
+        401
+    } else {
+        let payload: Value = serde_json::from_slice(&body).unwrap_or(Value::Null);
+        if is_relevant(&event, &payload, pr_number) {
+            notify();
+        }
+        204
+    };
+    let _ = request.respond(Response::empty(status));
+}
+
+/// Whether a delivery may change what a wait on PR `pr_number` sees: events
+/// about that PR, and check events that don't name another PR (statuses,
+/// and checks on forks, don't say which PR they're for).
+pub fn is_relevant(event: &str, payload: &Value, pr_number: u64) -> bool {
+    let number = |pointer: &str| payload.pointer(pointer).and_then(Value::as_u64);
+    let named: Vec<u64> = match event {
+        "pull_request" | "pull_request_review" | "pull_request_review_comment" | "pull_request_review_thread" => {
+            number("/pull_request/number").into_iter().collect()
+        }
+        "issue_comment" => number("/issue/number").into_iter().collect(),
+        "check_run" | "check_suite" | "workflow_run" => payload
+            .get(event)
+            .and_then(|run| run.get("pull_requests"))
+            .and_then(Value::as_array)
+            .map(|prs| prs.iter().filter_map(|pr| pr.get("number")?.as_u64()).collect())
+            .unwrap_or_default(),
+        "status" => vec![],
+        _ => return false,
+    };
+    named.is_empty() || named.contains(&pr_number)
+}
+
+/// Check an `X-Hub-Signature-256` header ("sha256=<hex HMAC of the body>").
+fn signature_matches(secret: &str, body: &[u8], header: Option<&str>) -> bool {
+    let Some(given) = header.and_then(|h| h.strip_prefix("sha256=")) else {
+        return false;
+    };
+    let expected: String = hmac_sha256(secret.as_bytes(), body)
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect();
+    // Compare without stopping at the first difference
+    given.len() == expected.len()
+        && given
+            .bytes()
+            .zip(expected.bytes())
+            .fold(0u8, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+/// HMAC-SHA256 (RFC 2104).
+fn hmac_sha256(key: &[u8], message: &[u8]) -> [u8; 32] {
+    const BLOCK_SIZE: usize = 64;
+    let mut block = [0u8; BLOCK_SIZE];
+    if key.len() > BLOCK_SIZE {
+        block[..32].copy_from_slice(&Sha256::digest(key));
+    } else {
+        block[..key.len()].copy_from_slice(key);
+    }
+    let padded = |pad: u8| block.iter().map(|b| b ^ pad).collect::<Vec<u8>>();
+    let inner = Sha256::new().chain_update(padded(0x36)).chain_update(message).finalize();
+    Sha256::new()
+        .chain_update(padded(0x5c))
+        .chain_update(inner)
+        .finalize()
+        .into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn verifies_github_signatures() {
+        // RFC 4231 test case 2
+        let hex: String = hmac_sha256(b"Jefe", b"what do ya want for nothing?")
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect();
+        assert_eq!(hex, "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843");
+
+        let signature = format!("sha256={}", hex);
+        assert!(signature_matches("Jefe", b"what do ya want for nothing?", Some(&signature)));
+        assert!(!signature_matches("Jefe", b"what do ya want for nothing!", Some(&signature)));
+        assert!(!signature_matches("Jefe", b"what do ya want for nothing?", None));
+    }
+
+    #[test]
+    fn relevant_deliveries_concern_the_pr() {
+        let review_comment = json!({ "action": "created", "pull_request": { "number": 7 } });
+        assert!(is_relevant("pull_request_review_comment", &review_comment, 7));
+        assert!(!is_relevant("pull_request_review_comment", &review_comment, 8));
+
+        let check = |prs: Value| json!({ "check_run": { "status": "completed", "pull_requests": prs } });
+        assert!(is_relevant("check_run", &check(json!([{ "number": 7 }])), 7));
+        assert!(!is_relevant("check_run", &check(json!([{ "number": 9 }])), 7));
+        // Checks on forks don't name their PR
+        assert!(is_relevant("check_run", &check(json!([])), 7));
+
+        assert!(!is_relevant("ping", &json!({ "zen": "Keep it logically awesome." }), 7));
+    }
+}
@@ -0,0 +1,289 @@
+// Bounded-memory capture of large CI logs.
+// Steps can print tens of megabytes; holding that in memory (several times
+// over, once per failed step) can take down a small agent container. Logs
+// are streamed through a `LogWindow`, which keeps the head and tail and
+// only the error-looking lines from the middle, and all downloads for one
+// analysis share a `LogBudget`. Logs are arbitrary bytes, so everything
+// here decodes lossily and cuts only at character boundaries.
+
+use std::collections::VecDeque;
+use std::io::Read;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Bytes kept from the start of a log.
+pub const HEAD_BYTES: usize = 16 * 1024;
+/// Bytes kept from the end of a log.
+pub const TAIL_BYTES: usize = 256 * 1024;
+/// Bytes of a failed step's stderr shown before truncating.
+pub const STDERR_BUDGET: usize = 2000;
+/// Bytes of a failed step's stdout tail shown before truncating.
+pub const STDOUT_BUDGET: usize = 4000;
+/// Error lines kept from the omitted middle of a log.
+const MAX_MIDDLE_ERROR_LINES: usize = 50;
+/// Longest middle line considered; longer lines are cut.
+const MAX_LINE_BYTES: usize = 1000;
+
+/// Returns true for lines that look like an error report.
+pub fn looks_like_error(line: &str) -> bool {
+    const MARKERS: &[&str] = &["error", "failed", "failure", "panicked", "exception", "assert"];
+    let lower = line.to_lowercase();
+    MARKERS.iter().any(|m| lower.contains(m))
+}
+
+/// Truncate a log to at most `max_len` bytes, keeping the beginning. The cut
+/// is moved back to a character boundary, so multi-byte output never splits.
+pub fn truncate_log(s: &str, max_len: usize) -> String {
+    if s.len() <= max_len {
+        s.to_string()
+    } else {
+        let end = s.floor_char_boundary(max_len);
+        format!("{}...\n[truncated, {} more bytes]", &s[..end], s.len() - end)
+    }
+}
+
+/// Truncate a log to about `max_len` bytes, keeping the tail and starting on
+/// a line boundary (or a character boundary if the tail is a single line).
+pub fn truncate_log_tail(s: &str, max_len: usize) -> String {
+    if s.len() <= max_len {
+        s.to_string()
+    } else {
+        let start = s.ceil_char_boundary(s.len() - max_len);
+        let start = s[start..].find('\n').map(|i| start + i + 1).unwrap_or(start);
+        format!("[... {} bytes truncated]\n{}", start, &s[start..])
+    }
+}
+
+/// Decode log bytes that may not be valid UTF-8 (binary output, a stream cut
+/// mid-character), replacing bad sequences rather than failing.
+pub fn decode_lossy(bytes: &[u8]) -> String {
+    String::from_utf8_lossy(bytes).into_owned()
+}
+
+/// Decode a window's head, dropping a character cut off at its end so it
+/// doesn't show up as a replacement character.
+fn decode_head(bytes: &[u8]) -> String {
+    let end = match std::str::from_utf8(bytes) {
+        Ok(_) => bytes.len(),
+        Err(e) if e.error_len().is_none() => e.valid_up_to(),
+        Err(_) => bytes.len(),
+    };
+    decode_lossy(&bytes[..end])
+}
+
+/// Total bytes that may still be downloaded, shared by every log fetched
+/// during one analysis.
+#[derive(Debug, Clone)]
+pub struct LogBudget(Arc<AtomicU64>);
+
+impl LogBudget {
+    pub fn new(bytes: u64) -> Self {
+        Self(Arc::new(AtomicU64::new(bytes)))
+    }
+
+    pub fn remaining(&self) -> u64 {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    fn consume(&self, bytes: u64) {
+        let _ = self
+            .0
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |r| Some(r.saturating_sub(bytes)));
+    }
+}
+
+/// Head, tail, and middle error lines of a log streamed through it.
+#[derive(Debug)]
+pub struct LogWindow {
+    head: Vec<u8>,
+    tail: VecDeque<u8>,
+    head_limit: usize,
+    tail_limit: usize,
+    omitted: u64,
+    /// The omitted line currently being assembled (capped at MAX_LINE_BYTES).
+    line: Vec<u8>,
+    middle_errors: Vec<String>,
+    capped: bool,
+}
+
+impl LogWindow {
+    pub fn new(head_limit: usize, tail_limit: usize) -> Self {
+        Self {
+            head: Vec::new(),
+            tail: VecDeque::new(),
+            head_limit,
+            tail_limit,
+            omitted: 0,
+            line: Vec::new(),
+            middle_errors: Vec::new(),
+            capped: false,
+        }
+    }
+
+    pub fn push(&mut self, mut bytes: &[u8]) {
+        if self.head.len() < self.head_limit {
+            let n = bytes.len().min(self.head_limit - self.head.len());
+            self.head.extend_from_slice(&bytes[..n]);
+            bytes = &bytes[n..];
+        }
+        self.tail.extend(bytes);
+        while self.tail.len() > self.tail_limit {
+            let b = self.tail.pop_front().unwrap_or_default();
+            self.omit(b);
+        }
+    }
+
+    /// Record a byte that fell out of the window, collecting error lines.
+    fn omit(&mut self, b: u8) {
+        self.omitted += 1;
+        if b == b'\n' {
+            let line = decode_lossy(&self.line).trim_end().to_string();
+            if self.middle_errors.len() < MAX_MIDDLE_ERROR_LINES && looks_like_error(&line) {
+                self.middle_errors.push(line);
+            }
+            self.line.clear();
+        } else if self.line.len() < MAX_LINE_BYTES {
+            self.line.push(b);
+        }
+    }
+
+    /// Read `reader` to the end through the window, stopping early once
+    /// `budget` runs out.
+    pub fn read_from(&mut self, mut reader: impl Read, budget: &LogBudget) {
+        let mut buf = vec![0u8; 64 * 1024];
+        loop {
+            // With no budget left, read one byte to tell EOF from truncation
+            let allowed = (budget.remaining() as usize).clamp(1, buf.len());
+            let n = match reader.read(&mut buf[..allowed]) {
+                Ok(0) | Err(_) => break,
+                Ok(n) => n,
+            };
+            if budget.remaining() == 0 {
+                self.capped = true;
+                break;
+            }
+            budget.consume(n as u64);
+            self.push(&buf[..n]);
+        }
+    }
+
+    /// Render the captured log. Nothing is added for logs that fit.
+    pub fn finish(self) -> String {
+        let tail: Vec<u8> = self.tail.into_iter().collect();
+        let mut out;
+        if self.omitted == 0 {
+            // Nothing was dropped: decode head and tail together, since a
+            // character may straddle the two.
+            let mut bytes = self.head;
+            bytes.extend_from_slice(&tail);
+            out = decode_lossy(&bytes);
+        } else {
+            out = decode_head(&self.head);
+            // Start the tail at a line boundary
+            let start = tail.iter().position(|&b| b == b'\n').map_or(0, |i| i + 1);
+            out.push_str(&format!("\n[... {} bytes omitted", self.omitted + start as u64));
+            if self.middle_errors.is_empty() {
+                out.push_str(" ...]\n");
+            } else {
+                out.push_str("; error lines from the omitted part:]\n");
+                for line in &self.middle_errors {
+                    out.push_str(line);
+                    out.push('\n');
+                }
+                out.push_str("[...]\n");
+            }
+            out.push_str(&decode_lossy(&tail[start..]));
+        }
+        if self.capped {
+            out.push_str("\n[log download cap reached; the rest of this log was not fetched]");
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn small_log_passes_through() {
+        let mut window = LogWindow::new(8, 8);
+        window.read_from("short log\n".as_bytes(), &LogBudget::new(1000));
+        assert_eq!(window.finish(), "short log\n");
+    }
+
+    #[test]
+    fn keeps_head_tail_and_middle_errors() {
+        let log = "HEAD\nnoise 1\nerror: boom\nnoise 2\nnoise 3\ntail line\n";
+        let mut window = LogWindow::new(5, 15);
+        window.read_from(log.as_bytes(), &LogBudget::new(1000));
+        let rendered = window.finish();
+        assert_eq!(
+            rendered,
+            "HEAD\n\n[... 36 bytes omitted; error lines from the omitted part:]\n\
+             error: boom\n[...]\ntail line\n"
+        );
+    }
+
+    /// Compiler output the way CI prints it: emoji, box drawing, CJK.
+    const EMOJI_LOG: &str = "🔨 Compiling app v0.1.0\n\
+        error[E0308]: mismatched types ─── 期待された型\n\
+        ❌ 1 test failed 💥\n";
+
+    #[test]
+    fn truncation_never_splits_characters() {
+        for max_len in 0..EMOJI_LOG.len() {
+            let head = truncate_log(EMOJI_LOG, max_len);
+            let kept = head.split("...\n[truncated").next().unwrap();
+            assert!(EMOJI_LOG.starts_with(kept) && kept.len() <= max_len);
+
+            let tail = truncate_log_tail(EMOJI_LOG, max_len);
+            let kept = tail.split_once("truncated]\n").map_or(tail.as_str(), |(_, t)| t);
+            assert!(EMOJI_LOG.ends_with(kept));
+        }
+        assert_eq!(
+            truncate_log_tail(EMOJI_LOG, 30),
+            format!("[... {} bytes truncated]\n❌ 1 test failed 💥\n", EMOJI_LOG.find('❌').unwrap())
+        );
+    }
+
+    #[test]
+    fn window_decodes_split_and_invalid_utf8() {
+        // A character straddling head and tail survives when nothing is omitted
+        let mut window = LogWindow::new(2, 100);
+        window.read_from("🔨 ok".as_bytes(), &LogBudget::new(1000));
+        assert_eq!(window.finish(), "🔨 ok");
+
+        // A head cut mid-character drops the partial character
+        let mut window = LogWindow::new(6, 4);
+        window.read_from("ok 🔨 noise\nend\n".as_bytes(), &LogBudget::new(1000));
+        assert!(window.finish().starts_with("ok \n[..."));
+
+        // Invalid bytes are replaced rather than failing
+        let mut window = LogWindow::new(100, 100);
+        window.read_from(&b"bad \xff\xfe byte"[..], &LogBudget::new(1000));
+        assert_eq!(window.finish(), "bad \u{fffd}\u{fffd} byte");
+    }
+
+    #[test]
+    fn budget_is_shared_and_caps_downloads() {
+        let budget = LogBudget::new(6);
+        let mut first = LogWindow::new(100, 100);
+        first.read_from("abcd".as_bytes(), &budget);
+        assert_eq!(first.finish(), "abcd");
+        assert_eq!(budget.remaining(), 2);
+
+        let mut second = LogWindow::new(100, 100);
+        second.read_from("efghij".as_bytes(), &budget);
+        assert_eq!(
+            second.finish(),
+            "ef\n[log download cap reached; the rest of this log was not fetched]"
+        );
+        assert_eq!(budget.remaining(), 0);
+
+        // A log that ends exactly at the cap isn't reported as cut off
+        let mut exact = LogWindow::new(100, 100);
+        exact.read_from("".as_bytes(), &budget);
+        assert_eq!(exact.finish(), "");
+    }
+}
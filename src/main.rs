@@ -2,49 +2,150 @@
 // Analyzes PR state (CI checks, review threads) and recommends next actions.
 
 mod analysis;
+mod annotations;
+mod attachments;
+mod audit;
+mod author_threads;
+mod auto_clean;
+mod azure_pipelines;
+mod base_failures;
+mod behind;
+mod bisect;
+mod body_history;
+mod cache_hints;
+mod capabilities;
 mod cc_status;
+mod check_run;
+mod checkout;
 mod checks;
 mod circleci;
 mod cli;
+mod comment_text;
 mod commits;
+mod concurrent;
 mod config;
+mod config_edit;
+mod context;
 mod credentials;
+mod debug_bundle;
+mod escalation;
+mod explain;
+mod files;
+mod followups;
 mod gh_actions;
+mod gist;
 mod git;
 mod github;
+mod graphql;
+mod heads;
+mod hours;
 mod hub;
+mod init;
+mod issues;
+mod listen;
+mod log_window;
+mod mcp;
+mod mentions;
+mod merge;
 #[cfg(test)]
 mod graphql_validation;
+mod policy;
+mod post_ready;
 mod pr;
+mod rate_limit;
 mod reply;
+mod reply_batch;
+mod report;
+mod repro;
+mod request_log;
+mod retry;
+mod review_groups;
+mod selftest;
+mod snooze;
+mod stats;
+mod streaks;
+mod tasks;
+mod test_report;
+mod thread_list;
 mod threads;
+mod triage;
 mod wait;
 mod web;
+mod workflows;
 
-use analysis::{analyze_pr, NextAction};
-use checks::{get_checks_summary, CheckStatus, ChecksSummary, RealChecksClient};
+use analysis::{analyze_actions, NextAction};
+use anyhow::Context;
+use bisect::{bisect, BisectResult};
+use body_history::{BodyHistory, Tracked};
+use cache_hints::{
+    clear_pr_caches, detect_cache_corruption, ActionsCacheClient, RealActionsCacheClient,
+};
+use audit::{format_digest, AuditLog, Audited};
+use auto_clean::AutoClean;
+use base_failures::{failing_on_base, BaseFailure, IgnoringBaseFailures};
+use policy::{Guarded, Policy, ThreadLookup};
+use annotations::{
+    fetch_head_sha, format_annotations, get_failed_check_annotations, Annotation,
+    RealAnnotationsClient,
+};
+use check_run::{build_check_run, CheckRunClient, RealCheckRunClient, ANALYSIS_CHECK_NAME};
+use checkout::{pick_remote, sync_checkout, CheckoutGit, RealCheckoutGit, SyncOutcome};
+use checks::{
+    get_checks_summary, group_checks, Check, CheckFilters, CheckGroup, CheckStatus, ChecksClient,
+    ChecksSummary,
+    CollapseRules, RealChecksClient,
+};
 use circleci::{
-    get_job_failures, is_circleci_url, parse_circleci_url, CircleCiFailureInfo, FailedStepLog,
-    RealCircleCiClient,
+    get_job_failures, is_circleci_url, parse_circleci_url, rerun_failed_workflows, wait_for_workflows,
+    CircleCiFailureInfo, CircleCiJobInfo, FailedJob, FailedStepLog, RealCircleCiClient, WorkflowsOutcome,
 };
-use clap::Parser;
+use clap::{CommandFactory, FromArgMatches};
 use cli::{Cli, Command};
+use commits::{CommitsClient, RealCommitsClient};
+use context::{
+    failing_checks, ActionSummary, Briefing, OutputFormat, DiffStat, IssueSummary, PrSummary,
+    ThreadSummary,
+};
 use credentials::{CredentialProvider, Credentials, RealCredentialProvider};
-use git::RealGitClient;
-use github::{
-    resolve_pr_context, MergeableClient, MergeableStatus, PrContext, RealGitHubClient,
-    RealMergeableClient,
+use files::{format_diff_stat, format_top_files, ChangedFile, FilesClient, RealFilesClient};
+use followups::{IssueCreator, RealIssueCreator};
+use gist::{upload_logs, RealGistClient};
+use retry::RetryBudget;
+use log_window::{truncate_log, truncate_log_tail, LogBudget, STDERR_BUDGET, STDOUT_BUDGET};
+use author_threads::{AuthorThreadPolicy, AuthorThreads};
+use mentions::SideConversations;
+use git::{GitClient, RealGitClient};
+use hours::{format_duration, WorkingHours};
+use issues::{acceptance_criteria, IssuesClient, LinkedIssue, RealIssuesClient};
+use github::{resolve_pr_context, MergeableStatus, PrContext, RealGitHubClient};
+use post_ready::{watch, Notifier, RealNotifier, WatchOutcome};
+use pr::{has_status_block, remove_status_block, update_body_with_status, MergeMethod, PrClient, RealPrClient};
+use reply::{
+    expand_template, format_claude_reply, read_suggestion, reply_to_comment, split_reply,
+    PostedReply, RealReplyClient, ReplyClient, ReplyOutcome, ReplyRequest, MAX_COMMENT_CHARS,
 };
-use pr::{has_status_block, remove_status_block, update_body_with_status, PrClient, RealPrClient};
-use reply::{format_claude_message, RealReplyClient, ReplyClient};
 use threads::{
-    RealThreadsClient, ReviewThread, ThreadsClient, CLAUDE_MARKER, PAPERCLIP_EMOJI,
-    PAPERCLIP_SHORTCODE,
+    describe_pending_reviews, find_actionable_threads, PendingReview, ReplyStatus, RealThreadsClient, ReviewThread, ThreadsClient,
+    CLAUDE_MARKER, PAPERCLIP_EMOJI, PAPERCLIP_SHORTCODE,
+};
+use snooze::{Snooze, SnoozeKind, Snoozed, Snoozes};
+use tasks::Task;
+use triage::{sort_by_urgency, tag_threads, CodeOwners, ThreadOrder};
+use wait::{
+    capture_snapshot, sleep_unless_cancelled, sleep_until_next_poll, wait_until_actionable,
+    wait_until_actionable_or_happy, wait_until_quiet, CancelFile, PrSnapshot, QuietPolling,
+    WaitReason, WaitResult,
 };
-use wait::{capture_snapshot, wait_until_actionable, wait_until_actionable_or_happy, WaitResult};
 
 fn main() {
-    let cli = Cli::parse();
+    let cli = Cli::from_arg_matches(&workflows::add_to_help(Cli::command()).get_matches())
+        .unwrap_or_else(|e| e.exit());
+
+    // Record gh request metadata for `pr-loop debug-bundle`
+    let state_root = git::get_repo_root().ok();
+    if let Some(root) = &state_root {
+        request_log::init(root);
+    }
 
     // Commands that don't need PR context, credentials, or GitHub access:
     // handle them before the rest of setup.
@@ -117,6 +218,45 @@ fn main() {
             }
             return;
         }
+        Some(Command::DebugBundle { output }) => {
+            let out = output.clone().unwrap_or_else(|| {
+                let stamp = jiff::Zoned::now().strftime("%Y%m%d-%H%M%S").to_string();
+                std::path::PathBuf::from(format!("pr-loop-debug-{}.tar.gz", stamp))
+            });
+            let files = debug_bundle::collect(state_root.as_deref());
+            if let Err(e) = debug_bundle::write_bundle(&out, &files) {
+                eprintln!("Error: {:#}", e);
+                std::process::exit(1);
+            }
+            println!("Wrote {}", out.display());
+            for (name, _) in &files {
+                println!("  {}", name);
+            }
+            println!("Review it before attaching it to a bug report.");
+            return;
+        }
+        Some(Command::Replay { bundle }) => {
+            run_replay_command(bundle);
+            return;
+        }
+        Some(Command::Init { hooks, force }) => {
+            run_init_command(state_root.as_deref(), *hooks, *force);
+            return;
+        }
+        Some(Command::Mcp) => {
+            run_mcp_command(cli.repo.as_deref(), cli.pr);
+            return;
+        }
+        Some(Command::Schema { analysis }) => {
+            let version = cli.output_version.unwrap_or(context::SCHEMA_VERSION);
+            let schema = if *analysis {
+                report::report_schema(version)
+            } else {
+                context::briefing_schema(version)
+            };
+            print!("{}", schema.expect("version checked by clap"));
+            return;
+        }
         Some(Command::Config { action }) => {
             match action {
                 cli::ConfigAction::Path => {
@@ -138,14 +278,53 @@ fn main() {
                         }
                     }
                 }
+                cli::ConfigAction::Get { key } => {
+                    match config::Layers::read().origins().into_iter().find(|(k, _, _)| k == key) {
+                        Some((_, value, _)) => println!("{}", value),
+                        None => {
+                            eprintln!("{} is not set (the default applies).", key);
+                            std::process::exit(1);
+                        }
+                    }
+                }
+                cli::ConfigAction::Set { key, value } => {
+                    edit_user_config(|text| config_edit::set(text, key, value));
+                }
+                cli::ConfigAction::Unset { key } => {
+                    edit_user_config(|text| config_edit::unset(text, key));
+                }
+                cli::ConfigAction::Validate => run_config_validate(),
+                cli::ConfigAction::Show { origin } => {
+                    let rows = config::Layers::read().origins();
+                    if rows.is_empty() {
+                        println!("# No settings; everything is at its default.");
+                    }
+                    for (key, value, source) in rows {
+                        if *origin {
+                            println!("{} = {}  # {}", key, value, source);
+                        } else {
+                            println!("{} = {}", key, value);
+                        }
+                    }
+                }
             }
             return;
         }
         _ => {}
     }
 
+    // Everything from here on talks to GitHub through gh
+    if let Err(e) = github::ensure_gh_available() {
+        eprintln!("Error: {}", e);
+        std::process::exit(1);
+    }
+
     // Get credentials
-    let provider = RealCredentialProvider;
+    let cred_cfg = config::load();
+    let provider = RealCredentialProvider {
+        circleci_org_token_envs: cred_cfg.circleci_org_token_envs(),
+        secret_store: cred_cfg.secret_store(),
+    };
     let creds = match provider.get_credentials() {
         Ok(c) => c,
         Err(e) => {
@@ -155,10 +334,33 @@ fn main() {
     };
 
     // Warn if CircleCI token is missing (needed for detailed CI logs, deferred)
-    if creds.circleci_token.is_none() {
+    if !creds.has_circleci_token() {
         eprintln!("Note: CIRCLECI_TOKEN not set. CircleCI log details will be unavailable.");
     }
 
+    // Fail fast on features this environment can't support
+    let required = required_features(&cli);
+    let is_doctor = matches!(cli.command, Some(Command::Doctor));
+    if is_doctor || !required.is_empty() {
+        let env = capabilities::Environment::detect(&creds, is_doctor || cli.forward_webhooks);
+        if is_doctor {
+            print_doctor(&env);
+            return;
+        }
+        for feature in required {
+            if let Err(e) = capabilities::require(feature, &env) {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    // Works on a PR of its own, so there's no PR to resolve
+    if matches!(cli.command, Some(Command::Selftest)) {
+        run_selftest_command(cli.repo.as_deref());
+        return;
+    }
+
     // Resolve PR context (from args or auto-detect)
     let gh_client = RealGitHubClient;
     let pr_context = match resolve_pr_context(&gh_client, cli.repo.as_deref(), cli.pr) {
@@ -169,8 +371,69 @@ fn main() {
         }
     };
 
+    // CLI flags override the config file's app exclusions
+    let startup_cfg = config::load();
+    let exclude_apps = if !cli.exclude_apps.is_empty() {
+        cli.exclude_apps.clone()
+    } else {
+        startup_cfg.exclude_apps()
+    };
+    let collapse_rules = startup_cfg.collapse_rules();
+    let working_hours = match WorkingHours::from_config(&startup_cfg.working_hours) {
+        Ok(h) => h,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+    };
+    if cli.pause_outside_hours && working_hours.is_none() {
+        eprintln!("Error: --pause-outside-hours requires [working_hours] in the config file.");
+        std::process::exit(1);
+    }
+    // Outside working hours, waits poll less often (checked on every poll)
+    if let Some(hours) = &working_hours {
+        hours::configure(hours.clone());
+    }
+    let poll_interval = cli.poll_interval;
+    if let Err(e) = group_checks(&[], &collapse_rules) {
+        eprintln!("Error: {}", e);
+        std::process::exit(1);
+    }
+    let check_filters = CheckFilters {
+        include_patterns: cli.include_checks.clone(),
+        // Never wait on (or count) our own published analysis
+        exclude_patterns: cli
+            .exclude_checks
+            .iter()
+            .cloned()
+            .chain([ANALYSIS_CHECK_NAME.to_string()])
+            .collect(),
+        exclude_apps,
+        delayed_checks: startup_cfg.delayed_checks(),
+    };
+
+    // Every mutation below goes through policy-guarded, audited clients
+    let mutations = MutationClients {
+        audit_log: open_audit_log(&pr_context, startup_cfg.audit_enabled()),
+        body_history: BodyHistory::new(
+            state_root.as_deref().unwrap_or(std::path::Path::new(".")),
+            &pr_label(&pr_context),
+            cli.verbose,
+        ),
+        policy: startup_cfg.policy(),
+        pr_context: pr_context.clone(),
+    };
+
+    // Fail before waiting, not after: --then-ready has no way to pass --confirm
+    if cli.then_ready && mutations.policy.require_confirm_for_ready {
+        eprintln!("Error: Policy requires confirmation to mark the PR ready, so --then-ready can't be used.");
+        eprintln!("Wait with --wait-until-actionable-or-happy, then run `pr-loop ready --confirm` once a human has approved.");
+        std::process::exit(1);
+    }
+
     // Initialize PR client for status operations
-    let pr_client = RealPrClient;
+    let ready_confirmed = matches!(cli.command, Some(Command::Ready { confirm: true, .. }));
+    let pr_client = mutations.pr_client(ready_confirmed);
 
     // If --maintain-status is set, check draft mode first
     if cli.maintain_status {
@@ -200,91 +463,235 @@ fn main() {
     }
 
     match cli.command {
-        Some(Command::Reply { in_reply_to, message }) => {
-            let reply_client = RealReplyClient;
-            let threads_client = RealThreadsClient;
-
-            // Fetch the thread containing this comment
-            let thread_data = match threads_client.fetch_thread_by_comment_id(&in_reply_to) {
-                Ok(t) => t,
-                Err(e) => {
-                    eprintln!("Error: Could not fetch thread for comment {}: {}", in_reply_to, e);
-                    std::process::exit(1);
-                }
+        Some(Command::Reply { in_reply_to, message, template, vars, status, quote, if_resolved, suggestion }) => {
+            let message = match (message, template) {
+                (Some(message), _) => message,
+                (None, Some(name)) => match startup_cfg.reply_template(&name) {
+                    Some(template) => expand_template(template, &vars).unwrap_or_else(|e| {
+                        eprintln!("Error: Reply template '{}': {}", name, e);
+                        std::process::exit(1);
+                    }),
+                    None => {
+                        eprintln!(
+                            "Error: No reply template named '{}'. Configured: {}",
+                            name,
+                            match startup_cfg.reply_template_names() {
+                                names if names.is_empty() => "none ([reply_templates] in the config file)".to_string(),
+                                names => names.join(", "),
+                            }
+                        );
+                        std::process::exit(1);
+                    }
+                },
+                (None, None) => "Suggested change:".to_string(),
             };
-
-            let thread_id = thread_data.id.clone();
-
-            // Check for newer human comments after the one we're replying to
-            let newer_comments = match thread_data.human_comments_after(&in_reply_to) {
-                Some(comments) => comments,
-                None => {
-                    eprintln!(
-                        "Error: Comment {} not found in thread {}",
-                        in_reply_to, thread_id
-                    );
+            let suggestion = suggestion.map(|arg| {
+                read_suggestion(&arg).unwrap_or_else(|e| {
+                    eprintln!("Error: {:#}", e);
                     std::process::exit(1);
-                }
-            };
-
-            // Modify message if there are newer human comments
-            let final_message = if !newer_comments.is_empty() {
-                format!(
-                    "{}\n\n(Looks like you had something else to say here while I was working. I'll look at that now.)",
-                    message
-                )
-            } else {
-                message.clone()
+                })
+            });
+            let request = ReplyRequest {
+                in_reply_to,
+                message,
+                status,
+                quote,
+                if_resolved,
+                suggestion,
+                answered_elsewhere: vec![],
             };
-
-            let formatted_message = format_claude_message(&final_message);
-
-            println!(
-                "Replying to thread {} on {}/{}#{}",
-                thread_id, pr_context.owner, pr_context.repo, pr_context.pr_number
-            );
-
-            match reply_client.post_reply(&thread_id, &formatted_message) {
-                Ok(result) => {
-                    println!("✓ Reply posted (comment ID: {})", result.comment_id);
-
+            match reply_to_comment(&RealThreadsClient, &mutations.reply_client(), &request) {
+                Ok(ReplyOutcome::Posted(posted)) => {
+                    print_posted_reply(&pr_context, &posted);
                     // If there were newer comments, print them for the invoker
-                    if !newer_comments.is_empty() {
-                        print_newer_comments(&newer_comments, &thread_id);
+                    if !posted.newer_comments.is_empty() {
+                        print_newer_comments(&posted.newer_comments, &posted.thread_id);
                     }
 
                     // Poke a running `pr-loop web` instance so its UI
                     // refreshes immediately. Best-effort, ignore failures.
                     web::poke_running_server(&pr_context);
                 }
+                Ok(ReplyOutcome::SkippedResolved { thread_id }) => {
+                    println!(
+                        "Thread {} was resolved in the meantime; not replying (use --if-resolved post to reply anyway).",
+                        thread_id
+                    );
+                }
                 Err(e) => {
-                    eprintln!("Error: Failed to post reply: {}", e);
+                    eprintln!("Error: {:#}", e);
                     std::process::exit(1);
                 }
             }
         }
 
-        Some(Command::Ready { preserve_claude_threads, reviewer }) => {
-            run_ready_command(
-                &pr_client,
+        Some(Command::Ready { preserve_claude_threads, reviewer, confirm, force, post_stats }) => {
+            // Fail before any cleanup; the PR client enforces this too.
+            if mutations.policy.require_confirm_for_ready && !confirm {
+                eprintln!("Error: Policy requires confirmation to mark the PR ready.");
+                eprintln!("Re-run with `pr-loop ready --confirm` once a human has approved.");
+                std::process::exit(1);
+            }
+            let options = ReadyOptions { preserve_claude_threads, reviewers: reviewer, force, post_stats };
+            run_ready_command(&pr_client, &pr_context, &check_filters, &options, &mutations);
+        }
+
+        Some(Command::Merge { squash: _, rebase, merge, auto, no_wait }) => {
+            let method = if rebase {
+                MergeMethod::Rebase
+            } else if merge {
+                MergeMethod::Merge
+            } else {
+                MergeMethod::Squash
+            };
+            let options = MergeOptions {
+                method,
+                auto,
+                wait: !no_wait,
+                timeout: std::time::Duration::from_secs(cli.timeout),
+                poll_interval: std::time::Duration::from_secs(poll_interval),
+            };
+            let cancel_file = cli.cancel_file.clone().map(CancelFile::new);
+            run_merge_command(&pr_client, &pr_context, &check_filters, &options, cancel_file.as_ref());
+        }
+
+        Some(Command::Comment { message }) => {
+            run_comment_command(&pr_client, &pr_context, &message);
+        }
+
+        Some(Command::ReplyBatch { file }) => {
+            run_reply_batch_command(&pr_context, &mutations, file.as_deref());
+        }
+
+        Some(Command::Resolve { threads, comments }) => {
+            run_resolve_command(&pr_context, &mutations, &threads, &comments);
+        }
+
+        Some(Command::CleanThreads) => {
+            run_clean_threads_command(&pr_context, &mutations);
+        }
+
+        Some(Command::PostReadyWatch { duration, webhook, desktop }) => {
+            let notifier = RealNotifier {
+                webhook: webhook.or_else(|| startup_cfg.notify_webhook()),
+                desktop: desktop || startup_cfg.notify_desktop(),
+            };
+            let _listener = start_listener(&cli.listen, cli.forward_webhooks, &cli.webhook_secret, &pr_context);
+            let cancel_file = cli.cancel_file.clone().map(CancelFile::new);
+            run_post_ready_watch_command(
                 &pr_context,
-                &cli.include_checks,
-                &cli.exclude_checks,
-                preserve_claude_threads,
-                &reviewer,
+                &check_filters,
+                &duration,
+                poll_interval,
+                &notifier,
+                cancel_file.as_ref(),
             );
         }
 
-        Some(Command::CleanThreads) => {
-            run_clean_threads_command(&pr_context);
+        Some(Command::FileFollowups { dry_run }) => {
+            run_file_followups_command(&pr_context, &mutations, &RealIssueCreator, dry_run);
+        }
+
+        Some(Command::Snooze { threads, checks, duration, reason, clear, list }) => {
+            let targets: Vec<(SnoozeKind, String)> = threads
+                .into_iter()
+                .map(|t| (SnoozeKind::Thread, t))
+                .chain(checks.into_iter().map(|c| (SnoozeKind::Check, c)))
+                .collect();
+            run_snooze_command(&state_root, &pr_context, targets, duration, reason, clear, list);
+        }
+
+        Some(Command::Audit { post }) => {
+            run_audit_command(&pr_client, &pr_context, &mutations.audit_log, post);
         }
 
         Some(Command::Checks) => {
             run_checks_command(
                 &creds,
                 &pr_context,
-                &cli.include_checks,
-                &cli.exclude_checks,
+                &check_filters,
+                &collapse_rules,
+            );
+        }
+
+        Some(Command::CiBisect { check, max_commits }) => {
+            run_ci_bisect_command(&pr_context, &check, max_commits);
+        }
+
+        Some(Command::Repro { check, docker }) => {
+            run_repro_command(&creds, &pr_context, &check_filters, &check, docker, state_root.as_deref());
+        }
+
+        Some(Command::Rerun {
+            failed_only,
+            wait,
+            bust_caches,
+        }) => {
+            let _listener = if wait {
+                start_listener(&cli.listen, cli.forward_webhooks, &cli.webhook_secret, &pr_context)
+            } else {
+                None
+            };
+            let cancel_file = cli.cancel_file.clone().map(CancelFile::new);
+            let wait = wait.then(|| RerunWait {
+                timeout: std::time::Duration::from_secs(cli.timeout),
+                poll_interval: std::time::Duration::from_secs(poll_interval),
+                cancel: cancel_file.as_ref(),
+            });
+            run_rerun_command(
+                &creds,
+                &pr_context,
+                &check_filters,
+                &mutations.audit_log,
+                failed_only,
+                wait,
+                bust_caches,
+            );
+        }
+
+        Some(Command::Checkout) => {
+            run_checkout_command(&pr_context);
+        }
+
+        Some(Command::Context { format }) => {
+            run_context_command(
+                &creds,
+                &pr_client,
+                &pr_context,
+                &check_filters,
+                cli.thread_order,
+                format,
+                cli.output_version.unwrap_or(context::SCHEMA_VERSION),
+            );
+        }
+
+        Some(Command::Tasks) => {
+            run_tasks_command(&pr_context, cli.thread_order);
+        }
+
+        Some(Command::Threads { json }) => {
+            run_threads_command(&pr_context, &state_root, &startup_cfg, json);
+        }
+
+        Some(Command::Watch) => {
+            let options = WatchOptions {
+                poll_interval,
+                tag_threads: cli.tag_threads,
+                thread_order: cli.thread_order,
+                toolchain: state_root.as_deref().and_then(repro::Toolchain::detect),
+                mentioned_others_actionable: startup_cfg.mentioned_others_actionable(),
+                author_thread_policy: startup_cfg.author_thread_policy(),
+                rebase_threshold: startup_cfg.rebase_threshold(),
+            };
+            let _listener = start_listener(&cli.listen, cli.forward_webhooks, &cli.webhook_secret, &pr_context);
+            let cancel_file = cli.cancel_file.clone().map(CancelFile::new);
+            run_watch_command(
+                &creds,
+                &pr_context,
+                &check_filters,
+                &collapse_rules,
+                &options,
+                cancel_file.as_ref(),
             );
         }
 
@@ -295,47 +702,92 @@ fn main() {
             } else {
                 cfg.web_binds()
             };
-            if let Err(e) = web::run(&pr_context, &resolved_binds, port, open) {
+            if let Err(e) = web::run(&pr_context, mutations.policy.clone(), &resolved_binds, port, open) {
                 eprintln!("Error: {}", e);
                 std::process::exit(1);
             }
         }
 
-        Some(Command::Hub { .. }) | Some(Command::Config { .. }) | Some(Command::CcStatus) => {
+        Some(Command::Hub { .. })
+        | Some(Command::Config { .. })
+        | Some(Command::CcStatus)
+        | Some(Command::DebugBundle { .. })
+        | Some(Command::Replay { .. })
+        | Some(Command::Schema { .. })
+        | Some(Command::Doctor)
+        | Some(Command::Init { .. })
+        | Some(Command::Selftest)
+        | Some(Command::Mcp) => {
             // Handled above before setup; unreachable.
             unreachable!();
         }
 
         None => {
-            let checks_client = RealChecksClient;
-            let threads_client = RealThreadsClient;
+            let snoozes = load_snoozes(&state_root);
+            let label = pr_label(&pr_context);
+            print_snoozes(&snoozes, &label);
+            let snoozed_checks = Snoozed::new(RealChecksClient, snoozes.clone(), label.clone());
+            let checks_client: Box<dyn ChecksClient> = if cli.ignore_base_failures {
+                Box::new(IgnoringBaseFailures::new(snoozed_checks))
+            } else {
+                Box::new(snoozed_checks)
+            };
+            let mentioned_others_actionable = startup_cfg.mentioned_others_actionable();
+            let threads_client = AuthorThreads::new(
+                SideConversations::new(
+                    Snoozed::new(
+                        AutoClean::new(RealThreadsClient, mutations.reply_client(), cli.auto_clean),
+                        snoozes,
+                        label,
+                    ),
+                    own_logins_lookup(&pr_context),
+                    mentioned_others_actionable,
+                ),
+                startup_cfg.author_thread_policy(),
+                pr_author_lookup(&pr_context),
+            );
             let git_client = RealGitClient;
-            let mergeable_client = RealMergeableClient;
+
+            // The snapshot that ended a wait, if any: the analysis below reuses
+            // it so it reflects exactly that state, without fetching again.
+            let mut waited: Option<Box<PrSnapshot>> = None;
+            let _listener = start_listener(&cli.listen, cli.forward_webhooks, &cli.webhook_secret, &pr_context);
+            let cancel_file = cli.cancel_file.clone().map(CancelFile::new);
+            let cancel = cancel_file.as_ref();
+            let exit_cancelled = |cancel: &CancelFile| -> ! {
+                exit_wait_cancelled(cancel, cli.wait_json, cli.maintain_status, &pr_client, &pr_context)
+            };
 
             // If --wait-until-actionable, poll until something needs attention
             if cli.wait_until_actionable {
                 match wait_until_actionable(
-                    &checks_client,
+                    checks_client.as_ref(),
                     &threads_client,
                     &pr_context.owner,
                     &pr_context.repo,
                     pr_context.pr_number,
-                    &cli.include_checks,
-                    &cli.exclude_checks,
+                    &check_filters,
                     cli.timeout,
-                    cli.poll_interval,
+                    poll_interval,
+                    cli.comment_debounce,
+                    cancel,
                 ) {
-                    Ok(WaitResult::Actionable) => {
-                        eprintln!("PR is now actionable.");
+                    Ok(WaitResult::Actionable { reason, snapshot }) => {
+                        eprintln!("PR is now actionable:");
+                        reason.print();
+                        print_wait_json(cli.wait_json, "actionable", Some(&reason));
+                        waited = Some(snapshot);
                     }
-                    Ok(WaitResult::Happy) => {
+                    Ok(WaitResult::Happy) | Ok(WaitResult::Quiet(_)) => {
                         // Should not happen with wait_until_actionable
                         eprintln!("PR is happy.");
                     }
                     Ok(WaitResult::Timeout) => {
                         eprintln!("Timeout reached without PR becoming actionable.");
-                        std::process::exit(2);
+                        print_wait_json(cli.wait_json, "timeout", None);
+                        std::process::exit(wait::TIMEOUT_EXIT_CODE);
                     }
+                    Ok(WaitResult::Cancelled) => exit_cancelled(cancel.expect("only cancelled with a cancel file")),
                     Err(e) => {
                         eprintln!("Error while waiting: {}", e);
                         std::process::exit(1);
@@ -345,30 +797,53 @@ fn main() {
 
             // If --wait-until-actionable-or-happy, poll until actionable or happy
             if cli.wait_until_actionable_or_happy {
+                // Without a push since CI last reported on this head, it has
+                // nothing new to pick up
+                let head = git_client.get_head_hash().ok();
+                let pushed = pushed_since_checks_seen(&state_root, &pr_context, head.as_deref());
                 match wait_until_actionable_or_happy(
-                    &checks_client,
+                    checks_client.as_ref(),
                     &threads_client,
                     &git_client,
                     &pr_context.owner,
                     &pr_context.repo,
                     pr_context.pr_number,
-                    &cli.include_checks,
-                    &cli.exclude_checks,
+                    &check_filters,
                     cli.timeout,
-                    cli.poll_interval,
-                    cli.min_wait_after_push,
+                    poll_interval,
+                    if pushed { cli.min_wait_after_push } else { 0 },
+                    cli.comment_debounce,
+                    cli.strict_happy || cli.then_ready,
+                    cancel,
                 ) {
-                    Ok(WaitResult::Actionable) => {
-                        eprintln!("PR is now actionable.");
+                    Ok(WaitResult::Actionable { reason, snapshot }) => {
+                        eprintln!("PR is now actionable:");
+                        reason.print();
+                        print_wait_json(cli.wait_json, "actionable", Some(&reason));
+                        if !snapshot.checks.checks.is_empty() {
+                            record_checks_seen(&state_root, &pr_context, head.as_deref());
+                        }
+                        waited = Some(snapshot);
                     }
                     Ok(WaitResult::Happy) => {
+                        record_checks_seen(&state_root, &pr_context, head.as_deref());
                         eprintln!("PR is happy (CI passing, no comments).");
+                        print_wait_json(cli.wait_json, "happy", None);
+                        if cli.then_ready {
+                            println!();
+                            run_ready_command(&pr_client, &pr_context, &check_filters, &ReadyOptions::default(), &mutations);
+                        }
                         std::process::exit(0);
                     }
+                    Ok(WaitResult::Quiet(_)) => {
+                        // Should not happen with wait_until_actionable_or_happy
+                    }
                     Ok(WaitResult::Timeout) => {
                         eprintln!("Timeout reached.");
-                        std::process::exit(2);
+                        print_wait_json(cli.wait_json, "timeout", None);
+                        std::process::exit(wait::TIMEOUT_EXIT_CODE);
                     }
+                    Ok(WaitResult::Cancelled) => exit_cancelled(cancel.expect("only cancelled with a cancel file")),
                     Err(e) => {
                         eprintln!("Error while waiting: {}", e);
                         std::process::exit(1);
@@ -376,539 +851,3177 @@ fn main() {
                 }
             }
 
-            // Fetch checks
-            let checks_summary = match get_checks_summary(
-                &checks_client,
-                &pr_context.owner,
-                &pr_context.repo,
-                pr_context.pr_number,
-                &cli.include_checks,
-                &cli.exclude_checks,
-            ) {
-                Ok(summary) => summary,
-                Err(e) => {
-                    eprintln!("Error: Failed to fetch checks: {}", e);
-                    // Continue with empty checks
-                    ChecksSummary { checks: vec![] }
+            // If --wait-until-quiet, let an in-progress review settle first
+            if let Some(quiet_secs) = cli.wait_until_quiet {
+                match wait_until_quiet(
+                    checks_client.as_ref(),
+                    &threads_client,
+                    &pr_context.owner,
+                    &pr_context.repo,
+                    pr_context.pr_number,
+                    &check_filters,
+                    QuietPolling {
+                        quiet_secs,
+                        timeout_secs: cli.timeout,
+                        poll_interval_secs: poll_interval,
+                        cancel,
+                    },
+                ) {
+                    Ok(WaitResult::Timeout) => {
+                        eprintln!("Timeout reached before PR activity settled.");
+                        print_wait_json(cli.wait_json, "timeout", None);
+                        std::process::exit(wait::TIMEOUT_EXIT_CODE);
+                    }
+                    Ok(WaitResult::Quiet(snapshot)) => {
+                        eprintln!("PR has been quiet for {}s.", quiet_secs);
+                        waited = Some(snapshot);
+                    }
+                    Ok(WaitResult::Cancelled) => exit_cancelled(cancel.expect("only cancelled with a cancel file")),
+                    Ok(_) => {
+                        eprintln!("PR has been quiet for {}s.", quiet_secs);
+                    }
+                    Err(e) => {
+                        eprintln!("Error while waiting: {}", e);
+                        std::process::exit(1);
+                    }
                 }
-            };
+            }
 
-            // Fetch review threads
-            let threads = match threads_client.fetch_threads(
-                &pr_context.owner,
-                &pr_context.repo,
-                pr_context.pr_number,
-            ) {
-                Ok(t) => t,
-                Err(e) => {
-                    eprintln!("Error: Failed to fetch review threads: {}", e);
-                    vec![]
+            // If --pause-outside-hours, idle until a human is around
+            if cli.pause_outside_hours
+                && let Some(hours) = &working_hours
+                && !hours.is_working_time()
+            {
+                if !pause_until_working_hours(hours, cli.timeout, cancel) {
+                    if let Some(cancel) = cancel.filter(|c| c.is_cancelled()) {
+                        exit_cancelled(cancel);
+                    }
+                    eprintln!("Timeout reached while paused outside working hours.");
+                    std::process::exit(wait::TIMEOUT_EXIT_CODE);
+                }
+                // The waited-on state may be hours old by now
+                waited = None;
+            }
+
+            let rebase_threshold = startup_cfg.rebase_threshold();
+            let (checks_summary, threads, pending_reviews, mergeable_status, rebase) = match waited {
+                Some(snapshot) => {
+                    let snapshot = *snapshot;
+                    let rebase = check_rebase(&pr_context, rebase_threshold);
+                    (snapshot.checks, snapshot.threads, snapshot.pending_reviews, snapshot.mergeable_status, rebase)
+                }
+                None => {
+                    // Checks, threads, pending reviews, mergeability, and how
+                    // far behind the base the branch is don't depend on each other
+                    let (checks_result, threads_result, pending_result, mergeable_result, rebase) = std::thread::scope(|s| {
+                        let threads = s.spawn(|| {
+                            threads_client.fetch_threads(
+                                &pr_context.owner,
+                                &pr_context.repo,
+                                pr_context.pr_number,
+                            )
+                        });
+                        let pending = s.spawn(|| {
+                            threads_client.fetch_pending_reviews(
+                                &pr_context.owner,
+                                &pr_context.repo,
+                                pr_context.pr_number,
+                            )
+                        });
+                        let mergeable = s.spawn(|| {
+                            checks_client.fetch_mergeable_status(
+                                &pr_context.owner,
+                                &pr_context.repo,
+                                pr_context.pr_number,
+                            )
+                        });
+                        let rebase = s.spawn(|| check_rebase(&pr_context, rebase_threshold));
+                        let checks = get_checks_summary(
+                            checks_client.as_ref(),
+                            &pr_context.owner,
+                            &pr_context.repo,
+                            pr_context.pr_number,
+                            &check_filters,
+                        );
+                        (
+                            checks,
+                            concurrent::join(threads),
+                            concurrent::join(pending),
+                            concurrent::join(mergeable),
+                            concurrent::join(rebase),
+                        )
+                    });
+
+                    let checks_summary = match checks_result {
+                        Ok(summary) => summary,
+                        Err(e) => {
+                            eprintln!("Error: Failed to fetch checks: {}", e);
+                            // Continue with empty checks
+                            ChecksSummary { checks: vec![] }
+                        }
+                    };
+
+                    let threads = match threads_result {
+                        Ok(t) => t,
+                        Err(e) => {
+                            eprintln!("Error: Failed to fetch review threads: {}", e);
+                            vec![]
+                        }
+                    };
+
+                    // Reviews still being written (only visible for the viewer's own)
+                    let pending_reviews = pending_result.unwrap_or_else(|e| {
+                        eprintln!("Warning: Failed to fetch pending reviews: {}", e);
+                        vec![]
+                    });
+                    let mergeable_status = mergeable_result.unwrap_or_else(|e| {
+                        eprintln!("Warning: Failed to fetch merge conflict status: {}", e);
+                        MergeableStatus::Unknown
+                    });
+                    (checks_summary, threads, pending_reviews, mergeable_status, rebase)
                 }
             };
 
             // Analyze and output recommendation
-            let action = analyze_pr(&checks_summary, threads);
+            let analyzed_threads = state_root.is_some().then(|| threads.clone());
+            let mut actions = analyze_actions(&checks_summary, threads, &mergeable_status, rebase.as_ref());
+            let mut action = actions.remove(0);
+            triage_threads(&mut action, &pr_context, cli.tag_threads, cli.thread_order);
 
-            // If there are CI failures, fetch logs. fetch_ci_failure_info
+            // The rest of what the recommendation shows, fetched at once. If
+            // there are CI failures, fetch logs: fetch_ci_failure_info
             // handles the no-CircleCI-token case internally; GitHub Actions
             // logs don't need extra credentials.
-            let circleci_info = fetch_ci_failure_info(&creds, &checks_summary);
-
-            let mergeable_status = match mergeable_client.fetch_mergeable_status(
-                &pr_context.owner,
-                &pr_context.repo,
-                pr_context.pr_number,
-            ) {
-                Ok(s) => s,
-                Err(e) => {
-                    eprintln!("Warning: Failed to fetch merge conflict status: {}", e);
-                    MergeableStatus::Unknown
+            let (mut circleci_info, annotations, linked_issues, changed_files, base_checks) =
+                std::thread::scope(|s| {
+                    let ci_info = s.spawn(|| fetch_ci_failure_info(&creds, &checks_summary));
+                    let annotations = s.spawn(|| fetch_check_annotations(&pr_context, &checks_summary));
+                    let linked_issues = s.spawn(|| fetch_linked_issues(&pr_context));
+                    let base_checks = s.spawn(|| {
+                        fetch_base_checks(checks_client.as_ref(), &pr_context, &checks_summary)
+                    });
+                    let changed_files = fetch_changed_files(&pr_context);
+                    (
+                        concurrent::join(ci_info),
+                        concurrent::join(annotations),
+                        concurrent::join(linked_issues),
+                        changed_files,
+                        concurrent::join(base_checks),
+                    )
+                });
+            if cli.upload_logs {
+                upload_truncated_logs(&pr_context, &mut circleci_info.step_logs);
+            }
+
+            let failing = context::failing_checks(&checks_summary, &annotations, &circleci_info);
+            let conditions = PrConditions {
+                mergeable_status,
+                pending_reviews,
+                linked_issues,
+                changed_files,
+                toolchain: state_root.as_deref().and_then(repro::Toolchain::detect),
+                base_failures: base_checks
+                    .map(|base| failing_on_base(&checks_summary.checks, &base))
+                    .unwrap_or_default(),
+                failure_streaks: record_failure_streaks(
+                    &state_root,
+                    &pr_context,
+                    &checks_summary,
+                    &failing,
+                ),
+                attachments: match &cli.download_attachments {
+                    Some(dir) => download_attachments(&action, dir),
+                    None => std::collections::HashMap::new(),
+                },
+                side_conversations: if mentioned_others_actionable {
+                    find_side_conversations(&action, &pr_context)
+                } else {
+                    std::collections::HashMap::new()
+                },
+                author_notes: threads_client.notes(),
+                also: actions,
+            };
+
+            // Inside a checkout (where streaks are tracked), a loop that isn't
+            // getting anywhere stops and asks for help instead
+            let stuck = match (startup_cfg.escalate_after(), &state_root) {
+                (Some(after), Some(_)) => {
+                    let threads: Vec<&ReviewThread> = match &action {
+                        NextAction::RespondToComments { threads, .. } => {
+                            threads.iter().map(|t| &t.thread).collect()
+                        }
+                        _ => vec![],
+                    };
+                    escalation::find_stuck(after, &conditions.failure_streaks, &failing, &threads)
                 }
+                _ => vec![],
             };
 
-            print_recommendation(
-                &pr_context,
-                &checks_summary,
-                &action,
-                &circleci_info,
-                &mergeable_status,
-            );
-        }
-    }
-}
+            match cli.format {
+                OutputFormat::Markdown if !stuck.is_empty() => print_escalation(&pr_context, &stuck),
+                OutputFormat::Markdown => print!("{}", format_recommendation(
+                    &pr_context,
+                    &checks_summary,
+                    &action,
+                    &circleci_info,
+                    &annotations,
+                    &conditions,
+                    &collapse_rules,
+                )),
+                OutputFormat::Json => {
+                    let mut report = report::AnalysisReport::new(
+                        &pr_context,
+                        &checks_summary,
+                        &action,
+                        &circleci_info,
+                        &annotations,
+                        &conditions.mergeable_status,
+                    );
+                    context::add_suggested_commands(
+                        &mut report.failing_checks,
+                        &circleci_info,
+                        &startup_cfg,
+                        conditions.toolchain,
+                    );
+                    report.failure_streaks = conditions.failure_streaks.clone();
+                    report.failing_on_base = conditions.base_failures.clone();
+                    report.escalation = stuck.clone();
+                    report.actions.extend(conditions.also.iter().map(ActionSummary::new));
+                    println!(
+                        "{}",
+                        report.to_json(cli.output_version.unwrap_or(context::SCHEMA_VERSION))
+                    );
+                }
+            }
 
-/// Fetch CI failure info (logs + test failures) for failed checks. Handles
-/// both CircleCI (via their API; requires CIRCLECI_TOKEN) and GitHub Actions
-/// (via `gh api`, no extra credentials needed).
-fn fetch_ci_failure_info(creds: &Credentials, checks: &ChecksSummary) -> CircleCiFailureInfo {
-    let circleci_client = creds
-        .circleci_token
-        .as_ref()
-        .map(|t| RealCircleCiClient::new(t.clone()));
-    let gh_actions_client = gh_actions::RealGhActionsClient;
-    let mut combined = CircleCiFailureInfo::default();
+            if cli.explain && matches!(cli.format, OutputFormat::Markdown) {
+                let rules = ExplainRules {
+                    check_filters: &check_filters,
+                    ignore_base_failures: cli.ignore_base_failures,
+                    mentions_actionable: mentioned_others_actionable,
+                    author_threads: startup_cfg.author_thread_policy(),
+                };
+                print_explanation(&pr_context, &state_root, &rules, &action);
+            }
 
-    for check in checks.failed() {
-        let Some(url) = &check.url else { continue };
-        if is_circleci_url(url) {
-            let Some(c) = &circleci_client else { continue };
-            if let Some(job_info) = parse_circleci_url(url) {
-                match get_job_failures(c, &job_info) {
-                    Ok(info) => {
-                        combined.step_logs.extend(info.step_logs);
-                        combined.test_failures.extend(info.test_failures);
-                    }
-                    Err(e) => eprintln!(
-                        "Warning: Failed to fetch CircleCI logs for {}: {}",
-                        check.name, e
-                    ),
+            // Keep the analysis inputs for `pr-loop debug-bundle`
+            if let (Some(root), Some(threads)) = (&state_root, &analyzed_threads) {
+                let snapshot = debug_bundle::AnalysisSnapshot {
+                    mergeable_status: conditions.mergeable_status.clone(),
+                    pending_reviews: conditions.pending_reviews.clone(),
+                    changed_files: conditions.changed_files.clone(),
+                    rebase: rebase.clone(),
+                    side_conversations: conditions.side_conversations.clone(),
+                    ..debug_bundle::AnalysisSnapshot::new(&pr_context, &checks_summary, threads, &action)
+                };
+                if let Err(e) = debug_bundle::save_snapshot(root, &snapshot) {
+                    eprintln!("Warning: Failed to save analysis snapshot: {}", e);
                 }
             }
-        } else if gh_actions::is_gh_actions_url(url) {
-            if let Some(job_info) = gh_actions::parse_gh_actions_url(url) {
-                match gh_actions::get_failed_step_logs(&gh_actions_client, &job_info) {
-                    Ok(logs) => combined.step_logs.extend(logs),
-                    Err(e) => eprintln!(
-                        "Warning: Failed to fetch GitHub Actions logs for {}: {}",
-                        check.name, e
-                    ),
-                }
+
+            if cli.publish_check {
+                let summary = format_recommendation(
+                    &pr_context,
+                    &checks_summary,
+                    &action,
+                    &circleci_info,
+                    &annotations,
+                    &conditions,
+                    &collapse_rules,
+                );
+                publish_check_run(&pr_context, &action, summary, &mutations.audit_log);
+            }
+
+            if let Some(root) = &state_root
+                && !stuck.is_empty()
+            {
+                post_escalation(root, &pr_client, &pr_context, &stuck);
+                std::process::exit(escalation::EXIT_CODE);
             }
         }
     }
-
-    combined
 }
 
-fn print_recommendation(
-    pr_context: &github::PrContext,
-    checks: &ChecksSummary,
-    action: &NextAction,
-    circleci_info: &CircleCiFailureInfo,
-    mergeable_status: &MergeableStatus,
-) {
+/// Print why the loop is stopping, in place of the recommendation.
+fn print_escalation(pr_context: &github::PrContext, stuck: &[escalation::StuckItem]) {
     println!(
         "# PR Analysis: {}/{}#{}",
         pr_context.owner, pr_context.repo, pr_context.pr_number
     );
     println!();
+    println!("## STOP: Escalated to a human");
+    println!();
+    println!("The loop isn't making progress:");
+    for item in stuck {
+        println!("  - {}", item.describe());
+    }
+    println!();
+    println!("A summary asking for help is posted to the PR. Don't push more fixes");
+    println!("for these; wait for a human to respond.");
+}
 
-    if *mergeable_status == MergeableStatus::Conflicting {
-        println!("⚠ **MERGE CONFLICTS**: This PR has merge conflicts that must be resolved.");
-        println!();
+/// Post the escalation summary to the PR, unless one covering the same state
+/// was already posted from this checkout.
+fn post_escalation(
+    root: &std::path::Path,
+    pr_client: &impl PrClient,
+    pr_context: &github::PrContext,
+    stuck: &[escalation::StuckItem],
+) {
+    let label = pr_label(pr_context);
+    let mut posted = escalation::Escalations::load(root).unwrap_or_else(|e| {
+        eprintln!("Warning: Ignoring recorded escalations: {:#}", e);
+        escalation::Escalations::default()
+    });
+    if posted.already_posted(&label, stuck) {
+        eprintln!("Escalation already posted to the PR.");
+        return;
+    }
+    match pr_client.add_comment(
+        &pr_context.owner,
+        &pr_context.repo,
+        pr_context.pr_number,
+        &escalation::format_summary(stuck),
+    ) {
+        Ok(()) => {
+            eprintln!("✓ Posted escalation to the PR");
+            posted.mark_posted(&label, stuck);
+            if let Err(e) = posted.save() {
+                eprintln!("Warning: Failed to record escalation: {:#}", e);
+            }
+        }
+        Err(e) => eprintln!("Error: Failed to post escalation: {}", e),
     }
+}
 
-    match action {
-        NextAction::RespondToComments {
-            threads,
-            also_has_ci_failures,
-            ci_pending,
-        } => {
-            println!("## ACTION REQUIRED: Respond to review comments");
-            println!();
-            println!(
-                "There {} {} unaddressed review thread{}:",
-                if threads.len() == 1 { "is" } else { "are" },
+/// Run the `replay` subcommand: rerun the analysis on a captured snapshot and
+/// print the recommendation as the analysis would have.
+fn run_replay_command(bundle: &std::path::Path) {
+    let snapshot = match debug_bundle::read_snapshot(bundle) {
+        Ok(s) => s,
+        Err(e) => {
+            eprintln!("Error: {:#}", e);
+            std::process::exit(1);
+        }
+    };
+    let pr_context = snapshot.pr_context();
+    eprintln!(
+        "Replaying the analysis of {}/{}#{} captured at {}.",
+        pr_context.owner, pr_context.repo, pr_context.pr_number, snapshot.at
+    );
+    let checks = snapshot.checks_summary();
+    let mut actions = analyze_actions(
+        &checks,
+        snapshot.threads(),
+        &snapshot.mergeable_status,
+        snapshot.rebase.as_ref(),
+    );
+    let action = actions.remove(0);
+    let conditions = PrConditions {
+        mergeable_status: snapshot.mergeable_status.clone(),
+        pending_reviews: snapshot.pending_reviews.clone(),
+        linked_issues: vec![],
+        changed_files: snapshot.changed_files.clone(),
+        toolchain: None,
+        base_failures: vec![],
+        failure_streaks: vec![],
+        attachments: std::collections::HashMap::new(),
+        side_conversations: snapshot.side_conversations.clone(),
+        author_notes: vec![],
+        also: actions,
+    };
+    print!("{}", format_recommendation(
+        &pr_context,
+        &checks,
+        &action,
+        &CircleCiFailureInfo::default(),
+        &[],
+        &conditions,
+        &config::load().collapse_rules(),
+    ));
+    let replayed = check_run::title(&action);
+    if replayed != snapshot.recommendation {
+        eprintln!(
+            "Note: the captured analysis recommended \"{}\"; this build recommends \"{}\".",
+            snapshot.recommendation, replayed
+        );
+    }
+}
+
+/// Publish the analysis as a check run on the PR head. Failures are warnings:
+/// the analysis itself has already been printed.
+fn publish_check_run(pr_context: &PrContext, action: &NextAction, summary: String, audit_log: &AuditLog) {
+    let head_sha = match fetch_head_sha(&pr_context.owner, &pr_context.repo, pr_context.pr_number)
+    {
+        Ok(sha) => sha,
+        Err(e) => {
+            eprintln!("Warning: Failed to publish analysis check run: {}", e);
+            return;
+        }
+    };
+    let run = build_check_run(&head_sha, action, summary);
+    match RealCheckRunClient.create_check_run(&pr_context.owner, &pr_context.repo, &run) {
+        Ok(url) => {
+            eprintln!("Published analysis check run: {}", url);
+            audit_log.record(
+                "check_run",
+                &format!("sha={} conclusion={} title={}", head_sha, run.conclusion, run.output.title),
+            );
+        }
+        Err(e) => eprintln!("Warning: Failed to publish analysis check run: {}", e),
+    }
+}
+
+/// Apply an edit to the user config file, creating it if needed. Exits 1
+/// if the edit is rejected or the file can't be written.
+fn edit_user_config(edit: impl FnOnce(&str) -> anyhow::Result<String>) {
+    let result = config::config_path().and_then(|path| {
+        let text = std::fs::read_to_string(&path).unwrap_or_default();
+        let updated = edit(&text)?;
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(&path, updated)?;
+        Ok(path)
+    });
+    match result {
+        Ok(path) => println!("Updated {}", path.display()),
+        Err(e) => {
+            eprintln!("Error: {:#}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Validate the user and repository config files, reporting each problem.
+/// Exits 1 if either is invalid.
+fn run_config_validate() {
+    let mut files = Vec::new();
+    if let Ok(path) = config::config_path() {
+        files.push((path, config_edit::validate as fn(&str) -> anyhow::Result<()>));
+    }
+    if let Ok(root) = git::get_repo_root() {
+        files.push((config::repo_config_path(&root), config_edit::validate_repo));
+    }
+    let mut ok = true;
+    let mut found = false;
+    for (path, validate) in files {
+        let Ok(text) = std::fs::read_to_string(&path) else {
+            continue;
+        };
+        found = true;
+        match validate(&text) {
+            Ok(()) => println!("✓ {}", path.display()),
+            Err(e) => {
+                println!("✗ {}:\n{}", path.display(), e);
+                ok = false;
+            }
+        }
+    }
+    if !found {
+        println!("No config files found; everything is at its default.");
+    }
+    if !ok {
+        std::process::exit(1);
+    }
+}
+
+/// Tag actionable threads by urgency if requested (or implied by ordering
+/// them by urgency), and sort them.
+fn triage_threads(
+    action: &mut NextAction,
+    pr_context: &PrContext,
+    tag: bool,
+    order: ThreadOrder,
+) {
+    if let NextAction::RespondToComments { threads, .. } = action {
+        let by_urgency = order == ThreadOrder::Urgency;
+        if tag || by_urgency {
+            tag_threads(threads, &load_code_owners(pr_context));
+        }
+        if by_urgency {
+            sort_by_urgency(threads);
+        }
+    }
+}
+
+/// The features the command and flags need beyond reading the PR.
+fn required_features(cli: &Cli) -> Vec<capabilities::Feature> {
+    use capabilities::Feature;
+    let mut features = Vec::new();
+    let writes = match &cli.command {
+        Some(Command::Reply { .. })
+        | Some(Command::Comment { .. })
+        | Some(Command::ReplyBatch { .. })
+        | Some(Command::Resolve { .. })
+        | Some(Command::Ready { .. })
+        | Some(Command::Merge { .. })
+        | Some(Command::CleanThreads)
+        | Some(Command::Rerun { .. })
+        | Some(Command::Selftest) => true,
+        Some(Command::FileFollowups { dry_run }) => {
+            if !dry_run {
+                features.push(Feature::FileIssues);
+            }
+            false
+        }
+        _ => false,
+    };
+    if writes || cli.maintain_status || cli.auto_clean.is_some() {
+        features.push(Feature::Write);
+    }
+    if cli.upload_logs {
+        features.push(Feature::UploadLogs);
+    }
+    if cli.forward_webhooks {
+        features.push(Feature::ForwardWebhooks);
+    }
+    features
+}
+
+/// `pr-loop doctor`: the environment and what it allows.
+fn print_doctor(env: &capabilities::Environment) {
+    use capabilities::{Auth, Availability, Forge};
+    match env.gh_version {
+        Some((major, minor, patch)) => println!("gh: {}.{}.{}", major, minor, patch),
+        None => println!("gh: version unknown"),
+    }
+    match &env.forge {
+        Forge::GitHubCom => println!("Host: github.com"),
+        Forge::Enterprise(host) => println!("Host: {} (GitHub Enterprise Server)", host),
+    }
+    match &env.auth {
+        Auth::None => println!("Token: not logged in"),
+        Auth::UnknownScopes => println!("Token: scopes not reported (fine-grained or app token)"),
+        Auth::Scopes(scopes) if scopes.is_empty() => println!("Token scopes: none"),
+        Auth::Scopes(scopes) => println!("Token scopes: {}", scopes.join(", ")),
+    }
+    println!("CircleCI token: {}", if env.circleci_token { "set" } else { "not set" });
+    println!();
+    for (feature, availability) in capabilities::matrix(env) {
+        match availability {
+            Availability::Available => println!("  ✓ {}", feature.describe()),
+            Availability::Unavailable(why) => println!("  ✗ {}: {}", feature.describe(), why),
+            Availability::Unknown(why) => println!("  ? {}: {}", feature.describe(), why),
+        }
+    }
+}
+
+/// The filters the analysis ran with, for `--explain`.
+struct ExplainRules<'a> {
+    check_filters: &'a CheckFilters,
+    ignore_base_failures: bool,
+    mentions_actionable: bool,
+    author_threads: AuthorThreadPolicy,
+}
+
+/// `--explain`: every thread and check the PR has, whether the analysis
+/// counted it and why, and the priority that picked `action`.
+fn print_explanation(
+    pr_context: &PrContext,
+    state_root: &Option<std::path::PathBuf>,
+    rules: &ExplainRules,
+    action: &NextAction,
+) {
+    let (owner, repo, pr_number) = (&pr_context.owner, &pr_context.repo, pr_context.pr_number);
+    let (threads, checks) = match (
+        RealThreadsClient.fetch_threads(owner, repo, pr_number),
+        RealChecksClient.fetch_checks(owner, repo, pr_number),
+    ) {
+        (Ok(threads), Ok(checks)) => (threads, checks),
+        (Err(e), _) | (_, Err(e)) => {
+            eprintln!("Warning: Failed to fetch threads and checks to explain the analysis: {}", e);
+            return;
+        }
+    };
+    let snoozes = load_snoozes(state_root);
+    let label = pr_label(pr_context);
+    let base_failures = if rules.ignore_base_failures && checks.iter().any(|c| c.status == CheckStatus::Fail) {
+        RealChecksClient
+            .fetch_base_checks(owner, repo, pr_number)
+            .map(|base| failing_on_base(&checks, &base))
+            .unwrap_or_default()
+    } else {
+        vec![]
+    };
+
+    let thread_entries =
+        explain_threads(pr_context, &threads, &snoozes, rules.mentions_actionable, rules.author_threads);
+    let check_entries = explain::checks(
+        &checks,
+        &explain::CheckRules {
+            filters: rules.check_filters,
+            snoozes: &snoozes,
+            pr: &label,
+            base_failures: &base_failures,
+        },
+    );
+    println!();
+    println!("## Why");
+    println!();
+    print!("{}", explain::render(&thread_entries, &check_entries, &explain::decision(action)));
+}
+
+/// Why each of `threads` did or didn't need a response, looking up the
+/// agent's logins and the PR author only when the rules need them.
+fn explain_threads(
+    pr_context: &PrContext,
+    threads: &[ReviewThread],
+    snoozes: &Snoozes,
+    mentions_actionable: bool,
+    author_policy: AuthorThreadPolicy,
+) -> Vec<explain::Entry> {
+    let mentions_anyone = threads
+        .iter()
+        .any(|t| t.last_comment().is_some_and(|c| !mentions::mentioned_logins(&c.body).is_empty()));
+    let own_logins = if mentions_anyone && !mentions_actionable {
+        own_logins_lookup(pr_context)()
+    } else {
+        vec![]
+    };
+    let pr_author = if author_policy != AuthorThreadPolicy::Actionable {
+        pr_author_lookup(pr_context)()
+    } else {
+        None
+    };
+    explain::threads(
+        threads,
+        &explain::ThreadRules {
+            snoozes,
+            pr: &pr_label(pr_context),
+            own_logins: &own_logins,
+            mentions_actionable,
+            pr_author: pr_author.as_deref(),
+            author_policy,
+        },
+    )
+}
+
+/// Start the `--listen` webhook receiver, if asked for: while the returned
+/// listener is alive, waits poll as soon as a delivery concerns the PR.
+fn start_listener(
+    bind: &Option<String>,
+    forward_webhooks: bool,
+    secret: &Option<String>,
+    pr_context: &PrContext,
+) -> Option<listen::Listener> {
+    let bind = bind.as_deref()?;
+    let started = listen::start(bind, pr_context.pr_number, secret.clone()).and_then(|mut listener| {
+        if forward_webhooks {
+            listener.forward_from_github(&pr_context.owner, &pr_context.repo, secret.as_deref())?;
+        }
+        Ok(listener)
+    });
+    match started {
+        Ok(listener) => Some(listener),
+        Err(e) => {
+            eprintln!("Error: {:#}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Looks up the agent's own logins (the `gh` user and the PR author) for
+/// telling which mentions are addressed to it.
+fn own_logins_lookup(pr_context: &PrContext) -> mentions::OwnLogins {
+    let (owner, repo, pr_number) = (pr_context.owner.clone(), pr_context.repo.clone(), pr_context.pr_number);
+    Box::new(move || {
+        mentions::fetch_own_logins(&owner, &repo, pr_number).unwrap_or_else(|e| {
+            eprintln!("Warning: Failed to look up the PR author: {}", e);
+            vec![]
+        })
+    })
+}
+
+/// Run the `selftest` subcommand against a disposable PR in `repo`, printing
+/// each step as it went. Exits 1 if any failed.
+fn run_selftest_command(repo: Option<&str>) {
+    let Some((owner, repo)) = repo.and_then(|r| r.split_once('/')) else {
+        eprintln!("Error: selftest needs --repo OWNER/REPO naming a sandbox repository it can open PRs in.");
+        std::process::exit(1);
+    };
+    let branch = format!("pr-loop-selftest-{}", jiff::Timestamp::now().as_second());
+    println!("Running selftest in {}/{} (branch {})...", owner, repo, branch);
+    let clients = selftest::Clients {
+        sandbox: &selftest::RealSandboxClient,
+        threads: &RealThreadsClient,
+        replies: &RealReplyClient,
+        prs: &RealPrClient,
+    };
+    let steps = selftest::run(&clients, owner, repo, &branch);
+    let mut failed = false;
+    for step in &steps {
+        match &step.result {
+            Ok(()) => println!("✓ {}", step.name),
+            Err(e) => {
+                println!("✗ {}: {:#}", step.name, e);
+                failed = true;
+            }
+        }
+    }
+    if failed {
+        std::process::exit(1);
+    }
+    println!("All {} steps passed.", steps.len());
+}
+
+/// Run the `mcp` subcommand: serve tool calls on stdio until the client
+/// disconnects, each one run as a subcommand for `repo` and `pr`.
+fn run_mcp_command(repo: Option<&str>, pr: Option<u64>) {
+    let mut global_args = vec![];
+    if let Some(repo) = repo {
+        global_args.push(format!("--repo={}", repo));
+    }
+    if let Some(pr) = pr {
+        global_args.push(format!("--pr={}", pr));
+    }
+    let runner = mcp::RealToolRunner { global_args };
+    if let Err(e) = mcp::serve(std::io::stdin().lock(), std::io::stdout().lock(), &runner) {
+        eprintln!("Error: {:#}", e);
+        std::process::exit(1);
+    }
+}
+
+/// Run the `init` subcommand: write the loop's integration files into the
+/// checkout (or the current directory outside one).
+fn run_init_command(root: Option<&std::path::Path>, hooks: bool, force: bool) {
+    let root = root.map(std::path::Path::to_path_buf).unwrap_or_else(|| std::path::PathBuf::from("."));
+    let providers = init::detect_providers(&root);
+    if providers.is_empty() {
+        println!("No CI configuration found; writing generic files.");
+    } else {
+        let names: Vec<&str> = providers.iter().map(|p| p.name()).collect();
+        println!("Detected CI: {}", names.join(", "));
+    }
+    match init::apply(&root, &init::plan(&providers, hooks), force) {
+        Ok(outcomes) => {
+            for (path, outcome) in outcomes {
+                println!("  {}: {}", path.display(), outcome.describe());
+            }
+        }
+        Err(e) => {
+            eprintln!("Error: {:#}", e);
+            std::process::exit(1);
+        }
+    }
+    if hooks {
+        println!();
+        println!(
+            "Register the Stop hook in .claude/settings.json to use it (see the comment at the top of {}).",
+            init::STOP_HOOK_PATH
+        );
+    }
+}
+
+/// Looks up the PR author's login for telling which threads are their own.
+fn pr_author_lookup(pr_context: &PrContext) -> author_threads::AuthorLookup {
+    let (owner, repo, pr_number) = (pr_context.owner.clone(), pr_context.repo.clone(), pr_context.pr_number);
+    Box::new(move || match author_threads::fetch_pr_author(&owner, &repo, pr_number) {
+        Ok(author) => Some(author),
+        Err(e) => {
+            eprintln!("Warning: {:#}", e);
+            None
+        }
+    })
+}
+
+/// The actionable threads addressed to someone else, with who they're for.
+fn find_side_conversations(
+    action: &NextAction,
+    pr_context: &PrContext,
+) -> std::collections::HashMap<String, Vec<String>> {
+    let NextAction::RespondToComments { threads, .. } = action else {
+        return std::collections::HashMap::new();
+    };
+    let mentioning: Vec<&ReviewThread> = threads
+        .iter()
+        .map(|t| &t.thread)
+        .filter(|t| t.last_comment().is_some_and(|c| !mentions::mentioned_logins(&c.body).is_empty()))
+        .collect();
+    if mentioning.is_empty() {
+        return std::collections::HashMap::new();
+    }
+    let own = own_logins_lookup(pr_context)();
+    mentioning
+        .into_iter()
+        .map(|t| (t.id.clone(), mentions::addressed_elsewhere(t, &own)))
+        .filter(|(_, others)| !others.is_empty())
+        .collect()
+}
+
+/// Download the images in the actionable threads' comments into `dir`,
+/// warning about (and leaving out) the ones that fail.
+fn download_attachments(
+    action: &NextAction,
+    dir: &std::path::Path,
+) -> std::collections::HashMap<String, std::path::PathBuf> {
+    let NextAction::RespondToComments { threads, .. } = action else {
+        return std::collections::HashMap::new();
+    };
+    let urls: Vec<String> = threads
+        .iter()
+        .flat_map(|t| &t.thread.comments)
+        .flat_map(|c| comment_text::image_urls(&c.body))
+        .collect();
+    attachments::download_all(&attachments::RealDownloader::new(), dir, &urls)
+        .into_iter()
+        .filter_map(|(url, path)| match path {
+            Ok(path) => Some((url, path)),
+            Err(e) => {
+                eprintln!("Warning: Failed to download {}: {:#}", url, e);
+                None
+            }
+        })
+        .collect()
+}
+
+/// Files the PR changes, or none if they can't be fetched.
+fn fetch_changed_files(pr_context: &PrContext) -> Vec<ChangedFile> {
+    RealFilesClient
+        .fetch_changed_files(&pr_context.owner, &pr_context.repo, pr_context.pr_number)
+        .unwrap_or_else(|e| {
+            eprintln!("Warning: Failed to fetch changed files: {}", e);
+            vec![]
+        })
+}
+
+/// A recommendation to rebase, or None if the branch is recent enough or
+/// its base can't be compared.
+fn check_rebase(pr_context: &PrContext, threshold: u64) -> Option<behind::Rebase> {
+    behind::check(&pr_context.owner, &pr_context.repo, pr_context.pr_number, threshold).unwrap_or_else(|e| {
+        eprintln!("Warning: Failed to compare the PR with its base branch: {}", e);
+        None
+    })
+}
+
+/// Issues the PR closes, or none if they can't be fetched.
+fn fetch_linked_issues(pr_context: &PrContext) -> Vec<LinkedIssue> {
+    RealIssuesClient
+        .fetch_linked_issues(&pr_context.owner, &pr_context.repo, pr_context.pr_number)
+        .unwrap_or_else(|e| {
+            eprintln!("Warning: Failed to fetch linked issues: {}", e);
+            vec![]
+        })
+}
+
+/// CODEOWNERS from the local checkout, or from GitHub if there's no checkout
+/// (or it has none). Empty if neither can be read.
+fn load_code_owners(pr_context: &PrContext) -> CodeOwners {
+    let local = git::get_repo_root()
+        .ok()
+        .and_then(|root| CodeOwners::load(&root).transpose());
+    local
+        .unwrap_or_else(|| {
+            CodeOwners::fetch(&pr_context.owner, &pr_context.repo).map(Option::unwrap_or_default)
+        })
+        .unwrap_or_else(|e| {
+            eprintln!("Warning: Failed to load CODEOWNERS: {}", e);
+            CodeOwners::default()
+        })
+}
+
+/// Sleep until working hours begin. Returns false if that's more than
+/// `timeout_secs` away (after sleeping for the timeout).
+/// With --wait-json, print how a wait ended as one JSON line on stdout.
+fn print_wait_json(enabled: bool, result: &str, reason: Option<&WaitReason>) {
+    if !enabled {
+        return;
+    }
+    let mut value = serde_json::json!({ "result": result });
+    if let Some(reason) = reason {
+        value["reason"] = serde_json::to_value(reason).unwrap_or_default();
+    }
+    println!("{}", value);
+}
+
+fn pause_until_working_hours(hours: &WorkingHours, timeout_secs: u64, cancel: Option<&CancelFile>) -> bool {
+    let wait = hours.until_next_start(&jiff::Zoned::now());
+    if wait.is_zero() {
+        return true;
+    }
+    eprintln!(
+        "Outside working hours ({}); pausing for {} until they begin...",
+        hours.describe(),
+        format_duration(wait)
+    );
+    let timeout = std::time::Duration::from_secs(timeout_secs);
+    if wait > timeout {
+        sleep_unless_cancelled(timeout, cancel);
+        return false;
+    }
+    !sleep_unless_cancelled(wait, cancel)
+}
+
+/// End a cancelled wait: refresh the status block so it doesn't claim
+/// pr-loop is still waiting, then exit with code 3.
+fn exit_wait_cancelled(
+    cancel: &CancelFile,
+    wait_json: bool,
+    maintain_status: bool,
+    pr_client: &dyn PrClient,
+    pr_context: &PrContext,
+) -> ! {
+    let reason = cancel.reason();
+    eprintln!(
+        "Wait cancelled via {}{}",
+        cancel.path().display(),
+        reason.as_deref().map_or(".".to_string(), |r| format!(": {}", r))
+    );
+    print_wait_json(wait_json, "cancelled", None);
+    if maintain_status {
+        let message = reason.unwrap_or_else(|| "Paused: pr-loop's wait was cancelled.".to_string());
+        if let Err(e) = update_pr_status(pr_client, pr_context, Some(&message)) {
+            eprintln!("Warning: Failed to update PR status block: {}", e);
+        }
+    }
+    std::process::exit(wait::CANCELLED_EXIT_CODE);
+}
+
+/// Fetch CI failure info (logs + test failures) for failed checks. Handles
+/// CircleCI (via their API; requires CIRCLECI_TOKEN or a per-org token),
+/// GitHub Actions (via `gh api`, no extra credentials needed), and Azure
+/// Pipelines (requires AZURE_DEVOPS_TOKEN).
+fn fetch_ci_failure_info(creds: &Credentials, checks: &ChecksSummary) -> CircleCiFailureInfo {
+    let log_budget = LogBudget::new(config::load().circleci_log_cap());
+    let retry_budget = RetryBudget::new(circleci::DEFAULT_OUTPUT_RETRIES);
+    let mut combined = CircleCiFailureInfo::default();
+    for info in concurrent::map(&checks.failed(), |check| {
+        fetch_check_failure_info(creds, check, &log_budget, &retry_budget)
+    })
+    .into_iter()
+    .flatten()
+    {
+        combined.jobs.extend(info.jobs);
+        combined.step_logs.extend(info.step_logs);
+        combined.test_failures.extend(info.test_failures);
+        combined.fetch_errors.extend(info.fetch_errors);
+        combined.cache_suspects.extend(info.cache_suspects);
+    }
+    combined
+}
+
+/// Fetch one failed check's CircleCI, GitHub Actions, or Azure Pipelines
+/// failure details.
+/// None for other providers, without a token, or when the fetch fails.
+fn fetch_check_failure_info(
+    creds: &Credentials,
+    check: &Check,
+    log_budget: &LogBudget,
+    retry_budget: &RetryBudget,
+) -> Option<CircleCiFailureInfo> {
+    let url = check.url.as_deref()?;
+    if is_circleci_url(url) {
+        let job_info = parse_circleci_url(url)?;
+        let token = creds.circleci_token_for(&job_info.owner)?;
+        let c = RealCircleCiClient::new(token.to_string())
+            .with_log_budget(log_budget.clone())
+            .with_retry_budget(retry_budget.clone());
+        match get_job_failures(&c, &job_info) {
+            Ok(info) => Some(info),
+            Err(e) => {
+                eprintln!(
+                    "Warning: Failed to fetch CircleCI logs for {}: {}",
+                    check.name, e
+                );
+                None
+            }
+        }
+    } else if gh_actions::is_gh_actions_url(url) {
+        let job_info = gh_actions::parse_gh_actions_url(url)?;
+        match gh_actions::get_failed_step_logs(&gh_actions::RealGhActionsClient, &job_info) {
+            Ok(step_logs) => Some(CircleCiFailureInfo {
+                cache_suspects: detect_cache_corruption(&step_logs),
+                step_logs,
+                ..Default::default()
+            }),
+            Err(e) => {
+                eprintln!(
+                    "Warning: Failed to fetch GitHub Actions logs for {}: {}",
+                    check.name, e
+                );
+                None
+            }
+        }
+    } else if azure_pipelines::is_azure_pipelines_url(url) {
+        let build = azure_pipelines::parse_azure_pipelines_url(url)?;
+        let token = creds.azure_devops_token.clone()?;
+        let client = azure_pipelines::RealAzurePipelinesClient::new(token, log_budget.clone(), retry_budget.clone());
+        match azure_pipelines::get_failed_task_logs(&client, &build) {
+            Ok(info) => Some(info),
+            Err(e) => {
+                eprintln!(
+                    "Warning: Failed to fetch Azure Pipelines logs for {}: {:#}",
+                    check.name, e
+                );
+                None
+            }
+        }
+    } else {
+        None
+    }
+}
+
+/// The base branch's latest check results, when there are failures to
+/// compare them with. Failures to fetch are reported as warnings.
+fn fetch_base_checks(
+    client: &dyn ChecksClient,
+    pr_context: &PrContext,
+    checks: &ChecksSummary,
+) -> Option<checks::BaseChecks> {
+    if checks.failed().is_empty() {
+        return None;
+    }
+    client
+        .fetch_base_checks(&pr_context.owner, &pr_context.repo, pr_context.pr_number)
+        .map_err(|e| eprintln!("Warning: Failed to fetch base branch checks: {}", e))
+        .ok()
+}
+
+/// Fetch check run annotations for failed checks. Works for any check run
+/// provider (no CircleCI token needed); failures are reported as warnings.
+fn fetch_check_annotations(pr_context: &PrContext, checks: &ChecksSummary) -> Vec<Annotation> {
+    let failed = checks.failed();
+    if failed.is_empty() {
+        return vec![];
+    }
+    let failed_names: Vec<&str> = failed.iter().map(|c| c.name.as_str()).collect();
+    match get_failed_check_annotations(
+        &RealAnnotationsClient,
+        &pr_context.owner,
+        &pr_context.repo,
+        pr_context.pr_number,
+        &failed_names,
+    ) {
+        Ok(annotations) => annotations,
+        Err(e) => {
+            eprintln!("Warning: Failed to fetch check annotations: {}", e);
+            vec![]
+        }
+    }
+}
+
+/// PR-level conditions shown alongside the recommendation.
+struct PrConditions {
+    mergeable_status: MergeableStatus,
+    pending_reviews: Vec<PendingReview>,
+    /// Issues the PR closes, with their requirements.
+    linked_issues: Vec<LinkedIssue>,
+    changed_files: Vec<ChangedFile>,
+    /// The local checkout's toolchain, for suggesting how to reproduce failures.
+    toolchain: Option<repro::Toolchain>,
+    /// Failing checks that are failing on the base branch too.
+    base_failures: Vec<BaseFailure>,
+    /// Checks that have kept failing the same way across runs.
+    failure_streaks: Vec<streaks::FailureStreak>,
+    /// Local copies of images in the comments shown, by URL.
+    attachments: std::collections::HashMap<String, std::path::PathBuf>,
+    /// Who each thread addressed to someone else is for, by thread ID (only
+    /// shown when such threads are actionable; otherwise they're left out).
+    side_conversations: std::collections::HashMap<String, Vec<String>>,
+    /// The PR author's own threads, shown as notes with
+    /// `[author_threads] policy = "informational"`.
+    author_notes: Vec<ReviewThread>,
+    /// The rest of the action list after the recommendation, most pressing first.
+    also: Vec<NextAction>,
+}
+
+/// Format the threads the PR author left on their own PR, as context that
+/// needs no reply.
+fn format_author_notes(notes: &[ReviewThread]) -> String {
+    let mut out = String::new();
+    out.push_str("## Notes from the PR author\n");
+    out.push('\n');
+    out.push_str("The author left these on their own PR. Take them into account; no reply is needed.\n");
+    out.push('\n');
+    for thread in notes {
+        out.push_str(&format!("**{}**:\n", thread.location()));
+        for comment in &thread.comments {
+            for line in comment_text::to_text(&comment.body).lines() {
+                out.push_str(&format!("> {}\n", line));
+            }
+        }
+        out.push('\n');
+    }
+    out
+}
+
+/// How many of the most-changed files the analysis lists by name.
+const TOP_CHANGED_FILES: usize = 8;
+
+/// Format the issues a PR closes with their acceptance criteria, so work can
+/// be checked against the original requirements.
+fn format_linked_issues(pr_context: &github::PrContext, issues: &[LinkedIssue]) -> String {
+    let mut out = String::new();
+    out.push_str("## Linked Issues\n");
+    out.push('\n');
+    for issue in issues {
+        let closed = if issue.state == "CLOSED" { " (closed)" } else { "" };
+        out.push_str(&format!(
+            "- **[{}]({})** {}{}\n",
+            issue.reference(&pr_context.owner, &pr_context.repo),
+            issue.url,
+            issue.title,
+            closed
+        ));
+        for criterion in acceptance_criteria(&issue.body) {
+            out.push_str(&format!("  - [ ] {}\n", criterion));
+        }
+    }
+    out.push('\n');
+    out
+}
+
+/// The analysis as Markdown: what pr-loop prints by default, and the summary
+/// of the check run `--publish-check` creates.
+fn format_recommendation(
+    pr_context: &github::PrContext,
+    checks: &ChecksSummary,
+    action: &NextAction,
+    circleci_info: &CircleCiFailureInfo,
+    annotations: &[Annotation],
+    conditions: &PrConditions,
+    collapse_rules: &CollapseRules,
+) -> String {
+    let mut out = String::new();
+    out.push_str(&format!(
+        "# PR Analysis: {}/{}#{}\n",
+        pr_context.owner, pr_context.repo, pr_context.pr_number
+    ));
+    out.push('\n');
+
+    if !conditions.changed_files.is_empty() {
+        out.push_str(&format!(
+            "**Changes:** {}: {}\n",
+            format_diff_stat(&conditions.changed_files),
+            format_top_files(&conditions.changed_files, TOP_CHANGED_FILES)
+        ));
+        out.push('\n');
+    }
+
+    if !conditions.linked_issues.is_empty() {
+        out.push_str(&format_linked_issues(pr_context, &conditions.linked_issues));
+    }
+
+    if conditions.mergeable_status == MergeableStatus::Conflicting {
+        out.push_str("⚠ **MERGE CONFLICTS**: This PR has merge conflicts that must be resolved.\n");
+        out.push('\n');
+    }
+
+    if !conditions.author_notes.is_empty() {
+        out.push_str(&format_author_notes(&conditions.author_notes));
+    }
+
+    if !conditions.pending_reviews.is_empty() {
+        out.push_str(&format!(
+            "⏳ **REVIEW IN PROGRESS**: review by {} not yet submitted; more comments may be coming.\n",
+            describe_pending_reviews(&conditions.pending_reviews)
+        ));
+        out.push('\n');
+    }
+
+    match action {
+        NextAction::RespondToComments {
+            threads,
+            also_has_ci_failures,
+            ci_pending,
+        } => {
+            out.push_str("## ACTION REQUIRED: Respond to review comments\n");
+            out.push('\n');
+            out.push_str(&format!(
+                "There {} {} unaddressed review thread{}:\n",
+                if threads.len() == 1 { "is" } else { "are" },
                 threads.len(),
                 if threads.len() == 1 { "" } else { "s" }
-            );
-            println!();
+            ));
+            out.push('\n');
 
-            for (i, actionable) in threads.iter().enumerate() {
-                println!("### Thread {} - {}", i + 1, actionable.location());
-                println!("Thread ID: `{}`", actionable.thread.id);
-                println!();
+            // Threads from the same review are shown together, numbered
+            // continuously across groups
+            let now = jiff::Timestamp::now();
+            let mut number = 0;
+            for group in review_groups::group_by_review(threads) {
+                out.push_str(&format!("**{}**\n", group.heading(now)));
+                out.push('\n');
+                for actionable in group.threads {
+                    match actionable.tag {
+                        Some(tag) => out.push_str(&format!(
+                            "### Thread {} - {} [{}]\n",
+                            number + 1,
+                            actionable.location(),
+                            tag.label()
+                        )),
+                        None => out.push_str(&format!(
+                            "### Thread {} - {}\n",
+                            number + 1,
+                            actionable.location()
+                        )),
+                    }
+                    out.push_str(&format!("Thread ID: `{}`\n", actionable.thread.id));
+                    if let Some(others) = conditions.side_conversations.get(&actionable.thread.id) {
+                        out.push_str(&format!(
+                            "Addressed to someone else ({}): reply only if you have something to add.\n",
+                            mentions::describe(others)
+                        ));
+                    }
+                    out.push('\n');
 
-                for comment in &actionable.thread.comments {
-                    println!("**@{}** (comment `{}`):", comment.author, comment.id);
-                    for line in comment.body.lines() {
-                        println!("> {}", line);
+                    // The line doesn't exist in the current code; show what was removed
+                    if actionable.thread.on_removed_code
+                        && let Some(hunk) = actionable.thread.diff_hunk()
+                    {
+                        out.push_str("Removed code under discussion:\n");
+                        out.push_str(&format!("```diff\n{}\n```\n", hunk.trim_end()));
+                        out.push('\n');
                     }
-                    println!();
-                }
 
-                if i < threads.len() - 1 {
-                    println!("---");
-                    println!();
-                }
-            }
+                    for comment in &actionable.thread.comments {
+                        out.push_str(&format!("**@{}** ({}):\n", comment.author, comment.reference()));
+                        for line in comment_text::to_text(&comment.body).lines() {
+                            out.push_str(&format!("> {}\n", line));
+                        }
+                        for url in comment_text::image_urls(&comment.body) {
+                            if let Some(path) = conditions.attachments.get(&url) {
+                                out.push_str(&format!(
+                                    "Attachment downloaded to `{}` ({})\n",
+                                    path.display(),
+                                    url
+                                ));
+                            }
+                        }
+                        out.push('\n');
+                    }
+
+                    number += 1;
+                    if number < threads.len() {
+                        out.push_str("---\n");
+                        out.push('\n');
+                    }
+                }
+            }
+
+            out.push_str("To reply, use:\n");
+            out.push_str("  pr-loop reply --in-reply-to <COMMENT_ID> --message \"Your response\"\n");
+            out.push('\n');
+            out.push_str("The --in-reply-to should be the ID of the last comment shown above.\n");
+            out.push_str(&format!(
+                "Your message will be prefixed with \"{}\"\n",
+                CLAUDE_MARKER
+            ));
+
+            if *also_has_ci_failures {
+                out.push('\n');
+                out.push_str(&format!(
+                    "⚠ Note: {} CI check(s) have also failed.\n",
+                    checks.failed().len()
+                ));
+            }
+            if *ci_pending {
+                out.push('\n');
+                out.push_str(&format!(
+                    "○ Note: {} CI check(s) are still pending.\n",
+                    checks.pending().len()
+                ));
+            }
+        }
+
+        NextAction::ResolveConflicts { failed_check_names } => {
+            out.push_str("## ACTION REQUIRED: Resolve merge conflicts\n");
+            out.push('\n');
+            out.push_str("This PR conflicts with its base branch and can't be merged until it's rebased:\n");
+            out.push('\n');
+            out.push_str("1. `git fetch origin && git rebase origin/<base branch>`\n");
+            out.push_str("2. Resolve each conflict, `git add` the files, and `git rebase --continue`\n");
+            out.push_str("3. Build and run the tests, then `git push --force-with-lease`\n");
+            if !failed_check_names.is_empty() {
+                out.push('\n');
+                out.push_str(&format!(
+                    "⚠ {} check{} also failed: {}. CI reruns after the rebase, so look\n",
+                    failed_check_names.len(),
+                    if failed_check_names.len() == 1 { "" } else { "s" },
+                    failed_check_names.join(", ")
+                ));
+                out.push_str("  into any that still fail then.\n");
+            }
+        }
+
+        NextAction::FixCiFailures { failed_check_names } => {
+            out.push_str("## ACTION REQUIRED: Fix CI failures\n");
+            out.push('\n');
+            out.push_str(&format!(
+                "The following {} check{} failed:\n",
+                failed_check_names.len(),
+                if failed_check_names.len() == 1 { "" } else { "s" }
+            ));
+            out.push_str(&format_check_groups(
+                &display_groups(checks, collapse_rules, CheckStatus::Fail),
+                "✗",
+            ));
+
+            if !conditions.base_failures.is_empty() {
+                out.push('\n');
+                for failure in &conditions.base_failures {
+                    out.push_str(&format!("⚠ **{}.**\n", failure.describe()));
+                }
+                if conditions.base_failures.len() == failed_check_names.len() {
+                    out.push_str("  Every failure here is on the base branch too. Wait for it to be fixed\n");
+                    out.push_str("  there (then rebase), or rerun with --ignore-base-failures.\n");
+                } else {
+                    out.push_str("  Don't try to fix these here; focus on the other failures.\n");
+                }
+            }
+
+            if !conditions.failure_streaks.is_empty() {
+                out.push('\n');
+                for streak in &conditions.failure_streaks {
+                    out.push_str(&format!("⚠ {}.\n", streak.describe()));
+                }
+                out.push_str("  If your fixes aren't changing the failure, stop and ask a human for help\n");
+                out.push_str("  rather than trying again.\n");
+            }
+
+            if !circleci_info.cache_suspects.is_empty() {
+                out.push('\n');
+                out.push_str("## Possible Cache Corruption\n");
+                out.push('\n');
+                for suspect in &circleci_info.cache_suspects {
+                    out.push_str(&format!(
+                        "- {} / {}: {} (`{}`)\n",
+                        suspect.job_name, suspect.step_name, suspect.kind, suspect.line
+                    ));
+                }
+                out.push('\n');
+                out.push_str("These failures may be a stale or corrupted dependency cache rather than\n");
+                out.push_str("bugs in the PR. Try re-running them with fresh caches first; if they fail\n");
+                out.push_str("the same way again, treat them as real failures:\n");
+                out.push_str("  pr-loop rerun --failed-only --bust-caches\n");
+            }
+
+            // Show check annotations if available (structured file:line output)
+            if !annotations.is_empty() {
+                out.push('\n');
+                out.push_str("## Check Annotations\n");
+                out.push('\n');
+                out.push_str(&format_annotations(annotations));
+            }
+
+            if !circleci_info.jobs.is_empty() {
+                out.push('\n');
+                out.push_str("## CircleCI Jobs\n");
+                out.push_str(&format_failed_jobs(&circleci_info.jobs));
+            }
+
+            // Show CircleCI test failures if available (structured, most useful)
+            if !circleci_info.test_failures.is_empty() {
+                out.push('\n');
+                out.push_str("## CI Test Failures\n");
+                out.push_str(&format_test_failures(&circleci_info.test_failures));
+
+                if let Some(toolchain) = conditions.toolchain {
+                    out.push('\n');
+                    out.push_str("## Reproduce Locally\n");
+                    out.push('\n');
+                    for command in repro::suggest_commands(toolchain, &circleci_info.test_failures) {
+                        out.push_str(&format!("  {}\n", command));
+                    }
+                }
+            }
+
+            // Show CircleCI step logs if available
+            if !circleci_info.step_logs.is_empty() {
+                out.push('\n');
+                out.push_str("## CI Failure Logs\n");
+                out.push_str(&format_step_logs(
+                    &circleci_info.step_logs,
+                    &circleci_info.test_failures,
+                ));
+                out.push_str(&format_log_fetch_errors(&circleci_info.fetch_errors));
+                out.push('\n');
+                out.push_str("Analyze the errors above and push fixes to resolve them.\n");
+            } else if circleci_info.test_failures.is_empty() && annotations.is_empty() {
+                out.push('\n');
+                out.push_str("Use the CircleCI MCP server to investigate the failures:\n");
+                out.push_str("  - List recent pipelines for this project\n");
+                out.push_str("  - Get job details and logs for the failed workflow\n");
+                out.push('\n');
+                out.push_str("Then push fixes to resolve the issues.\n");
+            } else {
+                out.push('\n');
+                out.push_str("Analyze the failures above and push fixes to resolve them.\n");
+            }
+        }
+
+        NextAction::WaitForCi { pending_check_names } => {
+            out.push_str("## WAITING: CI checks in progress\n");
+            out.push('\n');
+            out.push_str(&format!(
+                "The following {} check{} still running:\n",
+                pending_check_names.len(),
+                if pending_check_names.len() == 1 { " is" } else { "s are" }
+            ));
+            out.push_str(&format_check_groups(
+                &display_groups(checks, collapse_rules, CheckStatus::Pending),
+                "○",
+            ));
+            out.push('\n');
+            out.push_str("No action needed. Wait for CI to complete.\n");
+        }
+
+        NextAction::AwaitHumanInput { threads } => {
+            out.push_str("## WAITING: Awaiting human input\n");
+            out.push('\n');
+            out.push_str(&format!(
+                "✓ All CI checks passed, but Claude asked a question in {} thread{} that no reviewer has answered yet:\n",
+                threads.len(),
+                if threads.len() == 1 { "" } else { "s" }
+            ));
+            out.push('\n');
+            for thread in threads {
+                out.push_str(&format!("- {} (thread `{}`)\n", thread.location(), thread.id));
+                if let Some(comment) = thread.last_comment() {
+                    let question = comment.body.trim_start_matches(CLAUDE_MARKER).trim();
+                    if let Some(first) = question.lines().next() {
+                        out.push_str(&format!("  > {}\n", first));
+                    }
+                }
+            }
+            out.push('\n');
+            out.push_str("No action needed. Wait for a reviewer to answer; the thread becomes actionable once they do.\n");
+        }
+
+        NextAction::Rebase { rebase } => {
+            out.push_str(&format!("## ACTION REQUIRED: Rebase onto {}\n", rebase.base));
+            out.push('\n');
+            out.push_str(&format!("The branch is {}, so CI results don't\n", rebase.describe()));
+            out.push_str("reflect what merging it would do:\n");
+            out.push('\n');
+            out.push_str(&format!("1. `git fetch origin && git rebase origin/{}`\n", rebase.base));
+            out.push_str("2. Build and run the tests, then `git push --force-with-lease`\n");
+        }
+
+        NextAction::PrReady => {
+            out.push_str("## PR READY\n");
+            out.push('\n');
+            out.push_str("✓ All CI checks passed\n");
+            out.push_str("✓ No unaddressed review comments\n");
+            out.push('\n');
+            out.push_str("The PR is ready for merge or further review.\n");
+        }
+    }
+
+    if !conditions.also.is_empty() {
+        out.push('\n');
+        out.push_str("## Also\n");
+        out.push('\n');
+        for other in &conditions.also {
+            let summary = ActionSummary::new(other);
+            out.push_str(&format!("- **{}**: {}\n", summary.kind, summary.summary));
+        }
+    }
+    out
+}
+
+/// Print what `reply` posted.
+fn print_posted_reply(pr_context: &PrContext, posted: &PostedReply) {
+    println!(
+        "Replied to thread {} on {}/{}#{}",
+        posted.thread_id, pr_context.owner, pr_context.repo, pr_context.pr_number
+    );
+    if posted.parts > 1 {
+        println!(
+            "Reply was longer than GitHub's {} character limit; posted it in {} parts",
+            MAX_COMMENT_CHARS, posted.parts
+        );
+    }
+    match &posted.result.url {
+        Some(url) => println!("✓ Reply posted (comment ID: {}, {})", posted.result.comment_id, url),
+        None => println!("✓ Reply posted (comment ID: {})", posted.result.comment_id),
+    }
+    match &posted.re_resolved {
+        Some(Ok(())) => println!("✓ Thread left resolved"),
+        Some(Err(e)) => eprintln!("Warning: Failed to re-resolve thread {}: {}", posted.thread_id, e),
+        None => {}
+    }
+}
+
+/// Print newer comments that were posted while the LLM was working.
+fn print_newer_comments(comments: &[threads::ThreadComment], thread_id: &str) {
+    println!();
+    println!("## NEWER COMMENTS DETECTED");
+    println!();
+    println!(
+        "The following {} comment{} {} posted to this thread while you were working.",
+        comments.len(),
+        if comments.len() == 1 { "" } else { "s" },
+        if comments.len() == 1 { "was" } else { "were" }
+    );
+    println!("Please address {} as well:", if comments.len() == 1 { "it" } else { "them" });
+    println!();
+
+    for (i, comment) in comments.iter().enumerate() {
+        println!("### Comment {} (in thread {})", i + 1, thread_id);
+        println!("**@{}** ({}):", comment.author, comment.reference());
+        for line in comment_text::to_text(&comment.body).lines() {
+            println!("> {}", line);
+        }
+        println!();
+    }
+}
+
+/// Format structured test failures, grouped by job name.
+fn format_test_failures(failures: &[circleci::TestFailure]) -> String {
+    let mut out = String::new();
+    out.push('\n');
+    out.push_str(&format!(
+        "{} test failure{}:\n",
+        failures.len(),
+        if failures.len() == 1 { "" } else { "s" }
+    ));
+
+    // Group by job name, preserving order of first appearance
+    let mut job_order: Vec<&str> = Vec::new();
+    let mut by_job: std::collections::HashMap<&str, Vec<&circleci::TestFailure>> =
+        std::collections::HashMap::new();
+    for failure in failures {
+        let entry = by_job.entry(failure.job_name.as_str()).or_default();
+        if entry.is_empty() {
+            job_order.push(&failure.job_name);
+        }
+        entry.push(failure);
+    }
+
+    for job_name in &job_order {
+        out.push('\n');
+        out.push_str(&format!("### Job: {}\n", job_name));
+        for failure in &by_job[job_name] {
+            out.push('\n');
+            match &failure.location {
+                Some(location) => out.push_str(&format!(
+                    "- **{}** / {} ({})\n",
+                    failure.classname, failure.test_name, location
+                )),
+                None => out.push_str(&format!("- **{}** / {}\n", failure.classname, failure.test_name)),
+            }
+            if !failure.message.is_empty() {
+                out.push_str("  ```\n");
+                // Truncate long messages (stack traces can be very long)
+                let msg = truncate_log(&failure.message, 500);
+                for line in msg.lines() {
+                    out.push_str(&format!("  {}\n", line));
+                }
+                out.push_str("  ```\n");
+            }
+        }
+    }
+    out
+}
+
+/// Format job-level metadata for failed CircleCI jobs, with a pointer to
+/// re-running them with SSH for interactive debugging.
+fn format_failed_jobs(jobs: &[FailedJob]) -> String {
+    let mut out = String::new();
+    out.push('\n');
+    for job in jobs {
+        let mut facts = Vec::new();
+        if let Some(class) = &job.resource_class {
+            facts.push(format!("resource class {}", class));
+        }
+        if let Some(ms) = job.duration_ms {
+            facts.push(format!("ran {}", format_duration(std::time::Duration::from_millis(ms))));
+        }
+        if let Some(image) = &job.image {
+            facts.push(format!("image {}", image));
+        }
+        if facts.is_empty() {
+            out.push_str(&format!("- **{}**\n", job.job_name));
+        } else {
+            out.push_str(&format!("- **{}** ({})\n", job.job_name, facts.join(", ")));
+        }
+        if let Some(url) = &job.url {
+            out.push_str(&format!("  Rerun with SSH: {} (Rerun → Rerun Job with SSH)\n", url));
+        }
+    }
+    out
+}
+
+/// Format step log details. Stdout is left out for jobs whose failing tests
+/// `format_test_failures` already listed.
+fn format_step_logs(logs: &[FailedStepLog], test_failures: &[circleci::TestFailure]) -> String {
+    let mut out = String::new();
+    for log in logs {
+        out.push('\n');
+        match &log.container {
+            Some(container) => out.push_str(&format!(
+                "### Job: {} ({}) / Step: {}\n",
+                log.job_name, container, log.step_name
+            )),
+            None => out.push_str(&format!("### Job: {} / Step: {}\n", log.job_name, log.step_name)),
+        }
+        if !log.error.is_empty() {
+            out.push('\n');
+            out.push_str("**Stderr:**\n");
+            out.push_str("```\n");
+            let error_truncated = truncate_log(&log.error, STDERR_BUDGET);
+            out.push_str(&format!("{}\n", error_truncated));
+            out.push_str("```\n");
+        }
+        if !log.output.is_empty() && test_failures.iter().any(|t| t.job_name == log.job_name) {
+            out.push('\n');
+            out.push_str(&format!("Stdout omitted ({} bytes): the job's failing tests are listed above.\n", log.output.len()));
+        } else if !log.output.is_empty() {
+            out.push('\n');
+            out.push_str("**Stdout (last lines):**\n");
+            out.push_str("```\n");
+            // Gradle/Java failures often end with ~15 lines of "Try: Run
+            // with --stacktrace / BUILD FAILED / Publishing Build Scan"
+            // boilerplate, so keep a decently sized tail so the actual
+            // error is visible above it.
+            let output_truncated = truncate_log_tail(&log.output, STDOUT_BUDGET);
+            out.push_str(&format!("{}\n", output_truncated));
+            out.push_str("```\n");
+        }
+        if let Some(url) = &log.full_log_url {
+            out.push('\n');
+            out.push_str(&format!("Captured log (excerpts of long logs): {}\n", url));
+        }
+    }
+    out
+}
+
+/// Note the failed steps whose logs couldn't be fetched, so their absence
+/// isn't mistaken for the whole story.
+fn format_log_fetch_errors(errors: &[String]) -> String {
+    if errors.is_empty() {
+        return String::new();
+    }
+    let mut out = String::new();
+    out.push('\n');
+    out.push_str(&format!("⚠ Couldn't fetch the logs of {} failed step(s):\n", errors.len()));
+    for error in errors {
+        out.push_str(&format!("  - {}\n", error));
+    }
+    out
+}
+
+/// Upload the complete text of any step logs `format_step_logs` would
+/// truncate to a secret gist, linking each from its log.
+fn upload_truncated_logs(pr_context: &PrContext, logs: &mut [FailedStepLog]) {
+    let mut truncated: Vec<&mut FailedStepLog> = logs
+        .iter_mut()
+        .filter(|l| l.error.len() > STDERR_BUDGET || l.output.len() > STDOUT_BUDGET)
+        .collect();
+    let description = format!(
+        "CI log excerpts for {}/{}#{}",
+        pr_context.owner, pr_context.repo, pr_context.pr_number
+    );
+    if let Err(e) = upload_logs(&RealGistClient, &description, &mut truncated) {
+        eprintln!("Warning: Failed to upload CI logs: {}", e);
+    }
+}
+
+/// Update the PR description with a status block.
+fn update_pr_status(
+    pr_client: &dyn PrClient,
+    pr_context: &PrContext,
+    status_message: Option<&str>,
+) -> anyhow::Result<()> {
+    let current_body = pr_client.get_body(&pr_context.owner, &pr_context.repo, pr_context.pr_number)?;
+    let new_body = update_body_with_status(&current_body, status_message);
+    pr_client.set_body(&pr_context.owner, &pr_context.repo, pr_context.pr_number, &new_body)?;
+    eprintln!("✓ Updated PR status block");
+    Ok(())
+}
+
+/// Delete a batch of comments in parallel with bounded concurrency.
+/// Returns (success_count, failure_count).
+fn delete_comments_parallel(
+    comment_ids: &[&str],
+    max_concurrent: usize,
+    mutations: &MutationClients,
+) -> (usize, usize) {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    let success_count = Arc::new(AtomicUsize::new(0));
+    let failure_count = Arc::new(AtomicUsize::new(0));
+
+    // Process in chunks of max_concurrent
+    for chunk in comment_ids.chunks(max_concurrent) {
+        let handles: Vec<_> = chunk
+            .iter()
+            .map(|&id| {
+                let id = id.to_string();
+                let success = Arc::clone(&success_count);
+                let failure = Arc::clone(&failure_count);
+                let client = mutations.reply_client();
+                std::thread::spawn(move || {
+                    match client.delete_comment(&id) {
+                        Ok(()) => {
+                            success.fetch_add(1, Ordering::Relaxed);
+                        }
+                        Err(e) => {
+                            eprintln!("Warning: Failed to delete comment {}: {}", id, e);
+                            failure.fetch_add(1, Ordering::Relaxed);
+                        }
+                    }
+                })
+            })
+            .collect();
+
+        for handle in handles {
+            handle.join().expect("thread panicked during comment deletion");
+        }
+    }
+
+    (
+        success_count.load(Ordering::Relaxed),
+        failure_count.load(Ordering::Relaxed),
+    )
+}
+
+/// Strip the paperclip marker from comments in paperclip threads.
+/// These threads are preserved for human review; the marker is removed so the
+/// human reviewer sees the comments without the marker noise.
+fn strip_paperclips(threads: &[ReviewThread], mutations: &MutationClients) {
+    let paperclip_threads: Vec<_> = threads.iter().filter(|t| t.has_paperclip()).collect();
+
+    if paperclip_threads.is_empty() {
+        return;
+    }
+
+    let client = mutations.reply_client();
+    let mut updated = 0;
+    let mut failed = 0;
+
+    for thread in &paperclip_threads {
+        for comment in &thread.comments {
+            if comment.body.contains(PAPERCLIP_SHORTCODE)
+                || comment.body.contains(PAPERCLIP_EMOJI)
+            {
+                let new_body = comment
+                    .body
+                    .replace(PAPERCLIP_SHORTCODE, "")
+                    .replace(PAPERCLIP_EMOJI, "");
+                match client.update_comment(&comment.id, &new_body) {
+                    Ok(()) => updated += 1,
+                    Err(e) => {
+                        eprintln!(
+                            "Warning: Failed to strip paperclip from comment {}: {}",
+                            comment.id, e
+                        );
+                        failed += 1;
+                    }
+                }
+            }
+        }
+    }
+
+    if updated > 0 {
+        println!(
+            "✓ Stripped paperclip marker from {} comment(s) in {} thread(s)",
+            updated,
+            paperclip_threads.len()
+        );
+    }
+    if failed > 0 {
+        eprintln!("  ({} update(s) failed)", failed);
+    }
+}
+
+/// Builds the clients used for every PR mutation: checked against the
+/// configured policy, then recorded in the audit log.
+#[derive(Clone)]
+struct MutationClients {
+    audit_log: AuditLog,
+    body_history: BodyHistory,
+    policy: Policy,
+    /// The PR whose threads the policy looks up to tell who started them.
+    pr_context: PrContext,
+}
+
+impl MutationClients {
+    fn reply_client(&self) -> Audited<Guarded<RealReplyClient>> {
+        Audited::new(
+            Guarded::new(RealReplyClient, self.policy.clone()).with_thread_lookup(thread_lookup(&self.pr_context)),
+            self.audit_log.clone(),
+        )
+    }
+
+    fn pr_client(&self, ready_confirmed: bool) -> Tracked<Audited<Guarded<RealPrClient>>> {
+        Tracked::new(
+            Audited::new(
+                Guarded::new(RealPrClient, self.policy.clone()).with_ready_confirmed(ready_confirmed),
+                self.audit_log.clone(),
+            ),
+            self.body_history.clone(),
+        )
+    }
+}
+
+/// Looks a thread up among the PR's review threads, fetched the first time
+/// the policy needs to know who started one.
+fn thread_lookup(pr_context: &PrContext) -> ThreadLookup {
+    let (owner, repo, pr_number) = (pr_context.owner.clone(), pr_context.repo.clone(), pr_context.pr_number);
+    let threads = std::sync::OnceLock::new();
+    Box::new(move |id| {
+        let threads = threads
+            .get_or_init(|| RealThreadsClient.fetch_threads(&owner, &repo, pr_number).map_err(|e| format!("{:#}", e)));
+        match threads {
+            Ok(threads) => threads
+                .iter()
+                .find(|t| t.id == id)
+                .cloned()
+                .ok_or_else(|| anyhow::anyhow!("Thread not found: {}", id)),
+            Err(e) => anyhow::bail!("Failed to fetch threads: {}", e),
+        }
+    })
+}
+
+/// e.g. "owner/repo#123", identifying the PR in local state files.
+fn pr_label(pr_context: &PrContext) -> String {
+    format!("{}/{}#{}", pr_context.owner, pr_context.repo, pr_context.pr_number)
+}
+
+/// Audit log for this PR at the repository root (or the current directory
+/// outside a git checkout).
+fn open_audit_log(pr_context: &PrContext, enabled: bool) -> AuditLog {
+    if !enabled {
+        return AuditLog::disabled();
+    }
+    let root = git::get_repo_root().unwrap_or_else(|_| std::path::PathBuf::from("."));
+    AuditLog::new(&root, pr_label(pr_context))
+}
+
+/// Snoozes recorded in this checkout; none outside a git checkout.
+fn load_snoozes(state_root: &Option<std::path::PathBuf>) -> Snoozes {
+    let Some(root) = state_root else {
+        return Snoozes::default();
+    };
+    Snoozes::load(root).unwrap_or_else(|e| {
+        eprintln!("Warning: Ignoring snoozes: {:#}", e);
+        Snoozes::default()
+    })
+}
+
+/// Whether `head` moved since a happy wait last saw the PR's checks at it (a
+/// push, as far as CI is concerned). Outside a git checkout, or when the
+/// state can't be read, assume it did.
+fn pushed_since_checks_seen(
+    state_root: &Option<std::path::PathBuf>,
+    pr_context: &github::PrContext,
+    head: Option<&str>,
+) -> bool {
+    let (Some(root), Some(head)) = (state_root, head) else {
+        return true;
+    };
+    match heads::SeenHeads::load(root) {
+        Ok(heads) => heads.moved(&pr_label(pr_context), head),
+        Err(e) => {
+            eprintln!("Warning: Ignoring recorded heads: {:#}", e);
+            true
+        }
+    }
+}
+
+/// Record that a wait saw the PR's checks at `head`.
+fn record_checks_seen(state_root: &Option<std::path::PathBuf>, pr_context: &github::PrContext, head: Option<&str>) {
+    let (Some(root), Some(head)) = (state_root, head) else {
+        return;
+    };
+    let saved = heads::SeenHeads::load(root).and_then(|mut heads| {
+        heads.record(&pr_label(pr_context), head);
+        heads.save()
+    });
+    if let Err(e) = saved {
+        eprintln!("Warning: Failed to save recorded heads: {:#}", e);
+    }
+}
+
+/// Record this analysis's failing checks in the checkout's failure streaks,
+/// returning the streaks to report. None are tracked outside a git checkout.
+fn record_failure_streaks(
+    state_root: &Option<std::path::PathBuf>,
+    pr_context: &github::PrContext,
+    checks: &ChecksSummary,
+    failing: &[context::FailingCheck],
+) -> Vec<streaks::FailureStreak> {
+    let Some(root) = state_root else {
+        return vec![];
+    };
+    let mut history = match streaks::FailureStreaks::load(root) {
+        Ok(history) => history,
+        Err(e) => {
+            eprintln!("Warning: Ignoring failure streaks: {:#}", e);
+            return vec![];
+        }
+    };
+    let reported = history.record(&pr_label(pr_context), checks, failing);
+    if let Err(e) = history.save() {
+        eprintln!("Warning: Failed to save failure streaks: {:#}", e);
+    }
+    reported
+}
+
+/// Mention what the analysis is leaving out, so a snooze isn't forgotten.
+fn print_snoozes(snoozes: &Snoozes, label: &str) {
+    for snooze in snoozes.for_pr(label) {
+        eprintln!(
+            "Snoozed {} {} until {}{}",
+            snooze.kind,
+            snooze.target,
+            snooze.until.strftime("%Y-%m-%d %H:%M UTC"),
+            snooze.reason.as_deref().map(|r| format!(" ({})", r)).unwrap_or_default()
+        );
+    }
+}
+
+/// Run the `snooze` subcommand: add, clear, or list this PR's snoozes.
+fn run_snooze_command(
+    state_root: &Option<std::path::PathBuf>,
+    pr_context: &PrContext,
+    targets: Vec<(SnoozeKind, String)>,
+    duration: Option<String>,
+    reason: Option<String>,
+    clear: bool,
+    list: bool,
+) {
+    let Some(root) = state_root else {
+        eprintln!("Error: snooze must be run inside the PR's git checkout.");
+        std::process::exit(1);
+    };
+    let mut snoozes = match Snoozes::load(root) {
+        Ok(s) => s,
+        Err(e) => {
+            eprintln!("Error: {:#}", e);
+            std::process::exit(1);
+        }
+    };
+    let label = pr_label(pr_context);
+    if list {
+        if snoozes.for_pr(&label).next().is_none() {
+            eprintln!("Nothing is snoozed on {}.", label);
+        }
+        print_snoozes(&snoozes, &label);
+        return;
+    }
+    if targets.is_empty() {
+        eprintln!("Error: Pass at least one --thread or --check.");
+        std::process::exit(1);
+    }
+
+    if clear {
+        for (kind, target) in targets {
+            if snoozes.remove(&label, kind, &target) {
+                println!("Unsnoozed {} {}", kind, target);
+            } else {
+                eprintln!("Warning: {} {} wasn't snoozed", kind, target);
+            }
+        }
+    } else {
+        let duration = duration.expect("clap requires --for unless --clear or --list");
+        let until = match snooze::expiry(&duration, &jiff::Zoned::now()) {
+            Ok(t) => t,
+            Err(e) => {
+                eprintln!("Error: {:#}", e);
+                std::process::exit(1);
+            }
+        };
+        for (kind, target) in targets {
+            println!("Snoozed {} {} for {}", kind, target, duration);
+            snoozes.add(Snooze {
+                pr: label.clone(),
+                kind,
+                target,
+                until,
+                reason: reason.clone(),
+            });
+        }
+    }
+    if let Err(e) = snoozes.save() {
+        eprintln!("Error: {:#}", e);
+        std::process::exit(1);
+    }
+}
+
+/// The `watch` subcommand's settings.
+struct WatchOptions {
+    poll_interval: u64,
+    tag_threads: bool,
+    thread_order: ThreadOrder,
+    toolchain: Option<repro::Toolchain>,
+    mentioned_others_actionable: bool,
+    author_thread_policy: AuthorThreadPolicy,
+    rebase_threshold: u64,
+}
+
+/// Run the `watch` subcommand: re-analyze the PR every poll, printing the
+/// recommendation again whenever the next action changes.
+fn run_watch_command(
+    creds: &Credentials,
+    pr_context: &PrContext,
+    check_filters: &CheckFilters,
+    collapse_rules: &CollapseRules,
+    options: &WatchOptions,
+    cancel: Option<&CancelFile>,
+) {
+    let checks_client = RealChecksClient;
+    let threads_client = AuthorThreads::new(
+        SideConversations::new(
+            RealThreadsClient,
+            own_logins_lookup(pr_context),
+            options.mentioned_others_actionable,
+        ),
+        options.author_thread_policy,
+        pr_author_lookup(pr_context),
+    );
+    let mut last_fingerprint: Option<String> = None;
+    eprintln!(
+        "Watching {} (polling every {}s)...",
+        pr_label(pr_context),
+        options.poll_interval
+    );
+    loop {
+        match capture_snapshot(
+            &checks_client,
+            &threads_client,
+            &pr_context.owner,
+            &pr_context.repo,
+            pr_context.pr_number,
+            check_filters,
+        ) {
+            Ok(snapshot) => {
+                let rebase = check_rebase(pr_context, options.rebase_threshold);
+                let mut actions =
+                    analyze_actions(&snapshot.checks, snapshot.threads, &snapshot.mergeable_status, rebase.as_ref());
+                let mut action = actions.remove(0);
+                let fingerprint = action.fingerprint();
+                if last_fingerprint.as_ref() != Some(&fingerprint) {
+                    triage_threads(&mut action, pr_context, options.tag_threads, options.thread_order);
+                    let (circleci_info, annotations) = std::thread::scope(|s| {
+                        let ci_info = s.spawn(|| fetch_ci_failure_info(creds, &snapshot.checks));
+                        let annotations = fetch_check_annotations(pr_context, &snapshot.checks);
+                        (concurrent::join(ci_info), annotations)
+                    });
+                    let conditions = PrConditions {
+                        mergeable_status: snapshot.mergeable_status,
+                        pending_reviews: snapshot.pending_reviews,
+                        linked_issues: vec![],
+                        changed_files: vec![],
+                        toolchain: options.toolchain,
+                        base_failures: vec![],
+                        failure_streaks: vec![],
+                        attachments: std::collections::HashMap::new(),
+                        side_conversations: if options.mentioned_others_actionable {
+                            find_side_conversations(&action, pr_context)
+                        } else {
+                            std::collections::HashMap::new()
+                        },
+                        author_notes: threads_client.notes(),
+                        also: actions,
+                    };
+                    if last_fingerprint.is_some() {
+                        println!("\n---\n");
+                    }
+                    eprintln!("[{}] The next action changed.", jiff::Zoned::now().strftime("%H:%M:%S"));
+                    print!("{}", format_recommendation(
+                        pr_context,
+                        &snapshot.checks,
+                        &action,
+                        &circleci_info,
+                        &annotations,
+                        &conditions,
+                        collapse_rules,
+                    ));
+                    last_fingerprint = Some(fingerprint);
+                }
+            }
+            Err(e) => eprintln!("Warning: Failed to fetch PR state; retrying: {:#}", e),
+        }
+        if sleep_until_next_poll(std::time::Duration::from_secs(options.poll_interval), cancel) {
+            eprintln!("Watch cancelled.");
+            std::process::exit(wait::CANCELLED_EXIT_CODE);
+        }
+    }
+}
+
+/// Run the `post-ready-watch` subcommand: watch the PR for `duration`,
+/// notifying and exiting 0 as soon as it needs the agent again, or exiting 2
+/// when the window passes quietly.
+fn run_post_ready_watch_command(
+    pr_context: &PrContext,
+    check_filters: &CheckFilters,
+    duration: &str,
+    poll_interval: u64,
+    notifier: &dyn Notifier,
+    cancel: Option<&CancelFile>,
+) {
+    let now = jiff::Zoned::now();
+    let window = match snooze::expiry(duration, &now) {
+        Ok(until) => std::time::Duration::from_secs(until.as_second().saturating_sub(now.timestamp().as_second()) as u64),
+        Err(e) => {
+            eprintln!("Error: {:#}", e);
+            std::process::exit(1);
+        }
+    };
+    let outcome = watch(
+        &RealChecksClient,
+        &RealThreadsClient,
+        pr_context,
+        check_filters,
+        window,
+        std::time::Duration::from_secs(poll_interval),
+        cancel,
+    );
+    match outcome {
+        Ok(WatchOutcome::Attention(items)) => {
+            let title = format!("{} needs attention", pr_label(pr_context));
+            let message = items.join("\n");
+            println!("# {}\n", title);
+            for item in &items {
+                println!("- {}", item);
+            }
+            if !hours::is_working_time() {
+                eprintln!("Outside working hours; not sending a notification.");
+            } else if let Err(e) = notifier.notify(&title, &message) {
+                eprintln!("Warning: Failed to send notification: {:#}", e);
+            }
+        }
+        Ok(WatchOutcome::Quiet) => {
+            eprintln!("Nothing needed the agent during the last {}.", duration);
+            std::process::exit(wait::TIMEOUT_EXIT_CODE);
+        }
+        Ok(WatchOutcome::Cancelled) => {
+            eprintln!("Watch cancelled.");
+            std::process::exit(wait::CANCELLED_EXIT_CODE);
+        }
+        Err(e) => {
+            eprintln!("Error: {:#}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Options for `pr-loop merge`.
+struct MergeOptions {
+    method: MergeMethod,
+    auto: bool,
+    wait: bool,
+    timeout: std::time::Duration,
+    poll_interval: std::time::Duration,
+}
+
+/// Run the `merge` subcommand: validate the PR, merge it (or enable
+/// auto-merge), and wait for GitHub to report it merged.
+fn run_merge_command(
+    pr_client: &dyn PrClient,
+    pr_context: &PrContext,
+    check_filters: &CheckFilters,
+    options: &MergeOptions,
+    cancel: Option<&CancelFile>,
+) {
+    let (owner, repo, pr_number) = (&pr_context.owner, &pr_context.repo, pr_context.pr_number);
+    match pr_client.is_draft(owner, repo, pr_number) {
+        Ok(false) => {}
+        Ok(true) => {
+            eprintln!("Error: PR is still a draft. Run `pr-loop ready` first.");
+            std::process::exit(1);
+        }
+        Err(e) => {
+            eprintln!("Error: Failed to check PR draft status: {}", e);
+            std::process::exit(1);
+        }
+    }
+
+    println!("Validating PR state...");
+    let snapshot = match capture_snapshot(
+        &RealChecksClient,
+        &RealThreadsClient,
+        owner,
+        repo,
+        pr_number,
+        check_filters,
+    ) {
+        Ok(s) => s,
+        Err(e) => {
+            eprintln!("Error: Failed to check PR state: {}", e);
+            std::process::exit(1);
+        }
+    };
+    let blockers = merge::blockers(&snapshot, options.auto);
+    if !blockers.is_empty() {
+        eprintln!("Error: PR isn't ready to merge:");
+        for blocker in &blockers {
+            eprintln!("  - {}", blocker);
+        }
+        std::process::exit(1);
+    }
+    println!("✓ No unresolved threads and no failing checks");
+
+    let method = options.method.flag().trim_start_matches('-');
+    if let Err(e) = pr_client.merge(owner, repo, pr_number, options.method, options.auto) {
+        eprintln!("Error: {}", e);
+        std::process::exit(1);
+    }
+    if options.auto {
+        println!("✓ Auto-merge enabled ({})", method);
+    } else {
+        println!("✓ Merge requested ({})", method);
+    }
+    if !options.wait {
+        return;
+    }
+
+    println!("Waiting for the PR to be merged...");
+    match merge::wait_for_merge(pr_client, pr_context, options.timeout, options.poll_interval, cancel) {
+        Ok(merge::MergeOutcome::Merged) => println!("✓ PR merged"),
+        Ok(merge::MergeOutcome::Closed) => {
+            eprintln!("Error: PR was closed without being merged.");
+            std::process::exit(1);
+        }
+        Ok(merge::MergeOutcome::Timeout) => {
+            eprintln!("Timeout reached before the PR was merged.");
+            std::process::exit(wait::TIMEOUT_EXIT_CODE);
+        }
+        Ok(merge::MergeOutcome::Cancelled) => {
+            eprintln!("Wait for the merge cancelled.");
+            std::process::exit(wait::CANCELLED_EXIT_CODE);
+        }
+        Err(e) => {
+            eprintln!("Error while waiting for the merge: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Run the `audit` subcommand: print (and optionally post) this PR's audit log.
+fn run_audit_command(
+    pr_client: &dyn PrClient,
+    pr_context: &PrContext,
+    audit_log: &AuditLog,
+    post: bool,
+) {
+    let Some(path) = audit_log.path() else {
+        eprintln!("Audit logging is disabled ([audit] enabled = false).");
+        return;
+    };
+    let entries = match audit_log.entries_for_pr() {
+        Ok(entries) => entries,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+    };
+    if entries.is_empty() {
+        println!("No recorded mutations for this PR in {}.", path.display());
+        return;
+    }
+    for entry in &entries {
+        println!("{}", entry);
+    }
+    if post {
+        let digest = format_digest(&entries);
+        match pr_client.add_comment(&pr_context.owner, &pr_context.repo, pr_context.pr_number, &digest) {
+            Ok(()) => eprintln!("✓ Posted audit digest to the PR"),
+            Err(e) => {
+                eprintln!("Error: Failed to post audit digest: {}", e);
+                std::process::exit(1);
+            }
+        }
+    }
+}
+
+/// Run the `comment` subcommand: post `message` as Claude in the PR's
+/// conversation, split into parts if it's too long for one comment.
+fn run_comment_command(pr_client: &dyn PrClient, pr_context: &PrContext, message: &str) {
+    let parts = split_reply(None, &format_claude_reply(None, message));
+    for (i, part) in parts.iter().enumerate() {
+        if let Err(e) = pr_client.add_comment(&pr_context.owner, &pr_context.repo, pr_context.pr_number, part) {
+            match parts.len() {
+                1 => eprintln!("Error: Failed to post comment: {}", e),
+                n => eprintln!("Error: Failed to post part {}/{} of the comment: {}", i + 1, n, e),
+            }
+            std::process::exit(1);
+        }
+    }
+    match parts.len() {
+        1 => println!("✓ Posted comment on PR #{}", pr_context.pr_number),
+        n => println!("✓ Posted comment on PR #{} in {} parts", pr_context.pr_number, n),
+    }
+}
+
+/// Run the `clean-threads` subcommand: delete resolved pure-Claude threads.
+/// Run the `reply-batch` subcommand: post every reply in the file (or
+/// stdin), then report each one's result.
+fn run_reply_batch_command(pr_context: &PrContext, mutations: &MutationClients, file: Option<&std::path::Path>) {
+    let input = match file {
+        Some(path) if path != std::path::Path::new("-") => std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display())),
+        _ => std::io::read_to_string(std::io::stdin()).context("Failed to read stdin"),
+    };
+    let requests = match input.and_then(|json| reply_batch::parse(&json)) {
+        Ok(requests) => requests,
+        Err(e) => {
+            eprintln!("Error: {:#}", e);
+            std::process::exit(1);
+        }
+    };
+
+    println!(
+        "Posting {} replies on {}/{}#{}",
+        requests.len(),
+        pr_context.owner,
+        pr_context.repo,
+        pr_context.pr_number
+    );
+    let results = reply_batch::post_all(&RealThreadsClient, &mutations.reply_client(), &requests);
+    let mut failed = 0;
+    let mut newer: Vec<(String, Vec<threads::ThreadComment>)> = Vec::new();
+    for (request, result) in requests.iter().zip(results) {
+        match result {
+            Ok(ReplyOutcome::Posted(posted)) => {
+                let link = posted.result.url.as_deref().map(|u| format!(", {}", u)).unwrap_or_default();
+                println!(
+                    "✓ {}: replied in thread {} (comment ID: {}{})",
+                    request.in_reply_to, posted.thread_id, posted.result.comment_id, link
+                );
+                if let Some(Err(e)) = &posted.re_resolved {
+                    eprintln!("Warning: Failed to re-resolve thread {}: {}", posted.thread_id, e);
+                }
+                if !posted.newer_comments.is_empty() {
+                    newer.push((posted.thread_id, posted.newer_comments));
+                }
+            }
+            Ok(ReplyOutcome::SkippedResolved { thread_id }) => println!(
+                "- {}: thread {} was resolved in the meantime; not replying",
+                request.in_reply_to, thread_id
+            ),
+            Err(e) => {
+                println!("✗ {}: {:#}", request.in_reply_to, e);
+                failed += 1;
+            }
+        }
+    }
+    for (thread_id, comments) in &newer {
+        print_newer_comments(comments, thread_id);
+    }
+    web::poke_running_server(pr_context);
+    if failed > 0 {
+        eprintln!("Error: {} of {} replies failed.", failed, requests.len());
+        std::process::exit(1);
+    }
+}
+
+/// Run the `resolve` subcommand: resolve the given threads, and the threads
+/// containing the given comments.
+fn run_resolve_command(
+    pr_context: &PrContext,
+    mutations: &MutationClients,
+    thread_ids: &[String],
+    comment_ids: &[String],
+) {
+    let threads = match RealThreadsClient.fetch_threads(&pr_context.owner, &pr_context.repo, pr_context.pr_number) {
+        Ok(threads) => threads,
+        Err(e) => {
+            eprintln!("Error: Failed to fetch review threads: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    let mut targets: Vec<&ReviewThread> = Vec::new();
+    let mut failed = false;
+    let found = thread_ids
+        .iter()
+        .map(|id| (id, threads.iter().find(|t| &t.id == id), "Thread"))
+        .chain(comment_ids.iter().map(|id| {
+            (id, threads.iter().find(|t| t.comments.iter().any(|c| &c.id == id)), "Comment")
+        }));
+    for (id, thread, kind) in found {
+        match thread {
+            Some(thread) if !targets.iter().any(|t| t.id == thread.id) => targets.push(thread),
+            Some(_) => {}
+            None => {
+                eprintln!("Error: {} {} isn't in a review thread on this PR.", kind, id);
+                failed = true;
+            }
+        }
+    }
+
+    let reply_client = mutations.reply_client();
+    for thread in targets {
+        if thread.is_resolved {
+            println!("Thread {} ({}) is already resolved", thread.id, thread.location());
+            continue;
+        }
+        match reply_client.resolve_thread(&thread.id) {
+            Ok(()) => println!("✓ Resolved thread {} ({})", thread.id, thread.location()),
+            Err(e) => {
+                eprintln!("Error: Failed to resolve thread {}: {}", thread.id, e);
+                failed = true;
+            }
+        }
+    }
+    web::poke_running_server(pr_context);
+    if failed {
+        std::process::exit(1);
+    }
+}
+
+fn run_clean_threads_command(pr_context: &PrContext, mutations: &MutationClients) {
+    let threads_client = RealThreadsClient;
+
+    println!("Deleting resolved pure-Claude threads...");
+    match threads_client.fetch_threads(&pr_context.owner, &pr_context.repo, pr_context.pr_number) {
+        Ok(threads) => {
+            // Delete pure-Claude threads first, before stripping paperclips.
+            // This ordering matters: if we stripped paperclips first and then
+            // deletion failed midway, a retry would no longer detect the
+            // paperclip threads and might incorrectly delete them.
+            let pure_claude_threads: Vec<_> = threads
+                .iter()
+                .filter(|t| !t.has_paperclip() && t.is_resolved && t.is_pure_claude())
+                .collect();
 
-            println!("To reply, use:");
-            println!(
-                "  pr-loop reply --in-reply-to <COMMENT_ID> --message \"Your response\""
-            );
-            println!();
-            println!("The --in-reply-to should be the ID of the last comment shown above.");
-            println!(
-                "Your message will be prefixed with \"{}\"",
-                CLAUDE_MARKER
-            );
+            if pure_claude_threads.is_empty() {
+                println!("  (no resolved pure-Claude threads found)");
+            } else {
+                let comment_ids: Vec<&str> = pure_claude_threads
+                    .iter()
+                    .flat_map(|t| t.comment_ids())
+                    .collect();
 
-            if *also_has_ci_failures {
-                println!();
+                let (deleted, failed) = delete_comments_parallel(&comment_ids, 10, mutations);
                 println!(
-                    "⚠ Note: {} CI check(s) have also failed.",
-                    checks.failed().len()
+                    "✓ Deleted {} comment(s) from {} pure-Claude thread(s)",
+                    deleted,
+                    pure_claude_threads.len()
                 );
+                if failed > 0 {
+                    eprintln!("  ({} deletion(s) failed)", failed);
+                }
             }
-            if *ci_pending {
-                println!();
-                println!("○ Note: {} CI check(s) are still pending.", checks.pending().len());
-            }
+
+            // Strip paperclip markers (these threads are preserved for human review)
+            strip_paperclips(&threads, mutations);
+        }
+        Err(e) => {
+            eprintln!("Error: Failed to fetch threads: {}", e);
+            std::process::exit(1);
         }
+    }
+}
 
-        NextAction::FixCiFailures { failed_check_names } => {
-            println!("## ACTION REQUIRED: Fix CI failures");
-            println!();
-            println!(
-                "The following {} check{} failed:",
-                failed_check_names.len(),
-                if failed_check_names.len() == 1 { "" } else { "s" }
-            );
-            for name in failed_check_names {
-                println!("  ✗ {}", name);
-            }
+/// Run the `file-followups` subcommand: file an issue for each paperclip
+/// thread not already filed, and reply on the thread with its link.
+fn run_file_followups_command(
+    pr_context: &PrContext,
+    mutations: &MutationClients,
+    issues: &dyn IssueCreator,
+    dry_run: bool,
+) {
+    let threads = match RealThreadsClient.fetch_threads(
+        &pr_context.owner,
+        &pr_context.repo,
+        pr_context.pr_number,
+    ) {
+        Ok(t) => t,
+        Err(e) => {
+            eprintln!("Error: Failed to fetch threads: {}", e);
+            std::process::exit(1);
+        }
+    };
+    let pending: Vec<_> = threads
+        .iter()
+        .filter(|t| t.has_paperclip() && !followups::already_filed(t))
+        .collect();
+    if pending.is_empty() {
+        println!("No paperclip threads need a follow-up issue.");
+        return;
+    }
 
-            if *mergeable_status == MergeableStatus::Conflicting {
-                println!();
-                println!("⚠ This PR has merge conflicts. Consider rebasing to resolve conflicts");
-                println!("  before investigating CI failures — some failures may be caused by the");
-                println!("  conflicts, and CI will re-run after rebasing anyway.");
-            }
+    let reply_client = mutations.reply_client();
+    let mut failed = 0;
+    for thread in pending {
+        let issue = followups::issue_for_thread(
+            thread,
+            &pr_context.owner,
+            &pr_context.repo,
+            pr_context.pr_number,
+        );
+        if dry_run {
+            println!("## {} (thread {})
 
-            // Show CircleCI test failures if available (structured, most useful)
-            if !circleci_info.test_failures.is_empty() {
-                println!();
-                println!("## CI Test Failures");
-                print_test_failures(&circleci_info.test_failures);
+{}", issue.title, thread.id, issue.body);
+            continue;
+        }
+        let url = match issues.create_issue(&pr_context.owner, &pr_context.repo, &issue) {
+            Ok(url) => url,
+            Err(e) => {
+                eprintln!("Warning: Failed to file an issue for thread {}: {}", thread.id, e);
+                failed += 1;
+                continue;
             }
+        };
+        mutations
+            .audit_log
+            .record("issue_created", &format!("thread={} issue={}", thread.id, url));
+        println!("✓ Filed {} for thread {}", url, thread.id);
+        let reply = format_claude_reply(
+            Some(ReplyStatus::Followup),
+            &format!("{} {}", followups::FILED_REPLY_PREFIX, url),
+        );
+        if let Err(e) = reply_client.post_reply(&thread.id, &reply) {
+            // Without the reply a re-run would file a duplicate, so say so
+            eprintln!(
+                "Warning: Filed {} but failed to reply on thread {}: {}. Reply manually before re-running.",
+                url, thread.id, e
+            );
+            failed += 1;
+        }
+    }
+    if failed > 0 {
+        std::process::exit(1);
+    }
+}
 
-            // Show CircleCI step logs if available
-            if !circleci_info.step_logs.is_empty() {
-                println!();
-                println!("## CI Failure Logs");
-                print_step_logs(&circleci_info.step_logs);
-                println!();
-                println!("Analyze the errors above and push fixes to resolve them.");
-            } else if circleci_info.test_failures.is_empty() {
-                println!();
-                println!("Use the CircleCI MCP server to investigate the failures:");
-                println!("  - List recent pipelines for this project");
-                println!("  - Get job details and logs for the failed workflow");
-                println!();
-                println!("Then push fixes to resolve the issues.");
-            } else {
-                println!();
-                println!("Analyze the test failures above and push fixes to resolve them.");
+/// Group all checks per the collapse rules and keep the groups whose overall
+/// status is `status`.
+fn display_groups<'a>(
+    checks: &'a ChecksSummary,
+    rules: &CollapseRules,
+    status: CheckStatus,
+) -> Vec<CheckGroup<'a>> {
+    let all: Vec<&Check> = checks.checks.iter().collect();
+    group_checks(&all, rules)
+        .expect("collapse rules validated at startup")
+        .into_iter()
+        .filter(|g| g.status() == status)
+        .collect()
+}
+
+/// Format one line per check group. Matrix groups list only their failing
+/// variants underneath; passing shards stay collapsed into the counts.
+fn format_check_groups(groups: &[CheckGroup], symbol: &str) -> String {
+    let mut out = String::new();
+    for group in groups {
+        out.push_str(&format!("  {} {}\n", symbol, group.label()));
+        if group.is_matrix() {
+            for variant in group.with_status(&CheckStatus::Fail) {
+                out.push_str(&format!("      ✗ {}\n", variant.display_name()));
             }
         }
+    }
+    out
+}
 
-        NextAction::WaitForCi { pending_check_names } => {
-            println!("## WAITING: CI checks in progress");
-            println!();
+/// Run the `checks` subcommand: show CI check status and failure logs.
+/// Report the commit where a check went from green to red, with the files
+/// changed since the last green commit.
+fn run_ci_bisect_command(pr_context: &PrContext, check: &str, max_commits: u64) {
+    let (owner, repo, pr_number) = (&pr_context.owner, &pr_context.repo, pr_context.pr_number);
+
+    let history = match RealChecksClient.fetch_check_history(owner, repo, pr_number, max_commits) {
+        Ok(h) => h,
+        Err(e) => {
+            eprintln!("Error: Failed to fetch check history: {}", e);
+            std::process::exit(1);
+        }
+    };
+    let result = match bisect(&history, check) {
+        Ok(r) => r,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    println!("# CI Bisect: `{}` on {}/{}#{}", check, owner, repo, pr_number);
+    println!();
+    let describe = |i: usize| format!("`{}` {}", history[i].abbreviated_sha, history[i].headline);
+
+    let (last_green, first_red) = match result {
+        BisectResult::NeverRan => {
             println!(
-                "The following {} check{} still running:",
-                pending_check_names.len(),
-                if pending_check_names.len() == 1 { " is" } else { "s are" }
+                "No pass/fail result for `{}` on the last {} commit(s).",
+                check,
+                history.len()
             );
-            for name in pending_check_names {
-                println!("  ○ {}", name);
-            }
-            println!();
-            println!("No action needed. Wait for CI to complete.");
+            return;
+        }
+        BisectResult::Passing { latest } => {
+            println!("✓ `{}` passed on the latest commit that ran it: {}", check, describe(latest));
+            return;
         }
+        BisectResult::Broken {
+            last_green,
+            first_red,
+        } => (last_green, first_red),
+    };
 
-        NextAction::PrReady => {
-            println!("## PR READY");
-            println!();
-            println!("✓ All CI checks passed");
-            println!("✓ No unaddressed review comments");
-            println!();
-            println!("The PR is ready for merge or further review.");
+    match last_green {
+        Some(g) => println!("✓ Last green: {}", describe(g)),
+        None => println!(
+            "✓ Last green: none in the last {} commit(s) (try a larger --max-commits)",
+            history.len()
+        ),
+    }
+    println!("✗ First red:  {}", describe(first_red));
+
+    let suspects = result.suspects();
+    if suspects.len() > 1 {
+        println!();
+        println!("The check didn't run on every commit in between, so any of these may be responsible:");
+        for i in suspects {
+            println!("- {}", describe(i));
         }
     }
-}
 
-/// Print newer comments that were posted while the LLM was working.
-fn print_newer_comments(comments: &[threads::ThreadComment], thread_id: &str) {
-    println!();
-    println!("## NEWER COMMENTS DETECTED");
+    let base = last_green.map(|g| history[g].sha.as_str());
+    let head = &history[first_red].sha;
     println!();
-    println!(
-        "The following {} comment{} {} posted to this thread while you were working.",
-        comments.len(),
-        if comments.len() == 1 { "" } else { "s" },
-        if comments.len() == 1 { "was" } else { "were" }
-    );
-    println!("Please address {} as well:", if comments.len() == 1 { "it" } else { "them" });
+    match base {
+        Some(_) => println!("## Changes since last green"),
+        None => println!("## Changes in first red commit"),
+    }
     println!();
+    match RealFilesClient.fetch_commit_files(owner, repo, base, head) {
+        Ok(files) => {
+            println!("{}", format_diff_stat(&files));
+            println!();
+            for f in &files {
+                println!("- `{}` +{} −{}", f.path, f.additions, f.deletions);
+            }
+        }
+        Err(e) => eprintln!("Warning: Failed to fetch changed files: {}", e),
+    }
+}
 
-    for (i, comment) in comments.iter().enumerate() {
-        println!("### Comment {} (in thread {})", i + 1, thread_id);
-        println!("**@{}:**", comment.author);
-        for line in comment.body.lines() {
-            println!("> {}", line);
+/// Re-run the CircleCI workflows behind failed checks, optionally waiting
+/// for the new workflows. `wait` is (timeout, poll interval) in seconds.
+/// Run the `repro` subcommand: run each failed check matching `check`
+/// locally (in its CircleCI job's image with `docker`) and report whether
+/// its failure reproduces.
+fn run_repro_command(
+    creds: &Credentials,
+    pr_context: &PrContext,
+    check_filters: &CheckFilters,
+    check: &str,
+    docker: bool,
+    state_root: Option<&std::path::Path>,
+) {
+    let Some(root) = state_root else {
+        eprintln!("Error: `pr-loop repro` must be run inside the PR's repository.");
+        std::process::exit(1);
+    };
+    let pattern = match glob::Pattern::new(check) {
+        Ok(p) => p,
+        Err(e) => {
+            eprintln!("Error: Invalid check pattern `{}`: {}", check, e);
+            std::process::exit(1);
+        }
+    };
+    let checks_summary = match get_checks_summary(
+        &RealChecksClient,
+        &pr_context.owner,
+        &pr_context.repo,
+        pr_context.pr_number,
+        check_filters,
+    ) {
+        Ok(summary) => summary,
+        Err(e) => {
+            eprintln!("Error: Failed to fetch checks: {}", e);
+            std::process::exit(1);
+        }
+    };
+    let failed: Vec<&Check> = checks_summary
+        .failed()
+        .into_iter()
+        .filter(|c| pattern.matches(&c.name))
+        .collect();
+    if failed.is_empty() {
+        eprintln!("Error: No failed check matches `{}`.", check);
+        std::process::exit(1);
+    }
+
+    let cfg = config::load();
+    let toolchain = repro::Toolchain::detect(root);
+    let mut reproduced = Vec::new();
+    let mut passed = Vec::new();
+    for failed_check in failed {
+        let name = &failed_check.name;
+        let info = fetch_ci_failure_info(creds, &ChecksSummary { checks: vec![failed_check.clone()] });
+        let Some(commands) =
+            repro::commands_for(cfg.repro_command(name), toolchain, name, &info.test_failures)
+        else {
+            eprintln!("Error: Don't know how to run `{}` locally.", name);
+            eprintln!("Add a command for it to the config, e.g.:");
+            eprintln!("  [repro.commands]");
+            eprintln!("  \"{}\" = \"make test {{tests}}\"", name);
+            std::process::exit(1);
+        };
+
+        let image = info.jobs.iter().find_map(|j| j.image.as_deref());
+        if docker && image.is_none() {
+            eprintln!("Error: No Docker image known for `{}`.", name);
+            eprintln!("`--docker` needs a CircleCI job that runs on the docker executor.");
+            std::process::exit(1);
+        }
+
+        match image.filter(|_| docker) {
+            Some(image) => println!("## {} (in {})", name, image),
+            None => println!("## {}", name),
+        }
+        let mut failed_locally = false;
+        for command in &commands {
+            println!("$ {}", command);
+            let result = match image.filter(|_| docker) {
+                Some(image) => repro::run_in_image(command, root, image),
+                None => repro::run(command, root),
+            };
+            match result {
+                Ok(ok) => failed_locally |= !ok,
+                Err(e) => {
+                    eprintln!("Error: {:#}", e);
+                    std::process::exit(1);
+                }
+            }
         }
         println!();
+        if failed_locally {
+            reproduced.push(name.clone());
+        } else {
+            passed.push(name.clone());
+        }
+    }
+
+    for name in &reproduced {
+        println!("✗ `{}` fails locally too: the failure reproduces.", name);
+    }
+    for name in &passed {
+        println!(
+            "✓ `{}` passes locally: the failure didn't reproduce. It may depend on CI's environment, or be flaky.",
+            name
+        );
+    }
+    if !reproduced.is_empty() {
+        std::process::exit(1);
     }
 }
 
-/// Print structured test failures, grouped by job name.
-fn print_test_failures(failures: &[circleci::TestFailure]) {
-    println!();
-    println!(
-        "{} test failure{}:",
-        failures.len(),
-        if failures.len() == 1 { "" } else { "s" }
-    );
+/// How `rerun --wait` waits for the new workflows.
+struct RerunWait<'a> {
+    timeout: std::time::Duration,
+    poll_interval: std::time::Duration,
+    cancel: Option<&'a CancelFile>,
+}
 
-    // Group by job name, preserving order of first appearance
-    let mut job_order: Vec<&str> = Vec::new();
-    let mut by_job: std::collections::HashMap<&str, Vec<&circleci::TestFailure>> =
-        std::collections::HashMap::new();
-    for failure in failures {
-        let entry = by_job.entry(failure.job_name.as_str()).or_default();
-        if entry.is_empty() {
-            job_order.push(&failure.job_name);
+fn run_rerun_command(
+    creds: &Credentials,
+    pr_context: &PrContext,
+    check_filters: &CheckFilters,
+    audit_log: &AuditLog,
+    failed_only: bool,
+    wait: Option<RerunWait>,
+    bust_caches: bool,
+) {
+    let checks_summary = match get_checks_summary(
+        &RealChecksClient,
+        &pr_context.owner,
+        &pr_context.repo,
+        pr_context.pr_number,
+        check_filters,
+    ) {
+        Ok(summary) => summary,
+        Err(e) => {
+            eprintln!("Error: Failed to fetch checks: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    // Group failed CircleCI jobs by org, since tokens can be per-org
+    let mut jobs_by_org: Vec<(String, Vec<CircleCiJobInfo>)> = Vec::new();
+    let mut gh_runs: Vec<u64> = Vec::new();
+    for check in checks_summary.failed() {
+        let url = check.url.as_deref();
+        if let Some(job) = url.and_then(parse_circleci_url) {
+            match jobs_by_org.iter_mut().find(|(org, _)| *org == job.owner) {
+                Some((_, jobs)) => jobs.push(job),
+                None => jobs_by_org.push((job.owner.clone(), vec![job])),
+            }
+        } else if let Some(job) = url.and_then(gh_actions::parse_gh_actions_url)
+            && bust_caches
+        {
+            if !gh_runs.contains(&job.run_id) {
+                gh_runs.push(job.run_id);
+            }
+        } else {
+            eprintln!("Skipping {} (not a CircleCI job)", check.name);
         }
-        entry.push(failure);
     }
 
-    for job_name in &job_order {
-        println!();
-        println!("### Job: {}", job_name);
-        for failure in &by_job[job_name] {
-            println!();
-            println!("- **{}** / {}", failure.classname, failure.test_name);
-            if !failure.message.is_empty() {
-                println!("  ```");
-                // Truncate long messages (stack traces can be very long)
-                let msg = truncate_log(&failure.message, 500);
-                for line in msg.lines() {
-                    println!("  {}", line);
+    if bust_caches {
+        bust_caches_and_rerun_actions(pr_context, audit_log, &gh_runs);
+        if !jobs_by_org.is_empty() {
+            println!(
+                "Note: CircleCI caches can't be deleted. If a corrupt cache was saved, bump its key \
+                 (e.g. v1-deps- → v2-deps-) in .circleci/config.yml."
+            );
+        }
+    }
+    if jobs_by_org.is_empty() {
+        if gh_runs.is_empty() {
+            println!("No failed CircleCI jobs to re-run.");
+        }
+        return;
+    }
+
+    let mut new_workflows: Vec<(RealCircleCiClient, String)> = Vec::new();
+    for (org, jobs) in jobs_by_org {
+        let Some(token) = creds.circleci_token_for(&org) else {
+            eprintln!("Error: No CircleCI token for org '{}'. Set CIRCLECI_TOKEN.", org);
+            std::process::exit(1);
+        };
+        let client = RealCircleCiClient::new(token.to_string());
+        match rerun_failed_workflows(&client, &jobs, failed_only) {
+            Ok(reruns) => {
+                for (old, new) in reruns {
+                    println!(
+                        "↻ Re-running {} of workflow {} (new workflow {})",
+                        if failed_only { "failed jobs" } else { "all jobs" },
+                        old,
+                        new
+                    );
+                    audit_log.record(
+                        "ci_rerun",
+                        &format!("workflow={} new_workflow={} failed_only={}", old, new, failed_only),
+                    );
+                    new_workflows.push((RealCircleCiClient::new(token.to_string()), new));
                 }
-                println!("  ```");
             }
+            Err(e) => {
+                eprintln!("Error: Failed to re-run CircleCI workflows: {}", e);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    let Some(wait) = wait else {
+        return;
+    };
+    println!("Waiting for {} workflow(s) to finish...", new_workflows.len());
+    match wait_for_workflows(&new_workflows, wait.timeout, wait.poll_interval, wait.cancel) {
+        WorkflowsOutcome::Finished(finished) => {
+            let mut all_passed = true;
+            for wf in &finished {
+                let symbol = if wf.status == "success" { "✓" } else { "✗" };
+                println!("{} {} ({}): {}", symbol, wf.name, wf.id, wf.status);
+                all_passed &= wf.status == "success";
+            }
+            std::process::exit(if all_passed { 0 } else { 1 });
+        }
+        WorkflowsOutcome::Timeout => {
+            eprintln!("Timeout reached before the re-run workflows finished.");
+            std::process::exit(wait::TIMEOUT_EXIT_CODE);
+        }
+        WorkflowsOutcome::Cancelled => {
+            eprintln!("Wait for the re-run workflows cancelled.");
+            std::process::exit(wait::CANCELLED_EXIT_CODE);
+        }
+    }
+}
+
+/// Delete the PR's GitHub Actions caches and re-run the failed jobs of the
+/// given workflow runs. Exits 1 on failure.
+fn bust_caches_and_rerun_actions(pr_context: &PrContext, audit_log: &AuditLog, runs: &[u64]) {
+    let client = RealActionsCacheClient;
+    let (owner, repo) = (&pr_context.owner, &pr_context.repo);
+    match clear_pr_caches(&client, owner, repo, pr_context.pr_number) {
+        Ok(n) => {
+            println!("🗑 Deleted {} GitHub Actions cache(s) for this PR.", n);
+            audit_log.record("cache_clear", &format!("deleted={}", n));
+        }
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+    }
+    for run_id in runs {
+        if let Err(e) = client.rerun_failed_jobs(owner, repo, *run_id) {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
         }
+        println!("↻ Re-running failed jobs of GitHub Actions run {}", run_id);
+        audit_log.record("ci_rerun", &format!("actions_run={} failed_only=true", run_id));
     }
 }
 
-/// Print step log details.
-fn print_step_logs(logs: &[FailedStepLog]) {
-    for log in logs {
-        println!();
-        println!("### Job: {} / Step: {}", log.job_name, log.step_name);
-        if !log.error.is_empty() {
-            println!();
-            println!("**Stderr:**");
-            println!("```");
-            let error_truncated = truncate_log(&log.error, 2000);
-            println!("{}", error_truncated);
-            println!("```");
+/// Bring the local checkout up to date with the PR head, warning about
+/// local commits that aren't on the PR.
+fn run_checkout_command(pr_context: &PrContext) {
+    let git = RealCheckoutGit;
+    let (owner, repo, pr_number) = (&pr_context.owner, &pr_context.repo, pr_context.pr_number);
+
+    let head = match checkout::fetch_pr_head(owner, repo, pr_number) {
+        Ok(head) => head,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
         }
-        if !log.output.is_empty() {
-            println!();
-            println!("**Stdout (last lines):**");
-            println!("```");
-            // Gradle/Java failures often end with ~15 lines of "Try: Run
-            // with --stacktrace / BUILD FAILED / Publishing Build Scan"
-            // boilerplate, so keep a decently sized tail so the actual
-            // error is visible above it.
-            let output_truncated = truncate_log_tail(&log.output, 4000);
-            println!("{}", output_truncated);
-            println!("```");
+    };
+    let remote = match git.remotes() {
+        Ok(remotes) => pick_remote(&remotes, owner, repo),
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
         }
-    }
-}
+    };
 
-/// Truncate a log string to a maximum length, from the beginning.
-fn truncate_log(s: &str, max_len: usize) -> String {
-    if s.len() <= max_len {
-        s.to_string()
-    } else {
-        format!("{}...\n[truncated, {} more bytes]", &s[..max_len], s.len() - max_len)
+    let short = |sha: &str| sha.chars().take(7).collect::<String>();
+    match sync_checkout(&git, &head, &remote, pr_number) {
+        Ok(SyncOutcome::UpToDate) => {
+            println!("✓ '{}' is up to date with the PR.", head.branch);
+        }
+        Ok(SyncOutcome::Created) => {
+            println!("✓ Checked out '{}' at {}.", head.branch, short(&head.sha));
+        }
+        Ok(SyncOutcome::FastForwarded { from }) => {
+            println!(
+                "✓ Fast-forwarded '{}' from {} to {}.",
+                head.branch,
+                short(&from),
+                short(&head.sha)
+            );
+        }
+        Ok(SyncOutcome::LocalAhead) => {
+            println!(
+                "⚠ '{}' has local commits that aren't on the PR yet. Push them before",
+                head.branch
+            );
+            println!("  waiting on CI or reviewers.");
+            return;
+        }
+        Ok(SyncOutcome::Diverged { local }) => {
+            eprintln!(
+                "⚠ '{}' ({}) has diverged from the PR head ({}). The PR was likely",
+                head.branch,
+                short(&local),
+                short(&head.sha)
+            );
+            eprintln!("  force-pushed or updated elsewhere. Left the branch untouched; rebase");
+            eprintln!("  local work onto the PR head (or reset to it) before editing.");
+            std::process::exit(1);
+        }
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
     }
-}
 
-/// Truncate a log string to show only the tail (last lines).
-fn truncate_log_tail(s: &str, max_len: usize) -> String {
-    if s.len() <= max_len {
-        s.to_string()
-    } else {
-        let start = s.len() - max_len;
-        // Find the next newline to start on a line boundary
-        let start = s[start..].find('\n').map(|i| start + i + 1).unwrap_or(start);
-        format!("[... {} bytes truncated]\n{}", start, &s[start..])
+    // Verify against GitHub's view of the head; a push may have landed
+    // between looking it up and fetching.
+    match git.resolve("HEAD") {
+        Ok(Some(sha)) if sha == head.sha => {}
+        Ok(local) => {
+            eprintln!(
+                "⚠ HEAD ({}) doesn't match the PR head on GitHub ({}). Re-run `pr-loop checkout`.",
+                local.as_deref().map(short).unwrap_or_default(),
+                short(&head.sha)
+            );
+            std::process::exit(1);
+        }
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
     }
 }
 
-/// Update the PR description with a status block.
-fn update_pr_status(
-    pr_client: &dyn PrClient,
+/// Print a single briefing document with everything needed to start an
+/// iteration: description, linked issues, diff stat, actionable threads,
+/// failing checks with their errors, and the next action.
+fn run_context_command(
+    creds: &Credentials,
+    pr_client: &impl PrClient,
     pr_context: &PrContext,
-    status_message: Option<&str>,
-) -> anyhow::Result<()> {
-    let current_body = pr_client.get_body(&pr_context.owner, &pr_context.repo, pr_context.pr_number)?;
-    let new_body = update_body_with_status(&current_body, status_message);
-    pr_client.set_body(&pr_context.owner, &pr_context.repo, pr_context.pr_number, &new_body)?;
-    eprintln!("✓ Updated PR status block");
-    Ok(())
-}
+    check_filters: &CheckFilters,
+    thread_order: ThreadOrder,
+    format: OutputFormat,
+    output_version: u32,
+) {
+    let (owner, repo, pr_number) = (&pr_context.owner, &pr_context.repo, pr_context.pr_number);
+    let cfg = config::load();
 
-/// Delete a batch of comments in parallel with bounded concurrency.
-/// Returns (success_count, failure_count).
-fn delete_comments_parallel(comment_ids: &[&str], max_concurrent: usize) -> (usize, usize) {
-    use std::sync::atomic::{AtomicUsize, Ordering};
-    use std::sync::Arc;
+    let (info, body, files, checks_result, threads_result, linked_issues, mergeable, rebase) = std::thread::scope(|s| {
+        let info = s.spawn(|| RealCommitsClient.fetch_pr_info(owner, repo, pr_number));
+        let files = s.spawn(|| fetch_changed_files(pr_context));
+        let checks = s.spawn(|| {
+            get_checks_summary(&RealChecksClient, owner, repo, pr_number, check_filters)
+        });
+        let threads = s.spawn(|| RealThreadsClient.fetch_threads(owner, repo, pr_number));
+        let linked_issues = s.spawn(|| fetch_linked_issues(pr_context));
+        let mergeable = s.spawn(|| RealChecksClient.fetch_mergeable_status(owner, repo, pr_number));
+        let rebase = s.spawn(|| check_rebase(pr_context, cfg.rebase_threshold()));
+        let body = pr_client.get_body(owner, repo, pr_number);
+        (
+            concurrent::join(info),
+            body,
+            concurrent::join(files),
+            concurrent::join(checks),
+            concurrent::join(threads),
+            concurrent::join(linked_issues),
+            concurrent::join(mergeable),
+            concurrent::join(rebase),
+        )
+    });
+    let info = match info {
+        Ok(info) => info,
+        Err(e) => {
+            eprintln!("Error: Failed to fetch PR: {}", e);
+            std::process::exit(1);
+        }
+    };
+    let body = body.unwrap_or_else(|e| {
+        eprintln!("Warning: Failed to fetch PR description: {}", e);
+        String::new()
+    });
+    let checks_summary = checks_result.unwrap_or_else(|e| {
+        eprintln!("Warning: Failed to fetch checks: {}", e);
+        ChecksSummary { checks: vec![] }
+    });
+    let threads = threads_result.unwrap_or_else(|e| {
+        eprintln!("Warning: Failed to fetch review threads: {}", e);
+        vec![]
+    });
+    let mergeable_status = mergeable.unwrap_or_else(|e| {
+        eprintln!("Warning: Failed to fetch merge conflict status: {}", e);
+        MergeableStatus::Unknown
+    });
 
-    let success_count = Arc::new(AtomicUsize::new(0));
-    let failure_count = Arc::new(AtomicUsize::new(0));
+    let mut actions = analyze_actions(&checks_summary, threads, &mergeable_status, rebase.as_ref());
+    let mut action = actions.remove(0);
+    // Always tag: the briefing is for deciding what to do first.
+    triage_threads(&mut action, pr_context, true, thread_order);
+    let thread_summaries = match &action {
+        NextAction::RespondToComments { threads, .. } => {
+            threads.iter().map(ThreadSummary::new).collect()
+        }
+        _ => vec![],
+    };
 
-    // Process in chunks of max_concurrent
-    for chunk in comment_ids.chunks(max_concurrent) {
-        let handles: Vec<_> = chunk
+    let (circleci_info, annotations) = std::thread::scope(|s| {
+        let annotations = s.spawn(|| fetch_check_annotations(pr_context, &checks_summary));
+        let circleci_info = fetch_ci_failure_info(creds, &checks_summary);
+        (circleci_info, concurrent::join(annotations))
+    });
+
+    let mut failing = failing_checks(&checks_summary, &annotations, &circleci_info);
+    let toolchain = git::get_repo_root().ok().and_then(|root| repro::Toolchain::detect(&root));
+    context::add_suggested_commands(&mut failing, &circleci_info, &cfg, toolchain);
+
+    let briefing = Briefing {
+        schema_version: context::SCHEMA_VERSION,
+        pr: PrSummary {
+            owner: owner.clone(),
+            repo: repo.clone(),
+            number: pr_number,
+            title: info.title,
+            url: info.url,
+            body: remove_status_block(&body),
+        },
+        linked_issues: linked_issues
             .iter()
-            .map(|&id| {
-                let id = id.to_string();
-                let success = Arc::clone(&success_count);
-                let failure = Arc::clone(&failure_count);
-                std::thread::spawn(move || {
-                    let client = RealReplyClient;
-                    match client.delete_comment(&id) {
-                        Ok(()) => {
-                            success.fetch_add(1, Ordering::Relaxed);
-                        }
-                        Err(e) => {
-                            eprintln!("Warning: Failed to delete comment {}: {}", id, e);
-                            failure.fetch_add(1, Ordering::Relaxed);
-                        }
-                    }
-                })
-            })
-            .collect();
+            .map(|issue| IssueSummary::new(issue, owner, repo))
+            .collect(),
+        diff_stat: DiffStat::new(files),
+        threads: thread_summaries,
+        failing_checks: failing,
+        next_action: ActionSummary::new(&action),
+        actions: std::iter::once(&action).chain(&actions).map(ActionSummary::new).collect(),
+    };
 
-        for handle in handles {
-            handle.join().expect("thread panicked during comment deletion");
-        }
+    match format {
+        OutputFormat::Markdown => print!("{}", briefing.to_markdown()),
+        OutputFormat::Json => println!("{}", briefing.to_json(output_version)),
     }
-
-    (
-        success_count.load(Ordering::Relaxed),
-        failure_count.load(Ordering::Relaxed),
-    )
 }
 
-/// Strip the paperclip marker from comments in paperclip threads.
-/// These threads are preserved for human review; the marker is removed so the
-/// human reviewer sees the comments without the marker noise.
-fn strip_paperclips(threads: &[ReviewThread]) {
-    let paperclip_threads: Vec<_> = threads.iter().filter(|t| t.has_paperclip()).collect();
-
-    if paperclip_threads.is_empty() {
+/// Run the `tasks` subcommand: print actionable threads as structured tasks.
+/// Run the `threads` subcommand: every review thread with its classification
+/// and whether the analysis counts it.
+fn run_threads_command(
+    pr_context: &PrContext,
+    state_root: &Option<std::path::PathBuf>,
+    cfg: &config::Config,
+    json: bool,
+) {
+    let threads = match RealThreadsClient.fetch_threads(&pr_context.owner, &pr_context.repo, pr_context.pr_number) {
+        Ok(t) => t,
+        Err(e) => {
+            eprintln!("Error: Failed to fetch review threads: {}", e);
+            std::process::exit(1);
+        }
+    };
+    let entries = explain_threads(
+        pr_context,
+        &threads,
+        &load_snoozes(state_root),
+        cfg.mentioned_others_actionable(),
+        cfg.author_thread_policy(),
+    );
+    let rows = thread_list::rows(&threads, &entries);
+    if !json {
+        print!("{}", thread_list::render(&rows));
         return;
     }
-
-    let client = RealReplyClient;
-    let mut updated = 0;
-    let mut failed = 0;
-
-    for thread in &paperclip_threads {
-        for comment in &thread.comments {
-            if comment.body.contains(PAPERCLIP_SHORTCODE)
-                || comment.body.contains(PAPERCLIP_EMOJI)
-            {
-                let new_body = comment
-                    .body
-                    .replace(PAPERCLIP_SHORTCODE, "")
-                    .replace(PAPERCLIP_EMOJI, "");
-                match client.update_comment(&comment.id, &new_body) {
-                    Ok(()) => updated += 1,
-                    Err(e) => {
-                        eprintln!(
-                            "Warning: Failed to strip paperclip from comment {}: {}",
-                            comment.id, e
-                        );
-                        failed += 1;
-                    }
-                }
-            }
+    match serde_json::to_string_pretty(&rows) {
+        Ok(json) => println!("{}", json),
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
         }
     }
-
-    if updated > 0 {
-        println!(
-            "✓ Stripped paperclip marker from {} comment(s) in {} thread(s)",
-            updated,
-            paperclip_threads.len()
-        );
-    }
-    if failed > 0 {
-        eprintln!("  ({} update(s) failed)", failed);
-    }
 }
 
-/// Run the `clean-threads` subcommand: delete resolved pure-Claude threads.
-fn run_clean_threads_command(pr_context: &PrContext) {
-    let threads_client = RealThreadsClient;
-
-    println!("Deleting resolved pure-Claude threads...");
-    match threads_client.fetch_threads(&pr_context.owner, &pr_context.repo, pr_context.pr_number) {
-        Ok(threads) => {
-            // Delete pure-Claude threads first, before stripping paperclips.
-            // This ordering matters: if we stripped paperclips first and then
-            // deletion failed midway, a retry would no longer detect the
-            // paperclip threads and might incorrectly delete them.
-            let pure_claude_threads: Vec<_> = threads
-                .iter()
-                .filter(|t| !t.has_paperclip() && t.is_resolved && t.is_pure_claude())
-                .collect();
-
-            if pure_claude_threads.is_empty() {
-                println!("  (no resolved pure-Claude threads found)");
-            } else {
-                let comment_ids: Vec<&str> = pure_claude_threads
-                    .iter()
-                    .flat_map(|t| t.comment_ids())
-                    .collect();
-
-                let (deleted, failed) = delete_comments_parallel(&comment_ids, 10);
-                println!(
-                    "✓ Deleted {} comment(s) from {} pure-Claude thread(s)",
-                    deleted,
-                    pure_claude_threads.len()
-                );
-                if failed > 0 {
-                    eprintln!("  ({} deletion(s) failed)", failed);
-                }
-            }
-
-            // Strip paperclip markers (these threads are preserved for human review)
-            strip_paperclips(&threads);
+fn run_tasks_command(pr_context: &PrContext, thread_order: ThreadOrder) {
+    let threads = match RealThreadsClient.fetch_threads(
+        &pr_context.owner,
+        &pr_context.repo,
+        pr_context.pr_number,
+    ) {
+        Ok(t) => t,
+        Err(e) => {
+            eprintln!("Error: Failed to fetch review threads: {}", e);
+            std::process::exit(1);
         }
+    };
+    let mut actionable = find_actionable_threads(threads);
+    // Always tag, like the briefing: the tag helps decide what to do first.
+    tag_threads(&mut actionable, &load_code_owners(pr_context));
+    if thread_order == ThreadOrder::Urgency {
+        sort_by_urgency(&mut actionable);
+    }
+    let tasks: Vec<Task> = actionable.iter().map(Task::new).collect();
+    match serde_json::to_string_pretty(&tasks) {
+        Ok(json) => println!("{}", json),
         Err(e) => {
-            eprintln!("Error: Failed to fetch threads: {}", e);
+            eprintln!("Error: {}", e);
             std::process::exit(1);
         }
     }
 }
 
-/// Run the `checks` subcommand: show CI check status and failure logs.
 fn run_checks_command(
     creds: &Credentials,
     pr_context: &PrContext,
-    include_checks: &[String],
-    exclude_checks: &[String],
+    check_filters: &CheckFilters,
+    collapse_rules: &CollapseRules,
 ) {
     let checks_client = RealChecksClient;
-    let mergeable_client = RealMergeableClient;
 
     let checks_summary = match get_checks_summary(
         &checks_client,
         &pr_context.owner,
         &pr_context.repo,
         pr_context.pr_number,
-        include_checks,
-        exclude_checks,
+        check_filters,
     ) {
         Ok(summary) => summary,
         Err(e) => {
@@ -917,7 +4030,7 @@ fn run_checks_command(
         }
     };
 
-    let mergeable_status = match mergeable_client.fetch_mergeable_status(
+    let mergeable_status = match checks_client.fetch_mergeable_status(
         &pr_context.owner,
         &pr_context.repo,
         pr_context.pr_number,
@@ -945,35 +4058,25 @@ fn run_checks_command(
         return;
     }
 
-    // Group checks by status for display
-    let passed: Vec<_> = checks_summary
-        .checks
-        .iter()
-        .filter(|c| c.status == CheckStatus::Pass)
-        .collect();
+    // Group checks by status for display; matrix variants are collapsed into
+    // one logical check filed under its overall status.
     let failed = checks_summary.failed();
-    let pending = checks_summary.pending();
-    let skipped: Vec<_> = checks_summary
-        .checks
-        .iter()
-        .filter(|c| c.status == CheckStatus::Skipping)
-        .collect();
-    let cancelled: Vec<_> = checks_summary
-        .checks
-        .iter()
-        .filter(|c| c.status == CheckStatus::Cancelled)
-        .collect();
-
-    if !failed.is_empty() {
-        println!(
-            "## Failed ({})",
-            failed.len()
-        );
-        for check in &failed {
-            println!("  ✗ {}", check.name);
+    let sections = [
+        ("Failed", CheckStatus::Fail, "✗"),
+        ("Pending", CheckStatus::Pending, "○"),
+        ("Passed", CheckStatus::Pass, "✓"),
+        ("Skipped", CheckStatus::Skipping, "⊘"),
+        ("Cancelled", CheckStatus::Cancelled, "⊘"),
+    ];
+    for (title, status, symbol) in sections {
+        let groups = display_groups(&checks_summary, collapse_rules, status.clone());
+        if groups.is_empty() {
+            continue;
         }
+        println!("## {} ({})", title, groups.len());
+        print!("{}", format_check_groups(&groups, symbol));
         println!();
-        if mergeable_status == MergeableStatus::Conflicting {
+        if status == CheckStatus::Fail && mergeable_status == MergeableStatus::Conflicting {
             println!("⚠ This PR has merge conflicts. Consider rebasing to resolve conflicts");
             println!("  before investigating CI failures — some failures may be caused by the");
             println!("  conflicts, and CI will re-run after rebasing anyway.");
@@ -981,72 +4084,43 @@ fn run_checks_command(
         }
     }
 
-    if !pending.is_empty() {
-        println!(
-            "## Pending ({})",
-            pending.len()
-        );
-        for check in &pending {
-            println!("  ○ {}", check.name);
-        }
-        println!();
-    }
-
-    if !passed.is_empty() {
-        println!(
-            "## Passed ({})",
-            passed.len()
-        );
-        for check in &passed {
-            println!("  ✓ {}", check.name);
-        }
-        println!();
-    }
-
-    if !skipped.is_empty() {
-        println!(
-            "## Skipped ({})",
-            skipped.len()
-        );
-        for check in &skipped {
-            println!("  ⊘ {}", check.name);
-        }
-        println!();
-    }
-
-    if !cancelled.is_empty() {
-        println!(
-            "## Cancelled ({})",
-            cancelled.len()
-        );
-        for check in &cancelled {
-            println!("  ⊘ {}", check.name);
-        }
-        println!();
-    }
-
-    // Fetch and display CI failure info (CircleCI + GH Actions)
+    // Fetch and display CI failure info (annotations + CircleCI + GH Actions)
     if !failed.is_empty() {
+        let annotations = fetch_check_annotations(pr_context, &checks_summary);
+        if !annotations.is_empty() {
+            println!("## Check Annotations");
+            println!();
+            println!("{}", format_annotations(&annotations));
+        }
         let circleci_info = fetch_ci_failure_info(creds, &checks_summary);
         if !circleci_info.test_failures.is_empty() {
             println!("## CI Test Failures");
-            print_test_failures(&circleci_info.test_failures);
+            print!("{}", format_test_failures(&circleci_info.test_failures));
         }
         if !circleci_info.step_logs.is_empty() {
             println!("## CI Failure Logs");
-            print_step_logs(&circleci_info.step_logs);
+            print!("{}", format_step_logs(&circleci_info.step_logs, &circleci_info.test_failures));
         }
+        print!("{}", format_log_fetch_errors(&circleci_info.fetch_errors));
     }
 }
 
+/// The `ready` subcommand's flags; `--then-ready` uses the defaults.
+#[derive(Default)]
+struct ReadyOptions {
+    preserve_claude_threads: bool,
+    reviewers: Vec<String>,
+    force: bool,
+    post_stats: bool,
+}
+
 /// Run the `ready` subcommand.
 fn run_ready_command(
     pr_client: &dyn PrClient,
     pr_context: &PrContext,
-    include_checks: &[String],
-    exclude_checks: &[String],
-    preserve_claude_threads: bool,
-    reviewers: &[String],
+    check_filters: &CheckFilters,
+    options: &ReadyOptions,
+    mutations: &MutationClients,
 ) {
     let checks_client = RealChecksClient;
     let threads_client = RealThreadsClient;
@@ -1112,8 +4186,7 @@ fn run_ready_command(
         &pr_context.owner,
         &pr_context.repo,
         pr_context.pr_number,
-        include_checks,
-        exclude_checks,
+        check_filters,
     ) {
         Ok(s) => s,
         Err(e) => {
@@ -1122,12 +4195,30 @@ fn run_ready_command(
         }
     };
 
-    // Check for unresolved threads (ALL threads must be resolved, not just non-actionable)
-    if !snapshot.unresolved_thread_ids.is_empty() {
+    // Check for unresolved threads (ALL threads must be resolved, not just non-actionable).
+    // Threads only waiting on an answer to Claude's question can be let through with
+    // --force: marking the PR ready is often how the reviewer gets to see them.
+    let questions = snapshot.awaiting_input_thread_ids.len();
+    let unresolved = snapshot.unresolved_thread_ids.len() - questions;
+    if unresolved > 0 {
         eprintln!(
             "Error: PR has {} unresolved review thread(s). All threads must be resolved before marking ready.",
             snapshot.unresolved_thread_ids.len()
         );
+        if questions > 0 {
+            eprintln!(
+                "  {} of them end with a question from Claude that the reviewer hasn't answered.",
+                questions
+            );
+        }
+        std::process::exit(1);
+    }
+    if questions > 0 && !options.force {
+        eprintln!(
+            "Error: {} thread(s) are awaiting a reviewer's answer to Claude's question.",
+            questions
+        );
+        eprintln!("Re-run with `pr-loop ready --force` to mark the PR ready with them open.");
         std::process::exit(1);
     }
 
@@ -1150,15 +4241,25 @@ fn run_ready_command(
         std::process::exit(1);
     }
 
-    println!("✓ All threads resolved");
+    if questions > 0 {
+        println!(
+            "⚠ {} thread(s) still awaiting a reviewer's answer (--force)",
+            questions
+        );
+    } else {
+        println!("✓ All threads resolved");
+    }
     println!("✓ All CI checks passed");
 
+    // Gathered now: the cleanup below deletes the pure-Claude threads
+    let stats = stats::ThreadStats::from_threads(&snapshot.threads);
+
     // Step 4: Clean up threads (delete pure-Claude threads, then strip paperclips)
     // Deletion before stripping: if we stripped first and deletion failed midway,
     // a retry would no longer detect paperclip threads and might delete them.
     match threads_client.fetch_threads(&pr_context.owner, &pr_context.repo, pr_context.pr_number) {
         Ok(threads) => {
-            if !preserve_claude_threads {
+            if !options.preserve_claude_threads {
                 println!("Deleting pure-Claude threads...");
                 let pure_claude_threads: Vec<_> = threads
                     .iter()
@@ -1173,13 +4274,13 @@ fn run_ready_command(
                         .flat_map(|t| t.comment_ids())
                         .collect();
 
-                    let (deleted, _) = delete_comments_parallel(&comment_ids, 10);
+                    let (deleted, _) = delete_comments_parallel(&comment_ids, 10, mutations);
                     println!("✓ Deleted {} comment(s) from pure-Claude threads", deleted);
                 }
             }
 
             // Strip paperclip markers (these threads are preserved for human review)
-            strip_paperclips(&threads);
+            strip_paperclips(&threads, mutations);
         }
         Err(e) => {
             eprintln!("Warning: Failed to fetch threads for cleanup: {}", e);
@@ -1224,7 +4325,7 @@ fn run_ready_command(
     }
 
     // Step 7 (optional): Request review from specified reviewers
-    for username in reviewers {
+    for username in &options.reviewers {
         println!("Requesting review from @{}...", username);
         match pr_client.add_reviewer(&pr_context.owner, &pr_context.repo, pr_context.pr_number, username) {
             Ok(()) => {
@@ -1237,6 +4338,20 @@ fn run_ready_command(
         }
     }
 
+    println!();
+    print!("{}", stats.format_text());
+    if options.post_stats {
+        match pr_client.add_comment(
+            &pr_context.owner,
+            &pr_context.repo,
+            pr_context.pr_number,
+            &stats.format_markdown(),
+        ) {
+            Ok(()) => println!("✓ Posted thread statistics to the PR"),
+            Err(e) => eprintln!("Warning: Failed to post thread statistics: {}", e),
+        }
+    }
+
     println!();
     println!("🎉 PR is now ready for human review!");
 }
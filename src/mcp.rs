@@ -0,0 +1,335 @@
+// Model Context Protocol server.
+// `pr-loop mcp` speaks MCP over stdio (newline-delimited JSON-RPC), so Claude
+// Code can call pr-loop's operations as tools with typed arguments instead
+// of composing shell commands and parsing their output:
+//
+// - `analyze`: the `context --format json` briefing, as structured content.
+// - `reply`, `resolve`, `ready`: the subcommands of the same names.
+// - `checks`: CI status and failure logs.
+//
+// Each call runs this same binary as a subprocess with the `--repo` and
+// `--pr` the server was started with, so tools behave exactly like the
+// subcommands — same validation, policy, and audit log — and a failing one
+// (which exits the process) can't take the server down with it.
+//
+// `ready` doesn't take `--confirm`: with `[policy] require_confirm_for_ready`
+// the confirmation is meant to come from a human, not the agent.
+
+use anyhow::{bail, Context, Result};
+use serde_json::{json, Value};
+use std::io::{BufRead, Write};
+use std::process::{Command, Stdio};
+
+/// The protocol revision this server implements.
+const PROTOCOL_VERSION: &str = "2025-06-18";
+
+/// JSON-RPC error codes.
+const PARSE_ERROR: i64 = -32700;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+
+/// What running a tool's subcommand printed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ToolOutput {
+    pub stdout: String,
+    pub stderr: String,
+    pub success: bool,
+}
+
+/// Runs pr-loop subcommands for tool calls, allowing test implementations.
+pub trait ToolRunner {
+    fn run(&self, args: &[String]) -> Result<ToolOutput>;
+}
+
+/// Real runner that re-invokes the current executable.
+pub struct RealToolRunner {
+    /// Global arguments (`--repo`, `--pr`) put before every subcommand.
+    pub global_args: Vec<String>,
+}
+
+impl ToolRunner for RealToolRunner {
+    fn run(&self, args: &[String]) -> Result<ToolOutput> {
+        let exe = std::env::current_exe().context("current_exe")?;
+        let output = Command::new(exe)
+            .args(&self.global_args)
+            .args(args)
+            // stdin carries the protocol; the subcommand mustn't read it
+            .stdin(Stdio::null())
+            .output()
+            .context("Failed to run pr-loop")?;
+        Ok(ToolOutput {
+            stdout: String::from_utf8_lossy(&output.stdout).into_owned(),
+            stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+            success: output.status.success(),
+        })
+    }
+}
+
+/// The tools offered, as `tools/list` describes them.
+fn tools() -> Value {
+    json!([
+        {
+            "name": "analyze",
+            "description": "Analyze the PR: the recommended next action, actionable review threads with their diff hunks, failing checks with extracted errors, and the PR's description and linked issues.",
+            "inputSchema": {"type": "object", "properties": {}}
+        },
+        {
+            "name": "reply",
+            "description": "Reply to a review comment as Claude. If newer comments arrived in the thread, they're returned to address too.",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "in_reply_to": {"type": "string", "description": "The comment ID to reply to, as given by analyze."},
+                    "message": {"type": "string", "description": "The reply, without the Claude marker."},
+                    "status": {
+                        "type": "string",
+                        "enum": ["fixed", "question", "followup"],
+                        "description": "Whether the reply says the feedback is fixed, asks the reviewer a question, or defers to a follow-up."
+                    },
+                    "quote": {"type": "integer", "minimum": 1, "description": "Start the reply with a quote of the comment, trimmed to this many lines."},
+                    "suggestion": {"type": "string", "description": "Code to suggest in place of the thread's lines, which the reviewer can apply in one click."}
+                },
+                "required": ["in_reply_to", "message"]
+            }
+        },
+        {
+            "name": "resolve",
+            "description": "Resolve review threads, by thread ID or by the ID of a comment in them.",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "threads": {"type": "array", "items": {"type": "string"}},
+                    "comments": {"type": "array", "items": {"type": "string"}}
+                }
+            }
+        },
+        {
+            "name": "checks",
+            "description": "Show CI check status and failure logs.",
+            "inputSchema": {"type": "object", "properties": {}}
+        },
+        {
+            "name": "ready",
+            "description": "Mark the PR ready for review once it's happy (CI passing, every thread resolved): deletes Claude's resolved threads, removes the status block, and marks it non-draft.",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "reviewers": {"type": "array", "items": {"type": "string"}, "description": "GitHub users to request review from."},
+                    "force": {"type": "boolean", "description": "Go ahead while threads still wait on a reviewer's answer to Claude's question."}
+                }
+            }
+        }
+    ])
+}
+
+/// The subcommand arguments for calling `tool` with `arguments`. Values are
+/// passed as `--flag=value` so ones starting with `-` aren't taken for flags.
+pub fn tool_args(tool: &str, arguments: &Value) -> Result<Vec<String>> {
+    let string = |key: &str| arguments.get(key).and_then(Value::as_str);
+    let strings = |key: &str| -> Vec<&str> {
+        arguments
+            .get(key)
+            .and_then(Value::as_array)
+            .map(|a| a.iter().filter_map(Value::as_str).collect())
+            .unwrap_or_default()
+    };
+    let mut args = vec![tool.to_string()];
+    match tool {
+        "analyze" => args = vec!["context".to_string(), "--format=json".to_string()],
+        "checks" => {}
+        "reply" => {
+            let (Some(in_reply_to), Some(message)) = (string("in_reply_to"), string("message")) else {
+                bail!("reply needs in_reply_to and message");
+            };
+            args.push(format!("--in-reply-to={}", in_reply_to));
+            args.push(format!("--message={}", message));
+            if let Some(status) = string("status") {
+                args.push(format!("--status={}", status));
+            }
+            if let Some(quote) = arguments.get("quote").and_then(Value::as_u64) {
+                args.push(format!("--quote={}", quote));
+            }
+            if let Some(suggestion) = string("suggestion") {
+                args.push(format!("--suggestion={}", suggestion));
+            }
+        }
+        "resolve" => {
+            let (threads, comments) = (strings("threads"), strings("comments"));
+            if threads.is_empty() && comments.is_empty() {
+                bail!("resolve needs threads or comments");
+            }
+            args.extend(threads.iter().map(|t| format!("--thread={}", t)));
+            args.extend(comments.iter().map(|c| format!("--comment={}", c)));
+        }
+        "ready" => {
+            args.extend(strings("reviewers").iter().map(|r| format!("--reviewer={}", r)));
+            if arguments.get("force").and_then(Value::as_bool) == Some(true) {
+                args.push("--force".to_string());
+            }
+        }
+        _ => bail!("Unknown tool: {}", tool),
+    }
+    Ok(args)
+}
+
+/// The result of a tool call: what the subcommand printed, plus the parsed
+/// briefing for `analyze`.
+fn call_tool(runner: &dyn ToolRunner, tool: &str, arguments: &Value) -> Result<Value> {
+    let output = runner.run(&tool_args(tool, arguments)?)?;
+    let mut content = vec![json!({"type": "text", "text": output.stdout})];
+    if !output.success && !output.stderr.trim().is_empty() {
+        content.push(json!({"type": "text", "text": output.stderr}));
+    }
+    let mut result = json!({"content": content, "isError": !output.success});
+    if tool == "analyze" && output.success
+        && let Ok(briefing) = serde_json::from_str::<Value>(&output.stdout)
+    {
+        result["structuredContent"] = briefing;
+    }
+    Ok(result)
+}
+
+fn error_response(id: Value, code: i64, message: &str) -> Value {
+    json!({"jsonrpc": "2.0", "id": id, "error": {"code": code, "message": message}})
+}
+
+/// The response to one JSON-RPC message, or None for notifications.
+pub fn handle(message: &str, runner: &dyn ToolRunner) -> Option<Value> {
+    let Ok(request) = serde_json::from_str::<Value>(message) else {
+        return Some(error_response(Value::Null, PARSE_ERROR, "Parse error"));
+    };
+    // Notifications (like notifications/initialized) have no id and get no response
+    let id = request.get("id")?.clone();
+    let method = request.get("method").and_then(Value::as_str).unwrap_or_default();
+    let params = request.get("params").cloned().unwrap_or(Value::Null);
+    let result = match method {
+        "initialize" => json!({
+            "protocolVersion": PROTOCOL_VERSION,
+            "capabilities": {"tools": {}},
+            "serverInfo": {"name": "pr-loop", "version": env!("CARGO_PKG_VERSION")}
+        }),
+        "ping" => json!({}),
+        "tools/list" => json!({"tools": tools()}),
+        "tools/call" => {
+            let name = params.get("name").and_then(Value::as_str).unwrap_or_default();
+            let arguments = params.get("arguments").cloned().unwrap_or_else(|| json!({}));
+            match call_tool(runner, name, &arguments) {
+                Ok(result) => result,
+                Err(e) => return Some(error_response(id, INVALID_PARAMS, &format!("{:#}", e))),
+            }
+        }
+        _ => return Some(error_response(id, METHOD_NOT_FOUND, &format!("Unknown method: {}", method))),
+    };
+    Some(json!({"jsonrpc": "2.0", "id": id, "result": result}))
+}
+
+/// Serve requests from `input` until it closes, one JSON message per line.
+pub fn serve(input: impl BufRead, mut output: impl Write, runner: &dyn ToolRunner) -> Result<()> {
+    for line in input.lines() {
+        let line = line.context("Failed to read from stdin")?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        if let Some(response) = handle(&line, runner) {
+            writeln!(output, "{}", response)?;
+            output.flush()?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+
+    struct FakeRunner {
+        ran: RefCell<Vec<Vec<String>>>,
+        output: ToolOutput,
+    }
+
+    impl ToolRunner for FakeRunner {
+        fn run(&self, args: &[String]) -> Result<ToolOutput> {
+            self.ran.borrow_mut().push(args.to_vec());
+            Ok(self.output.clone())
+        }
+    }
+
+    #[test]
+    fn tool_calls_run_the_matching_subcommand() {
+        assert_eq!(
+            tool_args("reply", &json!({"in_reply_to": "PRRC_1", "message": "-1 is fine", "status": "fixed"})).unwrap(),
+            vec!["reply", "--in-reply-to=PRRC_1", "--message=-1 is fine", "--status=fixed"]
+        );
+        assert_eq!(
+            tool_args("resolve", &json!({"threads": ["PRRT_1"], "comments": ["PRRC_2"]})).unwrap(),
+            vec!["resolve", "--thread=PRRT_1", "--comment=PRRC_2"]
+        );
+        assert_eq!(
+            tool_args("ready", &json!({"reviewers": ["alice"], "force": true})).unwrap(),
+            vec!["ready", "--reviewer=alice", "--force"]
+        );
+        assert!(tool_args("resolve", &json!({})).is_err());
+        assert!(tool_args("merge", &json!({})).is_err());
+
+        let runner = FakeRunner {
+            ran: RefCell::new(vec![]),
+            output: ToolOutput {
+                stdout: r#"{"schema_version": 3}"#.to_string(),
+                stderr: String::new(),
+                success: true,
+            },
+        };
+        let call = r#"{"jsonrpc": "2.0", "id": 4, "method": "tools/call", "params": {"name": "analyze"}}"#;
+        let response = handle(call, &runner).unwrap();
+        assert_eq!(*runner.ran.borrow(), vec![vec!["context", "--format=json"]]);
+        assert_eq!(response["id"], 4);
+        assert_eq!(response["result"]["isError"], false);
+        assert_eq!(response["result"]["structuredContent"]["schema_version"], 3);
+    }
+
+    #[test]
+    fn speaks_the_protocol() {
+        let runner = FakeRunner {
+            ran: RefCell::new(vec![]),
+            output: ToolOutput {
+                stdout: String::new(),
+                stderr: "Error: Policy forbids resolving human threads\n".to_string(),
+                success: false,
+            },
+        };
+        let input = [
+            r#"{"jsonrpc": "2.0", "id": 1, "method": "initialize", "params": {}}"#,
+            r#"{"jsonrpc": "2.0", "method": "notifications/initialized"}"#,
+            r#"{"jsonrpc": "2.0", "id": 2, "method": "tools/list"}"#,
+            r#"{"jsonrpc": "2.0", "id": 3, "method": "tools/call", "params": {"name": "resolve", "arguments": {"threads": ["T"]}}}"#,
+            r#"{"jsonrpc": "2.0", "id": 5, "method": "resources/list"}"#,
+        ]
+        .join("\n");
+        let mut output = vec![];
+        serve(input.as_bytes(), &mut output, &runner).unwrap();
+        let responses: Vec<Value> = String::from_utf8(output)
+            .unwrap()
+            .lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect();
+
+        // No response to the notification
+        assert_eq!(responses.len(), 4);
+        assert_eq!(responses[0]["result"]["protocolVersion"], PROTOCOL_VERSION);
+        let names: Vec<&str> = responses[1]["result"]["tools"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|t| t["name"].as_str().unwrap())
+            .collect();
+        assert_eq!(names, vec!["analyze", "reply", "resolve", "checks", "ready"]);
+        // A failing subcommand is a tool error the agent can read, not a protocol error
+        assert_eq!(responses[2]["result"]["isError"], true);
+        assert_eq!(
+            responses[2]["result"]["content"][1]["text"],
+            "Error: Policy forbids resolving human threads\n"
+        );
+        assert_eq!(responses[3]["error"]["code"], METHOD_NOT_FOUND);
+    }
+}
@@ -0,0 +1,277 @@
+// Threads addressed to someone else.
+// When a reviewer's latest comment @mentions another person ("@alice can you
+// check the infra side?"), the thread is a side conversation: the agent
+// replying would be barging in. Such threads are marked "addressed to someone
+// else" and, unless `[mentions] actionable = true`, left out of the analysis
+// and the wait modes until someone else speaks in them.
+//
+// A mention counts as ours if it names the PR's author, the `gh` user, an
+// account that has posted Claude-marked comments in the thread, or `@claude`.
+//
+// Like snoozing, filtering wraps the threads client (`SideConversations<C>`),
+// so waits and the analysis agree on what needs a response.
+
+use crate::graphql::gh_graphql;
+use crate::threads::{PendingReview, ReviewThread, ThreadsClient};
+use anyhow::{Context, Result};
+use serde_json::json;
+use std::collections::HashSet;
+use std::sync::{Mutex, OnceLock};
+
+/// GraphQL query for the viewer's and the PR author's logins (loaded from
+/// graphql/operation/).
+const FETCH_OWN_LOGINS_QUERY: &str = include_str!("../graphql/operation/fetch_own_logins.graphql");
+
+/// Looks up the logins mentions of which are addressed to the agent.
+pub type OwnLogins = Box<dyn Fn() -> Vec<String> + Send + Sync>;
+
+/// The logins @mentioned in a comment, in order, outside code and quotes.
+/// Team mentions (`@org/team`) are included.
+pub fn mentioned_logins(body: &str) -> Vec<String> {
+    let mut logins: Vec<String> = Vec::new();
+    let mut in_fence = false;
+    for line in body.lines() {
+        if line.trim_start().starts_with("```") {
+            in_fence = !in_fence;
+            continue;
+        }
+        if in_fence || line.trim_start().starts_with('>') {
+            continue;
+        }
+        let mut in_code = false;
+        let mut previous: Option<char> = None;
+        for (i, c) in line.char_indices() {
+            if c == '`' {
+                in_code = !in_code;
+            } else if c == '@'
+                && !in_code
+                && !previous.is_some_and(|p| p.is_ascii_alphanumeric() || "_-./`".contains(p))
+            {
+                let login: String = line[i + 1..]
+                    .chars()
+                    .take_while(|c| c.is_ascii_alphanumeric() || "-/_".contains(*c))
+                    .collect();
+                let login = login.trim_end_matches(['-', '/', '_']).to_string();
+                if !login.is_empty() && !logins.iter().any(|l| l.eq_ignore_ascii_case(&login)) {
+                    logins.push(login);
+                }
+            }
+            previous = Some(c);
+        }
+    }
+    logins
+}
+
+/// Who `thread` is addressed to, if it's someone else: the people the latest
+/// (non-Claude) comment mentions when none of them is one of `own`. Empty
+/// when the thread is for the agent.
+pub fn addressed_elsewhere(thread: &ReviewThread, own: &[String]) -> Vec<String> {
+    let Some(last) = thread.last_comment() else {
+        return vec![];
+    };
+    if last.is_from_claude() {
+        return vec![];
+    }
+    let ours: Vec<&str> = own
+        .iter()
+        .map(String::as_str)
+        .chain(["claude"])
+        .chain(
+            thread
+                .comments
+                .iter()
+                .filter(|c| c.is_from_claude())
+                .map(|c| c.author.as_str()),
+        )
+        .collect();
+    let mentioned: Vec<String> = mentioned_logins(&last.body)
+        .into_iter()
+        .filter(|m| !m.eq_ignore_ascii_case(&last.author))
+        .collect();
+    if mentioned.iter().any(|m| ours.iter().any(|o| o.eq_ignore_ascii_case(m))) {
+        return vec![];
+    }
+    mentioned
+}
+
+/// "@alice, @bob"
+pub fn describe(logins: &[String]) -> String {
+    logins.iter().map(|l| format!("@{}", l)).collect::<Vec<_>>().join(", ")
+}
+
+/// The `gh` user's login and the PR author's.
+pub fn fetch_own_logins(owner: &str, repo: &str, pr_number: u64) -> Result<Vec<String>> {
+    let output = gh_graphql(FETCH_OWN_LOGINS_QUERY, json!({ "owner": owner, "repo": repo, "pr": pr_number }))
+        .context("Failed to run 'gh api graphql' for logins")?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        anyhow::bail!("GraphQL query failed: {}", stderr.trim());
+    }
+    let response: serde_json::Value =
+        serde_json::from_slice(&output.stdout).context("Failed to parse GraphQL response")?;
+    if let Some(errors) = response.get("errors") {
+        anyhow::bail!("GraphQL errors: {}", errors);
+    }
+    Ok(["/data/viewer/login", "/data/repository/pullRequest/author/login"]
+        .iter()
+        .filter_map(|pointer| response.pointer(pointer)?.as_str().map(String::from))
+        .collect())
+}
+
+/// A threads client whose results leave out threads addressed to someone
+/// else, unless they're configured to be actionable.
+pub struct SideConversations<C> {
+    inner: C,
+    own_lookup: OwnLogins,
+    /// Looked up the first time a thread mentions anyone.
+    own: OnceLock<Vec<String>>,
+    actionable: bool,
+    /// Threads (at their latest comment) already reported as left out.
+    announced: Mutex<HashSet<String>>,
+}
+
+impl<C> SideConversations<C> {
+    pub fn new(inner: C, own_lookup: OwnLogins, actionable: bool) -> Self {
+        Self {
+            inner,
+            own_lookup,
+            own: OnceLock::new(),
+            actionable,
+            announced: Mutex::new(HashSet::new()),
+        }
+    }
+}
+
+impl<C: ThreadsClient> ThreadsClient for SideConversations<C> {
+    fn fetch_threads(&self, owner: &str, repo: &str, pr_number: u64) -> Result<Vec<ReviewThread>> {
+        let threads = self.inner.fetch_threads(owner, repo, pr_number)?;
+        let mentions_anyone = |t: &ReviewThread| {
+            t.last_comment()
+                .is_some_and(|c| !mentioned_logins(&c.body).is_empty())
+        };
+        if self.actionable || !threads.iter().any(|t| t.needs_response() && mentions_anyone(t)) {
+            return Ok(threads);
+        }
+        let own = self.own.get_or_init(|| (self.own_lookup)());
+        let mut announced = self.announced.lock().expect("announced lock poisoned");
+        Ok(threads
+            .into_iter()
+            .filter(|t| {
+                if !t.needs_response() {
+                    return true;
+                }
+                let others = addressed_elsewhere(t, own);
+                if others.is_empty() {
+                    return true;
+                }
+                let key = format!("{}@{}", t.id, t.last_comment().map(|c| c.id.as_str()).unwrap_or(""));
+                if announced.insert(key) {
+                    eprintln!(
+                        "Leaving the thread on {} to {}: it's addressed to someone else.",
+                        t.location(),
+                        describe(&others)
+                    );
+                }
+                false
+            })
+            .collect())
+    }
+
+    fn fetch_thread_by_comment_id(&self, comment_id: &str) -> Result<ReviewThread> {
+        self.inner.fetch_thread_by_comment_id(comment_id)
+    }
+
+    fn fetch_pending_reviews(
+        &self,
+        owner: &str,
+        repo: &str,
+        pr_number: u64,
+    ) -> Result<Vec<PendingReview>> {
+        self.inner.fetch_pending_reviews(owner, repo, pr_number)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::threads::{ThreadComment, CLAUDE_MARKER};
+
+    fn comment(id: &str, author: &str, body: &str) -> ThreadComment {
+        ThreadComment::for_test(id, author, body)
+    }
+
+    fn thread(id: &str, comments: Vec<ThreadComment>) -> ReviewThread {
+        ReviewThread {
+            path: Some("infra/deploy.yml".to_string()),
+            line: Some(4),
+            ..ReviewThread::for_test(id, comments)
+        }
+    }
+
+    #[test]
+    fn finds_mentions_outside_code_quotes_and_emails() {
+        let body = "@alice can you check the infra side? cc @my-org/infra-team.\n\
+                    > @quoted said so\n\
+                    Mail ops@example.com or run `npm i @types/node`.\n\
+                    ```\n@Decorator\n```\n\
+                    Thanks @Alice!";
+        assert_eq!(mentioned_logins(body), vec!["alice", "my-org/infra-team"]);
+    }
+
+    #[test]
+    fn addressed_elsewhere_unless_a_mention_is_ours() {
+        let own = vec!["glasser".to_string()];
+        let side = thread("T1", vec![comment("C1", "bob", "@alice can you check the infra side?")]);
+        assert_eq!(addressed_elsewhere(&side, &own), vec!["alice"]);
+
+        let to_us = thread("T2", vec![comment("C2", "bob", "@alice @Glasser thoughts?")]);
+        assert!(addressed_elsewhere(&to_us, &own).is_empty());
+
+        // Claude replied from another account, which is ours too
+        let to_claude_account = thread(
+            "T3",
+            vec![
+                comment("C3", "bob", "Use a constant"),
+                comment("C4", "bot-account", &format!("{} Done", CLAUDE_MARKER)),
+                comment("C5", "bob", "@bot-account not that one"),
+            ],
+        );
+        assert!(addressed_elsewhere(&to_claude_account, &own).is_empty());
+
+        let plain = thread("T4", vec![comment("C6", "bob", "Rename this")]);
+        assert!(addressed_elsewhere(&plain, &own).is_empty());
+    }
+
+    struct FixedThreads;
+
+    impl ThreadsClient for FixedThreads {
+        fn fetch_threads(&self, _: &str, _: &str, _: u64) -> Result<Vec<ReviewThread>> {
+            Ok(vec![
+                thread("T1", vec![comment("C1", "bob", "@alice can you check the infra side?")]),
+                thread("T2", vec![comment("C2", "bob", "Rename this")]),
+            ])
+        }
+
+        fn fetch_thread_by_comment_id(&self, _: &str) -> Result<ReviewThread> {
+            anyhow::bail!("not used")
+        }
+
+        fn fetch_pending_reviews(&self, _: &str, _: &str, _: u64) -> Result<Vec<PendingReview>> {
+            Ok(vec![])
+        }
+    }
+
+    #[test]
+    fn leaves_out_side_conversations_unless_actionable() {
+        let ids = |actionable| {
+            SideConversations::new(FixedThreads, Box::new(|| vec!["glasser".to_string()]), actionable)
+                .fetch_threads("o", "r", 1)
+                .unwrap()
+                .into_iter()
+                .map(|t| t.id)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(ids(false), vec!["T2"]);
+        assert_eq!(ids(true), vec!["T1", "T2"]);
+    }
+}
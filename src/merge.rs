@@ -0,0 +1,180 @@
+// Merging a PR once it's happy.
+// `ready` hands the PR to reviewers; in fully automated workflows nobody
+// presses the merge button afterwards. `pr-loop merge` checks the PR the
+// same way `ready` does (no unresolved threads, CI green), merges it (or,
+// with `--auto`, enables GitHub auto-merge so it lands once required checks
+// and reviews allow), and then waits until GitHub reports it merged, so a
+// script can tell "merged" from "queued" from "closed".
+
+use crate::github::PrContext;
+use crate::pr::{PrClient, PrState};
+use crate::wait::{sleep_until_next_poll, CancelFile, PrSnapshot};
+use anyhow::Result;
+use std::time::{Duration, Instant};
+
+/// How waiting for the merge ended.
+#[derive(Debug, PartialEq)]
+pub enum MergeOutcome {
+    Merged,
+    /// Closed without being merged.
+    Closed,
+    Timeout,
+    Cancelled,
+}
+
+/// Why the PR can't be merged yet, one line per reason. Checks still
+/// running are fine with auto-merge, which waits for them.
+pub fn blockers(snapshot: &PrSnapshot, auto: bool) -> Vec<String> {
+    let mut reasons = Vec::new();
+    if snapshot.is_conflicting() {
+        reasons.push("merge conflicts with the base branch".to_string());
+    }
+    if !snapshot.unresolved_thread_ids.is_empty() {
+        reasons.push(format!(
+            "{} unresolved review thread(s)",
+            snapshot.unresolved_thread_ids.len()
+        ));
+    }
+    if !snapshot.pending_review_authors.is_empty() {
+        let mut authors: Vec<&String> = snapshot.pending_review_authors.iter().collect();
+        authors.sort();
+        let authors: Vec<String> = authors.iter().map(|a| format!("@{}", a)).collect();
+        reasons.push(format!("a review in progress by {}", authors.join(", ")));
+    }
+    let sorted = |names: &std::collections::HashSet<String>| {
+        let mut names: Vec<&str> = names.iter().map(String::as_str).collect();
+        names.sort();
+        names.join(", ")
+    };
+    if !snapshot.failed_check_names.is_empty() {
+        reasons.push(format!("failing CI check(s): {}", sorted(&snapshot.failed_check_names)));
+    }
+    if !auto && !snapshot.pending_check_names.is_empty() {
+        reasons.push(format!(
+            "CI check(s) still running: {} (use --auto to merge once they pass)",
+            sorted(&snapshot.pending_check_names)
+        ));
+    }
+    reasons
+}
+
+/// Poll until the PR is merged or closed.
+pub fn wait_for_merge(
+    pr_client: &dyn PrClient,
+    pr: &PrContext,
+    timeout: Duration,
+    poll_interval: Duration,
+    cancel: Option<&CancelFile>,
+) -> Result<MergeOutcome> {
+    let start = Instant::now();
+    loop {
+        match pr_client.state(&pr.owner, &pr.repo, pr.pr_number)? {
+            PrState::Merged => return Ok(MergeOutcome::Merged),
+            PrState::Closed => return Ok(MergeOutcome::Closed),
+            PrState::Open => {}
+        }
+        if start.elapsed() >= timeout {
+            return Ok(MergeOutcome::Timeout);
+        }
+        if sleep_until_next_poll(poll_interval.min(timeout.saturating_sub(start.elapsed())), cancel) {
+            return Ok(MergeOutcome::Cancelled);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::checks::ChecksSummary;
+    use crate::github::MergeableStatus;
+    use crate::pr::MergeMethod;
+    use std::cell::RefCell;
+    use std::collections::HashSet;
+
+    fn snapshot(unresolved: &[&str], failed: &[&str], pending: &[&str]) -> PrSnapshot {
+        let set = |items: &[&str]| items.iter().map(|s| s.to_string()).collect::<HashSet<_>>();
+        PrSnapshot {
+            actionable_thread_ids: HashSet::new(),
+            unresolved_thread_ids: set(unresolved),
+            awaiting_input_thread_ids: HashSet::new(),
+            failed_check_names: set(failed),
+            pending_check_names: set(pending),
+            comment_ids: HashSet::new(),
+            pending_review_authors: HashSet::new(),
+            checks: ChecksSummary { checks: vec![] },
+            threads: vec![],
+            pending_reviews: vec![],
+            mergeable_status: MergeableStatus::Unknown,
+        }
+    }
+
+    #[test]
+    fn pending_checks_only_block_without_auto() {
+        assert!(blockers(&snapshot(&[], &[], &[]), false).is_empty());
+        let running = snapshot(&[], &[], &["e2e"]);
+        assert_eq!(
+            blockers(&running, false),
+            vec!["CI check(s) still running: e2e (use --auto to merge once they pass)"]
+        );
+        assert!(blockers(&running, true).is_empty());
+        assert_eq!(
+            blockers(&snapshot(&["T1"], &["build"], &[]), true),
+            vec!["1 unresolved review thread(s)", "failing CI check(s): build"]
+        );
+    }
+
+    /// Reports the PR open until its states run out, then merged.
+    struct QueuedPr {
+        states: RefCell<Vec<PrState>>,
+    }
+
+    impl PrClient for QueuedPr {
+        fn is_draft(&self, _: &str, _: &str, _: u64) -> Result<bool> {
+            Ok(false)
+        }
+        fn get_body(&self, _: &str, _: &str, _: u64) -> Result<String> {
+            Ok(String::new())
+        }
+        fn set_body(&self, _: &str, _: &str, _: u64, _: &str) -> Result<()> {
+            Ok(())
+        }
+        fn mark_ready(&self, _: &str, _: &str, _: u64) -> Result<()> {
+            Ok(())
+        }
+        fn get_commit_count(&self, _: &str, _: &str, _: u64) -> Result<usize> {
+            Ok(1)
+        }
+        fn add_reviewer(&self, _: &str, _: &str, _: u64, _: &str) -> Result<()> {
+            Ok(())
+        }
+        fn add_comment(&self, _: &str, _: &str, _: u64, _: &str) -> Result<()> {
+            Ok(())
+        }
+        fn merge(&self, _: &str, _: &str, _: u64, _: MergeMethod, _: bool) -> Result<()> {
+            Ok(())
+        }
+        fn state(&self, _: &str, _: &str, _: u64) -> Result<PrState> {
+            Ok(self.states.borrow_mut().pop().unwrap_or(PrState::Merged))
+        }
+    }
+
+    #[test]
+    fn waits_until_merged() {
+        let pr = PrContext {
+            owner: "o".to_string(),
+            repo: "r".to_string(),
+            pr_number: 1,
+        };
+        let client = QueuedPr {
+            states: RefCell::new(vec![PrState::Open, PrState::Open]),
+        };
+        let outcome = wait_for_merge(&client, &pr, Duration::from_secs(5), Duration::ZERO, None).unwrap();
+        assert_eq!(outcome, MergeOutcome::Merged);
+
+        let closed = QueuedPr {
+            states: RefCell::new(vec![PrState::Closed]),
+        };
+        let outcome = wait_for_merge(&closed, &pr, Duration::from_secs(5), Duration::ZERO, None).unwrap();
+        assert_eq!(outcome, MergeOutcome::Closed);
+    }
+}
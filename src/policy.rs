@@ -0,0 +1,284 @@
+// Policy guardrails limiting what pr-loop may do to a PR.
+// Configured in the `[policy]` section of the config file and enforced by
+// wrapping the mutation clients (`Guarded<C>`), so a forbidden operation is
+// refused no matter which command or flag tried it. A safety net for running
+// pr-loop under an autonomous agent.
+
+use crate::pr::{MergeMethod, PrClient, PrState};
+use crate::reply::{ReplyClient, ReplyResult};
+use crate::threads::ReviewThread;
+use anyhow::Result;
+
+/// Which classes of mutation are permitted.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Policy {
+    pub allow_delete_comments: bool,
+    pub allow_resolve_human_threads: bool,
+    /// Marking the PR ready requires `ready --confirm`.
+    pub require_confirm_for_ready: bool,
+}
+
+impl Default for Policy {
+    fn default() -> Self {
+        Self {
+            allow_delete_comments: true,
+            allow_resolve_human_threads: true,
+            require_confirm_for_ready: false,
+        }
+    }
+}
+
+/// Looks up a review thread by ID, for checks that depend on who started it.
+pub type ThreadLookup = Box<dyn Fn(&str) -> Result<ReviewThread> + Send + Sync>;
+
+/// A client wrapper that refuses operations the policy forbids.
+pub struct Guarded<C> {
+    inner: C,
+    policy: Policy,
+    ready_confirmed: bool,
+    thread_lookup: Option<ThreadLookup>,
+}
+
+impl<C> Guarded<C> {
+    pub fn new(inner: C, policy: Policy) -> Self {
+        Self {
+            inner,
+            policy,
+            ready_confirmed: false,
+            thread_lookup: None,
+        }
+    }
+
+    /// Record that the user explicitly confirmed marking the PR ready.
+    pub fn with_ready_confirmed(mut self, confirmed: bool) -> Self {
+        self.ready_confirmed = confirmed;
+        self
+    }
+
+    /// Provide a way to look threads up, needed to tell human-started threads
+    /// apart when `allow_resolve_human_threads` is off. Without one, all
+    /// resolutions are refused under that policy.
+    pub fn with_thread_lookup(mut self, lookup: ThreadLookup) -> Self {
+        self.thread_lookup = Some(lookup);
+        self
+    }
+}
+
+/// Returns true if a human (not Claude) started the thread.
+fn is_human_authored(thread: &ReviewThread) -> bool {
+    thread
+        .comments
+        .first()
+        .is_some_and(|c| !c.is_from_claude())
+}
+
+impl<C: ReplyClient> ReplyClient for Guarded<C> {
+    fn post_reply(&self, thread_id: &str, body: &str) -> Result<ReplyResult> {
+        self.inner.post_reply(thread_id, body)
+    }
+
+    fn delete_comment(&self, comment_id: &str) -> Result<()> {
+        if !self.policy.allow_delete_comments {
+            anyhow::bail!("Policy forbids deleting comments (allow_delete_comments = false)");
+        }
+        self.inner.delete_comment(comment_id)
+    }
+
+    fn update_comment(&self, comment_id: &str, body: &str) -> Result<()> {
+        self.inner.update_comment(comment_id, body)
+    }
+
+    fn resolve_thread(&self, thread_id: &str) -> Result<()> {
+        if !self.policy.allow_resolve_human_threads {
+            let Some(lookup) = &self.thread_lookup else {
+                anyhow::bail!(
+                    "Policy forbids resolving human-authored threads and thread {} can't be checked",
+                    thread_id
+                );
+            };
+            if is_human_authored(&lookup(thread_id)?) {
+                anyhow::bail!(
+                    "Policy forbids resolving human-authored threads (allow_resolve_human_threads = false)"
+                );
+            }
+        }
+        self.inner.resolve_thread(thread_id)
+    }
+}
+
+impl<C: PrClient> PrClient for Guarded<C> {
+    fn is_draft(&self, owner: &str, repo: &str, pr_number: u64) -> Result<bool> {
+        self.inner.is_draft(owner, repo, pr_number)
+    }
+
+    fn get_body(&self, owner: &str, repo: &str, pr_number: u64) -> Result<String> {
+        self.inner.get_body(owner, repo, pr_number)
+    }
+
+    fn set_body(&self, owner: &str, repo: &str, pr_number: u64, body: &str) -> Result<()> {
+        self.inner.set_body(owner, repo, pr_number, body)
+    }
+
+    fn mark_ready(&self, owner: &str, repo: &str, pr_number: u64) -> Result<()> {
+        if self.policy.require_confirm_for_ready && !self.ready_confirmed {
+            anyhow::bail!(
+                "Policy requires confirmation to mark the PR ready; re-run `pr-loop ready --confirm`"
+            );
+        }
+        self.inner.mark_ready(owner, repo, pr_number)
+    }
+
+    fn get_commit_count(&self, owner: &str, repo: &str, pr_number: u64) -> Result<usize> {
+        self.inner.get_commit_count(owner, repo, pr_number)
+    }
+
+    fn add_reviewer(&self, owner: &str, repo: &str, pr_number: u64, reviewer: &str) -> Result<()> {
+        self.inner.add_reviewer(owner, repo, pr_number, reviewer)
+    }
+
+    fn add_comment(&self, owner: &str, repo: &str, pr_number: u64, body: &str) -> Result<()> {
+        self.inner.add_comment(owner, repo, pr_number, body)
+    }
+
+    fn merge(&self, owner: &str, repo: &str, pr_number: u64, method: MergeMethod, auto: bool) -> Result<()> {
+        self.inner.merge(owner, repo, pr_number, method, auto)
+    }
+
+    fn state(&self, owner: &str, repo: &str, pr_number: u64) -> Result<PrState> {
+        self.inner.state(owner, repo, pr_number)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::threads::{ThreadComment, CLAUDE_MARKER};
+    use std::cell::RefCell;
+
+    #[derive(Default)]
+    struct RecordingClient {
+        calls: RefCell<Vec<String>>,
+    }
+
+    impl ReplyClient for RecordingClient {
+        fn post_reply(&self, thread_id: &str, _body: &str) -> Result<ReplyResult> {
+            self.calls.borrow_mut().push(format!("reply {}", thread_id));
+            Ok(ReplyResult {
+                comment_id: "C1".to_string(),
+                url: None,
+            })
+        }
+        fn delete_comment(&self, comment_id: &str) -> Result<()> {
+            self.calls.borrow_mut().push(format!("delete {}", comment_id));
+            Ok(())
+        }
+        fn update_comment(&self, comment_id: &str, _body: &str) -> Result<()> {
+            self.calls.borrow_mut().push(format!("update {}", comment_id));
+            Ok(())
+        }
+        fn resolve_thread(&self, thread_id: &str) -> Result<()> {
+            self.calls.borrow_mut().push(format!("resolve {}", thread_id));
+            Ok(())
+        }
+    }
+
+    impl PrClient for RecordingClient {
+        fn is_draft(&self, _: &str, _: &str, _: u64) -> Result<bool> {
+            Ok(true)
+        }
+        fn get_body(&self, _: &str, _: &str, _: u64) -> Result<String> {
+            Ok(String::new())
+        }
+        fn set_body(&self, _: &str, _: &str, _: u64, _: &str) -> Result<()> {
+            Ok(())
+        }
+        fn mark_ready(&self, _: &str, _: &str, _: u64) -> Result<()> {
+            self.calls.borrow_mut().push("ready".to_string());
+            Ok(())
+        }
+        fn get_commit_count(&self, _: &str, _: &str, _: u64) -> Result<usize> {
+            Ok(1)
+        }
+        fn add_reviewer(&self, _: &str, _: &str, _: u64, _: &str) -> Result<()> {
+            Ok(())
+        }
+        fn add_comment(&self, _: &str, _: &str, _: u64, _: &str) -> Result<()> {
+            Ok(())
+        }
+        fn merge(&self, _: &str, _: &str, _: u64, _: MergeMethod, _: bool) -> Result<()> {
+            Ok(())
+        }
+        fn state(&self, _: &str, _: &str, _: u64) -> Result<PrState> {
+            Ok(PrState::Open)
+        }
+    }
+
+    fn thread_started_by(id: &str, body: &str) -> ReviewThread {
+        let comment = ThreadComment::for_test(&format!("{}-c", id), "someone", body);
+        ReviewThread {
+            path: None,
+            line: None,
+            ..ReviewThread::for_test(id, vec![comment])
+        }
+    }
+
+    #[test]
+    fn default_policy_allows_everything() {
+        let client = Guarded::new(RecordingClient::default(), Policy::default());
+        client.delete_comment("C1").unwrap();
+        client.resolve_thread("T1").unwrap();
+        assert_eq!(client.inner.calls.borrow().len(), 2);
+    }
+
+    #[test]
+    fn forbids_deleting_comments() {
+        let policy = Policy {
+            allow_delete_comments: false,
+            ..Policy::default()
+        };
+        let client = Guarded::new(RecordingClient::default(), policy);
+        assert!(client.delete_comment("C1").is_err());
+        client.update_comment("C1", "edited").unwrap();
+        assert_eq!(*client.inner.calls.borrow(), vec!["update C1"]);
+    }
+
+    #[test]
+    fn forbids_resolving_human_threads_only() {
+        let policy = Policy {
+            allow_resolve_human_threads: false,
+            ..Policy::default()
+        };
+        let client = Guarded::new(RecordingClient::default(), policy.clone()).with_thread_lookup(
+            Box::new(|id| {
+                Ok(if id == "human" {
+                    thread_started_by(id, "Please fix")
+                } else {
+                    thread_started_by(id, &format!("{} note", CLAUDE_MARKER))
+                })
+            }),
+        );
+        assert!(client.resolve_thread("human").is_err());
+        client.resolve_thread("claude").unwrap();
+        assert_eq!(*client.inner.calls.borrow(), vec!["resolve claude"]);
+
+        // Without a way to check authorship, refuse rather than guess
+        let blind = Guarded::new(RecordingClient::default(), policy);
+        assert!(blind.resolve_thread("claude").is_err());
+    }
+
+    #[test]
+    fn ready_requires_confirmation() {
+        let policy = Policy {
+            require_confirm_for_ready: true,
+            ..Policy::default()
+        };
+        let client = Guarded::new(RecordingClient::default(), policy.clone());
+        assert!(client.mark_ready("o", "r", 1).is_err());
+        assert!(client.inner.calls.borrow().is_empty());
+
+        let confirmed =
+            Guarded::new(RecordingClient::default(), policy).with_ready_confirmed(true);
+        confirmed.mark_ready("o", "r", 1).unwrap();
+        assert_eq!(*confirmed.inner.calls.borrow(), vec!["ready"]);
+    }
+}
@@ -0,0 +1,187 @@
+// Watching a PR after it's been marked ready.
+// Once `ready` hands the PR to reviewers, the agent usually goes away, but a
+// reviewer's first comments or a required check that flakes red afterwards
+// still need it. `post-ready-watch --for 2h` polls the PR quietly for that
+// window (no status block, no draft requirement) and, as soon as a new
+// non-Claude comment or a newly failing required check shows up, sends a
+// notification (webhook and/or desktop) and exits so the agent can be
+// brought back.
+
+use crate::checks::{CheckFilters, ChecksClient};
+use crate::github::PrContext;
+use crate::threads::ThreadsClient;
+use crate::wait::{capture_snapshot, sleep_until_next_poll, CancelFile, PrSnapshot};
+use anyhow::{bail, Context, Result};
+use std::process::Command;
+use std::time::{Duration, Instant};
+
+/// How a post-ready watch ended.
+#[derive(Debug, PartialEq)]
+pub enum WatchOutcome {
+    /// Something needs the agent again; one line per item.
+    Attention(Vec<String>),
+    /// The window passed without anything needing the agent.
+    Quiet,
+    Cancelled,
+}
+
+/// What changed since the watch started that needs the agent: comments that
+/// aren't Claude's, and checks that started failing. When `required` is
+/// non-empty only those checks count; otherwise any check does.
+pub fn needs_attention(baseline: &PrSnapshot, now: &PrSnapshot, required: &[String]) -> Vec<String> {
+    let mut items: Vec<String> = now
+        .threads
+        .iter()
+        .flat_map(|t| t.comments.iter().map(move |c| (t, c)))
+        .filter(|(_, c)| !baseline.comment_ids.contains(&c.id) && !c.is_from_claude())
+        .map(|(t, c)| format!("New comment from @{} on {}", c.author, t.location()))
+        .collect();
+    let mut failing: Vec<&String> = now
+        .failed_check_names
+        .difference(&baseline.failed_check_names)
+        .filter(|name| required.is_empty() || required.contains(name))
+        .collect();
+    failing.sort();
+    items.extend(failing.into_iter().map(|name| format!("{} started failing", name)));
+    items
+}
+
+/// Poll the PR every `poll_interval` for `window`, returning as soon as
+/// something needs the agent.
+pub fn watch(
+    checks_client: &dyn ChecksClient,
+    threads_client: &dyn ThreadsClient,
+    pr: &PrContext,
+    filters: &CheckFilters,
+    window: Duration,
+    poll_interval: Duration,
+    cancel: Option<&CancelFile>,
+) -> Result<WatchOutcome> {
+    let start = Instant::now();
+    let poll = || capture_snapshot(checks_client, threads_client, &pr.owner, &pr.repo, pr.pr_number, filters);
+    let required = match checks_client.fetch_required_checks(&pr.owner, &pr.repo, pr.pr_number) {
+        Ok(required) => required,
+        Err(e) => {
+            eprintln!("Warning: Failed to fetch required checks; watching every check: {}", e);
+            vec![]
+        }
+    };
+    let baseline = poll()?;
+
+    eprintln!(
+        "Watching for new comments or newly failing {} for {}s (polling every {}s)...",
+        if required.is_empty() { "checks" } else { "required checks" },
+        window.as_secs(),
+        poll_interval.as_secs()
+    );
+    loop {
+        if start.elapsed() >= window {
+            return Ok(WatchOutcome::Quiet);
+        }
+        if sleep_until_next_poll(poll_interval.min(window.saturating_sub(start.elapsed())), cancel) {
+            return Ok(WatchOutcome::Cancelled);
+        }
+        let items = needs_attention(&baseline, &poll()?, &required);
+        if !items.is_empty() {
+            return Ok(WatchOutcome::Attention(items));
+        }
+    }
+}
+
+/// Where notifications go.
+pub trait Notifier {
+    fn notify(&self, title: &str, message: &str) -> Result<()>;
+}
+
+/// Notifies via a webhook (a JSON `{"text": ...}` POST, as Slack and most
+/// chat incoming webhooks accept) and/or a desktop notification.
+pub struct RealNotifier {
+    pub webhook: Option<String>,
+    pub desktop: bool,
+}
+
+impl Notifier for RealNotifier {
+    fn notify(&self, title: &str, message: &str) -> Result<()> {
+        if let Some(url) = &self.webhook {
+            let response = reqwest::blocking::Client::new()
+                .post(url)
+                .json(&serde_json::json!({ "text": format!("{}\n{}", title, message) }))
+                .send()
+                .context("Failed to send webhook notification")?;
+            if !response.status().is_success() {
+                bail!("Webhook returned {}", response.status());
+            }
+        }
+        if self.desktop {
+            desktop_notification(title, message)?;
+        }
+        Ok(())
+    }
+}
+
+fn desktop_notification(title: &str, message: &str) -> Result<()> {
+    let output = if cfg!(target_os = "macos") {
+        let script = format!(
+            "display notification {} with title {}",
+            applescript_string(message),
+            applescript_string(title)
+        );
+        Command::new("osascript").args(["-e", &script]).output()
+    } else {
+        Command::new("notify-send").args([title, message]).output()
+    }
+    .context("Failed to run the desktop notifier")?;
+    if !output.status.success() {
+        bail!(
+            "Desktop notification failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(())
+}
+
+fn applescript_string(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::threads::{ReviewThread, ThreadComment, CLAUDE_MARKER};
+
+    fn comment(id: &str, body: &str) -> ThreadComment {
+        ThreadComment::for_test(id, "reviewer", body)
+    }
+
+    fn snapshot(comments: Vec<ThreadComment>, failed: &[&str]) -> PrSnapshot {
+        PrSnapshot {
+            comment_ids: comments.iter().map(|c| c.id.clone()).collect(),
+            failed_check_names: failed.iter().map(|s| s.to_string()).collect(),
+            threads: vec![ReviewThread {
+                line: Some(7),
+                ..ReviewThread::for_test("T1", comments)
+            }],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn reports_new_reviewer_comments_and_required_failures() {
+        let baseline = snapshot(vec![comment("C1", "Looks good")], &["flaky"]);
+        let now = snapshot(
+            vec![
+                comment("C1", "Looks good"),
+                comment("C2", &format!("{} Done", CLAUDE_MARKER)),
+                comment("C3", "Actually, one more thing"),
+            ],
+            &["flaky", "build", "optional-lint"],
+        );
+        assert_eq!(
+            needs_attention(&baseline, &now, &["build".to_string(), "flaky".to_string()]),
+            vec!["New comment from @reviewer on src/lib.rs:7", "build started failing"]
+        );
+        // Without required checks every new failure counts
+        assert_eq!(needs_attention(&baseline, &now, &[]).len(), 3);
+        assert!(needs_attention(&baseline, &baseline, &[]).is_empty());
+    }
+}
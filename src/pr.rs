@@ -1,6 +1,7 @@
 // PR operations: draft mode checking and description status block management.
 // Uses `gh` CLI for PR interactions.
 
+use crate::request_log::TracedOutput;
 use anyhow::{Context, Result};
 use serde::Deserialize;
 use std::process::Command;
@@ -9,6 +10,34 @@ use std::process::Command;
 const STATUS_BLOCK_START: &str = "<!-- pr-loop-status-start -->";
 const STATUS_BLOCK_END: &str = "<!-- pr-loop-status-end -->";
 
+/// How `merge` combines the PR into its base branch.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MergeMethod {
+    Squash,
+    Rebase,
+    Merge,
+}
+
+impl MergeMethod {
+    /// The `gh pr merge` flag.
+    pub fn flag(&self) -> &'static str {
+        match self {
+            MergeMethod::Squash => "--squash",
+            MergeMethod::Rebase => "--rebase",
+            MergeMethod::Merge => "--merge",
+        }
+    }
+}
+
+/// Whether the PR is still open.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum PrState {
+    Open,
+    Merged,
+    Closed,
+}
+
 /// Trait for PR operations, allowing test implementations.
 pub trait PrClient {
     /// Check if the PR is in draft mode.
@@ -28,6 +57,16 @@ pub trait PrClient {
 
     /// Request a review from a GitHub user.
     fn add_reviewer(&self, owner: &str, repo: &str, pr_number: u64, reviewer: &str) -> Result<()>;
+
+    /// Post a top-level comment on the PR conversation.
+    fn add_comment(&self, owner: &str, repo: &str, pr_number: u64, body: &str) -> Result<()>;
+
+    /// Merge the PR, or with `auto`, enable auto-merge so GitHub merges it once
+    /// its requirements are met.
+    fn merge(&self, owner: &str, repo: &str, pr_number: u64, method: MergeMethod, auto: bool) -> Result<()>;
+
+    /// Whether the PR is open, merged, or closed.
+    fn state(&self, owner: &str, repo: &str, pr_number: u64) -> Result<PrState>;
 }
 
 /// Real PR client that uses the `gh` CLI.
@@ -45,7 +84,7 @@ impl PrClient for RealPrClient {
                 "--json",
                 "isDraft",
             ])
-            .output()
+            .traced_output()
             .context("Failed to run 'gh pr view'")?;
 
         if !output.status.success() {
@@ -76,7 +115,7 @@ impl PrClient for RealPrClient {
                 "--json",
                 "body",
             ])
-            .output()
+            .traced_output()
             .context("Failed to run 'gh pr view'")?;
 
         if !output.status.success() {
@@ -106,7 +145,7 @@ impl PrClient for RealPrClient {
                 "--body",
                 body,
             ])
-            .output()
+            .traced_output()
             .context("Failed to run 'gh pr edit'")?;
 
         if !output.status.success() {
@@ -126,7 +165,7 @@ impl PrClient for RealPrClient {
                 "--repo",
                 &format!("{}/{}", owner, repo),
             ])
-            .output()
+            .traced_output()
             .context("Failed to run 'gh pr ready'")?;
 
         if !output.status.success() {
@@ -148,7 +187,7 @@ impl PrClient for RealPrClient {
                 "--add-reviewer",
                 reviewer,
             ])
-            .output()
+            .traced_output()
             .context("Failed to run 'gh pr edit'")?;
 
         if !output.status.success() {
@@ -159,6 +198,78 @@ impl PrClient for RealPrClient {
         Ok(())
     }
 
+    fn add_comment(&self, owner: &str, repo: &str, pr_number: u64, body: &str) -> Result<()> {
+        let output = Command::new("gh")
+            .args([
+                "pr",
+                "comment",
+                &pr_number.to_string(),
+                "--repo",
+                &format!("{}/{}", owner, repo),
+                "--body",
+                body,
+            ])
+            .traced_output()
+            .context("Failed to run 'gh pr comment'")?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            anyhow::bail!("Failed to post PR comment: {}", stderr.trim());
+        }
+
+        Ok(())
+    }
+
+    fn merge(&self, owner: &str, repo: &str, pr_number: u64, method: MergeMethod, auto: bool) -> Result<()> {
+        let pr = pr_number.to_string();
+        let repo = format!("{}/{}", owner, repo);
+        let mut args = vec!["pr", "merge", &pr, "--repo", &repo, method.flag()];
+        if auto {
+            args.push("--auto");
+        }
+        let output = Command::new("gh")
+            .args(&args)
+            .traced_output()
+            .context("Failed to run 'gh pr merge'")?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            anyhow::bail!("Failed to merge PR: {}", stderr.trim());
+        }
+
+        Ok(())
+    }
+
+    fn state(&self, owner: &str, repo: &str, pr_number: u64) -> Result<PrState> {
+        let output = Command::new("gh")
+            .args([
+                "pr",
+                "view",
+                &pr_number.to_string(),
+                "--repo",
+                &format!("{}/{}", owner, repo),
+                "--json",
+                "state",
+            ])
+            .traced_output()
+            .context("Failed to run 'gh pr view'")?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            anyhow::bail!("Failed to get PR state: {}", stderr.trim());
+        }
+
+        #[derive(Deserialize)]
+        struct StateOnly {
+            state: PrState,
+        }
+
+        let view: StateOnly =
+            serde_json::from_slice(&output.stdout).context("Failed to parse PR view output")?;
+
+        Ok(view.state)
+    }
+
     fn get_commit_count(&self, owner: &str, repo: &str, pr_number: u64) -> Result<usize> {
         let output = Command::new("gh")
             .args([
@@ -170,7 +281,7 @@ impl PrClient for RealPrClient {
                 "--json",
                 "commits",
             ])
-            .output()
+            .traced_output()
             .context("Failed to run 'gh pr view'")?;
 
         if !output.status.success() {
@@ -317,6 +428,18 @@ mod tests {
         fn add_reviewer(&self, _owner: &str, _repo: &str, _pr_number: u64, _reviewer: &str) -> Result<()> {
             Ok(())
         }
+
+        fn add_comment(&self, _owner: &str, _repo: &str, _pr_number: u64, _body: &str) -> Result<()> {
+            Ok(())
+        }
+
+        fn merge(&self, _: &str, _: &str, _: u64, _: MergeMethod, _: bool) -> Result<()> {
+            Ok(())
+        }
+
+        fn state(&self, _owner: &str, _repo: &str, _pr_number: u64) -> Result<PrState> {
+            Ok(PrState::Open)
+        }
     }
 
     #[test]
@@ -0,0 +1,100 @@
+// Pacing polls to GitHub's GraphQL rate limit.
+// A token gets a budget of points per hour, shared by everything using it.
+// A long wait polling threads and checks every few seconds can spend all of
+// it, after which every request fails until the window resets — for this
+// loop and for anything else on the same token. The threads query also asks
+// for `rateLimit`; when the remaining budget runs low, the wait loops poll
+// less often, spreading what's left over the time until the reset.
+//
+// Like the request log this is process-wide: the budget is seen deep inside
+// the threads client, and the loops that act on it are far away.
+
+use jiff::Timestamp;
+use serde::Deserialize;
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Below this fraction of the hourly budget, polling slows down.
+const LOW_FRACTION: f64 = 0.1;
+/// Points one poll is assumed to cost: threads, checks, and the odd extra
+/// query for CI details or base-branch checks.
+const POINTS_PER_POLL: u64 = 5;
+
+/// GitHub's `rateLimit` as of the last query that asked for it.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RateLimit {
+    pub limit: u64,
+    pub remaining: u64,
+    pub reset_at: Timestamp,
+}
+
+static LATEST: Mutex<Option<RateLimit>> = Mutex::new(None);
+/// The lengthened interval last warned about, to warn once per change.
+static WARNED: Mutex<Option<Duration>> = Mutex::new(None);
+
+/// Note the budget reported by a response.
+pub fn observe(rate_limit: RateLimit) {
+    *LATEST.lock().unwrap() = Some(rate_limit);
+}
+
+/// `interval`, lengthened if the last known budget is low, with a warning
+/// whenever the lengthened interval changes.
+pub fn paced(interval: Duration) -> Duration {
+    let Some(rate_limit) = LATEST.lock().unwrap().clone() else {
+        return interval;
+    };
+    let paced = rate_limit.poll_interval(interval, Timestamp::now());
+    let mut warned = WARNED.lock().unwrap();
+    if paced == interval {
+        *warned = None;
+    } else if *warned != Some(paced) {
+        eprintln!(
+            "Warning: GitHub API budget is low ({} of {} points left until {}); polling every {}s instead of every {}s",
+            rate_limit.remaining,
+            rate_limit.limit,
+            rate_limit.reset_at.strftime("%H:%M UTC"),
+            paced.as_secs(),
+            interval.as_secs()
+        );
+        *warned = Some(paced);
+    }
+    paced
+}
+
+impl RateLimit {
+    /// How often to poll instead of every `interval`: unchanged while the
+    /// budget is healthy, otherwise slow enough that what's left lasts
+    /// until the reset.
+    pub fn poll_interval(&self, interval: Duration, now: Timestamp) -> Duration {
+        if self.remaining as f64 >= self.limit as f64 * LOW_FRACTION {
+            return interval;
+        }
+        let until_reset = Duration::from_secs(self.reset_at.duration_since(now).as_secs().max(0) as u64);
+        let polls_left = (self.remaining / POINTS_PER_POLL).max(1);
+        interval.max(until_reset / polls_left as u32)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn slows_polling_only_when_budget_is_low() {
+        let now: Timestamp = "2026-10-17T12:00:00Z".parse().unwrap();
+        let reset_at: Timestamp = "2026-10-17T12:30:00Z".parse().unwrap();
+        let interval = Duration::from_secs(5);
+
+        let healthy = RateLimit { limit: 5000, remaining: 2000, reset_at };
+        assert_eq!(healthy.poll_interval(interval, now), interval);
+
+        // 100 points is 20 polls for 30 minutes: one every 90s
+        let low = RateLimit { limit: 5000, remaining: 100, reset_at };
+        assert_eq!(low.poll_interval(interval, now), Duration::from_secs(90));
+
+        // Nothing left: wait out the window in one go
+        let spent = RateLimit { limit: 5000, remaining: 0, reset_at };
+        assert_eq!(spent.poll_interval(interval, now), Duration::from_secs(1800));
+    }
+}
@@ -1,15 +1,138 @@
 // Reply to PR review threads via GitHub GraphQL API.
 // Posts comments with the Claude marker prefix.
 
-use crate::threads::CLAUDE_MARKER;
+use crate::threads::{ReplyStatus, ReviewThread, ThreadComment, ThreadsClient, CLAUDE_MARKER};
+use crate::graphql::gh_graphql;
 use anyhow::{Context, Result};
 use serde::Deserialize;
-use std::process::Command;
+use serde_json::json;
+
+/// What `reply` does when the thread was resolved before the reply went out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum IfResolved {
+    /// Don't post; a reviewer already closed the discussion.
+    #[default]
+    Skip,
+    /// Post anyway, then resolve the thread again so it stays closed.
+    Post,
+}
 
 /// Result of posting a reply.
 #[derive(Debug)]
 pub struct ReplyResult {
     pub comment_id: String,
+    /// The new comment's permalink.
+    pub url: Option<String>,
+}
+
+/// A reply to one review comment.
+#[derive(Debug, Clone)]
+pub struct ReplyRequest {
+    pub in_reply_to: String,
+    pub message: String,
+    pub status: Option<ReplyStatus>,
+    /// Quote up to this many lines of the comment being answered.
+    pub quote: Option<usize>,
+    pub if_resolved: IfResolved,
+    /// Code to suggest in place of the thread's lines.
+    pub suggestion: Option<String>,
+    /// Comments other replies answer (in a batch), which don't count as
+    /// newer comments needing acknowledgement.
+    pub answered_elsewhere: Vec<String>,
+}
+
+/// What became of a reply.
+#[derive(Debug)]
+pub enum ReplyOutcome {
+    Posted(PostedReply),
+    /// A reviewer resolved the thread in the meantime (`IfResolved::Skip`).
+    SkippedResolved { thread_id: String },
+}
+
+#[derive(Debug)]
+pub struct PostedReply {
+    pub thread_id: String,
+    /// The last comment posted (the only one unless the reply was split).
+    pub result: ReplyResult,
+    /// How many comments the reply was split into.
+    pub parts: usize,
+    /// Human comments after the one answered, which the reply acknowledged
+    /// and the invoker still needs to address.
+    pub newer_comments: Vec<ThreadComment>,
+    /// For a thread that was resolved: whether resolving it again worked.
+    pub re_resolved: Option<Result<(), String>>,
+}
+
+/// Post a reply the way `reply` does: fetch the comment's thread fresh,
+/// skip it if a reviewer resolved the thread (unless told otherwise),
+/// acknowledge human comments posted after the one answered, quote it if
+/// asked, and split replies that are too long for one comment.
+pub fn reply_to_comment(
+    threads_client: &dyn ThreadsClient,
+    reply_client: &dyn ReplyClient,
+    request: &ReplyRequest,
+) -> Result<ReplyOutcome> {
+    let in_reply_to = &request.in_reply_to;
+    let thread = threads_client
+        .fetch_thread_by_comment_id(in_reply_to)
+        .with_context(|| format!("Could not fetch thread for comment {}", in_reply_to))?;
+    let thread_id = thread.id.clone();
+
+    // The thread was fetched just now, so this catches a reviewer resolving
+    // it after the analysis that prompted this reply.
+    if thread.is_resolved && request.if_resolved == IfResolved::Skip {
+        return Ok(ReplyOutcome::SkippedResolved { thread_id });
+    }
+
+    let mut newer_comments = thread
+        .human_comments_after(in_reply_to)
+        .ok_or_else(|| anyhow::anyhow!("Comment {} not found in thread {}", in_reply_to, thread_id))?;
+    newer_comments.retain(|c| !request.answered_elsewhere.contains(&c.id));
+    let mut message = request.message.clone();
+    if let Some(code) = &request.suggestion {
+        check_suggestion_anchor(&thread)?;
+        message = format!("{}\n\n{}", message, suggestion_block(code));
+    }
+    if !newer_comments.is_empty() {
+        message.push_str(
+            "\n\n(Looks like you had something else to say here while I was working. I'll look at that now.)",
+        );
+    }
+
+    let quoted = request.quote.and_then(|lines| {
+        let comment = thread.comments.iter().find(|c| &c.id == in_reply_to)?;
+        Some((comment, lines))
+    });
+    let formatted = match quoted {
+        Some((comment, lines)) => {
+            format_claude_message_quoting(request.status, &message, &comment.author, &comment.body, lines)
+        }
+        None => format_claude_reply(request.status, &message),
+    };
+
+    let parts = split_reply(request.status, &formatted);
+    let (last_part, first_parts) = parts.split_last().expect("split_reply returns at least one part");
+    for (i, part) in first_parts.iter().enumerate() {
+        reply_client
+            .post_reply(&thread_id, part)
+            .with_context(|| format!("Failed to post part {}/{} of the reply", i + 1, parts.len()))?;
+    }
+    let result = reply_client
+        .post_reply(&thread_id, last_part)
+        .context("Failed to post reply")?;
+
+    let re_resolved = thread.is_resolved.then(|| {
+        reply_client
+            .resolve_thread(&thread_id)
+            .map_err(|e| e.to_string())
+    });
+    Ok(ReplyOutcome::Posted(PostedReply {
+        thread_id,
+        result,
+        parts: parts.len(),
+        newer_comments,
+        re_resolved,
+    }))
 }
 
 /// Trait for posting replies, allowing test implementations.
@@ -67,6 +190,8 @@ struct AddReplyPayload {
 #[derive(Deserialize)]
 struct CommentNode {
     id: String,
+    #[serde(default)]
+    url: Option<String>,
 }
 
 /// GraphQL mutation for adding a reply (loaded from graphql/operation/).
@@ -76,18 +201,7 @@ const ADD_REPLY_MUTATION: &str = include_str!("../graphql/operation/add_reply.gr
 fn post_reply_graphql(thread_id: &str, body: &str) -> Result<ReplyResult> {
     let mutation = ADD_REPLY_MUTATION;
 
-    let output = Command::new("gh")
-        .args([
-            "api",
-            "graphql",
-            "-f",
-            &format!("query={}", mutation),
-            "-f",
-            &format!("threadId={}", thread_id),
-            "-f",
-            &format!("body={}", body),
-        ])
-        .output()
+    let output = gh_graphql(mutation, json!({ "threadId": thread_id, "body": body }))
         .context("Failed to run 'gh api graphql' for reply")?;
 
     if !output.status.success() {
@@ -103,14 +217,16 @@ fn post_reply_graphql(thread_id: &str, body: &str) -> Result<ReplyResult> {
         anyhow::bail!("GraphQL errors: {}", messages.join(", "));
     }
 
-    let comment_id = response
+    let comment = response
         .data
         .and_then(|d| d.add_reply)
         .and_then(|r| r.comment)
-        .map(|c| c.id)
         .ok_or_else(|| anyhow::anyhow!("No comment ID returned from mutation"))?;
 
-    Ok(ReplyResult { comment_id })
+    Ok(ReplyResult {
+        comment_id: comment.id,
+        url: comment.url,
+    })
 }
 
 /// GraphQL mutation for deleting a comment (loaded from graphql/operation/).
@@ -120,16 +236,7 @@ const DELETE_COMMENT_MUTATION: &str = include_str!("../graphql/operation/delete_
 fn delete_comment_graphql(comment_id: &str) -> Result<()> {
     let mutation = DELETE_COMMENT_MUTATION;
 
-    let output = Command::new("gh")
-        .args([
-            "api",
-            "graphql",
-            "-f",
-            &format!("query={}", mutation),
-            "-f",
-            &format!("commentId={}", comment_id),
-        ])
-        .output()
+    let output = gh_graphql(mutation, json!({ "commentId": comment_id }))
         .context("Failed to run 'gh api graphql' for delete comment")?;
 
     if !output.status.success() {
@@ -148,18 +255,7 @@ const UPDATE_COMMENT_MUTATION: &str = include_str!("../graphql/operation/update_
 fn update_comment_graphql(comment_id: &str, body: &str) -> Result<()> {
     let mutation = UPDATE_COMMENT_MUTATION;
 
-    let output = Command::new("gh")
-        .args([
-            "api",
-            "graphql",
-            "-f",
-            &format!("query={}", mutation),
-            "-f",
-            &format!("commentId={}", comment_id),
-            "-f",
-            &format!("body={}", body),
-        ])
-        .output()
+    let output = gh_graphql(mutation, json!({ "commentId": comment_id, "body": body }))
         .context("Failed to run 'gh api graphql' for update comment")?;
 
     if !output.status.success() {
@@ -175,16 +271,7 @@ const RESOLVE_THREAD_MUTATION: &str = include_str!("../graphql/operation/resolve
 
 /// Resolve a review thread using GraphQL.
 fn resolve_thread_graphql(thread_id: &str) -> Result<()> {
-    let output = Command::new("gh")
-        .args([
-            "api",
-            "graphql",
-            "-f",
-            &format!("query={}", RESOLVE_THREAD_MUTATION),
-            "-f",
-            &format!("threadId={}", thread_id),
-        ])
-        .output()
+    let output = gh_graphql(RESOLVE_THREAD_MUTATION, json!({ "threadId": thread_id }))
         .context("Failed to run 'gh api graphql' for resolve thread")?;
 
     if !output.status.success() {
@@ -195,14 +282,269 @@ fn resolve_thread_graphql(thread_id: &str) -> Result<()> {
     Ok(())
 }
 
-/// Format the message with the Claude marker prefix.
-pub fn format_claude_message(message: &str) -> String {
-    format!("{} {}", CLAUDE_MARKER, message)
+/// A GitHub suggestion replaces the lines its thread is on, so the thread has
+/// to be on lines of the PR's current version of the file.
+fn check_suggestion_anchor(thread: &ReviewThread) -> Result<()> {
+    let problem = if thread.path.is_none() || thread.is_file_level {
+        "isn't on particular lines"
+    } else if thread.on_removed_code {
+        "is on removed lines"
+    } else if thread.is_outdated || thread.line.is_none() {
+        "is outdated (its lines have changed since)"
+    } else {
+        return Ok(());
+    };
+    anyhow::bail!(
+        "Thread {} ({}) {}, so there's nothing a suggestion could replace; reply without --suggestion",
+        thread.id,
+        thread.location(),
+        problem
+    )
+}
+
+/// `code` in a ```suggestion block, fenced with more backticks than any run
+/// in the code so a fence inside it can't end the block early.
+pub fn suggestion_block(code: &str) -> String {
+    let longest_run = code
+        .split(|c| c != '`')
+        .map(str::len)
+        .max()
+        .unwrap_or(0);
+    let fence = "`".repeat(longest_run.max(2) + 1);
+    let code = code.trim_end_matches('\n');
+    if code.is_empty() {
+        // Suggests deleting the lines
+        format!("{}suggestion\n{}", fence, fence)
+    } else {
+        format!("{}suggestion\n{}\n{}", fence, code, fence)
+    }
+}
+
+/// The code for `reply --suggestion`: the contents of the file it names, or
+/// else the argument itself.
+pub fn read_suggestion(arg: &str) -> Result<String> {
+    let path = std::path::Path::new(arg);
+    if !arg.contains('\n') && path.is_file() {
+        return std::fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()));
+    }
+    Ok(arg.to_string())
+}
+
+/// Format the message with the Claude marker prefix and, if given, the
+/// status emoji after it.
+pub fn format_claude_reply(status: Option<ReplyStatus>, message: &str) -> String {
+    format!("{} {}", claude_marker(status), message)
+}
+
+fn claude_marker(status: Option<ReplyStatus>) -> String {
+    match status {
+        Some(status) => format!("{} {}", CLAUDE_MARKER, status.emoji()),
+        None => CLAUDE_MARKER.to_string(),
+    }
+}
+
+/// GitHub rejects comment bodies longer than this many characters.
+pub const MAX_COMMENT_CHARS: usize = 65_536;
+
+/// Room kept in each part for the marker and the "(part i/n)" label.
+const PART_HEADER_CHARS: usize = 64;
+
+/// Split a formatted reply that's too long for one comment into "(part i/n)"
+/// comments, breaking at paragraph boundaries where possible (then lines,
+/// then anywhere). Every part starts with the marker, so each one still reads
+/// as Claude's. Replies that fit are returned unchanged.
+pub fn split_reply(status: Option<ReplyStatus>, formatted: &str) -> Vec<String> {
+    split_reply_at(status, formatted, MAX_COMMENT_CHARS)
+}
+
+fn split_reply_at(status: Option<ReplyStatus>, formatted: &str, max_chars: usize) -> Vec<String> {
+    if formatted.chars().count() <= max_chars {
+        return vec![formatted.to_string()];
+    }
+    let marker = claude_marker(status);
+    let text = formatted.strip_prefix(marker.as_str()).unwrap_or(formatted).trim_start();
+    let pieces = split_text(text, max_chars - PART_HEADER_CHARS);
+    let total = pieces.len();
+    pieces
+        .into_iter()
+        .enumerate()
+        .map(|(i, piece)| format!("{} (part {}/{})\n\n{}", marker, i + 1, total, piece))
+        .collect()
+}
+
+/// Pack `text` into pieces of at most `max_chars` characters.
+fn split_text(text: &str, max_chars: usize) -> Vec<String> {
+    let mut pieces = Vec::new();
+    let mut current = String::new();
+    let mut current_chars = 0;
+    for paragraph in text.split("\n\n") {
+        for unit in fit_paragraph(paragraph, max_chars) {
+            let unit_chars = unit.chars().count();
+            let needed = if current.is_empty() { unit_chars } else { current_chars + 2 + unit_chars };
+            if needed > max_chars && !current.is_empty() {
+                pieces.push(std::mem::take(&mut current));
+                current_chars = 0;
+            }
+            if !current.is_empty() {
+                current.push_str("\n\n");
+                current_chars += 2;
+            }
+            current.push_str(&unit);
+            current_chars += unit_chars;
+        }
+    }
+    if !current.is_empty() {
+        pieces.push(current);
+    }
+    pieces
+}
+
+/// Break a paragraph too long for one piece at line boundaries, cutting
+/// single lines that are still too long.
+fn fit_paragraph(paragraph: &str, max_chars: usize) -> Vec<String> {
+    if paragraph.chars().count() <= max_chars {
+        return vec![paragraph.to_string()];
+    }
+    let mut units: Vec<String> = Vec::new();
+    let mut current = String::new();
+    for line in paragraph.lines() {
+        let chars: Vec<char> = line.chars().collect();
+        for segment in chars.chunks(max_chars) {
+            let segment: String = segment.iter().collect();
+            if current.chars().count() + 1 + segment.chars().count() > max_chars && !current.is_empty() {
+                units.push(std::mem::take(&mut current));
+            }
+            if !current.is_empty() {
+                current.push('\n');
+            }
+            current.push_str(&segment);
+        }
+    }
+    if !current.is_empty() {
+        units.push(current);
+    }
+    units
+}
+
+/// Expand `{name}` placeholders in a reply template. Every placeholder must
+/// have a value, so a half-filled standard response is never posted.
+pub fn expand_template(template: &str, vars: &[(String, String)]) -> Result<String> {
+    let mut out = String::new();
+    let mut missing: Vec<&str> = Vec::new();
+    let mut rest = template;
+    while let Some(open) = rest.find('{') {
+        out.push_str(&rest[..open]);
+        let after = &rest[open + 1..];
+        let name_len = after
+            .find(|c: char| !(c.is_alphanumeric() || c == '_' || c == '-'))
+            .unwrap_or(after.len());
+        if name_len > 0 && after[name_len..].starts_with('}') {
+            let name = &after[..name_len];
+            match vars.iter().find(|(k, _)| k == name) {
+                Some((_, value)) => out.push_str(value),
+                None => {
+                    if !missing.contains(&name) {
+                        missing.push(name);
+                    }
+                }
+            }
+            rest = &after[name_len + 1..];
+        } else {
+            // Not a placeholder; keep the brace as text
+            out.push('{');
+            rest = after;
+        }
+    }
+    out.push_str(rest);
+    if !missing.is_empty() {
+        anyhow::bail!(
+            "Template needs {}",
+            missing.iter().map(|m| format!("--var {}=...", m)).collect::<Vec<_>>().join(" ")
+        );
+    }
+    Ok(out)
+}
+
+/// Format the message with the Claude marker prefix, preceded by a quote of
+/// the comment being answered so the reply reads on its own (e.g. in an
+/// email notification).
+pub fn format_claude_message_quoting(
+    status: Option<ReplyStatus>,
+    message: &str,
+    author: &str,
+    quoted: &str,
+    max_lines: usize,
+) -> String {
+    format!(
+        "{}\n{}\n\n{}",
+        claude_marker(status),
+        quote_excerpt(author, quoted, max_lines),
+        message
+    )
+}
+
+/// Quote up to `max_lines` lines of a comment as Markdown. Lines the comment
+/// itself quotes are dropped, and an ellipsis marks a cut.
+pub fn quote_excerpt(author: &str, body: &str, max_lines: usize) -> String {
+    let lines: Vec<&str> = body
+        .lines()
+        .map(str::trim_end)
+        .filter(|l| !l.trim_start().starts_with('>'))
+        .collect();
+    // Trim blank lines at either end
+    let start = lines.iter().position(|l| !l.trim().is_empty()).unwrap_or(lines.len());
+    let end = lines.iter().rposition(|l| !l.trim().is_empty()).map_or(start, |i| i + 1);
+    let lines = &lines[start..end];
+
+    let mut out = format!("> @{} wrote:", author);
+    for line in lines.iter().take(max_lines) {
+        out.push_str("\n>");
+        if !line.is_empty() {
+            out.push(' ');
+            out.push_str(line);
+        }
+    }
+    if lines.len() > max_lines {
+        out.push_str("\n> …");
+    }
+    out
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::sync::Mutex;
+
+    #[test]
+    fn short_reply_is_not_split() {
+        let body = format_claude_reply(None, "Done");
+        assert_eq!(split_reply(None, &body), vec![body]);
+    }
+
+    #[test]
+    fn long_reply_splits_at_paragraphs() {
+        let message = format!("{}\n\n{}\n\n{}", "a".repeat(60), "b".repeat(60), "ü".repeat(60));
+        let body = format_claude_reply(Some(ReplyStatus::Fixed), &message);
+        let parts = split_reply_at(Some(ReplyStatus::Fixed), &body, 200);
+        assert_eq!(
+            parts,
+            vec![
+                format!("🤖 From Claude: ✅ (part 1/2)\n\n{}\n\n{}", "a".repeat(60), "b".repeat(60)),
+                format!("🤖 From Claude: ✅ (part 2/2)\n\n{}", "ü".repeat(60)),
+            ]
+        );
+        assert!(parts.iter().all(|p| ReplyStatus::of(p) == Some(ReplyStatus::Fixed)));
+    }
+
+    #[test]
+    fn oversized_lines_are_cut() {
+        let body = format_claude_reply(None, &"x".repeat(500));
+        let parts = split_reply_at(None, &body, 200);
+        assert_eq!(parts.len(), 4);
+        assert!(parts.iter().all(|p| p.chars().count() <= 200));
+        let rejoined: String = parts.iter().map(|p| p.rsplit("\n\n").next().unwrap()).collect();
+        assert_eq!(rejoined, "x".repeat(500));
+    }
 
     /// Test client that tracks calls.
     pub struct TestReplyClient {
@@ -216,6 +558,7 @@ mod tests {
             } else {
                 Ok(ReplyResult {
                     comment_id: "test_comment_id".to_string(),
+                    url: None,
                 })
             }
         }
@@ -245,15 +588,137 @@ mod tests {
         }
     }
 
+    /// Serves one thread, whichever comment is asked about.
+    struct OneThread(ReviewThread);
+
+    impl ThreadsClient for OneThread {
+        fn fetch_threads(&self, _: &str, _: &str, _: u64) -> Result<Vec<ReviewThread>> {
+            Ok(vec![self.0.clone()])
+        }
+        fn fetch_thread_by_comment_id(&self, _: &str) -> Result<ReviewThread> {
+            Ok(self.0.clone())
+        }
+    }
+
+    /// Records the replies posted and the threads resolved.
+    #[derive(Default)]
+    struct Recording {
+        posted: Mutex<Vec<String>>,
+        resolved: Mutex<Vec<String>>,
+    }
+
+    impl ReplyClient for Recording {
+        fn post_reply(&self, _: &str, body: &str) -> Result<ReplyResult> {
+            self.posted.lock().unwrap().push(body.to_string());
+            Ok(ReplyResult { comment_id: "C2".to_string(), url: None })
+        }
+        fn delete_comment(&self, _: &str) -> Result<()> {
+            anyhow::bail!("not used")
+        }
+        fn update_comment(&self, _: &str, _: &str) -> Result<()> {
+            anyhow::bail!("not used")
+        }
+        fn resolve_thread(&self, thread_id: &str) -> Result<()> {
+            self.resolved.lock().unwrap().push(thread_id.to_string());
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn suggestions_are_fenced_and_anchored_to_current_lines() {
+        assert_eq!(suggestion_block("let x = 1;\n"), "```suggestion\nlet x = 1;\n```");
+        assert_eq!(suggestion_block(""), "```suggestion\n```");
+        assert_eq!(
+            suggestion_block("/// ```\n/// f()\n/// ```"),
+            "````suggestion\n/// ```\n/// f()\n/// ```\n````"
+        );
+
+        let mut thread = ReviewThread {
+            line: Some(4),
+            start_line: Some(3),
+            original_line: Some(4),
+            ..ReviewThread::for_test("T1", vec![ThreadComment::for_test("C1", "alice", "Use a constant")])
+        };
+        let request = ReplyRequest {
+            in_reply_to: "C1".to_string(),
+            message: "How about this?".to_string(),
+            status: None,
+            quote: None,
+            if_resolved: IfResolved::Skip,
+            suggestion: Some("const LIMIT: u32 = 10;".to_string()),
+            answered_elsewhere: vec![],
+        };
+        let posted = Recording::default();
+        reply_to_comment(&OneThread(thread.clone()), &posted, &request).unwrap();
+        assert_eq!(
+            posted.posted.lock().unwrap()[0],
+            "🤖 From Claude: How about this?\n\n```suggestion\nconst LIMIT: u32 = 10;\n```"
+        );
+
+        thread.is_outdated = true;
+        thread.line = None;
+        let err = reply_to_comment(&OneThread(thread), &posted, &request).unwrap_err();
+        assert!(err.to_string().contains("is outdated"), "{}", err);
+        assert_eq!(posted.posted.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn replies_leave_threads_resolved_as_they_found_them() {
+        let thread = ReviewThread {
+            is_resolved: true,
+            ..ReviewThread::for_test("T1", vec![ThreadComment::for_test("C1", "alice", "Use a constant")])
+        };
+        let request = ReplyRequest {
+            in_reply_to: "C1".to_string(),
+            message: "Done".to_string(),
+            status: None,
+            quote: None,
+            if_resolved: IfResolved::Post,
+            suggestion: None,
+            answered_elsewhere: vec![],
+        };
+
+        let client = Recording::default();
+        let ReplyOutcome::Posted(reply) = reply_to_comment(&OneThread(thread.clone()), &client, &request).unwrap()
+        else {
+            panic!("reply was skipped");
+        };
+        assert_eq!(reply.re_resolved, Some(Ok(())));
+        assert_eq!(*client.posted.lock().unwrap(), vec!["🤖 From Claude: Done"]);
+        assert_eq!(*client.resolved.lock().unwrap(), vec!["T1"]);
+
+        let open = ReviewThread { is_resolved: false, ..thread };
+        let client = Recording::default();
+        let ReplyOutcome::Posted(reply) = reply_to_comment(&OneThread(open), &client, &request).unwrap() else {
+            panic!("reply was skipped");
+        };
+        assert_eq!(reply.re_resolved, None);
+        assert_eq!(client.posted.lock().unwrap().len(), 1);
+        assert!(client.resolved.lock().unwrap().is_empty());
+    }
+
     #[test]
     fn format_message_adds_marker() {
-        let formatted = format_claude_message("Hello world");
+        let formatted = format_claude_reply(None, "Hello world");
         assert_eq!(formatted, "🤖 From Claude: Hello world");
     }
 
+    #[test]
+    fn format_reply_with_status() {
+        let formatted = format_claude_reply(Some(ReplyStatus::Question), "Should this be async?");
+        assert_eq!(formatted, "🤖 From Claude: ❓ Should this be async?");
+        assert_eq!(ReplyStatus::of(&formatted), Some(ReplyStatus::Question));
+
+        let quoting = format_claude_message_quoting(Some(ReplyStatus::Fixed), "Done", "bob", "Fix", 1);
+        assert_eq!(ReplyStatus::of(&quoting), Some(ReplyStatus::Fixed));
+
+        assert_eq!(ReplyStatus::of(&format_claude_reply(None, "Done")), None);
+        assert_eq!(ReplyStatus::of("✅ from a human"), None);
+    }
+
     #[test]
     fn format_message_multiline() {
-        let formatted = format_claude_message("Line 1\nLine 2");
+        let formatted = format_claude_reply(None, "Line 1\nLine 2");
         assert!(formatted.starts_with(CLAUDE_MARKER));
         assert!(formatted.contains("Line 1\nLine 2"));
     }
@@ -270,4 +735,28 @@ mod tests {
         let client = TestReplyClient { should_fail: true };
         assert!(client.post_reply("T1", "test").is_err());
     }
+
+    #[test]
+    fn quotes_excerpt_of_comment() {
+        let body = "> earlier point\n\nPlease rename this.\n\nAlso the docs.\nAnd tests.\n";
+        assert_eq!(
+            quote_excerpt("alice", body, 3),
+            "> @alice wrote:\n> Please rename this.\n>\n> Also the docs.\n> …"
+        );
+        assert_eq!(
+            format_claude_message_quoting(None, "Done", "bob", "Fix it", 5),
+            format!("{}\n> @bob wrote:\n> Fix it\n\nDone", CLAUDE_MARKER)
+        );
+    }
+
+    #[test]
+    fn expands_templates() {
+        let vars = vec![("commit".to_string(), "abc123".to_string())];
+        assert_eq!(
+            expand_template("Fixed in {commit}. See {docs page}.", &vars).unwrap(),
+            "Fixed in abc123. See {docs page}."
+        );
+        let err = expand_template("Filed {issue} for {commit}", &[]).unwrap_err();
+        assert_eq!(err.to_string(), "Template needs --var issue=... --var commit=...");
+    }
 }
@@ -0,0 +1,147 @@
+// Posting many replies in one invocation.
+// An agent working through a review often answers ten or more comments in
+// one pass. `reply-batch` takes them as a JSON list of `{in_reply_to,
+// message}` objects (optionally with a `status`) and posts each exactly as
+// `reply` would, one after another, reporting which ones went out.
+
+use crate::reply::{reply_to_comment, IfResolved, ReplyClient, ReplyOutcome, ReplyRequest};
+use crate::threads::{ReplyStatus, ThreadsClient};
+use anyhow::{Context, Result};
+use serde::Deserialize;
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct BatchEntry {
+    in_reply_to: String,
+    message: String,
+    #[serde(default)]
+    status: Option<ReplyStatus>,
+}
+
+/// Parse a batch. Each reply knows about the comments the others answer, so
+/// answering two comments in one thread doesn't make the first reply
+/// acknowledge the second comment as new.
+pub fn parse(json: &str) -> Result<Vec<ReplyRequest>> {
+    let entries: Vec<BatchEntry> = serde_json::from_str(json)
+        .context("Expected a JSON list of {\"in_reply_to\": ..., \"message\": ...} objects")?;
+    let answered: Vec<String> = entries.iter().map(|e| e.in_reply_to.clone()).collect();
+    Ok(entries
+        .into_iter()
+        .map(|e| ReplyRequest {
+            answered_elsewhere: answered.iter().filter(|id| **id != e.in_reply_to).cloned().collect(),
+            in_reply_to: e.in_reply_to,
+            message: e.message,
+            status: e.status,
+            quote: None,
+            if_resolved: IfResolved::Skip,
+            suggestion: None,
+        })
+        .collect())
+}
+
+/// Post every reply in the requests' order, so replies to one thread land
+/// in the order they were written. One failing doesn't stop the others.
+pub fn post_all(
+    threads_client: &dyn ThreadsClient,
+    reply_client: &dyn ReplyClient,
+    requests: &[ReplyRequest],
+) -> Vec<Result<ReplyOutcome>> {
+    requests
+        .iter()
+        .map(|request| reply_to_comment(threads_client, reply_client, request))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::reply::ReplyResult;
+    use crate::threads::{ReviewThread, ThreadComment};
+    use std::sync::Mutex;
+
+    fn comment(id: &str) -> ThreadComment {
+        ThreadComment::for_test(id, "reviewer", &format!("Comment {}", id))
+    }
+
+    /// One thread with comments C1 and C2; any other comment is unknown.
+    struct OneThread;
+
+    impl ThreadsClient for OneThread {
+        fn fetch_threads(&self, _: &str, _: &str, _: u64) -> Result<Vec<ReviewThread>> {
+            Ok(vec![])
+        }
+
+        fn fetch_thread_by_comment_id(&self, comment_id: &str) -> Result<ReviewThread> {
+            anyhow::ensure!(comment_id.starts_with('C'), "No such comment {}", comment_id);
+            Ok(ReviewThread::for_test("T1", vec![comment("C1"), comment("C2")]))
+        }
+    }
+
+    #[derive(Default)]
+    struct RecordingReplies(Mutex<Vec<String>>);
+
+    impl ReplyClient for RecordingReplies {
+        fn post_reply(&self, _thread_id: &str, body: &str) -> Result<ReplyResult> {
+            self.0.lock().unwrap().push(body.to_string());
+            Ok(ReplyResult {
+                comment_id: "R".to_string(),
+                url: None,
+            })
+        }
+        fn delete_comment(&self, _: &str) -> Result<()> {
+            Ok(())
+        }
+        fn update_comment(&self, _: &str, _: &str) -> Result<()> {
+            Ok(())
+        }
+        fn resolve_thread(&self, _: &str) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn posts_each_reply_and_reports_failures() {
+        let requests = parse(
+            r#"[
+                {"in_reply_to": "C1", "message": "Renamed", "status": "fixed"},
+                {"in_reply_to": "X9", "message": "Lost"},
+                {"in_reply_to": "C2", "message": "Added a test"}
+            ]"#,
+        )
+        .unwrap();
+        let replies = RecordingReplies::default();
+        let results = post_all(&OneThread, &replies, &requests);
+
+        assert!(matches!(results[0], Ok(ReplyOutcome::Posted(ref p)) if p.newer_comments.is_empty()));
+        assert!(results[1].is_err());
+        assert!(results[2].is_ok());
+        let mut posted = replies.0.into_inner().unwrap();
+        posted.sort();
+        // C2 is answered in the same batch, so the reply to C1 doesn't
+        // acknowledge it as a newer comment
+        assert!(posted[0].ends_with("Added a test"));
+        assert!(posted[1].ends_with("✅ Renamed"));
+    }
+
+    #[test]
+    fn replies_to_one_thread_land_in_batch_order() {
+        let requests = parse(
+            r#"[
+                {"in_reply_to": "C2", "message": "First"},
+                {"in_reply_to": "C1", "message": "Second"}
+            ]"#,
+        )
+        .unwrap();
+        let replies = RecordingReplies::default();
+        post_all(&OneThread, &replies, &requests);
+
+        let posted = replies.0.into_inner().unwrap();
+        assert_eq!(posted, vec!["🤖 From Claude: First", "🤖 From Claude: Second"]);
+    }
+
+    #[test]
+    fn rejects_malformed_batches() {
+        assert!(parse(r#"[{"in_reply_to": "C1"}]"#).is_err());
+        assert!(parse(r#"{"in_reply_to": "C1", "message": "x"}"#).is_err());
+    }
+}
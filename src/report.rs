@@ -0,0 +1,281 @@
+// JSON form of the default analysis (`pr-loop --format json`).
+// Carries what the Markdown recommendation shows (the next action, the
+// actionable threads with the IDs to reply to, every check's status, and the
+// CI failure details) as fields, so wrapper scripts and other agents don't
+// have to parse prose. Threads, the action, and failing checks have the same
+// shape as in the `context` briefing, and the report is versioned alongside
+// it: one `schema_version` numbering, one `--output-version`.
+
+use crate::analysis::NextAction;
+use crate::annotations::Annotation;
+use crate::base_failures::BaseFailure;
+use crate::checks::{CheckStatus, ChecksSummary};
+use crate::circleci::{CircleCiFailureInfo, FailedStepLog};
+use crate::context::{failing_checks, ActionSummary, FailingCheck, ThreadSummary, SCHEMA_VERSION};
+use crate::escalation::StuckItem;
+use crate::github::{MergeableStatus, PrContext};
+use crate::log_window::{truncate_log, truncate_log_tail, STDERR_BUDGET, STDOUT_BUDGET};
+use crate::streaks::FailureStreak;
+use serde::Serialize;
+
+/// JSON Schema for each report version, oldest first.
+const REPORT_SCHEMAS: &[&str] = &[include_str!("../schema/analysis.v1.schema.json")];
+
+/// JSON Schema for a report version (1..=SCHEMA_VERSION).
+pub fn report_schema(version: u32) -> Option<&'static str> {
+    REPORT_SCHEMAS.get((version as usize).checked_sub(1)?).copied()
+}
+
+#[derive(Debug, Serialize)]
+pub struct AnalysisReport {
+    /// Always SCHEMA_VERSION.
+    pub schema_version: u32,
+    pub pr: PrRef,
+    pub next_action: ActionSummary,
+    /// Everything the PR needs, most pressing first; `next_action` is the
+    /// first. The rest are filled in by the caller.
+    pub actions: Vec<ActionSummary>,
+    /// Threads needing a response; empty unless that's the next action.
+    pub threads: Vec<ThreadSummary>,
+    pub checks: Vec<CheckEntry>,
+    pub failing_checks: Vec<FailingCheck>,
+    pub logs: Vec<LogExcerpt>,
+    /// Checks that have failed several runs in a row; filled in by the caller
+    /// from the checkout's recorded history.
+    pub failure_streaks: Vec<FailureStreak>,
+    /// Failing checks whose latest run on the base branch failed too.
+    pub failing_on_base: Vec<BaseFailure>,
+    /// What the loop is stuck on, when it's stopping to ask a human for help
+    /// (see `[escalation]`).
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub escalation: Vec<StuckItem>,
+    /// "mergeable", "conflicting", or "unknown"
+    pub mergeable: &'static str,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PrRef {
+    pub owner: String,
+    pub repo: String,
+    pub number: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CheckEntry {
+    pub name: String,
+    /// "pass", "fail", "pending", "skipping", or "cancelled"
+    pub status: &'static str,
+    pub url: Option<String>,
+    pub attempts: u32,
+}
+
+/// A failed step's log, truncated as in the Markdown output.
+#[derive(Debug, Serialize)]
+pub struct LogExcerpt {
+    pub job: String,
+    pub step: String,
+    pub container: Option<String>,
+    /// The start of stderr.
+    pub stderr: String,
+    /// The tail of stdout.
+    pub stdout: String,
+    /// Everything captured of the log, uploaded with `--upload-logs`.
+    pub full_log_url: Option<String>,
+}
+
+impl LogExcerpt {
+    fn new(log: &FailedStepLog) -> Self {
+        Self {
+            job: log.job_name.clone(),
+            step: log.step_name.clone(),
+            container: log.container.clone(),
+            stderr: truncate_log(&log.error, STDERR_BUDGET),
+            stdout: truncate_log_tail(&log.output, STDOUT_BUDGET),
+            full_log_url: log.full_log_url.clone(),
+        }
+    }
+}
+
+fn status_label(status: &CheckStatus) -> &'static str {
+    match status {
+        CheckStatus::Pass => "pass",
+        CheckStatus::Fail => "fail",
+        CheckStatus::Pending => "pending",
+        CheckStatus::Skipping => "skipping",
+        CheckStatus::Cancelled => "cancelled",
+    }
+}
+
+impl AnalysisReport {
+    pub fn new(
+        pr_context: &PrContext,
+        checks: &ChecksSummary,
+        action: &NextAction,
+        ci_info: &CircleCiFailureInfo,
+        annotations: &[Annotation],
+        mergeable: &MergeableStatus,
+    ) -> Self {
+        let threads = match action {
+            NextAction::RespondToComments { threads, .. } => {
+                threads.iter().map(ThreadSummary::new).collect()
+            }
+            _ => vec![],
+        };
+        Self {
+            schema_version: SCHEMA_VERSION,
+            pr: PrRef {
+                owner: pr_context.owner.clone(),
+                repo: pr_context.repo.clone(),
+                number: pr_context.pr_number,
+            },
+            next_action: ActionSummary::new(action),
+            actions: vec![ActionSummary::new(action)],
+            threads,
+            checks: checks
+                .checks
+                .iter()
+                .map(|c| CheckEntry {
+                    name: c.name.clone(),
+                    status: status_label(&c.status),
+                    url: c.url.clone(),
+                    attempts: c.attempts,
+                })
+                .collect(),
+            failing_checks: failing_checks(checks, annotations, ci_info),
+            logs: ci_info.step_logs.iter().map(LogExcerpt::new).collect(),
+            failure_streaks: vec![],
+            failing_on_base: vec![],
+            escalation: vec![],
+            mergeable: match mergeable {
+                MergeableStatus::Mergeable => "mergeable",
+                MergeableStatus::Conflicting => "conflicting",
+                MergeableStatus::Unknown => "unknown",
+            },
+        }
+    }
+
+    /// JSON in the given schema version (1..=SCHEMA_VERSION). Version 1 is
+    /// the only one so far, so there's nothing to downgrade yet.
+    pub fn to_json(&self, version: u32) -> String {
+        let mut value = serde_json::to_value(self).expect("report serializes");
+        value["schema_version"] = version.into();
+        serde_json::to_string_pretty(&value).expect("report serializes")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::checks::{Check, CheckSource};
+    use crate::context::conforms;
+
+    #[test]
+    fn reports_failures_as_fields() {
+        let check = |name: &str, status| Check {
+            name: name.to_string(),
+            status,
+            url: Some(format!("https://ci/{}", name)),
+            source: CheckSource::CheckRun,
+            started_at: None,
+            attempts: 1,
+            app: None,
+        };
+        let checks = ChecksSummary {
+            checks: vec![check("lint", CheckStatus::Pass), check("ci/circleci: test", CheckStatus::Fail)],
+        };
+        let ci_info = CircleCiFailureInfo {
+            step_logs: vec![FailedStepLog {
+                job_name: "test".to_string(),
+                step_name: "Run tests".to_string(),
+                output: "running 3 tests\ntest foo ... FAILED".to_string(),
+                error: String::new(),
+                container: None,
+                full_log_url: None,
+            }],
+            ..Default::default()
+        };
+        let pr_context = PrContext {
+            owner: "o".to_string(),
+            repo: "r".to_string(),
+            pr_number: 7,
+        };
+        let action = NextAction::FixCiFailures {
+            failed_check_names: vec!["ci/circleci: test".to_string()],
+        };
+
+        let report = AnalysisReport::new(
+            &pr_context,
+            &checks,
+            &action,
+            &ci_info,
+            &[],
+            &MergeableStatus::Mergeable,
+        );
+        let json: serde_json::Value = serde_json::from_str(&report.to_json(SCHEMA_VERSION)).unwrap();
+        assert_eq!(json["schema_version"], SCHEMA_VERSION);
+        assert_eq!(json["pr"]["number"], 7);
+        assert_eq!(json["next_action"]["kind"], "fix_ci_failures");
+        assert_eq!(json["checks"][1]["status"], "fail");
+        assert_eq!(json["failing_checks"][0]["name"], "ci/circleci: test");
+        assert_eq!(json["logs"][0]["step"], "Run tests");
+        assert_eq!(json["mergeable"], "mergeable");
+    }
+
+    #[test]
+    fn every_version_matches_its_published_schema() {
+        let check = Check {
+            name: "test".to_string(),
+            status: CheckStatus::Fail,
+            url: None,
+            source: CheckSource::CheckRun,
+            started_at: None,
+            attempts: 2,
+            app: None,
+        };
+        let ci_info = CircleCiFailureInfo {
+            step_logs: vec![FailedStepLog {
+                job_name: "test".to_string(),
+                step_name: "Run tests".to_string(),
+                output: "FAILED".to_string(),
+                error: "boom".to_string(),
+                container: Some("container 1/2".to_string()),
+                full_log_url: None,
+            }],
+            ..Default::default()
+        };
+        let pr_context = PrContext {
+            owner: "o".to_string(),
+            repo: "r".to_string(),
+            pr_number: 7,
+        };
+        let mut report = AnalysisReport::new(
+            &pr_context,
+            &ChecksSummary { checks: vec![check] },
+            &NextAction::FixCiFailures {
+                failed_check_names: vec!["test".to_string()],
+            },
+            &ci_info,
+            &[],
+            &MergeableStatus::Unknown,
+        );
+        report.escalation = vec![StuckItem::Check {
+            name: "test".to_string(),
+            runs: 3,
+            history: vec!["abc1234".to_string()],
+            errors: vec!["boom".to_string()],
+        }];
+
+        assert!(report_schema(0).is_none());
+        assert!(report_schema(SCHEMA_VERSION + 1).is_none());
+        for version in 1..=SCHEMA_VERSION {
+            let schema: serde_json::Value = serde_json::from_str(report_schema(version).unwrap()).unwrap();
+            assert_eq!(schema["properties"]["schema_version"]["const"], version);
+            let json: serde_json::Value = serde_json::from_str(&report.to_json(version)).unwrap();
+            conforms(&json, &schema, "$").unwrap_or_else(|e| panic!("v{}: {}", version, e));
+
+            let mut broken = json.clone();
+            broken["checks"][0]["status"] = "flaky".into();
+            assert!(conforms(&broken, &schema, "$").is_err());
+        }
+    }
+}
@@ -0,0 +1,345 @@
+// Reproducing CI failures locally.
+// The repository's toolchain is detected from its manifest (Cargo.toml,
+// package.json, pyproject.toml), and each failing test extracted from CI is
+// turned into the command that runs just that test, so the agent can check
+// a fix without waiting for another CI round. `pr-loop repro` runs those
+// commands (or ones configured per check) and reports whether the failure
+// reproduces.
+
+use crate::circleci::TestFailure;
+use anyhow::{Context, Result};
+use std::path::Path;
+use std::process::Command;
+
+/// How many per-test commands to suggest before falling back to the suite.
+const MAX_TEST_COMMANDS: usize = 5;
+
+/// A JavaScript package manager, chosen by lockfile.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PackageManager {
+    Npm,
+    Pnpm,
+    Yarn,
+}
+
+impl PackageManager {
+    fn command(self) -> &'static str {
+        match self {
+            Self::Npm => "npm",
+            Self::Pnpm => "pnpm",
+            Self::Yarn => "yarn",
+        }
+    }
+}
+
+/// The build toolchain a repository uses.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Toolchain {
+    Cargo,
+    Node(PackageManager),
+    Python,
+}
+
+impl Toolchain {
+    /// Detect the toolchain from the manifest at the repository root. When
+    /// several are present, the first of Cargo, Node, Python wins.
+    pub fn detect(root: &Path) -> Option<Self> {
+        if root.join("Cargo.toml").is_file() {
+            return Some(Self::Cargo);
+        }
+        if root.join("package.json").is_file() {
+            let manager = if root.join("pnpm-lock.yaml").is_file() {
+                PackageManager::Pnpm
+            } else if root.join("yarn.lock").is_file() {
+                PackageManager::Yarn
+            } else {
+                PackageManager::Npm
+            };
+            return Some(Self::Node(manager));
+        }
+        if root.join("pyproject.toml").is_file() {
+            return Some(Self::Python);
+        }
+        None
+    }
+
+    /// The command that runs the whole test suite.
+    pub fn suite_command(self) -> String {
+        match self {
+            Self::Cargo => "cargo test".to_string(),
+            Self::Node(manager) => format!("{} test", manager.command()),
+            Self::Python => "pytest".to_string(),
+        }
+    }
+
+    /// The command that runs just the failing test.
+    pub fn test_command(self, failure: &TestFailure) -> String {
+        match self {
+            Self::Cargo => match cargo_package(&failure.classname) {
+                Some(package) => format!("cargo test -p {} {}", package, failure.test_name),
+                None => format!("cargo test {}", failure.test_name),
+            },
+            Self::Node(manager) => format!(
+                "{} test -- -t {}",
+                manager.command(),
+                shell_quote(&failure.test_name)
+            ),
+            Self::Python => format!("pytest {}", pytest_node_id(failure)),
+        }
+    }
+}
+
+/// Commands reproducing `failures` locally, one per distinct test. Past
+/// `MAX_TEST_COMMANDS` tests, the whole suite is suggested instead.
+pub fn suggest_commands(toolchain: Toolchain, failures: &[TestFailure]) -> Vec<String> {
+    let mut commands: Vec<String> = Vec::new();
+    for failure in failures {
+        let command = toolchain.test_command(failure);
+        if !commands.contains(&command) {
+            commands.push(command);
+        }
+    }
+    if commands.len() > MAX_TEST_COMMANDS {
+        return vec![toolchain.suite_command()];
+    }
+    commands
+}
+
+/// Fill in a `[repro.commands]` template: `{check}` is the check's name and
+/// `{tests}` the failing tests' names, shell-quoted and space-separated.
+pub fn render_template(template: &str, check: &str, failures: &[TestFailure]) -> String {
+    let mut tests: Vec<String> = Vec::new();
+    for failure in failures {
+        let quoted = shell_quote(&failure.test_name);
+        if !tests.contains(&quoted) {
+            tests.push(quoted);
+        }
+    }
+    template
+        .replace("{check}", &shell_quote(check))
+        .replace("{tests}", &tests.join(" "))
+}
+
+/// The commands reproducing a failed check: the configured template if
+/// there is one, else the toolchain's commands for the failing tests (or its
+/// whole suite if none were reported). None if there's neither.
+pub fn commands_for(
+    template: Option<&str>,
+    toolchain: Option<Toolchain>,
+    check: &str,
+    failures: &[TestFailure],
+) -> Option<Vec<String>> {
+    if let Some(template) = template {
+        return Some(vec![render_template(template, check, failures)]);
+    }
+    let toolchain = toolchain?;
+    if failures.is_empty() {
+        return Some(vec![toolchain.suite_command()]);
+    }
+    Some(suggest_commands(toolchain, failures))
+}
+
+/// Commands to suggest for a failed check: the configured one, or per-test
+/// commands when CI reported failing tests. Unlike `commands_for`, this never
+/// falls back to the whole suite, since the check may not run tests at all.
+pub fn suggested_commands(
+    template: Option<&str>,
+    toolchain: Option<Toolchain>,
+    check: &str,
+    failures: &[TestFailure],
+) -> Vec<String> {
+    match (template, toolchain) {
+        (Some(template), _) => vec![render_template(template, check, failures)],
+        (None, Some(toolchain)) => suggest_commands(toolchain, failures),
+        (None, None) => vec![],
+    }
+}
+
+/// Run `command` through the shell in `dir` with its output passed through.
+/// Returns whether it succeeded.
+pub fn run(command: &str, dir: &Path) -> Result<bool> {
+    let mut shell = if cfg!(windows) {
+        let mut c = Command::new("cmd");
+        c.args(["/C", command]);
+        c
+    } else {
+        let mut c = Command::new("sh");
+        c.args(["-c", command]);
+        c
+    };
+    let status = shell
+        .current_dir(dir)
+        .status()
+        .with_context(|| format!("Failed to run `{}`", command))?;
+    Ok(status.success())
+}
+
+/// Run `command` in a container of `image` via docker, with `dir` mounted
+/// at `/repo` as the working directory. Returns whether it succeeded.
+pub fn run_in_image(command: &str, dir: &Path, image: &str) -> Result<bool> {
+    let status = Command::new("docker")
+        .args(docker_args(command, dir, image))
+        .status()
+        .context("Failed to run docker (is it installed and running?)")?;
+    Ok(status.success())
+}
+
+fn docker_args(command: &str, dir: &Path, image: &str) -> Vec<String> {
+    vec![
+        "run".to_string(),
+        "--rm".to_string(),
+        "--volume".to_string(),
+        format!("{}:/repo", dir.display()),
+        "--workdir".to_string(),
+        "/repo".to_string(),
+        image.to_string(),
+        "sh".to_string(),
+        "-c".to_string(),
+        command.to_string(),
+    ]
+}
+
+/// The package a cargo JUnit classname names. cargo-nextest uses the test
+/// binary's ID (`my-crate` or `my-crate::integration`) as the classname.
+fn cargo_package(classname: &str) -> Option<&str> {
+    let package = classname.split("::").next()?;
+    let valid = !package.is_empty()
+        && package
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    valid.then_some(package)
+}
+
+/// A pytest node ID (`tests/test_api.py::TestAuth::test_login`) from a JUnit
+/// classname (`tests.test_api.TestAuth`) and test name. Capitalized trailing
+/// segments are classes; the rest is the module path.
+fn pytest_node_id(failure: &TestFailure) -> String {
+    let segments: Vec<&str> = failure.classname.split('.').filter(|s| !s.is_empty()).collect();
+    let module_len = segments
+        .iter()
+        .position(|s| s.starts_with(|c: char| c.is_ascii_uppercase()))
+        .unwrap_or(segments.len());
+    if module_len == 0 {
+        return format!("-k {}", shell_quote(&failure.test_name));
+    }
+    let mut id = format!("{}.py", segments[..module_len].join("/"));
+    for part in segments[module_len..].iter().chain([&failure.test_name.as_str()]) {
+        id.push_str("::");
+        id.push_str(part);
+    }
+    shell_quote(&id)
+}
+
+/// Quote `s` for a POSIX shell unless it's made only of safe characters.
+fn shell_quote(s: &str) -> String {
+    let safe = !s.is_empty()
+        && s.chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_./:=@".contains(c));
+    if safe {
+        s.to_string()
+    } else {
+        format!("'{}'", s.replace('\'', r"'\''"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn failure(classname: &str, test_name: &str) -> TestFailure {
+        TestFailure {
+            job_name: "test".to_string(),
+            classname: classname.to_string(),
+            test_name: test_name.to_string(),
+            message: String::new(),
+            location: None,
+        }
+    }
+
+    #[test]
+    fn detects_toolchain_from_manifest() {
+        let dir = std::env::temp_dir().join(format!("pr-loop-repro-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        assert_eq!(Toolchain::detect(&dir), None);
+        std::fs::write(dir.join("pyproject.toml"), "").unwrap();
+        assert_eq!(Toolchain::detect(&dir), Some(Toolchain::Python));
+        std::fs::write(dir.join("package.json"), "{}").unwrap();
+        std::fs::write(dir.join("yarn.lock"), "").unwrap();
+        assert_eq!(Toolchain::detect(&dir), Some(Toolchain::Node(PackageManager::Yarn)));
+        std::fs::write(dir.join("Cargo.toml"), "").unwrap();
+        assert_eq!(Toolchain::detect(&dir), Some(Toolchain::Cargo));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn per_test_commands() {
+        assert_eq!(
+            Toolchain::Cargo.test_command(&failure("pr-loop::integration", "wait::times_out")),
+            "cargo test -p pr-loop wait::times_out"
+        );
+        assert_eq!(
+            Toolchain::Node(PackageManager::Npm).test_command(&failure("Login", "rejects bad password")),
+            "npm test -- -t 'rejects bad password'"
+        );
+        assert_eq!(
+            Toolchain::Python.test_command(&failure("tests.test_api.TestAuth", "test_login")),
+            "pytest tests/test_api.py::TestAuth::test_login"
+        );
+        assert_eq!(
+            Toolchain::Python.test_command(&failure("", "test_login")),
+            "pytest -k test_login"
+        );
+    }
+
+    #[test]
+    fn configured_template_wins() {
+        let failures = vec![failure("app", "it works"), failure("app", "it works")];
+        assert_eq!(
+            commands_for(Some("make test TESTS=\"{tests}\" # {check}"), Some(Toolchain::Cargo), "ci/test", &failures),
+            Some(vec!["make test TESTS=\"'it works'\" # ci/test".to_string()])
+        );
+        assert_eq!(
+            commands_for(None, Some(Toolchain::Python), "ci/test", &[]),
+            Some(vec!["pytest".to_string()])
+        );
+        assert_eq!(commands_for(None, None, "ci/test", &failures), None);
+    }
+
+    #[test]
+    fn suggestions_need_tests_or_config() {
+        assert!(suggested_commands(None, Some(Toolchain::Cargo), "lint", &[]).is_empty());
+        assert_eq!(
+            suggested_commands(Some("make lint"), Some(Toolchain::Cargo), "lint", &[]),
+            vec!["make lint"]
+        );
+        assert_eq!(
+            suggested_commands(None, Some(Toolchain::Cargo), "test", &[failure("app", "t1")]),
+            vec!["cargo test -p app t1"]
+        );
+    }
+
+    #[test]
+    fn run_reports_success() {
+        let dir = std::env::temp_dir();
+        assert!(run("exit 0", &dir).unwrap());
+        assert!(!run("exit 3", &dir).unwrap());
+    }
+
+    #[test]
+    fn docker_mounts_the_checkout() {
+        assert_eq!(
+            docker_args("cargo test", Path::new("/src/app"), "cimg/rust:1.80").join(" "),
+            "run --rm --volume /src/app:/repo --workdir /repo cimg/rust:1.80 sh -c cargo test"
+        );
+    }
+
+    #[test]
+    fn many_failures_suggest_the_suite() {
+        let few: Vec<_> = (0..2).map(|i| failure("app", &format!("t{}", i))).collect();
+        assert_eq!(suggest_commands(Toolchain::Cargo, &few).len(), 2);
+        let many: Vec<_> = (0..MAX_TEST_COMMANDS + 1)
+            .map(|i| failure("app", &format!("t{}", i)))
+            .collect();
+        assert_eq!(suggest_commands(Toolchain::Cargo, &many), vec!["cargo test"]);
+    }
+}
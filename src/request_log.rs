@@ -0,0 +1,340 @@
+// Metadata log of the gh commands pr-loop runs.
+// When an analysis looks wrong, the first question is what GitHub actually
+// answered. Every gh invocation is appended to `.pr-loop/requests.log` as a
+// JSON line — the (redacted) command, exit code, timing, and output size —
+// so `pr-loop debug-bundle` can include the recent history. Request bodies,
+// comment text, and output are never logged, and anything token-shaped is
+// scrubbed.
+//
+// Unlike the audit log this is a process-wide sink, set up once by main:
+// gh is run from dozens of places, most of them free functions, and
+// threading a logger through each would touch every client signature.
+
+use crate::audit::STATE_DIR;
+use serde::{Deserialize, Serialize};
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+use std::sync::OnceLock;
+use std::time::Instant;
+
+const REQUESTS_FILE: &str = "requests.log";
+/// Entries kept when the log is trimmed.
+pub const MAX_ENTRIES: usize = 500;
+/// Longest stderr excerpt recorded.
+const MAX_STDERR_CHARS: usize = 300;
+
+/// `-f`/`-F` fields whose values are identifiers, safe to record as-is.
+/// Anything else (comment bodies, PR descriptions) is replaced by its size.
+const SAFE_FIELDS: &[&str] = &[
+    "owner", "repo", "pr", "number", "count", "cursor", "after", "first", "id", "threadId",
+    "commentId", "subjectId",
+];
+
+/// Flags whose value is free text written by the user or the agent.
+const TEXT_FLAGS: &[&str] = &["--body", "-b", "--title", "-t"];
+
+static LOG_PATH: OnceLock<PathBuf> = OnceLock::new();
+
+/// One recorded gh invocation.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RequestEntry {
+    pub at: String,
+    /// The redacted command line, e.g. `gh api graphql query=<FetchThreads> owner=o ...`.
+    pub command: String,
+    /// None if gh couldn't be started or was killed by a signal.
+    pub exit_code: Option<i32>,
+    pub duration_ms: u64,
+    pub stdout_bytes: usize,
+    /// First line of stderr, redacted.
+    pub stderr: String,
+}
+
+/// Start recording to `<root>/.pr-loop/requests.log`. Until this is called
+/// (and in tests) nothing is recorded.
+pub fn init(root: &Path) {
+    let path = log_path(root);
+    trim(&path, MAX_ENTRIES);
+    let _ = LOG_PATH.set(path);
+}
+
+/// Path of the request log under `root`.
+pub fn log_path(root: &Path) -> PathBuf {
+    root.join(STATE_DIR).join(REQUESTS_FILE)
+}
+
+/// Run commands with their metadata recorded in the request log.
+pub trait TracedOutput {
+    /// Like `Command::output`, but records the invocation.
+    fn traced_output(&mut self) -> std::io::Result<Output>;
+}
+
+impl TracedOutput for Command {
+    fn traced_output(&mut self) -> std::io::Result<Output> {
+        let started = Instant::now();
+        let result = self.output();
+        record(self, started, result.as_ref().ok());
+        result
+    }
+}
+
+/// Record a finished invocation. For commands run with `spawn` (to pipe a
+/// body to stdin), call this after `wait_with_output`.
+pub fn record(command: &Command, started: Instant, output: Option<&Output>) {
+    record_entry(redact_command(command), started, output);
+}
+
+/// Record a `gh api graphql --input -` invocation. The request body isn't on
+/// the command line, so the operation name and variables are logged as if
+/// they had been passed as fields, with the same redaction.
+pub fn record_graphql(
+    command: &Command,
+    started: Instant,
+    output: Option<&Output>,
+    query: &str,
+    variables: &serde_json::Value,
+) {
+    record_entry(
+        format!("{} {}", redact_command(command), redact_graphql(query, variables)),
+        started,
+        output,
+    );
+}
+
+fn record_entry(command: String, started: Instant, output: Option<&Output>) {
+    let Some(path) = LOG_PATH.get() else { return };
+    let entry = RequestEntry {
+        at: jiff::Timestamp::now().to_string(),
+        command,
+        exit_code: output.and_then(|o| o.status.code()),
+        duration_ms: started.elapsed().as_millis() as u64,
+        stdout_bytes: output.map_or(0, |o| o.stdout.len()),
+        stderr: output
+            .map(|o| {
+                let stderr = String::from_utf8_lossy(&o.stderr);
+                let first = stderr.lines().next().unwrap_or("");
+                scrub_tokens(first).chars().take(MAX_STDERR_CHARS).collect()
+            })
+            .unwrap_or_else(|| "failed to start".to_string()),
+    };
+    // Best-effort: a diagnostics log must never break the command itself.
+    let _ = append(path, &entry);
+}
+
+fn append(path: &Path, entry: &RequestEntry) -> anyhow::Result<()> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+        let gitignore = dir.join(".gitignore");
+        if !gitignore.exists() {
+            std::fs::write(&gitignore, "*\n")?;
+        }
+    }
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(file, "{}", serde_json::to_string(entry)?)?;
+    Ok(())
+}
+
+/// Keep the log from growing without bound: once it has twice `keep`
+/// entries, drop all but the last `keep`.
+fn trim(path: &Path, keep: usize) {
+    let Ok(content) = std::fs::read_to_string(path) else { return };
+    let lines: Vec<&str> = content.lines().collect();
+    if lines.len() < keep * 2 {
+        return;
+    }
+    let kept = lines[lines.len() - keep..].join("\n") + "\n";
+    let _ = std::fs::write(path, kept);
+}
+
+/// Read the last `n` entries, skipping lines that don't parse.
+pub fn recent_entries(path: &Path, n: usize) -> Vec<RequestEntry> {
+    let Ok(content) = std::fs::read_to_string(path) else {
+        return vec![];
+    };
+    let entries: Vec<RequestEntry> = content
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect();
+    entries[entries.len().saturating_sub(n)..].to_vec()
+}
+
+fn redact_command(command: &Command) -> String {
+    let program = command.get_program().to_string_lossy().into_owned();
+    let args: Vec<String> = command
+        .get_args()
+        .map(|a| a.to_string_lossy().into_owned())
+        .collect();
+    let mut parts = vec![program];
+    parts.extend(redact_args(&args));
+    parts.join(" ")
+}
+
+/// Redact a gh argument list. GraphQL queries are reduced to their operation
+/// name, field values outside SAFE_FIELDS and free-text flag values to their
+/// size, and token-shaped strings anywhere are scrubbed.
+pub fn redact_args(args: &[String]) -> Vec<String> {
+    let mut out = Vec::with_capacity(args.len());
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        if matches!(arg.as_str(), "-f" | "-F" | "--raw-field" | "--field") {
+            if let Some(field) = iter.next() {
+                out.push(redact_field(field));
+            }
+        } else if TEXT_FLAGS.contains(&arg.as_str()) {
+            out.push(arg.clone());
+            if let Some(value) = iter.next() {
+                out.push(format!("<{} bytes>", value.len()));
+            }
+        } else {
+            out.push(scrub_tokens(arg));
+        }
+    }
+    out
+}
+
+/// Redact a GraphQL request body the way `redact_args` would its fields.
+fn redact_graphql(query: &str, variables: &serde_json::Value) -> String {
+    let mut fields = vec![redact_field(&format!("query={}", query))];
+    if let Some(variables) = variables.as_object() {
+        for (key, value) in variables {
+            let value = match value {
+                serde_json::Value::String(s) => s.clone(),
+                other => other.to_string(),
+            };
+            fields.push(redact_field(&format!("{}={}", key, value)));
+        }
+    }
+    fields.join(" ")
+}
+
+fn redact_field(field: &str) -> String {
+    let Some((key, value)) = field.split_once('=') else {
+        return scrub_tokens(field);
+    };
+    if key == "query" {
+        return format!("query=<{}>", operation_name(value).unwrap_or("anonymous"));
+    }
+    if SAFE_FIELDS.contains(&key) {
+        return format!("{}={}", key, scrub_tokens(value));
+    }
+    format!("{}=<{} bytes>", key, value.len())
+}
+
+/// Name of a GraphQL operation, e.g. `FetchThreads` for
+/// `query FetchThreads($owner: String!) { ... }`.
+fn operation_name(query: &str) -> Option<&str> {
+    let rest = query
+        .trim_start()
+        .strip_prefix("query")
+        .or_else(|| query.trim_start().strip_prefix("mutation"))?;
+    let name = rest
+        .trim_start()
+        .split(|c: char| !c.is_alphanumeric() && c != '_')
+        .next()?;
+    (!name.is_empty()).then_some(name)
+}
+
+/// Prefixes of GitHub and CircleCI credentials.
+const TOKEN_PREFIXES: &[&str] = &["ghp_", "gho_", "ghu_", "ghs_", "ghr_", "github_pat_", "CCIPAT_"];
+
+/// Replace anything that looks like a credential with `<redacted>`: known
+/// token prefixes, and the word after `Bearer`/`token`.
+pub fn scrub_tokens(text: &str) -> String {
+    let mut out = Vec::new();
+    let mut redact_next = false;
+    for word in text.split(' ') {
+        if matches!(word.to_lowercase().as_str(), "bearer" | "token" | "authorization:") {
+            out.push(word.to_string());
+            redact_next = true;
+            continue;
+        }
+        if redact_next && !word.is_empty() {
+            out.push("<redacted>".to_string());
+            redact_next = false;
+            continue;
+        }
+        let trimmed = word.trim_start_matches(|c: char| !c.is_alphanumeric());
+        if TOKEN_PREFIXES.iter().any(|p| trimmed.starts_with(p) && trimmed.len() > p.len() + 8) {
+            out.push("<redacted>".to_string());
+        } else {
+            out.push(word.to_string());
+        }
+    }
+    out.join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(a: &[&str]) -> Vec<String> {
+        a.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn redacts_bodies_and_names_operations() {
+        let redacted = redact_args(&args(&[
+            "api",
+            "graphql",
+            "-f",
+            "query=query FetchThreads($owner: String!) { repository { id } }",
+            "-f",
+            "owner=acme",
+            "-F",
+            "pr=7",
+            "-f",
+            "body=secret plans",
+        ]));
+        assert_eq!(
+            redacted.join(" "),
+            "api graphql query=<FetchThreads> owner=acme pr=7 body=<12 bytes>"
+        );
+
+        let redacted = redact_args(&args(&["pr", "comment", "7", "--body", "hello"]));
+        assert_eq!(redacted.join(" "), "pr comment 7 --body <5 bytes>");
+    }
+
+    #[test]
+    fn redacts_graphql_request_bodies() {
+        let variables = serde_json::json!({ "threadId": "PRRT_1", "body": "secret plans", "pr": 7 });
+        assert_eq!(
+            redact_graphql("mutation AddReply($threadId: ID!) { x }", &variables),
+            "query=<AddReply> body=<12 bytes> pr=7 threadId=PRRT_1"
+        );
+    }
+
+    #[test]
+    fn scrubs_tokens() {
+        assert_eq!(
+            scrub_tokens("HTTP 401: Bad credentials for ghp_abcdefghijklmnop"),
+            "HTTP 401: Bad credentials for <redacted>"
+        );
+        assert_eq!(scrub_tokens("Authorization: Bearer abc.def"), "Authorization: Bearer <redacted>");
+        assert_eq!(scrub_tokens("ghp_ is a prefix"), "ghp_ is a prefix");
+    }
+
+    #[test]
+    fn trims_and_reads_recent_entries() {
+        let dir = std::env::temp_dir().join(format!("pr-loop-requests-{}", std::process::id()));
+        let path = dir.join("requests.log");
+        let _ = std::fs::remove_dir_all(&dir);
+        for i in 0..6 {
+            let entry = RequestEntry {
+                at: format!("t{}", i),
+                command: "gh pr view".to_string(),
+                exit_code: Some(0),
+                duration_ms: 5,
+                stdout_bytes: 10,
+                stderr: String::new(),
+            };
+            append(&path, &entry).unwrap();
+        }
+        trim(&path, 2);
+        let entries = recent_entries(&path, 10);
+        let times: Vec<_> = entries.iter().map(|e| e.at.as_str()).collect();
+        assert_eq!(times, vec!["t4", "t5"]);
+        assert_eq!(recent_entries(&path, 1)[0].at, "t5");
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
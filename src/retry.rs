@@ -0,0 +1,211 @@
+// Retrying transient API failures.
+// A long `--wait-until-actionable` run makes hundreds of requests, and one
+// 502 from GitHub or a dropped connection shouldn't end it. Failures that
+// look transient (network errors, 5xx responses, GitHub's secondary rate
+// limit) are retried with jittered exponential backoff; anything else, like
+// a bad token or a missing PR, fails straight away.
+//
+// Mutations are more careful: a 502 can arrive after GitHub already posted
+// the reply, and posting it again would duplicate it. They're only retried
+// when the request can't have been acted on — rate-limited or never
+// connected.
+
+use std::io;
+use std::process::Output;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Attempts in total, including the first.
+const MAX_ATTEMPTS: u32 = 4;
+/// Delay before the first retry; doubled for each one after.
+const BASE_DELAY: Duration = Duration::from_secs(2);
+const MAX_DELAY: Duration = Duration::from_secs(30);
+
+/// gh stderr (lowercased) for failures that may succeed on retry.
+const TRANSIENT_ERRORS: &[&str] = &[
+    "http 500",
+    "http 502",
+    "http 503",
+    "http 504",
+    "secondary rate limit",
+    "connection reset",
+    "connection refused",
+    "could not resolve host",
+    "tls handshake timeout",
+    "i/o timeout",
+    "timed out",
+    "unexpected eof",
+];
+
+/// The subset of `TRANSIENT_ERRORS` where the request wasn't acted on.
+const NOT_ACTED_ON_ERRORS: &[&str] = &["secondary rate limit", "connection refused", "could not resolve host"];
+
+/// Run a read-only gh command, retrying transient failures.
+pub fn gh(run: impl FnMut() -> io::Result<Output>) -> io::Result<Output> {
+    retry_with(run, |result| failed_with(result, TRANSIENT_ERRORS), std::thread::sleep)
+}
+
+/// Run a gh command that changes something, retrying only failures where
+/// GitHub can't have acted on the request.
+pub fn gh_mutation(run: impl FnMut() -> io::Result<Output>) -> io::Result<Output> {
+    retry_with(run, |result| failed_with(result, NOT_ACTED_ON_ERRORS), std::thread::sleep)
+}
+
+/// Send an idempotent HTTP request, retrying network errors, 5xx responses,
+/// and 429s.
+pub fn http(
+    send: impl FnMut() -> reqwest::Result<reqwest::blocking::Response>,
+) -> reqwest::Result<reqwest::blocking::Response> {
+    retry_with(send, http_failed, std::thread::sleep)
+}
+
+/// Like `http`, but each retry is taken from `budget`: with many requests
+/// per analysis (one per failed step), an outage would otherwise cost each
+/// of them a full round of backoff.
+pub fn http_within(
+    budget: &RetryBudget,
+    send: impl FnMut() -> reqwest::Result<reqwest::blocking::Response>,
+) -> reqwest::Result<reqwest::blocking::Response> {
+    retry_with(send, |result| http_failed(result) && budget.take(), std::thread::sleep)
+}
+
+fn http_failed(result: &reqwest::Result<reqwest::blocking::Response>) -> bool {
+    match result {
+        Ok(response) => response.status().is_server_error() || response.status() == 429,
+        Err(e) => e.is_connect() || e.is_timeout(),
+    }
+}
+
+/// Retries that may still be made, shared by every request of one analysis.
+#[derive(Debug, Clone)]
+pub struct RetryBudget(Arc<AtomicU32>);
+
+impl RetryBudget {
+    pub fn new(retries: u32) -> Self {
+        Self(Arc::new(AtomicU32::new(retries)))
+    }
+
+    /// Use up one retry, if any are left.
+    fn take(&self) -> bool {
+        self.0
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |r| r.checked_sub(1))
+            .is_ok()
+    }
+}
+
+/// Whether gh ran and failed with stderr matching one of `patterns`.
+fn failed_with(result: &io::Result<Output>, patterns: &[&str]) -> bool {
+    let Ok(output) = result else { return false };
+    !output.status.success() && matches_any(&String::from_utf8_lossy(&output.stderr), patterns)
+}
+
+fn matches_any(stderr: &str, patterns: &[&str]) -> bool {
+    let stderr = stderr.to_lowercase();
+    patterns.iter().any(|p| stderr.contains(p))
+}
+
+/// Run `op` until it succeeds, fails in a way `transient` doesn't accept, or
+/// `MAX_ATTEMPTS` is reached, sleeping between attempts.
+fn retry_with<T>(mut op: impl FnMut() -> T, transient: impl Fn(&T) -> bool, sleep: impl Fn(Duration)) -> T {
+    let mut attempt = 1;
+    loop {
+        let result = op();
+        if attempt >= MAX_ATTEMPTS || !transient(&result) {
+            return result;
+        }
+        let delay = backoff(attempt, jitter());
+        eprintln!(
+            "Transient API failure; retrying in {:.1}s (attempt {}/{})...",
+            delay.as_secs_f64(),
+            attempt + 1,
+            MAX_ATTEMPTS
+        );
+        sleep(delay);
+        attempt += 1;
+    }
+}
+
+/// The delay after the `attempt`th failure: exponential, capped, and scaled
+/// into its upper half by `jitter` (0.0 to 1.0) so parallel pollers spread
+/// out instead of retrying in lockstep.
+fn backoff(attempt: u32, jitter: f64) -> Duration {
+    let full = BASE_DELAY.saturating_mul(1 << (attempt - 1).min(16)).min(MAX_DELAY);
+    full.mul_f64(0.5 + jitter.clamp(0.0, 1.0) / 2.0)
+}
+
+/// A value in 0.0..1.0 that varies between calls. Good enough to
+/// desynchronize retries without pulling in a random number crate.
+fn jitter() -> f64 {
+    let nanos = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().subsec_nanos();
+    f64::from(nanos % 1000) / 1000.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+
+    #[test]
+    fn retries_transient_failures_until_success_or_exhaustion() {
+        let calls = Cell::new(0);
+        let slept = Cell::new(0);
+        let result = retry_with(
+            || {
+                calls.set(calls.get() + 1);
+                if calls.get() < 3 { Err("HTTP 502") } else { Ok(()) }
+            },
+            |r| r.is_err_and(|e| matches_any(e, TRANSIENT_ERRORS)),
+            |_| slept.set(slept.get() + 1),
+        );
+        assert_eq!(result, Ok(()));
+        assert_eq!((calls.get(), slept.get()), (3, 2));
+
+        calls.set(0);
+        let result: Result<(), &str> = retry_with(
+            || {
+                calls.set(calls.get() + 1);
+                Err("HTTP 503: Service Unavailable")
+            },
+            |r| r.is_err_and(|e| matches_any(e, TRANSIENT_ERRORS)),
+            |_| {},
+        );
+        assert!(result.is_err());
+        assert_eq!(calls.get(), MAX_ATTEMPTS);
+
+        // Permanent failures aren't retried
+        calls.set(0);
+        let _: Result<(), &str> = retry_with(
+            || {
+                calls.set(calls.get() + 1);
+                Err("HTTP 401: Bad credentials")
+            },
+            |r| r.is_err_and(|e| matches_any(e, TRANSIENT_ERRORS)),
+            |_| {},
+        );
+        assert_eq!(calls.get(), 1);
+    }
+
+    #[test]
+    fn budget_is_shared_and_runs_out() {
+        let budget = RetryBudget::new(2);
+        let shared = budget.clone();
+        assert!(budget.take());
+        assert!(shared.take());
+        assert!(!budget.take());
+    }
+
+    #[test]
+    fn mutations_retry_only_unacted_failures() {
+        assert!(matches_any("You have exceeded a secondary rate limit", NOT_ACTED_ON_ERRORS));
+        assert!(!matches_any("HTTP 502: Bad Gateway (https://api.github.com/graphql)", NOT_ACTED_ON_ERRORS));
+    }
+
+    #[test]
+    fn backoff_doubles_up_to_the_cap() {
+        assert_eq!(backoff(1, 1.0), Duration::from_secs(2));
+        assert_eq!(backoff(2, 1.0), Duration::from_secs(4));
+        assert_eq!(backoff(2, 0.0), Duration::from_secs(2));
+        assert_eq!(backoff(10, 1.0), MAX_DELAY);
+    }
+}
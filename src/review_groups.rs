@@ -0,0 +1,112 @@
+// Actionable threads grouped by the review they arrived in.
+// A reviewer's comments usually come as one submitted review: related
+// remarks across several files, often with one concern behind them. Showing
+// them together ("Review from @alice (2h ago): 5 threads") lets the agent
+// answer the batch coherently, and follow up with one summary per review
+// rather than treating each thread in isolation.
+
+use crate::threads::ActionableThread;
+use jiff::Timestamp;
+
+/// The actionable threads whose latest comment came in the same review.
+pub struct ReviewGroup<'a> {
+    pub reviewer: String,
+    /// When the review was submitted, if known.
+    pub submitted_at: Option<Timestamp>,
+    pub threads: Vec<&'a ActionableThread>,
+}
+
+impl ReviewGroup<'_> {
+    /// e.g. "Review from @alice (2h ago): 5 threads"
+    pub fn heading(&self, now: Timestamp) -> String {
+        let age = self
+            .submitted_at
+            .map_or(String::new(), |at| format!(" ({} ago)", format_age(now, at)));
+        format!(
+            "Review from @{}{}: {} thread{}",
+            self.reviewer,
+            age,
+            self.threads.len(),
+            if self.threads.len() == 1 { "" } else { "s" }
+        )
+    }
+}
+
+/// Group threads by the review of their latest comment, keeping the given
+/// order of threads within a group and ordering groups by their first
+/// thread. Comments with no review (e.g. from an older API response) are
+/// grouped by author.
+pub fn group_by_review(threads: &[ActionableThread]) -> Vec<ReviewGroup<'_>> {
+    let mut keys: Vec<(String, String)> = Vec::new();
+    let mut groups: Vec<ReviewGroup> = Vec::new();
+    for actionable in threads {
+        let last = actionable.thread.last_comment();
+        let reviewer = last.map_or("unknown", |c| c.author.as_str());
+        let review = last.and_then(|c| c.review.as_ref());
+        let key = (reviewer.to_string(), review.map_or(String::new(), |r| r.id.clone()));
+        match keys.iter().position(|k| *k == key) {
+            Some(i) => groups[i].threads.push(actionable),
+            None => {
+                keys.push(key);
+                groups.push(ReviewGroup {
+                    reviewer: reviewer.to_string(),
+                    submitted_at: review
+                        .and_then(|r| r.submitted_at.as_deref())
+                        .and_then(|at| at.parse().ok()),
+                    threads: vec![actionable],
+                });
+            }
+        }
+    }
+    groups
+}
+
+/// "45m", "2h", or "3d".
+fn format_age(now: Timestamp, then: Timestamp) -> String {
+    let mins = now.duration_since(then).as_secs().max(0) / 60;
+    match mins {
+        0..60 => format!("{}m", mins),
+        60..1440 => format!("{}h", mins / 60),
+        _ => format!("{}d", mins / 1440),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::threads::{CommentReview, ReviewThread, ThreadComment};
+
+    fn actionable(id: &str, author: &str, review: Option<&str>) -> ActionableThread {
+        let comment = ThreadComment {
+            review: review.map(|r| CommentReview {
+                id: r.to_string(),
+                submitted_at: Some("2026-10-17T10:00:00Z".to_string()),
+            }),
+            ..ThreadComment::for_test(&format!("{}-c", id), author, "Fix this")
+        };
+        ActionableThread {
+            thread: ReviewThread::for_test(id, vec![comment]),
+            tag: None,
+        }
+    }
+
+    #[test]
+    fn groups_threads_by_review_in_order() {
+        let threads = vec![
+            actionable("T1", "alice", Some("R1")),
+            actionable("T2", "bob", Some("R2")),
+            actionable("T3", "alice", Some("R1")),
+            actionable("T4", "alice", Some("R3")),
+        ];
+        let groups = group_by_review(&threads);
+        let ids: Vec<Vec<&str>> = groups
+            .iter()
+            .map(|g| g.threads.iter().map(|t| t.thread.id.as_str()).collect())
+            .collect();
+        assert_eq!(ids, vec![vec!["T1", "T3"], vec!["T2"], vec!["T4"]]);
+
+        let now: Timestamp = "2026-10-17T12:05:00Z".parse().unwrap();
+        assert_eq!(groups[0].heading(now), "Review from @alice (2h ago): 2 threads");
+        assert_eq!(groups[1].heading(now), "Review from @bob (2h ago): 1 thread");
+    }
+}
@@ -0,0 +1,481 @@
+// End-to-end smoke test of everything pr-loop changes on GitHub.
+// After a token is rotated, "can the loop still do its job?" takes more than
+// `gh auth status`: a token can read PRs but not resolve threads, or post
+// replies but not edit descriptions. `pr-loop selftest --repo owner/sandbox`
+// goes through the whole mutating surface on a disposable PR it opens in a
+// sandbox repository — open a review thread, reply, resolve, add and remove
+// the status block, clean up Claude's threads — checking that each step took
+// effect, then closes the PR and deletes its branch.
+//
+// Steps run in order and stop at the first failure, since each builds on the
+// last, but the PR and branch are always cleaned up.
+
+use crate::pr::{has_status_block, remove_status_block, update_body_with_status, PrClient};
+use crate::reply::{format_claude_reply, ReplyClient};
+use crate::request_log::TracedOutput;
+use crate::threads::{ReplyStatus, ReviewThread, ThreadsClient};
+use anyhow::{bail, Context, Result};
+use std::process::Command;
+
+/// The file the selftest branch adds, so the PR has a line to comment on.
+pub const SELFTEST_FILE: &str = "pr-loop-selftest.md";
+/// `SELFTEST_FILE`'s contents, base64-encoded for the contents API:
+/// "This file was added by `pr-loop selftest` and is deleted with its branch."
+const SELFTEST_FILE_BASE64: &str =
+    "VGhpcyBmaWxlIHdhcyBhZGRlZCBieSBgcHItbG9vcCBzZWxmdGVzdGAgYW5kIGlzIGRlbGV0ZWQgd2l0aCBpdHMgYnJhbmNoLgo=";
+
+const THREAD_TEXT: &str = "pr-loop selftest: a review thread.";
+const REPLY_TEXT: &str = "pr-loop selftest: a reply.";
+const STATUS_TEXT: &str = "pr-loop selftest";
+
+/// Setting up and tearing down the disposable PR, allowing test implementations.
+pub trait SandboxClient {
+    /// Create `branch` off the default branch with `SELFTEST_FILE` added.
+    /// Returns the default branch's name.
+    fn create_branch(&self, owner: &str, repo: &str, branch: &str) -> Result<String>;
+
+    /// Open a draft PR from `branch` into `base`. Returns its number.
+    fn open_pr(&self, owner: &str, repo: &str, branch: &str, base: &str) -> Result<u64>;
+
+    /// Start a review thread on the first line of `SELFTEST_FILE`.
+    fn open_thread(&self, owner: &str, repo: &str, pr_number: u64, body: &str) -> Result<()>;
+
+    fn close_pr(&self, owner: &str, repo: &str, pr_number: u64) -> Result<()>;
+
+    fn delete_branch(&self, owner: &str, repo: &str, branch: &str) -> Result<()>;
+}
+
+/// Real client that uses `gh`.
+pub struct RealSandboxClient;
+
+fn run_gh(args: &[&str], what: &str) -> Result<String> {
+    let output = Command::new("gh")
+        .args(args)
+        .traced_output()
+        .context(format!("Failed to run gh to {}", what))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        bail!("Failed to {}: {}", what, stderr.trim());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+impl SandboxClient for RealSandboxClient {
+    fn create_branch(&self, owner: &str, repo: &str, branch: &str) -> Result<String> {
+        let repo_path = format!("repos/{}/{}", owner, repo);
+        let base = run_gh(
+            &[
+                "repo",
+                "view",
+                &format!("{}/{}", owner, repo),
+                "--json",
+                "defaultBranchRef",
+                "--jq",
+                ".defaultBranchRef.name",
+            ],
+            "look up the default branch",
+        )?;
+        let sha = run_gh(
+            &["api", &format!("{}/git/ref/heads/{}", repo_path, base), "--jq", ".object.sha"],
+            "look up the default branch's head",
+        )?;
+        run_gh(
+            &[
+                "api",
+                &format!("{}/git/refs", repo_path),
+                "-f",
+                &format!("ref=refs/heads/{}", branch),
+                "-f",
+                &format!("sha={}", sha),
+            ],
+            "create the branch",
+        )?;
+        let added = run_gh(
+            &[
+                "api",
+                "-X",
+                "PUT",
+                &format!("{}/contents/{}", repo_path, SELFTEST_FILE),
+                "-f",
+                "message=pr-loop selftest",
+                "-f",
+                &format!("content={}", SELFTEST_FILE_BASE64),
+                "-f",
+                &format!("branch={}", branch),
+            ],
+            "commit to the branch",
+        );
+        if let Err(e) = added {
+            // The branch exists but the caller won't know to delete it
+            let _ = self.delete_branch(owner, repo, branch);
+            return Err(e);
+        }
+        Ok(base)
+    }
+
+    fn open_pr(&self, owner: &str, repo: &str, branch: &str, base: &str) -> Result<u64> {
+        let stdout = run_gh(
+            &[
+                "pr",
+                "create",
+                "--repo",
+                &format!("{}/{}", owner, repo),
+                "--head",
+                branch,
+                "--base",
+                base,
+                "--draft",
+                "--title",
+                "pr-loop selftest",
+                "--body",
+                "Opened by `pr-loop selftest`; it closes this PR when it's done.",
+            ],
+            "open the PR",
+        )?;
+        // gh prints the new PR's URL as the last line
+        stdout
+            .lines()
+            .last()
+            .and_then(|url| url.rsplit('/').next())
+            .and_then(|n| n.trim().parse().ok())
+            .context("gh pr create didn't print the PR URL")
+    }
+
+    fn open_thread(&self, owner: &str, repo: &str, pr_number: u64, body: &str) -> Result<()> {
+        let pr_path = format!("repos/{}/{}/pulls/{}", owner, repo, pr_number);
+        let head = run_gh(&["api", &pr_path, "--jq", ".head.sha"], "look up the PR head")?;
+        run_gh(
+            &[
+                "api",
+                &format!("{}/comments", pr_path),
+                "-f",
+                &format!("body={}", body),
+                "-f",
+                &format!("commit_id={}", head),
+                "-f",
+                &format!("path={}", SELFTEST_FILE),
+                "-F",
+                "line=1",
+                "-f",
+                "side=RIGHT",
+            ],
+            "open a review thread",
+        )?;
+        Ok(())
+    }
+
+    fn close_pr(&self, owner: &str, repo: &str, pr_number: u64) -> Result<()> {
+        run_gh(
+            &["pr", "close", &pr_number.to_string(), "--repo", &format!("{}/{}", owner, repo)],
+            "close the PR",
+        )?;
+        Ok(())
+    }
+
+    fn delete_branch(&self, owner: &str, repo: &str, branch: &str) -> Result<()> {
+        run_gh(
+            &["api", "-X", "DELETE", &format!("repos/{}/{}/git/refs/heads/{}", owner, repo, branch)],
+            "delete the branch",
+        )?;
+        Ok(())
+    }
+}
+
+/// The clients the selftest goes through: the sandbox for its own PR, and the
+/// ones the loop itself uses for everything else.
+pub struct Clients<'a> {
+    pub sandbox: &'a dyn SandboxClient,
+    pub threads: &'a dyn ThreadsClient,
+    pub replies: &'a dyn ReplyClient,
+    pub prs: &'a dyn PrClient,
+}
+
+/// One step and how it went.
+#[derive(Debug)]
+pub struct Step {
+    pub name: &'static str,
+    pub result: Result<()>,
+}
+
+#[derive(Default)]
+struct Steps(Vec<Step>);
+
+impl Steps {
+    /// Record a step, passing on its value if it succeeded.
+    fn check<T>(&mut self, name: &'static str, result: Result<T>) -> Option<T> {
+        let (value, result) = match result {
+            Ok(v) => (Some(v), Ok(())),
+            Err(e) => (None, Err(e)),
+        };
+        self.0.push(Step { name, result });
+        value
+    }
+}
+
+/// Run every step against a new PR from `branch` in `owner/repo`.
+pub fn run(clients: &Clients, owner: &str, repo: &str, branch: &str) -> Vec<Step> {
+    let mut steps = Steps::default();
+    let sandbox = clients.sandbox;
+    if let Some(base) = steps.check("create a branch", sandbox.create_branch(owner, repo, branch)) {
+        if let Some(pr_number) = steps.check("open a draft PR", sandbox.open_pr(owner, repo, branch, &base)) {
+            exercise(clients, owner, repo, pr_number, &mut steps);
+            steps.check("close the PR", sandbox.close_pr(owner, repo, pr_number));
+        }
+        steps.check("delete the branch", sandbox.delete_branch(owner, repo, branch));
+    }
+    steps.0
+}
+
+/// The loop's own mutations, each checked to have taken effect.
+fn exercise(clients: &Clients, owner: &str, repo: &str, pr_number: u64, steps: &mut Steps) -> Option<()> {
+    let thread = steps.check("open a review thread", open_thread(clients, owner, repo, pr_number))?;
+    steps.check("reply to the thread", reply(clients, &thread))?;
+    steps.check("resolve the thread", resolve(clients, owner, repo, pr_number, &thread))?;
+    steps.check("add the status block", set_status(clients, owner, repo, pr_number, true))?;
+    steps.check("remove the status block", set_status(clients, owner, repo, pr_number, false))?;
+    steps.check("clean up Claude's threads", clean(clients, owner, repo, pr_number, &thread))
+}
+
+fn open_thread(clients: &Clients, owner: &str, repo: &str, pr_number: u64) -> Result<ReviewThread> {
+    let body = format_claude_reply(None, THREAD_TEXT);
+    clients.sandbox.open_thread(owner, repo, pr_number, &body)?;
+    clients
+        .threads
+        .fetch_threads(owner, repo, pr_number)?
+        .into_iter()
+        .find(|t| t.comments.first().is_some_and(|c| c.body == body))
+        .context("the new thread isn't among the PR's threads")
+}
+
+fn reply(clients: &Clients, thread: &ReviewThread) -> Result<()> {
+    let posted = clients
+        .replies
+        .post_reply(&thread.id, &format_claude_reply(Some(ReplyStatus::Fixed), REPLY_TEXT))?;
+    let fetched = clients.threads.fetch_thread_by_comment_id(&posted.comment_id)?;
+    if fetched.id != thread.id {
+        bail!("the reply landed in thread {} instead of {}", fetched.id, thread.id);
+    }
+    Ok(())
+}
+
+fn resolve(clients: &Clients, owner: &str, repo: &str, pr_number: u64, thread: &ReviewThread) -> Result<()> {
+    clients.replies.resolve_thread(&thread.id)?;
+    let resolved = clients
+        .threads
+        .fetch_threads(owner, repo, pr_number)?
+        .iter()
+        .any(|t| t.id == thread.id && t.is_resolved);
+    if !resolved {
+        bail!("the thread still isn't resolved");
+    }
+    Ok(())
+}
+
+fn set_status(clients: &Clients, owner: &str, repo: &str, pr_number: u64, add: bool) -> Result<()> {
+    let body = clients.prs.get_body(owner, repo, pr_number)?;
+    let new_body = if add {
+        update_body_with_status(&body, Some(STATUS_TEXT))
+    } else {
+        remove_status_block(&body)
+    };
+    clients.prs.set_body(owner, repo, pr_number, &new_body)?;
+    if has_status_block(&clients.prs.get_body(owner, repo, pr_number)?) != add {
+        bail!("the description wasn't updated");
+    }
+    Ok(())
+}
+
+/// Delete resolved pure-Claude threads, as `ready` and `clean-threads` do.
+fn clean(clients: &Clients, owner: &str, repo: &str, pr_number: u64, thread: &ReviewThread) -> Result<()> {
+    let threads = clients.threads.fetch_threads(owner, repo, pr_number)?;
+    let ours = threads
+        .iter()
+        .find(|t| t.id == thread.id)
+        .context("the thread disappeared before cleanup")?;
+    if ours.has_paperclip() || !ours.is_resolved || !ours.is_pure_claude() {
+        bail!("the thread doesn't count as a resolved pure-Claude thread");
+    }
+    for comment_id in ours.comment_ids() {
+        clients.replies.delete_comment(comment_id)?;
+    }
+    if clients.threads.fetch_threads(owner, repo, pr_number)?.iter().any(|t| t.id == thread.id) {
+        bail!("the thread is still there after deleting its comments");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pr::{MergeMethod, PrState};
+    use crate::reply::ReplyResult;
+    use crate::threads::ThreadComment;
+    use std::sync::Mutex;
+
+    /// A sandbox repository in memory: one PR's threads and description.
+    #[derive(Default)]
+    struct FakeGitHub {
+        threads: Mutex<Vec<ReviewThread>>,
+        body: Mutex<String>,
+        calls: Mutex<Vec<String>>,
+        fail_resolve: bool,
+    }
+
+    impl FakeGitHub {
+        fn call(&self, name: &str) {
+            self.calls.lock().unwrap().push(name.to_string());
+        }
+
+        fn comment(id: String, body: &str) -> ThreadComment {
+            ThreadComment::for_test(&id, "bot", body)
+        }
+    }
+
+    impl SandboxClient for FakeGitHub {
+        fn create_branch(&self, _: &str, _: &str, _: &str) -> Result<String> {
+            self.call("create_branch");
+            Ok("main".to_string())
+        }
+        fn open_pr(&self, _: &str, _: &str, _: &str, _: &str) -> Result<u64> {
+            self.call("open_pr");
+            Ok(7)
+        }
+        fn open_thread(&self, _: &str, _: &str, _: u64, body: &str) -> Result<()> {
+            self.threads.lock().unwrap().push(ReviewThread {
+                path: Some(SELFTEST_FILE.to_string()),
+                ..ReviewThread::for_test("T1", vec![Self::comment("C1".to_string(), body)])
+            });
+            Ok(())
+        }
+        fn close_pr(&self, _: &str, _: &str, _: u64) -> Result<()> {
+            self.call("close_pr");
+            Ok(())
+        }
+        fn delete_branch(&self, _: &str, _: &str, _: &str) -> Result<()> {
+            self.call("delete_branch");
+            Ok(())
+        }
+    }
+
+    impl ThreadsClient for FakeGitHub {
+        fn fetch_threads(&self, _: &str, _: &str, _: u64) -> Result<Vec<ReviewThread>> {
+            Ok(self.threads.lock().unwrap().clone())
+        }
+        fn fetch_thread_by_comment_id(&self, comment_id: &str) -> Result<ReviewThread> {
+            self.threads
+                .lock()
+                .unwrap()
+                .iter()
+                .find(|t| t.comment_ids().contains(&comment_id))
+                .cloned()
+                .context("no such comment")
+        }
+    }
+
+    impl ReplyClient for FakeGitHub {
+        fn post_reply(&self, thread_id: &str, body: &str) -> Result<ReplyResult> {
+            let mut threads = self.threads.lock().unwrap();
+            let thread = threads.iter_mut().find(|t| t.id == thread_id).context("no such thread")?;
+            let comment_id = format!("C{}", thread.comments.len() + 1);
+            thread.comments.push(Self::comment(comment_id.clone(), body));
+            Ok(ReplyResult { comment_id, url: None })
+        }
+        fn delete_comment(&self, comment_id: &str) -> Result<()> {
+            let mut threads = self.threads.lock().unwrap();
+            for thread in threads.iter_mut() {
+                thread.comments.retain(|c| c.id != comment_id);
+            }
+            threads.retain(|t| !t.comments.is_empty());
+            Ok(())
+        }
+        fn update_comment(&self, _: &str, _: &str) -> Result<()> {
+            bail!("not used")
+        }
+        fn resolve_thread(&self, thread_id: &str) -> Result<()> {
+            if self.fail_resolve {
+                bail!("Resource not accessible by integration");
+            }
+            for thread in self.threads.lock().unwrap().iter_mut().filter(|t| t.id == thread_id) {
+                thread.is_resolved = true;
+            }
+            Ok(())
+        }
+    }
+
+    impl PrClient for FakeGitHub {
+        fn is_draft(&self, _: &str, _: &str, _: u64) -> Result<bool> {
+            Ok(true)
+        }
+        fn get_body(&self, _: &str, _: &str, _: u64) -> Result<String> {
+            Ok(self.body.lock().unwrap().clone())
+        }
+        fn set_body(&self, _: &str, _: &str, _: u64, body: &str) -> Result<()> {
+            *self.body.lock().unwrap() = body.to_string();
+            Ok(())
+        }
+        fn mark_ready(&self, _: &str, _: &str, _: u64) -> Result<()> {
+            bail!("not used")
+        }
+        fn get_commit_count(&self, _: &str, _: &str, _: u64) -> Result<usize> {
+            Ok(1)
+        }
+        fn add_reviewer(&self, _: &str, _: &str, _: u64, _: &str) -> Result<()> {
+            bail!("not used")
+        }
+        fn add_comment(&self, _: &str, _: &str, _: u64, _: &str) -> Result<()> {
+            bail!("not used")
+        }
+        fn merge(&self, _: &str, _: &str, _: u64, _: MergeMethod, _: bool) -> Result<()> {
+            bail!("not used")
+        }
+        fn state(&self, _: &str, _: &str, _: u64) -> Result<PrState> {
+            Ok(PrState::Open)
+        }
+    }
+
+    fn run_against(github: &FakeGitHub) -> Vec<(&'static str, bool)> {
+        let clients = Clients {
+            sandbox: github,
+            threads: github,
+            replies: github,
+            prs: github,
+        };
+        run(&clients, "o", "sandbox", "pr-loop-selftest-1")
+            .into_iter()
+            .map(|s| (s.name, s.result.is_ok()))
+            .collect()
+    }
+
+    #[test]
+    fn runs_every_step_and_always_cleans_up() {
+        let github = FakeGitHub::default();
+        let steps = run_against(&github);
+        assert!(steps.iter().all(|(_, ok)| *ok), "{:?}", steps);
+        assert_eq!(steps.len(), 10);
+        assert!(github.threads.lock().unwrap().is_empty());
+        assert!(github.body.lock().unwrap().is_empty());
+
+        // A failing step skips the rest, but the PR is still closed
+        let github = FakeGitHub {
+            fail_resolve: true,
+            ..Default::default()
+        };
+        let steps = run_against(&github);
+        assert_eq!(
+            steps,
+            vec![
+                ("create a branch", true),
+                ("open a draft PR", true),
+                ("open a review thread", true),
+                ("reply to the thread", true),
+                ("resolve the thread", false),
+                ("close the PR", true),
+                ("delete the branch", true),
+            ]
+        );
+        assert_eq!(
+            *github.calls.lock().unwrap(),
+            vec!["create_branch", "open_pr", "close_pr", "delete_branch"]
+        );
+    }
+}
@@ -0,0 +1,273 @@
+// Snoozed review threads and checks.
+// A human can park a discussion ("we'll handle this in a follow-up") or a
+// known-broken check without resolving it: `pr-loop snooze` records it in
+// `.pr-loop/snoozes.json`, and until the snooze expires the item is left out
+// of the analysis and the wait modes.
+//
+// Like the audit log, filtering wraps the real clients (`Snoozed<C>`
+// implements ChecksClient and ThreadsClient), so waits and the analysis see
+// the same state without each call site checking.
+
+use crate::audit::STATE_DIR;
+use crate::checks::{BaseChecks, Check, ChecksClient, CommitChecks};
+use crate::github::MergeableStatus;
+use crate::threads::{PendingReview, ReviewThread, ThreadsClient};
+use anyhow::{bail, Context, Result};
+use jiff::{Span, Timestamp, Zoned};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+const SNOOZE_FILE: &str = "snoozes.json";
+
+/// What a snooze applies to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SnoozeKind {
+    Thread,
+    Check,
+}
+
+impl std::fmt::Display for SnoozeKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SnoozeKind::Thread => write!(f, "thread"),
+            SnoozeKind::Check => write!(f, "check"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Snooze {
+    /// e.g. "owner/repo#123"
+    pub pr: String,
+    pub kind: SnoozeKind,
+    /// Thread ID or check name.
+    pub target: String,
+    pub until: Timestamp,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+/// The snoozes recorded under a repository root.
+#[derive(Debug, Clone, Default)]
+pub struct Snoozes {
+    /// None when there's no repository to store them in.
+    path: Option<PathBuf>,
+    entries: Vec<Snooze>,
+}
+
+impl Snoozes {
+    /// Load `<root>/.pr-loop/snoozes.json`, dropping expired entries.
+    pub fn load(root: &Path) -> Result<Self> {
+        let path = root.join(STATE_DIR).join(SNOOZE_FILE);
+        let entries: Vec<Snooze> = match std::fs::read_to_string(&path) {
+            Ok(content) => serde_json::from_str(&content)
+                .with_context(|| format!("Failed to parse {}", path.display()))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => vec![],
+            Err(e) => return Err(e).with_context(|| format!("Failed to read {}", path.display())),
+        };
+        let now = Timestamp::now();
+        Ok(Self {
+            path: Some(path),
+            entries: entries.into_iter().filter(|s| s.until > now).collect(),
+        })
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let Some(path) = &self.path else { return Ok(()) };
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+            let gitignore = dir.join(".gitignore");
+            if !gitignore.exists() {
+                std::fs::write(&gitignore, "*\n")?;
+            }
+        }
+        std::fs::write(path, serde_json::to_string_pretty(&self.entries)? + "\n")
+            .with_context(|| format!("Failed to write {}", path.display()))
+    }
+
+    /// Snooze `target`, replacing any existing snooze on it.
+    pub fn add(&mut self, snooze: Snooze) {
+        self.remove(&snooze.pr, snooze.kind, &snooze.target);
+        self.entries.push(snooze);
+    }
+
+    /// Remove the snooze on `target`, returning whether there was one.
+    pub fn remove(&mut self, pr: &str, kind: SnoozeKind, target: &str) -> bool {
+        let before = self.entries.len();
+        self.entries
+            .retain(|s| !(s.pr == pr && s.kind == kind && s.target == target));
+        self.entries.len() != before
+    }
+
+    /// Unexpired snoozes on `pr`.
+    pub fn for_pr<'a>(&'a self, pr: &'a str) -> impl Iterator<Item = &'a Snooze> + 'a {
+        let now = Timestamp::now();
+        self.entries.iter().filter(move |s| s.pr == pr && s.until > now)
+    }
+
+    /// The unexpired snooze on `target`, if any.
+    pub fn find(&self, pr: &str, kind: SnoozeKind, target: &str) -> Option<&Snooze> {
+        let now = Timestamp::now();
+        self.entries
+            .iter()
+            .find(|s| s.pr == pr && s.until > now && s.kind == kind && s.target == target)
+    }
+
+    fn is_snoozed(&self, pr: &str, kind: SnoozeKind, target: &str) -> bool {
+        self.find(pr, kind, target).is_some()
+    }
+}
+
+/// Parse a snooze length like `30m`, `2h`, or `3d` into an expiry time.
+pub fn expiry(duration: &str, now: &Zoned) -> Result<Timestamp> {
+    let span: Span = duration
+        .parse()
+        .with_context(|| format!("Invalid duration '{}' (expected e.g. 30m, 2h, 3d)", duration))?;
+    if span.is_negative() || span.is_zero() {
+        bail!("Duration must be positive, got '{}'", duration);
+    }
+    Ok(now.checked_add(span)?.timestamp())
+}
+
+/// A client whose results leave out what's snoozed on one PR.
+pub struct Snoozed<C> {
+    inner: C,
+    snoozes: Snoozes,
+    pr: String,
+}
+
+impl<C> Snoozed<C> {
+    pub fn new(inner: C, snoozes: Snoozes, pr: String) -> Self {
+        Self { inner, snoozes, pr }
+    }
+}
+
+impl<C: ChecksClient> ChecksClient for Snoozed<C> {
+    fn fetch_checks(&self, owner: &str, repo: &str, pr_number: u64) -> Result<Vec<Check>> {
+        let checks = self.inner.fetch_checks(owner, repo, pr_number)?;
+        Ok(checks
+            .into_iter()
+            .filter(|c| !self.snoozes.is_snoozed(&self.pr, SnoozeKind::Check, &c.name))
+            .collect())
+    }
+
+    fn fetch_check_history(
+        &self,
+        owner: &str,
+        repo: &str,
+        pr_number: u64,
+        count: u64,
+    ) -> Result<Vec<CommitChecks>> {
+        self.inner.fetch_check_history(owner, repo, pr_number, count)
+    }
+
+    fn fetch_base_checks(&self, owner: &str, repo: &str, pr_number: u64) -> Result<BaseChecks> {
+        self.inner.fetch_base_checks(owner, repo, pr_number)
+    }
+
+    fn fetch_required_checks(&self, owner: &str, repo: &str, pr_number: u64) -> Result<Vec<String>> {
+        self.inner.fetch_required_checks(owner, repo, pr_number)
+    }
+
+    fn fetch_mergeable_status(&self, owner: &str, repo: &str, pr_number: u64) -> Result<MergeableStatus> {
+        self.inner.fetch_mergeable_status(owner, repo, pr_number)
+    }
+}
+
+impl<C: ThreadsClient> ThreadsClient for Snoozed<C> {
+    fn fetch_threads(&self, owner: &str, repo: &str, pr_number: u64) -> Result<Vec<ReviewThread>> {
+        let threads = self.inner.fetch_threads(owner, repo, pr_number)?;
+        Ok(threads
+            .into_iter()
+            .filter(|t| !self.snoozes.is_snoozed(&self.pr, SnoozeKind::Thread, &t.id))
+            .collect())
+    }
+
+    fn fetch_thread_by_comment_id(&self, comment_id: &str) -> Result<ReviewThread> {
+        self.inner.fetch_thread_by_comment_id(comment_id)
+    }
+
+    fn fetch_pending_reviews(
+        &self,
+        owner: &str,
+        repo: &str,
+        pr_number: u64,
+    ) -> Result<Vec<PendingReview>> {
+        self.inner.fetch_pending_reviews(owner, repo, pr_number)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::checks::{CheckSource, CheckStatus};
+
+    struct FixedChecks;
+
+    impl ChecksClient for FixedChecks {
+        fn fetch_checks(&self, _: &str, _: &str, _: u64) -> Result<Vec<Check>> {
+            Ok(["build", "flaky-e2e"]
+                .iter()
+                .map(|name| Check {
+                    name: name.to_string(),
+                    status: CheckStatus::Fail,
+                    url: None,
+                    source: CheckSource::Unknown,
+                    started_at: None,
+                    attempts: 1,
+                    app: None,
+                })
+                .collect())
+        }
+    }
+
+    fn snooze(pr: &str, kind: SnoozeKind, target: &str, until: Timestamp) -> Snooze {
+        Snooze {
+            pr: pr.to_string(),
+            kind,
+            target: target.to_string(),
+            until,
+            reason: None,
+        }
+    }
+
+    #[test]
+    fn filters_only_unexpired_snoozes_on_this_pr() {
+        let later = Timestamp::now() + jiff::SignedDuration::from_hours(1);
+        let earlier = Timestamp::now() - jiff::SignedDuration::from_hours(1);
+        let mut snoozes = Snoozes::default();
+        snoozes.add(snooze("o/r#1", SnoozeKind::Check, "flaky-e2e", later));
+        snoozes.add(snooze("o/r#2", SnoozeKind::Check, "build", later));
+        snoozes.add(snooze("o/r#1", SnoozeKind::Check, "build", earlier));
+
+        let client = Snoozed::new(FixedChecks, snoozes, "o/r#1".to_string());
+        let names: Vec<_> = client
+            .fetch_checks("o", "r", 1)
+            .unwrap()
+            .into_iter()
+            .map(|c| c.name)
+            .collect();
+        assert_eq!(names, vec!["build"]);
+    }
+
+    #[test]
+    fn add_replaces_and_remove_reports() {
+        let later = Timestamp::now() + jiff::SignedDuration::from_hours(1);
+        let mut snoozes = Snoozes::default();
+        snoozes.add(snooze("o/r#1", SnoozeKind::Thread, "T1", later));
+        snoozes.add(snooze("o/r#1", SnoozeKind::Thread, "T1", later));
+        assert_eq!(snoozes.for_pr("o/r#1").count(), 1);
+        assert!(snoozes.remove("o/r#1", SnoozeKind::Thread, "T1"));
+        assert!(!snoozes.remove("o/r#1", SnoozeKind::Thread, "T1"));
+    }
+
+    #[test]
+    fn parses_durations() {
+        let now: Zoned = "2026-10-12T10:00:00[UTC]".parse().unwrap();
+        assert_eq!(expiry("2h", &now).unwrap().to_string(), "2026-10-12T12:00:00Z");
+        assert_eq!(expiry("3d", &now).unwrap().to_string(), "2026-10-15T10:00:00Z");
+        assert!(expiry("soon", &now).is_err());
+        assert!(expiry("0m", &now).is_err());
+    }
+}
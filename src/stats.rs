@@ -0,0 +1,180 @@
+// Statistics about how review threads on a PR were handled, printed by
+// `ready` (and optionally posted to the PR) so teams can see what the agent
+// actually took care of. Computed before `ready` deletes pure-Claude threads.
+
+use crate::hours::format_duration;
+use crate::threads::ReviewThread;
+use jiff::{SignedDuration, Timestamp};
+
+/// Counts gathered from a PR's review threads.
+#[derive(Debug, Default, PartialEq)]
+pub struct ThreadStats {
+    /// Threads started by a reviewer (not by Claude, not deferred)
+    pub opened: usize,
+    /// Reviewer threads Claude replied in at least once
+    pub replied: usize,
+    /// Reviewer threads that are resolved and whose last comment is Claude's
+    pub resolved_by_claude: usize,
+    /// Threads deferred with a paperclip
+    pub deferred: usize,
+    /// Average time from a reviewer's comment to Claude's next reply
+    pub average_response: Option<SignedDuration>,
+}
+
+fn timestamp(at: Option<&str>) -> Option<Timestamp> {
+    at.and_then(|t| t.parse().ok())
+}
+
+impl ThreadStats {
+    pub fn from_threads(threads: &[ReviewThread]) -> Self {
+        let mut stats = Self::default();
+        let mut responses = Vec::new();
+        for thread in threads {
+            if thread.has_paperclip() {
+                stats.deferred += 1;
+                continue;
+            }
+            let Some(first) = thread.comments.first() else {
+                continue;
+            };
+            if first.is_from_claude() {
+                continue;
+            }
+            stats.opened += 1;
+            if thread.comments.iter().any(|c| c.is_from_claude()) {
+                stats.replied += 1;
+            }
+            if thread.is_resolved
+                && thread.last_comment().is_some_and(|c| c.is_from_claude())
+            {
+                stats.resolved_by_claude += 1;
+            }
+
+            // A reply answers every reviewer comment since Claude last spoke;
+            // time it from the earliest of them.
+            let mut waiting_since = None;
+            for comment in &thread.comments {
+                let at = timestamp(comment.created_at.as_deref());
+                if !comment.is_from_claude() {
+                    waiting_since = waiting_since.or(at);
+                } else if let (Some(since), Some(at)) = (waiting_since.take(), at) {
+                    responses.push(at.duration_since(since));
+                }
+            }
+        }
+        if !responses.is_empty() {
+            let total = responses.iter().fold(SignedDuration::ZERO, |sum, d| sum + *d);
+            stats.average_response = Some(total / responses.len() as i32);
+        }
+        stats
+    }
+
+    fn lines(&self) -> Vec<(&'static str, String)> {
+        let mut lines = vec![
+            ("Threads opened by reviewers", self.opened.to_string()),
+            ("Replied to by Claude", self.replied.to_string()),
+            ("Resolved after a Claude reply", self.resolved_by_claude.to_string()),
+            ("Deferred (📎)", self.deferred.to_string()),
+        ];
+        if let Some(average) = self.average_response {
+            lines.push(("Average time to Claude's reply", format_duration(average.unsigned_abs())));
+        }
+        lines
+    }
+
+    /// Plain-text summary for the terminal.
+    pub fn format_text(&self) -> String {
+        let mut out = String::from("Thread statistics:\n");
+        for (label, value) in self.lines() {
+            out.push_str(&format!("  {}: {}\n", label, value));
+        }
+        out
+    }
+
+    /// Markdown summary for posting as a PR comment.
+    pub fn format_markdown(&self) -> String {
+        let mut out = String::from("### pr-loop thread statistics\n\n");
+        out.push_str("| | |\n|---|---|\n");
+        for (label, value) in self.lines() {
+            out.push_str(&format!("| {} | {} |\n", label, value));
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::threads::{ThreadComment, CLAUDE_MARKER};
+
+    fn comment(author: &str, body: &str, at: &str) -> ThreadComment {
+        ThreadComment {
+            created_at: Some(at.to_string()),
+            ..ThreadComment::for_test(&format!("{}-{}", author, at), author, body)
+        }
+    }
+
+    fn thread(id: &str, resolved: bool, comments: Vec<ThreadComment>) -> ReviewThread {
+        ReviewThread {
+            is_resolved: resolved,
+            ..ReviewThread::for_test(id, comments)
+        }
+    }
+
+    #[test]
+    fn counts_threads_and_response_times() {
+        let claude = |text: &str| format!("{} {}", CLAUDE_MARKER, text);
+        let threads = vec![
+            thread(
+                "FIXED",
+                true,
+                vec![
+                    comment("alice", "Rename this", "2026-01-01T10:00:00Z"),
+                    comment("alice", "And this", "2026-01-01T10:05:00Z"),
+                    comment("bot", &claude("Done"), "2026-01-01T10:20:00Z"),
+                ],
+            ),
+            thread(
+                "DISCUSSED",
+                true,
+                vec![
+                    comment("bob", "Why?", "2026-01-01T11:00:00Z"),
+                    comment("bot", &claude("Because"), "2026-01-01T11:10:00Z"),
+                    comment("bob", "OK, resolved", "2026-01-01T11:30:00Z"),
+                ],
+            ),
+            thread("OPEN", false, vec![comment("bob", "Hmm", "2026-01-01T12:00:00Z")]),
+            thread(
+                "DEFERRED",
+                true,
+                vec![comment("alice", "Later 📎", "2026-01-01T12:00:00Z")],
+            ),
+            thread(
+                "SELF",
+                true,
+                vec![comment("bot", &claude("Note"), "2026-01-01T12:00:00Z")],
+            ),
+        ];
+
+        let stats = ThreadStats::from_threads(&threads);
+        assert_eq!(
+            stats,
+            ThreadStats {
+                opened: 3,
+                replied: 2,
+                resolved_by_claude: 1,
+                deferred: 1,
+                average_response: Some(SignedDuration::from_mins(15)),
+            }
+        );
+        assert!(stats.format_text().contains("Average time to Claude's reply: 15m"));
+        assert!(stats.format_markdown().contains("| Threads opened by reviewers | 3 |"));
+    }
+
+    #[test]
+    fn no_replies_means_no_average() {
+        let stats = ThreadStats::from_threads(&[]);
+        assert_eq!(stats.average_response, None);
+        assert!(!stats.format_text().contains("Average"));
+    }
+}
@@ -0,0 +1,258 @@
+// Consecutive-failure streaks per check.
+// Each analysis records, for every failing check, which run it looked at and
+// the errors extracted from it in `.pr-loop/streaks.json`. When a new run of
+// the check fails with the same errors as the last one, the streak grows;
+// a different error or a pass resets it. A long streak means the agent's
+// fixes aren't working, which is the signal to stop and escalate.
+
+use crate::audit::STATE_DIR;
+use crate::checks::{CheckStatus, ChecksSummary};
+use crate::context::FailingCheck;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+const STREAKS_FILE: &str = "streaks.json";
+
+/// Streaks shorter than this aren't worth mentioning.
+const MIN_REPORTED_RUNS: u32 = 2;
+/// Runs remembered per streak.
+const MAX_HISTORY: usize = 10;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct StreakEntry {
+    /// e.g. "owner/repo#123"
+    pr: String,
+    check: String,
+    /// The last run counted: its URL, or its start time without one.
+    run: String,
+    /// The errors extracted from that run, normalized (see `error_signature`).
+    error: String,
+    runs: u32,
+    /// The streak's most recent runs, oldest first.
+    #[serde(default)]
+    history: Vec<String>,
+}
+
+/// A check that keeps failing, as reported in the analysis.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FailureStreak {
+    pub check: String,
+    /// Consecutive failed runs, counting the current one.
+    pub runs: u32,
+    /// Whether errors were extracted (and matched across the runs), as
+    /// opposed to the check just failing without details each time.
+    pub same_error: bool,
+    /// The failed runs' URLs (or start times), oldest first; at most the
+    /// last ten.
+    pub history: Vec<String>,
+}
+
+impl FailureStreak {
+    pub fn describe(&self) -> String {
+        if self.same_error {
+            format!(
+                "{} has failed {} consecutive runs with the same extracted error",
+                self.check, self.runs
+            )
+        } else {
+            format!("{} has failed {} consecutive runs", self.check, self.runs)
+        }
+    }
+}
+
+/// The streaks recorded under a repository root.
+#[derive(Debug, Clone, Default)]
+pub struct FailureStreaks {
+    /// None when there's no repository to store them in.
+    path: Option<PathBuf>,
+    entries: Vec<StreakEntry>,
+}
+
+impl FailureStreaks {
+    /// Load `<root>/.pr-loop/streaks.json`.
+    pub fn load(root: &Path) -> Result<Self> {
+        let path = root.join(STATE_DIR).join(STREAKS_FILE);
+        let entries = match std::fs::read_to_string(&path) {
+            Ok(content) => serde_json::from_str(&content)
+                .with_context(|| format!("Failed to parse {}", path.display()))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => vec![],
+            Err(e) => return Err(e).with_context(|| format!("Failed to read {}", path.display())),
+        };
+        Ok(Self {
+            path: Some(path),
+            entries,
+        })
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let Some(path) = &self.path else { return Ok(()) };
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+            let gitignore = dir.join(".gitignore");
+            if !gitignore.exists() {
+                std::fs::write(&gitignore, "*\n")?;
+            }
+        }
+        std::fs::write(path, serde_json::to_string_pretty(&self.entries)? + "\n")
+            .with_context(|| format!("Failed to write {}", path.display()))
+    }
+
+    /// Record this analysis of `pr`: extend or start a streak for each
+    /// failing check, and end the streaks of checks that passed. Seeing the
+    /// same run again (the analysis rerun without a new CI run) doesn't
+    /// count. Returns the streaks worth reporting.
+    pub fn record(
+        &mut self,
+        pr: &str,
+        checks: &ChecksSummary,
+        failing: &[FailingCheck],
+    ) -> Vec<FailureStreak> {
+        self.entries.retain(|e| {
+            e.pr != pr
+                || !checks
+                    .checks
+                    .iter()
+                    .any(|c| c.name == e.check && c.status == CheckStatus::Pass)
+        });
+
+        let mut streaks = Vec::new();
+        for failed in checks.failed() {
+            let Some(run) = failed.url.clone().or_else(|| failed.started_at.clone()) else {
+                continue;
+            };
+            let errors = failing
+                .iter()
+                .find(|f| f.name == failed.name)
+                .map(|f| f.errors.as_slice())
+                .unwrap_or_default();
+            let error = error_signature(errors);
+
+            let existing = self
+                .entries
+                .iter_mut()
+                .find(|e| e.pr == pr && e.check == failed.name);
+            let entry = match existing {
+                Some(entry) => {
+                    if entry.run != run {
+                        if entry.error == error {
+                            entry.runs += 1;
+                        } else {
+                            entry.runs = 1;
+                            entry.history.clear();
+                        }
+                        entry.history.push(run.clone());
+                        if entry.history.len() > MAX_HISTORY {
+                            entry.history.remove(0);
+                        }
+                        entry.run = run;
+                        entry.error = error;
+                    }
+                    entry
+                }
+                None => {
+                    self.entries.push(StreakEntry {
+                        pr: pr.to_string(),
+                        check: failed.name.clone(),
+                        history: vec![run.clone()],
+                        run,
+                        error,
+                        runs: 1,
+                    });
+                    self.entries.last_mut().expect("just pushed")
+                }
+            };
+            if entry.runs >= MIN_REPORTED_RUNS {
+                streaks.push(FailureStreak {
+                    check: entry.check.clone(),
+                    runs: entry.runs,
+                    same_error: !entry.error.is_empty(),
+                    history: entry.history.clone(),
+                });
+            }
+        }
+        streaks
+    }
+}
+
+/// The extracted errors with digits collapsed, so durations, timestamps, and
+/// line counts that differ between runs don't hide a repeated failure.
+fn error_signature(errors: &[String]) -> String {
+    let mut signature = String::new();
+    let mut in_number = false;
+    for c in errors.join("\n").chars() {
+        if c.is_ascii_digit() {
+            if !in_number {
+                signature.push('#');
+            }
+            in_number = true;
+        } else {
+            signature.push(c);
+            in_number = false;
+        }
+    }
+    signature
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::checks::{Check, CheckSource};
+
+    fn summary(status: CheckStatus, run: u32) -> ChecksSummary {
+        ChecksSummary {
+            checks: vec![Check {
+                name: "ci/test".to_string(),
+                status,
+                url: Some(format!("https://ci/jobs/{}", run)),
+                source: CheckSource::CheckRun,
+                started_at: None,
+                attempts: 1,
+                app: None,
+            }],
+        }
+    }
+
+    fn failing(error: &str) -> Vec<FailingCheck> {
+        vec![FailingCheck {
+            name: "ci/test".to_string(),
+            url: None,
+            errors: vec![error.to_string()],
+            suggested_commands: vec![],
+        }]
+    }
+
+    #[test]
+    fn counts_new_runs_with_the_same_error() {
+        let mut streaks = FailureStreaks::default();
+        let same = "test foo failed after 1.2s";
+        assert!(streaks.record("o/r#1", &summary(CheckStatus::Fail, 1), &failing(same)).is_empty());
+        // The same run seen again doesn't count
+        assert!(streaks.record("o/r#1", &summary(CheckStatus::Fail, 1), &failing(same)).is_empty());
+        let reported = streaks.record(
+            "o/r#1",
+            &summary(CheckStatus::Fail, 2),
+            &failing("test foo failed after 3.4s"),
+        );
+        assert_eq!(
+            reported[0].describe(),
+            "ci/test has failed 2 consecutive runs with the same extracted error"
+        );
+        assert_eq!(
+            streaks.record("o/r#1", &summary(CheckStatus::Fail, 3), &failing(same))[0].history,
+            vec!["https://ci/jobs/1", "https://ci/jobs/2", "https://ci/jobs/3"]
+        );
+        // Other PRs have their own streaks
+        assert!(streaks.record("o/r#2", &summary(CheckStatus::Fail, 4), &failing(same)).is_empty());
+    }
+
+    #[test]
+    fn new_error_or_pass_resets() {
+        let mut streaks = FailureStreaks::default();
+        streaks.record("o/r#1", &summary(CheckStatus::Fail, 1), &failing("a"));
+        streaks.record("o/r#1", &summary(CheckStatus::Fail, 2), &failing("a"));
+        assert!(streaks.record("o/r#1", &summary(CheckStatus::Fail, 3), &failing("b")).is_empty());
+        streaks.record("o/r#1", &summary(CheckStatus::Pass, 4), &[]);
+        assert!(streaks.record("o/r#1", &summary(CheckStatus::Fail, 5), &failing("b")).is_empty());
+    }
+}
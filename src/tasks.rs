@@ -0,0 +1,239 @@
+// Structured tasks derived from actionable review threads.
+// `pr-loop tasks` pre-digests each thread awaiting a response into a task
+// object — where, what's being asked, constraints the reviewer stated, and
+// any suggested change — so an agent can plan from fields rather than from
+// raw comment bodies. Extraction is heuristic; the comment ID to reply to is
+// included so the agent can always go back to the source.
+
+use crate::comment_text;
+use crate::threads::ActionableThread;
+use serde::Serialize;
+
+/// Phrases that mark a sentence as a constraint on the fix rather than the
+/// fix itself, e.g. "without changing the public API".
+const CONSTRAINT_PHRASES: &[&str] = &[
+    "must",
+    "must not",
+    "don't",
+    "do not",
+    "never",
+    "avoid",
+    "without",
+    "keep",
+    "make sure",
+    "ensure",
+    "only",
+    "instead of",
+    "rather than",
+    "backwards compatible",
+    "backward compatible",
+];
+
+/// One actionable thread, normalized.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Task {
+    /// Thread ID.
+    pub id: String,
+    /// Latest unanswered comment, for `pr-loop reply --in-reply-to`.
+    pub reply_to: String,
+    /// Permalink to that comment.
+    pub reply_to_url: Option<String>,
+    /// Where the thread is, e.g. `src/lib.rs:10-12` or `src/lib.rs (file)`.
+    pub location: String,
+    pub path: Option<String>,
+    pub line: Option<u64>,
+    pub outdated: bool,
+    /// Urgency tag (blocking, question, comment, nit).
+    pub tag: Option<&'static str>,
+    /// Reviewers who commented since Claude's last reply.
+    pub reviewers: Vec<String>,
+    /// The request, from the first unanswered comment's opening paragraph.
+    pub ask: String,
+    /// Sentences stating constraints on how to address it.
+    pub constraints: Vec<String>,
+    pub has_suggestion: bool,
+    /// The last suggested change (GitHub ```suggestion block), verbatim.
+    pub suggestion: Option<String>,
+}
+
+impl Task {
+    pub fn new(actionable: &ActionableThread) -> Self {
+        let thread = &actionable.thread;
+        let pending = thread.unanswered_comments();
+        let texts: Vec<String> = pending.iter().map(|c| prose(&comment_text::to_text(&c.body))).collect();
+
+        let ask = texts
+            .iter()
+            .find_map(|t| {
+                t.split("\n\n")
+                    .map(collapse_whitespace)
+                    .find(|p| !p.is_empty())
+            })
+            .unwrap_or_default();
+
+        let mut constraints: Vec<String> = Vec::new();
+        for sentence in texts.iter().flat_map(|t| sentences(t)) {
+            if sentence != ask && is_constraint(&sentence) && !constraints.contains(&sentence) {
+                constraints.push(sentence);
+            }
+        }
+
+        let suggestion = pending.iter().rev().find_map(|c| suggestion_block(&c.body));
+        let mut reviewers: Vec<String> = Vec::new();
+        for comment in pending {
+            if !reviewers.contains(&comment.author) {
+                reviewers.push(comment.author.clone());
+            }
+        }
+
+        Self {
+            id: thread.id.clone(),
+            reply_to: pending.last().map(|c| c.id.clone()).unwrap_or_default(),
+            reply_to_url: pending.last().and_then(|c| c.url.clone()),
+            location: thread.location(),
+            path: thread.path.clone(),
+            line: thread.line,
+            outdated: thread.is_outdated,
+            tag: actionable.tag.map(|t| t.label()),
+            reviewers,
+            ask,
+            constraints,
+            has_suggestion: suggestion.is_some(),
+            suggestion,
+        }
+    }
+}
+
+/// The comment's prose: code blocks and quoted lines (usually the reviewer
+/// quoting someone else) removed.
+fn prose(body: &str) -> String {
+    let mut out = Vec::new();
+    let mut in_code = false;
+    for line in body.lines() {
+        if line.trim_start().starts_with("```") {
+            in_code = !in_code;
+            continue;
+        }
+        if in_code || line.trim_start().starts_with('>') {
+            continue;
+        }
+        out.push(line);
+    }
+    out.join("\n")
+}
+
+fn collapse_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Split prose into trimmed sentences on `.`, `!`, `?` and blank lines.
+fn sentences(text: &str) -> Vec<String> {
+    let mut out = Vec::new();
+    for paragraph in text.split("\n\n") {
+        let paragraph = collapse_whitespace(paragraph);
+        let mut current = String::new();
+        let mut chars = paragraph.chars().peekable();
+        while let Some(c) = chars.next() {
+            current.push(c);
+            let at_boundary = chars.peek().is_none_or(|n| *n == ' ');
+            if matches!(c, '.' | '!' | '?') && at_boundary {
+                out.push(current.trim().to_string());
+                current.clear();
+            }
+        }
+        if !current.trim().is_empty() {
+            out.push(current.trim().to_string());
+        }
+    }
+    out
+}
+
+fn is_constraint(sentence: &str) -> bool {
+    let lower = sentence.to_lowercase();
+    let words: Vec<&str> = lower
+        .split(|c: char| !(c.is_alphanumeric() || c == '\''))
+        .filter(|w| !w.is_empty())
+        .collect();
+    CONSTRAINT_PHRASES.iter().any(|phrase| {
+        let needle: Vec<&str> = phrase.split(' ').collect();
+        words.windows(needle.len()).any(|w| w == needle.as_slice())
+    })
+}
+
+/// Contents of the last ```suggestion block in a comment.
+fn suggestion_block(body: &str) -> Option<String> {
+    let mut found = None;
+    let mut current: Option<Vec<&str>> = None;
+    for line in body.lines() {
+        let trimmed = line.trim_start();
+        match &mut current {
+            None if trimmed.starts_with("```suggestion") => current = Some(Vec::new()),
+            Some(lines) if trimmed.starts_with("```") => {
+                found = Some(lines.join("\n"));
+                current = None;
+            }
+            Some(lines) => lines.push(line),
+            None => {}
+        }
+    }
+    found
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::threads::{ReviewThread, ThreadComment, CLAUDE_MARKER};
+
+    fn comment(id: &str, author: &str, body: &str) -> ThreadComment {
+        ThreadComment::for_test(id, author, body)
+    }
+
+    fn actionable(comments: Vec<ThreadComment>) -> ActionableThread {
+        ActionableThread {
+            thread: ReviewThread {
+                path: Some("src/db.rs".to_string()),
+                line: Some(10),
+                ..ReviewThread::for_test("T1", comments)
+            },
+            tag: None,
+        }
+    }
+
+    #[test]
+    fn extracts_ask_constraints_and_suggestion() {
+        let task = Task::new(&actionable(vec![
+            comment("C1", "alice", "Old question"),
+            comment("C2", "claude", &format!("{} Answered", CLAUDE_MARKER)),
+            comment(
+                "C3",
+                "alice",
+                "Please batch these queries.\nIt's slow on big repos.\n\n\
+                 Keep the public signature. Don't add a dependency for this!\n\n\
+                 ```suggestion\nlet rows = db.batch(&ids)?;\n```",
+            ),
+            comment("C4", "bob", "> Keep the public signature.\n+1, and avoid unwrap here"),
+        ]));
+
+        assert_eq!(task.reply_to, "C4");
+        assert_eq!(task.reviewers, vec!["alice", "bob"]);
+        assert_eq!(task.ask, "Please batch these queries. It's slow on big repos.");
+        assert_eq!(
+            task.constraints,
+            vec![
+                "Keep the public signature.",
+                "Don't add a dependency for this!",
+                "+1, and avoid unwrap here",
+            ]
+        );
+        assert!(task.has_suggestion);
+        assert_eq!(task.suggestion.as_deref(), Some("let rows = db.batch(&ids)?;"));
+    }
+
+    #[test]
+    fn plain_comment_has_no_constraints_or_suggestion() {
+        let task = Task::new(&actionable(vec![comment("C1", "alice", "Rename this to `fetch_rows`.")]));
+        assert_eq!(task.ask, "Rename this to `fetch_rows`.");
+        assert!(task.constraints.is_empty());
+        assert!(!task.has_suggestion);
+    }
+}
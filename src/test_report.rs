@@ -0,0 +1,164 @@
+// Failing tests from CI test reports.
+// A failed test job's stdout is mostly passing tests and runner noise; the
+// failures themselves are in the job's test report. CircleCI keeps one as
+// test metadata when the job uses `store_test_results`; other jobs often
+// still upload the JUnit XML their runner wrote as an artifact. Both are read
+// into the same compact list: test, message, and file location, so the
+// recommendation can show that instead of the raw log.
+
+use crate::circleci::RawTestFailure;
+use anyhow::{Context, Result};
+use serde::Deserialize;
+
+/// Only this many XML artifacts are read per job.
+pub const MAX_REPORT_ARTIFACTS: usize = 10;
+
+/// Larger artifacts are skipped.
+pub const MAX_REPORT_BYTES: u64 = 10 * 1024 * 1024;
+
+/// One test in CircleCI's test metadata (`/project/{slug}/{job}/tests`).
+#[derive(Deserialize)]
+pub struct MetadataItem {
+    classname: String,
+    name: String,
+    result: String,
+    #[serde(default)]
+    message: String,
+    #[serde(default)]
+    file: Option<String>,
+}
+
+/// The failures among CircleCI test metadata items.
+pub fn metadata_failures(items: Vec<MetadataItem>) -> Vec<RawTestFailure> {
+    items
+        .into_iter()
+        .filter(|item| item.result == "failure")
+        .map(|item| RawTestFailure {
+            location: location(item.file.as_deref(), None, &item.message),
+            classname: item.classname,
+            test_name: item.name,
+            message: item.message,
+        })
+        .collect()
+}
+
+/// Whether an artifact path looks like a JUnit report worth downloading.
+pub fn is_report_artifact(path: &str) -> bool {
+    path.to_ascii_lowercase().ends_with(".xml")
+}
+
+/// The failed and errored `<testcase>`s of a JUnit XML report (a
+/// `<testsuites>` or a single `<testsuite>`). Anything else, e.g. an
+/// unrelated XML artifact, has none.
+pub fn junit_failures(xml: &str) -> Result<Vec<RawTestFailure>> {
+    let document = roxmltree::Document::parse(xml).context("Failed to parse test report XML")?;
+    Ok(document
+        .descendants()
+        .filter(|node| node.has_tag_name("testcase"))
+        .filter_map(|case| {
+            let problem = case
+                .children()
+                .find(|c| c.has_tag_name("failure") || c.has_tag_name("error"))?;
+            let details = problem.text().unwrap_or("").trim();
+            let message = match problem.attribute("message").map(str::trim) {
+                Some(summary) if !summary.is_empty() && !details.starts_with(summary) => {
+                    if details.is_empty() {
+                        summary.to_string()
+                    } else {
+                        format!("{}\n{}", summary, details)
+                    }
+                }
+                _ => details.to_string(),
+            };
+            Some(RawTestFailure {
+                classname: case.attribute("classname").unwrap_or("").to_string(),
+                test_name: case.attribute("name").unwrap_or("").to_string(),
+                location: location(case.attribute("file"), case.attribute("line"), &message),
+                message,
+            })
+        })
+        .collect())
+}
+
+/// Where a failing test is: the report's file (and line) if it gave one,
+/// else the first `path:line` in the failure message. A message's line for
+/// the report's file completes it.
+fn location(file: Option<&str>, line: Option<&str>, message: &str) -> Option<String> {
+    match (file.filter(|f| !f.is_empty()), line) {
+        (Some(file), Some(line)) => Some(format!("{}:{}", file, line)),
+        (Some(file), None) => Some(
+            message_locations(message)
+                .find(|(path, _)| file.ends_with(path) || path.ends_with(file))
+                .map_or_else(|| file.to_string(), |(_, line)| format!("{}:{}", file, line)),
+        ),
+        (None, _) => message_locations(message)
+            .next()
+            .map(|(path, line)| format!("{}:{}", path, line)),
+    }
+}
+
+/// The `path/to/file.ext:line` references in `message`, e.g. from
+/// "tests/test_api.py:42: AssertionError" or "at parse (src/parse.ts:12:7)".
+fn message_locations(message: &str) -> impl Iterator<Item = (&str, &str)> {
+    message
+        .split(|c: char| c.is_whitespace() || "()[]<>\"'".contains(c))
+        .filter_map(|word| {
+            let mut parts = word.split(':');
+            let path = parts.next()?.trim_start_matches("file://");
+            let line = parts.next()?;
+            let is_path = path.contains('.') && !path.contains("//") && !path.ends_with('.');
+            let is_line = !line.is_empty() && line.bytes().all(|b| b.is_ascii_digit());
+            (is_path && is_line).then_some((path, line))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_failures_from_junit_xml() {
+        let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+<testsuites>
+  <testsuite name="api" tests="3" failures="1" errors="1">
+    <testcase classname="tests.test_api" name="test_ok" time="0.01"/>
+    <testcase classname="tests.test_api" name="test_limit" file="tests/test_api.py" line="42">
+      <failure message="AssertionError: assert 3 == 2">def test_limit():
+&gt;       assert len(page) == 2
+E       AssertionError: assert 3 == 2
+
+tests/test_api.py:44: AssertionError</failure>
+    </testcase>
+    <testcase classname="src/parse.test.ts" name="parses dates">
+      <error message="TypeError: x is undefined">TypeError: x is undefined
+    at parse (src/parse.ts:12:7)</error>
+    </testcase>
+  </testsuite>
+</testsuites>"#;
+        let failures = junit_failures(xml).unwrap();
+        assert_eq!(failures.len(), 2);
+        assert_eq!(failures[0].test_name, "test_limit");
+        assert_eq!(failures[0].location.as_deref(), Some("tests/test_api.py:42"));
+        assert!(failures[0].message.starts_with("AssertionError: assert 3 == 2\ndef test_limit():"));
+        assert_eq!(failures[1].classname, "src/parse.test.ts");
+        assert_eq!(failures[1].message, "TypeError: x is undefined\n    at parse (src/parse.ts:12:7)");
+        assert_eq!(failures[1].location.as_deref(), Some("src/parse.ts:12"));
+
+        assert!(junit_failures("<coverage line-rate=\"0.9\"/>").unwrap().is_empty());
+        assert!(junit_failures("not xml").is_err());
+    }
+
+    #[test]
+    fn keeps_only_failed_metadata() {
+        let items: Vec<MetadataItem> = serde_json::from_value(serde_json::json!([
+            { "classname": "FooTest", "name": "passes", "result": "success" },
+            { "classname": "FooTest", "name": "fails", "result": "failure", "file": "src/foo_test.go",
+              "message": "foo_test.go:12: got 1, want 2" },
+        ]))
+        .unwrap();
+        let failures = metadata_failures(items);
+        assert_eq!(failures.len(), 1);
+        assert_eq!(failures[0].test_name, "fails");
+        assert_eq!(failures[0].location.as_deref(), Some("src/foo_test.go:12"));
+    }
+}
@@ -0,0 +1,136 @@
+// Every review thread on the PR, for inspection.
+// The analysis only shows threads that need a response; when one is missing
+// (or one shows up that shouldn't), `pr-loop threads` lists them all with
+// what the cleanup and filters see in each — resolved, pure-Claude,
+// paperclip — and whether the analysis counts it, with the same reason
+// `--explain` gives.
+
+use crate::explain::Entry;
+use crate::threads::ReviewThread;
+use serde::Serialize;
+
+/// One thread as `pr-loop threads` reports it.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ThreadRow {
+    pub id: String,
+    /// e.g. "src/main.rs:42"
+    pub location: String,
+    pub resolved: bool,
+    pub outdated: bool,
+    pub comments: usize,
+    pub last_author: Option<String>,
+    /// Every comment is Claude's, so `ready` and `clean-threads` delete it
+    /// once resolved.
+    pub pure_claude: bool,
+    /// Marked 📎 for a human: ignored by the analysis, kept by cleanup.
+    pub paperclip: bool,
+    /// Whether the analysis counts it as needing a response.
+    pub actionable: bool,
+    pub reason: String,
+}
+
+/// Rows for `threads`, given the explanation of each (in the same order).
+pub fn rows(threads: &[ReviewThread], entries: &[Entry]) -> Vec<ThreadRow> {
+    threads
+        .iter()
+        .zip(entries)
+        .map(|(thread, entry)| ThreadRow {
+            id: thread.id.clone(),
+            location: thread.location(),
+            resolved: thread.is_resolved,
+            outdated: thread.is_outdated,
+            comments: thread.comments.len(),
+            last_author: thread.last_comment().map(|c| c.author.clone()),
+            pure_claude: thread.is_pure_claude(),
+            paperclip: thread.has_paperclip(),
+            actionable: entry.counted,
+            reason: entry.reason.clone(),
+        })
+        .collect()
+}
+
+/// The rows as a numbered list, two lines per thread.
+pub fn render(rows: &[ThreadRow]) -> String {
+    let mut out = format!("## Review Threads ({})\n", rows.len());
+    if rows.is_empty() {
+        out.push_str("\n(no review threads)\n");
+    }
+    for (i, row) in rows.iter().enumerate() {
+        let mut facts = vec![
+            if row.resolved { "resolved" } else { "unresolved" }.to_string(),
+            format!("{} comment{}", row.comments, if row.comments == 1 { "" } else { "s" }),
+        ];
+        if let Some(author) = &row.last_author {
+            facts.push(format!("last by @{}", author));
+        }
+        if row.outdated {
+            facts.push("outdated".to_string());
+        }
+        if row.pure_claude {
+            facts.push("pure Claude".to_string());
+        }
+        if row.paperclip {
+            facts.push("📎".to_string());
+        }
+        out.push_str(&format!("\n{}. {} (`{}`): {}\n", i + 1, row.location, row.id, facts.join(", ")));
+        out.push_str(&format!(
+            "   {}: {}\n",
+            if row.actionable { "actionable" } else { "not actionable" },
+            row.reason
+        ));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::threads::{ThreadComment, CLAUDE_MARKER};
+
+    fn thread(id: &str, resolved: bool, comments: &[(&str, &str)]) -> ReviewThread {
+        let comments = comments
+            .iter()
+            .enumerate()
+            .map(|(i, (author, body))| {
+                ThreadComment::for_test(&format!("{}-C{}", id, i), author, body)
+            })
+            .collect();
+        ReviewThread {
+            is_resolved: resolved,
+            line: Some(3),
+            ..ReviewThread::for_test(id, comments)
+        }
+    }
+
+    #[test]
+    fn lists_each_thread_with_its_classification() {
+        let claude = format!("{} Done", CLAUDE_MARKER);
+        let threads = vec![
+            thread("T1", false, &[("alice", "Rename this?")]),
+            thread("T2", true, &[("bot", &claude)]),
+        ];
+        let entries = vec![
+            Entry {
+                subject: "src/lib.rs:3".to_string(),
+                counted: true,
+                reason: "needs a response (@alice commented last)".to_string(),
+            },
+            Entry {
+                subject: "src/lib.rs:3".to_string(),
+                counted: false,
+                reason: "resolved".to_string(),
+            },
+        ];
+        let rows = rows(&threads, &entries);
+        assert!(!rows[0].pure_claude && rows[1].pure_claude);
+        assert_eq!(rows[1].last_author.as_deref(), Some("bot"));
+        assert_eq!(
+            render(&rows),
+            "## Review Threads (2)\n\
+             \n1. src/lib.rs:3 (`T1`): unresolved, 1 comment, last by @alice\n\
+             \x20  actionable: needs a response (@alice commented last)\n\
+             \n2. src/lib.rs:3 (`T2`): resolved, 1 comment, last by @bot, pure Claude\n\
+             \x20  not actionable: resolved\n"
+        );
+    }
+}
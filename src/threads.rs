@@ -1,9 +1,12 @@
 // PR review thread handling via GitHub GraphQL API.
 // Fetches review threads including resolution status and comments.
 
+use crate::triage::ThreadTag;
+use crate::graphql::{batch_alias, batch_operation, gh_graphql};
+use crate::rate_limit::{self, RateLimit};
 use anyhow::{Context, Result};
-use serde::Deserialize;
-use std::process::Command;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
 
 /// A comment in a review thread.
 #[derive(Debug, Clone)]
@@ -14,6 +17,38 @@ pub struct ThreadComment {
     pub diff_hunk: Option<String>,
     pub url: Option<String>,
     pub created_at: Option<String>,
+    /// The review the comment was submitted in.
+    pub review: Option<CommentReview>,
+}
+
+/// The review a comment arrived in: a reviewer's batch of comments,
+/// submitted together.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommentReview {
+    pub id: String,
+    /// None while the review is still pending.
+    pub submitted_at: Option<String>,
+}
+
+impl ThreadComment {
+    /// "comment `ID`", followed by the comment's permalink when known.
+    pub fn reference(&self) -> String {
+        comment_reference(&self.id, self.url.as_deref())
+    }
+
+    /// Whether Claude posted this comment: its body starts with the marker.
+    pub fn is_from_claude(&self) -> bool {
+        self.body.starts_with(CLAUDE_MARKER)
+    }
+}
+
+/// How output refers to a comment: its node ID (what `reply --in-reply-to`
+/// takes) and, when known, its URL for humans to click.
+pub fn comment_reference(id: &str, url: Option<&str>) -> String {
+    match url {
+        Some(url) => format!("comment `{}`, {}", id, url),
+        None => format!("comment `{}`", id),
+    }
 }
 
 /// A review thread on a PR.
@@ -23,10 +58,29 @@ pub struct ReviewThread {
     pub is_resolved: bool,
     pub is_outdated: bool,
     pub path: Option<String>,
+    /// Last line of the commented range; None once the lines are gone
     pub line: Option<u64>,
+    /// First line of a multi-line comment
+    pub start_line: Option<u64>,
+    /// The line the comment was made on, kept after the code changes
+    pub original_line: Option<u64>,
+    /// A comment on the file as a whole rather than on particular lines
+    pub is_file_level: bool,
+    /// A comment on the left side of the diff: `line` is in the base version
+    /// and the code it points at no longer exists
+    pub on_removed_code: bool,
     pub comments: Vec<ThreadComment>,
 }
 
+/// A review that has been started but not yet submitted. GitHub only exposes
+/// pending reviews to their author, so these are the viewer's own drafts.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PendingReview {
+    pub author: String,
+    /// Number of draft comments in the review so far.
+    pub comment_count: u64,
+}
+
 /// The marker prefix that Claude uses when replying to threads.
 pub const CLAUDE_MARKER: &str = "🤖 From Claude:";
 
@@ -38,19 +92,96 @@ pub const CLAUDE_MARKER: &str = "🤖 From Claude:";
 pub const PAPERCLIP_SHORTCODE: &str = ":paperclip:";
 pub const PAPERCLIP_EMOJI: &str = "📎";
 
+/// What a Claude reply claims about its thread, shown as an emoji right
+/// after the marker (`🤖 From Claude: ✅ ...`). A small fixed set, so the
+/// analysis can tell "Claude asked the reviewer something" from "Claude
+/// says it's fixed".
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum ReplyStatus {
+    /// The feedback was addressed.
+    Fixed,
+    /// Claude asked the reviewer a question and needs an answer.
+    Question,
+    /// The work was deferred to a follow-up issue.
+    Followup,
+}
+
+impl ReplyStatus {
+    const ALL: [ReplyStatus; 3] = [ReplyStatus::Fixed, ReplyStatus::Question, ReplyStatus::Followup];
+
+    pub fn emoji(&self) -> &'static str {
+        match self {
+            ReplyStatus::Fixed => "✅",
+            ReplyStatus::Question => "❓",
+            ReplyStatus::Followup => "📝",
+        }
+    }
+
+    /// The status a comment was posted with: None for human comments and
+    /// Claude replies without one.
+    pub fn of(body: &str) -> Option<Self> {
+        let rest = body.strip_prefix(CLAUDE_MARKER)?.trim_start();
+        Self::ALL.into_iter().find(|s| rest.starts_with(s.emoji()))
+    }
+}
+
 impl ReviewThread {
     /// Returns the last comment in the thread.
     pub fn last_comment(&self) -> Option<&ThreadComment> {
         self.comments.last()
     }
 
+    /// The status Claude's reply was posted with, if Claude has the last word.
+    pub fn last_reply_status(&self) -> Option<ReplyStatus> {
+        self.last_comment().and_then(|c| ReplyStatus::of(&c.body))
+    }
+
+    /// Format the thread location for display: `path:line`, `path:a-b` for
+    /// a range, the original line of an outdated comment, or the file alone
+    /// for file-level comments. Comments on removed code are labelled so the
+    /// line isn't mistaken for one in the current file.
+    pub fn location(&self) -> String {
+        let Some(path) = &self.path else {
+            return "the PR as a whole".to_string();
+        };
+        if self.is_file_level {
+            return format!("{} (file)", path);
+        }
+        if self.on_removed_code {
+            return match self.line.or(self.original_line) {
+                Some(line) => format!("{}:{} (comment on removed code)", path, line),
+                None => format!("{} (comment on removed code)", path),
+            };
+        }
+        match (self.start_line, self.line, self.original_line) {
+            (Some(start), Some(end), _) if start < end => format!("{}:{}-{}", path, start, end),
+            (_, Some(line), _) => format!("{}:{}", path, line),
+            (_, None, Some(original)) => format!("{}:{} (outdated)", path, original),
+            _ => path.clone(),
+        }
+    }
+
+    /// The diff hunk GitHub recorded with the first comment, ending at the
+    /// commented line.
+    pub fn diff_hunk(&self) -> Option<&str> {
+        self.comments.first().and_then(|c| c.diff_hunk.as_deref())
+    }
+
+    /// Returns true if Claude's last reply asked the reviewer a question
+    /// (`reply --status question`) and the thread is still open: waiting on a
+    /// human, not on Claude.
+    pub fn is_awaiting_input(&self) -> bool {
+        !self.is_resolved && self.last_reply_status() == Some(ReplyStatus::Question)
+    }
+
     /// Returns human (non-Claude) comments that appear after the specified comment ID.
     /// Returns None if the comment ID is not found in this thread.
     pub fn human_comments_after(&self, comment_id: &str) -> Option<Vec<ThreadComment>> {
         let index = self.comments.iter().position(|c| c.id == comment_id)?;
         let comments_after: Vec<_> = self.comments[index + 1..]
             .iter()
-            .filter(|c| !c.body.starts_with(CLAUDE_MARKER))
+            .filter(|c| !c.is_from_claude())
             .cloned()
             .collect();
         Some(comments_after)
@@ -65,7 +196,7 @@ impl ReviewThread {
         }
 
         match self.last_comment() {
-            Some(comment) => !comment.body.starts_with(CLAUDE_MARKER),
+            Some(comment) => !comment.is_from_claude(),
             None => false, // Empty thread, nothing to respond to
         }
     }
@@ -83,13 +214,13 @@ impl ReviewThread {
         let claude_authors: std::collections::HashSet<&str> = self
             .comments
             .iter()
-            .filter(|c| c.body.starts_with(CLAUDE_MARKER))
+            .filter(|c| c.is_from_claude())
             .map(|c| c.author.as_str())
             .collect();
 
         // Thread is pure-Claude if every comment is either Claude-marked OR from a Claude author
         self.comments.iter().all(|c| {
-            c.body.starts_with(CLAUDE_MARKER) || claude_authors.contains(c.author.as_str())
+            c.is_from_claude() || claude_authors.contains(c.author.as_str())
         })
     }
 
@@ -105,22 +236,68 @@ impl ReviewThread {
     pub fn comment_ids(&self) -> Vec<&str> {
         self.comments.iter().map(|c| c.id.as_str()).collect()
     }
+
+    /// Returns the comments since Claude's last reply (all of them if Claude
+    /// hasn't replied): what still needs a response.
+    pub fn unanswered_comments(&self) -> &[ThreadComment] {
+        let start = self
+            .comments
+            .iter()
+            .rposition(|c| c.is_from_claude())
+            .map_or(0, |i| i + 1);
+        &self.comments[start..]
+    }
+}
+
+#[cfg(test)]
+impl ThreadComment {
+    /// A comment for tests; fill in the optional fields with struct update
+    /// syntax.
+    pub(crate) fn for_test(id: &str, author: &str, body: &str) -> Self {
+        ThreadComment {
+            id: id.to_string(),
+            author: author.to_string(),
+            body: body.to_string(),
+            diff_hunk: None,
+            url: None,
+            created_at: None,
+            review: None,
+        }
+    }
+}
+
+#[cfg(test)]
+impl ReviewThread {
+    /// An unresolved thread on src/lib.rs:1 for tests; override the rest with
+    /// struct update syntax.
+    pub(crate) fn for_test(id: &str, comments: Vec<ThreadComment>) -> Self {
+        ReviewThread {
+            id: id.to_string(),
+            is_resolved: false,
+            is_outdated: false,
+            path: Some("src/lib.rs".to_string()),
+            line: Some(1),
+            start_line: None,
+            original_line: None,
+            is_file_level: false,
+            on_removed_code: false,
+            comments,
+        }
+    }
 }
 
 /// A thread that needs a response, with additional context for display.
 #[derive(Debug, Clone)]
 pub struct ActionableThread {
     pub thread: ReviewThread,
+    /// Urgency tag, when `--tag-threads` is on.
+    pub tag: Option<ThreadTag>,
 }
 
 impl ActionableThread {
     /// Format the thread location for display.
     pub fn location(&self) -> String {
-        match (&self.thread.path, self.thread.line) {
-            (Some(path), Some(line)) => format!("{}:{}", path, line),
-            (Some(path), None) => path.clone(),
-            _ => "unknown location".to_string(),
-        }
+        self.thread.location()
     }
 }
 
@@ -130,18 +307,39 @@ pub fn find_actionable_threads(threads: Vec<ReviewThread>) -> Vec<ActionableThre
     threads
         .into_iter()
         .filter(|t| !t.has_paperclip() && t.needs_response())
-        .map(|thread| ActionableThread { thread })
+        .map(|thread| ActionableThread { thread, tag: None })
         .collect()
 }
 
-/// Trait for fetching review threads, allowing test implementations.
-pub trait ThreadsClient {
+/// Find threads waiting on a reviewer to answer Claude's question.
+/// Paperclip threads are excluded, as for actionable threads.
+pub fn find_awaiting_input_threads(threads: &[ReviewThread]) -> Vec<ReviewThread> {
+    threads
+        .iter()
+        .filter(|t| !t.has_paperclip() && t.is_awaiting_input())
+        .cloned()
+        .collect()
+}
+
+/// Trait for fetching review threads, allowing test implementations. `Sync`
+/// so threads can be fetched while other requests are in flight.
+pub trait ThreadsClient: Sync {
     fn fetch_threads(&self, owner: &str, repo: &str, pr_number: u64)
         -> Result<Vec<ReviewThread>>;
 
     /// Fetch the thread containing a specific comment, returning both the thread and confirming
     /// the comment exists.
     fn fetch_thread_by_comment_id(&self, comment_id: &str) -> Result<ReviewThread>;
+
+    /// Fetch reviews that are in progress (started but not submitted).
+    fn fetch_pending_reviews(
+        &self,
+        _owner: &str,
+        _repo: &str,
+        _pr_number: u64,
+    ) -> Result<Vec<PendingReview>> {
+        Ok(vec![])
+    }
 }
 
 /// Real client that uses `gh api graphql`.
@@ -160,6 +358,15 @@ impl ThreadsClient for RealThreadsClient {
     fn fetch_thread_by_comment_id(&self, comment_id: &str) -> Result<ReviewThread> {
         fetch_thread_by_comment_id_graphql(comment_id)
     }
+
+    fn fetch_pending_reviews(
+        &self,
+        owner: &str,
+        repo: &str,
+        pr_number: u64,
+    ) -> Result<Vec<PendingReview>> {
+        fetch_pending_reviews_graphql(owner, repo, pr_number)
+    }
 }
 
 // GraphQL response structures
@@ -177,6 +384,8 @@ struct GraphQLError {
 #[derive(Deserialize)]
 struct GraphQLData {
     repository: Option<RepositoryData>,
+    #[serde(rename = "rateLimit", default)]
+    rate_limit: Option<RateLimit>,
 }
 
 #[derive(Deserialize)]
@@ -215,6 +424,14 @@ struct ReviewThreadNode {
     is_outdated: bool,
     path: Option<String>,
     line: Option<u64>,
+    #[serde(rename = "startLine", default)]
+    start_line: Option<u64>,
+    #[serde(rename = "originalLine", default)]
+    original_line: Option<u64>,
+    #[serde(rename = "subjectType", default)]
+    subject_type: Option<String>,
+    #[serde(rename = "diffSide", default)]
+    diff_side: Option<String>,
     comments: CommentsConnection,
 }
 
@@ -236,6 +453,32 @@ struct CommentNode {
     url: Option<String>,
     #[serde(rename = "createdAt", default)]
     created_at: Option<String>,
+    #[serde(rename = "pullRequestReview", default)]
+    review: Option<ReviewNode>,
+}
+
+#[derive(Deserialize)]
+struct ReviewNode {
+    id: String,
+    #[serde(rename = "submittedAt", default)]
+    submitted_at: Option<String>,
+}
+
+impl CommentNode {
+    fn into_comment(self) -> ThreadComment {
+        ThreadComment {
+            id: self.id,
+            author: self.author.map(|a| a.login).unwrap_or_else(|| "ghost".to_string()),
+            body: self.body,
+            diff_hunk: self.diff_hunk,
+            url: self.url,
+            created_at: self.created_at,
+            review: self.review.map(|r| CommentReview {
+                id: r.id,
+                submitted_at: r.submitted_at,
+            }),
+        }
+    }
 }
 
 #[derive(Deserialize)]
@@ -251,6 +494,8 @@ fn fetch_threads_from_graphql(
 ) -> Result<Vec<ReviewThread>> {
     let mut all_threads: Vec<ReviewThread> = Vec::new();
     let mut threads_cursor: Option<String> = None;
+    // Threads with more than a page of comments, and where their next page starts
+    let mut unfinished: Vec<(String, Option<String>)> = Vec::new();
 
     // Paginate through all review threads
     loop {
@@ -258,35 +503,20 @@ fn fetch_threads_from_graphql(
             fetch_threads_page(owner, repo, pr_number, threads_cursor.as_deref())?;
 
         for t in thread_nodes {
-            let thread_id = t.id.clone();
-            let mut comments: Vec<ThreadComment> = t
-                .comments
-                .nodes
-                .into_iter()
-                .map(|c| ThreadComment {
-                    id: c.id,
-                    author: c.author.map(|a| a.login).unwrap_or_else(|| "ghost".to_string()),
-                    body: c.body,
-                    diff_hunk: c.diff_hunk,
-                    url: c.url,
-                    created_at: c.created_at,
-                })
-                .collect();
-
-            // If this thread has more comments, fetch them
             if t.comments.page_info.has_next_page {
-                let additional_comments =
-                    fetch_remaining_comments(&thread_id, t.comments.page_info.end_cursor)?;
-                comments.extend(additional_comments);
+                unfinished.push((t.id.clone(), t.comments.page_info.end_cursor));
             }
-
             all_threads.push(ReviewThread {
                 id: t.id,
                 is_resolved: t.is_resolved,
                 is_outdated: t.is_outdated,
                 path: t.path,
                 line: t.line,
-                comments,
+                start_line: t.start_line,
+                original_line: t.original_line,
+                is_file_level: t.subject_type.as_deref() == Some("FILE"),
+                on_removed_code: t.diff_side.as_deref() == Some("LEFT"),
+                comments: t.comments.nodes.into_iter().map(CommentNode::into_comment).collect(),
             });
         }
 
@@ -296,6 +526,15 @@ fn fetch_threads_from_graphql(
         threads_cursor = page_info.end_cursor;
     }
 
+    if !unfinished.is_empty() {
+        let mut remaining = fetch_remaining_comments(unfinished)?;
+        for thread in &mut all_threads {
+            if let Some(comments) = remaining.remove(&thread.id) {
+                thread.comments.extend(comments);
+            }
+        }
+    }
+
     Ok(all_threads)
 }
 
@@ -311,27 +550,13 @@ fn fetch_threads_page(
 ) -> Result<(Vec<ReviewThreadNode>, PageInfo)> {
     let query = FETCH_THREADS_QUERY;
 
-    let mut args = vec![
-        "api".to_string(),
-        "graphql".to_string(),
-        "-f".to_string(),
-        format!("query={}", query),
-        "-f".to_string(),
-        format!("owner={}", owner),
-        "-f".to_string(),
-        format!("repo={}", repo),
-        "-F".to_string(),
-        format!("pr={}", pr_number),
-    ];
+    let mut variables = json!({ "owner": owner, "repo": repo, "pr": pr_number });
 
     if let Some(c) = cursor {
-        args.push("-f".to_string());
-        args.push(format!("cursor={}", c));
+        variables["cursor"] = json!(c);
     }
 
-    let output = Command::new("gh")
-        .args(&args)
-        .output()
+    let output = gh_graphql(query, variables)
         .context("Failed to run 'gh api graphql'")?;
 
     if !output.status.success() {
@@ -347,8 +572,11 @@ fn fetch_threads_page(
         anyhow::bail!("GraphQL errors: {}", messages.join(", "));
     }
 
-    let review_threads = response
-        .data
+    let data = response.data;
+    if let Some(rate_limit) = data.as_ref().and_then(|d| d.rate_limit.clone()) {
+        rate_limit::observe(rate_limit);
+    }
+    let review_threads = data
         .and_then(|d| d.repository)
         .and_then(|r| r.pull_request)
         .map(|pr| pr.review_threads)
@@ -361,89 +589,77 @@ fn fetch_threads_page(
 const FETCH_REMAINING_COMMENTS_QUERY: &str =
     include_str!("../graphql/operation/fetch_remaining_comments.graphql");
 
-/// Fetch remaining comments for a thread that has more than 100 comments.
-fn fetch_remaining_comments(
-    thread_id: &str,
-    start_cursor: Option<String>,
-) -> Result<Vec<ThreadComment>> {
-    let mut all_comments: Vec<ThreadComment> = Vec::new();
-    let mut cursor = start_cursor;
-
-    loop {
-        let query = FETCH_REMAINING_COMMENTS_QUERY;
-
-        let mut args = vec![
-            "api".to_string(),
-            "graphql".to_string(),
-            "-f".to_string(),
-            format!("query={}", query),
-            "-f".to_string(),
-            format!("id={}", thread_id),
-        ];
-
-        if let Some(c) = &cursor {
-            args.push("-f".to_string());
-            args.push(format!("cursor={}", c));
-        }
-
-        let output = Command::new("gh")
-            .args(&args)
-            .output()
-            .context("Failed to run 'gh api graphql'")?;
-
-        if !output.status.success() {
-            let stderr = String::from_utf8_lossy(&output.stderr);
-            anyhow::bail!("GraphQL query failed: {}", stderr.trim());
-        }
+/// How many threads' next pages of comments one request fetches.
+const REMAINING_COMMENTS_BATCH: usize = 25;
 
-        let response: SingleThreadGraphQLResponse = serde_json::from_slice(&output.stdout)
-            .context("Failed to parse GraphQL response")?;
+/// Fetch the rest of the comments of threads with more than 100, given each
+/// thread's ID and where its next page starts. The threads' next pages are
+/// fetched together, in batched requests, until every thread is complete.
+fn fetch_remaining_comments(
+    unfinished: Vec<(String, Option<String>)>,
+) -> Result<std::collections::HashMap<String, Vec<ThreadComment>>> {
+    let mut fetched: std::collections::HashMap<String, Vec<ThreadComment>> =
+        std::collections::HashMap::new();
+    let mut pending = unfinished;
+
+    while !pending.is_empty() {
+        let mut still_pending = Vec::new();
+        for batch in pending.chunks(REMAINING_COMMENTS_BATCH) {
+            let items: Vec<serde_json::Value> = batch
+                .iter()
+                .map(|(id, cursor)| json!({ "id": id, "cursor": cursor }))
+                .collect();
+            let (query, variables) = batch_operation(FETCH_REMAINING_COMMENTS_QUERY, &items);
 
-        if let Some(errors) = response.errors {
-            let messages: Vec<_> = errors.iter().map(|e| e.message.as_str()).collect();
-            anyhow::bail!("GraphQL errors: {}", messages.join(", "));
-        }
+            let output = gh_graphql(&query, variables)
+                .context("Failed to run 'gh api graphql'")?;
 
-        let thread_node = response
-            .data
-            .and_then(|d| d.node)
-            .ok_or_else(|| anyhow::anyhow!("Thread not found: {}", thread_id))?;
+            if !output.status.success() {
+                let stderr = String::from_utf8_lossy(&output.stderr);
+                anyhow::bail!("GraphQL query failed: {}", stderr.trim());
+            }
 
-        let comments: Vec<ThreadComment> = thread_node
-            .comments
-            .nodes
-            .into_iter()
-            .map(|c| ThreadComment {
-                id: c.id,
-                author: c.author.map(|a| a.login).unwrap_or_else(|| "ghost".to_string()),
-                body: c.body,
-                diff_hunk: c.diff_hunk,
-                url: c.url,
-                created_at: c.created_at,
-            })
-            .collect();
+            let response: RemainingCommentsResponse = serde_json::from_slice(&output.stdout)
+                .context("Failed to parse GraphQL response")?;
 
-        all_comments.extend(comments);
+            if let Some(errors) = response.errors {
+                let messages: Vec<_> = errors.iter().map(|e| e.message.as_str()).collect();
+                anyhow::bail!("GraphQL errors: {}", messages.join(", "));
+            }
 
-        if !thread_node.comments.page_info.has_next_page {
-            break;
+            let mut data = response.data.unwrap_or_default();
+            for (i, (thread_id, _)) in batch.iter().enumerate() {
+                let comments = data
+                    .remove(&batch_alias(i))
+                    .flatten()
+                    .ok_or_else(|| anyhow::anyhow!("Thread not found: {}", thread_id))?
+                    .comments;
+                fetched
+                    .entry(thread_id.clone())
+                    .or_default()
+                    .extend(comments.nodes.into_iter().map(CommentNode::into_comment));
+                if comments.page_info.has_next_page {
+                    still_pending.push((thread_id.clone(), comments.page_info.end_cursor));
+                }
+            }
         }
-        cursor = thread_node.comments.page_info.end_cursor;
+        pending = still_pending;
     }
 
-    Ok(all_comments)
+    Ok(fetched)
 }
 
-// GraphQL response structures for single thread query
+// GraphQL response structures for the batched remaining-comments query: each
+// thread's comments, by alias
 #[derive(Deserialize)]
-struct SingleThreadGraphQLResponse {
-    data: Option<SingleThreadData>,
+struct RemainingCommentsResponse {
+    data: Option<std::collections::HashMap<String, Option<ThreadCommentsNode>>>,
     errors: Option<Vec<GraphQLError>>,
 }
 
 #[derive(Deserialize)]
-struct SingleThreadData {
-    node: Option<ReviewThreadNode>,
+struct ThreadCommentsNode {
+    comments: CommentsConnection,
 }
 
 /// GraphQL query for fetching PR info from a comment (loaded from graphql/operation/).
@@ -455,16 +671,7 @@ fn fetch_thread_by_comment_id_graphql(comment_id: &str) -> Result<ReviewThread>
     // First, get the PR info from the comment (GitHub doesn't expose a direct thread field)
     let query = FETCH_COMMENT_PR_INFO_QUERY;
 
-    let output = Command::new("gh")
-        .args([
-            "api",
-            "graphql",
-            "-f",
-            &format!("query={}", query),
-            "-f",
-            &format!("id={}", comment_id),
-        ])
-        .output()
+    let output = gh_graphql(query, json!({ "id": comment_id }))
         .context("Failed to run 'gh api graphql'")?;
 
     if !output.status.success() {
@@ -533,6 +740,103 @@ fn fetch_thread_by_comment_id_graphql(comment_id: &str) -> Result<ReviewThread>
         .ok_or_else(|| anyhow::anyhow!("Comment {} not found in any thread", comment_id))
 }
 
+/// GraphQL query for fetching in-progress reviews (loaded from graphql/operation/).
+const FETCH_PENDING_REVIEWS_QUERY: &str =
+    include_str!("../graphql/operation/fetch_pending_reviews.graphql");
+
+#[derive(Deserialize)]
+struct PendingReviewsResponse {
+    data: Option<PendingReviewsData>,
+    errors: Option<Vec<GraphQLError>>,
+}
+
+#[derive(Deserialize)]
+struct PendingReviewsData {
+    repository: Option<PendingReviewsRepository>,
+}
+
+#[derive(Deserialize)]
+struct PendingReviewsRepository {
+    #[serde(rename = "pullRequest")]
+    pull_request: Option<PendingReviewsPullRequest>,
+}
+
+#[derive(Deserialize)]
+struct PendingReviewsPullRequest {
+    reviews: PendingReviewsConnection,
+}
+
+#[derive(Deserialize)]
+struct PendingReviewsConnection {
+    nodes: Vec<PendingReviewNode>,
+}
+
+#[derive(Deserialize)]
+struct PendingReviewNode {
+    author: Option<AuthorNode>,
+    comments: TotalCount,
+}
+
+#[derive(Deserialize)]
+struct TotalCount {
+    #[serde(rename = "totalCount")]
+    total_count: u64,
+}
+
+/// Fetch pending (unsubmitted) reviews on a PR.
+fn fetch_pending_reviews_graphql(
+    owner: &str,
+    repo: &str,
+    pr_number: u64,
+) -> Result<Vec<PendingReview>> {
+    let output = gh_graphql(FETCH_PENDING_REVIEWS_QUERY, json!({ "owner": owner, "repo": repo, "pr": pr_number }))
+        .context("Failed to run 'gh api graphql'")?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        anyhow::bail!("GraphQL query failed: {}", stderr.trim());
+    }
+
+    let response: PendingReviewsResponse = serde_json::from_slice(&output.stdout)
+        .context("Failed to parse GraphQL response")?;
+
+    if let Some(errors) = response.errors {
+        let messages: Vec<_> = errors.iter().map(|e| e.message.as_str()).collect();
+        anyhow::bail!("GraphQL errors: {}", messages.join(", "));
+    }
+
+    let reviews = response
+        .data
+        .and_then(|d| d.repository)
+        .and_then(|r| r.pull_request)
+        .map(|pr| pr.reviews.nodes)
+        .ok_or_else(|| anyhow::anyhow!("No reviews data in response"))?;
+
+    Ok(reviews
+        .into_iter()
+        .map(|r| PendingReview {
+            author: r.author.map(|a| a.login).unwrap_or_else(|| "ghost".to_string()),
+            comment_count: r.comments.total_count,
+        })
+        .collect())
+}
+
+/// Describe in-progress reviews, e.g. "@alice (3 draft comments)".
+pub fn describe_pending_reviews(reviews: &[PendingReview]) -> String {
+    reviews
+        .iter()
+        .map(|r| {
+            format!(
+                "@{} ({} draft comment{})",
+                r.author,
+                r.comment_count,
+                if r.comment_count == 1 { "" } else { "s" }
+            )
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -562,24 +866,15 @@ mod tests {
     }
 
     fn make_comment(author: &str, body: &str) -> ThreadComment {
-        ThreadComment {
-            id: format!("comment_{}", body.len()),
-            author: author.to_string(),
-            body: body.to_string(),
-            diff_hunk: None,
-            url: None,
-            created_at: None,
-        }
+        ThreadComment::for_test(&format!("comment_{}", body.len()), author, body)
     }
 
     fn make_thread(id: &str, resolved: bool, comments: Vec<ThreadComment>) -> ReviewThread {
         ReviewThread {
-            id: id.to_string(),
             is_resolved: resolved,
-            is_outdated: false,
             path: Some("src/main.rs".to_string()),
             line: Some(42),
-            comments,
+            ..ReviewThread::for_test(id, comments)
         }
     }
 
@@ -599,16 +894,18 @@ mod tests {
         assert_eq!(last.body, "Second comment");
     }
 
+    #[test]
+    fn comment_reference_includes_permalink() {
+        assert_eq!(comment_reference("C1", None), "comment `C1`");
+        assert_eq!(
+            comment_reference("C1", Some("https://github.com/o/r/pull/5#discussion_r1")),
+            "comment `C1`, https://github.com/o/r/pull/5#discussion_r1"
+        );
+    }
+
     #[test]
     fn thread_last_comment_empty() {
-        let thread = ReviewThread {
-            id: "T1".to_string(),
-            is_resolved: false,
-            is_outdated: false,
-            path: None,
-            line: None,
-            comments: vec![],
-        };
+        let thread = ReviewThread::for_test("T1", vec![]);
 
         assert!(thread.last_comment().is_none());
     }
@@ -668,14 +965,7 @@ mod tests {
 
     #[test]
     fn thread_needs_response_empty() {
-        let thread = ReviewThread {
-            id: "T1".to_string(),
-            is_resolved: false,
-            is_outdated: false,
-            path: None,
-            line: None,
-            comments: vec![],
-        };
+        let thread = ReviewThread::for_test("T1", vec![]);
         assert!(!thread.needs_response());
     }
 
@@ -698,10 +988,31 @@ mod tests {
         assert_eq!(actionable[1].thread.id, "T4");
     }
 
+    #[test]
+    fn find_awaiting_input_threads_needs_open_question() {
+        let threads = vec![
+            make_thread("T1", false, vec![make_comment("bot", "🤖 From Claude: ❓ Which API?")]),
+            make_thread("T2", true, vec![make_comment("bot", "🤖 From Claude: ❓ Resolved")]),
+            make_thread("T3", false, vec![make_comment("bot", "🤖 From Claude: ✅ Done")]),
+            make_thread(
+                "T4",
+                false,
+                vec![
+                    make_comment("bot", "🤖 From Claude: ❓ Which API?"),
+                    make_comment("reviewer", "The new one"),
+                ],
+            ),
+        ];
+
+        let awaiting = find_awaiting_input_threads(&threads);
+        assert_eq!(awaiting.len(), 1);
+        assert_eq!(awaiting[0].id, "T1");
+    }
+
     #[test]
     fn actionable_thread_location() {
         let thread = make_thread("T1", false, vec![make_comment("a", "b")]);
-        let actionable = ActionableThread { thread };
+        let actionable = ActionableThread { thread, tag: None };
         assert_eq!(actionable.location(), "src/main.rs:42");
     }
 
@@ -709,10 +1020,31 @@ mod tests {
     fn actionable_thread_location_no_line() {
         let mut thread = make_thread("T1", false, vec![make_comment("a", "b")]);
         thread.line = None;
-        let actionable = ActionableThread { thread };
+        let actionable = ActionableThread { thread, tag: None };
         assert_eq!(actionable.location(), "src/main.rs");
     }
 
+    #[test]
+    fn thread_location_variants() {
+        let mut thread = make_thread("T1", false, vec![make_comment("a", "b")]);
+        thread.start_line = Some(40);
+        assert_eq!(thread.location(), "src/main.rs:40-42");
+
+        // Comments on deleted lines lose `line` but keep where they were made
+        thread.line = None;
+        thread.original_line = Some(38);
+        assert_eq!(thread.location(), "src/main.rs:38 (outdated)");
+
+        thread.on_removed_code = true;
+        assert_eq!(thread.location(), "src/main.rs:38 (comment on removed code)");
+
+        thread.is_file_level = true;
+        assert_eq!(thread.location(), "src/main.rs (file)");
+
+        thread.path = None;
+        assert_eq!(thread.location(), "the PR as a whole");
+    }
+
     #[test]
     fn is_pure_claude_all_claude_comments() {
         let thread = make_thread(
@@ -765,12 +1097,8 @@ mod tests {
     #[test]
     fn is_pure_claude_empty_thread() {
         let thread = ReviewThread {
-            id: "T1".to_string(),
             is_resolved: true,
-            is_outdated: false,
-            path: None,
-            line: None,
-            comments: vec![],
+            ..ReviewThread::for_test("T1", vec![])
         };
         assert!(!thread.is_pure_claude());
     }
@@ -789,27 +1117,16 @@ mod tests {
         assert_eq!(ids.len(), 2);
     }
 
-    fn make_comment_with_id(id: &str, author: &str, body: &str) -> ThreadComment {
-        ThreadComment {
-            id: id.to_string(),
-            author: author.to_string(),
-            body: body.to_string(),
-            diff_hunk: None,
-            url: None,
-            created_at: None,
-        }
-    }
-
     #[test]
     fn human_comments_after_returns_human_comments() {
         let thread = make_thread(
             "T1",
             false,
             vec![
-                make_comment_with_id("C1", "reviewer", "Please fix this"),
-                make_comment_with_id("C2", "claude-bot", "🤖 From Claude: Fixed!"),
-                make_comment_with_id("C3", "reviewer", "Actually, one more thing"),
-                make_comment_with_id("C4", "reviewer", "And another thing"),
+                ThreadComment::for_test("C1", "reviewer", "Please fix this"),
+                ThreadComment::for_test("C2", "claude-bot", "🤖 From Claude: Fixed!"),
+                ThreadComment::for_test("C3", "reviewer", "Actually, one more thing"),
+                ThreadComment::for_test("C4", "reviewer", "And another thing"),
             ],
         );
 
@@ -827,9 +1144,9 @@ mod tests {
             "T1",
             false,
             vec![
-                make_comment_with_id("C1", "reviewer", "Please fix this"),
-                make_comment_with_id("C2", "claude-bot", "🤖 From Claude: Fixed!"),
-                make_comment_with_id("C3", "reviewer", "Actually, one more thing"),
+                ThreadComment::for_test("C1", "reviewer", "Please fix this"),
+                ThreadComment::for_test("C2", "claude-bot", "🤖 From Claude: Fixed!"),
+                ThreadComment::for_test("C3", "reviewer", "Actually, one more thing"),
             ],
         );
 
@@ -845,8 +1162,8 @@ mod tests {
             "T1",
             false,
             vec![
-                make_comment_with_id("C1", "reviewer", "Please fix this"),
-                make_comment_with_id("C2", "claude-bot", "🤖 From Claude: Fixed!"),
+                ThreadComment::for_test("C1", "reviewer", "Please fix this"),
+                ThreadComment::for_test("C2", "claude-bot", "🤖 From Claude: Fixed!"),
             ],
         );
 
@@ -860,7 +1177,7 @@ mod tests {
         let thread = make_thread(
             "T1",
             false,
-            vec![make_comment_with_id("C1", "reviewer", "Please fix this")],
+            vec![ThreadComment::for_test("C1", "reviewer", "Please fix this")],
         );
 
         // Unknown comment should return None
@@ -873,9 +1190,9 @@ mod tests {
             "T1",
             false,
             vec![
-                make_comment_with_id("C1", "reviewer", "First"),
-                make_comment_with_id("C2", "claude-bot", "🤖 From Claude: Response"),
-                make_comment_with_id("C3", "other-claude", "🤖 From Claude: Another response"),
+                ThreadComment::for_test("C1", "reviewer", "First"),
+                ThreadComment::for_test("C2", "claude-bot", "🤖 From Claude: Response"),
+                ThreadComment::for_test("C3", "other-claude", "🤖 From Claude: Another response"),
             ],
         );
 
@@ -933,14 +1250,7 @@ mod tests {
 
     #[test]
     fn has_paperclip_empty_thread() {
-        let thread = ReviewThread {
-            id: "T1".to_string(),
-            is_resolved: false,
-            is_outdated: false,
-            path: None,
-            line: None,
-            comments: vec![],
-        };
+        let thread = ReviewThread::for_test("T1", vec![]);
         assert!(!thread.has_paperclip());
     }
 
@@ -977,4 +1287,22 @@ mod tests {
         let actionable = find_actionable_threads(threads);
         assert!(actionable.is_empty());
     }
+
+    #[test]
+    fn describe_pending_reviews_lists_authors() {
+        let reviews = vec![
+            PendingReview {
+                author: "alice".to_string(),
+                comment_count: 1,
+            },
+            PendingReview {
+                author: "bob".to_string(),
+                comment_count: 3,
+            },
+        ];
+        assert_eq!(
+            describe_pending_reviews(&reviews),
+            "@alice (1 draft comment), @bob (3 draft comments)"
+        );
+    }
 }
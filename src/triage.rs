@@ -0,0 +1,352 @@
+// Heuristic urgency tagging for actionable review threads.
+// Classifies each thread as blocking, question, nit, or plain comment from
+// keywords in the unanswered comments and whether the commenter owns the file
+// per CODEOWNERS, so blocking feedback can be addressed first.
+
+use crate::threads::ActionableThread;
+use crate::request_log::TracedOutput;
+use anyhow::{Context, Result};
+use glob::{MatchOptions, Pattern};
+use std::path::Path;
+use std::process::Command;
+
+/// How urgent a thread looks. Ordered from most to least urgent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ThreadTag {
+    Blocking,
+    Question,
+    Comment,
+    Nit,
+}
+
+impl ThreadTag {
+    pub fn label(&self) -> &'static str {
+        match self {
+            ThreadTag::Blocking => "blocking",
+            ThreadTag::Question => "question",
+            ThreadTag::Comment => "comment",
+            ThreadTag::Nit => "nit",
+        }
+    }
+}
+
+/// Order in which actionable threads are listed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum ThreadOrder {
+    /// The order GitHub returns them (oldest first).
+    #[default]
+    Posted,
+    /// Blocking first, then questions, comments, and nits.
+    Urgency,
+}
+
+const NIT_KEYWORDS: &[&str] = &[
+    "nit",
+    "nitpick",
+    "non-blocking",
+    "nonblocking",
+    "optional",
+    "minor",
+    "style",
+    "typo",
+    "take it or leave it",
+    "feel free to ignore",
+];
+
+const BLOCKING_KEYWORDS: &[&str] = &[
+    "blocking",
+    "blocker",
+    "must",
+    "needs to",
+    "need to",
+    "required",
+    "bug",
+    "broken",
+    "breaks",
+    "incorrect",
+    "wrong",
+    "security",
+    "vulnerab*",
+    "data loss",
+    "race condition",
+    "panic",
+    "crash",
+    "please fix",
+    "don't merge",
+    "do not merge",
+];
+
+const QUESTION_OPENERS: &[&str] = &[
+    "why ", "what ", "how ", "is ", "are ", "does ", "do ", "can ", "could ", "should ",
+];
+
+/// Tag one thread. Only the human comments since Claude's last reply count,
+/// since those are what still needs a response.
+pub fn tag_thread(actionable: &ActionableThread, owners: &CodeOwners) -> ThreadTag {
+    let pending = actionable.thread.unanswered_comments();
+    let text = pending
+        .iter()
+        .map(|c| c.body.to_lowercase())
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let tag = classify_text(&text);
+    // Feedback from a code owner gates the merge unless they said otherwise.
+    let from_owner = actionable
+        .thread
+        .path
+        .as_deref()
+        .is_some_and(|path| pending.iter().any(|c| owners.is_owner(path, &c.author)));
+    if from_owner && tag == ThreadTag::Comment {
+        ThreadTag::Blocking
+    } else {
+        tag
+    }
+}
+
+/// Classify lowercased comment text by keyword. Explicit nit markers win over
+/// blocking words ("nit: this name is wrong" is still a nit).
+fn classify_text(text: &str) -> ThreadTag {
+    let words = words(text);
+    if NIT_KEYWORDS.iter().any(|k| contains_phrase(&words, k)) {
+        return ThreadTag::Nit;
+    }
+    if BLOCKING_KEYWORDS.iter().any(|k| contains_phrase(&words, k)) {
+        return ThreadTag::Blocking;
+    }
+    let is_question = text.lines().any(|line| {
+        let line = line.trim();
+        line.ends_with('?') || QUESTION_OPENERS.iter().any(|q| line.starts_with(q))
+    });
+    if is_question {
+        ThreadTag::Question
+    } else {
+        ThreadTag::Comment
+    }
+}
+
+/// Split text into words, keeping apostrophes and hyphens inside words.
+fn words(text: &str) -> Vec<&str> {
+    text.split(|c: char| !(c.is_alphanumeric() || c == '\'' || c == '-'))
+        .filter(|w| !w.is_empty())
+        .collect()
+}
+
+/// True if `phrase` occurs as a sequence of whole words. A word ending in
+/// `*` (e.g. "vulnerab*") matches any word with that prefix.
+fn contains_phrase(words: &[&str], phrase: &str) -> bool {
+    let needle: Vec<&str> = phrase.split(' ').collect();
+    words.windows(needle.len()).any(|window| {
+        window.iter().zip(&needle).all(|(w, n)| match n.strip_suffix('*') {
+            Some(stem) => w.starts_with(stem),
+            None => w == n,
+        })
+    })
+}
+
+/// Tag every thread in place.
+pub fn tag_threads(threads: &mut [ActionableThread], owners: &CodeOwners) {
+    for t in threads {
+        t.tag = Some(tag_thread(t, owners));
+    }
+}
+
+/// Stable-sort threads most urgent first. Untagged threads sort as comments.
+pub fn sort_by_urgency(threads: &mut [ActionableThread]) {
+    threads.sort_by_key(|t| t.tag.unwrap_or(ThreadTag::Comment));
+}
+
+/// Parsed CODEOWNERS rules. Later rules take precedence, as on GitHub.
+#[derive(Debug, Default)]
+pub struct CodeOwners {
+    rules: Vec<(Vec<Pattern>, Vec<String>)>,
+}
+
+/// Locations GitHub checks for a CODEOWNERS file, in priority order.
+const CODEOWNERS_PATHS: &[&str] = &[".github/CODEOWNERS", "CODEOWNERS", "docs/CODEOWNERS"];
+
+impl CodeOwners {
+    /// Load CODEOWNERS from a checkout, or None if it doesn't have one.
+    pub fn load(root: &Path) -> Result<Option<Self>> {
+        for rel in CODEOWNERS_PATHS {
+            let path = root.join(rel);
+            if path.exists() {
+                let content = std::fs::read_to_string(&path)
+                    .with_context(|| format!("Failed to read {}", path.display()))?;
+                return Ok(Some(Self::parse(&content)));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Fetch CODEOWNERS from the repository's default branch on GitHub, for
+    /// when there's no local checkout. Returns None if the repo has none.
+    pub fn fetch(owner: &str, repo: &str) -> Result<Option<Self>> {
+        for rel in CODEOWNERS_PATHS {
+            let output = Command::new("gh")
+                .args([
+                    "api",
+                    "-H",
+                    "Accept: application/vnd.github.raw",
+                    &format!("repos/{}/{}/contents/{}", owner, repo, rel),
+                ])
+                .traced_output()
+                .context("Failed to run 'gh api' for CODEOWNERS")?;
+            if output.status.success() {
+                return Ok(Some(Self::parse(&String::from_utf8_lossy(&output.stdout))));
+            }
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            if !stderr.contains("404") {
+                anyhow::bail!("Failed to fetch {}: {}", rel, stderr.trim());
+            }
+        }
+        Ok(None)
+    }
+
+    /// Parse CODEOWNERS content. Lines with invalid patterns are skipped.
+    pub fn parse(content: &str) -> Self {
+        let rules = content
+            .lines()
+            .map(|line| line.split('#').next().unwrap_or("").trim())
+            .filter(|line| !line.is_empty())
+            .filter_map(|line| {
+                let mut fields = line.split_whitespace();
+                let patterns = owner_patterns(fields.next()?)?;
+                let owners = fields
+                    .map(|o| o.trim_start_matches('@').to_lowercase())
+                    .collect();
+                Some((patterns, owners))
+            })
+            .collect();
+        Self { rules }
+    }
+
+    /// Owners of `path` (logins and `org/team` names, without `@`).
+    pub fn owners_of(&self, path: &str) -> &[String] {
+        let options = MatchOptions {
+            require_literal_separator: true,
+            ..Default::default()
+        };
+        self.rules
+            .iter()
+            .rev()
+            .find(|(patterns, _)| patterns.iter().any(|p| p.matches_with(path, options)))
+            .map_or(&[], |(_, owners)| owners.as_slice())
+    }
+
+    /// Returns true if `login` is listed directly as an owner of `path`.
+    /// Team ownership isn't resolved (that needs an API call per team).
+    pub fn is_owner(&self, path: &str, login: &str) -> bool {
+        self.owners_of(path)
+            .iter()
+            .any(|o| o.eq_ignore_ascii_case(login))
+    }
+}
+
+/// Translate a gitignore-style CODEOWNERS pattern into globs matching file paths.
+fn owner_patterns(pattern: &str) -> Option<Vec<Pattern>> {
+    // A slash at the start or in the middle anchors the pattern to the root
+    let anchored = pattern.trim_end_matches('/').contains('/');
+    let mut base = pattern.trim_start_matches('/').to_string();
+    if base.ends_with('/') {
+        base.push_str("**");
+    }
+    if !anchored && base != "*" {
+        base = format!("**/{}", base);
+    }
+    let mut globs = vec![base.clone()];
+    // A pattern naming a directory also owns everything beneath it
+    if !base.ends_with("**") {
+        globs.push(format!("{}/**", base));
+    }
+    globs.iter().map(|g| Pattern::new(g).ok()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::threads::{ReviewThread, ThreadComment, CLAUDE_MARKER};
+
+    fn comment(author: &str, body: &str) -> ThreadComment {
+        ThreadComment::for_test(&format!("c-{}", body.len()), author, body)
+    }
+
+    fn thread(id: &str, path: &str, comments: Vec<ThreadComment>) -> ActionableThread {
+        ActionableThread {
+            thread: ReviewThread {
+                path: Some(path.to_string()),
+                ..ReviewThread::for_test(id, comments)
+            },
+            tag: None,
+        }
+    }
+
+    #[test]
+    fn classifies_by_keywords() {
+        assert_eq!(classify_text("nit: trailing whitespace"), ThreadTag::Nit);
+        assert_eq!(classify_text("nit: this name is wrong"), ThreadTag::Nit);
+        assert_eq!(classify_text("this is a security hole"), ThreadTag::Blocking);
+        assert_eq!(classify_text("potential vulnerability here"), ThreadTag::Blocking);
+        assert_eq!(classify_text("why not use a hashmap?"), ThreadTag::Question);
+        assert_eq!(classify_text("consider a hashmap here"), ThreadTag::Comment);
+        // Whole words only
+        assert_eq!(classify_text("the mustache template"), ThreadTag::Comment);
+        assert_eq!(classify_text("debugging output"), ThreadTag::Comment);
+    }
+
+    #[test]
+    fn only_comments_after_last_claude_reply_count() {
+        let t = thread(
+            "T1",
+            "src/lib.rs",
+            vec![
+                comment("alice", "This is broken"),
+                comment("claude", &format!("{} Fixed", CLAUDE_MARKER)),
+                comment("alice", "Thanks, why the extra clone though?"),
+            ],
+        );
+        assert_eq!(tag_thread(&t, &CodeOwners::default()), ThreadTag::Question);
+    }
+
+    #[test]
+    fn code_owner_comments_are_blocking() {
+        let owners = CodeOwners::parse("* @someone\n/src/ @Alice @org/core # comment\n");
+        let t = thread("T1", "src/lib.rs", vec![comment("alice", "consider renaming")]);
+        assert_eq!(tag_thread(&t, &owners), ThreadTag::Blocking);
+        // Owners' nits stay nits
+        let t = thread("T2", "src/lib.rs", vec![comment("alice", "nit: rename")]);
+        assert_eq!(tag_thread(&t, &owners), ThreadTag::Nit);
+        let t = thread("T3", "README.md", vec![comment("alice", "consider renaming")]);
+        assert_eq!(tag_thread(&t, &owners), ThreadTag::Comment);
+    }
+
+    #[test]
+    fn codeowners_patterns_follow_gitignore_rules() {
+        let owners = CodeOwners::parse(
+            "*.js @js\n/docs/*.md @docs\napps/ @apps\nlib @lib\n/build/logs/ @logs\n",
+        );
+        assert_eq!(owners.owners_of("web/app.js"), ["js"]);
+        assert_eq!(owners.owners_of("docs/intro.md"), ["docs"]);
+        assert!(owners.owners_of("docs/deep/intro.md").is_empty());
+        assert_eq!(owners.owners_of("x/apps/main.rs"), ["apps"]);
+        assert_eq!(owners.owners_of("a/lib/b/c.rs"), ["lib"]);
+        assert_eq!(owners.owners_of("build/logs/x/y.log"), ["logs"]);
+        // Later rules win
+        assert_eq!(owners.owners_of("apps/app.js"), ["apps"]);
+        assert!(owners.is_owner("apps/x", "APPS"));
+    }
+
+    #[test]
+    fn sort_puts_blocking_first() {
+        let mut threads = vec![
+            thread("nit", "a", vec![comment("a", "nit: spacing")]),
+            thread("q", "a", vec![comment("a", "why?")]),
+            thread("block", "a", vec![comment("a", "this must not panic")]),
+            thread("plain", "a", vec![comment("a", "ok")]),
+        ];
+        tag_threads(&mut threads, &CodeOwners::default());
+        sort_by_urgency(&mut threads);
+        let ids: Vec<_> = threads.iter().map(|t| t.thread.id.as_str()).collect();
+        assert_eq!(ids, ["block", "q", "plain", "nit"]);
+    }
+}
@@ -1,31 +1,83 @@
 // Wait-until-actionable polling logic.
 // Blocks until PR state changes to something requiring action.
 
-use crate::checks::{CheckStatus, ChecksClient, ChecksSummary};
+use crate::checks::{CheckFilters, CheckStatus, ChecksClient, ChecksSummary};
+use crate::concurrent;
 use crate::git::GitClient;
-use crate::threads::{ThreadsClient, CLAUDE_MARKER};
+use crate::hours;
+use crate::github::MergeableStatus;
+use crate::listen;
+use crate::rate_limit;
+use crate::threads::{find_awaiting_input_threads, PendingReview, ReviewThread, ThreadsClient};
 use anyhow::Result;
+use serde::Serialize;
 use std::collections::HashSet;
+use std::path::{Path, PathBuf};
 use std::thread;
 use std::time::{Duration, Instant, SystemTime};
 
 /// Snapshot of PR state for comparison.
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, Default)]
 pub struct PrSnapshot {
     /// IDs of threads that need a response (unresolved, last comment not from Claude)
     pub actionable_thread_ids: HashSet<String>,
     /// IDs of all unresolved threads (regardless of who commented last)
     pub unresolved_thread_ids: HashSet<String>,
+    /// IDs of unresolved threads where Claude's last reply is a question
+    pub awaiting_input_thread_ids: HashSet<String>,
     /// Names of failed CI checks
     pub failed_check_names: HashSet<String>,
     /// Names of pending CI checks
     pub pending_check_names: HashSet<String>,
+    /// IDs of every review comment, used to notice new comments in existing threads
+    pub comment_ids: HashSet<String>,
+    /// Authors of reviews that are started but not yet submitted
+    pub pending_review_authors: HashSet<String>,
+    /// The filtered checks the sets above were derived from
+    pub checks: ChecksSummary,
+    /// Every review thread, including paperclip threads the sets above skip
+    pub threads: Vec<ReviewThread>,
+    /// Reviews that are started but not yet submitted
+    pub pending_reviews: Vec<PendingReview>,
+    /// Whether the PR merges cleanly into its base branch
+    pub mergeable_status: MergeableStatus,
 }
 
+/// Snapshots are compared by the state derived from them; the raw checks and
+/// threads carry details (timestamps, URLs) that change without any activity.
+impl PartialEq for PrSnapshot {
+    fn eq(&self, other: &Self) -> bool {
+        self.actionable_thread_ids == other.actionable_thread_ids
+            && self.unresolved_thread_ids == other.unresolved_thread_ids
+            && self.awaiting_input_thread_ids == other.awaiting_input_thread_ids
+            && self.failed_check_names == other.failed_check_names
+            && self.pending_check_names == other.pending_check_names
+            && self.comment_ids == other.comment_ids
+            && self.pending_review_authors == other.pending_review_authors
+            && self.mergeable_status == other.mergeable_status
+    }
+}
+
+impl Eq for PrSnapshot {}
+
 impl PrSnapshot {
-    /// Returns true if the PR is currently actionable (needs work).
+    /// Returns true if a reviewer appears to be mid-review (pending review).
+    pub fn review_in_progress(&self) -> bool {
+        !self.pending_review_authors.is_empty()
+    }
+
+    /// Returns true if the PR is currently actionable (needs work). Comments
+    /// don't count while a review is still in progress, so the whole review
+    /// is handled once submitted; CI failures and merge conflicts always do.
     pub fn is_actionable(&self) -> bool {
-        !self.actionable_thread_ids.is_empty() || !self.failed_check_names.is_empty()
+        (!self.actionable_thread_ids.is_empty() && !self.review_in_progress())
+            || !self.failed_check_names.is_empty()
+            || self.is_conflicting()
+    }
+
+    /// Returns true if the PR has merge conflicts with its base branch.
+    pub fn is_conflicting(&self) -> bool {
+        self.mergeable_status == MergeableStatus::Conflicting
     }
 
     /// Returns true if CI is "happy" - all checks passed, none pending or failed.
@@ -33,9 +85,27 @@ impl PrSnapshot {
         self.failed_check_names.is_empty() && self.pending_check_names.is_empty()
     }
 
-    /// Returns true if the PR is "happy" - CI passing and no actionable comments.
+    /// Returns true if the PR is "happy" - CI passing, no actionable comments,
+    /// no questions awaiting a reviewer's answer, no review in progress, and
+    /// no merge conflicts.
     pub fn is_happy(&self) -> bool {
-        self.is_ci_happy() && self.actionable_thread_ids.is_empty()
+        self.is_ci_happy()
+            && !self.is_conflicting()
+            && self.actionable_thread_ids.is_empty()
+            && self.awaiting_input_thread_ids.is_empty()
+            && !self.review_in_progress()
+    }
+
+    /// Like `is_happy`, but also requires every thread to be resolved — the
+    /// same bar `ready` checks. Threads Claude replied to last still count
+    /// until the reviewer resolves them.
+    pub fn is_strictly_happy(&self) -> bool {
+        self.is_happy() && self.unresolved_thread_ids.is_empty()
+    }
+
+    /// Unresolved threads that are only waiting on the reviewer to resolve.
+    pub fn awaiting_resolution(&self) -> usize {
+        self.unresolved_thread_ids.difference(&self.actionable_thread_ids).count()
     }
 }
 
@@ -46,13 +116,18 @@ pub fn capture_snapshot(
     owner: &str,
     repo: &str,
     pr_number: u64,
-    include_patterns: &[String],
-    exclude_patterns: &[String],
+    filters: &CheckFilters,
 ) -> Result<PrSnapshot> {
-    // Fetch checks
-    let checks = checks_client.fetch_checks(owner, repo, pr_number).unwrap_or_default();
-    let filtered = crate::checks::filter_checks(checks, include_patterns, exclude_patterns)?;
-    let checks_summary = ChecksSummary { checks: filtered };
+    // Checks, threads, pending reviews, and mergeability don't depend on each other
+    let (checks, all_threads, pending_reviews, mergeable_status) = thread::scope(|s| {
+        let threads = s.spawn(|| threads_client.fetch_threads(owner, repo, pr_number));
+        let pending = s.spawn(|| threads_client.fetch_pending_reviews(owner, repo, pr_number));
+        let mergeable = s.spawn(|| checks_client.fetch_mergeable_status(owner, repo, pr_number));
+        let checks = checks_client.fetch_checks(owner, repo, pr_number);
+        (checks, concurrent::join(threads), concurrent::join(pending), concurrent::join(mergeable))
+    });
+    let checks = checks.unwrap_or_default();
+    let checks_summary = ChecksSummary::new(filters.apply(checks)?);
 
     let failed_check_names: HashSet<String> = checks_summary
         .checks
@@ -68,13 +143,9 @@ pub fn capture_snapshot(
         .map(|c| c.name.clone())
         .collect();
 
-    // Fetch threads, excluding paperclip threads (preserved for human review)
-    let threads: Vec<_> = threads_client
-        .fetch_threads(owner, repo, pr_number)
-        .unwrap_or_default()
-        .into_iter()
-        .filter(|t| !t.has_paperclip())
-        .collect();
+    // Exclude paperclip threads (preserved for human review)
+    let all_threads = all_threads.unwrap_or_default();
+    let threads: Vec<_> = all_threads.iter().filter(|t| !t.has_paperclip()).collect();
 
     // All unresolved threads (regardless of who commented last)
     let unresolved_thread_ids: HashSet<String> = threads
@@ -83,38 +154,235 @@ pub fn capture_snapshot(
         .map(|t| t.id.clone())
         .collect();
 
+    // The analysis's own list, so the two agree on which questions count
+    let awaiting_input_thread_ids: HashSet<String> = find_awaiting_input_threads(&all_threads)
+        .into_iter()
+        .map(|t| t.id)
+        .collect();
+
+    let pending_reviews = pending_reviews.unwrap_or_default();
+    let pending_review_authors: HashSet<String> =
+        pending_reviews.iter().map(|r| r.author.clone()).collect();
+
+    let comment_ids: HashSet<String> = threads
+        .iter()
+        .flat_map(|t| t.comments.iter().map(|c| c.id.clone()))
+        .collect();
+
     // Actionable threads (unresolved AND last comment not from Claude)
     let actionable_thread_ids: HashSet<String> = threads
-        .into_iter()
+        .iter()
         .filter(|t| {
             if t.is_resolved {
                 return false;
             }
             match t.comments.last() {
-                Some(comment) => !comment.body.starts_with(CLAUDE_MARKER),
+                Some(comment) => !comment.is_from_claude(),
                 None => false,
             }
         })
-        .map(|t| t.id)
+        .map(|t| t.id.clone())
         .collect();
 
     Ok(PrSnapshot {
         actionable_thread_ids,
         unresolved_thread_ids,
+        awaiting_input_thread_ids,
         failed_check_names,
         pending_check_names,
+        comment_ids,
+        pending_review_authors,
+        checks: checks_summary,
+        threads: all_threads,
+        pending_reviews,
+        mergeable_status: mergeable_status.unwrap_or(MergeableStatus::Unknown),
     })
 }
 
 /// Result of waiting.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum WaitResult {
-    /// PR became actionable (has work to do)
-    Actionable,
+    /// PR became actionable (has work to do). Carries the snapshot that ended
+    /// the wait, so the analysis can use exactly that state without refetching.
+    Actionable {
+        reason: WaitReason,
+        snapshot: Box<PrSnapshot>,
+    },
     /// PR is "happy" (CI passing, no comments needing response)
     Happy,
+    /// No new comments or check transitions for the requested quiet period;
+    /// carries the latest snapshot
+    Quiet(Box<PrSnapshot>),
     /// Timeout reached
     Timeout,
+    /// The cancel file appeared or changed
+    Cancelled,
+}
+
+/// Exit code of a wait that reached `--timeout`.
+pub const TIMEOUT_EXIT_CODE: i32 = 2;
+
+/// Exit code of a wait ended through `--cancel-file`.
+pub const CANCELLED_EXIT_CODE: i32 = 3;
+
+impl WaitResult {
+    fn actionable(first: &PrSnapshot, last: PrSnapshot) -> Self {
+        WaitResult::Actionable {
+            reason: WaitReason::between(first, &last),
+            snapshot: Box::new(last),
+        }
+    }
+}
+
+/// A file an orchestrator can create (or rewrite) to end a wait early,
+/// without killing pr-loop mid-update.
+#[derive(Debug)]
+pub struct CancelFile {
+    path: PathBuf,
+    /// Contents when the wait began; None if the file didn't exist.
+    initial: Option<Vec<u8>>,
+}
+
+/// How often a sleeping wait looks at the cancel file.
+const CANCEL_CHECK_INTERVAL: Duration = Duration::from_millis(250);
+
+impl CancelFile {
+    pub fn new(path: PathBuf) -> Self {
+        let initial = std::fs::read(&path).ok();
+        Self { path, initial }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// True once the file exists with different contents than when the wait
+    /// began (including appearing for the first time).
+    pub fn is_cancelled(&self) -> bool {
+        match std::fs::read(&self.path) {
+            Ok(contents) => self.initial.as_ref() != Some(&contents),
+            Err(_) => false,
+        }
+    }
+
+    /// The file's contents, trimmed, if it says anything.
+    pub fn reason(&self) -> Option<String> {
+        let contents = std::fs::read_to_string(&self.path).ok()?;
+        let trimmed = contents.trim();
+        (!trimmed.is_empty()).then(|| trimmed.to_string())
+    }
+}
+
+fn cancelled(cancel: Option<&CancelFile>) -> bool {
+    cancel.is_some_and(CancelFile::is_cancelled)
+}
+
+/// Sleep for `duration`, waking early if the wait is cancelled. Returns true
+/// if it was.
+pub fn sleep_unless_cancelled(duration: Duration, cancel: Option<&CancelFile>) -> bool {
+    let Some(cancel) = cancel else {
+        thread::sleep(duration);
+        return false;
+    };
+    let deadline = Instant::now() + duration;
+    loop {
+        if cancel.is_cancelled() {
+            return true;
+        }
+        let now = Instant::now();
+        if now >= deadline {
+            return false;
+        }
+        thread::sleep(CANCEL_CHECK_INTERVAL.min(deadline - now));
+    }
+}
+
+/// Sleep until the next poll is due, waking early if the wait is cancelled
+/// or, with `--listen`, a webhook delivery says the PR may have changed.
+/// Returns true if the wait was cancelled.
+pub fn sleep_until_next_poll(duration: Duration, cancel: Option<&CancelFile>) -> bool {
+    // Poll less often outside working hours, and when GitHub's rate limit
+    // is running low
+    let deadline = Instant::now() + rate_limit::paced(hours::paced(duration));
+    loop {
+        if cancelled(cancel) {
+            return true;
+        }
+        let now = Instant::now();
+        if now >= deadline {
+            return false;
+        }
+        if listen::wait_for_event(CANCEL_CHECK_INTERVAL.min(deadline - now)) {
+            thread::sleep(listen::EVENT_SETTLE);
+            listen::wait_for_event(Duration::ZERO);
+            return cancelled(cancel);
+        }
+    }
+}
+
+/// What made a wait end actionable, so the caller can act on it without
+/// running a second analysis.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct WaitReason {
+    /// Threads needing a response, sorted.
+    pub actionable_thread_ids: Vec<String>,
+    /// The subset of `actionable_thread_ids` that weren't actionable when the wait began.
+    pub new_thread_ids: Vec<String>,
+    /// Failing checks, sorted.
+    pub failed_checks: Vec<String>,
+    /// The subset of `failed_checks` that weren't failing when the wait began.
+    pub newly_failed_checks: Vec<String>,
+    /// Whether the PR has merge conflicts with its base branch.
+    pub conflicting: bool,
+}
+
+impl WaitReason {
+    /// Describe `last` relative to `first`, the snapshot taken when the wait began.
+    pub fn between(first: &PrSnapshot, last: &PrSnapshot) -> Self {
+        let sorted = |ids: &HashSet<String>, exclude: &HashSet<String>| {
+            let mut v: Vec<String> = ids.difference(exclude).cloned().collect();
+            v.sort();
+            v
+        };
+        let none = HashSet::new();
+        Self {
+            actionable_thread_ids: sorted(&last.actionable_thread_ids, &none),
+            new_thread_ids: sorted(&last.actionable_thread_ids, &first.actionable_thread_ids),
+            failed_checks: sorted(&last.failed_check_names, &none),
+            newly_failed_checks: sorted(&last.failed_check_names, &first.failed_check_names),
+            conflicting: last.is_conflicting(),
+        }
+    }
+
+    pub fn print(&self) {
+        if !self.actionable_thread_ids.is_empty() {
+            eprintln!(
+                "  {} thread(s) need a response: {}{}",
+                self.actionable_thread_ids.len(),
+                self.actionable_thread_ids.join(", "),
+                new_suffix(&self.new_thread_ids)
+            );
+        }
+        if !self.failed_checks.is_empty() {
+            eprintln!(
+                "  {} check(s) failing: {}{}",
+                self.failed_checks.len(),
+                self.failed_checks.join(", "),
+                new_suffix(&self.newly_failed_checks)
+            );
+        }
+        if self.conflicting {
+            eprintln!("  The PR has merge conflicts with its base branch.");
+        }
+    }
+}
+
+fn new_suffix(new: &[String]) -> String {
+    if new.is_empty() {
+        String::new()
+    } else {
+        format!(" (new: {})", new.join(", "))
+    }
 }
 
 /// Summarized PR status for printing during wait polling.
@@ -122,6 +390,7 @@ pub enum WaitResult {
 struct WaitStatus {
     ci_happy: bool,
     has_unresolved_threads: bool,
+    review_in_progress: bool,
 }
 
 impl WaitStatus {
@@ -129,6 +398,7 @@ impl WaitStatus {
         Self {
             ci_happy: snapshot.is_ci_happy(),
             has_unresolved_threads: !snapshot.unresolved_thread_ids.is_empty(),
+            review_in_progress: snapshot.review_in_progress(),
         }
     }
 
@@ -147,9 +417,49 @@ impl WaitStatus {
                 eprintln!("○ CI checks running. Waiting for review activity...");
             }
         }
+        if self.review_in_progress {
+            eprintln!("⏳ A review is in progress; waiting for it to be submitted.");
+        }
     }
 }
 
+/// Once a snapshot has actionable comments, keep polling until no further
+/// comments have arrived for `debounce`, so a reviewer's whole batch is
+/// returned instead of just the first comment. Gives up waiting (still
+/// returning actionable) at `deadline`, and stops if the wait is cancelled.
+fn settle_comment_burst(
+    mut poll: impl FnMut() -> Result<PrSnapshot>,
+    first: &PrSnapshot,
+    snapshot: PrSnapshot,
+    debounce: Duration,
+    poll_interval: Duration,
+    deadline: Instant,
+    cancel: Option<&CancelFile>,
+) -> Result<WaitResult> {
+    if debounce.is_zero() || snapshot.actionable_thread_ids.is_empty() {
+        return Ok(WaitResult::actionable(first, snapshot));
+    }
+
+    eprintln!(
+        "New review comments detected; waiting for {}s of silence to collect the full batch...",
+        debounce.as_secs()
+    );
+
+    let mut latest = snapshot;
+    let mut last_change = Instant::now();
+    while last_change.elapsed() < debounce && Instant::now() < deadline {
+        if sleep_until_next_poll(poll_interval, cancel) {
+            return Ok(WaitResult::Cancelled);
+        }
+        let snapshot = poll()?;
+        if snapshot.comment_ids != latest.comment_ids {
+            last_change = Instant::now();
+        }
+        latest = snapshot;
+    }
+    Ok(WaitResult::actionable(first, latest))
+}
+
 /// Wait until PR becomes actionable or timeout is reached.
 pub fn wait_until_actionable(
     checks_client: &dyn ChecksClient,
@@ -157,28 +467,24 @@ pub fn wait_until_actionable(
     owner: &str,
     repo: &str,
     pr_number: u64,
-    include_patterns: &[String],
-    exclude_patterns: &[String],
+    filters: &CheckFilters,
     timeout_secs: u64,
     poll_interval_secs: u64,
+    comment_debounce_secs: u64,
+    cancel: Option<&CancelFile>,
 ) -> Result<WaitResult> {
     let start = Instant::now();
     let timeout = Duration::from_secs(timeout_secs);
     let poll_interval = Duration::from_secs(poll_interval_secs);
+    let debounce = Duration::from_secs(comment_debounce_secs);
+    let poll = || capture_snapshot(checks_client, threads_client, owner, repo, pr_number, filters);
 
     // Check immediately first
-    let snapshot = capture_snapshot(
-        checks_client,
-        threads_client,
-        owner,
-        repo,
-        pr_number,
-        include_patterns,
-        exclude_patterns,
-    )?;
+    let first = poll()?;
 
-    if snapshot.is_actionable() {
-        return Ok(WaitResult::Actionable);
+    if first.is_actionable() {
+        let snapshot = first.clone();
+        return settle_comment_burst(poll, &first, snapshot, debounce, poll_interval, start + timeout, cancel);
     }
 
     eprintln!(
@@ -186,7 +492,7 @@ pub fn wait_until_actionable(
         timeout_secs, poll_interval_secs
     );
 
-    let mut prev_status = WaitStatus::from_snapshot(&snapshot);
+    let mut prev_status = WaitStatus::from_snapshot(&first);
     prev_status.print();
 
     loop {
@@ -194,20 +500,14 @@ pub fn wait_until_actionable(
             return Ok(WaitResult::Timeout);
         }
 
-        thread::sleep(poll_interval);
+        if sleep_until_next_poll(poll_interval, cancel) {
+            return Ok(WaitResult::Cancelled);
+        }
 
-        let snapshot = capture_snapshot(
-            checks_client,
-            threads_client,
-            owner,
-            repo,
-            pr_number,
-            include_patterns,
-            exclude_patterns,
-        )?;
+        let snapshot = poll()?;
 
         if snapshot.is_actionable() {
-            return Ok(WaitResult::Actionable);
+            return settle_comment_burst(poll, &first, snapshot, debounce, poll_interval, start + timeout, cancel);
         }
 
         let status = WaitStatus::from_snapshot(&snapshot);
@@ -220,6 +520,7 @@ pub fn wait_until_actionable(
 
 /// Wait until PR is actionable or "happy" (CI passing, no comments, min time since last push).
 /// Returns Happy when the PR is in a good state, Actionable if work is needed, or Timeout.
+/// With `strict`, happy also requires every thread to be resolved (see `is_strictly_happy`).
 pub fn wait_until_actionable_or_happy(
     checks_client: &dyn ChecksClient,
     threads_client: &dyn ThreadsClient,
@@ -227,44 +528,70 @@ pub fn wait_until_actionable_or_happy(
     owner: &str,
     repo: &str,
     pr_number: u64,
-    include_patterns: &[String],
-    exclude_patterns: &[String],
+    filters: &CheckFilters,
     timeout_secs: u64,
     poll_interval_secs: u64,
     min_wait_after_push_secs: u64,
+    comment_debounce_secs: u64,
+    strict: bool,
+    cancel: Option<&CancelFile>,
 ) -> Result<WaitResult> {
     let start = Instant::now();
     let timeout = Duration::from_secs(timeout_secs);
     let poll_interval = Duration::from_secs(poll_interval_secs);
     let min_wait_after_push = Duration::from_secs(min_wait_after_push_secs);
+    let debounce = Duration::from_secs(comment_debounce_secs);
+    let poll = || capture_snapshot(checks_client, threads_client, owner, repo, pr_number, filters);
 
     eprintln!(
         "Waiting for PR to become actionable or happy (timeout: {}s, polling every {}s)...",
         timeout_secs, poll_interval_secs
     );
 
+    let mut first: Option<PrSnapshot> = None;
+    let mut last_awaiting = 0;
+    let mut last_questions = 0;
     loop {
         if start.elapsed() >= timeout {
             return Ok(WaitResult::Timeout);
         }
+        if cancelled(cancel) {
+            return Ok(WaitResult::Cancelled);
+        }
 
-        let snapshot = capture_snapshot(
-            checks_client,
-            threads_client,
-            owner,
-            repo,
-            pr_number,
-            include_patterns,
-            exclude_patterns,
-        )?;
+        let snapshot = poll()?;
+        let first = first.get_or_insert_with(|| snapshot.clone());
 
-        // If actionable (comments or failures), return immediately
+        // If actionable (comments or failures), return once any comment burst settles
         if snapshot.is_actionable() {
-            return Ok(WaitResult::Actionable);
+            return settle_comment_burst(poll, first, snapshot, debounce, poll_interval, start + timeout, cancel);
+        }
+
+        // Claude's open questions keep the PR from being happy; the wait
+        // ends when a reviewer answers (the thread becomes actionable).
+        let questions = snapshot.awaiting_input_thread_ids.len();
+        if questions > 0 && questions != last_questions {
+            eprintln!(
+                "Waiting for a reviewer to answer Claude's question in {} thread(s)...",
+                questions
+            );
+        }
+        last_questions = questions;
+
+        if strict && snapshot.is_happy() {
+            let awaiting = snapshot.awaiting_resolution();
+            if awaiting > 0 && awaiting != last_awaiting {
+                eprintln!(
+                    "Waiting for the reviewer to resolve {} replied-to thread(s) (--strict-happy)...",
+                    awaiting
+                );
+            }
+            last_awaiting = awaiting;
         }
 
         // Check if "happy": CI passing (no failures, no pending) and no comments
-        if snapshot.is_happy() {
+        let happy = if strict { snapshot.is_strictly_happy() } else { snapshot.is_happy() };
+        if happy {
             // Also need to wait min time after last push to ensure CI has triggered
             let last_commit_time = git_client.get_last_commit_time()?;
             let elapsed_since_commit = SystemTime::now()
@@ -282,15 +609,90 @@ pub fn wait_until_actionable_or_happy(
             }
         }
 
-        thread::sleep(poll_interval);
+        if sleep_until_next_poll(poll_interval, cancel) {
+            return Ok(WaitResult::Cancelled);
+        }
+    }
+}
+
+/// How `wait_until_quiet` polls.
+#[derive(Debug, Clone, Copy)]
+pub struct QuietPolling<'a> {
+    /// How long the PR has to stay unchanged.
+    pub quiet_secs: u64,
+    pub timeout_secs: u64,
+    pub poll_interval_secs: u64,
+    pub cancel: Option<&'a CancelFile>,
+}
+
+/// Wait until the PR has been quiet — no new comments, thread resolutions, or
+/// check transitions — for `quiet_secs`. Useful when a human is still leaving a
+/// batch of review comments and responding mid-review would be premature.
+pub fn wait_until_quiet(
+    checks_client: &dyn ChecksClient,
+    threads_client: &dyn ThreadsClient,
+    owner: &str,
+    repo: &str,
+    pr_number: u64,
+    filters: &CheckFilters,
+    polling: QuietPolling,
+) -> Result<WaitResult> {
+    let QuietPolling {
+        quiet_secs,
+        timeout_secs,
+        poll_interval_secs,
+        cancel,
+    } = polling;
+    let start = Instant::now();
+    let timeout = Duration::from_secs(timeout_secs);
+    let poll_interval = Duration::from_secs(poll_interval_secs);
+    let quiet = Duration::from_secs(quiet_secs);
+
+    eprintln!(
+        "Waiting for {}s without new comments or check changes (timeout: {}s)...",
+        quiet_secs, timeout_secs
+    );
+
+    let mut last_snapshot: Option<PrSnapshot> = None;
+    let mut last_change = Instant::now();
+
+    loop {
+        let snapshot = capture_snapshot(
+            checks_client,
+            threads_client,
+            owner,
+            repo,
+            pr_number,
+            filters,
+        )?;
+
+        if last_snapshot.as_ref() != Some(&snapshot) {
+            if last_snapshot.is_some() {
+                eprintln!("○ PR activity detected; restarting quiet period.");
+            }
+            last_snapshot = Some(snapshot);
+            last_change = Instant::now();
+        } else if last_change.elapsed() >= quiet {
+            return Ok(WaitResult::Quiet(Box::new(snapshot)));
+        }
+
+        if start.elapsed() >= timeout {
+            return Ok(WaitResult::Timeout);
+        }
+
+        if sleep_until_next_poll(poll_interval, cancel) {
+            return Ok(WaitResult::Cancelled);
+        }
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::checks::{Check, CheckStatus};
-    use crate::threads::{ReviewThread, ThreadComment};
+    use crate::checks::{Check, CheckSource, CheckStatus};
+    use crate::threads::{ReplyStatus, ReviewThread, ThreadComment, CLAUDE_MARKER};
+    use std::cell::RefCell;
+    use std::sync::Mutex;
 
     struct TestChecksClient {
         checks: Vec<Check>,
@@ -325,24 +727,18 @@ mod tests {
             name: name.to_string(),
             status,
             url: None,
+            source: CheckSource::CheckRun,
+            started_at: None,
+            attempts: 1,
+            app: None,
         }
     }
 
     fn make_thread(id: &str, resolved: bool, last_comment_body: &str) -> ReviewThread {
+        let comment = ThreadComment::for_test(&format!("comment_{}", id), "reviewer", last_comment_body);
         ReviewThread {
-            id: id.to_string(),
             is_resolved: resolved,
-            is_outdated: false,
-            path: Some("test.rs".to_string()),
-            line: Some(1),
-            comments: vec![ThreadComment {
-                id: format!("comment_{}", id),
-                author: "reviewer".to_string(),
-                body: last_comment_body.to_string(),
-                diff_hunk: None,
-                url: None,
-                created_at: None,
-            }],
+            ..ReviewThread::for_test(id, vec![comment])
         }
     }
 
@@ -362,8 +758,7 @@ mod tests {
             "owner",
             "repo",
             1,
-            &[],
-            &[],
+            &CheckFilters::default(),
         )
         .unwrap();
 
@@ -372,6 +767,26 @@ mod tests {
         assert!(snapshot.actionable_thread_ids.is_empty());
     }
 
+    #[test]
+    fn snapshot_not_happy_until_delayed_check_registers() {
+        let checks_client = TestChecksClient {
+            checks: vec![make_check("build", CheckStatus::Pass)],
+        };
+        let threads_client = TestThreadsClient { threads: vec![] };
+        let filters = CheckFilters {
+            delayed_checks: vec!["e2e*".to_string()],
+            ..Default::default()
+        };
+
+        let snapshot =
+            capture_snapshot(&checks_client, &threads_client, "owner", "repo", 1, &filters)
+                .unwrap();
+
+        assert!(!snapshot.is_happy());
+        assert!(!snapshot.is_actionable());
+        assert!(snapshot.pending_check_names.contains("e2e*"));
+    }
+
     #[test]
     fn snapshot_actionable_with_unresolved_thread() {
         let checks_client = TestChecksClient {
@@ -387,8 +802,7 @@ mod tests {
             "owner",
             "repo",
             1,
-            &[],
-            &[],
+            &CheckFilters::default(),
         )
         .unwrap();
 
@@ -411,8 +825,7 @@ mod tests {
             "owner",
             "repo",
             1,
-            &[],
-            &[],
+            &CheckFilters::default(),
         )
         .unwrap();
 
@@ -434,8 +847,7 @@ mod tests {
             "owner",
             "repo",
             1,
-            &[],
-            &[],
+            &CheckFilters::default(),
         )
         .unwrap();
 
@@ -458,8 +870,7 @@ mod tests {
             "owner",
             "repo",
             1,
-            &[],
-            &[],
+            &CheckFilters::default(),
         )
         .unwrap();
 
@@ -479,14 +890,25 @@ mod tests {
             "owner",
             "repo",
             1,
-            &[],
-            &[],
+            &CheckFilters::default(),
         )
         .unwrap();
 
         assert!(!snapshot.is_actionable());
     }
 
+    #[test]
+    fn conflicting_snapshot_is_actionable_not_happy() {
+        let snapshot = PrSnapshot {
+            mergeable_status: MergeableStatus::Conflicting,
+            ..Default::default()
+        };
+        assert!(snapshot.is_ci_happy());
+        assert!(snapshot.is_actionable());
+        assert!(!snapshot.is_happy());
+        assert!(WaitReason::between(&PrSnapshot::default(), &snapshot).conflicting);
+    }
+
     #[test]
     fn snapshot_is_ci_happy_all_passing() {
         let snapshot = PrSnapshot {
@@ -494,6 +916,9 @@ mod tests {
             unresolved_thread_ids: HashSet::new(),
             failed_check_names: HashSet::new(),
             pending_check_names: HashSet::new(),
+            comment_ids: HashSet::new(),
+            pending_review_authors: HashSet::new(),
+            ..Default::default()
         };
         assert!(snapshot.is_ci_happy());
     }
@@ -507,6 +932,9 @@ mod tests {
             unresolved_thread_ids: HashSet::new(),
             failed_check_names: HashSet::new(),
             pending_check_names: pending,
+            comment_ids: HashSet::new(),
+            pending_review_authors: HashSet::new(),
+            ..Default::default()
         };
         assert!(!snapshot.is_ci_happy());
     }
@@ -520,6 +948,9 @@ mod tests {
             unresolved_thread_ids: HashSet::new(),
             failed_check_names: failed,
             pending_check_names: HashSet::new(),
+            comment_ids: HashSet::new(),
+            pending_review_authors: HashSet::new(),
+            ..Default::default()
         };
         assert!(!snapshot.is_ci_happy());
     }
@@ -531,6 +962,9 @@ mod tests {
             unresolved_thread_ids: HashSet::new(),
             failed_check_names: HashSet::new(),
             pending_check_names: HashSet::new(),
+            comment_ids: HashSet::new(),
+            pending_review_authors: HashSet::new(),
+            ..Default::default()
         };
         assert!(snapshot.is_happy());
     }
@@ -544,6 +978,9 @@ mod tests {
             unresolved_thread_ids: HashSet::new(),
             failed_check_names: HashSet::new(),
             pending_check_names: HashSet::new(),
+            comment_ids: HashSet::new(),
+            pending_review_authors: HashSet::new(),
+            ..Default::default()
         };
         assert!(!snapshot.is_happy());
     }
@@ -557,6 +994,9 @@ mod tests {
             unresolved_thread_ids: HashSet::new(),
             failed_check_names: HashSet::new(),
             pending_check_names: pending,
+            comment_ids: HashSet::new(),
+            pending_review_authors: HashSet::new(),
+            ..Default::default()
         };
         assert!(!snapshot.is_happy());
     }
@@ -568,21 +1008,10 @@ mod tests {
         };
         // An unresolved thread with a paperclip should be ignored
         let threads_client = TestThreadsClient {
-            threads: vec![ReviewThread {
-                id: "T1".to_string(),
-                is_resolved: false,
-                is_outdated: false,
-                path: Some("test.rs".to_string()),
-                line: Some(1),
-                comments: vec![ThreadComment {
-                    id: "C1".to_string(),
-                    author: "reviewer".to_string(),
-                    body: ":paperclip: This is for human review".to_string(),
-                    diff_hunk: None,
-                    url: None,
-                    created_at: None,
-                }],
-            }],
+            threads: vec![ReviewThread::for_test(
+                "T1",
+                vec![ThreadComment::for_test("C1", "reviewer", ":paperclip: This is for human review")],
+            )],
         };
 
         let snapshot = capture_snapshot(
@@ -591,8 +1020,7 @@ mod tests {
             "owner",
             "repo",
             1,
-            &[],
-            &[],
+            &CheckFilters::default(),
         )
         .unwrap();
 
@@ -600,6 +1028,15 @@ mod tests {
         assert!(snapshot.actionable_thread_ids.is_empty());
         assert!(snapshot.unresolved_thread_ids.is_empty());
         assert!(snapshot.is_happy());
+
+        // ...but is kept for the analysis, which lists it for the human
+        assert_eq!(snapshot.threads.len(), 1);
+        assert_eq!(snapshot.checks.checks.len(), 1);
+
+        // Raw details don't affect equality
+        let mut other = snapshot.clone();
+        other.checks.checks[0].url = Some("https://example.com/rerun".to_string());
+        assert_eq!(other, snapshot);
     }
 
     #[test]
@@ -609,31 +1046,13 @@ mod tests {
         };
         // Thread has paperclip in only one comment but entire thread is excluded
         let threads_client = TestThreadsClient {
-            threads: vec![ReviewThread {
-                id: "T1".to_string(),
-                is_resolved: false,
-                is_outdated: false,
-                path: Some("test.rs".to_string()),
-                line: Some(1),
-                comments: vec![
-                    ThreadComment {
-                        id: "C1".to_string(),
-                        author: "reviewer".to_string(),
-                        body: "Please fix this".to_string(),
-                        diff_hunk: None,
-                        url: None,
-                        created_at: None,
-                    },
-                    ThreadComment {
-                        id: "C2".to_string(),
-                        author: "reviewer".to_string(),
-                        body: ":paperclip: But note this for human review".to_string(),
-                        diff_hunk: None,
-                        url: None,
-                        created_at: None,
-                    },
+            threads: vec![ReviewThread::for_test(
+                "T1",
+                vec![
+                    ThreadComment::for_test("C1", "reviewer", "Please fix this"),
+                    ThreadComment::for_test("C2", "reviewer", ":paperclip: But note this for human review"),
                 ],
-            }],
+            )],
         };
 
         let snapshot = capture_snapshot(
@@ -642,12 +1061,336 @@ mod tests {
             "owner",
             "repo",
             1,
-            &[],
-            &[],
+            &CheckFilters::default(),
         )
         .unwrap();
 
         assert!(snapshot.actionable_thread_ids.is_empty());
         assert!(snapshot.unresolved_thread_ids.is_empty());
     }
+
+    struct ChangingThreadsClient {
+        calls: Mutex<u32>,
+    }
+
+    impl ThreadsClient for ChangingThreadsClient {
+        fn fetch_threads(&self, _owner: &str, _repo: &str, _pr: u64) -> Result<Vec<ReviewThread>> {
+            // A new comment arrives on every poll.
+            let mut calls = self.calls.lock().unwrap();
+            *calls += 1;
+            let mut thread = make_thread("T1", false, "first");
+            for n in 1..*calls {
+                let mut comment = thread.comments[0].clone();
+                comment.id = format!("C{}", n);
+                thread.comments.push(comment);
+            }
+            Ok(vec![thread])
+        }
+
+        fn fetch_thread_by_comment_id(&self, comment_id: &str) -> Result<ReviewThread> {
+            anyhow::bail!("Comment not found: {}", comment_id)
+        }
+    }
+
+    #[test]
+    fn snapshot_tracks_all_comment_ids() {
+        let checks_client = TestChecksClient { checks: vec![] };
+        let mut thread = make_thread("T1", true, "Done");
+        thread.comments.push(ThreadComment {
+            id: "C2".to_string(),
+            ..thread.comments[0].clone()
+        });
+        let threads_client = TestThreadsClient {
+            threads: vec![thread],
+        };
+
+        let snapshot = capture_snapshot(
+            &checks_client,
+            &threads_client,
+            "owner",
+            "repo",
+            1,
+            &CheckFilters::default(),
+        )
+        .unwrap();
+
+        // Resolved threads still count toward activity
+        assert_eq!(snapshot.comment_ids.len(), 2);
+        assert!(snapshot.comment_ids.contains("C2"));
+    }
+
+    #[test]
+    fn wait_until_quiet_returns_once_state_is_stable() {
+        let checks_client = TestChecksClient {
+            checks: vec![make_check("build", CheckStatus::Pending)],
+        };
+        let threads_client = TestThreadsClient {
+            threads: vec![make_thread("T1", false, "Please fix")],
+        };
+
+        let result = wait_until_quiet(
+            &checks_client,
+            &threads_client,
+            "owner",
+            "repo",
+            1,
+            &CheckFilters::default(),
+            QuietPolling {
+                quiet_secs: 0,
+                timeout_secs: 60,
+                poll_interval_secs: 0,
+                cancel: None,
+            },
+        )
+        .unwrap();
+        assert!(matches!(result, WaitResult::Quiet(_)));
+    }
+
+    #[test]
+    fn wait_until_quiet_times_out_while_comments_keep_arriving() {
+        let checks_client = TestChecksClient { checks: vec![] };
+        let threads_client = ChangingThreadsClient {
+            calls: Mutex::new(0),
+        };
+
+        let result = wait_until_quiet(
+            &checks_client,
+            &threads_client,
+            "owner",
+            "repo",
+            1,
+            &CheckFilters::default(),
+            QuietPolling {
+                quiet_secs: 60,
+                timeout_secs: 0,
+                poll_interval_secs: 0,
+                cancel: None,
+            },
+        )
+        .unwrap();
+        assert_eq!(result, WaitResult::Timeout);
+        assert_eq!(*threads_client.calls.lock().unwrap(), 1);
+    }
+
+    #[test]
+    fn cancel_file_appearing_or_changing_cancels() {
+        let dir = std::env::temp_dir().join(format!("pr-loop-cancel-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("cancel");
+
+        let cancel = CancelFile::new(path.clone());
+        assert!(!cancel.is_cancelled());
+        std::fs::write(&path, "").unwrap();
+        assert!(cancel.is_cancelled());
+        assert_eq!(cancel.reason(), None);
+
+        // A file left over from before the wait only cancels once rewritten
+        let cancel = CancelFile::new(path.clone());
+        assert!(!cancel.is_cancelled());
+        assert!(!sleep_unless_cancelled(Duration::ZERO, Some(&cancel)));
+        std::fs::write(&path, "superseded by a new push\n").unwrap();
+        assert!(sleep_unless_cancelled(Duration::from_secs(60), Some(&cancel)));
+        assert_eq!(cancel.reason().as_deref(), Some("superseded by a new push"));
+
+        let checks_client = TestChecksClient { checks: vec![] };
+        let threads_client = TestThreadsClient { threads: vec![] };
+        let result = wait_until_quiet(
+            &checks_client,
+            &threads_client,
+            "owner",
+            "repo",
+            1,
+            &CheckFilters::default(),
+            QuietPolling {
+                quiet_secs: 60,
+                timeout_secs: 60,
+                poll_interval_secs: 60,
+                cancel: Some(&cancel),
+            },
+        )
+        .unwrap();
+        assert_eq!(result, WaitResult::Cancelled);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    fn snapshot_with_comments(actionable: &[&str], comments: &[&str]) -> PrSnapshot {
+        PrSnapshot {
+            actionable_thread_ids: actionable.iter().map(|s| s.to_string()).collect(),
+            unresolved_thread_ids: actionable.iter().map(|s| s.to_string()).collect(),
+            failed_check_names: HashSet::new(),
+            pending_check_names: HashSet::new(),
+            comment_ids: comments.iter().map(|s| s.to_string()).collect(),
+            pending_review_authors: HashSet::new(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn settle_comment_burst_waits_for_batch_to_finish() {
+        // The reviewer adds C2 and C3 on the next two polls, then stops.
+        let batches = [vec!["C1", "C2"], vec!["C1", "C2", "C3"]];
+        let calls = RefCell::new(0usize);
+        let poll = || {
+            let mut n = calls.borrow_mut();
+            let ids = batches.get(*n).cloned().unwrap_or(vec!["C1", "C2", "C3"]);
+            *n += 1;
+            Ok(snapshot_with_comments(&["T1"], &ids))
+        };
+
+        let result = settle_comment_burst(
+            poll,
+            &snapshot_with_comments(&[], &[]),
+            snapshot_with_comments(&["T1"], &["C1"]),
+            Duration::from_millis(30),
+            Duration::from_millis(5),
+            Instant::now() + Duration::from_secs(10),
+            None,
+        )
+        .unwrap();
+
+        let WaitResult::Actionable { reason, .. } = result else {
+            panic!("expected actionable, got {:?}", result);
+        };
+        assert_eq!(reason.actionable_thread_ids, vec!["T1"]);
+        assert_eq!(reason.new_thread_ids, vec!["T1"]);
+        assert!(*calls.borrow() > batches.len());
+    }
+
+    #[test]
+    fn settle_comment_burst_skips_when_only_ci_failed() {
+        let mut snapshot = snapshot_with_comments(&[], &[]);
+        snapshot.failed_check_names.insert("build".to_string());
+        let calls = RefCell::new(0);
+
+        let result = settle_comment_burst(
+            || {
+                *calls.borrow_mut() += 1;
+                Ok(snapshot_with_comments(&[], &[]))
+            },
+            &snapshot.clone(),
+            snapshot,
+            Duration::from_secs(60),
+            Duration::ZERO,
+            Instant::now() + Duration::from_secs(60),
+            None,
+        )
+        .unwrap();
+
+        let WaitResult::Actionable { reason, .. } = result else {
+            panic!("expected actionable, got {:?}", result);
+        };
+        assert_eq!(reason.failed_checks, vec!["build"]);
+        assert!(reason.newly_failed_checks.is_empty());
+        assert_eq!(*calls.borrow(), 0);
+    }
+
+    #[test]
+    fn settle_comment_burst_stops_when_cancelled() {
+        let dir = std::env::temp_dir().join(format!("pr-loop-settle-cancel-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("cancel");
+        let cancel = CancelFile::new(path.clone());
+        std::fs::write(&path, "stop").unwrap();
+        let calls = RefCell::new(0);
+
+        let result = settle_comment_burst(
+            || {
+                *calls.borrow_mut() += 1;
+                Ok(snapshot_with_comments(&["T1"], &["C1", "C2"]))
+            },
+            &snapshot_with_comments(&[], &[]),
+            snapshot_with_comments(&["T1"], &["C1"]),
+            Duration::from_secs(60),
+            Duration::from_secs(60),
+            Instant::now() + Duration::from_secs(600),
+            Some(&cancel),
+        )
+        .unwrap();
+
+        assert_eq!(result, WaitResult::Cancelled);
+        assert_eq!(*calls.borrow(), 0);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn pending_review_holds_off_comment_actionability() {
+        let mut snapshot = snapshot_with_comments(&["T1"], &["C1"]);
+        assert!(snapshot.is_actionable());
+
+        snapshot.pending_review_authors.insert("alice".to_string());
+        assert!(!snapshot.is_actionable());
+        assert!(!snapshot.is_happy());
+
+        // CI failures are still actionable mid-review
+        snapshot.failed_check_names.insert("build".to_string());
+        assert!(snapshot.is_actionable());
+    }
+
+    #[test]
+    fn pending_review_blocks_happy() {
+        let mut snapshot = snapshot_with_comments(&[], &[]);
+        assert!(snapshot.is_happy());
+        snapshot.pending_review_authors.insert("alice".to_string());
+        assert!(!snapshot.is_happy());
+    }
+
+    #[test]
+    fn strict_happy_requires_resolved_threads() {
+        // T1 has Claude's reply last: not actionable, but still unresolved
+        let mut snapshot = snapshot_with_comments(&[], &["C1"]);
+        snapshot.unresolved_thread_ids.insert("T1".to_string());
+        assert!(snapshot.is_happy());
+        assert!(!snapshot.is_strictly_happy());
+        assert_eq!(snapshot.awaiting_resolution(), 1);
+
+        snapshot.unresolved_thread_ids.clear();
+        assert!(snapshot.is_strictly_happy());
+    }
+
+    #[test]
+    fn open_question_blocks_happy_but_is_not_actionable() {
+        let mut snapshot = snapshot_with_comments(&[], &["C1"]);
+        snapshot.unresolved_thread_ids.insert("T1".to_string());
+        snapshot.awaiting_input_thread_ids.insert("T1".to_string());
+        assert!(!snapshot.is_actionable());
+        assert!(!snapshot.is_happy());
+    }
+
+    #[test]
+    fn paperclip_questions_do_not_block_happy() {
+        let checks_client = TestChecksClient { checks: vec![] };
+        let question = format!("{} {} Which backend?", CLAUDE_MARKER, ReplyStatus::Question.emoji());
+        let thread = |id: &str, body: &str| {
+            ReviewThread::for_test(
+                id,
+                vec![
+                    ThreadComment::for_test(&format!("{}-C1", id), "reviewer", body),
+                    ThreadComment::for_test(&format!("{}-C2", id), "bot", &question),
+                ],
+            )
+        };
+        let threads_client = TestThreadsClient {
+            threads: vec![
+                thread("T1", "Pick a backend"),
+                thread("T2", ":paperclip: Pick a backend later"),
+            ],
+        };
+
+        let snapshot = capture_snapshot(
+            &checks_client,
+            &threads_client,
+            "owner",
+            "repo",
+            1,
+            &CheckFilters::default(),
+        )
+        .unwrap();
+
+        assert_eq!(snapshot.awaiting_input_thread_ids, HashSet::from(["T1".to_string()]));
+        let analyzed = find_awaiting_input_threads(&snapshot.threads);
+        assert_eq!(analyzed.len(), 1);
+        assert_eq!(analyzed[0].id, "T1");
+    }
 }
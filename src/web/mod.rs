@@ -2,11 +2,12 @@
 // commits in a browser with live updates.
 
 use crate::cc_status::{read_cc_status, CcStatus};
-use crate::checks::{Check, CheckStatus, ChecksClient, RealChecksClient};
+use crate::checks::{collapse_reruns, Check, CheckStatus, ChecksClient, RealChecksClient};
 use crate::commits::{CommitsClient, PrCommit, RealCommitsClient};
 use crate::threads::CLAUDE_MARKER;
 use crate::git::{GitClient, RealGitClient};
 use crate::github::PrContext;
+use crate::policy::{Guarded, Policy};
 use crate::reply::{RealReplyClient, ReplyClient};
 use crate::threads::{RealThreadsClient, ReviewThread, ThreadComment, ThreadsClient};
 use anyhow::{Context, Result};
@@ -66,6 +67,7 @@ struct ThreadDto {
     is_paperclip: bool,
     path: Option<String>,
     line: Option<u64>,
+    location: String,
     comments: Vec<CommentDto>,
 }
 
@@ -78,6 +80,7 @@ impl From<&ReviewThread> for ThreadDto {
             is_paperclip: t.has_paperclip(),
             path: t.path.clone(),
             line: t.line,
+            location: t.location(),
             comments: t.comments.iter().map(CommentDto::from).collect(),
         }
     }
@@ -158,6 +161,8 @@ struct StateResponse<'a> {
 
 struct Shared {
     pr_context: PrContext,
+    /// Guardrails applied to mutations made from the UI.
+    policy: Policy,
     state: Mutex<State>,
     peers: Mutex<Vec<PeerSummary>>,
     // Condvar-paired flag so handlers can poke the poller.
@@ -187,6 +192,7 @@ impl Shared {
 
 pub fn run(
     pr_context: &PrContext,
+    policy: Policy,
     binds: &[String],
     port: Option<u16>,
     open_browser: bool,
@@ -218,6 +224,7 @@ pub fn run(
 
     let shared = Arc::new(Shared {
         pr_context: pr_context.clone(),
+        policy,
         state: Mutex::new(State {
             pr: Some(PrDto {
                 owner: pr_context.owner.clone(),
@@ -335,19 +342,8 @@ fn handle_request(mut request: tiny_http::Request, shared: &Arc<Shared>) -> Resu
             build_response("{}".to_string(), "application/json", 200)
         }
         (&Method::Post, "/api/restart") => {
-            #[cfg(unix)]
-            {
-                thread::spawn(restart_self);
-                build_response("{}".to_string(), "application/json", 200)
-            }
-            #[cfg(not(unix))]
-            {
-                build_response(
-                    r#"{"error":"restart is only supported on Unix"}"#.to_string(),
-                    "application/json",
-                    501,
-                )
-            }
+            thread::spawn(restart_self);
+            build_response("{}".to_string(), "application/json", 200)
         }
         (&Method::Get, "/api/peers") => {
             let peers = shared.peers.lock().unwrap().clone();
@@ -357,7 +353,16 @@ fn handle_request(mut request: tiny_http::Request, shared: &Arc<Shared>) -> Resu
         (&Method::Post, p) if p.starts_with("/api/threads/") && p.ends_with("/resolve") => {
             let thread_id =
                 decode_thread_id(&p["/api/threads/".len()..p.len() - "/resolve".len()]);
-            let client = RealReplyClient;
+            let ctx = pr_context.clone();
+            let client = Guarded::new(RealReplyClient, shared.policy.clone()).with_thread_lookup(
+                Box::new(move |id| {
+                    RealThreadsClient
+                        .fetch_threads(&ctx.owner, &ctx.repo, ctx.pr_number)?
+                        .into_iter()
+                        .find(|t| t.id == id)
+                        .ok_or_else(|| anyhow::anyhow!("Thread not found: {}", id))
+                }),
+            );
             match client.resolve_thread(&thread_id) {
                 Ok(()) => {
                     // Synchronously re-fetch so the client's next /api/state
@@ -526,7 +531,7 @@ fn fetch_now(
         }
     }
     if let Ok(checks) = checks_result {
-        state.checks = checks.iter().map(CheckDto::from).collect();
+        state.checks = collapse_reruns(checks).iter().map(CheckDto::from).collect();
     }
     state.last_fetched_at = Some(iso_now());
 }
@@ -608,14 +613,18 @@ fn dirs_cache() -> Result<PathBuf> {
             return Ok(PathBuf::from(x));
         }
     }
-    let home = std::env::var("HOME").context("HOME not set")?;
+    #[cfg(windows)]
+    if let Some(local) = std::env::var_os("LOCALAPPDATA").filter(|v| !v.is_empty()) {
+        return Ok(PathBuf::from(local));
+    }
+    let home = crate::config::home_dir().context("HOME not set")?;
     #[cfg(target_os = "macos")]
     {
-        Ok(PathBuf::from(home).join("Library/Caches"))
+        Ok(home.join("Library").join("Caches"))
     }
     #[cfg(not(target_os = "macos"))]
     {
-        Ok(PathBuf::from(home).join(".cache"))
+        Ok(home.join(".cache"))
     }
 }
 
@@ -842,6 +851,33 @@ fn restart_self() {
     std::process::exit(1);
 }
 
+/// Windows has no exec(): start the new binary as a child sharing our
+/// console, then exit so it can take over the port.
+#[cfg(not(unix))]
+fn restart_self() {
+    thread::sleep(Duration::from_millis(200));
+    let exe = match std::env::current_exe() {
+        Ok(p) => p,
+        Err(e) => {
+            eprintln!("pr-loop web: restart failed (current_exe): {}", e);
+            std::process::exit(1);
+        }
+    };
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    eprintln!(
+        "pr-loop web: restarting via spawn: {} {}",
+        exe.display(),
+        args.join(" ")
+    );
+    match std::process::Command::new(exe).args(&args).spawn() {
+        Ok(_) => std::process::exit(0),
+        Err(e) => {
+            eprintln!("pr-loop web: restart failed (spawn): {}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
 fn ctrlc_cleanup(port_file: PathBuf) -> Result<()> {
     // Best-effort: install a SIGINT handler that removes the port file and exits.
     // We don't add a signal-handling crate; use ctrlc-free approach via libc is overkill,
@@ -0,0 +1,222 @@
+// Example commands for pr-loop's workflows, kept in one place.
+// Agents copy commands from `--help` and from the `context` briefing, so the
+// examples there effectively program them. Both are rendered from the tables
+// here: each subcommand's long help (`--help`) ends with its examples, the
+// top-level one with the whole iterate → wait → ready sequence, the
+// environment variables, and the exit codes; and the briefing lists the
+// commands for its recommended next action. One source keeps the two from
+// teaching different habits.
+
+use crate::{escalation, wait};
+
+/// A command an agent can run, and when to run it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Example {
+    pub command: &'static str,
+    pub purpose: &'static str,
+}
+
+const WAIT: Example = Example {
+    command: "pr-loop --wait-until-actionable-or-happy --maintain-status",
+    purpose: "Wait until a comment or CI failure needs attention, or the PR is happy",
+};
+const CONTEXT: Example = Example {
+    command: "pr-loop context",
+    purpose: "Read the briefing: next action, actionable threads, failing checks",
+};
+const REPLY_FIXED: Example = Example {
+    command: "pr-loop reply --in-reply-to COMMENT_ID --status fixed --message \"Renamed it in abc123.\"",
+    purpose: "Answer a thread once the fix is pushed",
+};
+const REPLY_QUESTION: Example = Example {
+    command: "pr-loop reply --in-reply-to COMMENT_ID --status question --message \"Should this also cover X?\"",
+    purpose: "Ask the reviewer before changing anything",
+};
+const REPLY_SUGGESTION: Example = Example {
+    command: "pr-loop reply --in-reply-to COMMENT_ID --suggestion fixed.rs --message \"Like this?\"",
+    purpose: "Propose replacement code the reviewer can apply in one click",
+};
+const REPLY_BATCH: Example = Example {
+    command: "pr-loop reply-batch replies.json",
+    purpose: "Post several replies at once from a JSON list",
+};
+const RESOLVE: Example = Example {
+    command: "pr-loop resolve --thread THREAD_ID",
+    purpose: "Resolve a thread the reviewer agreed is done",
+};
+const COMMENT: Example = Example {
+    command: "pr-loop comment --message \"Rebased onto main; no changes beyond conflicts.\"",
+    purpose: "Tell reviewers about something that isn't a thread, like a force-push",
+};
+const CHECKS: Example = Example {
+    command: "pr-loop checks",
+    purpose: "Show failing checks with their extracted errors",
+};
+const REPRO: Example = Example {
+    command: "pr-loop repro --check \"test (*)\"",
+    purpose: "Run a failed check's tests locally before fixing it",
+};
+const RERUN: Example = Example {
+    command: "pr-loop rerun --failed-only --wait",
+    purpose: "Re-run only the failed CircleCI jobs of a flaky check and wait for them",
+};
+const READY: Example = Example {
+    command: "pr-loop ready",
+    purpose: "Once happy: clean up Claude's threads, remove the status block, mark ready",
+};
+const READY_REVIEWERS: Example = Example {
+    command: "pr-loop ready --reviewer alice --reviewer bob",
+    purpose: "Also request review once the PR is ready",
+};
+const MERGE: Example = Example {
+    command: "pr-loop merge",
+    purpose: "Merge once happy and wait until GitHub reports it merged",
+};
+const THREADS: Example = Example {
+    command: "pr-loop threads",
+    purpose: "See every thread and why it does or doesn't count as actionable",
+};
+const SNOOZE: Example = Example {
+    command: "pr-loop snooze --thread THREAD_ID --for 2d --reason \"deferred to a follow-up\"",
+    purpose: "Park a thread so it doesn't count as actionable for a while",
+};
+const CHECKOUT: Example = Example {
+    command: "pr-loop checkout",
+    purpose: "Sync the local branch to the PR head before editing",
+};
+const GIT_REBASE: Example = Example {
+    command: "git fetch origin && git rebase origin/BASE && git push --force-with-lease",
+    purpose: "Bring the branch up to date with its base",
+};
+
+/// The loop an agent runs on a PR, in order.
+pub const ITERATION: &[Example] = &[WAIT, CONTEXT, REPLY_FIXED, WAIT, READY];
+
+/// Examples for each subcommand's `--help`, by its command-line name.
+const SUBCOMMAND_EXAMPLES: &[(&str, &[Example])] = &[
+    ("reply", &[REPLY_FIXED, REPLY_QUESTION, REPLY_SUGGESTION]),
+    ("comment", &[COMMENT]),
+    ("reply-batch", &[REPLY_BATCH]),
+    ("resolve", &[RESOLVE]),
+    ("ready", &[READY, READY_REVIEWERS]),
+    ("merge", &[MERGE]),
+    ("snooze", &[SNOOZE]),
+    ("context", &[CONTEXT]),
+    ("threads", &[THREADS]),
+    ("checkout", &[CHECKOUT]),
+    ("repro", &[REPRO]),
+    ("rerun", &[RERUN]),
+    ("checks", &[CHECKS]),
+];
+
+/// The commands for each of the briefing's next-action kinds.
+const NEXT_ACTION_COMMANDS: &[(&str, &[Example])] = &[
+    ("respond_to_comments", &[REPLY_FIXED, REPLY_QUESTION, RESOLVE, WAIT]),
+    ("resolve_conflicts", &[GIT_REBASE, WAIT]),
+    ("fix_ci_failures", &[CHECKS, REPRO, RERUN, WAIT]),
+    ("wait_for_ci", &[WAIT]),
+    ("rebase", &[GIT_REBASE, WAIT]),
+    ("await_human_input", &[WAIT]),
+    ("pr_ready", &[READY]),
+];
+
+/// Environment variables pr-loop reads besides the per-flag ones.
+pub const ENVIRONMENT: &[(&str, &str)] = &[
+    ("PR_LOOP_*", "Many flags can be set in the environment instead; see [env: ...] beside each"),
+    ("CIRCLECI_TOKEN", "CircleCI API token, for CircleCI failure logs"),
+    ("AZURE_DEVOPS_TOKEN", "Azure DevOps personal access token, for Azure Pipelines failure logs"),
+    ("GH_HOST", "GitHub host for GitHub Enterprise, as for gh"),
+];
+
+/// Exit codes shared by the waits and the commands that wait. The codes are
+/// defined beside the code that exits with them.
+pub const EXIT_CODES: &[(i32, &str)] = &[
+    (0, "Success; for a wait, the PR is actionable or happy"),
+    (1, "Error, or the PR isn't in the state the command needs"),
+    (wait::TIMEOUT_EXIT_CODE, "A wait reached --timeout"),
+    (wait::CANCELLED_EXIT_CODE, "A wait was cancelled through --cancel-file"),
+    (escalation::EXIT_CODE, "The loop escalated to a human; pause"),
+];
+
+/// The commands to show under the briefing's next action.
+pub fn for_next_action(kind: &str) -> &'static [Example] {
+    NEXT_ACTION_COMMANDS
+        .iter()
+        .find(|(k, _)| *k == kind)
+        .map(|(_, examples)| *examples)
+        .unwrap_or(&[])
+}
+
+fn render_examples(heading: &str, examples: &[Example]) -> String {
+    let mut out = format!("{}:\n", heading);
+    for example in examples {
+        out.push_str(&format!("  # {}\n  {}\n", example.purpose, example.command));
+    }
+    out
+}
+
+/// The long help shown after the top-level options.
+fn top_level_help() -> String {
+    let mut out = render_examples("Workflow", ITERATION);
+    out.push_str("\nEnvironment:\n");
+    for (name, meaning) in ENVIRONMENT {
+        out.push_str(&format!("  {:<20}{}\n", name, meaning));
+    }
+    out.push_str("\nExit codes:\n");
+    for (code, meaning) in EXIT_CODES {
+        out.push_str(&format!("  {}  {}\n", code, meaning));
+    }
+    out
+}
+
+/// Add the workflow and examples to the command's long help.
+pub fn add_to_help(command: clap::Command) -> clap::Command {
+    SUBCOMMAND_EXAMPLES.iter().fold(
+        command.after_long_help(top_level_help()),
+        |command, (name, examples)| {
+            command.mut_subcommand(*name, |sub| sub.after_long_help(render_examples("Examples", examples)))
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::Cli;
+    use clap::CommandFactory;
+
+    /// Split on spaces outside double quotes, which is all the examples use.
+    fn shell_words(command: &str) -> Vec<String> {
+        let mut words = vec![String::new()];
+        let mut quoted = false;
+        for c in command.chars() {
+            match c {
+                '"' => quoted = !quoted,
+                ' ' if !quoted => words.push(String::new()),
+                c => words.last_mut().unwrap().push(c),
+            }
+        }
+        words
+    }
+
+    #[test]
+    fn examples_parse_as_real_commands() {
+        let mut command = add_to_help(Cli::command());
+        let examples = SUBCOMMAND_EXAMPLES
+            .iter()
+            .chain(NEXT_ACTION_COMMANDS)
+            .flat_map(|(_, examples)| examples.iter())
+            .chain(ITERATION)
+            .filter(|e| e.command.starts_with("pr-loop"));
+        for example in examples {
+            if let Err(e) = command.try_get_matches_from_mut(shell_words(example.command)) {
+                panic!("{}: {}", example.command, e);
+            }
+        }
+        let help = command.find_subcommand_mut("reply").unwrap().render_long_help().to_string();
+        assert!(help.contains("Examples:\n  # Answer a thread once the fix is pushed\n  pr-loop reply"));
+
+        let help = command.render_long_help().to_string();
+        assert!(help.contains("\n  4  The loop escalated to a human; pause\n"));
+    }
+}